@@ -1,4 +1,4 @@
-use rips_packets::ethernet::{ether_types, EtherType, EthernetPacket, MacAddr};
+use rips_packets::ethernet::{EtherType, EthernetPacket, MacAddr};
 use std::io;
 
 pub trait EthernetPayloadListener<E: ::std::error::Error> {
@@ -42,11 +42,9 @@ macro_rules! ethernet_rx {
 
             #[inline]
             pub fn recv(&mut self, data: &[u8]) -> Result<(), $error_struct_name> {
-                use rips_packets::ethernet::BROADCAST_MAC;
-
                 let packet = EthernetPacket::new(data).ok_or($error_struct_name::TooShortPacket)?;
                 let destination = packet.destination();
-                if destination == self.mac || destination == BROADCAST_MAC {
+                if destination == self.mac || destination == MacAddr::BROADCAST {
                     self.route(packet)
                 } else {
                     Err($error_struct_name::InvalidDestination(destination))
@@ -67,8 +65,8 @@ macro_rules! ethernet_rx {
 }
 
 ethernet_rx!(EthernetRx, EthernetRxError {
-    ether_types::IPV4 => [ipv4: (), Ipv4Error: io::Error]
-    ether_types::ARP => [arp: (), ArpError: io::Error]
+    EtherType::IPV4 => [ipv4: (), Ipv4Error: io::Error]
+    EtherType::ARP => [arp: (), ArpError: io::Error]
 });
 
 
@@ -79,35 +77,34 @@ mod tests {
     use std::io;
     use std::sync::mpsc;
 
-    pub struct ErrorListener;
-
-    impl EthernetPayloadListener<io::Error> for ErrorListener {
-        fn recv(&mut self, _data: &[u8]) -> Result<(), io::Error> {
-            Err(io::ErrorKind::AddrNotAvailable.into())
-        }
-    }
-
+    /// Signals each received payload on the channel, and fails once the receiver is dropped.
     pub struct TestListener(mpsc::Sender<()>);
 
     impl EthernetPayloadListener<io::Error> for TestListener {
         fn recv(&mut self, _data: &[u8]) -> Result<(), io::Error> {
-            self.0.send(()).unwrap();
-            Ok(())
+            self.0.send(()).map_err(|_| io::ErrorKind::BrokenPipe.into())
         }
     }
 
     ethernet_rx!(EmptyEthernetRx, EmptyEthernetRxError {});
-    ethernet_rx!(ErrorEthernetRx, ErrorEthernetRxError {
-        ether_types::ARP => [arp: ErrorListener, ArpError: io::Error]
-    });
-    ethernet_rx!(HappyEthernetRx, HappyEthernetRxError {
-        ether_types::IPV4 => [ipv4: TestListener, Ipv4Error: io::Error]
-        ether_types::ARP => [arp: TestListener, ArpError: io::Error]
+    ethernet_rx!(TestEthernetRx, TestEthernetRxError {
+        EtherType::IPV4 => [ipv4: TestListener, Ipv4Error: io::Error]
+        EtherType::ARP => [arp: TestListener, ArpError: io::Error]
     });
 
     static MY_MAC: MacAddr = MacAddr([0xff, 0x01, 0x02, 0x03, 0x04, 0x05]);
     static ZERO_MAC: MacAddr = MacAddr([0x0, 0x0, 0x0, 0x0, 0x0, 0x0]);
 
+    fn packet(destination: MacAddr, ether_type: EtherType) -> [u8; 14] {
+        let mut data = [0u8; 14];
+        {
+            let mut packet = MutEthernetPacket::new(&mut data).unwrap();
+            packet.set_destination(destination);
+            packet.set_ether_type(ether_type);
+        }
+        data
+    }
+
     #[test]
     fn too_short_packet() {
         let mut rx = EmptyEthernetRx::new(MY_MAC);
@@ -121,36 +118,49 @@ mod tests {
             rx.recv(&[0; 14]),
             Err(EmptyEthernetRxError::InvalidDestination(actual_mac)) if actual_mac == ZERO_MAC
         );
+        assert_matches!(
+            rx.recv(&packet(MY_MAC, EtherType::IPV4)),
+            Err(EmptyEthernetRxError::IgnoredEtherType(EtherType::IPV4))
+        );
+    }
+
+    #[test]
+    fn invalid_destination() {
+        let (ipv4_tx, _ipv4_rx) = mpsc::channel();
+        let (arp_tx, _arp_rx) = mpsc::channel();
+        let mut rx = TestEthernetRx::new(MY_MAC, TestListener(ipv4_tx), TestListener(arp_tx));
+
+        assert_matches!(
+            rx.recv(&packet(ZERO_MAC, EtherType::IPV4)),
+            Err(TestEthernetRxError::InvalidDestination(actual_mac)) if actual_mac == ZERO_MAC
+        );
     }
 
     #[test]
     fn ignored_ether_type() {
-        let mut rx = ErrorEthernetRx::new(MY_MAC, ErrorListener);
-        let mut data = [0u8; 14];
-        {
-            let mut packet = MutEthernetPacket::new(&mut data).unwrap();
-            packet.set_destination(MY_MAC);
-        }
+        let (ipv4_tx, _ipv4_rx) = mpsc::channel();
+        let (arp_tx, _arp_rx) = mpsc::channel();
+        let mut rx = TestEthernetRx::new(MY_MAC, TestListener(ipv4_tx), TestListener(arp_tx));
 
         assert_matches!(
-            rx.recv(&data),
-            Err(ErrorEthernetRxError::IgnoredEtherType(EtherType(0)))
+            rx.recv(&packet(MY_MAC, EtherType(0))),
+            Err(TestEthernetRxError::IgnoredEtherType(EtherType(0)))
         );
     }
 
     #[test]
     fn next_level_error() {
-        let mut rx = ErrorEthernetRx::new(MY_MAC, ErrorListener);
-        let mut data = [0u8; 14];
-        {
-            let mut packet = MutEthernetPacket::new(&mut data).unwrap();
-            packet.set_destination(MY_MAC);
-            packet.set_ether_type(ether_types::ARP);
-        }
+        let (ipv4_tx, _) = mpsc::channel();
+        let (arp_tx, _) = mpsc::channel();
+        let mut rx = TestEthernetRx::new(MY_MAC, TestListener(ipv4_tx), TestListener(arp_tx));
 
         assert_matches!(
-            rx.recv(&data).unwrap_err(),
-            ErrorEthernetRxError::ArpError(ref e) if e.kind() == io::ErrorKind::AddrNotAvailable
+            rx.recv(&packet(MY_MAC, EtherType::IPV4)).unwrap_err(),
+            TestEthernetRxError::Ipv4Error(ref e) if e.kind() == io::ErrorKind::BrokenPipe
+        );
+        assert_matches!(
+            rx.recv(&packet(MY_MAC, EtherType::ARP)).unwrap_err(),
+            TestEthernetRxError::ArpError(ref e) if e.kind() == io::ErrorKind::BrokenPipe
         );
     }
 
@@ -159,13 +169,8 @@ mod tests {
         let (ipv4_tx, ipv4_rx) = mpsc::channel();
         let (arp_tx, arp_rx) = mpsc::channel();
 
-        let mut rx = HappyEthernetRx::new(MY_MAC, TestListener(ipv4_tx), TestListener(arp_tx));
-        let mut data = [0u8; 14];
-        {
-            let mut packet = MutEthernetPacket::new(&mut data).unwrap();
-            packet.set_destination(MY_MAC);
-            packet.set_ether_type(ether_types::IPV4);
-        }
+        let mut rx = TestEthernetRx::new(MY_MAC, TestListener(ipv4_tx), TestListener(arp_tx));
+        let mut data = packet(MY_MAC, EtherType::IPV4);
 
         // No listener was called yet
         assert!(ipv4_rx.try_recv().is_err());
@@ -178,7 +183,7 @@ mod tests {
 
         {
             let mut packet = MutEthernetPacket::new(&mut data).unwrap();
-            packet.set_ether_type(ether_types::ARP);
+            packet.set_ether_type(EtherType::ARP);
         }
         // Make sure Arp listener is called
         assert!(rx.recv(&data).is_ok());
@@ -0,0 +1,612 @@
+//! The `#[packet]` attribute, used by `rips-packets` to generate its packet types from a
+//! declaration of the header.
+//!
+//! A header is declared as a struct with one field per header field, in the order they appear
+//! on the wire. The width of a field is taken from its type: `bool` is one bit, and the bit
+//! field aliases of `rips_packets::types` (`u4`, `u13` and so on) as well as the builtin
+//! unsigned integers are as wide as their name says. Other types need a width in a
+//! `#[bits(N)]` attribute and must convert from and into the smallest unsigned integer that
+//! holds `N` bits. Fields are big endian unless marked `#[little_endian]`, which is only
+//! allowed on byte aligned fields of 16 bits or more. Fields named with a leading underscore
+//! are reserved, they take up their bits but get no getters or setters.
+//!
+//! ```rust,ignore
+//! #[packet]
+//! /// A UDP datagram.
+//! pub struct Udp {
+//!     source_port: u16,
+//!     destination_port: u16,
+//!     /// Returns the length of the header and payload in bytes.
+//!     length: u16,
+//!     checksum: u16,
+//! }
+//! ```
+//!
+//! This expands to `packet!(UdpPacket, MutUdpPacket, UdpPacketBuf, 8)`, a `fields!` table with
+//! the position of every field, a getter and setter per field reading through that table and a
+//! test module checking that every setter writes exactly the bits of its field. The doc
+//! comments of the struct and its fields end up on the packet types and getters.
+//!
+//! The minimum length defaults to the length of the declared fields. It can be given as
+//! `#[packet(min_len = N)]`, and a header length that depends on the packet as
+//! `#[packet(header_len = |data| ...)]`, the end of the packet given by a length field as
+//! `#[packet(len = |data| ...)]`, the update of that field by `set_payload` as
+//! `#[packet(set_len = |data, len| ...)]`, and the initialization of new packets as
+//! `#[packet(init = |data| ...)]`, all as for `packet!`.
+//!
+//! So far the attribute declares the UDP, Radiotap and MPLS headers. The other packet types
+//! still define their getters and setters with the `getters!` and `setters!` macros, either
+//! because the position of their fields depends on the packet, which a struct can not describe,
+//! or because their fixed layout has not been ported yet. New headers with a fixed layout are
+//! declared with the attribute.
+//!
+//! The expansion uses the internal macros of `rips-packets`, so the attribute can only be used
+//! inside that crate. It is implemented on top of the compiler's `proc_macro` API alone, to
+//! keep `rips-packets` free of heavy build dependencies.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+use std::fmt::Write;
+
+/// Generates a packet type from a header declaration. See the crate documentation.
+#[proc_macro_attribute]
+pub fn packet(args: TokenStream, item: TokenStream) -> TokenStream {
+    let code = match Options::parse(args).and_then(|options| Header::parse(options, item)) {
+        Ok(header) => header.expand(),
+        Err(message) => format!("compile_error!({:?});", message),
+    };
+    code.parse().expect("Generated code is not valid Rust tokens")
+}
+
+
+/// The arguments given to the attribute itself, kept as source text.
+#[derive(Default)]
+struct Options {
+    min_len: Option<String>,
+    header_len: Option<String>,
+    len: Option<String>,
+    set_len: Option<String>,
+    init: Option<String>,
+}
+
+impl Options {
+    fn parse(args: TokenStream) -> Result<Options, String> {
+        // The arguments are split at every comma, so the parameter list of a closure taking
+        // two arguments ends up in two parts. Parts not starting with `name =` are joined
+        // back onto the previous one.
+        let mut values: Vec<(String, String)> = Vec::new();
+        for arg in split(args) {
+            match (arg.as_slice(), values.last_mut()) {
+                ([TokenTree::Ident(name), TokenTree::Punct(eq), ..], _) if eq.as_char() == '=' => {
+                    values.push((name.to_string(), to_string(&arg[2..])));
+                }
+                (_, Some(&mut (_, ref mut value))) => {
+                    value.push_str(", ");
+                    value.push_str(&to_string(&arg));
+                }
+                _ => {
+                    return Err(
+                        "Expected `min_len`, `header_len`, `len`, `set_len` or `init` = ..."
+                            .to_owned()
+                    )
+                }
+            }
+        }
+
+        let mut options = Options::default();
+        for (name, value) in values {
+            let value = Some(value);
+            match name.as_str() {
+                "min_len" => options.min_len = value,
+                "header_len" => options.header_len = value,
+                "len" => options.len = value,
+                "set_len" => options.set_len = value,
+                "init" => options.init = value,
+                _ => return Err(format!("Unknown packet option `{}`", name)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+
+/// The kind of value a field is read as.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Kind {
+    Bool,
+    /// An unsigned integer with the given number of bits.
+    Uint(usize),
+    /// Any other type, converted from and into the unsigned integer holding the field.
+    Custom,
+}
+
+struct Field {
+    docs: Vec<String>,
+    name: String,
+    ty: String,
+    kind: Kind,
+    start: usize,
+    width: usize,
+    little_endian: bool,
+}
+
+impl Field {
+    fn parse(tokens: &[TokenTree], start: usize) -> Result<Field, String> {
+        let (attributes, rest) = take_attributes(tokens);
+        let rest = skip_visibility(rest);
+        let name = match rest.first() {
+            Some(TokenTree::Ident(name)) => name.to_string(),
+            _ => return Err("Expected a field name".to_owned()),
+        };
+        match rest.get(1) {
+            Some(TokenTree::Punct(colon)) if colon.as_char() == ':' => (),
+            _ => return Err(format!("Expected a type for field `{}`", name)),
+        }
+        let ty = to_string(&rest[2..]);
+
+        let mut docs = Vec::new();
+        let mut bits = None;
+        let mut little_endian = false;
+        for attribute in attributes {
+            let tokens: Vec<TokenTree> = attribute.clone().into_iter().collect();
+            match tokens.first().map(|token| token.to_string()).as_deref() {
+                Some("doc") => docs.push(format!("#[{}]", attribute)),
+                Some("bits") => bits = Some(parse_bits(&tokens, &name)?),
+                Some("little_endian") => little_endian = true,
+                _ => return Err(format!("Unknown attribute `{}` on field `{}`", attribute, name)),
+            }
+        }
+
+        let (kind, type_width) = match (ty.as_str(), uint_width(&ty)) {
+            ("bool", _) => (Kind::Bool, Some(1)),
+            (_, Some(width)) => (Kind::Uint(int_bits(width).unwrap()), Some(width)),
+            _ => (Kind::Custom, None),
+        };
+        let width = match (bits, type_width) {
+            (Some(bits), Some(type_width)) if bits > type_width => {
+                return Err(format!("Field `{}` is wider than its type", name));
+            }
+            (Some(bits), _) => bits,
+            (None, Some(type_width)) => type_width,
+            (None, None) => return Err(format!("Field `{}` needs a `#[bits(N)]` width", name)),
+        };
+        if width == 0 || width > 128 {
+            return Err(format!("Field `{}` must be between 1 and 128 bits wide", name));
+        }
+        if little_endian && (!start.is_multiple_of(8) || width < 16 || kind != Kind::Uint(width)) {
+            return Err(format!(
+                "Little endian field `{}` must be a byte aligned u16, u32, u64 or u128",
+                name
+            ));
+        }
+        if int_bits(start % 8 + width).is_none() {
+            return Err(format!("Field `{}` spans too many bytes to be read at once", name));
+        }
+        Ok(Field { docs, name, ty, kind, start, width, little_endian })
+    }
+
+    fn is_reserved(&self) -> bool {
+        self.name.starts_with('_')
+    }
+
+    fn constant(&self) -> String {
+        self.name.to_uppercase()
+    }
+
+    /// Returns the number of bits of the integer the field is read and written through.
+    fn storage(&self) -> usize {
+        int_bits(self.start % 8 + self.width).unwrap()
+    }
+
+    /// Returns the number of bits of the integer a custom type is converted from and into.
+    fn repr(&self) -> usize {
+        int_bits(self.width).unwrap()
+    }
+
+    fn position_doc(&self) -> String {
+        format!(
+            "Bits {} to {} of the header, see `fields::{}`.",
+            self.start,
+            self.start + self.width - 1,
+            self.constant()
+        )
+    }
+
+    fn getter(&self) -> String {
+        let storage = self.storage();
+        let body = if self.little_endian {
+            format!(
+                "let mut bytes = [0; {}]; \
+                 bytes.copy_from_slice(&self.0[fields::{}.byte_range()]); \
+                 u{}::from_le_bytes(bytes)",
+                self.width / 8,
+                self.constant(),
+                self.width
+            )
+        } else {
+            let raw = format!("read_field!(self.0, fields::{}, u{})", self.constant(), storage);
+            match self.kind {
+                Kind::Bool => format!("{} != 0", raw),
+                Kind::Uint(bits) => convert(&raw, storage, bits),
+                Kind::Custom => {
+                    format!("{}::from({})", self.ty, convert(&raw, storage, self.repr()))
+                }
+            }
+        };
+        let mut docs = self.docs.clone();
+        if !docs.is_empty() {
+            docs.push("#[doc = \"\"]".to_owned());
+        }
+        docs.push(format!("#[doc = {:?}]", self.position_doc()));
+        format!(
+            "{} pub fn {}(&self) -> {} {{ {} }}\n",
+            docs.join(" "),
+            self.name,
+            self.ty,
+            body
+        )
+    }
+
+    fn setter(&self) -> String {
+        let storage = self.storage();
+        let body = if self.little_endian {
+            format!(
+                "self.0[fields::{}.byte_range()].copy_from_slice(&{}.to_le_bytes());",
+                self.constant(),
+                self.name
+            )
+        } else {
+            let value = match self.kind {
+                Kind::Bool => format!("u{}::from({})", storage, self.name),
+                Kind::Uint(bits) => convert(&self.name, bits, storage),
+                Kind::Custom => {
+                    let repr = self.repr();
+                    convert(&format!("u{}::from({})", repr, self.name), repr, storage)
+                }
+            };
+            format!(
+                "write_field!(self.0, fields::{}, {}, u{});",
+                self.constant(),
+                value,
+                storage
+            )
+        };
+        let doc = format!("Sets the value returned by `{}`. {}", self.name, self.position_doc());
+        format!(
+            "#[doc = {:?}] pub fn set_{}(&mut self, {}: {}) {{ {} }}\n",
+            doc, self.name, self.name, self.ty, body
+        )
+    }
+
+    /// Returns a value for the test of this field, with different bytes so that a byte order
+    /// mistake shows.
+    fn test_value(&self) -> u128 {
+        (0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10 & mask(self.width)) | 1
+    }
+
+    /// Writes the bits of `value` into the field in `data`, the way the generated setter is
+    /// expected to.
+    fn encode(&self, value: u128, data: &mut [u8]) {
+        if self.little_endian {
+            let start = self.start / 8;
+            let len = self.width / 8;
+            data[start..start + len].copy_from_slice(&value.to_le_bytes()[..len]);
+        } else {
+            for i in 0..self.width {
+                let bit = self.start + i;
+                let mask = 0x80 >> (bit % 8);
+                if (value >> (self.width - 1 - i)) & 1 == 1 {
+                    data[bit / 8] |= mask;
+                } else {
+                    data[bit / 8] &= !mask;
+                }
+            }
+        }
+    }
+
+    fn test(&self, mut_name: &str, len: usize) -> String {
+        let (value, zero, check) = if self.kind == Kind::Bool {
+            let check = format!("assert!(packet.as_immutable().{}());", self.name);
+            ("true".to_owned(), "false".to_owned(), check)
+        } else {
+            let value = format!("{:#x}", self.test_value());
+            let check = format!("assert_eq!({}, packet.as_immutable().{}());", value, self.name);
+            (value, "0".to_owned(), check)
+        };
+        let mut expected = vec![0; len];
+        self.encode(self.test_value(), &mut expected);
+        let mut cleared = vec![0xff; len];
+        self.encode(0, &mut cleared);
+        format!(
+            "#[test] fn {name}() {{
+                let mut data = [0; {mut_name}::MIN_LEN];
+                {{
+                    let mut packet = {mut_name}::new(&mut data[..]).unwrap();
+                    packet.set_{name}({value});
+                    {check}
+                }}
+                assert_eq!(&{expected:?}[..], &data[..{len}]);
+                assert!(data[{len}..].iter().all(|&byte| byte == 0));
+                let mut data = [0xff; {mut_name}::MIN_LEN];
+                {mut_name}::new(&mut data[..]).unwrap().set_{name}({zero});
+                assert_eq!(&{cleared:?}[..], &data[..{len}]);
+            }}\n",
+            name = self.name,
+            mut_name = mut_name,
+            value = value,
+            check = check,
+            expected = expected,
+            len = len,
+            zero = zero,
+            cleared = cleared,
+        )
+    }
+}
+
+
+struct Header {
+    options: Options,
+    docs: Vec<String>,
+    name: String,
+    fields: Vec<Field>,
+}
+
+impl Header {
+    fn parse(options: Options, item: TokenStream) -> Result<Header, String> {
+        let tokens: Vec<TokenTree> = item.into_iter().collect();
+        let (attributes, rest) = take_attributes(&tokens);
+        let mut docs = Vec::new();
+        for attribute in attributes {
+            match attribute.clone().into_iter().next() {
+                Some(TokenTree::Ident(ref ident)) if ident.to_string() == "doc" => {
+                    docs.push(format!("#[{}]", attribute))
+                }
+                _ => return Err(format!("Unsupported attribute `{}` on a packet", attribute)),
+            }
+        }
+        let rest = skip_visibility(rest);
+        let (name, body) = match rest {
+            [TokenTree::Ident(keyword), TokenTree::Ident(name), TokenTree::Group(body)]
+                if keyword.to_string() == "struct" && body.delimiter() == Delimiter::Brace =>
+            {
+                (name.to_string(), body.stream())
+            }
+            _ => return Err("Expected a struct with named fields and no generics".to_owned()),
+        };
+
+        let mut fields = Vec::new();
+        let mut start = 0;
+        for tokens in split(body) {
+            let field = Field::parse(&tokens, start)?;
+            start += field.width;
+            fields.push(field);
+        }
+        if options.min_len.is_none() && !start.is_multiple_of(8) {
+            return Err(format!("The fields of `{}` do not add up to whole bytes", name));
+        }
+        Ok(Header { options, docs, name, fields })
+    }
+
+    /// Returns the number of bytes covered by the declared fields.
+    fn len(&self) -> usize {
+        self.fields.last().map_or(0, |field| (field.start + field.width).div_ceil(8))
+    }
+
+    fn expand(&self) -> String {
+        let packet = format!("{}Packet", self.name);
+        let mut_packet = format!("Mut{}Packet", self.name);
+        let fields: Vec<&Field> = self.fields.iter().filter(|field| !field.is_reserved()).collect();
+
+        let mut code = String::new();
+        let min_len = self.options.min_len.clone().unwrap_or_else(|| self.len().to_string());
+        let mut hooks = String::new();
+        let options = &self.options;
+        let hooked = [&options.header_len, &options.len, &options.set_len, &options.init];
+        if hooked.iter().any(|hook| hook.is_some()) {
+            match options.header_len {
+                Some(ref header_len) => write!(hooks, ", {}", header_len).unwrap(),
+                None => write!(hooks, ", |_data| {}", min_len).unwrap(),
+            }
+            if let Some(ref len) = options.len {
+                write!(hooks, ", len {}", len).unwrap();
+            }
+            if let Some(ref set_len) = options.set_len {
+                write!(hooks, ", set_len {}", set_len).unwrap();
+            }
+            let init = options.init.as_ref().map_or("|_data| {}", String::as_str);
+            write!(hooks, ", init {}", init).unwrap();
+        }
+        writeln!(
+            code,
+            "packet!({} {}, {}, {}Buf, {}{});",
+            self.docs.join(" "),
+            packet,
+            mut_packet,
+            packet,
+            min_len,
+            hooks
+        )
+        .unwrap();
+
+        write!(code, "fields!({},", packet).unwrap();
+        for field in &fields {
+            write!(code, " {}: {}, {};", field.constant(), field.start, field.width).unwrap();
+        }
+        code.push_str(");\n");
+
+        writeln!(code, "getters!(common {}", packet).unwrap();
+        for field in &fields {
+            code.push_str(&field.getter());
+        }
+        code.push_str(");\n");
+
+        writeln!(code, "setters!({}", mut_packet).unwrap();
+        for field in &fields {
+            code.push_str(&field.setter());
+        }
+        code.push_str(");\n");
+
+        writeln!(code, "#[cfg(test)] mod {}_layout_tests {{ use super::*;", snake_case(&self.name))
+            .unwrap();
+        for field in fields.iter().filter(|field| field.kind != Kind::Custom) {
+            code.push_str(&field.test(&mut_packet, self.len()));
+        }
+        code.push_str("}\n");
+        code
+    }
+}
+
+
+/// Splits `tokens` at the commas that are not nested in any group, dropping empty parts.
+fn split(tokens: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    for token in tokens {
+        match token {
+            TokenTree::Punct(ref comma) if comma.as_char() == ',' => parts.push(Vec::new()),
+            token => parts.last_mut().unwrap().push(token),
+        }
+    }
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Returns the contents of the leading `#[...]` attributes and the tokens after them.
+fn take_attributes(tokens: &[TokenTree]) -> (Vec<TokenStream>, &[TokenTree]) {
+    let mut attributes = Vec::new();
+    let mut rest = tokens;
+    while let [TokenTree::Punct(hash), TokenTree::Group(group), tail @ ..] = rest {
+        if hash.as_char() != '#' || group.delimiter() != Delimiter::Bracket {
+            break;
+        }
+        attributes.push(group.stream());
+        rest = tail;
+    }
+    (attributes, rest)
+}
+
+/// Skips a leading `pub` or `pub(...)`.
+fn skip_visibility(tokens: &[TokenTree]) -> &[TokenTree] {
+    match tokens {
+        [TokenTree::Ident(vis), TokenTree::Group(group), rest @ ..]
+            if vis.to_string() == "pub" && group.delimiter() == Delimiter::Parenthesis =>
+        {
+            rest
+        }
+        [TokenTree::Ident(vis), rest @ ..] if vis.to_string() == "pub" => rest,
+        _ => tokens,
+    }
+}
+
+fn parse_bits(tokens: &[TokenTree], name: &str) -> Result<usize, String> {
+    match tokens.get(1) {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream().to_string().trim().parse().map_err(|_| {
+                format!("Expected a number of bits in `#[bits(N)]` on field `{}`", name)
+            })
+        }
+        _ => Err(format!("Expected `#[bits(N)]` on field `{}`", name)),
+    }
+}
+
+fn to_string(tokens: &[TokenTree]) -> String {
+    tokens.iter().cloned().collect::<TokenStream>().to_string()
+}
+
+/// Returns the width of the unsigned integer type or bit field alias `ty`, such as 4 for `u4`.
+fn uint_width(ty: &str) -> Option<usize> {
+    let digits = ty.strip_prefix('u')?;
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok().filter(|&width| width > 0 && width <= 128)
+}
+
+/// Returns the number of bits in the smallest unsigned integer type holding `bits` bits.
+fn int_bits(bits: usize) -> Option<usize> {
+    [8, 16, 32, 64, 128].iter().cloned().find(|&size| bits <= size)
+}
+
+/// Returns an expression converting `expr` from an unsigned integer of `from` bits to one of
+/// `to` bits. Narrowing conversions are only done on values known to fit.
+fn convert(expr: &str, from: usize, to: usize) -> String {
+    if from == to {
+        expr.to_owned()
+    } else if from < to {
+        format!("u{}::from({})", to, expr)
+    } else {
+        format!("{} as u{}", expr, to)
+    }
+}
+
+fn mask(width: usize) -> u128 {
+    if width >= 128 {
+        u128::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(Some(4), uint_width("u4"));
+        assert_eq!(Some(128), uint_width("u128"));
+        assert_eq!(None, uint_width("usize"));
+        assert_eq!(None, uint_width("u0"));
+        assert_eq!(None, uint_width("Protocol"));
+        assert_eq!(Some(8), int_bits(3));
+        assert_eq!(Some(32), int_bits(20));
+        assert_eq!(None, int_bits(129));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!("x", convert("x", 16, 16));
+        assert_eq!("u32::from(x)", convert("x", 16, 32));
+        assert_eq!("x as u8", convert("x", 16, 8));
+    }
+
+    #[test]
+    fn encode() {
+        let field = |start, width, little_endian| Field {
+            docs: Vec::new(),
+            name: "field".to_owned(),
+            ty: "u16".to_owned(),
+            kind: Kind::Uint(16),
+            start,
+            width,
+            little_endian,
+        };
+        let mut data = [0; 3];
+        field(4, 13, false).encode(0x1fff, &mut data);
+        assert_eq!([0x0f, 0xff, 0x80], data);
+        field(4, 13, false).encode(0, &mut data);
+        assert_eq!([0; 3], data);
+        field(8, 16, true).encode(0x0f11, &mut data);
+        assert_eq!([0, 0x11, 0x0f], data);
+        assert_eq!(0x0f11, field(8, 16, true).test_value());
+        assert_eq!(1, field(0, 1, false).test_value());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!("udp", snake_case("Udp"));
+        assert_eq!("udp_lite", snake_case("UdpLite"));
+    }
+}
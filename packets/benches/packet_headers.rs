@@ -3,9 +3,13 @@
 extern crate rips_packets;
 extern crate test;
 
-use rips_packets::ethernet::{MutEthernetPacket, ether_types};
-use rips_packets::ipv4::{self, MutIpv4Packet};
-use rips_packets::macaddr::MacAddr;
+use rips_packets::arp::{ArpPacket, HardwareType, MutArpPacket, Operation};
+use rips_packets::checksum;
+use rips_packets::ethernet::{EtherType, MacAddr, MutEthernetPacket};
+use rips_packets::ip::Protocol;
+use rips_packets::ipv4::{Flags, Ipv4Packet, MutIpv4Packet};
+use rips_packets::tcp::TcpPacket;
+use rips_packets::vxlan::{MutVxlanPacket, VxlanPacket};
 use std::net::Ipv4Addr;
 use test::{Bencher, black_box};
 
@@ -18,7 +22,7 @@ fn set_all_ethernet_fields(b: &mut Bencher) {
         let mut packet = MutEthernetPacket::new(black_box(&mut buffer[..])).unwrap();
         packet.set_destination(black_box(destination));
         packet.set_source(black_box(source));
-        packet.set_ether_type(black_box(ether_types::ARP));
+        packet.set_ether_type(black_box(EtherType::ARP));
     });
 }
 
@@ -35,12 +39,103 @@ fn set_all_ipv4_fields(b: &mut Bencher) {
         packet.set_ecn(black_box(0));
         packet.set_total_length(black_box(20));
         packet.set_identification(black_box(0x1337));
-        packet.set_flags(black_box(0b011));
+        packet.set_flags(black_box(Flags::DF | Flags::MF));
         packet.set_fragment_offset(black_box(13));
         packet.set_ttl(black_box(40));
-        packet.set_protocol(black_box(ipv4::protocols::UDP));
+        packet.set_protocol(black_box(Protocol::UDP));
         packet.set_header_checksum(black_box(0x1337));
         packet.set_source(black_box(source));
         packet.set_destination(black_box(destination));
     });
 }
+
+#[bench]
+fn get_all_ipv4_fields(b: &mut Bencher) {
+    let mut buffer = [0; 20];
+    buffer[0] = 0x45;
+    b.iter(|| {
+        let packet = Ipv4Packet::new(black_box(&buffer[..])).unwrap();
+        black_box(packet.version());
+        black_box(packet.header_length());
+        black_box(packet.dscp());
+        black_box(packet.ecn());
+        black_box(packet.total_length());
+        black_box(packet.identification());
+        black_box(packet.flags());
+        black_box(packet.fragment_offset());
+        black_box(packet.ttl());
+        black_box(packet.protocol());
+        black_box(packet.header_checksum());
+        black_box(packet.source());
+        black_box(packet.destination());
+    });
+}
+
+#[bench]
+fn get_all_tcp_fields(b: &mut Bencher) {
+    let mut buffer = [0; 20];
+    buffer[12] = 0x50;
+    b.iter(|| {
+        let packet = TcpPacket::new(black_box(&buffer[..])).unwrap();
+        black_box(packet.source_port());
+        black_box(packet.destination_port());
+        black_box(packet.sequence_number());
+        black_box(packet.acknowledgment_number());
+        black_box(packet.data_offset());
+        black_box(packet.flags());
+        black_box(packet.window_size());
+        black_box(packet.checksum());
+        black_box(packet.urgent_pointer());
+    });
+}
+
+#[bench]
+fn get_all_arp_fields(b: &mut Bencher) {
+    let buffer = [0x12; 28];
+    b.iter(|| {
+        let packet = ArpPacket::new(black_box(&buffer[..])).unwrap();
+        black_box(packet.hardware_type());
+        black_box(packet.protocol_type());
+        black_box(packet.hardware_length());
+        black_box(packet.protocol_length());
+        black_box(packet.operation());
+        black_box(packet.sender_mac_addr());
+        black_box(packet.sender_ip_addr());
+        black_box(packet.target_mac_addr());
+        black_box(packet.target_ip_addr());
+    });
+}
+
+#[bench]
+fn set_all_arp_fields(b: &mut Bencher) {
+    let mut buffer = [0; 28];
+    let mac = MacAddr([0xff, 0x00, 0xff, 0x00, 0xff, 0x00]);
+    let ip = Ipv4Addr::new(192, 168, 0, 1);
+    b.iter(|| {
+        let mut packet = MutArpPacket::new(black_box(&mut buffer[..])).unwrap();
+        packet.set_hardware_type(black_box(HardwareType::ETHERNET));
+        packet.set_protocol_type(black_box(EtherType::IPV4));
+        packet.set_hardware_length(black_box(6));
+        packet.set_protocol_length(black_box(4));
+        packet.set_operation(black_box(Operation::REPLY));
+        packet.set_sender_mac_addr(black_box(mac));
+        packet.set_sender_ip_addr(black_box(ip));
+        packet.set_target_mac_addr(black_box(mac));
+        packet.set_target_ip_addr(black_box(ip));
+    });
+}
+
+#[bench]
+fn get_and_set_vxlan_vni(b: &mut Bencher) {
+    let mut buffer = [0; 8];
+    b.iter(|| {
+        MutVxlanPacket::new(black_box(&mut buffer[..])).unwrap().set_vni(black_box(0x12_3456));
+        black_box(VxlanPacket::new(black_box(&buffer[..])).unwrap().vni());
+    });
+}
+
+#[bench]
+fn internet_checksum_1500_bytes(b: &mut Bencher) {
+    let buffer = [0xab; 1500];
+    b.iter(|| checksum::internet_checksum(black_box(&buffer[..])));
+}
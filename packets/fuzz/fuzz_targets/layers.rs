@@ -0,0 +1,35 @@
+//! Feeds the input to the layered dissectors, as an Ethernet frame and as an IP packet, and
+//! formats and renders as JSON every layer found. The sliced view of the frame must agree with
+//! the layers.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate rips_packets;
+
+use rips_packets::json::ToJson;
+use rips_packets::layer::{Layer, Layers};
+use rips_packets::sliced::SlicedPacket;
+
+fuzz_target!(|data: &[u8]| {
+    let mut layers = 0;
+    for layer in Layers::from_ethernet(data) {
+        let _ = (format!("{:?}", layer), layer.to_json());
+        if let Layer::Payload(payload) = layer {
+            assert!(payload.len() <= data.len());
+        }
+        layers += 1;
+    }
+    assert!(layers > 0 || data.is_empty());
+    for layer in Layers::from_ip(data) {
+        let _ = (format!("{:?}", layer), layer.to_json());
+    }
+
+    if let Some(sliced) = SlicedPacket::from_ethernet(data) {
+        let _ = format!("{:?}", sliced);
+        if let Some(ip) = sliced.ip {
+            let _ = (ip.source(), ip.destination());
+        }
+        assert!(sliced.payload.len() <= data.len());
+    }
+});
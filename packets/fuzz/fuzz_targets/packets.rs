@@ -0,0 +1,308 @@
+//! Feeds the input to the constructors of every packet type: immutable, mutable and owned, and
+//! `from_chain` with the input split in two. Checks that the header and payload they report lie
+//! within the input and agree between the constructors.
+//!
+//! Each packet is formatted with `Debug`, which calls every getter of the type and collects
+//! every iterator it returns, and with `Display` and as a hexdump. The methods that `Debug` does
+//! not cover, the ones taking arguments and the ones viewing the packet as another type, are
+//! called in the expression given after the type.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate rips_packets;
+
+use rips_packets::chain::Chain;
+use rips_packets::sixlowpan::LinkAddress;
+use rips_packets::*;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+const IPV4: Ipv4Addr = Ipv4Addr::LOCALHOST;
+const IPV6: Ipv6Addr = Ipv6Addr::LOCALHOST;
+const LINK: LinkAddress = LinkAddress::Short(0x1234);
+const CONTEXTS: [Ipv6Addr; 2] = [Ipv6Addr::UNSPECIFIED, Ipv6Addr::LOCALHOST];
+
+/// Checks that `header` and `payload` are the parts of `data` that follow each other, starting
+/// at its beginning.
+fn check_parts(data: &[u8], header: &[u8], payload: &[u8], min_len: usize) {
+    assert!(header.len() >= min_len);
+    assert_eq!(data.as_ptr(), header.as_ptr());
+    if !payload.is_empty() {
+        assert_eq!(data[header.len()..].as_ptr(), payload.as_ptr());
+    }
+    assert!(header.len() + payload.len() <= data.len());
+}
+
+macro_rules! fuzz_packets {
+    ($data:expr, $chain:expr, $(
+        $module:ident::{$packet:ident, $mut_packet:ident, $buf:ident $(,)?}
+        $(=> |$extra_packet:ident| $extra:expr)?;
+    )*) => {$(
+        let mut scratch = vec![0; $data.len()];
+        let chained = $module::$packet::from_chain($chain, &mut scratch);
+        if let Some(packet) = $module::$packet::new($data) {
+            assert_eq!(packet.header_len(), packet.header().len());
+            check_parts($data, packet.header(), packet.payload(), $module::$packet::MIN_LEN);
+            let _ = format!("{:?} {} {}", packet, packet, packet.hexdump());
+            $(
+                let $extra_packet = packet;
+                let _ = format!("{:?}", $extra);
+            )?
+            // Headers found by scanning them can come out shorter when split over the chain.
+            if let Some((chained, rest)) = chained {
+                assert!(packet.header().starts_with(chained.data()));
+                assert_eq!($data.len(), chained.data().len() + rest.len());
+            }
+        } else {
+            assert!($data.len() < $module::$packet::MIN_LEN);
+            assert!(chained.is_none());
+        }
+        let mut copy = $data.to_vec();
+        if let Some(mut packet) = $module::$mut_packet::new(&mut copy) {
+            let header_len = packet.as_immutable().header_len();
+            assert_eq!(header_len, packet.header().len());
+            let payload_len = packet.payload().len();
+            assert_eq!(payload_len, packet.as_immutable().payload().len());
+            let _ = format!("{:?}", packet);
+        }
+        match $module::$buf::new($data.to_vec()) {
+            Some(packet) => assert_eq!($data, packet.packet().data()),
+            None => assert!($data.len() < $module::$buf::<Vec<u8>>::MIN_LEN),
+        }
+    )*};
+}
+
+fuzz_target!(|data: &[u8]| {
+    let split = data.first().map_or(0, |&byte| usize::from(byte)).min(data.len());
+    let segments = [&data[..split], &data[split..]];
+    fuzz_packets!(data, Chain::new(&segments),
+    ah::{AhPacket, MutAhPacket, AhPacketBuf};
+    arp::{ArpPacket, MutArpPacket, ArpPacketBuf};
+    bgp::{BgpPacket, MutBgpPacket, BgpPacketBuf} => |packet| {
+        (packet.open(), packet.update(), packet.notification())
+    };
+    bgp::{OpenPacket, MutOpenPacket, OpenPacketBuf};
+    bgp::{UpdatePacket, MutUpdatePacket, UpdatePacketBuf};
+    bgp::{NotificationPacket, MutNotificationPacket, NotificationPacketBuf};
+    dccp::{DccpPacket, MutDccpPacket, DccpPacketBuf} => |packet| {
+        (packet.generic_header_len(), packet.fixed_header_len())
+    };
+    dhcp::{DhcpPacket, MutDhcpPacket, DhcpPacketBuf};
+    dhcpv6::{Dhcpv6Packet, MutDhcpv6Packet, Dhcpv6PacketBuf};
+    dns::{DnsPacket, MutDnsPacket, DnsPacketBuf};
+    eapol::{EapolPacket, MutEapolPacket, EapolPacketBuf};
+    eapol::{EapolKeyPacket, MutEapolKeyPacket, EapolKeyPacketBuf};
+    erspan::{Erspan2Packet, MutErspan2Packet, Erspan2PacketBuf};
+    erspan::{Erspan3Packet, MutErspan3Packet, Erspan3PacketBuf};
+    esp::{EspPacket, MutEspPacket, EspPacketBuf} => |packet| {
+        (0..=32)
+            .map(|len| (packet.iv(len), packet.encrypted_data(len, len), packet.icv(len)))
+            .last()
+    };
+    ethernet::{EthernetPacket, MutEthernetPacket, EthernetPacketBuf} => |packet| packet.to_header();
+    geneve::{GenevePacket, MutGenevePacket, GenevePacketBuf};
+    geneve::{GeneveOptionPacket, MutGeneveOptionPacket, GeneveOptionPacketBuf};
+    gre::{GrePacket, MutGrePacket, GrePacketBuf} => |packet| packet.calculate_checksum();
+    gtpu::{GtpuPacket, MutGtpuPacket, GtpuPacketBuf};
+    hsrp::{HsrpPacket, MutHsrpPacket, HsrpPacketBuf};
+    hsrp::{GroupStatePacket, MutGroupStatePacket, GroupStatePacketBuf};
+    icmp_extension::{ExtensionPacket, MutExtensionPacket, ExtensionPacketBuf};
+    icmpv4::{Icmpv4Packet, MutIcmpv4Packet, Icmpv4PacketBuf};
+    icmpv4::{EchoPacket, MutEchoPacket, EchoPacketBuf};
+    icmpv4::{
+        DestinationUnreachablePacket,
+        MutDestinationUnreachablePacket,
+        DestinationUnreachablePacketBuf,
+    };
+    icmpv4::{TimeExceededPacket, MutTimeExceededPacket, TimeExceededPacketBuf};
+    icmpv4::{RedirectPacket, MutRedirectPacket, RedirectPacketBuf};
+    icmpv6::{MldPacket, MutMldPacket, MldPacketBuf};
+    icmpv6::{Mldv2QueryPacket, MutMldv2QueryPacket, Mldv2QueryPacketBuf};
+    icmpv6::{Mldv2ReportPacket, MutMldv2ReportPacket, Mldv2ReportPacketBuf};
+    icmpv6::{
+        MulticastAddressRecordPacket,
+        MutMulticastAddressRecordPacket,
+        MulticastAddressRecordPacketBuf,
+    };
+    icmpv6::{Icmpv6Packet, MutIcmpv6Packet, Icmpv6PacketBuf};
+    icmpv6::{RouterSolicitationPacket, MutRouterSolicitationPacket, RouterSolicitationPacketBuf};
+    icmpv6::{RouterAdvertisementPacket, MutRouterAdvertisementPacket, RouterAdvertisementPacketBuf};
+    icmpv6::{
+        NeighborSolicitationPacket,
+        MutNeighborSolicitationPacket,
+        NeighborSolicitationPacketBuf,
+    };
+    icmpv6::{
+        NeighborAdvertisementPacket,
+        MutNeighborAdvertisementPacket,
+        NeighborAdvertisementPacketBuf,
+    };
+    icmpv6::{PrefixInformationPacket, MutPrefixInformationPacket, PrefixInformationPacketBuf};
+    ieee80211::{Ieee80211Packet, MutIeee80211Packet, Ieee80211PacketBuf} => |packet| {
+        (packet.beacon(), packet.probe_request(), packet.probe_response(), packet.llc())
+    };
+    ieee80211::{BeaconPacket, MutBeaconPacket, BeaconPacketBuf};
+    ieee80211::{ProbeRequestPacket, MutProbeRequestPacket, ProbeRequestPacketBuf};
+    igmp::{IgmpPacket, MutIgmpPacket, IgmpPacketBuf};
+    igmp::{Igmpv3QueryPacket, MutIgmpv3QueryPacket, Igmpv3QueryPacketBuf};
+    igmp::{Igmpv3ReportPacket, MutIgmpv3ReportPacket, Igmpv3ReportPacketBuf};
+    igmp::{GroupRecordPacket, MutGroupRecordPacket, GroupRecordPacketBuf};
+    ikev2::{Ikev2Packet, MutIkev2Packet, Ikev2PacketBuf};
+    ipv4::{Ipv4Packet, MutIpv4Packet, Ipv4PacketBuf} => |packet| {
+        (packet.padding(), packet.split_padding(), packet.trimmed(), packet.to_header())
+    };
+    ipv6::{
+        Ipv6DestinationOptionsPacket,
+        MutIpv6DestinationOptionsPacket,
+        Ipv6DestinationOptionsPacketBuf,
+    };
+    ipv6::{Ipv6FragmentPacket, MutIpv6FragmentPacket, Ipv6FragmentPacketBuf};
+    ipv6::{Ipv6HopByHopPacket, MutIpv6HopByHopPacket, Ipv6HopByHopPacketBuf};
+    ipv6::{Ipv6Packet, MutIpv6Packet, Ipv6PacketBuf} => |packet| packet.to_header();
+    ipv6::{Ipv6RoutingPacket, MutIpv6RoutingPacket, Ipv6RoutingPacketBuf} => |packet| {
+        (0..=u8::MAX).map(|index| packet.segment(index)).last()
+    };
+    isis::{IsisPacket, MutIsisPacket, IsisPacketBuf} => |packet| {
+        (packet.lan_hello(), packet.p2p_hello(), packet.lsp())
+    };
+    isis::{LanHelloPacket, MutLanHelloPacket, LanHelloPacketBuf};
+    isis::{P2pHelloPacket, MutP2pHelloPacket, P2pHelloPacketBuf};
+    isis::{LspPacket, MutLspPacket, LspPacketBuf} => |packet| packet.calculate_checksum();
+    l2tp::{L2tpPacket, MutL2tpPacket, L2tpPacketBuf} => |packet| packet.is_control();
+    lacp::{LacpPacket, MutLacpPacket, LacpPacketBuf};
+    lacp::{PortInfoPacket, MutPortInfoPacket, PortInfoPacketBuf};
+    llc::{LlcPacket, MutLlcPacket, LlcPacketBuf};
+    llc::{SnapPacket, MutSnapPacket, SnapPacketBuf};
+    lldp::{LldpPacket, MutLldpPacket, LldpPacketBuf};
+    modbus::{ModbusPacket, MutModbusPacket, ModbusPacketBuf};
+    mpls::{MplsPacket, MutMplsPacket, MplsPacketBuf} => |packet| packet.top();
+    netflow::{NetflowV5Packet, MutNetflowV5Packet, NetflowV5PacketBuf};
+    netflow::{NetflowV5RecordPacket, MutNetflowV5RecordPacket, NetflowV5RecordPacketBuf};
+    netflow::{NetflowV9Packet, MutNetflowV9Packet, NetflowV9PacketBuf};
+    netflow::{IpfixPacket, MutIpfixPacket, IpfixPacketBuf};
+    ntp::{NtpPacket, MutNtpPacket, NtpPacketBuf};
+    nvgre::{NvgrePacket, MutNvgrePacket, NvgrePacketBuf};
+    ospf::{OspfPacket, MutOspfPacket, OspfPacketBuf} => |packet| {
+        (
+            packet.hello(),
+            packet.database_description(),
+            packet.link_state_request(),
+            packet.link_state_update(),
+            packet.link_state_ack(),
+        )
+    };
+    ospf::{HelloPacket, MutHelloPacket, HelloPacketBuf};
+    ospf::{DatabaseDescriptionPacket, MutDatabaseDescriptionPacket, DatabaseDescriptionPacketBuf};
+    ospf::{LinkStateRequestPacket, MutLinkStateRequestPacket, LinkStateRequestPacketBuf};
+    ospf::{LinkStateUpdatePacket, MutLinkStateUpdatePacket, LinkStateUpdatePacketBuf};
+    ospf::{LinkStateAckPacket, MutLinkStateAckPacket, LinkStateAckPacketBuf};
+    ospf::{LsaHeaderPacket, MutLsaHeaderPacket, LsaHeaderPacketBuf} => |packet| {
+        packet.calculate_checksum()
+    };
+    ppp::{PppPacket, MutPppPacket, PppPacketBuf};
+    pppoe::{PppoePacket, MutPppoePacket, PppoePacketBuf};
+    quic::{QuicPacket, MutQuicPacket, QuicPacketBuf} => |packet| {
+        (0..=20).map(|len| (packet.short_header_dcid(len), packet.dcid_with_short_len(len))).last()
+    };
+    radiotap::{RadiotapPacket, MutRadiotapPacket, RadiotapPacketBuf};
+    rip::{RipPacket, MutRipPacket, RipPacketBuf};
+    rip::{RouteEntryPacket, MutRouteEntryPacket, RouteEntryPacketBuf};
+    rtcp::{RtcpPacket, MutRtcpPacket, RtcpPacketBuf} => |packet| {
+        (
+            packet.sender_report(),
+            packet.receiver_report(),
+            packet.source_description(),
+            packet.bye(),
+        )
+    };
+    rtcp::{SenderReportPacket, MutSenderReportPacket, SenderReportPacketBuf};
+    rtcp::{ReceiverReportPacket, MutReceiverReportPacket, ReceiverReportPacketBuf};
+    rtcp::{ReportBlockPacket, MutReportBlockPacket, ReportBlockPacketBuf};
+    rtcp::{SdesPacket, MutSdesPacket, SdesPacketBuf};
+    rtcp::{ByePacket, MutByePacket, ByePacketBuf};
+    rtp::{RtpPacket, MutRtpPacket, RtpPacketBuf};
+    sctp::{ChunkPacket, MutChunkPacket, ChunkPacketBuf};
+    sctp::{DataChunkPacket, MutDataChunkPacket, DataChunkPacketBuf};
+    sctp::{InitChunkPacket, MutInitChunkPacket, InitChunkPacketBuf};
+    sctp::{SackChunkPacket, MutSackChunkPacket, SackChunkPacketBuf};
+    sctp::{HeartbeatChunkPacket, MutHeartbeatChunkPacket, HeartbeatChunkPacketBuf};
+    sctp::{SctpPacket, MutSctpPacket, SctpPacketBuf} => |packet| packet.calculate_checksum();
+    sflow::{SflowPacket, MutSflowPacket, SflowPacketBuf};
+    sflow::{FlowSamplePacket, MutFlowSamplePacket, FlowSamplePacketBuf};
+    sflow::{ExpandedFlowSamplePacket, MutExpandedFlowSamplePacket, ExpandedFlowSamplePacketBuf};
+    sflow::{CounterSamplePacket, MutCounterSamplePacket, CounterSamplePacketBuf};
+    sflow::{
+        ExpandedCounterSamplePacket,
+        MutExpandedCounterSamplePacket,
+        ExpandedCounterSamplePacketBuf,
+    };
+    sflow::{RawPacketHeaderPacket, MutRawPacketHeaderPacket, RawPacketHeaderPacketBuf};
+    sflow::{
+        GenericInterfaceCountersPacket,
+        MutGenericInterfaceCountersPacket,
+        GenericInterfaceCountersPacketBuf,
+    };
+    sixlowpan::{IphcPacket, MutIphcPacket, IphcPacketBuf} => |packet| {
+        (packet.source(LINK, &CONTEXTS), packet.destination(LINK, &CONTEXTS))
+    };
+    sixlowpan::{UdpNhcPacket, MutUdpNhcPacket, UdpNhcPacketBuf};
+    stp::{BpduPacket, MutBpduPacket, BpduPacketBuf};
+    stp::{ConfigBpduPacket, MutConfigBpduPacket, ConfigBpduPacketBuf};
+    tcp::{TcpPacket, MutTcpPacket, TcpPacketBuf} => |packet| {
+        (
+            packet.calculate_checksum(IPV4.into(), IPV4.into()),
+            packet.verify_checksum(IPV6.into(), IPV6.into()),
+            packet.to_header(),
+        )
+    };
+    tftp::{TftpPacket, MutTftpPacket, TftpPacketBuf} => |packet| {
+        (packet.request(), packet.data_packet(), packet.ack(), packet.error(), packet.option_ack())
+    };
+    tftp::{RequestPacket, MutRequestPacket, RequestPacketBuf};
+    tftp::{DataPacket, MutDataPacket, DataPacketBuf};
+    tftp::{AckPacket, MutAckPacket, AckPacketBuf};
+    tftp::{ErrorPacket, MutErrorPacket, ErrorPacketBuf};
+    tftp::{OptionAckPacket, MutOptionAckPacket, OptionAckPacketBuf};
+    tls::{TlsRecordPacket, MutTlsRecordPacket, TlsRecordPacketBuf};
+    tls::{HandshakePacket, MutHandshakePacket, HandshakePacketBuf} => |packet| {
+        packet.client_hello()
+    };
+    tls::{ClientHelloPacket, MutClientHelloPacket, ClientHelloPacketBuf};
+    udp::{UdpPacket, MutUdpPacket, UdpPacketBuf} => |packet| {
+        (
+            packet.calculate_checksum(IPV4.into(), IPV4.into()),
+            packet.verify_checksum(IPV6.into(), IPV6.into()),
+            packet.to_header(),
+        )
+    };
+    udp_lite::{UdpLitePacket, MutUdpLitePacket, UdpLitePacketBuf} => |packet| {
+        (packet.coverage_len(), packet.ipv4_checksum(IPV4, IPV4), packet.ipv6_checksum(IPV6, IPV6))
+    };
+    vlan::{VlanPacket, MutVlanPacket, VlanPacketBuf};
+    vrrp::{VrrpPacket, MutVrrpPacket, VrrpPacketBuf} => |packet| {
+        (
+            packet.interval(),
+            packet.calculate_checksum(),
+            packet.ipv4_checksum(IPV4, IPV4),
+            packet.ipv6_checksum(IPV6, IPV6),
+        )
+    };
+    vxlan::{VxlanPacket, MutVxlanPacket, VxlanPacketBuf};
+    wireguard::{WireguardPacket, MutWireguardPacket, WireguardPacketBuf} => |packet| {
+        (
+            packet.handshake_initiation(),
+            packet.handshake_response(),
+            packet.cookie_reply(),
+            packet.transport_data(),
+        )
+    };
+    wireguard::{
+        HandshakeInitiationPacket,
+        MutHandshakeInitiationPacket,
+        HandshakeInitiationPacketBuf,
+    };
+    wireguard::{HandshakeResponsePacket, MutHandshakeResponsePacket, HandshakeResponsePacketBuf};
+    wireguard::{CookieReplyPacket, MutCookieReplyPacket, CookieReplyPacketBuf};
+    wireguard::{TransportDataPacket, MutTransportDataPacket, TransportDataPacketBuf};
+    );
+});
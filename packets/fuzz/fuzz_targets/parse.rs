@@ -0,0 +1,76 @@
+//! Feeds the input to the constructors that validate it or find a packet inside another one:
+//! the `parse` constructors, the ones reading a packet out of the payload of another, and the
+//! parsers of the ESP trailer and Teredo indicators. Packets that parse are displayed, rendered
+//! as JSON and have their options and checksums decoded.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate rips_packets;
+
+use rips_packets::erspan::{Erspan2Packet, Erspan3Packet};
+use rips_packets::esp::EspTrailer;
+use rips_packets::ethernet::EthernetPacket;
+use rips_packets::gre::GrePacket;
+use rips_packets::ipv4::Ipv4Packet;
+use rips_packets::ipv6::Ipv6Packet;
+use rips_packets::json::ToJson;
+use rips_packets::llc::LlcPacket;
+use rips_packets::nvgre::NvgrePacket;
+use rips_packets::ppp::PppPacket;
+use rips_packets::tcp::TcpPacket;
+use rips_packets::teredo::Indicators;
+use rips_packets::udp::UdpPacket;
+use std::net::{IpAddr, Ipv4Addr};
+
+const ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(packet) = Ipv4Packet::parse(data) {
+        let _ = format!("{} {}", packet, packet.to_json());
+        packet.options().for_each(drop);
+        assert!(packet.is_checksum_valid());
+    }
+    if let Ok(packet) = Ipv6Packet::parse(data) {
+        let _ = format!("{} {}", packet, packet.to_json());
+    }
+    if let Ok(packet) = TcpPacket::parse(data) {
+        let _ = format!("{} {}", packet, packet.to_json());
+        packet.options().for_each(drop);
+        let _ = packet.calculate_checksum(ADDRESS, ADDRESS);
+    }
+    if let Ok(packet) = UdpPacket::parse(data) {
+        let _ = format!("{} {}", packet, packet.to_json());
+        let _ = packet.calculate_checksum(ADDRESS, ADDRESS);
+    }
+
+    if let Some(packet) = PppPacket::new_hdlc(data) {
+        assert!(packet.data().len() <= data.len());
+        let _ = format!("{:?} {}", packet, packet);
+    }
+    if let Some(gre) = GrePacket::new(data) {
+        if let Some(packet) = Erspan2Packet::from_gre(gre) {
+            let _ = format!("{:?} {}", packet, packet);
+        }
+        if let Some(packet) = Erspan3Packet::from_gre(gre) {
+            let _ = format!("{:?} {}", packet, packet);
+        }
+        if let Some(packet) = NvgrePacket::from_gre(gre) {
+            let _ = format!("{:?} {}", packet, packet);
+        }
+    }
+    if let Some(ethernet) = EthernetPacket::new(data) {
+        if let Some(packet) = LlcPacket::from_ethernet(ethernet) {
+            assert!(packet.data().len() <= ethernet.payload().len());
+            let _ = format!("{:?} {}", packet, packet);
+        }
+    }
+
+    if let Some(trailer) = EspTrailer::parse(data) {
+        let len = trailer.payload.len() + trailer.padding.len() + 2;
+        assert_eq!(data.len(), len);
+    }
+    let indicators = Indicators::parse(data);
+    assert!(indicators.rest.len() <= data.len());
+    let _ = format!("{:?}", indicators);
+});
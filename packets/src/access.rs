@@ -0,0 +1,172 @@
+//! Bounds checked reads and writes of integers and byte arrays at byte offsets in a slice. This
+//! is what `read_offset!`, `write_offset!`, `read_field!` and `write_field!` expand to, so every
+//! getter and setter of the packet types goes through the few functions here.
+//!
+//! All of it is safe code, copying the bytes with `copy_from_slice` and converting them with
+//! `from_ne_bytes` and `to_ne_bytes`, which compiles to the same unaligned loads and stores as
+//! raw pointer accesses would. An access that does not fit in the slice panics. Debug builds
+//! check this with a `debug_assert!` first, to report the offset and size of the access.
+
+use std::mem;
+use std::ops::Range;
+
+/// A value of a fixed size that can be copied out of and into bytes in native byte order.
+pub trait Bytes: Copy {
+    /// The number of bytes in the value.
+    const SIZE: usize;
+
+    /// Reads the value from `bytes`, which must be exactly `SIZE` bytes long.
+    fn from_bytes(bytes: &[u8]) -> Self;
+
+    /// Writes the value to `bytes`, which must be exactly `SIZE` bytes long.
+    fn to_bytes(self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_bytes {
+    ($($type:ident),*) => {$(
+        impl Bytes for $type {
+            const SIZE: usize = mem::size_of::<$type>();
+
+            #[inline]
+            fn from_bytes(bytes: &[u8]) -> $type {
+                let mut array = [0; mem::size_of::<$type>()];
+                array.copy_from_slice(bytes);
+                $type::from_ne_bytes(array)
+            }
+
+            #[inline]
+            fn to_bytes(self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_ne_bytes());
+            }
+        }
+    )*};
+}
+
+impl_bytes!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128);
+
+impl<const N: usize> Bytes for [u8; N] {
+    const SIZE: usize = N;
+
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> [u8; N] {
+        let mut array = [0; N];
+        array.copy_from_slice(bytes);
+        array
+    }
+
+    #[inline]
+    fn to_bytes(self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self);
+    }
+}
+
+/// Returns the range of `size` bytes at `offset`, checking in debug builds that it lies within
+/// `len` bytes.
+#[inline]
+fn range(len: usize, offset: usize, size: usize) -> Range<usize> {
+    debug_assert!(
+        offset.checked_add(size).is_some_and(|end| end <= len),
+        "Access to {} bytes at offset {} of {} bytes",
+        size,
+        offset,
+        len
+    );
+    offset..offset + size
+}
+
+/// Reads a `T` in native byte order from `data` at byte `offset`.
+#[inline]
+pub fn read<T: Bytes>(data: &[u8], offset: usize) -> T {
+    T::from_bytes(&data[range(data.len(), offset, T::SIZE)])
+}
+
+/// Writes `value` in native byte order to `data` at byte `offset`.
+#[inline]
+pub fn write<T: Bytes>(data: &mut [u8], offset: usize, value: T) {
+    let range = range(data.len(), offset, T::SIZE);
+    value.to_bytes(&mut data[range]);
+}
+
+/// Reads a big endian 24 bit integer from `data` at byte `offset`.
+#[inline]
+pub fn read_u24(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes[1..].copy_from_slice(&data[range(data.len(), offset, 3)]);
+    u32::from_be_bytes(bytes)
+}
+
+/// Writes the lowest three bytes of `value` as a big endian integer to `data` at byte `offset`.
+#[inline]
+pub fn write_u24(data: &mut [u8], offset: usize, value: u32) {
+    let range = range(data.len(), offset, 3);
+    data[range].copy_from_slice(&value.to_be_bytes()[1..]);
+}
+
+/// Reads a big endian 48 bit integer from `data` at byte `offset`.
+#[inline]
+pub fn read_u48(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes[2..].copy_from_slice(&data[range(data.len(), offset, 6)]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Writes the lowest six bytes of `value` as a big endian integer to `data` at byte `offset`.
+#[inline]
+pub fn write_u48(data: &mut [u8], offset: usize, value: u64) {
+    let range = range(data.len(), offset, 6);
+    data[range].copy_from_slice(&value.to_be_bytes()[2..]);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        let mut data = [0; 6];
+        write(&mut data, 1, 0x1234u16.to_be());
+        write(&mut data, 3, -2i8);
+        assert_eq!([0, 0x12, 0x34, 0xfe, 0, 0], data);
+        assert_eq!(0x1234, u16::from_be(read(&data, 1)));
+        assert_eq!(-2, read::<i8>(&data, 3));
+        assert_eq!(0x0012_34fe, u32::from_be(read(&data, 0)));
+    }
+
+    #[test]
+    fn arrays() {
+        let mut data = [0; 6];
+        write(&mut data, 2, [1, 2, 3, 4]);
+        assert_eq!([0, 0, 1, 2, 3, 4], data);
+        assert_eq!([2, 3], read::<[u8; 2]>(&data, 3));
+        assert_eq!([0u8; 0], read::<[u8; 0]>(&data, 6));
+    }
+
+    #[test]
+    fn odd_sizes() {
+        let mut data = [0xaa; 8];
+        write_u24(&mut data, 0, 0xff12_3456);
+        write_u48(&mut data, 2, 0xffff_0102_0304_0506);
+        assert_eq!([0x12, 0x34, 1, 2, 3, 4, 5, 6], data);
+        assert_eq!(0x0012_3401, read_u24(&data, 0));
+        assert_eq!(0x0102_0304_0506, read_u48(&data, 2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn read_out_of_bounds() {
+        let _ = read::<u32>(&[0; 4], 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn write_out_of_bounds() {
+        write(&mut [0; 4], 3, 0u16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn offset_overflow() {
+        let _ = read::<u16>(&[0; 4], usize::MAX);
+    }
+}
@@ -0,0 +1,141 @@
+//! The IP Authentication Header, RFC 4302. Unlike ESP, AH leaves the payload in the clear and
+//! only adds an integrity check value covering the payload and the immutable parts of the IP
+//! header.
+
+use ip::Protocol;
+use std::fmt;
+
+packet!(
+    /// An Authentication Header. The header includes the integrity check value, its length
+    /// being given by the payload length field.
+    AhPacket,
+    MutAhPacket,
+    AhPacketBuf,
+    12,
+    |data| (usize::from(data[1]) + 2) * 4,
+    init |data| data[1] = 1
+);
+
+getters!(AhPacket
+    /// Returns the protocol of the payload following this header.
+    pub fn next_header(&self) -> Protocol {
+        Protocol(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the payload length field, the length of this header in 32 bit words minus two.
+    pub fn payload_len(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    pub fn reserved(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the security parameters index, identifying the security association.
+    pub fn spi(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns the integrity check value, including any padding after it. Shorter than given by
+    /// the payload length field if the packet is truncated.
+    pub fn icv(&self) -> &'a [u8] {
+        &self.0[12..self.header_len()]
+    }
+);
+
+setters!(MutAhPacket
+    pub fn set_next_header(&mut self, next_header: Protocol) {
+        write_offset!(self.0, 0, next_header.value(), u8);
+    }
+
+    pub fn set_payload_len(&mut self, payload_len: u8) {
+        write_offset!(self.0, 1, payload_len, u8);
+    }
+
+    pub fn set_reserved(&mut self, reserved: u16) {
+        write_offset!(self.0, 2, reserved, u16, to_be);
+    }
+
+    pub fn set_spi(&mut self, spi: u32) {
+        write_offset!(self.0, 4, spi, u32, to_be);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 8, sequence_number, u32, to_be);
+    }
+);
+
+impl<'a> MutAhPacket<'a> {
+    /// Sets the payload length field for an integrity check value of `icv_len` bytes, including
+    /// padding. Over IPv6 the whole header must be a multiple of eight bytes, so the ICV must
+    /// be padded accordingly. Returns false, leaving the packet unchanged, if `icv_len` is not
+    /// a multiple of four or is too long to encode.
+    pub fn set_icv_len(&mut self, icv_len: usize) -> bool {
+        if !icv_len.is_multiple_of(4) || icv_len / 4 >= 255 {
+            return false;
+        }
+        self.set_payload_len((icv_len / 4 + 1) as u8);
+        true
+    }
+
+    /// Returns the integrity check value for modification. Zero it before computing the ICV,
+    /// and write the result into it afterwards.
+    pub fn icv(&mut self) -> &mut [u8] {
+        let header_len = self.header_len();
+        &mut self.0[12..header_len]
+    }
+}
+
+impl<'a> fmt::Display for AhPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AH spi={:#010x} seq={} next={}",
+            self.spi(),
+            self.sequence_number(),
+            self.next_header()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! ah_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutAhPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ah_setget_test!(next_header, set_next_header, Protocol::TCP, 0, [6]);
+    ah_setget_test!(payload_len, set_payload_len, 0xab, 1, [0xab]);
+    ah_setget_test!(reserved, set_reserved, 0xabcd, 2, [0xab, 0xcd]);
+    ah_setget_test!(spi, set_spi, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 0x01]);
+    ah_setget_test!(sequence_number, set_sequence_number, 0xabcdef01, 8, [0xab, 0xcd, 0xef, 0x01]);
+
+    #[test]
+    fn icv() {
+        let mut data = [0; 12 + 12 + 4];
+        {
+            let mut testee = MutAhPacket::new(&mut data).unwrap();
+            assert!(!testee.set_icv_len(10));
+            assert!(!testee.set_icv_len(255 * 4));
+            assert!(testee.set_icv_len(12));
+            assert_eq!(24, testee.header_len());
+            testee.icv().copy_from_slice(&[0xab; 12]);
+        }
+        assert_eq!(4, data[1]);
+        let testee = AhPacket::new(&data).unwrap();
+        assert_eq!(&[0xab; 12], testee.icv());
+        assert_eq!(&[0; 4], testee.payload());
+        assert_eq!(&[0xab; 4], AhPacket::new(&data[..16]).unwrap().icv());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, AhPacketBuf => |packet| packet.icv().is_empty());
+}
@@ -1,43 +1,55 @@
 use ethernet::{EtherType, MacAddr};
+use std::fmt;
 use std::net::Ipv4Addr;
 
-packet!(ArpPacket, MutArpPacket, 28);
+packet!(
+    ArpPacket,
+    MutArpPacket,
+    ArpPacketBuf,
+    28,
+    |_data| 28,
+    init |data| {
+        write_offset!(data, 0, HardwareType::ETHERNET.value(), u16, to_be);
+        write_offset!(data, 2, EtherType::IPV4.value(), u16, to_be);
+        write_offset!(data, 4, [6, 4], [u8; 2]);
+    }
+);
 
 getters!(ArpPacket
     pub fn hardware_type(&self) -> HardwareType {
-        HardwareType(read_offset!(self.0.as_ref(), 0, u16, from_be))
+        HardwareType(read_offset!(self.0, 0, u16, from_be))
     }
 
     pub fn protocol_type(&self) -> EtherType {
-        EtherType(read_offset!(self.0.as_ref(), 2, u16, from_be))
+        EtherType(read_offset!(self.0, 2, u16, from_be))
     }
 
     pub fn hardware_length(&self) -> u8 {
-        read_offset!(self.0.as_ref(), 4, u8)
+        read_offset!(self.0, 4, u8)
     }
 
     pub fn protocol_length(&self) -> u8 {
-        read_offset!(self.0.as_ref(), 5, u8)
+        read_offset!(self.0, 5, u8)
     }
 
     pub fn operation(&self) -> Operation {
-        Operation(read_offset!(self.0.as_ref(), 6, u16, from_be))
+        Operation(read_offset!(self.0, 6, u16, from_be))
     }
 
     pub fn sender_mac_addr(&self) -> MacAddr {
-        MacAddr::from_slice(&self.0.as_ref()[8..14])
+        MacAddr::from_slice(&self.0[8..14])
     }
 
     pub fn sender_ip_addr(&self) -> Ipv4Addr {
-        Ipv4Addr::from(read_offset!(self.0.as_ref(), 14, [u8; 4]))
+        Ipv4Addr::from(read_offset!(self.0, 14, [u8; 4]))
     }
 
     pub fn target_mac_addr(&self) -> MacAddr {
-        MacAddr::from_slice(&self.0.as_ref()[18..24])
+        MacAddr::from_slice(&self.0[18..24])
     }
 
     pub fn target_ip_addr(&self) -> Ipv4Addr {
-        Ipv4Addr::from(read_offset!(self.0.as_ref(), 24, [u8; 4]))
+        Ipv4Addr::from(read_offset!(self.0, 24, [u8; 4]))
     }
 );
 
@@ -92,6 +104,10 @@ setters!(MutArpPacket
 );
 
 
+/// The 16 bit hardware type field of an ARP packet. Specifies the link layer protocol the
+/// addresses in the packet belong to. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/arp-parameters/arp-parameters.xhtml
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct HardwareType(pub u16);
 
@@ -103,6 +119,7 @@ impl HardwareType {
     }
 }
 
+/// The 16 bit operation (opcode) field of an ARP packet.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Operation(pub u16);
 
@@ -116,13 +133,71 @@ impl Operation {
 }
 
 
+impl<'a> fmt::Display for ArpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operation() {
+            Operation::REQUEST => write!(
+                f,
+                "ARP who-has {} tell {}",
+                self.target_ip_addr(),
+                self.sender_ip_addr()
+            ),
+            Operation::REPLY => write!(
+                f,
+                "ARP {} is-at {}",
+                self.sender_ip_addr(),
+                self.sender_mac_addr()
+            ),
+            operation => write!(
+                f,
+                "ARP op={} {} -> {}",
+                operation.value(),
+                self.sender_ip_addr(),
+                self.target_ip_addr()
+            ),
+        }
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn display() {
+        let mut data = [0; 28];
+        data[7] = 1;
+        data[8..14].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+        data[14..18].copy_from_slice(&[10, 0, 0, 1]);
+        data[24..28].copy_from_slice(&[10, 0, 0, 2]);
+        assert_eq!(
+            "ARP who-has 10.0.0.2 tell 10.0.0.1",
+            ArpPacket::new(&data).unwrap().to_string()
+        );
+        data[7] = 2;
+        assert_eq!(
+            "ARP 10.0.0.1 is-at 02:00:00:00:00:01",
+            ArpPacket::new(&data).unwrap().to_string()
+        );
+        data[7] = 3;
+        assert_eq!("ARP op=3 10.0.0.1 -> 10.0.0.2", ArpPacket::new(&data).unwrap().to_string());
+    }
+
     static MAC: [u8; 6] = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
     static IP: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
 
+    #[test]
+    fn min_length() {
+        assert_eq!(ArpPacket::MIN_LEN, 28);
+    }
+
+    #[test]
+    fn too_short_slice() {
+        assert!(ArpPacket::new(&[0; 27]).is_none());
+        assert!(MutArpPacket::new(&mut [0; 27]).is_none());
+    }
+
     macro_rules! arp_setget_test {
         ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
             setget_test!(MutArpPacket, $name, $set_name, $value, $offset, $expected);
@@ -197,4 +272,9 @@ mod tests {
         assert_eq!(6, testee.as_immutable().hardware_length());
         assert_eq!(4, testee.as_immutable().protocol_length());
     }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        ArpPacketBuf => |packet| (packet.hardware_length(), packet.protocol_length()) == (6, 4)
+    );
 }
@@ -0,0 +1,1102 @@
+//! The Border Gateway Protocol version 4, RFC 4271.
+//!
+//! BGP messages are sent over TCP, each starting with a 19 byte header giving the length and
+//! type of the message. `BgpPacket` gives access to that header, and its conversion methods to
+//! views of the type specific messages. IPv4 prefixes in UPDATE messages are encoded as a
+//! length in bits followed by as few bytes as needed, see `Prefixes` and `write_prefix`.
+
+use std::cmp;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// The TCP port BGP speakers listen on.
+pub const TCP_PORT: u16 = 179;
+
+/// The AS number used in place of four octet AS numbers towards speakers that don't support
+/// them, RFC 6793.
+pub const AS_TRANS: u16 = 23456;
+
+macro_rules! bgp_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            /// Returns the marker, all ones in every message.
+            pub fn marker(&self) -> [u8; 16] {
+                read_offset!(self.0, 0, [u8; 16])
+            }
+
+            /// Returns the length of the message in bytes, including the header.
+            pub fn length(&self) -> u16 {
+                read_offset!(self.0, 16, u16, from_be)
+            }
+
+            pub fn message_type(&self) -> MessageType {
+                MessageType(read_offset!(self.0, 18, u8))
+            }
+
+            /// Returns true if the marker is all ones, as required.
+            pub fn is_marker_valid(&self) -> bool {
+                self.0[..16].iter().all(|&byte| byte == 0xff)
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_marker(&mut self, marker: [u8; 16]) {
+                write_offset!(self.0, 0, marker, [u8; 16]);
+            }
+
+            pub fn set_length(&mut self, length: u16) {
+                write_offset!(self.0, 16, length, u16, to_be);
+            }
+
+            pub fn set_message_type(&mut self, message_type: MessageType) {
+                write_offset!(self.0, 18, message_type.value(), u8);
+            }
+        );
+    }
+}
+
+packet!(
+    /// The header common to all BGP messages. Use one of the conversion methods to get a view
+    /// of the message type specific fields.
+    BgpPacket,
+    MutBgpPacket,
+    BgpPacketBuf,
+    19,
+    |_data| 19,
+    len |data| message_len(data),
+    set_len |data, len| set_message_len(data, len),
+    init |data| init_message(data)
+);
+bgp_common!(BgpPacket, MutBgpPacket);
+
+impl<'a> BgpPacket<'a> {
+    /// Returns the message as an OPEN message, or `None` if it is of another type or too short.
+    pub fn open(&self) -> Option<OpenPacket<'a>> {
+        self.as_type(MessageType::OPEN).and_then(OpenPacket::new)
+    }
+
+    /// Returns the message as an UPDATE message, or `None` if it is of another type or too
+    /// short.
+    pub fn update(&self) -> Option<UpdatePacket<'a>> {
+        self.as_type(MessageType::UPDATE).and_then(UpdatePacket::new)
+    }
+
+    /// Returns the message as a NOTIFICATION message, or `None` if it is of another type or
+    /// too short.
+    pub fn notification(&self) -> Option<NotificationPacket<'a>> {
+        self.as_type(MessageType::NOTIFICATION).and_then(NotificationPacket::new)
+    }
+
+    fn as_type(&self, message_type: MessageType) -> Option<&'a [u8]> {
+        if self.message_type() == message_type {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the message in `data` from `offset`, limited by the length of the message given in
+/// its header.
+fn message_data(data: &[u8], offset: usize) -> &[u8] {
+    let end = cmp::min(usize::from(read_offset!(data, 16, u16, from_be)), data.len());
+    data.get(offset..end).unwrap_or(&[])
+}
+
+/// Returns the length of the message in `data` given by its header.
+fn message_len(data: &[u8]) -> usize {
+    usize::from(read_offset!(data, 16, u16, from_be))
+}
+
+/// Sets the length field of the message in `data` to `len`. Returns false if it does not fit.
+fn set_message_len(data: &mut [u8], len: usize) -> bool {
+    len <= usize::from(u16::MAX) && {
+        write_offset!(data, 16, len as u16, u16, to_be);
+        true
+    }
+}
+
+/// Sets the marker to all ones, as it must be, and the length field to the length of `data`.
+fn init_message(data: &mut [u8]) {
+    write_offset!(data, 0, [0xff; 16], [u8; 16]);
+    set_message_len(data, data.len());
+}
+
+
+packet!(
+    /// An OPEN message, the first message sent on a connection to negotiate the session.
+    OpenPacket,
+    MutOpenPacket,
+    OpenPacketBuf,
+    29,
+    |_data| 29,
+    len |data| message_len(data),
+    set_len |data, len| set_message_len(data, len),
+    init |data| {
+        init_message(data);
+        write_offset!(data, 18, MessageType::OPEN.value(), u8);
+        write_offset!(data, 19, 4u8, u8);
+        write_offset!(data, 28, 0u8, u8);
+    }
+);
+bgp_common!(OpenPacket, MutOpenPacket);
+
+getters!(OpenPacket
+    /// Returns the BGP version, 4 for BGP-4.
+    pub fn version(&self) -> u8 {
+        read_offset!(self.0, 19, u8)
+    }
+
+    /// Returns the AS number of the sender, or `AS_TRANS` if it does not fit in two octets.
+    pub fn my_as(&self) -> u16 {
+        read_offset!(self.0, 20, u16, from_be)
+    }
+
+    /// Returns the proposed hold time in seconds, zero or at least three.
+    pub fn hold_time(&self) -> u16 {
+        read_offset!(self.0, 22, u16, from_be)
+    }
+
+    pub fn bgp_identifier(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 24, [u8; 4]))
+    }
+
+    pub fn optional_parameters_length(&self) -> u8 {
+        read_offset!(self.0, 28, u8)
+    }
+
+    /// Returns an iterator over the optional parameters, limited by both the parameters length
+    /// and the message length.
+    pub fn optional_parameters(&self) -> OptionalParameters<'a> {
+        let data = message_data(self.0, 29);
+        let len = cmp::min(usize::from(self.optional_parameters_length()), data.len());
+        OptionalParameters { data: &data[..len] }
+    }
+
+    /// Returns the four octet AS number of the sender if it advertises the capability, or
+    /// `my_as` otherwise.
+    pub fn four_octet_as(&self) -> u32 {
+        self.optional_parameters()
+            .filter_map(|parameter| parameter.capabilities())
+            .flatten()
+            .filter_map(|capability| capability.four_octet_as())
+            .next()
+            .unwrap_or_else(|| u32::from(self.my_as()))
+    }
+);
+
+setters!(MutOpenPacket
+    pub fn set_version(&mut self, version: u8) {
+        write_offset!(self.0, 19, version, u8);
+    }
+
+    pub fn set_my_as(&mut self, my_as: u16) {
+        write_offset!(self.0, 20, my_as, u16, to_be);
+    }
+
+    pub fn set_hold_time(&mut self, hold_time: u16) {
+        write_offset!(self.0, 22, hold_time, u16, to_be);
+    }
+
+    pub fn set_bgp_identifier(&mut self, bgp_identifier: Ipv4Addr) {
+        write_offset!(self.0, 24, bgp_identifier.octets(), [u8; 4]);
+    }
+
+    pub fn set_optional_parameters_length(&mut self, optional_parameters_length: u8) {
+        write_offset!(self.0, 28, optional_parameters_length, u8);
+    }
+);
+
+
+packet!(
+    /// An UPDATE message, withdrawing routes and advertising routes sharing the same path
+    /// attributes. The three variable length parts are available through `withdrawn_routes`,
+    /// `path_attributes` and `nlri`.
+    UpdatePacket,
+    MutUpdatePacket,
+    UpdatePacketBuf,
+    23,
+    |_data| 23,
+    len |data| message_len(data),
+    set_len |data, len| set_message_len(data, len),
+    init |data| {
+        init_message(data);
+        write_offset!(data, 18, MessageType::UPDATE.value(), u8);
+        write_offset!(data, 19, 0u16, u16);
+        write_offset!(data, 21, 0u16, u16);
+    }
+);
+bgp_common!(UpdatePacket, MutUpdatePacket);
+
+getters!(UpdatePacket
+    pub fn withdrawn_routes_length(&self) -> u16 {
+        read_offset!(self.0, 19, u16, from_be)
+    }
+
+    /// Returns the total length of the path attributes, or `None` if the field lies after the
+    /// end of the message.
+    pub fn total_path_attribute_length(&self) -> Option<u16> {
+        let offset = 21 + usize::from(self.withdrawn_routes_length());
+        let data = message_data(self.0, offset);
+        if data.len() < 2 {
+            return None;
+        }
+        Some(read_offset!(data, 0, u16, from_be))
+    }
+
+    /// Returns an iterator over the prefixes of the withdrawn routes.
+    pub fn withdrawn_routes(&self) -> Prefixes<'a> {
+        Prefixes::new(self.sections().0)
+    }
+
+    /// Returns an iterator over the path attributes.
+    pub fn path_attributes(&self) -> PathAttributes<'a> {
+        PathAttributes::new(self.sections().1)
+    }
+
+    /// Returns an iterator over the prefixes of the advertised routes, the network layer
+    /// reachability information filling the rest of the message.
+    pub fn nlri(&self) -> Prefixes<'a> {
+        Prefixes::new(self.sections().2)
+    }
+);
+
+impl<'a> UpdatePacket<'a> {
+    /// Splits the body into the withdrawn routes, path attributes and NLRI, each limited to
+    /// what is present in the message.
+    fn sections(&self) -> (&'a [u8], &'a [u8], &'a [u8]) {
+        let data = message_data(self.0, 21);
+        let withdrawn_len = cmp::min(usize::from(self.withdrawn_routes_length()), data.len());
+        let (withdrawn, rest) = data.split_at(withdrawn_len);
+        if rest.len() < 2 {
+            return (withdrawn, &[], &[]);
+        }
+        let attributes_len = usize::from(read_offset!(rest, 0, u16, from_be));
+        let rest = &rest[2..];
+        let (attributes, nlri) = rest.split_at(cmp::min(attributes_len, rest.len()));
+        (withdrawn, attributes, nlri)
+    }
+}
+
+setters!(MutUpdatePacket
+    pub fn set_withdrawn_routes_length(&mut self, withdrawn_routes_length: u16) {
+        write_offset!(self.0, 19, withdrawn_routes_length, u16, to_be);
+    }
+);
+
+impl<'a> MutUpdatePacket<'a> {
+    /// Sets the total path attribute length, following the withdrawn routes. Returns false,
+    /// leaving the packet unchanged, if the withdrawn routes length places the field outside
+    /// the backing slice.
+    pub fn set_total_path_attribute_length(&mut self, total_path_attribute_length: u16) -> bool {
+        let offset = 21 + usize::from(self.as_immutable().withdrawn_routes_length());
+        offset + 2 <= self.0.len() && {
+            write_offset!(self.0, offset, total_path_attribute_length, u16, to_be);
+            true
+        }
+    }
+}
+
+
+packet!(
+    /// A NOTIFICATION message, sent when an error is detected right before closing the
+    /// connection. The payload holds the error data.
+    NotificationPacket,
+    MutNotificationPacket,
+    NotificationPacketBuf,
+    21,
+    |_data| 21,
+    len |data| message_len(data),
+    set_len |data, len| set_message_len(data, len),
+    init |data| {
+        init_message(data);
+        write_offset!(data, 18, MessageType::NOTIFICATION.value(), u8);
+    }
+);
+bgp_common!(NotificationPacket, MutNotificationPacket);
+
+getters!(NotificationPacket
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode(read_offset!(self.0, 19, u8))
+    }
+
+    /// Returns the error subcode, whose meaning depends on the error code.
+    pub fn error_subcode(&self) -> u8 {
+        read_offset!(self.0, 20, u8)
+    }
+);
+
+setters!(MutNotificationPacket
+    pub fn set_error_code(&mut self, error_code: ErrorCode) {
+        write_offset!(self.0, 19, error_code.value(), u8);
+    }
+
+    pub fn set_error_subcode(&mut self, error_subcode: u8) {
+        write_offset!(self.0, 20, error_subcode, u8);
+    }
+);
+
+
+/// Represents the type of a BGP message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    pub const OPEN: MessageType = MessageType(1);
+    pub const UPDATE: MessageType = MessageType(2);
+    pub const NOTIFICATION: MessageType = MessageType(3);
+    pub const KEEPALIVE: MessageType = MessageType(4);
+    pub const ROUTE_REFRESH: MessageType = MessageType(5);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the error code of a NOTIFICATION message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ErrorCode(pub u8);
+
+impl ErrorCode {
+    pub const MESSAGE_HEADER_ERROR: ErrorCode = ErrorCode(1);
+    pub const OPEN_MESSAGE_ERROR: ErrorCode = ErrorCode(2);
+    pub const UPDATE_MESSAGE_ERROR: ErrorCode = ErrorCode(3);
+    pub const HOLD_TIMER_EXPIRED: ErrorCode = ErrorCode(4);
+    pub const FINITE_STATE_MACHINE_ERROR: ErrorCode = ErrorCode(5);
+    pub const CEASE: ErrorCode = ErrorCode(6);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the type of an optional parameter in an OPEN message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ParameterType(pub u8);
+
+impl ParameterType {
+    pub const CAPABILITIES: ParameterType = ParameterType(2);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the code of a capability advertised in an OPEN message, RFC 5492.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CapabilityCode(pub u8);
+
+impl CapabilityCode {
+    pub const MULTIPROTOCOL: CapabilityCode = CapabilityCode(1);
+    pub const ROUTE_REFRESH: CapabilityCode = CapabilityCode(2);
+    pub const GRACEFUL_RESTART: CapabilityCode = CapabilityCode(64);
+    pub const FOUR_OCTET_AS: CapabilityCode = CapabilityCode(65);
+    pub const ADD_PATH: CapabilityCode = CapabilityCode(69);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the type code of a path attribute.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AttributeType(pub u8);
+
+impl AttributeType {
+    pub const ORIGIN: AttributeType = AttributeType(1);
+    pub const AS_PATH: AttributeType = AttributeType(2);
+    pub const NEXT_HOP: AttributeType = AttributeType(3);
+    pub const MULTI_EXIT_DISC: AttributeType = AttributeType(4);
+    pub const LOCAL_PREF: AttributeType = AttributeType(5);
+    pub const ATOMIC_AGGREGATE: AttributeType = AttributeType(6);
+    pub const AGGREGATOR: AttributeType = AttributeType(7);
+    pub const COMMUNITIES: AttributeType = AttributeType(8);
+    pub const MP_REACH_NLRI: AttributeType = AttributeType(14);
+    pub const MP_UNREACH_NLRI: AttributeType = AttributeType(15);
+    pub const AS4_PATH: AttributeType = AttributeType(17);
+    pub const LARGE_COMMUNITIES: AttributeType = AttributeType(32);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the value of the ORIGIN path attribute.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Origin(pub u8);
+
+impl Origin {
+    pub const IGP: Origin = Origin(0);
+    pub const EGP: Origin = Origin(1);
+    pub const INCOMPLETE: Origin = Origin(2);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the type of an AS_PATH segment.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SegmentType(pub u8);
+
+impl SegmentType {
+    pub const AS_SET: SegmentType = SegmentType(1);
+    pub const AS_SEQUENCE: SegmentType = SegmentType(2);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags of a path attribute.
+    pub struct AttributeFlags: u8 {
+        /// A bitmask with a one in the "Optional" position.
+        const OPTIONAL = 0x80;
+        /// A bitmask with a one in the "Transitive" position.
+        const TRANSITIVE = 0x40;
+        /// A bitmask with a one in the "Partial" position.
+        const PARTIAL = 0x20;
+        /// A bitmask with a one in the "Extended Length" position, set when the attribute
+        /// length takes two bytes.
+        const EXTENDED_LENGTH = 0x10;
+    }
+}
+
+
+/// An optional parameter in an OPEN message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OptionalParameter<'a> {
+    pub parameter_type: ParameterType,
+    pub data: &'a [u8],
+}
+
+impl<'a> OptionalParameter<'a> {
+    /// Returns an iterator over the capabilities, or `None` if this is not a capabilities
+    /// parameter.
+    pub fn capabilities(&self) -> Option<Capabilities<'a>> {
+        if self.parameter_type == ParameterType::CAPABILITIES {
+            Some(Capabilities { data: self.data })
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the optional parameters of an OPEN message. Iteration stops at the first
+/// parameter that does not fit in the data.
+#[derive(Clone)]
+pub struct OptionalParameters<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(OptionalParameters<'a>);
+
+impl<'a> Iterator for OptionalParameters<'a> {
+    type Item = OptionalParameter<'a>;
+
+    fn next(&mut self) -> Option<OptionalParameter<'a>> {
+        let (parameter_type, data) = split_tlv(&mut self.data)?;
+        Some(OptionalParameter {
+            parameter_type: ParameterType(parameter_type),
+            data,
+        })
+    }
+}
+
+/// A capability advertised in an OPEN message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Capability<'a> {
+    pub code: CapabilityCode,
+    pub data: &'a [u8],
+}
+
+impl<'a> Capability<'a> {
+    /// Returns the AS number of a four octet AS capability, or `None` if this is another
+    /// capability or it is malformed.
+    pub fn four_octet_as(&self) -> Option<u32> {
+        if self.code == CapabilityCode::FOUR_OCTET_AS && self.data.len() == 4 {
+            Some(read_offset!(self.data, 0, u32, from_be))
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the capabilities in a capabilities parameter. Iteration stops at the first
+/// capability that does not fit in the data.
+#[derive(Clone)]
+pub struct Capabilities<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Capabilities<'a>);
+
+impl<'a> Iterator for Capabilities<'a> {
+    type Item = Capability<'a>;
+
+    fn next(&mut self) -> Option<Capability<'a>> {
+        let (code, data) = split_tlv(&mut self.data)?;
+        Some(Capability { code: CapabilityCode(code), data })
+    }
+}
+
+/// Splits a type, one byte length and value off the beginning of `data`. Empties `data` and
+/// returns `None` if it does not fit.
+fn split_tlv<'a>(data: &mut &'a [u8]) -> Option<(u8, &'a [u8])> {
+    let len = match data.get(1) {
+        Some(&len) if 2 + usize::from(len) <= data.len() => 2 + usize::from(len),
+        _ => {
+            *data = &[];
+            return None;
+        }
+    };
+    let (tlv, rest) = data.split_at(len);
+    *data = rest;
+    Some((tlv[0], &tlv[2..]))
+}
+
+
+/// An IPv4 address prefix, as found in withdrawn routes and NLRI.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Ipv4Prefix {
+    pub address: Ipv4Addr,
+    /// The length of the prefix in bits, at most 32.
+    pub length: u8,
+}
+
+/// Returns the number of bytes taken by an encoded prefix of `length` bits, including the
+/// length byte.
+pub fn prefix_len(length: u8) -> usize {
+    1 + usize::from(length).div_ceil(8)
+}
+
+/// Encodes `prefix` into the beginning of `buffer`. Bits of the address past the prefix length
+/// are cleared. Returns the number of bytes written, or `None` if the prefix does not fit in
+/// `buffer` or is longer than 32 bits.
+pub fn write_prefix(buffer: &mut [u8], prefix: Ipv4Prefix) -> Option<usize> {
+    if prefix.length > 32 {
+        return None;
+    }
+    let len = prefix_len(prefix.length);
+    let encoded = buffer.get_mut(..len)?;
+    let mask = u32::MAX.checked_shl(32 - u32::from(prefix.length)).unwrap_or(0);
+    let address = (u32::from(prefix.address) & mask).to_be_bytes();
+    encoded[0] = prefix.length;
+    encoded[1..].copy_from_slice(&address[..len - 1]);
+    Some(len)
+}
+
+/// Iterator over a list of encoded IPv4 prefixes. Iteration stops at the first prefix that
+/// does not fit in the data or is longer than 32 bits.
+#[derive(Clone)]
+pub struct Prefixes<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Prefixes<'a>);
+
+impl<'a> Prefixes<'a> {
+    /// Creates an iterator over the prefixes encoded in `data`.
+    pub fn new(data: &'a [u8]) -> Prefixes<'a> {
+        Prefixes { data }
+    }
+}
+
+impl<'a> Iterator for Prefixes<'a> {
+    type Item = Ipv4Prefix;
+
+    fn next(&mut self) -> Option<Ipv4Prefix> {
+        let length = *self.data.first()?;
+        let len = prefix_len(length);
+        if length > 32 || len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let mut address = [0; 4];
+        address[..len - 1].copy_from_slice(&self.data[1..len]);
+        self.data = &self.data[len..];
+        Some(Ipv4Prefix { address: Ipv4Addr::from(address), length })
+    }
+}
+
+
+/// A path attribute in an UPDATE message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PathAttribute<'a> {
+    pub flags: AttributeFlags,
+    pub attribute_type: AttributeType,
+    /// The attribute value following the flags, type code and length.
+    pub data: &'a [u8],
+}
+
+impl<'a> PathAttribute<'a> {
+    /// Returns the value of an ORIGIN attribute, or `None` if this is another attribute or it
+    /// is malformed.
+    pub fn origin(&self) -> Option<Origin> {
+        match (self.attribute_type, self.data) {
+            (AttributeType::ORIGIN, &[origin]) => Some(Origin(origin)),
+            _ => None,
+        }
+    }
+
+    /// Returns the address of a NEXT_HOP attribute, or `None` if this is another attribute or
+    /// it is malformed.
+    pub fn next_hop(&self) -> Option<Ipv4Addr> {
+        self.as_u32(AttributeType::NEXT_HOP).map(Ipv4Addr::from)
+    }
+
+    /// Returns the value of a MULTI_EXIT_DISC attribute, or `None` if this is another
+    /// attribute or it is malformed.
+    pub fn multi_exit_disc(&self) -> Option<u32> {
+        self.as_u32(AttributeType::MULTI_EXIT_DISC)
+    }
+
+    /// Returns the value of a LOCAL_PREF attribute, or `None` if this is another attribute or
+    /// it is malformed.
+    pub fn local_pref(&self) -> Option<u32> {
+        self.as_u32(AttributeType::LOCAL_PREF)
+    }
+
+    /// Returns an iterator over the segments of an AS_PATH or AS4_PATH attribute, or `None` if
+    /// this is another attribute. AS4_PATH always uses four octet AS numbers, AS_PATH only
+    /// when both speakers advertised the four octet AS capability, as given by `four_octet`.
+    pub fn as_path(&self, four_octet: bool) -> Option<AsPathSegments<'a>> {
+        let as_size = match self.attribute_type {
+            AttributeType::AS_PATH if four_octet => 4,
+            AttributeType::AS_PATH => 2,
+            AttributeType::AS4_PATH => 4,
+            _ => return None,
+        };
+        Some(AsPathSegments { data: self.data, as_size })
+    }
+
+    fn as_u32(&self, attribute_type: AttributeType) -> Option<u32> {
+        if self.attribute_type == attribute_type && self.data.len() == 4 {
+            Some(read_offset!(self.data, 0, u32, from_be))
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the path attributes of an UPDATE message. Iteration stops at the first
+/// attribute that does not fit in the data.
+#[derive(Clone)]
+pub struct PathAttributes<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(PathAttributes<'a>);
+
+impl<'a> PathAttributes<'a> {
+    /// Creates an iterator over the path attributes encoded in `data`.
+    pub fn new(data: &'a [u8]) -> PathAttributes<'a> {
+        PathAttributes { data }
+    }
+}
+
+impl<'a> Iterator for PathAttributes<'a> {
+    type Item = PathAttribute<'a>;
+
+    fn next(&mut self) -> Option<PathAttribute<'a>> {
+        if self.data.len() < 3 {
+            self.data = &[];
+            return None;
+        }
+        let flags = AttributeFlags::from_bits_truncate(self.data[0]);
+        let (header_len, len) = if flags.contains(AttributeFlags::EXTENDED_LENGTH) {
+            (4, self.data.get(2..4).map(|len| read_offset!(len, 0, u16, from_be)))
+        } else {
+            (3, Some(u16::from(self.data[2])))
+        };
+        let len = match len {
+            Some(len) if header_len + usize::from(len) <= self.data.len() => {
+                header_len + usize::from(len)
+            }
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (attribute, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(PathAttribute {
+            flags,
+            attribute_type: AttributeType(attribute[1]),
+            data: &attribute[header_len..],
+        })
+    }
+}
+
+/// Encodes a path attribute into the beginning of `buffer`. The extended length flag is set
+/// when `data` is longer than 255 bytes, and cleared otherwise. Returns the number of bytes
+/// written, or `None` if the attribute does not fit in `buffer` or is too long to encode.
+pub fn write_path_attribute(
+    buffer: &mut [u8],
+    flags: AttributeFlags,
+    attribute_type: AttributeType,
+    data: &[u8],
+) -> Option<usize> {
+    let extended = data.len() > usize::from(u8::MAX);
+    if data.len() > usize::from(u16::MAX) {
+        return None;
+    }
+    let header_len = if extended { 4 } else { 3 };
+    let len = header_len + data.len();
+    let attribute = buffer.get_mut(..len)?;
+    let mut flags = flags - AttributeFlags::EXTENDED_LENGTH;
+    if extended {
+        flags |= AttributeFlags::EXTENDED_LENGTH;
+        write_offset!(attribute, 2, data.len() as u16, u16, to_be);
+    } else {
+        attribute[2] = data.len() as u8;
+    }
+    attribute[0] = flags.bits();
+    attribute[1] = attribute_type.value();
+    attribute[header_len..].copy_from_slice(data);
+    Some(len)
+}
+
+/// A segment of an AS path.
+#[derive(Debug, Clone)]
+pub struct AsPathSegment<'a> {
+    pub segment_type: SegmentType,
+    /// The AS numbers in the segment.
+    pub asns: Asns<'a>,
+}
+
+/// Iterator over the segments of an AS path. Iteration stops at the first segment that does
+/// not fit in the data.
+#[derive(Clone)]
+pub struct AsPathSegments<'a> {
+    data: &'a [u8],
+    as_size: usize,
+}
+
+debug_items!(AsPathSegments<'a>);
+
+impl<'a> Iterator for AsPathSegments<'a> {
+    type Item = AsPathSegment<'a>;
+
+    fn next(&mut self) -> Option<AsPathSegment<'a>> {
+        let len = match self.data.get(1) {
+            Some(&count) if 2 + usize::from(count) * self.as_size <= self.data.len() => {
+                2 + usize::from(count) * self.as_size
+            }
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (segment, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(AsPathSegment {
+            segment_type: SegmentType(segment[0]),
+            asns: Asns { data: &segment[2..], as_size: self.as_size },
+        })
+    }
+}
+
+/// Iterator over the AS numbers in an AS path segment.
+#[derive(Clone)]
+pub struct Asns<'a> {
+    data: &'a [u8],
+    as_size: usize,
+}
+
+debug_items!(Asns<'a>);
+
+impl<'a> Iterator for Asns<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.data.len() < self.as_size {
+            return None;
+        }
+        let (asn, rest) = self.data.split_at(self.as_size);
+        self.data = rest;
+        Some(match self.as_size {
+            4 => read_offset!(asn, 0, u32, from_be),
+            _ => u32::from(read_offset!(asn, 0, u16, from_be)),
+        })
+    }
+}
+
+impl<'a> fmt::Display for BgpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BGP type={} len={}",
+            self.message_type().value(),
+            self.length()
+        )
+    }
+}
+
+impl<'a> fmt::Display for OpenPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BGP OPEN version={} as={} hold_time={} id={}",
+            self.version(),
+            self.four_octet_as(),
+            self.hold_time(),
+            self.bgp_identifier()
+        )
+    }
+}
+
+impl<'a> fmt::Display for UpdatePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BGP UPDATE withdrawn={} nlri={}",
+            self.withdrawn_routes().count(),
+            self.nlri().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for NotificationPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BGP NOTIFICATION code={} subcode={}",
+            self.error_code().value(),
+            self.error_subcode()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! bgp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutBgpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    bgp_setget_test!(marker, set_marker, [0xff; 16], 0, [0xff; 16]);
+    bgp_setget_test!(length, set_length, 0xabcd, 16, [0xab, 0xcd]);
+    bgp_setget_test!(message_type, set_message_type, MessageType::KEEPALIVE, 18, [4]);
+
+    macro_rules! open_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutOpenPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    open_setget_test!(version, set_version, 4, 19, [4]);
+    open_setget_test!(my_as, set_my_as, 0xabcd, 20, [0xab, 0xcd]);
+    open_setget_test!(hold_time, set_hold_time, 0xabcd, 22, [0xab, 0xcd]);
+    open_setget_test!(
+        bgp_identifier,
+        set_bgp_identifier,
+        Ipv4Addr::new(1, 2, 3, 4),
+        24,
+        [1, 2, 3, 4]
+    );
+    open_setget_test!(optional_parameters_length, set_optional_parameters_length, 0xab, 28, [0xab]);
+
+    macro_rules! notification_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutNotificationPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    notification_setget_test!(error_code, set_error_code, ErrorCode::CEASE, 19, [6]);
+    notification_setget_test!(error_subcode, set_error_subcode, 0xab, 20, [0xab]);
+
+    #[test]
+    fn marker_validity() {
+        let mut data = [0xff; 19];
+        assert!(BgpPacket::new(&data).unwrap().is_marker_valid());
+        data[15] = 0;
+        assert!(!BgpPacket::new(&data).unwrap().is_marker_valid());
+    }
+
+    #[test]
+    fn open() {
+        let mut data = [0; 29 + 12];
+        {
+            let mut testee = MutOpenPacket::new(&mut data).unwrap();
+            testee.set_length(41);
+            testee.set_message_type(MessageType::OPEN);
+            testee.set_version(4);
+            testee.set_my_as(AS_TRANS);
+            testee.set_optional_parameters_length(12);
+        }
+        data[29..].copy_from_slice(&[2, 8, 2, 0, 65, 4, 0, 1, 0, 0, 1, 0]);
+        let testee = BgpPacket::new(&data).unwrap().open().unwrap();
+        let parameters = testee.optional_parameters().collect::<Vec<_>>();
+        assert_eq!(2, parameters.len());
+        let codes = parameters[0].capabilities().unwrap().map(|c| c.code).collect::<Vec<_>>();
+        assert_eq!(vec![CapabilityCode::ROUTE_REFRESH, CapabilityCode::FOUR_OCTET_AS], codes);
+        assert!(parameters[1].capabilities().is_none());
+        assert_eq!(0x10000, testee.four_octet_as());
+        assert!(BgpPacket::new(&data).unwrap().update().is_none());
+
+        // Parameters are limited by the message length
+        data[17] = 35;
+        let testee = OpenPacket::new(&data).unwrap();
+        assert_eq!(0, testee.optional_parameters().count());
+        assert_eq!(u32::from(AS_TRANS), testee.four_octet_as());
+    }
+
+    #[test]
+    fn update() {
+        let mut data = [0; 64];
+        let mut offset = 21;
+        for &length in &[24, 0] {
+            let prefix = Ipv4Prefix { address: Ipv4Addr::new(10, 1, 2, 3), length };
+            offset += write_prefix(&mut data[offset..], prefix).unwrap();
+        }
+        let withdrawn_len = offset - 21;
+        offset += 2;
+        let attributes_start = offset;
+        offset += write_path_attribute(
+            &mut data[offset..],
+            AttributeFlags::TRANSITIVE,
+            AttributeType::ORIGIN,
+            &[Origin::EGP.value()],
+        ).unwrap();
+        offset += write_path_attribute(
+            &mut data[offset..],
+            AttributeFlags::TRANSITIVE,
+            AttributeType::AS_PATH,
+            &[2, 2, 0, 1, 0, 2],
+        ).unwrap();
+        offset += write_path_attribute(
+            &mut data[offset..],
+            AttributeFlags::TRANSITIVE,
+            AttributeType::NEXT_HOP,
+            &[192, 0, 2, 1],
+        ).unwrap();
+        let attributes_len = offset - attributes_start;
+        let prefix = Ipv4Prefix { address: Ipv4Addr::new(198, 51, 100, 0), length: 22 };
+        offset += write_prefix(&mut data[offset..], prefix).unwrap();
+        {
+            let mut testee = MutUpdatePacket::new(&mut data).unwrap();
+            testee.set_marker([0xff; 16]);
+            testee.set_length(offset as u16);
+            testee.set_message_type(MessageType::UPDATE);
+            testee.set_withdrawn_routes_length(withdrawn_len as u16);
+            assert!(testee.set_total_path_attribute_length(attributes_len as u16));
+        }
+
+        let testee = BgpPacket::new(&data).unwrap().update().unwrap();
+        assert_eq!(Some(attributes_len as u16), testee.total_path_attribute_length());
+        assert_eq!(
+            vec![
+                Ipv4Prefix { address: Ipv4Addr::new(10, 1, 2, 0), length: 24 },
+                Ipv4Prefix { address: Ipv4Addr::new(0, 0, 0, 0), length: 0 },
+            ],
+            testee.withdrawn_routes().collect::<Vec<_>>()
+        );
+        let attributes = testee.path_attributes().collect::<Vec<_>>();
+        assert_eq!(3, attributes.len());
+        assert_eq!(Some(Origin::EGP), attributes[0].origin());
+        assert_eq!(None, attributes[0].next_hop());
+        let segments = attributes[1].as_path(false).unwrap().collect::<Vec<_>>();
+        assert_eq!(1, segments.len());
+        assert_eq!(SegmentType::AS_SEQUENCE, segments[0].segment_type);
+        assert_eq!(vec![1, 2], segments[0].asns.clone().collect::<Vec<_>>());
+        // Two four octet AS numbers do not fit in the segment
+        assert_eq!(0, attributes[1].as_path(true).unwrap().count());
+        assert_eq!(Some(Ipv4Addr::new(192, 0, 2, 1)), attributes[2].next_hop());
+        assert_eq!(vec![prefix], testee.nlri().collect::<Vec<_>>());
+
+        // Everything past the message length is ignored
+        let testee = UpdatePacket::new(&data[..offset - 1]).unwrap();
+        assert_eq!(0, testee.nlri().count());
+        assert_eq!(3, testee.path_attributes().count());
+    }
+
+    #[test]
+    fn total_path_attribute_length_outside_packet() {
+        let mut data = [0; 24];
+        let mut testee = MutUpdatePacket::new(&mut data).unwrap();
+        testee.set_withdrawn_routes_length(1);
+        assert!(testee.set_total_path_attribute_length(7));
+        testee.set_withdrawn_routes_length(2);
+        assert!(!testee.set_total_path_attribute_length(8));
+        testee.set_withdrawn_routes_length(u16::MAX);
+        assert!(!testee.set_total_path_attribute_length(9));
+        assert_eq!([0, 0xff, 0xff, 0, 0, 7], data[18..]);
+    }
+
+    #[test]
+    fn extended_length_attribute() {
+        let mut buffer = [0; 4 + 300];
+        let len = write_path_attribute(
+            &mut buffer,
+            AttributeFlags::OPTIONAL,
+            AttributeType::COMMUNITIES,
+            &[7; 300],
+        ).unwrap();
+        assert_eq!(304, len);
+        assert_eq!([0x90, 8, 0x01, 0x2c], buffer[..4]);
+        let attribute = PathAttributes::new(&buffer).next().unwrap();
+        assert_eq!(AttributeFlags::OPTIONAL | AttributeFlags::EXTENDED_LENGTH, attribute.flags);
+        assert_eq!(300, attribute.data.len());
+        assert_eq!(None, PathAttributes::new(&buffer[..303]).next());
+    }
+
+    #[test]
+    fn prefixes() {
+        let data = [32, 1, 2, 3, 4, 9, 0x80, 0x80, 33, 0, 0, 0, 0, 0];
+        let prefixes = Prefixes::new(&data).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Ipv4Prefix { address: Ipv4Addr::new(1, 2, 3, 4), length: 32 },
+                Ipv4Prefix { address: Ipv4Addr::new(128, 128, 0, 0), length: 9 },
+            ],
+            prefixes
+        );
+        assert_eq!(1, Prefixes::new(&data[..7]).count());
+        assert_eq!(5, prefix_len(32));
+        assert_eq!(1, prefix_len(0));
+        let prefix = Ipv4Prefix { address: Ipv4Addr::new(1, 2, 3, 4), length: 33 };
+        assert_eq!(None, write_prefix(&mut [0; 8], prefix));
+    }
+
+    #[test]
+    fn notification() {
+        let mut data = [0xff; 23];
+        data[16..].copy_from_slice(&[0, 23, 3, 6, 2, 0xab, 0xcd]);
+        let testee = BgpPacket::new(&data).unwrap().notification().unwrap();
+        assert_eq!(ErrorCode::CEASE, testee.error_code());
+        assert_eq!(2, testee.error_subcode());
+        assert_eq!(&[0xab, 0xcd], testee.payload());
+        assert_eq!("BGP NOTIFICATION code=6 subcode=2", testee.to_string());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 19 + 4];
+        let mut testee = MutNotificationPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[0xab]));
+        assert_eq!(22, testee.as_immutable().length());
+        assert_eq!(&[0xab], testee.as_immutable().payload());
+        assert_eq!(22, BgpPacketBuf::new_with_payload_len(3).packet().length());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        BgpPacketBuf => |packet| packet.marker() == [0xff; 16],
+        OpenPacketBuf => |packet| packet.version() == 4,
+        UpdatePacketBuf => |packet| packet.total_path_attribute_length() == Some(0),
+        NotificationPacketBuf => |packet| packet.message_type() == MessageType::NOTIFICATION,
+    );
+}
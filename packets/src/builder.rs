@@ -0,0 +1,78 @@
+//! Building complete packets from scratch. Each protocol with a builder, such as
+//! `ipv4::Ipv4Builder`, takes the interesting header fields and fills in the rest itself: the
+//! version, the header length, lengths covering the payload and checksums. Builders nest, so the
+//! payload of one builder can be the builder of the next layer, or just bytes.
+//!
+//! ```rust
+//! extern crate rips_packets;
+//!
+//! use rips_packets::ethernet::{EthernetBuilder, EthernetPacket, MacAddr};
+//! use rips_packets::ipv4::Ipv4Builder;
+//! use rips_packets::udp::UdpBuilder;
+//! use std::net::Ipv4Addr;
+//!
+//! fn main() {
+//!     let udp = UdpBuilder::new().source_port(1234).destination_port(53).payload(b"hi");
+//!     let ipv4 = Ipv4Builder::new()
+//!         .source(Ipv4Addr::new(10, 0, 0, 1))
+//!         .destination(Ipv4Addr::new(10, 0, 0, 2))
+//!         .payload(udp);
+//!     let frame = EthernetBuilder::new()
+//!         .source(MacAddr([0x02, 0, 0, 0, 0, 0x01]))
+//!         .destination(MacAddr::BROADCAST)
+//!         .payload(ipv4)
+//!         .build()
+//!         .unwrap();
+//!     assert_eq!(14 + 20 + 8 + 2, frame.len());
+//!     assert_eq!(b"hi", &EthernetPacket::new(&frame).unwrap().payload()[28..]);
+//! }
+//! ```
+
+use alloc::vec::Vec;
+use ethernet::EtherType;
+use ip::Protocol;
+use std::net::IpAddr;
+
+/// Something that can be written as the payload of a packet by a builder. Implemented for the
+/// builders themselves and for everything that can be viewed as bytes.
+pub trait Payload {
+    /// Returns the number of bytes `write` produces.
+    fn encoded_len(&self) -> usize;
+
+    /// Writes the payload to the start of `buffer`. `addresses` holds the source and
+    /// destination of the enclosing IP packet, if there is one, for protocols that include them
+    /// in their checksum. Returns the number of bytes written, or `None` if `buffer` is too
+    /// short or the payload can't be encoded.
+    fn write(&self, buffer: &mut [u8], addresses: Option<(IpAddr, IpAddr)>) -> Option<usize>;
+
+    /// Returns the EtherType identifying this payload, used when the enclosing Ethernet frame
+    /// does not specify one.
+    fn ether_type(&self) -> Option<EtherType> {
+        None
+    }
+
+    /// Returns the IP protocol identifying this payload, used when the enclosing IP packet does
+    /// not specify one.
+    fn protocol(&self) -> Option<Protocol> {
+        None
+    }
+}
+
+impl<T: AsRef<[u8]>> Payload for T {
+    fn encoded_len(&self) -> usize {
+        self.as_ref().len()
+    }
+
+    fn write(&self, buffer: &mut [u8], _addresses: Option<(IpAddr, IpAddr)>) -> Option<usize> {
+        let data = self.as_ref();
+        buffer.get_mut(..data.len())?.copy_from_slice(data);
+        Some(data.len())
+    }
+}
+
+/// Allocates a buffer of exactly the right size and writes `payload` to it.
+pub(crate) fn build<P: Payload>(payload: &P) -> Option<Vec<u8>> {
+    let mut buffer = vec![0; payload.encoded_len()];
+    payload.write(&mut buffer, None)?;
+    Some(buffer)
+}
@@ -0,0 +1,249 @@
+//! Packets spread over several byte slices, such as the segments of a ring buffer or the
+//! buffers of an `iovec`.
+//!
+//! A `Chain` presents a list of slices as one sequence of bytes. Every packet type can be
+//! created from the start of a chain with `from_chain`, which returns the packet together with
+//! the rest of the chain after its header. The header is borrowed straight from the chain when
+//! it lies within a single slice, and is otherwise copied into a small scratch buffer supplied
+//! by the caller. The payload is never copied.
+//!
+//! ```rust
+//! use rips_packets::chain::Chain;
+//! use rips_packets::ethernet::{EtherType, EthernetPacket};
+//! use rips_packets::ipv4::Ipv4Packet;
+//!
+//! let mut frame = [0; 34];
+//! frame[12..14].copy_from_slice(&[0x08, 0x00]);
+//! frame[14] = 0x45;
+//! // The IPv4 header is split between the two slices.
+//! let (first, second) = frame.split_at(20);
+//! let segments = [first, second];
+//!
+//! // Each layer needs its own scratch buffer, as the packets may borrow from them.
+//! let (mut ethernet_scratch, mut ip_scratch) = ([0; 14], [0; 60]);
+//! let chain = Chain::new(&segments);
+//! let (ethernet, rest) = EthernetPacket::from_chain(chain, &mut ethernet_scratch).unwrap();
+//! assert_eq!(EtherType::IPV4, ethernet.ether_type());
+//! let (ip, payload) = Ipv4Packet::from_chain(rest, &mut ip_scratch).unwrap();
+//! assert_eq!(20, ip.header_len());
+//! assert!(payload.is_empty());
+//! ```
+
+use alloc::vec::Vec;
+
+/// A sequence of bytes made up of a list of slices.
+#[derive(Debug, Copy, Clone)]
+pub struct Chain<'a> {
+    segments: &'a [&'a [u8]],
+    /// The number of bytes of the first segment that are not part of the chain.
+    offset: usize,
+}
+
+impl<'a> Chain<'a> {
+    /// Creates a chain of the bytes in `segments`, in order.
+    pub fn new(segments: &'a [&'a [u8]]) -> Chain<'a> {
+        Chain { segments, offset: 0 }.normalized()
+    }
+
+    /// Skips segments holding no bytes of the chain, so that the first segment is never empty
+    /// unless the whole chain is.
+    fn normalized(mut self) -> Chain<'a> {
+        while let Some((first, rest)) = self.segments.split_first() {
+            if self.offset < first.len() {
+                break;
+            }
+            self.offset -= first.len();
+            self.segments = rest;
+        }
+        self
+    }
+
+    /// Returns the number of bytes in the chain.
+    pub fn len(&self) -> usize {
+        self.chunks().map(|chunk| chunk.len()).sum()
+    }
+
+    /// Returns true if the chain holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns the bytes of the chain as a series of non-empty slices.
+    pub fn chunks(&self) -> Chunks<'a> {
+        Chunks { chain: *self }
+    }
+
+    /// Returns the first non-empty slice of the chain, or an empty slice if the chain is empty.
+    pub fn first(&self) -> &'a [u8] {
+        match self.segments.first() {
+            Some(first) => &first[self.offset..],
+            None => &[],
+        }
+    }
+
+    /// Returns the byte at `index`, or `None` if the chain is not that long.
+    pub fn get(&self, index: usize) -> Option<u8> {
+        let chain = self.skip(index);
+        chain.first().first().cloned()
+    }
+
+    /// Returns the chain without its first `count` bytes. The result is empty if the chain is
+    /// shorter than that.
+    pub fn skip(&self, count: usize) -> Chain<'a> {
+        Chain { segments: self.segments, offset: self.offset + count }.normalized()
+    }
+
+    /// Copies the bytes starting at `offset` into `dst`, filling it. Returns false, leaving
+    /// `dst` in an unspecified state, if the chain does not hold that many bytes.
+    pub fn copy_to(&self, offset: usize, dst: &mut [u8]) -> bool {
+        let mut copied = 0;
+        for chunk in self.skip(offset).chunks() {
+            if copied == dst.len() {
+                break;
+            }
+            let len = ::std::cmp::min(chunk.len(), dst.len() - copied);
+            dst[copied..copied + len].copy_from_slice(&chunk[..len]);
+            copied += len;
+        }
+        copied == dst.len()
+    }
+
+    /// Returns the first `len` bytes as one slice. The slice is borrowed from the chain if they
+    /// are in its first segment, otherwise they are copied to the start of `scratch`. Returns
+    /// `None` if the chain or `scratch` is shorter than `len`.
+    pub fn contiguous<'s>(&self, len: usize, scratch: &'s mut [u8]) -> Option<&'s [u8]>
+    where
+        'a: 's,
+    {
+        let first = self.first();
+        if first.len() >= len {
+            Some(&first[..len])
+        } else if len <= scratch.len() && self.copy_to(0, &mut scratch[..len]) {
+            Some(&scratch[..len])
+        } else {
+            None
+        }
+    }
+
+    /// Copies all bytes of the chain into one vector.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.len());
+        for chunk in self.chunks() {
+            data.extend_from_slice(chunk);
+        }
+        data
+    }
+}
+
+impl<'a> PartialEq for Chain<'a> {
+    /// Chains are equal if they hold the same bytes, however they are split.
+    fn eq(&self, other: &Chain<'a>) -> bool {
+        let mut bytes = other.chunks().flat_map(|chunk| chunk.iter());
+        self.chunks()
+            .flat_map(|chunk| chunk.iter())
+            .all(|byte| bytes.next() == Some(byte))
+            && bytes.next().is_none()
+    }
+}
+
+impl<'a> Eq for Chain<'a> {}
+
+
+/// Iterator over the non-empty slices of a `Chain`.
+#[derive(Clone)]
+pub struct Chunks<'a> {
+    chain: Chain<'a>,
+}
+
+debug_items!(Chunks<'a>);
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let first = self.chain.first();
+        if first.is_empty() {
+            return None;
+        }
+        self.chain = self.chain.skip(first.len());
+        Some(first)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv4::Ipv4Packet;
+    use udp::UdpPacket;
+
+    #[test]
+    fn bytes() {
+        let segments: [&[u8]; 4] = [&[], &[1, 2], &[], &[3, 4, 5]];
+        let chain = Chain::new(&segments);
+        assert_eq!(5, chain.len());
+        assert_eq!(vec![&[1, 2][..], &[3, 4, 5][..]], chain.chunks().collect::<Vec<_>>());
+        assert_eq!(Some(3), chain.get(2));
+        assert_eq!(None, chain.get(5));
+        assert_eq!(vec![4, 5], chain.skip(3).to_vec());
+        assert!(chain.skip(5).is_empty());
+        assert!(chain.skip(9).is_empty());
+
+        let mut dst = [0; 3];
+        assert!(chain.copy_to(1, &mut dst));
+        assert_eq!([2, 3, 4], dst);
+        assert!(!chain.copy_to(3, &mut dst));
+    }
+
+    #[test]
+    fn equality() {
+        let split: [&[u8]; 2] = [&[1], &[2, 3]];
+        let whole: [&[u8]; 1] = [&[1, 2, 3]];
+        let shorter: [&[u8]; 1] = [&[1, 2]];
+        assert_eq!(Chain::new(&split), Chain::new(&whole));
+        assert_ne!(Chain::new(&whole), Chain::new(&shorter));
+        assert_ne!(Chain::new(&shorter), Chain::new(&whole));
+    }
+
+    #[test]
+    fn contiguous() {
+        let segments: [&[u8]; 2] = [&[1, 2], &[3, 4]];
+        let chain = Chain::new(&segments);
+        let mut scratch = [0; 3];
+        let borrowed = chain.contiguous(2, &mut scratch).unwrap();
+        assert_eq!(segments[0].as_ptr(), borrowed.as_ptr());
+        assert_eq!(&[1, 2, 3], chain.contiguous(3, &mut scratch).unwrap());
+        assert_eq!(None, chain.contiguous(4, &mut scratch));
+        assert_eq!(None, chain.contiguous(5, &mut [0; 8]));
+    }
+
+    #[test]
+    fn packet_in_one_segment() {
+        let header = [0x12, 0x34, 0, 53, 0, 12, 0, 0];
+        let payload = [1, 2, 3, 4];
+        let segments = [&header[..], &payload[..]];
+        let mut scratch = [0; 0];
+        let (packet, rest) = UdpPacket::from_chain(Chain::new(&segments), &mut scratch).unwrap();
+        assert_eq!(0x1234, packet.source_port());
+        assert_eq!(header.as_ptr(), packet.data().as_ptr());
+        assert_eq!(vec![&payload[..]], rest.chunks().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn packet_across_segments() {
+        let mut data = [0; 28];
+        data[0] = 0x46;
+        data[9] = 17;
+        data[27] = 0xff;
+        let segments = [&data[..3], &data[3..22], &data[22..]];
+        let mut scratch = [0; 24];
+        let (packet, rest) = Ipv4Packet::from_chain(Chain::new(&segments), &mut scratch).unwrap();
+        assert_eq!(24, packet.header_len());
+        assert_eq!(&data[..24], packet.data());
+        assert_eq!(vec![0, 0, 0, 0xff], rest.to_vec());
+
+        assert!(Ipv4Packet::from_chain(Chain::new(&segments), &mut [0; 20]).is_none());
+        assert!(Ipv4Packet::from_chain(Chain::new(&segments[..2]), &mut [0; 64]).is_none());
+        assert!(Ipv4Packet::from_chain(Chain::new(&[]), &mut [0; 64]).is_none());
+    }
+}
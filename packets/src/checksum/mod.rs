@@ -0,0 +1,295 @@
+use ip::Protocol;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+#[allow(unsafe_code)]
+mod simd;
+
+/// Computes the internet checksum of `data`, the one's complement of the one's complement sum
+/// of its big endian 16 bit words. Summing data that includes a correct checksum gives zero.
+/// Use `Checksum` to compute the checksum over several slices.
+pub fn internet_checksum(data: &[u8]) -> u16 {
+    let mut checksum = Checksum::new();
+    checksum.add_bytes(data);
+    checksum.finish()
+}
+
+/// Accumulator for the one's complement sum used by the internet checksum.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Checksum {
+    sum: u64,
+}
+
+impl Checksum {
+    pub fn new() -> Checksum {
+        Checksum::default()
+    }
+
+    /// Adds `data` to the sum as a sequence of big endian 16 bit words. An odd trailing byte is
+    /// padded with a zero, so only the last slice added may have an odd length.
+    ///
+    /// Longer slices are summed with SSE2, AVX2 or NEON instructions when the CPU has them.
+    pub fn add_bytes(&mut self, data: &[u8]) {
+        if data.len() >= simd::MIN_LEN {
+            if let Some(sum) = simd::sum(data) {
+                self.sum += sum;
+                return;
+            }
+        }
+        let mut chunks = data.chunks_exact(2);
+        for word in &mut chunks {
+            self.sum += u64::from(u16::from_be_bytes([word[0], word[1]]));
+        }
+        if let Some(&byte) = chunks.remainder().first() {
+            self.sum += u64::from(u16::from_be_bytes([byte, 0]));
+        }
+    }
+
+    pub fn add_u16(&mut self, value: u16) {
+        self.sum += u64::from(value);
+    }
+
+    pub fn add_u32(&mut self, value: u32) {
+        self.add_u16((value >> 16) as u16);
+        self.add_u16(value as u16);
+    }
+
+    /// Folds the sum and returns its one's complement.
+    pub fn finish(&self) -> u16 {
+        let mut sum = self.sum;
+        while sum > 0xffff {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+        !(sum as u16)
+    }
+}
+
+/// Adjusts `checksum` for data where the bytes `old` were replaced by `new`, following
+/// equation 3 of RFC 1624. Both slices must have the same length and start at an even offset
+/// in the checksummed data. Useful when rewriting a single field, such as a TTL or an address,
+/// as the rest of the data does not have to be summed again.
+pub fn update(checksum: u16, old: &[u8], new: &[u8]) -> u16 {
+    debug_assert_eq!(old.len(), new.len());
+    let mut sum = Checksum::new();
+    sum.add_u16(!checksum);
+    for word in old.chunks(2) {
+        let word = u16::from_be_bytes([word[0], word.get(1).cloned().unwrap_or(0)]);
+        sum.add_u16(!word);
+    }
+    sum.add_bytes(new);
+    sum.finish()
+}
+
+/// Adds the IPv4 pseudo header used by transport protocol checksums.
+pub fn add_ipv4_pseudo_header(
+    checksum: &mut Checksum,
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    protocol: Protocol,
+    length: usize,
+) {
+    checksum.add_bytes(&source.octets());
+    checksum.add_bytes(&destination.octets());
+    checksum.add_u16(u16::from(protocol.value()));
+    checksum.add_u16(length as u16);
+}
+
+/// Adds the IPv6 pseudo header used by transport protocol checksums.
+pub fn add_ipv6_pseudo_header(
+    checksum: &mut Checksum,
+    source: Ipv6Addr,
+    destination: Ipv6Addr,
+    protocol: Protocol,
+    length: usize,
+) {
+    checksum.add_bytes(&source.octets());
+    checksum.add_bytes(&destination.octets());
+    checksum.add_u32(length as u32);
+    checksum.add_u16(u16::from(protocol.value()));
+}
+
+/// Computes the checksum of a transport protocol segment sent over IPv4, including the pseudo
+/// header. The segment is given as the concatenation of `segments`, which may have any length.
+/// Its total length goes into the pseudo header, so when computing a checksum to send, pass
+/// zeros in place of the checksum field rather than leaving it out.
+pub fn ipv4_transport_checksum(
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    protocol: Protocol,
+    segments: &[&[u8]],
+) -> u16 {
+    let mut checksum = Checksum::new();
+    add_ipv4_pseudo_header(&mut checksum, source, destination, protocol, total_len(segments));
+    add_segments(&mut checksum, segments);
+    checksum.finish()
+}
+
+/// Computes the checksum of a transport protocol segment sent over IPv6. See
+/// `ipv4_transport_checksum` for details.
+pub fn ipv6_transport_checksum(
+    source: Ipv6Addr,
+    destination: Ipv6Addr,
+    protocol: Protocol,
+    segments: &[&[u8]],
+) -> u16 {
+    let mut checksum = Checksum::new();
+    add_ipv6_pseudo_header(&mut checksum, source, destination, protocol, total_len(segments));
+    add_segments(&mut checksum, segments);
+    checksum.finish()
+}
+
+/// Computes the checksum of a transport protocol segment over either IP version. Returns
+/// `None` if `source` and `destination` are not of the same version.
+pub fn transport_checksum(
+    source: IpAddr,
+    destination: IpAddr,
+    protocol: Protocol,
+    segments: &[&[u8]],
+) -> Option<u16> {
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            Some(ipv4_transport_checksum(source, destination, protocol, segments))
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => {
+            Some(ipv6_transport_checksum(source, destination, protocol, segments))
+        }
+        _ => None,
+    }
+}
+
+fn total_len(segments: &[&[u8]]) -> usize {
+    segments.iter().map(|segment| segment.len()).sum()
+}
+
+/// Adds the concatenation of `segments` to the sum. Unlike repeated calls to `add_bytes`, a
+/// segment of odd length is joined with the first byte of the next one.
+fn add_segments(checksum: &mut Checksum, segments: &[&[u8]]) {
+    let mut pending = None;
+    for &segment in segments {
+        let mut segment = segment;
+        if let (Some(byte), Some((&next, rest))) = (pending, segment.split_first()) {
+            checksum.add_u16(u16::from_be_bytes([byte, next]));
+            segment = rest;
+            pending = None;
+        }
+        if pending.is_none() {
+            let even_len = segment.len() & !1;
+            checksum.add_bytes(&segment[..even_len]);
+            pending = segment.get(even_len).cloned();
+        }
+    }
+    if let Some(byte) = pending {
+        checksum.add_u16(u16::from_be_bytes([byte, 0]));
+    }
+}
+
+
+/// Computes the ISO 8473 Fletcher checksum used by OSPF and IS-IS link state PDUs. The two
+/// checksum bytes at `offset` in `data` are treated as zero. Once the result is written there,
+/// both Fletcher sums over `data` are zero modulo 255.
+pub fn fletcher_checksum(data: &[u8], offset: usize) -> u16 {
+    let mut c0: i64 = 0;
+    let mut c1: i64 = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        if i != offset && i != offset + 1 {
+            c0 = (c0 + i64::from(byte)) % 255;
+        }
+        c1 = (c1 + c0) % 255;
+    }
+    let mut x = ((data.len() as i64 - offset as i64 - 1) * c0 - c1) % 255;
+    if x <= 0 {
+        x += 255;
+    }
+    let mut y = 510 - c0 - x;
+    if y > 255 {
+        y -= 255;
+    }
+    ((x as u16) << 8) | (y as u16)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc1071_example() {
+        let mut checksum = Checksum::new();
+        checksum.add_bytes(&[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7]);
+        assert_eq!(!0xddf2, checksum.finish());
+    }
+
+    #[test]
+    fn odd_length() {
+        let mut checksum = Checksum::new();
+        checksum.add_bytes(&[0x12, 0x34, 0x56]);
+        assert_eq!(!(0x1234 + 0x5600), checksum.finish());
+    }
+
+    #[test]
+    fn single_slice() {
+        let mut data = [0x45, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00];
+        let checksum = internet_checksum(&data);
+        data[10..].copy_from_slice(&checksum.to_be_bytes());
+        assert_eq!(0, internet_checksum(&data));
+    }
+
+    #[test]
+    fn segments_of_odd_length() {
+        let source = Ipv4Addr::new(10, 0, 0, 1);
+        let destination = Ipv4Addr::new(10, 0, 0, 2);
+        let data = [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde];
+        let whole = ipv4_transport_checksum(source, destination, Protocol::UDP, &[&data]);
+        let split = ipv4_transport_checksum(
+            source,
+            destination,
+            Protocol::UDP,
+            &[&data[..1], &[], &data[1..4], &data[4..]],
+        );
+        assert_eq!(whole, split);
+    }
+
+    #[test]
+    fn transport_checksum_versions() {
+        let v4 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert!(transport_checksum(v4, v4, Protocol::TCP, &[&[1, 2]]).is_some());
+        assert!(transport_checksum(v6, v6, Protocol::TCP, &[&[1, 2]]).is_some());
+        assert_eq!(None, transport_checksum(v4, v6, Protocol::TCP, &[&[1, 2]]));
+    }
+
+    #[test]
+    fn incremental_update() {
+        let mut data = [0x45, 0x00, 0x00, 0x1c, 0x12, 0x34, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00];
+        let checksum = internet_checksum(&data);
+        data[8] = 0x3f;
+        assert_eq!(internet_checksum(&data), update(checksum, &[0x40, 0x11], &[0x3f, 0x11]));
+        data[4..8].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        let updated = update(checksum, &[0x40, 0x11, 0x12, 0x34], &[0x3f, 0x11, 0xff, 0xff]);
+        assert_eq!(internet_checksum(&data), update(updated, &[0x40, 0x00], &[0xff, 0xff]));
+    }
+
+    #[test]
+    fn incremental_update_odd_length() {
+        let mut data = [0x12, 0x34, 0x56];
+        let checksum = internet_checksum(&data);
+        data[2] = 0x78;
+        assert_eq!(internet_checksum(&data), update(checksum, &[0x56], &[0x78]));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(0xffff, Checksum::new().finish());
+    }
+
+    #[test]
+    fn fletcher() {
+        let mut data = [0x01, 0x02, 0xff, 0xff, 0x03, 0x04, 0x05];
+        let checksum = fletcher_checksum(&data, 2);
+        data[2..4].copy_from_slice(&checksum.to_be_bytes());
+        let (c0, c1) = data.iter().fold((0u32, 0u32), |(c0, c1), &byte| {
+            let c0 = (c0 + u32::from(byte)) % 255;
+            (c0, (c1 + c0) % 255)
+        });
+        assert_eq!((0, 0), (c0, c1));
+    }
+}
@@ -0,0 +1,209 @@
+//! Vectorized summing of 16 bit words for the internet checksum. The instruction set is picked
+//! at runtime on x86, or at compile time without the `std` feature, while NEON is always
+//! available on aarch64.
+//!
+//! The vector code sums the data as little endian words, which is what the CPU loads natively.
+//! The one's complement sum is independent of byte order (RFC 1071, section 2), so folding the
+//! little endian sum to 16 bits and swapping its bytes gives the big endian sum.
+
+/// Slices shorter than this are summed by the scalar code, where vector setup does not pay off.
+pub const MIN_LEN: usize = 64;
+
+/// Number of vector loads to accumulate before flushing the 32 bit lanes. Every load adds at
+/// most two words of 0xffff to each lane, so the lanes can not overflow within one block.
+const BLOCK_LOADS: usize = 0x8000;
+
+/// Returns a value congruent, modulo 0xffff, to the sum of `data` as big endian 16 bit words,
+/// with an odd trailing byte padded with a zero. Returns `None` if no vector instructions are
+/// available, in which case the caller has to use the scalar code.
+pub fn sum(data: &[u8]) -> Option<u64> {
+    sum_le(data).map(|sum| u64::from(fold(sum).swap_bytes()))
+}
+
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "std"))]
+fn sum_le(data: &[u8]) -> Option<u64> {
+    if is_x86_feature_detected!("avx2") {
+        Some(unsafe { x86::sum_avx2(data) })
+    } else if is_x86_feature_detected!("sse2") {
+        Some(unsafe { x86::sum_sse2(data) })
+    } else {
+        None
+    }
+}
+
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(feature = "std")))]
+fn sum_le(data: &[u8]) -> Option<u64> {
+    if cfg!(target_feature = "avx2") {
+        Some(unsafe { x86::sum_avx2(data) })
+    } else if cfg!(target_feature = "sse2") {
+        Some(unsafe { x86::sum_sse2(data) })
+    } else {
+        None
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+fn sum_le(data: &[u8]) -> Option<u64> {
+    Some(unsafe { aarch64::sum_neon(data) })
+}
+
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_endian = "little")
+)))]
+fn sum_le(_data: &[u8]) -> Option<u64> {
+    None
+}
+
+/// Sums the bytes the vector loop did not cover as little endian words.
+#[allow(dead_code)]
+fn sum_le_scalar(data: &[u8]) -> u64 {
+    let mut chunks = data.chunks_exact(2);
+    let mut sum = 0;
+    for word in &mut chunks {
+        sum += u64::from(u16::from_le_bytes([word[0], word[1]]));
+    }
+    if let Some(&byte) = chunks.remainder().first() {
+        sum += u64::from(byte);
+    }
+    sum
+}
+
+fn fold(mut sum: u64) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use super::{sum_le_scalar, BLOCK_LOADS};
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn sum_sse2(data: &[u8]) -> u64 {
+        let zero = _mm_setzero_si128();
+        let mut chunks = data.chunks_exact(16);
+        let mut sum = 0;
+        loop {
+            let mut lanes = _mm_setzero_si128();
+            let mut loads = 0;
+            for chunk in (&mut chunks).take(BLOCK_LOADS) {
+                let words = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+                lanes = _mm_add_epi32(lanes, _mm_unpacklo_epi16(words, zero));
+                lanes = _mm_add_epi32(lanes, _mm_unpackhi_epi16(words, zero));
+                loads += 1;
+            }
+            let mut out = [0u32; 4];
+            _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, lanes);
+            sum += out.iter().map(|&lane| u64::from(lane)).sum::<u64>();
+            if loads < BLOCK_LOADS {
+                break;
+            }
+        }
+        sum + sum_le_scalar(chunks.remainder())
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn sum_avx2(data: &[u8]) -> u64 {
+        let zero = _mm256_setzero_si256();
+        let mut chunks = data.chunks_exact(32);
+        let mut sum = 0;
+        loop {
+            let mut lanes = _mm256_setzero_si256();
+            let mut loads = 0;
+            for chunk in (&mut chunks).take(BLOCK_LOADS) {
+                let words = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+                lanes = _mm256_add_epi32(lanes, _mm256_unpacklo_epi16(words, zero));
+                lanes = _mm256_add_epi32(lanes, _mm256_unpackhi_epi16(words, zero));
+                loads += 1;
+            }
+            let mut out = [0u32; 8];
+            _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, lanes);
+            sum += out.iter().map(|&lane| u64::from(lane)).sum::<u64>();
+            if loads < BLOCK_LOADS {
+                break;
+            }
+        }
+        sum + sum_le_scalar(chunks.remainder())
+    }
+}
+
+
+#[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+mod aarch64 {
+    use std::arch::aarch64::*;
+
+    use super::{sum_le_scalar, BLOCK_LOADS};
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn sum_neon(data: &[u8]) -> u64 {
+        let mut chunks = data.chunks_exact(16);
+        let mut sum = 0;
+        loop {
+            let mut lanes = vdupq_n_u32(0);
+            let mut loads = 0;
+            for chunk in (&mut chunks).take(BLOCK_LOADS) {
+                let words = vreinterpretq_u16_u8(vld1q_u8(chunk.as_ptr()));
+                lanes = vpadalq_u16(lanes, words);
+                loads += 1;
+            }
+            sum += vaddlvq_u32(lanes);
+            if loads < BLOCK_LOADS {
+                break;
+            }
+        }
+        sum + sum_le_scalar(chunks.remainder())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Checksum;
+
+    fn scalar_sum(data: &[u8]) -> u16 {
+        let mut checksum = Checksum::new();
+        for word in data.chunks(2) {
+            checksum.add_u16(u16::from_be_bytes([word[0], word.get(1).cloned().unwrap_or(0)]));
+        }
+        checksum.finish()
+    }
+
+    fn vector_sum(data: &[u8]) -> Option<u16> {
+        sum(data).map(|sum| !fold(sum))
+    }
+
+    #[test]
+    fn matches_scalar() {
+        let data: Vec<u8> = (0..1600u32).map(|i| (i * 7 + i / 3) as u8).collect();
+        for start in 0..4 {
+            for len in (0..200).chain(1490..1597) {
+                let data = &data[start..start + len];
+                if let Some(checksum) = vector_sum(data) {
+                    assert_eq!(scalar_sum(data), checksum, "start {} len {}", start, len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lanes_do_not_overflow() {
+        let data = vec![0xff; 3 * 32 * BLOCK_LOADS + 5];
+        if let Some(checksum) = vector_sum(&data) {
+            assert_eq!(scalar_sum(&data), checksum);
+        }
+        let data = vec![0xfe; 3 * 32 * BLOCK_LOADS + 5];
+        if let Some(checksum) = vector_sum(&data) {
+            assert_eq!(scalar_sum(&data), checksum);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Datagram Congestion Control Protocol, RFC 4340.
+
+use std::cmp;
+use std::fmt;
+use types::*;
+
+packet!(
+    DccpPacket,
+    MutDccpPacket,
+    DccpPacketBuf,
+    12,
+    |data| usize::from(data[4]) * 4,
+    init |data| {
+        // A Data packet with short sequence numbers is the only type without a subheader.
+        write_offset!(data, 4, 3u8, u8);
+        write_offset!(data, 8, DccpType::DATA.value() << 1, u8);
+    }
+);
+
+getters!(DccpPacket
+    pub fn source_port(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    pub fn destination_port(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the size of the header, including options, in 32 bit words.
+    pub fn data_offset(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    /// Returns the value reserved for the sender's congestion control mechanism.
+    pub fn ccval(&self) -> u4 {
+        read_offset!(self.0, 5, u8) >> 4
+    }
+
+    /// Returns the checksum coverage. Zero means the entire packet is covered, otherwise the
+    /// checksum covers the header and the first `(cscov - 1) * 4` bytes of application data.
+    pub fn cscov(&self) -> u4 {
+        read_offset!(self.0, 5, u8) & 0x0f
+    }
+
+    pub fn checksum(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    pub fn packet_type(&self) -> DccpType {
+        DccpType((read_offset!(self.0, 8, u8) >> 1) & 0x0f)
+    }
+
+    /// Returns the X bit. When set, the header uses 48 bit sequence numbers and is 16 bytes
+    /// long, otherwise it uses 24 bit sequence numbers and is 12 bytes long.
+    pub fn extended_sequence_numbers(&self) -> bool {
+        read_offset!(self.0, 8, u8) & 0x01 != 0
+    }
+
+    /// Returns the sequence number, or `None` if it does not fit in the backing slice.
+    pub fn sequence_number(&self) -> Option<u64> {
+        if self.extended_sequence_numbers() {
+            self.0.get(10..16).map(|field| read_offset!(field, 0, u48, from_be))
+        } else {
+            self.0.get(9..12).map(|field| u64::from(read_offset!(field, 0, u24, from_be)))
+        }
+    }
+
+    /// Returns the acknowledgement number, or `None` if the packet type has no acknowledgement
+    /// number subheader or it does not fit in the backing slice.
+    pub fn acknowledgement_number(&self) -> Option<u64> {
+        if !self.packet_type().has_acknowledgement_number() {
+            return None;
+        }
+        let offset = self.generic_header_len();
+        if self.extended_sequence_numbers() {
+            self.0.get(offset + 2..offset + 8).map(|field| read_offset!(field, 0, u48, from_be))
+        } else {
+            self.0
+                .get(offset + 1..offset + 4)
+                .map(|field| u64::from(read_offset!(field, 0, u24, from_be)))
+        }
+    }
+
+    /// Returns the service code of a Request or Response packet, or `None` for other packet
+    /// types or if it does not fit in the backing slice.
+    pub fn service_code(&self) -> Option<u32> {
+        let offset = self.service_code_offset()?;
+        self.0.get(offset..offset + 4).map(|code| read_offset!(code, 0, u32, from_be))
+    }
+
+    /// Returns the raw options part of the header. Everything between the fixed part of the
+    /// header for this packet type and the offset given by `data_offset`.
+    pub fn options(&self) -> &[u8] {
+        let start = cmp::min(self.fixed_header_len(), self.header_len());
+        &self.0[start..self.header_len()]
+    }
+);
+
+impl<'a> DccpPacket<'a> {
+    /// Returns the length of the generic header, 12 or 16 bytes depending on the X bit.
+    pub fn generic_header_len(&self) -> usize {
+        if self.extended_sequence_numbers() { 16 } else { 12 }
+    }
+
+    /// Returns the length of the header before any options. That is the generic header plus the
+    /// acknowledgement number subheader and service code, if present for this packet type.
+    pub fn fixed_header_len(&self) -> usize {
+        match self.service_code_offset() {
+            Some(offset) => offset + 4,
+            None => self.generic_header_len() + self.acknowledgement_subheader_len(),
+        }
+    }
+
+    fn acknowledgement_subheader_len(&self) -> usize {
+        match (
+            self.packet_type().has_acknowledgement_number(),
+            self.extended_sequence_numbers(),
+        ) {
+            (false, _) => 0,
+            (true, true) => 8,
+            (true, false) => 4,
+        }
+    }
+
+    fn service_code_offset(&self) -> Option<usize> {
+        match self.packet_type() {
+            DccpType::REQUEST | DccpType::RESPONSE => {
+                Some(self.generic_header_len() + self.acknowledgement_subheader_len())
+            }
+            _ => None,
+        }
+    }
+}
+
+setters!(MutDccpPacket
+    pub fn set_source_port(&mut self, source_port: u16) {
+        write_offset!(self.0, 0, source_port, u16, to_be);
+    }
+
+    pub fn set_destination_port(&mut self, destination_port: u16) {
+        write_offset!(self.0, 2, destination_port, u16, to_be);
+    }
+
+    pub fn set_data_offset(&mut self, data_offset: u8) {
+        write_offset!(self.0, 4, data_offset, u8);
+    }
+
+    pub fn set_ccval(&mut self, ccval: u4) {
+        let new_byte = (ccval << 4) | (read_offset!(self.0, 5, u8) & 0x0f);
+        write_offset!(self.0, 5, new_byte, u8);
+    }
+
+    pub fn set_cscov(&mut self, cscov: u4) {
+        let new_byte = (read_offset!(self.0, 5, u8) & 0xf0) | (cscov & 0x0f);
+        write_offset!(self.0, 5, new_byte, u8);
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        write_offset!(self.0, 6, checksum, u16, to_be);
+    }
+
+    pub fn set_packet_type(&mut self, packet_type: DccpType) {
+        let new_byte = (read_offset!(self.0, 8, u8) & 0xe1) | ((packet_type.value() & 0x0f) << 1);
+        write_offset!(self.0, 8, new_byte, u8);
+    }
+
+    pub fn set_extended_sequence_numbers(&mut self, extended_sequence_numbers: bool) {
+        let new_byte = (read_offset!(self.0, 8, u8) & 0xfe) | extended_sequence_numbers as u8;
+        write_offset!(self.0, 8, new_byte, u8);
+    }
+);
+
+impl<'a> MutDccpPacket<'a> {
+    /// Sets the sequence number, using the format given by the X bit. Only the lowest 24 or 48
+    /// bits are stored. Also clears the reserved byte preceding a 48 bit sequence number.
+    /// Returns false, leaving the packet unchanged, if the generic header does not fit in the
+    /// backing slice.
+    pub fn set_sequence_number(&mut self, sequence_number: u64) -> bool {
+        let packet = self.as_immutable();
+        if packet.generic_header_len() > self.0.len() {
+            return false;
+        }
+        if packet.extended_sequence_numbers() {
+            write_offset!(self.0, 9, 0u8, u8);
+            write_offset!(self.0, 10, sequence_number, u48, to_be);
+        } else {
+            write_offset!(self.0, 9, sequence_number as u32, u24, to_be);
+        }
+        true
+    }
+
+    /// Sets the acknowledgement number, using the format given by the X bit. Only the lowest 24
+    /// or 48 bits are stored, and the reserved bytes of the subheader are cleared. Returns
+    /// false, leaving the packet unchanged, if the packet type has no acknowledgement number
+    /// subheader, or if the subheader does not fit in the backing slice. Set the packet type and
+    /// X bit first.
+    pub fn set_acknowledgement_number(&mut self, acknowledgement_number: u64) -> bool {
+        let packet = self.as_immutable();
+        let offset = packet.generic_header_len();
+        if !packet.packet_type().has_acknowledgement_number() ||
+            offset + packet.acknowledgement_subheader_len() > self.0.len()
+        {
+            return false;
+        }
+        // Two reserved bytes precede a 48 bit number, one precedes a 24 bit number
+        if packet.extended_sequence_numbers() {
+            write_offset!(self.0, offset, [0; 2], [u8; 2]);
+            write_offset!(self.0, offset + 2, acknowledgement_number, u48, to_be);
+        } else {
+            write_offset!(self.0, offset, 0u8, u8);
+            write_offset!(self.0, offset + 1, acknowledgement_number as u32, u24, to_be);
+        }
+        true
+    }
+
+    /// Sets the service code of a Request or Response packet. Returns false, leaving the packet
+    /// unchanged, if the packet type has no service code, or if it does not fit in the backing
+    /// slice. Set the packet type and X bit first.
+    pub fn set_service_code(&mut self, service_code: u32) -> bool {
+        match self.as_immutable().service_code_offset() {
+            Some(offset) if offset + 4 <= self.0.len() => {
+                write_offset!(self.0, offset, service_code, u32, to_be);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+
+/// The 4 bit packet type field of a DCCP header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DccpType(pub u4);
+
+impl DccpType {
+    pub const REQUEST: DccpType = DccpType(0);
+    pub const RESPONSE: DccpType = DccpType(1);
+    pub const DATA: DccpType = DccpType(2);
+    pub const ACK: DccpType = DccpType(3);
+    pub const DATA_ACK: DccpType = DccpType(4);
+    pub const CLOSE_REQ: DccpType = DccpType(5);
+    pub const CLOSE: DccpType = DccpType(6);
+    pub const RESET: DccpType = DccpType(7);
+    pub const SYNC: DccpType = DccpType(8);
+    pub const SYNC_ACK: DccpType = DccpType(9);
+
+    #[inline]
+    pub fn value(&self) -> u4 {
+        self.0
+    }
+
+    /// Returns true if packets of this type carry an acknowledgement number subheader. That is
+    /// every type except Request and Data.
+    pub fn has_acknowledgement_number(&self) -> bool {
+        *self != DccpType::REQUEST && *self != DccpType::DATA
+    }
+}
+
+impl<'a> fmt::Display for DccpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DCCP {} -> {} type={}",
+            self.source_port(),
+            self.destination_port(),
+            self.packet_type().value()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! dccp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutDccpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    dccp_setget_test!(source_port, set_source_port, 0xabcd, 0, [0xab, 0xcd]);
+    dccp_setget_test!(destination_port, set_destination_port, 0xabcd, 2, [0xab, 0xcd]);
+    dccp_setget_test!(data_offset, set_data_offset, 0xff, 4, [0xff]);
+    dccp_setget_test!(ccval, set_ccval, 0xf, 5, [0xf0]);
+    dccp_setget_test!(cscov, set_cscov, 0xf, 5, [0x0f]);
+    dccp_setget_test!(checksum, set_checksum, 0xabcd, 6, [0xab, 0xcd]);
+    dccp_setget_test!(packet_type, set_packet_type, DccpType(0xf), 8, [0x1e]);
+    dccp_setget_test!(
+        extended_sequence_numbers,
+        set_extended_sequence_numbers,
+        true,
+        8,
+        [0x01]
+    );
+
+    #[test]
+    fn extended_request() {
+        let mut data = [0; 20];
+        {
+            let mut testee = MutDccpPacket::new(&mut data).unwrap();
+            testee.set_data_offset(5);
+            testee.set_packet_type(DccpType::REQUEST);
+            testee.set_extended_sequence_numbers(true);
+            assert!(testee.set_sequence_number(0x0102_0304_0506));
+            assert!(testee.set_service_code(0xdeadbeef));
+        }
+        assert_eq!(&[0x01, 0x02, 0x03, 0x04, 0x05, 0x06], &data[10..16]);
+        let testee = DccpPacket::new(&data).unwrap();
+        assert_eq!(DccpType::REQUEST, testee.packet_type());
+        assert_eq!(16, testee.generic_header_len());
+        assert_eq!(20, testee.fixed_header_len());
+        assert_eq!(Some(0x0102_0304_0506), testee.sequence_number());
+        assert_eq!(None, testee.acknowledgement_number());
+        assert_eq!(Some(0xdeadbeef), testee.service_code());
+        assert!(testee.options().is_empty());
+    }
+
+    #[test]
+    fn extended_response() {
+        let mut data = [0xff; 28];
+        {
+            let mut testee = MutDccpPacket::new(&mut data).unwrap();
+            testee.set_data_offset(7);
+            testee.set_packet_type(DccpType::RESPONSE);
+            testee.set_extended_sequence_numbers(true);
+            assert!(testee.set_acknowledgement_number(0xaabb_ccdd_eeff));
+            assert!(testee.set_service_code(1));
+        }
+        assert_eq!(&[0, 0, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff], &data[16..24]);
+        let testee = DccpPacket::new(&data).unwrap();
+        assert_eq!(Some(0xaabb_ccdd_eeff), testee.acknowledgement_number());
+        assert_eq!(Some(1), testee.service_code());
+        assert_eq!(28, testee.fixed_header_len());
+    }
+
+    #[test]
+    fn short_ack_with_options() {
+        let mut data = [0; 20];
+        {
+            let mut testee = MutDccpPacket::new(&mut data).unwrap();
+            testee.set_data_offset(5);
+            testee.set_packet_type(DccpType::ACK);
+            assert!(testee.set_sequence_number(0xab_cdef));
+            assert!(testee.set_acknowledgement_number(0x12_3456));
+        }
+        data[16..20].copy_from_slice(&[0x01, 0x01, 0x01, 0x00]);
+        let testee = DccpPacket::new(&data).unwrap();
+        assert_eq!(12, testee.generic_header_len());
+        assert_eq!(Some(0xab_cdef), testee.sequence_number());
+        assert_eq!(Some(0x12_3456), testee.acknowledgement_number());
+        assert_eq!(None, testee.service_code());
+        assert_eq!(&[0x01, 0x01, 0x01, 0x00], testee.options());
+    }
+
+    #[test]
+    fn truncated_fields() {
+        let mut data = [0; 12];
+        data[8] = 0x01;
+        let testee = DccpPacket::new(&data).unwrap();
+        assert_eq!(None, testee.sequence_number());
+        data[8] = DccpType::SYNC.value() << 1;
+        assert_eq!(None, DccpPacket::new(&data).unwrap().acknowledgement_number());
+    }
+
+    #[test]
+    fn set_fields_not_in_packet() {
+        let mut data = [0; 16];
+        let mut testee = MutDccpPacket::new(&mut data).unwrap();
+        testee.set_packet_type(DccpType::DATA);
+        assert!(!testee.set_service_code(1));
+        assert!(!testee.set_acknowledgement_number(1));
+        testee.set_packet_type(DccpType::ACK);
+        assert!(testee.set_acknowledgement_number(1));
+        testee.set_extended_sequence_numbers(true);
+        assert!(!testee.set_acknowledgement_number(2));
+        assert!(testee.set_sequence_number(3));
+        assert_eq!(Some(3), testee.as_immutable().sequence_number());
+        assert_eq!(None, testee.as_immutable().acknowledgement_number());
+
+        let mut data = [0; 12];
+        let mut testee = MutDccpPacket::new(&mut data).unwrap();
+        testee.set_extended_sequence_numbers(true);
+        assert!(!testee.set_sequence_number(1));
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        DccpPacketBuf => |packet| packet.options().is_empty() && packet.sequence_number().is_some()
+    );
+}
@@ -0,0 +1,616 @@
+//! The Dynamic Host Configuration Protocol for IPv4, RFC 2131, using the BOOTP message format
+//! with options as defined in RFC 2132.
+
+use ethernet::MacAddr;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// The UDP port DHCP servers listen on.
+pub const SERVER_PORT: u16 = 67;
+
+/// The UDP port DHCP clients listen on.
+pub const CLIENT_PORT: u16 = 68;
+
+/// The value of the magic cookie field, separating the BOOTP fields from DHCP options.
+pub const MAGIC_COOKIE: u32 = 0x6382_5363;
+
+packet!(
+    /// A DHCP message. The header covers the fixed BOOTP fields and the magic cookie, so the
+    /// payload is the options.
+    DhcpPacket,
+    MutDhcpPacket,
+    DhcpPacketBuf,
+    240,
+    |_data| 240,
+    init |data| {
+        write_offset!(data, 1, [1, 6], [u8; 2]);
+        write_offset!(data, 236, MAGIC_COOKIE, u32, to_be);
+    }
+);
+
+getters!(DhcpPacket
+    pub fn op(&self) -> Op {
+        Op(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the hardware address type, 1 for Ethernet.
+    pub fn htype(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns the hardware address length, 6 for Ethernet.
+    pub fn hlen(&self) -> u8 {
+        read_offset!(self.0, 2, u8)
+    }
+
+    pub fn hops(&self) -> u8 {
+        read_offset!(self.0, 3, u8)
+    }
+
+    /// Returns the transaction ID, chosen by the client to match replies to its requests.
+    pub fn xid(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the number of seconds since the client began acquiring or renewing an address.
+    pub fn secs(&self) -> u16 {
+        read_offset!(self.0, 8, u16, from_be)
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 10, u16, from_be))
+    }
+
+    /// Returns the current address of the client, if it has one.
+    pub fn ciaddr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 12, [u8; 4]))
+    }
+
+    /// Returns the address offered to or assigned to the client.
+    pub fn yiaddr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 16, [u8; 4]))
+    }
+
+    /// Returns the address of the next server to use in bootstrap.
+    pub fn siaddr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 20, [u8; 4]))
+    }
+
+    /// Returns the address of the relay agent the message went through, if any.
+    pub fn giaddr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 24, [u8; 4]))
+    }
+
+    /// Returns the client hardware address as a MAC address, the first six bytes of the
+    /// 16 byte field.
+    pub fn chaddr(&self) -> MacAddr {
+        MacAddr::from_slice(&self.0[28..34])
+    }
+
+    /// Returns the optional, zero terminated, server host name field.
+    pub fn sname(&self) -> &'a [u8] {
+        &self.0[44..108]
+    }
+
+    /// Returns the optional, zero terminated, boot file name field.
+    pub fn file(&self) -> &'a [u8] {
+        &self.0[108..236]
+    }
+
+    pub fn magic_cookie(&self) -> u32 {
+        read_offset!(self.0, 236, u32, from_be)
+    }
+
+    /// Returns an iterator over the options.
+    pub fn options(&self) -> DhcpOptions<'a> {
+        DhcpOptions::new(&self.0[240..])
+    }
+
+    /// Returns the DHCP message type from the options, or `None` if there is no such option,
+    /// which is the case for plain BOOTP messages.
+    pub fn message_type(&self) -> Option<MessageType> {
+        self.options().find_map(|option| match option {
+            DhcpOption::MessageType(message_type) => Some(message_type),
+            _ => None,
+        })
+    }
+);
+
+setters!(MutDhcpPacket
+    pub fn set_op(&mut self, op: Op) {
+        write_offset!(self.0, 0, op.value(), u8);
+    }
+
+    pub fn set_htype(&mut self, htype: u8) {
+        write_offset!(self.0, 1, htype, u8);
+    }
+
+    pub fn set_hlen(&mut self, hlen: u8) {
+        write_offset!(self.0, 2, hlen, u8);
+    }
+
+    pub fn set_hops(&mut self, hops: u8) {
+        write_offset!(self.0, 3, hops, u8);
+    }
+
+    pub fn set_xid(&mut self, xid: u32) {
+        write_offset!(self.0, 4, xid, u32, to_be);
+    }
+
+    pub fn set_secs(&mut self, secs: u16) {
+        write_offset!(self.0, 8, secs, u16, to_be);
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_offset!(self.0, 10, flags.bits(), u16, to_be);
+    }
+
+    pub fn set_ciaddr(&mut self, ciaddr: Ipv4Addr) {
+        write_offset!(self.0, 12, ciaddr.octets(), [u8; 4]);
+    }
+
+    pub fn set_yiaddr(&mut self, yiaddr: Ipv4Addr) {
+        write_offset!(self.0, 16, yiaddr.octets(), [u8; 4]);
+    }
+
+    pub fn set_siaddr(&mut self, siaddr: Ipv4Addr) {
+        write_offset!(self.0, 20, siaddr.octets(), [u8; 4]);
+    }
+
+    pub fn set_giaddr(&mut self, giaddr: Ipv4Addr) {
+        write_offset!(self.0, 24, giaddr.octets(), [u8; 4]);
+    }
+
+    /// Sets the client hardware address to a MAC address, zeroing the rest of the 16 byte field.
+    pub fn set_chaddr(&mut self, chaddr: MacAddr) {
+        self.0[28..34].copy_from_slice(chaddr.as_ref());
+        for byte in &mut self.0[34..44] {
+            *byte = 0;
+        }
+    }
+
+    pub fn set_magic_cookie(&mut self, magic_cookie: u32) {
+        write_offset!(self.0, 236, magic_cookie, u32, to_be);
+    }
+);
+
+impl<'a> MutDhcpPacket<'a> {
+    /// Sets the hardware type and length for Ethernet and the magic cookie, and zeroes the
+    /// server host name and boot file name fields.
+    pub fn set_dhcp_defaults(&mut self) {
+        self.set_htype(1);
+        self.set_hlen(6);
+        for byte in &mut self.0[44..236] {
+            *byte = 0;
+        }
+        self.set_magic_cookie(MAGIC_COOKIE);
+    }
+
+    /// Encodes `options` after the header, followed by an end option. Returns the length of
+    /// the encoded options including the end option, or `None` if they do not fit in the
+    /// backing slice or can't be encoded.
+    pub fn set_options(&mut self, options: &[DhcpOption]) -> Option<usize> {
+        let len = write_options(&mut self.0[240..], options)?;
+        Some(len + DhcpOption::End.write(&mut self.0[240 + len..])?)
+    }
+}
+
+
+/// The op field of a BOOTP message, telling requests from replies.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Op(pub u8);
+
+impl Op {
+    pub const BOOTREQUEST: Op = Op(1);
+    pub const BOOTREPLY: Op = Op(2);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags field of a DHCP message.
+    pub struct Flags: u16 {
+        /// A bitmask with a one in the "Broadcast" position, asking the server to broadcast its
+        /// replies since the client can't receive unicast before it has an address.
+        const BROADCAST = 0x8000;
+    }
+}
+
+/// The 8 bit code of a DHCP option.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OptionCode(pub u8);
+
+impl OptionCode {
+    pub const PAD: OptionCode = OptionCode(0);
+    pub const SUBNET_MASK: OptionCode = OptionCode(1);
+    pub const ROUTER: OptionCode = OptionCode(3);
+    pub const DOMAIN_NAME_SERVER: OptionCode = OptionCode(6);
+    pub const HOST_NAME: OptionCode = OptionCode(12);
+    pub const REQUESTED_IP_ADDRESS: OptionCode = OptionCode(50);
+    pub const LEASE_TIME: OptionCode = OptionCode(51);
+    pub const MESSAGE_TYPE: OptionCode = OptionCode(53);
+    pub const SERVER_IDENTIFIER: OptionCode = OptionCode(54);
+    pub const PARAMETER_REQUEST_LIST: OptionCode = OptionCode(55);
+    pub const END: OptionCode = OptionCode(255);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The DHCP message type, carried in the message type option.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    pub const DISCOVER: MessageType = MessageType(1);
+    pub const OFFER: MessageType = MessageType(2);
+    pub const REQUEST: MessageType = MessageType(3);
+    pub const DECLINE: MessageType = MessageType(4);
+    pub const ACK: MessageType = MessageType(5);
+    pub const NAK: MessageType = MessageType(6);
+    pub const RELEASE: MessageType = MessageType(7);
+    pub const INFORM: MessageType = MessageType(8);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+/// A list of IPv4 addresses in a DHCP option, kept as the encoded bytes.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Ipv4Addresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ipv4Addresses<'a>);
+
+impl<'a> Ipv4Addresses<'a> {
+    /// Creates a list from the encoded addresses in `data`, or returns `None` if the length of
+    /// `data` is not a multiple of four.
+    pub fn new(data: &'a [u8]) -> Option<Ipv4Addresses<'a>> {
+        if data.len().is_multiple_of(4) {
+            Some(Ipv4Addresses { data })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the encoded addresses.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> Iterator for Ipv4Addresses<'a> {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Ipv4Addr> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (address, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(Ipv4Addr::from(read_offset!(address, 0, [u8; 4])))
+    }
+}
+
+/// A single option in a DHCP message. Pad options are skipped when reading and not
+/// represented.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DhcpOption<'a> {
+    /// Marks the end of the options.
+    End,
+    SubnetMask(Ipv4Addr),
+    /// Routers on the subnet of the client, in order of preference.
+    Router(Ipv4Addresses<'a>),
+    /// DNS servers available to the client, in order of preference.
+    DomainNameServer(Ipv4Addresses<'a>),
+    HostName(&'a [u8]),
+    /// The address the client asks for in a discover or request message.
+    RequestedIpAddress(Ipv4Addr),
+    /// The lease time in seconds.
+    LeaseTime(u32),
+    MessageType(MessageType),
+    /// The address of the server, telling which server an offer was accepted from.
+    ServerIdentifier(Ipv4Addr),
+    /// The option codes the client asks the server to include in its reply.
+    ParameterRequestList(&'a [u8]),
+    /// Any other option, or one of the above with an unexpected length. Holds the option data
+    /// following the code and length fields.
+    Other(OptionCode, &'a [u8]),
+}
+
+impl<'a> DhcpOption<'a> {
+    /// Returns the number of bytes this option occupies when encoded, or `None` if it can't be
+    /// encoded because its data is empty where not allowed, or longer than 255 bytes.
+    pub fn encoded_len(&self) -> Option<usize> {
+        let data_len = match *self {
+            DhcpOption::End => return Some(1),
+            DhcpOption::SubnetMask(_) |
+            DhcpOption::RequestedIpAddress(_) |
+            DhcpOption::LeaseTime(_) |
+            DhcpOption::ServerIdentifier(_) => 4,
+            DhcpOption::MessageType(_) => 1,
+            DhcpOption::Router(addresses) | DhcpOption::DomainNameServer(addresses) => {
+                addresses.data().len()
+            }
+            DhcpOption::HostName(data) | DhcpOption::ParameterRequestList(data) => data.len(),
+            DhcpOption::Other(_, data) if data.len() <= 255 => return Some(2 + data.len()),
+            DhcpOption::Other(..) => return None,
+        };
+        if data_len > 0 && data_len <= 255 {
+            Some(2 + data_len)
+        } else {
+            None
+        }
+    }
+
+    /// Encodes this option into the beginning of `buffer`. Returns the number of bytes written,
+    /// or `None` if the option does not fit in `buffer` or can't be encoded.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len()?;
+        if buffer.len() < len {
+            return None;
+        }
+        let code = match *self {
+            DhcpOption::End => {
+                buffer[0] = OptionCode::END.value();
+                return Some(1);
+            }
+            DhcpOption::SubnetMask(address) => {
+                buffer[2..6].copy_from_slice(&address.octets());
+                OptionCode::SUBNET_MASK
+            }
+            DhcpOption::Router(addresses) => {
+                buffer[2..len].copy_from_slice(addresses.data());
+                OptionCode::ROUTER
+            }
+            DhcpOption::DomainNameServer(addresses) => {
+                buffer[2..len].copy_from_slice(addresses.data());
+                OptionCode::DOMAIN_NAME_SERVER
+            }
+            DhcpOption::HostName(data) => {
+                buffer[2..len].copy_from_slice(data);
+                OptionCode::HOST_NAME
+            }
+            DhcpOption::RequestedIpAddress(address) => {
+                buffer[2..6].copy_from_slice(&address.octets());
+                OptionCode::REQUESTED_IP_ADDRESS
+            }
+            DhcpOption::LeaseTime(lease_time) => {
+                write_offset!(buffer, 2, lease_time, u32, to_be);
+                OptionCode::LEASE_TIME
+            }
+            DhcpOption::MessageType(message_type) => {
+                buffer[2] = message_type.value();
+                OptionCode::MESSAGE_TYPE
+            }
+            DhcpOption::ServerIdentifier(address) => {
+                buffer[2..6].copy_from_slice(&address.octets());
+                OptionCode::SERVER_IDENTIFIER
+            }
+            DhcpOption::ParameterRequestList(data) => {
+                buffer[2..len].copy_from_slice(data);
+                OptionCode::PARAMETER_REQUEST_LIST
+            }
+            DhcpOption::Other(code, data) => {
+                buffer[2..len].copy_from_slice(data);
+                code
+            }
+        };
+        buffer[0] = code.value();
+        buffer[1] = (len - 2) as u8;
+        Some(len)
+    }
+}
+
+/// Encodes `options` one after another into the beginning of `buffer`. Returns the number of
+/// bytes written, or `None` if the options do not fit in `buffer` or can't be encoded.
+pub fn write_options(buffer: &mut [u8], options: &[DhcpOption]) -> Option<usize> {
+    let mut offset = 0;
+    for option in options {
+        offset += option.write(&mut buffer[offset..])?;
+    }
+    Some(offset)
+}
+
+/// Iterator over the options in a DHCP message. Pad options are skipped, and iteration stops
+/// after an end option or at the first option that does not fit in the data. Option overloading
+/// into the sname and file fields is not followed.
+#[derive(Clone)]
+pub struct DhcpOptions<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(DhcpOptions<'a>);
+
+impl<'a> DhcpOptions<'a> {
+    /// Creates an iterator over the options encoded in `data`.
+    pub fn new(data: &'a [u8]) -> DhcpOptions<'a> {
+        DhcpOptions { data }
+    }
+}
+
+impl<'a> Iterator for DhcpOptions<'a> {
+    type Item = DhcpOption<'a>;
+
+    fn next(&mut self) -> Option<DhcpOption<'a>> {
+        while self.data.first() == Some(&OptionCode::PAD.value()) {
+            self.data = &self.data[1..];
+        }
+        let code = OptionCode(*self.data.first()?);
+        if code == OptionCode::END {
+            self.data = &[];
+            return Some(DhcpOption::End);
+        }
+        let len = match self.data.get(1) {
+            Some(&len) if 2 + usize::from(len) <= self.data.len() => 2 + usize::from(len),
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (option, rest) = self.data.split_at(len);
+        self.data = rest;
+
+        let data = &option[2..];
+        Some(match (code, data.len()) {
+            (OptionCode::SUBNET_MASK, 4) => DhcpOption::SubnetMask(read_address(data)),
+            (OptionCode::ROUTER, _) if !data.is_empty() => match Ipv4Addresses::new(data) {
+                Some(addresses) => DhcpOption::Router(addresses),
+                None => DhcpOption::Other(code, data),
+            },
+            (OptionCode::DOMAIN_NAME_SERVER, _) if !data.is_empty() => {
+                match Ipv4Addresses::new(data) {
+                    Some(addresses) => DhcpOption::DomainNameServer(addresses),
+                    None => DhcpOption::Other(code, data),
+                }
+            }
+            (OptionCode::HOST_NAME, _) if !data.is_empty() => DhcpOption::HostName(data),
+            (OptionCode::REQUESTED_IP_ADDRESS, 4) => {
+                DhcpOption::RequestedIpAddress(read_address(data))
+            }
+            (OptionCode::LEASE_TIME, 4) => {
+                DhcpOption::LeaseTime(read_offset!(data, 0, u32, from_be))
+            }
+            (OptionCode::MESSAGE_TYPE, 1) => DhcpOption::MessageType(MessageType(data[0])),
+            (OptionCode::SERVER_IDENTIFIER, 4) => DhcpOption::ServerIdentifier(read_address(data)),
+            (OptionCode::PARAMETER_REQUEST_LIST, _) if !data.is_empty() => {
+                DhcpOption::ParameterRequestList(data)
+            }
+            _ => DhcpOption::Other(code, data),
+        })
+    }
+}
+
+fn read_address(data: &[u8]) -> Ipv4Addr {
+    Ipv4Addr::from(read_offset!(data, 0, [u8; 4]))
+}
+
+impl<'a> fmt::Display for DhcpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DHCP op={} xid={:#010x} chaddr={}",
+            self.op().value(),
+            self.xid(),
+            self.chaddr()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static MAC: [u8; 6] = [0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e];
+
+    macro_rules! dhcp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutDhcpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    dhcp_setget_test!(op, set_op, Op::BOOTREPLY, 0, [2]);
+    dhcp_setget_test!(htype, set_htype, 0xab, 1, [0xab]);
+    dhcp_setget_test!(hlen, set_hlen, 0xab, 2, [0xab]);
+    dhcp_setget_test!(hops, set_hops, 0xab, 3, [0xab]);
+    dhcp_setget_test!(xid, set_xid, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 0x01]);
+    dhcp_setget_test!(secs, set_secs, 0xabcd, 8, [0xab, 0xcd]);
+    dhcp_setget_test!(flags, set_flags, Flags::BROADCAST, 10, [0x80, 0x00]);
+    dhcp_setget_test!(ciaddr, set_ciaddr, Ipv4Addr::new(1, 2, 3, 4), 12, [1, 2, 3, 4]);
+    dhcp_setget_test!(yiaddr, set_yiaddr, Ipv4Addr::new(1, 2, 3, 4), 16, [1, 2, 3, 4]);
+    dhcp_setget_test!(siaddr, set_siaddr, Ipv4Addr::new(1, 2, 3, 4), 20, [1, 2, 3, 4]);
+    dhcp_setget_test!(giaddr, set_giaddr, Ipv4Addr::new(1, 2, 3, 4), 24, [1, 2, 3, 4]);
+    dhcp_setget_test!(chaddr, set_chaddr, MacAddr(MAC), 28, MAC);
+    dhcp_setget_test!(magic_cookie, set_magic_cookie, MAGIC_COOKIE, 236, [99, 130, 83, 99]);
+
+    #[test]
+    fn build_and_parse_discover() {
+        let mut data = [0xff; 240 + 32];
+        let options_len = {
+            let mut testee = MutDhcpPacket::new(&mut data).unwrap();
+            testee.set_op(Op::BOOTREQUEST);
+            testee.set_dhcp_defaults();
+            testee.set_chaddr(MacAddr(MAC));
+            testee
+                .set_options(&[
+                    DhcpOption::MessageType(MessageType::DISCOVER),
+                    DhcpOption::RequestedIpAddress(Ipv4Addr::new(192, 168, 1, 100)),
+                    DhcpOption::ParameterRequestList(&[1, 3, 6, 51]),
+                ])
+                .unwrap()
+        };
+        assert_eq!(3 + 6 + 6 + 1, options_len);
+        assert_eq!(&[53, 1, 1, 50, 4, 192, 168, 1, 100], &data[240..249]);
+        assert_eq!(255, data[255]);
+
+        let testee = DhcpPacket::new(&data).unwrap();
+        assert_eq!(1, testee.htype());
+        assert_eq!(6, testee.hlen());
+        assert_eq!(MacAddr(MAC), testee.chaddr());
+        assert_eq!(&[0; 64][..], testee.sname());
+        assert_eq!(Some(MessageType::DISCOVER), testee.message_type());
+        assert_eq!(
+            vec![
+                DhcpOption::MessageType(MessageType::DISCOVER),
+                DhcpOption::RequestedIpAddress(Ipv4Addr::new(192, 168, 1, 100)),
+                DhcpOption::ParameterRequestList(&[1, 3, 6, 51]),
+                DhcpOption::End,
+            ],
+            testee.options().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn offer_options() {
+        let data = [
+            0, 0, // Pad
+            1, 4, 255, 255, 255, 0, // Subnet mask
+            3, 4, 192, 168, 1, 1, // Router
+            6, 8, 8, 8, 8, 8, 8, 8, 4, 4, // DNS servers
+            51, 4, 0, 1, 0x51, 0x80, // Lease time
+            54, 4, 192, 168, 1, 1, // Server identifier
+            12, 0, // Empty host name
+            255, 1, 2, // End and garbage after it
+        ];
+        let options = DhcpOptions::new(&data).collect::<Vec<_>>();
+        assert_eq!(7, options.len());
+        assert_eq!(DhcpOption::SubnetMask(Ipv4Addr::new(255, 255, 255, 0)), options[0]);
+        match options[2] {
+            DhcpOption::DomainNameServer(servers) => assert_eq!(
+                vec![Ipv4Addr::new(8, 8, 8, 8), Ipv4Addr::new(8, 8, 4, 4)],
+                servers.collect::<Vec<_>>()
+            ),
+            option => panic!("Unexpected option {:?}", option),
+        }
+        assert_eq!(DhcpOption::LeaseTime(86400), options[3]);
+        assert_eq!(DhcpOption::Other(OptionCode::HOST_NAME, &[]), options[5]);
+        assert_eq!(DhcpOption::End, options[6]);
+    }
+
+    #[test]
+    fn malformed_options() {
+        let data = [3, 3, 1, 2, 3, 53, 2, 1];
+        assert_eq!(
+            vec![DhcpOption::Other(OptionCode::ROUTER, &[1, 2, 3])],
+            DhcpOptions::new(&data).collect::<Vec<_>>()
+        );
+        assert!(Ipv4Addresses::new(&[1, 2, 3]).is_none());
+        assert_eq!(None, DhcpOption::HostName(&[]).encoded_len());
+        assert_eq!(None, DhcpOption::ParameterRequestList(&[0; 256]).encoded_len());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        DhcpPacketBuf => |packet| packet.magic_cookie() == MAGIC_COOKIE && packet.hlen() == 6
+    );
+}
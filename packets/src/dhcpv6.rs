@@ -0,0 +1,460 @@
+//! The Dynamic Host Configuration Protocol for IPv6, RFC 8415. Only client and server messages
+//! are covered, relay agent messages have a different layout.
+
+use std::fmt;
+use std::net::Ipv6Addr;
+use types::*;
+
+/// The UDP port DHCPv6 clients listen on.
+pub const CLIENT_PORT: u16 = 546;
+
+/// The UDP port DHCPv6 servers and relay agents listen on.
+pub const SERVER_PORT: u16 = 547;
+
+/// The link scoped multicast address clients send their messages to.
+pub const ALL_DHCP_RELAY_AGENTS_AND_SERVERS: Ipv6Addr =
+    Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0x1, 0x2);
+
+packet!(
+    /// A DHCPv6 client or server message. The payload is the options.
+    Dhcpv6Packet,
+    MutDhcpv6Packet,
+    Dhcpv6PacketBuf,
+    4
+);
+
+getters!(Dhcpv6Packet
+    pub fn msg_type(&self) -> MessageType {
+        MessageType(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the 24 bit transaction ID, chosen by the client to match replies to its
+    /// requests.
+    pub fn transaction_id(&self) -> u24 {
+        read_offset!(self.0, 1, u24, from_be)
+    }
+
+    /// Returns an iterator over the options.
+    pub fn options(&self) -> Dhcpv6Options<'a> {
+        Dhcpv6Options::new(&self.0[4..])
+    }
+);
+
+setters!(MutDhcpv6Packet
+    pub fn set_msg_type(&mut self, msg_type: MessageType) {
+        write_offset!(self.0, 0, msg_type.value(), u8);
+    }
+
+    /// Sets the 24 bit transaction ID. The highest byte of `transaction_id` is ignored.
+    pub fn set_transaction_id(&mut self, transaction_id: u24) {
+        write_offset!(self.0, 1, transaction_id, u24, to_be);
+    }
+);
+
+impl<'a> MutDhcpv6Packet<'a> {
+    /// Encodes `options` after the header. Returns the length of the encoded options, or `None`
+    /// if they do not fit in the backing slice or can't be encoded.
+    pub fn set_options(&mut self, options: &[Dhcpv6Option]) -> Option<usize> {
+        write_options(&mut self.0[4..], options)
+    }
+}
+
+
+/// The type of a DHCPv6 message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    pub const SOLICIT: MessageType = MessageType(1);
+    pub const ADVERTISE: MessageType = MessageType(2);
+    pub const REQUEST: MessageType = MessageType(3);
+    pub const CONFIRM: MessageType = MessageType(4);
+    pub const RENEW: MessageType = MessageType(5);
+    pub const REBIND: MessageType = MessageType(6);
+    pub const REPLY: MessageType = MessageType(7);
+    pub const RELEASE: MessageType = MessageType(8);
+    pub const DECLINE: MessageType = MessageType(9);
+    pub const RECONFIGURE: MessageType = MessageType(10);
+    pub const INFORMATION_REQUEST: MessageType = MessageType(11);
+    pub const RELAY_FORW: MessageType = MessageType(12);
+    pub const RELAY_REPL: MessageType = MessageType(13);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns true for the relay agent message types, which do not use the client and server
+    /// message layout.
+    pub fn is_relay(&self) -> bool {
+        *self == MessageType::RELAY_FORW || *self == MessageType::RELAY_REPL
+    }
+}
+
+/// The 16 bit code of a DHCPv6 option.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OptionCode(pub u16);
+
+impl OptionCode {
+    pub const CLIENTID: OptionCode = OptionCode(1);
+    pub const SERVERID: OptionCode = OptionCode(2);
+    pub const IA_NA: OptionCode = OptionCode(3);
+    pub const IA_TA: OptionCode = OptionCode(4);
+    pub const IAADDR: OptionCode = OptionCode(5);
+    pub const ORO: OptionCode = OptionCode(6);
+    pub const PREFERENCE: OptionCode = OptionCode(7);
+    pub const ELAPSED_TIME: OptionCode = OptionCode(8);
+    pub const STATUS_CODE: OptionCode = OptionCode(13);
+    pub const RAPID_COMMIT: OptionCode = OptionCode(14);
+    pub const DNS_SERVERS: OptionCode = OptionCode(23);
+    pub const DOMAIN_LIST: OptionCode = OptionCode(24);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+
+/// The content of an identity association for non-temporary addresses option.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct IaNa<'a> {
+    /// The identity association ID, chosen by the client.
+    pub iaid: u32,
+    /// The time in seconds after which the client should renew its addresses.
+    pub t1: u32,
+    /// The time in seconds after which the client should rebind its addresses.
+    pub t2: u32,
+    /// The encoded options within this option, normally IAADDR options.
+    pub options: &'a [u8],
+}
+
+impl<'a> IaNa<'a> {
+    /// Returns an iterator over the options within this option.
+    pub fn iter_options(&self) -> Dhcpv6Options<'a> {
+        Dhcpv6Options::new(self.options)
+    }
+}
+
+/// The content of an IA address option, giving one address of an identity association.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct IaAddress<'a> {
+    pub address: Ipv6Addr,
+    /// The time in seconds the address remains preferred.
+    pub preferred_lifetime: u32,
+    /// The time in seconds the address remains valid.
+    pub valid_lifetime: u32,
+    /// The encoded options within this option, normally a status code.
+    pub options: &'a [u8],
+}
+
+impl<'a> IaAddress<'a> {
+    /// Returns an iterator over the options within this option.
+    pub fn iter_options(&self) -> Dhcpv6Options<'a> {
+        Dhcpv6Options::new(self.options)
+    }
+}
+
+/// A list of IPv6 addresses in a DHCPv6 option, kept as the encoded bytes.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Ipv6Addresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ipv6Addresses<'a>);
+
+impl<'a> Ipv6Addresses<'a> {
+    /// Creates a list from the encoded addresses in `data`, or returns `None` if the length of
+    /// `data` is not a multiple of 16.
+    pub fn new(data: &'a [u8]) -> Option<Ipv6Addresses<'a>> {
+        if data.len().is_multiple_of(16) {
+            Some(Ipv6Addresses { data })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the encoded addresses.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> Iterator for Ipv6Addresses<'a> {
+    type Item = Ipv6Addr;
+
+    fn next(&mut self) -> Option<Ipv6Addr> {
+        if self.data.len() < 16 {
+            return None;
+        }
+        let (address, rest) = self.data.split_at(16);
+        self.data = rest;
+        Some(Ipv6Addr::from(read_offset!(address, 0, [u8; 16])))
+    }
+}
+
+/// A single option in a DHCPv6 message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Dhcpv6Option<'a> {
+    /// The DUID of the client.
+    ClientId(&'a [u8]),
+    /// The DUID of the server.
+    ServerId(&'a [u8]),
+    IaNa(IaNa<'a>),
+    IaAddress(IaAddress<'a>),
+    /// The time in hundredths of a second since the client began the current exchange.
+    ElapsedTime(u16),
+    /// Recursive DNS servers, in order of preference.
+    DnsServers(Ipv6Addresses<'a>),
+    /// Any other option, or one of the above with an unexpected length. Holds the option data
+    /// following the code and length fields.
+    Other(OptionCode, &'a [u8]),
+}
+
+impl<'a> Dhcpv6Option<'a> {
+    /// Returns the number of bytes this option occupies when encoded, or `None` if its data is
+    /// longer than 65535 bytes.
+    pub fn encoded_len(&self) -> Option<usize> {
+        let data_len = match *self {
+            Dhcpv6Option::ClientId(data) |
+            Dhcpv6Option::ServerId(data) |
+            Dhcpv6Option::Other(_, data) => data.len(),
+            Dhcpv6Option::IaNa(ia_na) => 12 + ia_na.options.len(),
+            Dhcpv6Option::IaAddress(ia_address) => 24 + ia_address.options.len(),
+            Dhcpv6Option::ElapsedTime(_) => 2,
+            Dhcpv6Option::DnsServers(addresses) => addresses.data().len(),
+        };
+        if data_len <= usize::from(u16::MAX) {
+            Some(4 + data_len)
+        } else {
+            None
+        }
+    }
+
+    /// Encodes this option into the beginning of `buffer`. Returns the number of bytes written,
+    /// or `None` if the option does not fit in `buffer` or can't be encoded.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len()?;
+        let buffer = buffer.get_mut(..len)?;
+        let code = match *self {
+            Dhcpv6Option::ClientId(data) => {
+                buffer[4..].copy_from_slice(data);
+                OptionCode::CLIENTID
+            }
+            Dhcpv6Option::ServerId(data) => {
+                buffer[4..].copy_from_slice(data);
+                OptionCode::SERVERID
+            }
+            Dhcpv6Option::IaNa(ia_na) => {
+                write_offset!(buffer, 4, ia_na.iaid, u32, to_be);
+                write_offset!(buffer, 8, ia_na.t1, u32, to_be);
+                write_offset!(buffer, 12, ia_na.t2, u32, to_be);
+                buffer[16..].copy_from_slice(ia_na.options);
+                OptionCode::IA_NA
+            }
+            Dhcpv6Option::IaAddress(ia_address) => {
+                buffer[4..20].copy_from_slice(&ia_address.address.octets());
+                write_offset!(buffer, 20, ia_address.preferred_lifetime, u32, to_be);
+                write_offset!(buffer, 24, ia_address.valid_lifetime, u32, to_be);
+                buffer[28..].copy_from_slice(ia_address.options);
+                OptionCode::IAADDR
+            }
+            Dhcpv6Option::ElapsedTime(elapsed_time) => {
+                write_offset!(buffer, 4, elapsed_time, u16, to_be);
+                OptionCode::ELAPSED_TIME
+            }
+            Dhcpv6Option::DnsServers(addresses) => {
+                buffer[4..].copy_from_slice(addresses.data());
+                OptionCode::DNS_SERVERS
+            }
+            Dhcpv6Option::Other(code, data) => {
+                buffer[4..].copy_from_slice(data);
+                code
+            }
+        };
+        write_offset!(buffer, 0, code.value(), u16, to_be);
+        write_offset!(buffer, 2, (len - 4) as u16, u16, to_be);
+        Some(len)
+    }
+}
+
+/// Encodes `options` one after another into the beginning of `buffer`. Returns the number of
+/// bytes written, or `None` if the options do not fit in `buffer` or can't be encoded.
+pub fn write_options(buffer: &mut [u8], options: &[Dhcpv6Option]) -> Option<usize> {
+    let mut offset = 0;
+    for option in options {
+        offset += option.write(&mut buffer[offset..])?;
+    }
+    Some(offset)
+}
+
+/// Iterator over DHCPv6 options, either those of a message or those nested in another option.
+/// Iteration stops at the first option that does not fit in the data.
+#[derive(Clone)]
+pub struct Dhcpv6Options<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Dhcpv6Options<'a>);
+
+impl<'a> Dhcpv6Options<'a> {
+    /// Creates an iterator over the options encoded in `data`.
+    pub fn new(data: &'a [u8]) -> Dhcpv6Options<'a> {
+        Dhcpv6Options { data }
+    }
+}
+
+impl<'a> Iterator for Dhcpv6Options<'a> {
+    type Item = Dhcpv6Option<'a>;
+
+    fn next(&mut self) -> Option<Dhcpv6Option<'a>> {
+        if self.data.len() < 4 {
+            self.data = &[];
+            return None;
+        }
+        let code = OptionCode(read_offset!(self.data, 0, u16, from_be));
+        let len = 4 + usize::from(read_offset!(self.data, 2, u16, from_be));
+        if len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (option, rest) = self.data.split_at(len);
+        self.data = rest;
+
+        let data = &option[4..];
+        Some(match code {
+            OptionCode::CLIENTID => Dhcpv6Option::ClientId(data),
+            OptionCode::SERVERID => Dhcpv6Option::ServerId(data),
+            OptionCode::IA_NA if data.len() >= 12 => Dhcpv6Option::IaNa(IaNa {
+                iaid: read_offset!(data, 0, u32, from_be),
+                t1: read_offset!(data, 4, u32, from_be),
+                t2: read_offset!(data, 8, u32, from_be),
+                options: &data[12..],
+            }),
+            OptionCode::IAADDR if data.len() >= 24 => Dhcpv6Option::IaAddress(IaAddress {
+                address: Ipv6Addr::from(read_offset!(data, 0, [u8; 16])),
+                preferred_lifetime: read_offset!(data, 16, u32, from_be),
+                valid_lifetime: read_offset!(data, 20, u32, from_be),
+                options: &data[24..],
+            }),
+            OptionCode::ELAPSED_TIME if data.len() == 2 => {
+                Dhcpv6Option::ElapsedTime(read_offset!(data, 0, u16, from_be))
+            }
+            OptionCode::DNS_SERVERS => match Ipv6Addresses::new(data) {
+                Some(addresses) => Dhcpv6Option::DnsServers(addresses),
+                None => Dhcpv6Option::Other(code, data),
+            },
+            _ => Dhcpv6Option::Other(code, data),
+        })
+    }
+}
+
+impl<'a> fmt::Display for Dhcpv6Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DHCPv6 type={} xid={:#08x}",
+            self.msg_type().value(),
+            self.transaction_id()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! dhcpv6_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutDhcpv6Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    dhcpv6_setget_test!(msg_type, set_msg_type, MessageType::REPLY, 0, [7]);
+    dhcpv6_setget_test!(transaction_id, set_transaction_id, 0xabcdef, 1, [0xab, 0xcd, 0xef]);
+
+    #[test]
+    fn transaction_id_keeps_msg_type() {
+        let mut data = [0x01, 0, 0, 0];
+        MutDhcpv6Packet::new(&mut data).unwrap().set_transaction_id(0xffab_cdef);
+        assert_eq!([0x01, 0xab, 0xcd, 0xef], data);
+    }
+
+    #[test]
+    fn build_and_parse_reply() {
+        let duid = [0, 3, 0, 1, 0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e];
+        let address = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x100);
+        let dns = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x53];
+
+        let mut nested = [0; 28];
+        let nested_len = write_options(&mut nested, &[Dhcpv6Option::IaAddress(IaAddress {
+            address,
+            preferred_lifetime: 3600,
+            valid_lifetime: 7200,
+            options: &[],
+        })]).unwrap();
+        assert_eq!(28, nested_len);
+
+        let mut data = [0; 4 + 14 + 44 + 20];
+        let options_len = {
+            let mut testee = MutDhcpv6Packet::new(&mut data).unwrap();
+            testee.set_msg_type(MessageType::REPLY);
+            testee.set_transaction_id(0x123456);
+            testee
+                .set_options(&[
+                    Dhcpv6Option::ServerId(&duid),
+                    Dhcpv6Option::IaNa(IaNa { iaid: 1, t1: 1800, t2: 2880, options: &nested }),
+                    Dhcpv6Option::DnsServers(Ipv6Addresses::new(&dns).unwrap()),
+                ])
+                .unwrap()
+        };
+        assert_eq!(14 + 44 + 20, options_len);
+        assert_eq!(&[0, 2, 0, 10], &data[4..8]);
+        assert_eq!(&[0, 3, 0, 40, 0, 0, 0, 1], &data[18..26]);
+
+        let testee = Dhcpv6Packet::new(&data).unwrap();
+        assert_eq!(MessageType::REPLY, testee.msg_type());
+        assert_eq!(0x123456, testee.transaction_id());
+        let options = testee.options().collect::<Vec<_>>();
+        assert_eq!(3, options.len());
+        assert_eq!(Dhcpv6Option::ServerId(&duid), options[0]);
+        let ia_na = match options[1] {
+            Dhcpv6Option::IaNa(ia_na) => ia_na,
+            option => panic!("Unexpected option {:?}", option),
+        };
+        assert_eq!(1800, ia_na.t1);
+        match ia_na.iter_options().next() {
+            Some(Dhcpv6Option::IaAddress(ia_address)) => {
+                assert_eq!(address, ia_address.address);
+                assert_eq!(7200, ia_address.valid_lifetime);
+            }
+            option => panic!("Unexpected option {:?}", option),
+        }
+        match options[2] {
+            Dhcpv6Option::DnsServers(servers) => {
+                assert_eq!(vec![Ipv6Addr::from(dns)], servers.collect::<Vec<_>>())
+            }
+            option => panic!("Unexpected option {:?}", option),
+        }
+    }
+
+    #[test]
+    fn malformed_options() {
+        let data = [0, 23, 0, 3, 1, 2, 3, 0, 8, 0, 1, 0, 0, 1, 0, 5];
+        assert_eq!(
+            vec![
+                Dhcpv6Option::Other(OptionCode::DNS_SERVERS, &[1, 2, 3]),
+                Dhcpv6Option::Other(OptionCode::ELAPSED_TIME, &[0]),
+            ],
+            Dhcpv6Options::new(&data).collect::<Vec<_>>()
+        );
+        assert!(MessageType::RELAY_FORW.is_relay());
+        assert!(!MessageType::SOLICIT.is_relay());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Dhcpv6PacketBuf => |packet| packet.options().count() <= packet.payload().len() / 4
+    );
+}
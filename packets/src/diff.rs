@@ -0,0 +1,195 @@
+//! Comparison of packets field by field, for tests that check generated packets against golden
+//! captures.
+//!
+//! The fields of a header are given by its field table from the `layout` module, and packet
+//! types with a table have a `diff` method using it. Bytes outside of the fields, like options
+//! and payloads, are compared as ranges of bytes.
+//!
+//! ```rust
+//! use rips_packets::diff::Difference;
+//! use rips_packets::udp::UdpPacket;
+//!
+//! let golden = [0x30, 0x39, 0, 53, 0, 10, 0, 0, b'h', b'i'];
+//! let generated = [0x30, 0x39, 0, 54, 0, 10, 0, 0, b'h', b'o'];
+//! let differences = UdpPacket::new(&golden).unwrap().diff(&UdpPacket::new(&generated).unwrap());
+//! assert_eq!(2, differences.len());
+//! assert_eq!("destination_port: 0x35 != 0x36", differences[0].to_string());
+//! assert_eq!(Difference::Bytes(9..10), differences[1]);
+//! ```
+
+use alloc::vec::Vec;
+use layout::Field;
+use std::cmp;
+use std::fmt;
+use std::ops::Range;
+
+/// A difference found by `diff`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Difference {
+    /// A field holding different values, read as big endian integers.
+    Field { name: &'static str, field: Field, left: u128, right: u128 },
+    /// A range of bytes outside of the fields where every byte differs.
+    Bytes(Range<usize>),
+    /// The packets have different lengths. The bytes beyond the shorter one are not compared.
+    Length { left: usize, right: usize },
+}
+
+impl Difference {
+    /// Returns the offset of the first differing byte, used to order the differences.
+    fn offset(&self) -> usize {
+        match *self {
+            Difference::Field { field, .. } => field.byte_offset(),
+            Difference::Bytes(ref bytes) => bytes.start,
+            Difference::Length { left, right } => cmp::min(left, right),
+        }
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Difference::Field { name, left, right, .. } => {
+                write!(f, "{}: {:#x} != {:#x}", name.to_lowercase(), left, right)
+            }
+            Difference::Bytes(ref bytes) => {
+                write!(f, "bytes {}..{} differ", bytes.start, bytes.end)
+            }
+            Difference::Length { left, right } => write!(f, "length: {} != {}", left, right),
+        }
+    }
+}
+
+/// Compares `left` and `right` as headers with the layout `fields`, such as
+/// `ipv4::fields::NAMED`. Returns the differences ordered by where they start, with a
+/// differing length last.
+///
+/// Fields that lie beyond the end of either slice are compared as bytes instead.
+pub fn diff(left: &[u8], right: &[u8], fields: &[(&'static str, Field)]) -> Vec<Difference> {
+    let len = cmp::min(left.len(), right.len());
+    let mut differences = Vec::new();
+    let mut covered = vec![false; len];
+    for &(name, field) in fields {
+        if field.byte_end() > len {
+            continue;
+        }
+        for byte in &mut covered[field.byte_range()] {
+            *byte = true;
+        }
+        let (left, right) = (read(left, field), read(right, field));
+        if left != right {
+            differences.push(Difference::Field { name, field, left, right });
+        }
+    }
+
+    let mut i = 0;
+    while i < len {
+        let start = i;
+        while i < len && !covered[i] && left[i] != right[i] {
+            i += 1;
+        }
+        if i > start {
+            differences.push(Difference::Bytes(start..i));
+        } else {
+            i += 1;
+        }
+    }
+
+    differences.sort_by_key(Difference::offset);
+    if left.len() != right.len() {
+        differences.push(Difference::Length { left: left.len(), right: right.len() });
+    }
+    differences
+}
+
+/// Reads `field` from `data` as a big endian integer.
+fn read(data: &[u8], field: Field) -> u128 {
+    (field.start..field.end()).fold(0, |value, bit| {
+        (value << 1) | u128::from((data[bit / 8] >> (7 - bit % 8)) & 1)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv4::{self, Ipv4Packet, MutIpv4Packet};
+    use std::net::Ipv4Addr;
+
+    fn packet() -> [u8; 24] {
+        let mut data = [0; 24];
+        data[0] = 0x45;
+        data
+    }
+
+    #[test]
+    fn equal() {
+        let data = packet();
+        let packet = Ipv4Packet::new(&data).unwrap();
+        assert!(packet.diff(&packet).is_empty());
+        assert!(diff(&[1, 2], &[1, 2], &[]).is_empty());
+    }
+
+    #[test]
+    fn fields() {
+        let left = packet();
+        let mut right = packet();
+        {
+            let mut packet = MutIpv4Packet::new(&mut right).unwrap();
+            packet.set_fragment_offset(0x1fff);
+            packet.set_destination(Ipv4Addr::new(10, 0, 0, 2));
+        }
+        let differences = diff(&left, &right, ipv4::fields::NAMED);
+        assert_eq!(
+            vec![
+                Difference::Field {
+                    name: "FRAGMENT_OFFSET",
+                    field: ipv4::fields::FRAGMENT_OFFSET,
+                    left: 0,
+                    right: 0x1fff
+                },
+                Difference::Field {
+                    name: "DESTINATION",
+                    field: ipv4::fields::DESTINATION,
+                    left: 0,
+                    right: 0x0a00_0002
+                },
+            ],
+            differences
+        );
+        assert_eq!("destination: 0x0 != 0xa000002", differences[1].to_string());
+    }
+
+    #[test]
+    fn bytes_and_length() {
+        let left = packet();
+        let mut right = [0; 26];
+        right[..24].copy_from_slice(&left);
+        right[21] = 1;
+        right[22] = 1;
+        right[23] = 1;
+        right[3] = 1;
+        let differences = diff(&left, &right, ipv4::fields::NAMED);
+        assert_eq!(3, differences.len());
+        assert_eq!("total_length: 0x0 != 0x1", differences[0].to_string());
+        assert_eq!(Difference::Bytes(21..24), differences[1]);
+        assert_eq!(Difference::Length { left: 24, right: 26 }, differences[2]);
+        assert_eq!("bytes 21..24 differ", differences[1].to_string());
+    }
+
+    #[test]
+    fn short_data() {
+        let differences = diff(&[0x45, 0, 0x12], &[0x46, 1, 0x34], ipv4::fields::NAMED);
+        assert_eq!(3, differences.len());
+        assert_eq!("header_length: 0x5 != 0x6", differences[0].to_string());
+        assert_eq!("ecn: 0x0 != 0x1", differences[1].to_string());
+        assert_eq!(Difference::Bytes(2..3), differences[2]);
+    }
+
+    #[test]
+    fn read_bits() {
+        let data = [0x12, 0x34, 0x56];
+        assert_eq!(0x2345, read(&data, Field::new(4, 16)));
+        assert_eq!(1, read(&data, Field::new(3, 1)));
+        assert_eq!(0x123456, read(&data, Field::new(0, 24)));
+    }
+}
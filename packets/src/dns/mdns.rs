@@ -0,0 +1,265 @@
+//! Multicast DNS, RFC 6762, and the record types used by DNS-Based Service Discovery, RFC 6763.
+
+use dns::{write_name, DnsBuilder, Flags, Name, Question, RecordClass, RecordType, ResourceRecord};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// The UDP port used by mDNS.
+pub const MDNS_UDP_PORT: u16 = 5353;
+
+/// The IPv4 multicast group mDNS messages are sent to.
+pub const MDNS_IPV4_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// The IPv6 multicast group mDNS messages are sent to.
+pub const MDNS_IPV6_GROUP: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
+
+/// The top bit of the class field. mDNS uses it as the unicast response bit in questions and the
+/// cache flush bit in records.
+const MDNS_BIT: u16 = 0x8000;
+
+impl RecordClass {
+    /// Returns the class with the mDNS unicast response or cache flush bit cleared.
+    pub fn without_mdns_bit(&self) -> RecordClass {
+        RecordClass(self.0 & !MDNS_BIT)
+    }
+
+    /// Returns the class with the mDNS unicast response or cache flush bit set to `bit`.
+    pub fn with_mdns_bit(&self, bit: bool) -> RecordClass {
+        if bit {
+            RecordClass(self.0 | MDNS_BIT)
+        } else {
+            self.without_mdns_bit()
+        }
+    }
+
+    /// Returns true if the mDNS unicast response or cache flush bit is set.
+    pub fn has_mdns_bit(&self) -> bool {
+        self.0 & MDNS_BIT != 0
+    }
+}
+
+impl<'a> Question<'a> {
+    /// Returns true if the QU bit is set, asking for a unicast response in mDNS.
+    pub fn unicast_response(&self) -> bool {
+        self.class.has_mdns_bit()
+    }
+}
+
+impl<'a> ResourceRecord<'a> {
+    /// Returns true if the cache flush bit is set, telling mDNS receivers to replace all cached
+    /// records with the same name, type and class.
+    pub fn cache_flush(&self) -> bool {
+        self.class.has_mdns_bit()
+    }
+
+    /// Returns the content of an SRV record, or `None` for other types or if the data is too
+    /// short.
+    pub fn srv(&self) -> Option<SrvRecord<'a>> {
+        if self.record_type != RecordType::SRV || self.data.len() < 7 {
+            return None;
+        }
+        Some(SrvRecord {
+            priority: read_offset!(self.data, 0, u16, from_be),
+            weight: read_offset!(self.data, 2, u16, from_be),
+            port: read_offset!(self.data, 4, u16, from_be),
+            target: self.data_name(6),
+        })
+    }
+
+    /// Returns an iterator over the strings of a TXT record, or `None` for other types.
+    pub fn txt(&self) -> Option<TxtStrings<'a>> {
+        if self.record_type == RecordType::TXT {
+            Some(TxtStrings::new(self.data))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> DnsBuilder<'a> {
+    /// Creates a builder for an mDNS response, with a zero ID and the authoritative answer
+    /// flag set. See `new`.
+    pub fn new_mdns_response(buffer: &'a mut [u8]) -> Option<DnsBuilder<'a>> {
+        DnsBuilder::new(buffer, 0, Flags::RESPONSE | Flags::AUTHORITATIVE_ANSWER)
+    }
+}
+
+
+/// The content of an SRV record, giving the host and port of a service instance.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SrvRecord<'a> {
+    /// Lower values are preferred.
+    pub priority: u16,
+    /// The relative weight among records with the same priority.
+    pub weight: u16,
+    pub port: u16,
+    /// The host providing the service.
+    pub target: Name<'a>,
+}
+
+/// Encodes the data of an SRV record into the beginning of `buffer`, with the target written
+/// without compression. Returns the number of bytes written, or `None` if the data does not fit
+/// in `buffer` or the target can't be encoded.
+pub fn write_srv_data(
+    buffer: &mut [u8],
+    priority: u16,
+    weight: u16,
+    port: u16,
+    target: &str,
+) -> Option<usize> {
+    let fields = buffer.get_mut(..6)?;
+    write_offset!(fields, 0, priority, u16, to_be);
+    write_offset!(fields, 2, weight, u16, to_be);
+    write_offset!(fields, 4, port, u16, to_be);
+    Some(6 + write_name(&mut buffer[6..], target)?)
+}
+
+/// Encodes `strings` as the data of a TXT record into the beginning of `buffer`. An empty list
+/// is encoded as a single empty string, as TXT records must not be empty. Returns the number of
+/// bytes written, or `None` if the data does not fit in `buffer` or a string is longer than
+/// 255 bytes.
+pub fn write_txt_data(buffer: &mut [u8], strings: &[&[u8]]) -> Option<usize> {
+    if strings.is_empty() {
+        *buffer.get_mut(0)? = 0;
+        return Some(1);
+    }
+    let mut offset = 0;
+    for string in strings {
+        if string.len() > 255 {
+            return None;
+        }
+        let encoded = buffer.get_mut(offset..offset + 1 + string.len())?;
+        encoded[0] = string.len() as u8;
+        encoded[1..].copy_from_slice(string);
+        offset += encoded.len();
+    }
+    Some(offset)
+}
+
+/// Iterator over the strings of a TXT record. For DNS-SD these are `key=value` pairs, see
+/// `get`. Iteration stops at the first string that does not fit in the data.
+#[derive(Clone)]
+pub struct TxtStrings<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(TxtStrings<'a>);
+
+impl<'a> TxtStrings<'a> {
+    /// Creates an iterator over the strings encoded in `data`.
+    pub fn new(data: &'a [u8]) -> TxtStrings<'a> {
+        TxtStrings { data }
+    }
+
+    /// Returns the value of the first DNS-SD attribute named `key`, comparing keys ignoring
+    /// ASCII case. An attribute without an equals sign has an empty value. Returns `None` if
+    /// there is no such attribute.
+    pub fn get(&self, key: &str) -> Option<&'a [u8]> {
+        self.clone().find_map(|string| {
+            let (name, value) = match string.iter().position(|&byte| byte == b'=') {
+                Some(position) => (&string[..position], &string[position + 1..]),
+                None => (string, &[][..]),
+            };
+            if name.eq_ignore_ascii_case(key.as_bytes()) {
+                Some(value)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl<'a> Iterator for TxtStrings<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let len = match self.data.first() {
+            Some(&len) if usize::from(len) < self.data.len() => 1 + usize::from(len),
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (string, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(&string[1..])
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dns::DnsPacket;
+
+    #[test]
+    fn class_bits() {
+        let class = RecordClass::IN.with_mdns_bit(true);
+        assert_eq!(RecordClass(0x8001), class);
+        assert!(class.has_mdns_bit());
+        assert_eq!(RecordClass::IN, class.without_mdns_bit());
+        assert_eq!(RecordClass::IN, class.with_mdns_bit(false));
+    }
+
+    #[test]
+    fn service_discovery_response() {
+        let mut buffer = [0; 256];
+        let len = {
+            let mut data = [0; 64];
+            let mut builder = DnsBuilder::new_mdns_response(&mut buffer).unwrap();
+            let service = "printer._ipp._tcp.local";
+            let ptr_len = write_name(&mut data, service).unwrap();
+            let ptr = &data[..ptr_len];
+            let class = RecordClass::IN;
+            builder.add_answer("_ipp._tcp.local", RecordType::PTR, class, 4500, ptr).unwrap();
+            let srv_len = write_srv_data(&mut data, 0, 0, 631, "printer.local").unwrap();
+            let class = RecordClass::IN.with_mdns_bit(true);
+            builder.add_answer(service, RecordType::SRV, class, 120, &data[..srv_len]).unwrap();
+            let strings: [&[u8]; 3] = [b"txtvers=1", b"Color=T", b"duplex"];
+            let txt_len = write_txt_data(&mut data, &strings).unwrap();
+            builder.add_answer(service, RecordType::TXT, class, 4500, &data[..txt_len]).unwrap();
+            builder.finish()
+        };
+
+        let testee = DnsPacket::new(&buffer[..len]).unwrap();
+        assert_eq!(Flags::RESPONSE | Flags::AUTHORITATIVE_ANSWER, testee.flags());
+        let answers = testee.answers().collect::<Vec<_>>();
+        assert_eq!(3, answers.len());
+        assert!(!answers[0].cache_flush());
+        assert!(answers[0].target().unwrap().matches("printer._ipp._tcp.local"));
+        assert!(answers[0].srv().is_none());
+
+        let srv = answers[1].srv().unwrap();
+        assert!(answers[1].cache_flush());
+        assert_eq!(631, srv.port);
+        assert!(srv.target.matches("printer.local"));
+
+        let txt = answers[2].txt().unwrap();
+        assert_eq!(3, txt.clone().count());
+        assert_eq!(Some(&b"1"[..]), txt.get("TXTVERS"));
+        assert_eq!(Some(&b"T"[..]), txt.get("color"));
+        assert_eq!(Some(&b""[..]), txt.get("duplex"));
+        assert_eq!(None, txt.get("missing"));
+    }
+
+    #[test]
+    fn unicast_question() {
+        let mut buffer = [0; 64];
+        let len = {
+            let mut builder = DnsBuilder::new(&mut buffer, 0, Flags::empty()).unwrap();
+            let class = RecordClass::IN.with_mdns_bit(true);
+            builder.add_question("host.local", RecordType::A, class).unwrap();
+            builder.finish()
+        };
+        let testee = DnsPacket::new(&buffer[..len]).unwrap();
+        assert!(testee.questions().next().unwrap().unicast_response());
+    }
+
+    #[test]
+    fn txt_data() {
+        let mut buffer = [0xff; 4];
+        assert_eq!(Some(1), write_txt_data(&mut buffer, &[]));
+        assert_eq!(vec![&b""[..]], TxtStrings::new(&buffer[..1]).collect::<Vec<_>>());
+        assert_eq!(None, write_txt_data(&mut buffer, &[b"abcd"]));
+        assert_eq!(0, TxtStrings::new(&[0x05, b'a']).count());
+    }
+}
@@ -0,0 +1,894 @@
+//! The Domain Name System, RFC 1035. Names in a message may be compressed by pointing back to an
+//! earlier occurrence, so the views over names and records keep a reference to the whole
+//! message.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use types::*;
+
+mod mdns;
+pub use self::mdns::*;
+
+/// The UDP and TCP port assigned to DNS.
+pub const UDP_PORT: u16 = 53;
+
+packet!(DnsPacket, MutDnsPacket, DnsPacketBuf, 12);
+
+getters!(DnsPacket
+    pub fn id(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 2, u16, from_be))
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        Opcode((read_offset!(self.0, 2, u8) >> 3) & 0x0f)
+    }
+
+    pub fn rcode(&self) -> Rcode {
+        Rcode(read_offset!(self.0, 3, u8) & 0x0f)
+    }
+
+    pub fn question_count(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    pub fn answer_count(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    pub fn authority_count(&self) -> u16 {
+        read_offset!(self.0, 8, u16, from_be)
+    }
+
+    pub fn additional_count(&self) -> u16 {
+        read_offset!(self.0, 10, u16, from_be)
+    }
+
+    /// Returns an iterator over the question section.
+    pub fn questions(&self) -> Questions<'a> {
+        Questions { message: self.0, offset: 12, remaining: self.question_count() }
+    }
+
+    /// Returns an iterator over the answer section. Empty if an earlier section is truncated.
+    pub fn answers(&self) -> ResourceRecords<'a> {
+        self.records(self.questions().end(), self.answer_count())
+    }
+
+    /// Returns an iterator over the authority section. Empty if an earlier section is
+    /// truncated.
+    pub fn authorities(&self) -> ResourceRecords<'a> {
+        let start = self.answers().end();
+        self.records(start, self.authority_count())
+    }
+
+    /// Returns an iterator over the additional section. Empty if an earlier section is
+    /// truncated.
+    pub fn additionals(&self) -> ResourceRecords<'a> {
+        let start = self.authorities().end();
+        self.records(start, self.additional_count())
+    }
+);
+
+impl<'a> DnsPacket<'a> {
+    fn records(&self, start: Option<usize>, count: u16) -> ResourceRecords<'a> {
+        match start {
+            Some(offset) => ResourceRecords { message: self.0, offset, remaining: count },
+            None => ResourceRecords { message: self.0, offset: self.0.len(), remaining: 0 },
+        }
+    }
+}
+
+setters!(MutDnsPacket
+    pub fn set_id(&mut self, id: u16) {
+        write_offset!(self.0, 0, id, u16, to_be);
+    }
+
+    /// Sets the flags, leaving the opcode, rcode and reserved bit untouched.
+    pub fn set_flags(&mut self, flags: Flags) {
+        let other_bits = read_offset!(self.0, 2, u16, from_be) & !Flags::all().bits();
+        write_offset!(self.0, 2, other_bits | flags.bits(), u16, to_be);
+    }
+
+    pub fn set_opcode(&mut self, opcode: Opcode) {
+        let new_byte = (read_offset!(self.0, 2, u8) & 0x87) | ((opcode.value() & 0x0f) << 3);
+        write_offset!(self.0, 2, new_byte, u8);
+    }
+
+    pub fn set_rcode(&mut self, rcode: Rcode) {
+        let new_byte = (read_offset!(self.0, 3, u8) & 0xf0) | (rcode.value() & 0x0f);
+        write_offset!(self.0, 3, new_byte, u8);
+    }
+
+    pub fn set_question_count(&mut self, question_count: u16) {
+        write_offset!(self.0, 4, question_count, u16, to_be);
+    }
+
+    pub fn set_answer_count(&mut self, answer_count: u16) {
+        write_offset!(self.0, 6, answer_count, u16, to_be);
+    }
+
+    pub fn set_authority_count(&mut self, authority_count: u16) {
+        write_offset!(self.0, 8, authority_count, u16, to_be);
+    }
+
+    pub fn set_additional_count(&mut self, additional_count: u16) {
+        write_offset!(self.0, 10, additional_count, u16, to_be);
+    }
+);
+
+
+bitflags! {
+    /// Bitmasks for the single bit flags in the second and third byte of a DNS header.
+    pub struct Flags: u16 {
+        /// A bitmask with a one in the "Query/Response" position, set for responses.
+        const RESPONSE = 0x8000;
+        /// A bitmask with a one in the "Authoritative Answer" position.
+        const AUTHORITATIVE_ANSWER = 0x0400;
+        /// A bitmask with a one in the "Truncation" position.
+        const TRUNCATED = 0x0200;
+        /// A bitmask with a one in the "Recursion Desired" position.
+        const RECURSION_DESIRED = 0x0100;
+        /// A bitmask with a one in the "Recursion Available" position.
+        const RECURSION_AVAILABLE = 0x0080;
+        /// A bitmask with a one in the "Authentic Data" position.
+        const AUTHENTIC_DATA = 0x0020;
+        /// A bitmask with a one in the "Checking Disabled" position.
+        const CHECKING_DISABLED = 0x0010;
+    }
+}
+
+
+/// The 4 bit kind of query in a DNS message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Opcode(pub u4);
+
+impl Opcode {
+    pub const QUERY: Opcode = Opcode(0);
+    pub const STATUS: Opcode = Opcode(2);
+    pub const NOTIFY: Opcode = Opcode(4);
+    pub const UPDATE: Opcode = Opcode(5);
+
+    #[inline]
+    pub fn value(&self) -> u4 {
+        self.0
+    }
+}
+
+/// The 4 bit response code of a DNS message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Rcode(pub u4);
+
+impl Rcode {
+    pub const NO_ERROR: Rcode = Rcode(0);
+    pub const FORMAT_ERROR: Rcode = Rcode(1);
+    pub const SERVER_FAILURE: Rcode = Rcode(2);
+    /// The queried name does not exist, usually called NXDOMAIN.
+    pub const NAME_ERROR: Rcode = Rcode(3);
+    pub const NOT_IMPLEMENTED: Rcode = Rcode(4);
+    pub const REFUSED: Rcode = Rcode(5);
+
+    #[inline]
+    pub fn value(&self) -> u4 {
+        self.0
+    }
+}
+
+/// The 16 bit type of a resource record or question.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RecordType(pub u16);
+
+impl RecordType {
+    pub const A: RecordType = RecordType(1);
+    pub const NS: RecordType = RecordType(2);
+    pub const CNAME: RecordType = RecordType(5);
+    pub const SOA: RecordType = RecordType(6);
+    pub const PTR: RecordType = RecordType(12);
+    pub const MX: RecordType = RecordType(15);
+    pub const TXT: RecordType = RecordType(16);
+    pub const AAAA: RecordType = RecordType(28);
+    pub const SRV: RecordType = RecordType(33);
+    pub const OPT: RecordType = RecordType(41);
+    /// Only valid in questions, asking for records of all types.
+    pub const ANY: RecordType = RecordType(255);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// The 16 bit class of a resource record or question.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RecordClass(pub u16);
+
+impl RecordClass {
+    /// The Internet.
+    pub const IN: RecordClass = RecordClass(1);
+    pub const CH: RecordClass = RecordClass(3);
+    pub const HS: RecordClass = RecordClass(4);
+    /// Only valid in questions, asking for records of all classes.
+    pub const ANY: RecordClass = RecordClass(255);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+
+/// A domain name inside a DNS message. Compression pointers are followed when reading the
+/// labels, as long as they point to an earlier part of the message, which also rules out loops.
+/// Names compare equal if their labels are equal, ignoring ASCII case.
+#[derive(Copy, Clone)]
+pub struct Name<'a> {
+    message: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Name<'a> {
+    /// Creates a view of the name starting at `offset` in `message`. The name is not validated
+    /// until its labels are read.
+    pub fn new(message: &'a [u8], offset: usize) -> Name<'a> {
+        Name { message, offset }
+    }
+
+    /// Returns an iterator over the labels of this name, not including the empty root label.
+    pub fn labels(&self) -> Labels<'a> {
+        Labels { message: self.message, offset: self.offset, limit: self.offset }
+    }
+
+    /// Returns true if this name equals the dotted name `name`, ignoring ASCII case. A single
+    /// trailing dot in `name` is ignored.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.strip_suffix('.').unwrap_or(name);
+        if name.is_empty() {
+            return self.labels().next().is_none();
+        }
+        let mut labels = self.labels();
+        let all_equal = name.split('.').all(|expected| {
+            labels.next().is_some_and(|label| label.eq_ignore_ascii_case(expected.as_bytes()))
+        });
+        all_equal && labels.next().is_none()
+    }
+}
+
+impl<'a, 'b> PartialEq<Name<'b>> for Name<'a> {
+    fn eq(&self, other: &Name<'b>) -> bool {
+        let mut other_labels = other.labels();
+        self.labels().all(|label| {
+            other_labels.next().is_some_and(|other| label.eq_ignore_ascii_case(other))
+        }) && other_labels.next().is_none()
+    }
+}
+
+impl<'a> Eq for Name<'a> {}
+
+impl<'a> fmt::Display for Name<'a> {
+    /// Formats the name as dot separated labels, with the root name formatted as a single dot.
+    /// Dots, backslashes and non-printable bytes inside labels are escaped as `\DDD`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut labels = self.labels().peekable();
+        if labels.peek().is_none() {
+            return write!(f, ".");
+        }
+        let mut first = true;
+        for label in labels {
+            if !first {
+                write!(f, ".")?;
+            }
+            first = false;
+            for &byte in label {
+                if byte.is_ascii_graphic() && byte != b'.' && byte != b'\\' {
+                    write!(f, "{}", byte as char)?;
+                } else {
+                    write!(f, "\\{:03}", byte)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for Name<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Name(\"{}\")", self)
+    }
+}
+
+/// Iterator over the labels of a name, following compression pointers. Iteration stops at the
+/// root label, or at the first label that is truncated, of an unknown kind or a pointer that
+/// does not point before the target of the previous pointer.
+#[derive(Clone)]
+pub struct Labels<'a> {
+    message: &'a [u8],
+    offset: usize,
+    /// Pointers must point before this offset, which shrinks with every pointer followed.
+    limit: usize,
+}
+
+debug_items!(Labels<'a>);
+
+impl<'a> Iterator for Labels<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        loop {
+            let len = match self.message.get(self.offset) {
+                Some(&len) => len,
+                None => return None,
+            };
+            let label = match len & 0xc0 {
+                0x00 if len > 0 => {
+                    let start = self.offset + 1;
+                    self.message.get(start..start + usize::from(len)).map(|label| (start, label))
+                }
+                0xc0 => {
+                    let pointer = self.message.get(self.offset..self.offset + 2).map(|pointer| {
+                        usize::from(read_offset!(pointer, 0, u16, from_be) & 0x3fff)
+                    });
+                    match pointer {
+                        Some(pointer) if pointer < self.limit => {
+                            self.offset = pointer;
+                            self.limit = pointer;
+                            continue;
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            return match label {
+                Some((start, label)) => {
+                    self.offset = start + label.len();
+                    Some(label)
+                }
+                None => {
+                    self.offset = self.message.len();
+                    None
+                }
+            };
+        }
+    }
+}
+
+/// Returns the offset right after the name starting at `offset`, without following compression
+/// pointers, or `None` if the name is truncated or uses an unknown label kind.
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)?;
+        match len & 0xc0 {
+            0x00 if len == 0 => return Some(offset + 1),
+            0x00 => offset += 1 + usize::from(len),
+            0xc0 if offset + 2 <= message.len() => return Some(offset + 2),
+            _ => return None,
+        }
+    }
+}
+
+/// Encodes the dotted name `name` into the beginning of `buffer`, without compression. Returns
+/// the number of bytes written, or `None` if the name does not fit in `buffer`, has an empty
+/// label or a label longer than 63 bytes, or is longer than 255 bytes when encoded. A single
+/// trailing dot is ignored, and an empty name or a single dot is the root name.
+pub fn write_name(buffer: &mut [u8], name: &str) -> Option<usize> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let mut offset = 0;
+    if !name.is_empty() {
+        for label in name.split('.') {
+            let len = label.len();
+            if len == 0 || len > 63 {
+                return None;
+            }
+            let encoded = buffer.get_mut(offset..offset + 1 + len)?;
+            encoded[0] = len as u8;
+            encoded[1..].copy_from_slice(label.as_bytes());
+            offset += 1 + len;
+        }
+    }
+    *buffer.get_mut(offset)? = 0;
+    offset += 1;
+    if offset <= 255 {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+
+/// A single entry in the question section.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Question<'a> {
+    pub name: Name<'a>,
+    pub record_type: RecordType,
+    pub class: RecordClass,
+}
+
+/// Iterator over the question section of a DNS message. Iteration stops after the number of
+/// questions given in the header, or at the first question that does not fit in the message.
+#[derive(Clone)]
+pub struct Questions<'a> {
+    message: &'a [u8],
+    offset: usize,
+    remaining: u16,
+}
+
+debug_items!(Questions<'a>);
+
+impl<'a> Questions<'a> {
+    /// Returns the offset following the last question, or `None` if a question is truncated.
+    fn end(mut self) -> Option<usize> {
+        while self.remaining > 0 {
+            self.next()?;
+        }
+        Some(self.offset)
+    }
+}
+
+impl<'a> Iterator for Questions<'a> {
+    type Item = Question<'a>;
+
+    fn next(&mut self) -> Option<Question<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let question = skip_name(self.message, self.offset).and_then(|end| {
+            let fields = self.message.get(end..end + 4)?;
+            let question = Question {
+                name: Name::new(self.message, self.offset),
+                record_type: RecordType(read_offset!(fields, 0, u16, from_be)),
+                class: RecordClass(read_offset!(fields, 2, u16, from_be)),
+            };
+            Some((end + 4, question))
+        });
+        match question {
+            Some((end, question)) => {
+                self.offset = end;
+                self.remaining -= 1;
+                Some(question)
+            }
+            None => {
+                self.remaining = 0;
+                None
+            }
+        }
+    }
+}
+
+
+/// A single resource record in the answer, authority or additional section.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ResourceRecord<'a> {
+    pub name: Name<'a>,
+    pub record_type: RecordType,
+    pub class: RecordClass,
+    /// The number of seconds the record may be cached.
+    pub ttl: u32,
+    /// The record data, whose format depends on the type and class.
+    pub data: &'a [u8],
+    message: &'a [u8],
+    data_offset: usize,
+}
+
+impl<'a> ResourceRecord<'a> {
+    /// Returns the name starting at `offset` within the record data. Names in the record data
+    /// may be compressed, so they are read relative to the whole message.
+    pub fn data_name(&self, offset: usize) -> Name<'a> {
+        Name::new(self.message, self.data_offset + offset)
+    }
+
+    /// Returns the address of an A or AAAA record in the Internet class, or `None` for other
+    /// records or if the data has the wrong length.
+    pub fn address(&self) -> Option<IpAddr> {
+        if self.class != RecordClass::IN {
+            return None;
+        }
+        match (self.record_type, self.data.len()) {
+            (RecordType::A, 4) => {
+                Some(IpAddr::V4(Ipv4Addr::from(read_offset!(self.data, 0, [u8; 4]))))
+            }
+            (RecordType::AAAA, 16) => {
+                Some(IpAddr::V6(Ipv6Addr::from(read_offset!(self.data, 0, [u8; 16]))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the name held by an NS, CNAME or PTR record, or `None` for other types.
+    pub fn target(&self) -> Option<Name<'a>> {
+        match self.record_type {
+            RecordType::NS | RecordType::CNAME | RecordType::PTR => Some(self.data_name(0)),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the resource records of a section in a DNS message. Iteration stops after the
+/// number of records given in the header, or at the first record that does not fit in the
+/// message.
+#[derive(Clone)]
+pub struct ResourceRecords<'a> {
+    message: &'a [u8],
+    offset: usize,
+    remaining: u16,
+}
+
+debug_items!(ResourceRecords<'a>);
+
+impl<'a> ResourceRecords<'a> {
+    /// Returns the offset following the last record, or `None` if a record is truncated.
+    fn end(mut self) -> Option<usize> {
+        while self.remaining > 0 {
+            self.next()?;
+        }
+        Some(self.offset)
+    }
+}
+
+impl<'a> Iterator for ResourceRecords<'a> {
+    type Item = ResourceRecord<'a>;
+
+    fn next(&mut self) -> Option<ResourceRecord<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let message = self.message;
+        let record = skip_name(message, self.offset).and_then(|end| {
+            let fields = message.get(end..end + 10)?;
+            let data_offset = end + 10;
+            let data_len = usize::from(read_offset!(fields, 8, u16, from_be));
+            let record = ResourceRecord {
+                name: Name::new(message, self.offset),
+                record_type: RecordType(read_offset!(fields, 0, u16, from_be)),
+                class: RecordClass(read_offset!(fields, 2, u16, from_be)),
+                ttl: read_offset!(fields, 4, u32, from_be),
+                data: message.get(data_offset..data_offset + data_len)?,
+                message,
+                data_offset,
+            };
+            Some((data_offset + data_len, record))
+        });
+        match record {
+            Some((end, record)) => {
+                self.offset = end;
+                self.remaining -= 1;
+                Some(record)
+            }
+            None => {
+                self.remaining = 0;
+                None
+            }
+        }
+    }
+}
+
+
+/// Writes a DNS message into a buffer. Entries must be added section by section, in the order
+/// questions, answers, authorities and additionals, and the counts in the header are kept up to
+/// date. Names are written without compression.
+#[derive(Debug)]
+pub struct DnsBuilder<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+    section: usize,
+}
+
+impl<'a> DnsBuilder<'a> {
+    /// Creates a builder writing to the beginning of `buffer`, starting with a header holding
+    /// `id` and `flags`, the query opcode and zero counts. Returns `None` if the header does not
+    /// fit in `buffer`.
+    pub fn new(buffer: &'a mut [u8], id: u16, flags: Flags) -> Option<DnsBuilder<'a>> {
+        {
+            let mut header = MutDnsPacket::new(buffer)?;
+            for byte in &mut header.data()[..12] {
+                *byte = 0;
+            }
+            header.set_id(id);
+            header.set_flags(flags);
+        }
+        Some(DnsBuilder { buffer, len: 12, section: 0 })
+    }
+
+    /// Creates a builder for a standard query with recursion desired. See `new`.
+    pub fn new_query(buffer: &'a mut [u8], id: u16) -> Option<DnsBuilder<'a>> {
+        DnsBuilder::new(buffer, id, Flags::RECURSION_DESIRED)
+    }
+
+    /// Returns the header for modification, for example to set the opcode or rcode.
+    pub fn header(&mut self) -> MutDnsPacket<'_> {
+        MutDnsPacket(&mut self.buffer[..12])
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always false, since the header is written when the builder is created.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Appends a question. Returns the number of bytes written so far, or `None` if the
+    /// question does not fit in the rest of the buffer, the name can't be encoded, a record
+    /// has already been added or the question count would overflow.
+    pub fn add_question(
+        &mut self,
+        name: &str,
+        record_type: RecordType,
+        class: RecordClass,
+    ) -> Option<usize> {
+        self.start_entry(0)?;
+        let name_len = write_name(&mut self.buffer[self.len..], name)?;
+        let fields = self.buffer.get_mut(self.len + name_len..self.len + name_len + 4)?;
+        write_offset!(fields, 0, record_type.value(), u16, to_be);
+        write_offset!(fields, 2, class.value(), u16, to_be);
+        self.finish_entry(0, name_len + 4)
+    }
+
+    /// Appends a record to the answer section. See `add_additional`.
+    pub fn add_answer(
+        &mut self,
+        name: &str,
+        record_type: RecordType,
+        class: RecordClass,
+        ttl: u32,
+        data: &[u8],
+    ) -> Option<usize> {
+        self.add_record(1, name, record_type, class, ttl, data)
+    }
+
+    /// Appends a record to the authority section. See `add_additional`.
+    pub fn add_authority(
+        &mut self,
+        name: &str,
+        record_type: RecordType,
+        class: RecordClass,
+        ttl: u32,
+        data: &[u8],
+    ) -> Option<usize> {
+        self.add_record(2, name, record_type, class, ttl, data)
+    }
+
+    /// Appends a record to the additional section. Returns the number of bytes written so far,
+    /// or `None` if the record does not fit in the rest of the buffer, the name can't be
+    /// encoded, the data is longer than 65535 bytes, a record in a later section has already
+    /// been added or the count of the section would overflow.
+    pub fn add_additional(
+        &mut self,
+        name: &str,
+        record_type: RecordType,
+        class: RecordClass,
+        ttl: u32,
+        data: &[u8],
+    ) -> Option<usize> {
+        self.add_record(3, name, record_type, class, ttl, data)
+    }
+
+    /// Returns the total length of the message.
+    pub fn finish(self) -> usize {
+        self.len
+    }
+
+    fn add_record(
+        &mut self,
+        section: usize,
+        name: &str,
+        record_type: RecordType,
+        class: RecordClass,
+        ttl: u32,
+        data: &[u8],
+    ) -> Option<usize> {
+        self.start_entry(section)?;
+        if data.len() > usize::from(u16::MAX) {
+            return None;
+        }
+        let name_len = write_name(&mut self.buffer[self.len..], name)?;
+        let start = self.len + name_len;
+        let record = self.buffer.get_mut(start..start + 10 + data.len())?;
+        write_offset!(record, 0, record_type.value(), u16, to_be);
+        write_offset!(record, 2, class.value(), u16, to_be);
+        write_offset!(record, 4, ttl, u32, to_be);
+        write_offset!(record, 8, data.len() as u16, u16, to_be);
+        record[10..].copy_from_slice(data);
+        self.finish_entry(section, name_len + 10 + data.len())
+    }
+
+    fn start_entry(&mut self, section: usize) -> Option<()> {
+        let count = read_offset!(self.buffer, 4 + section * 2, u16, from_be);
+        if section < self.section || count == u16::MAX {
+            None
+        } else {
+            Some(())
+        }
+    }
+
+    fn finish_entry(&mut self, section: usize, len: usize) -> Option<usize> {
+        let count_offset = 4 + section * 2;
+        let count = read_offset!(self.buffer, count_offset, u16, from_be);
+        write_offset!(self.buffer, count_offset, count + 1, u16, to_be);
+        self.section = section;
+        self.len += len;
+        Some(self.len)
+    }
+}
+
+impl<'a> fmt::Display for DnsPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DNS id={:#06x} opcode={} rcode={} qd={} an={} ns={} ar={}",
+            self.id(),
+            self.opcode().value(),
+            self.rcode().value(),
+            self.question_count(),
+            self.answer_count(),
+            self.authority_count(),
+            self.additional_count()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! dns_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutDnsPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    dns_setget_test!(id, set_id, 0xabcd, 0, [0xab, 0xcd]);
+    dns_setget_test!(flags, set_flags, Flags::all(), 2, [0x87, 0xb0]);
+    dns_setget_test!(opcode, set_opcode, Opcode(0xf), 2, [0x78]);
+    dns_setget_test!(rcode, set_rcode, Rcode(0xf), 3, [0x0f]);
+    dns_setget_test!(question_count, set_question_count, 0xabcd, 4, [0xab, 0xcd]);
+    dns_setget_test!(answer_count, set_answer_count, 0xabcd, 6, [0xab, 0xcd]);
+    dns_setget_test!(authority_count, set_authority_count, 0xabcd, 8, [0xab, 0xcd]);
+    dns_setget_test!(additional_count, set_additional_count, 0xabcd, 10, [0xab, 0xcd]);
+
+    /// A response for the A record of www.example.com, with the answer being a CNAME to
+    /// example.com and its A record, both using compressed names.
+    static RESPONSE: [u8; 73] = [
+        0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // Header
+        0x03, b'w', b'w', b'w', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o',
+        b'm', 0x00, 0x00, 0x01, 0x00, 0x01, // Question www.example.com A IN
+        0xc0, 0x0c, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x02, 0xc0, 0x10,
+        // www.example.com CNAME example.com, TTL 3600
+        0xc0, 0x10, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x04, 93, 184, 216, 34,
+        // example.com A 93.184.216.34
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Padding
+    ];
+
+    #[test]
+    fn parse_response() {
+        let testee = DnsPacket::new(&RESPONSE).unwrap();
+        assert_eq!(0x1234, testee.id());
+        assert_eq!(
+            Flags::RESPONSE | Flags::RECURSION_DESIRED | Flags::RECURSION_AVAILABLE,
+            testee.flags()
+        );
+        assert_eq!(Opcode::QUERY, testee.opcode());
+        assert_eq!(Rcode::NO_ERROR, testee.rcode());
+
+        let questions = testee.questions().collect::<Vec<_>>();
+        assert_eq!(1, questions.len());
+        assert!(questions[0].name.matches("WWW.example.com."));
+        assert_eq!(RecordType::A, questions[0].record_type);
+        assert_eq!(RecordClass::IN, questions[0].class);
+
+        let answers = testee.answers().collect::<Vec<_>>();
+        assert_eq!(2, answers.len());
+        assert_eq!(questions[0].name, answers[0].name);
+        assert_eq!(RecordType::CNAME, answers[0].record_type);
+        assert_eq!(3600, answers[0].ttl);
+        assert_eq!("example.com", answers[0].target().unwrap().to_string());
+        assert_eq!(None, answers[0].address());
+        assert_eq!(answers[0].target().unwrap(), answers[1].name);
+        assert_eq!(
+            Some(IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34))),
+            answers[1].address()
+        );
+        assert_eq!(0, testee.authorities().count());
+        assert_eq!(0, testee.additionals().count());
+    }
+
+    #[test]
+    fn truncated_sections() {
+        let testee = DnsPacket::new(&RESPONSE[..60]).unwrap();
+        assert_eq!(1, testee.questions().count());
+        assert_eq!(1, testee.answers().count());
+
+        let mut data = RESPONSE;
+        data[11] = 1;
+        let testee = DnsPacket::new(&data[..60]).unwrap();
+        assert_eq!(0, testee.additionals().count());
+    }
+
+    #[test]
+    fn name_display_and_escaping() {
+        let message = [0x03, b'a', b'.', 0xff, 0x00, 0x00];
+        assert_eq!("a\\046\\255", Name::new(&message, 0).to_string());
+        assert_eq!(".", Name::new(&message, 4).to_string());
+        assert!(Name::new(&message, 4).matches("."));
+        assert!(Name::new(&message, 4).matches(""));
+    }
+
+    #[test]
+    fn pointer_loops_stop() {
+        let message = [0x01, b'a', 0xc0, 0x00, 0xc0, 0x04];
+        assert_eq!("a", Name::new(&message, 2).to_string());
+        assert_eq!(0, Name::new(&message, 4).labels().count());
+        assert_eq!(Some(4), skip_name(&message, 0));
+        assert_eq!(None, skip_name(&message[..3], 0));
+    }
+
+    #[test]
+    fn write_names() {
+        let mut buffer = [0xff; 16];
+        assert_eq!(Some(13), write_name(&mut buffer, "example.com."));
+        assert_eq!(&RESPONSE[16..29], &buffer[..13]);
+        assert_eq!(Some(1), write_name(&mut buffer, "."));
+        assert_eq!(None, write_name(&mut buffer, "a..b"));
+        assert_eq!(None, write_name(&mut buffer, "example.community"));
+        let long_label = "a".repeat(64);
+        assert_eq!(None, write_name(&mut [0; 80], &long_label));
+    }
+
+    #[test]
+    fn build_query() {
+        let mut buffer = [0; 64];
+        let len = {
+            let mut builder = DnsBuilder::new_query(&mut buffer, 0x1234).unwrap();
+            builder.add_question("www.example.com", RecordType::A, RecordClass::IN).unwrap();
+            builder
+                .add_additional("", RecordType::OPT, RecordClass(1232), 0, &[])
+                .unwrap();
+            assert_eq!(
+                None,
+                builder.add_question("example.com", RecordType::A, RecordClass::IN)
+            );
+            builder.finish()
+        };
+        assert_eq!(33 + 11, len);
+        assert_eq!(&[0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00], &buffer[..8]);
+        assert_eq!(&RESPONSE[12..33], &buffer[12..33]);
+
+        let testee = DnsPacket::new(&buffer[..len]).unwrap();
+        assert_eq!(1, testee.additional_count());
+        let additional = testee.additionals().next().unwrap();
+        assert_eq!(RecordType::OPT, additional.record_type);
+        assert!(additional.name.matches("."));
+    }
+
+    #[test]
+    fn build_response() {
+        let mut buffer = [0; 64];
+        let len = {
+            let mut builder = DnsBuilder::new(&mut buffer, 1, Flags::RESPONSE).unwrap();
+            builder.header().set_rcode(Rcode::NAME_ERROR);
+            builder.add_answer("a", RecordType::A, RecordClass::IN, 60, &[10, 0, 0, 1]).unwrap();
+            let too_long = [0; 64];
+            assert_eq!(
+                None,
+                builder.add_answer("a", RecordType::TXT, RecordClass::IN, 0, &too_long)
+            );
+            builder.finish()
+        };
+        let testee = DnsPacket::new(&buffer[..len]).unwrap();
+        assert_eq!(Rcode::NAME_ERROR, testee.rcode());
+        assert_eq!(1, testee.answer_count());
+        assert_eq!(
+            Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+            testee.answers().next().unwrap().address()
+        );
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        DnsPacketBuf => |packet| packet.questions().count() <= usize::from(packet.question_count())
+    );
+}
@@ -0,0 +1,378 @@
+//! EAP over LAN, IEEE 802.1X. Carries EAP between a supplicant and an authenticator, and the
+//! EAPOL-Key frames of the IEEE 802.11 four-way handshake.
+
+use std::cmp;
+use std::fmt;
+use types::*;
+
+packet!(
+    EapolPacket,
+    MutEapolPacket,
+    EapolPacketBuf,
+    4,
+    |_data| 4,
+    len |data| 4 + usize::from(read_offset!(data, 2, u16, from_be)),
+    set_len |data, len| {
+        len - 4 <= usize::from(u16::MAX) && {
+            write_offset!(data, 2, (len - 4) as u16, u16, to_be);
+            true
+        }
+    },
+    init |data| {
+        write_offset!(data, 0, 2u8, u8);
+        MutEapolPacket::set_len(data, data.len());
+    }
+);
+
+getters!(EapolPacket
+    pub fn version(&self) -> u8 {
+        read_offset!(self.0, 0, u8)
+    }
+
+    pub fn packet_type(&self) -> EapolType {
+        EapolType(read_offset!(self.0, 1, u8))
+    }
+
+    /// Returns the length of the packet body, not including the EAPOL header.
+    pub fn body_length(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the part of the payload given by the body length field, without any Ethernet
+    /// padding. Truncated if the length field is longer than the backing slice.
+    pub fn body(&self) -> &'a [u8] {
+        let end = cmp::min(4 + usize::from(self.body_length()), self.0.len());
+        &self.0[4..end]
+    }
+
+    /// Returns the body as an EAPOL-Key descriptor, or `None` if this is not a key packet or
+    /// the body is too short.
+    pub fn key(&self) -> Option<EapolKeyPacket<'a>> {
+        if self.packet_type() == EapolType::KEY {
+            EapolKeyPacket::new(self.body())
+        } else {
+            None
+        }
+    }
+);
+
+setters!(MutEapolPacket
+    pub fn set_version(&mut self, version: u8) {
+        write_offset!(self.0, 0, version, u8);
+    }
+
+    pub fn set_packet_type(&mut self, packet_type: EapolType) {
+        write_offset!(self.0, 1, packet_type.value(), u8);
+    }
+
+    pub fn set_body_length(&mut self, body_length: u16) {
+        write_offset!(self.0, 2, body_length, u16, to_be);
+    }
+);
+
+
+packet!(
+    /// An EAPOL-Key descriptor, as used by the IEEE 802.11 four-way and group key handshakes. The
+    /// header covers the fixed fields up to and including the key data length, so the payload
+    /// is the key data. This layout assumes a 16 byte MIC, which holds for all but the AKM
+    /// suites using 24 byte MICs.
+    EapolKeyPacket,
+    MutEapolKeyPacket,
+    EapolKeyPacketBuf,
+    95,
+    |_data| 95,
+    init |data| write_offset!(data, 93, (data.len() - 95) as u16, u16, to_be)
+);
+
+getters!(EapolKeyPacket
+    pub fn descriptor_type(&self) -> KeyDescriptorType {
+        KeyDescriptorType(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the flags of the key information field.
+    pub fn key_information(&self) -> KeyInformation {
+        KeyInformation::from_bits_truncate(read_offset!(self.0, 1, u16, from_be))
+    }
+
+    /// Returns the key descriptor version in the lowest three bits of the key information
+    /// field, telling which algorithms are used for the MIC and key data encryption.
+    pub fn key_descriptor_version(&self) -> u3 {
+        read_offset!(self.0, 2, u8) & 0x07
+    }
+
+    /// Returns the length of the pairwise temporal key in bytes.
+    pub fn key_length(&self) -> u16 {
+        read_offset!(self.0, 3, u16, from_be)
+    }
+
+    pub fn key_replay_counter(&self) -> u64 {
+        read_offset!(self.0, 5, u64, from_be)
+    }
+
+    pub fn key_nonce(&self) -> [u8; 32] {
+        read_offset!(self.0, 13, [u8; 32])
+    }
+
+    pub fn key_iv(&self) -> [u8; 16] {
+        read_offset!(self.0, 45, [u8; 16])
+    }
+
+    /// Returns the receive sequence counter of the group key, as raw bytes.
+    pub fn key_rsc(&self) -> [u8; 8] {
+        read_offset!(self.0, 61, [u8; 8])
+    }
+
+    pub fn key_mic(&self) -> [u8; 16] {
+        read_offset!(self.0, 77, [u8; 16])
+    }
+
+    pub fn key_data_length(&self) -> u16 {
+        read_offset!(self.0, 93, u16, from_be)
+    }
+
+    /// Returns the key data, as given by the key data length field. Truncated if the length
+    /// field is longer than the backing slice.
+    pub fn key_data(&self) -> &'a [u8] {
+        let end = cmp::min(95 + usize::from(self.key_data_length()), self.0.len());
+        &self.0[95..end]
+    }
+);
+
+setters!(MutEapolKeyPacket
+    pub fn set_descriptor_type(&mut self, descriptor_type: KeyDescriptorType) {
+        write_offset!(self.0, 0, descriptor_type.value(), u8);
+    }
+
+    /// Sets the flags of the key information field, leaving the key descriptor version
+    /// untouched.
+    pub fn set_key_information(&mut self, key_information: KeyInformation) {
+        let version = read_offset!(self.0, 1, u16, from_be) & 0x0007;
+        write_offset!(self.0, 1, key_information.bits() | version, u16, to_be);
+    }
+
+    pub fn set_key_descriptor_version(&mut self, key_descriptor_version: u3) {
+        let new_byte = (read_offset!(self.0, 2, u8) & 0xf8) | (key_descriptor_version & 0x07);
+        write_offset!(self.0, 2, new_byte, u8);
+    }
+
+    pub fn set_key_length(&mut self, key_length: u16) {
+        write_offset!(self.0, 3, key_length, u16, to_be);
+    }
+
+    pub fn set_key_replay_counter(&mut self, key_replay_counter: u64) {
+        write_offset!(self.0, 5, key_replay_counter, u64, to_be);
+    }
+
+    pub fn set_key_nonce(&mut self, key_nonce: [u8; 32]) {
+        write_offset!(self.0, 13, key_nonce, [u8; 32]);
+    }
+
+    pub fn set_key_iv(&mut self, key_iv: [u8; 16]) {
+        write_offset!(self.0, 45, key_iv, [u8; 16]);
+    }
+
+    pub fn set_key_rsc(&mut self, key_rsc: [u8; 8]) {
+        write_offset!(self.0, 61, key_rsc, [u8; 8]);
+    }
+
+    pub fn set_key_mic(&mut self, key_mic: [u8; 16]) {
+        write_offset!(self.0, 77, key_mic, [u8; 16]);
+    }
+
+    pub fn set_key_data_length(&mut self, key_data_length: u16) {
+        write_offset!(self.0, 93, key_data_length, u16, to_be);
+    }
+);
+
+
+/// The type of an EAPOL packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct EapolType(pub u8);
+
+impl EapolType {
+    pub const EAP_PACKET: EapolType = EapolType(0);
+    pub const START: EapolType = EapolType(1);
+    pub const LOGOFF: EapolType = EapolType(2);
+    pub const KEY: EapolType = EapolType(3);
+    pub const ENCAPSULATED_ASF_ALERT: EapolType = EapolType(4);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The descriptor type of an EAPOL-Key packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct KeyDescriptorType(pub u8);
+
+impl KeyDescriptorType {
+    /// IEEE 802.11 RSN, used by WPA2 and WPA3.
+    pub const RSN: KeyDescriptorType = KeyDescriptorType(2);
+    /// The pre-standard descriptor used by WPA.
+    pub const WPA: KeyDescriptorType = KeyDescriptorType(254);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+bitflags! {
+    /// Bitmasks for the key information field of an EAPOL-Key packet. The lowest three bits hold
+    /// the key descriptor version and are not covered by these flags.
+    pub struct KeyInformation: u16 {
+        /// A bitmask with a one in the "Key Type" position, set for pairwise keys.
+        const PAIRWISE = 0x0008;
+        /// A bitmask with a one in the "Install" position.
+        const INSTALL = 0x0040;
+        /// A bitmask with a one in the "Key Ack" position.
+        const ACK = 0x0080;
+        /// A bitmask with a one in the "Key MIC" position.
+        const MIC = 0x0100;
+        /// A bitmask with a one in the "Secure" position.
+        const SECURE = 0x0200;
+        /// A bitmask with a one in the "Error" position.
+        const ERROR = 0x0400;
+        /// A bitmask with a one in the "Request" position.
+        const REQUEST = 0x0800;
+        /// A bitmask with a one in the "Encrypted Key Data" position.
+        const ENCRYPTED_KEY_DATA = 0x1000;
+        /// A bitmask with a one in the "SMK Message" position.
+        const SMK_MESSAGE = 0x2000;
+    }
+}
+
+impl<'a> fmt::Display for EapolPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "EAPOL version={} type={} len={}",
+            self.version(),
+            self.packet_type().value(),
+            self.body_length()
+        )
+    }
+}
+
+impl<'a> fmt::Display for EapolKeyPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "EAPOL-Key type={} info={:#06x} replay={}",
+            self.descriptor_type().value(),
+            self.key_information().bits(),
+            self.key_replay_counter()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! eapol_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutEapolPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    eapol_setget_test!(version, set_version, 0xab, 0, [0xab]);
+    eapol_setget_test!(packet_type, set_packet_type, EapolType::KEY, 1, [0x03]);
+    eapol_setget_test!(body_length, set_body_length, 0xabcd, 2, [0xab, 0xcd]);
+
+    mod key {
+        use super::super::*;
+
+        macro_rules! key_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutEapolKeyPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        key_setget_test!(descriptor_type, set_descriptor_type, KeyDescriptorType::RSN, 0, [2]);
+        key_setget_test!(
+            key_information,
+            set_key_information,
+            KeyInformation::all(),
+            1,
+            [0x3f, 0xc8]
+        );
+        key_setget_test!(key_descriptor_version, set_key_descriptor_version, 0x07, 2, [0x07]);
+        key_setget_test!(key_length, set_key_length, 0xabcd, 3, [0xab, 0xcd]);
+        key_setget_test!(
+            key_replay_counter,
+            set_key_replay_counter,
+            0x0102030405060708,
+            5,
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        key_setget_test!(key_nonce, set_key_nonce, [0xab; 32], 13, [0xab; 32]);
+        key_setget_test!(key_iv, set_key_iv, [0xab; 16], 45, [0xab; 16]);
+        key_setget_test!(key_rsc, set_key_rsc, [0xab; 8], 61, [0xab; 8]);
+        key_setget_test!(key_mic, set_key_mic, [0xab; 16], 77, [0xab; 16]);
+        key_setget_test!(key_data_length, set_key_data_length, 0xabcd, 93, [0xab, 0xcd]);
+    }
+
+    #[test]
+    fn handshake_message_1() {
+        let mut data = [0; 4 + 95 + 2 + 2];
+        data[..4].copy_from_slice(&[0x02, 0x03, 0x00, 95 + 2]);
+        data[4..7].copy_from_slice(&[0x02, 0x00, 0x8a]); // RSN, pairwise, ack, version 2
+        data[7..9].copy_from_slice(&[0x00, 0x10]);
+        data[16] = 1;
+        data[17..49].copy_from_slice(&[0x5a; 32]);
+        data[97..101].copy_from_slice(&[0x00, 0x02, 0xdd, 0x00]);
+
+        let eapol = EapolPacket::new(&data).unwrap();
+        assert_eq!(97, eapol.body().len());
+        let testee = eapol.key().unwrap();
+        assert_eq!(KeyDescriptorType::RSN, testee.descriptor_type());
+        assert_eq!(KeyInformation::PAIRWISE | KeyInformation::ACK, testee.key_information());
+        assert_eq!(2, testee.key_descriptor_version());
+        assert_eq!(16, testee.key_length());
+        assert_eq!(1, testee.key_replay_counter());
+        assert_eq!([0x5a; 32], testee.key_nonce());
+        assert_eq!([0; 16], testee.key_mic());
+        assert_eq!(&[0xdd, 0x00], testee.key_data());
+        assert_eq!(&[0xdd, 0x00], testee.payload());
+    }
+
+    #[test]
+    fn key_information_keeps_version() {
+        let mut data = [0; 95];
+        let mut testee = MutEapolKeyPacket::new(&mut data).unwrap();
+        testee.set_key_descriptor_version(2);
+        testee.set_key_information(KeyInformation::MIC | KeyInformation::SECURE);
+        testee.set_key_descriptor_version(1);
+        let testee = testee.as_immutable();
+        assert_eq!(KeyInformation::MIC | KeyInformation::SECURE, testee.key_information());
+        assert_eq!(1, testee.key_descriptor_version());
+    }
+
+    #[test]
+    fn not_a_key() {
+        let data = [0x02, 0x01, 0x00, 0x00];
+        let testee = EapolPacket::new(&data).unwrap();
+        assert_eq!(EapolType::START, testee.packet_type());
+        assert!(testee.body().is_empty());
+        assert!(testee.key().is_none());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 4 + 4];
+        let mut testee = MutEapolPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[1, 2]));
+        assert_eq!(2, testee.as_immutable().body_length());
+        assert_eq!(&[1, 2], testee.as_immutable().payload());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        EapolPacketBuf => |packet| packet.body().len() == packet.payload().len(),
+        EapolKeyPacketBuf => |packet| packet.key_data() == packet.payload(),
+    );
+}
@@ -0,0 +1,69 @@
+use std::error::Error;
+use std::fmt;
+
+/// The reason a packet was rejected by one of the strict `parse` constructors. Unlike `new`,
+/// which only checks that the slice is long enough to read the header from, `parse` also
+/// checks that the fields describing the packet itself are consistent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ParseError {
+    /// The slice is shorter than the minimum length of the packet.
+    TooShort { min_len: usize, len: usize },
+    /// The version field does not hold the version of the protocol.
+    InvalidVersion(u8),
+    /// The header length field describes a header shorter than the fixed part of the header,
+    /// or longer than the slice.
+    InvalidHeaderLength(usize),
+    /// The length field, covering the header and payload, describes a packet shorter than its
+    /// header or longer than the slice.
+    InvalidLength(usize),
+    /// The checksum field does not match the checksum computed over the packet.
+    InvalidChecksum { expected: u16, actual: u16 },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::TooShort { min_len, len } => {
+                write!(f, "Packet is {} bytes, shorter than the minimum {}", len, min_len)
+            }
+            ParseError::InvalidVersion(version) => write!(f, "Invalid version {}", version),
+            ParseError::InvalidHeaderLength(len) => write!(f, "Invalid header length {}", len),
+            ParseError::InvalidLength(len) => write!(f, "Invalid packet length {}", len),
+            ParseError::InvalidChecksum { expected, actual } => write!(
+                f,
+                "Invalid checksum {:#06x}, expected {:#06x}",
+                actual,
+                expected
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Returns `ParseError::TooShort` unless `data` is at least `min_len` bytes.
+pub(crate) fn check_len(data: &[u8], min_len: usize) -> Result<(), ParseError> {
+    if data.len() >= min_len {
+        Ok(())
+    } else {
+        Err(ParseError::TooShort { min_len, len: data.len() })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            "Packet is 3 bytes, shorter than the minimum 20",
+            ParseError::TooShort { min_len: 20, len: 3 }.to_string()
+        );
+        assert_eq!(
+            "Invalid checksum 0x00ab, expected 0xb1e6",
+            ParseError::InvalidChecksum { expected: 0xb1e6, actual: 0xab }.to_string()
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Encapsulated Remote Switch Port Analyzer headers, draft-foschiano-erspan. ERSPAN carries
+//! mirrored frames over GRE. Type II uses the GRE protocol type 0x88BE and a GRE header with a
+//! sequence number, type III uses 0x22EB and adds a timestamp and hardware details.
+
+use ethernet::{EtherType, EthernetPacket, MutEthernetPacket};
+use gre::GrePacket;
+use std::fmt;
+use types::*;
+
+macro_rules! erspan_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn version(&self) -> u4 {
+                read_offset!(self.0, 0, u8) >> 4
+            }
+
+            /// Returns the VLAN of the mirrored frame before encapsulation.
+            pub fn vlan(&self) -> u12 {
+                read_offset!(self.0, 0, u16, from_be) & 0x0fff
+            }
+
+            /// Returns the class of service of the mirrored frame.
+            pub fn cos(&self) -> u3 {
+                read_offset!(self.0, 2, u8) >> 5
+            }
+
+            /// Returns true if the mirrored frame was truncated to fit the encapsulation.
+            pub fn is_truncated(&self) -> bool {
+                read_offset!(self.0, 2, u8) & 0x04 != 0
+            }
+
+            /// Returns the session ID, identifying the monitoring session on the receiver.
+            pub fn session_id(&self) -> u10 {
+                read_offset!(self.0, 2, u16, from_be) & 0x03ff
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_version(&mut self, version: u4) {
+                let new_byte = (version << 4) | (read_offset!(self.0, 0, u8) & 0x0f);
+                write_offset!(self.0, 0, new_byte, u8);
+            }
+
+            pub fn set_vlan(&mut self, vlan: u12) {
+                let new_value = (read_offset!(self.0, 0, u16, from_be) & 0xf000) | (vlan & 0x0fff);
+                write_offset!(self.0, 0, new_value, u16, to_be);
+            }
+
+            pub fn set_cos(&mut self, cos: u3) {
+                let new_byte = (cos << 5) | (read_offset!(self.0, 2, u8) & 0x1f);
+                write_offset!(self.0, 2, new_byte, u8);
+            }
+
+            pub fn set_truncated(&mut self, truncated: bool) {
+                let new_byte = (read_offset!(self.0, 2, u8) & 0xfb) | ((truncated as u8) << 2);
+                write_offset!(self.0, 2, new_byte, u8);
+            }
+
+            pub fn set_session_id(&mut self, session_id: u10) {
+                let new_value =
+                    (read_offset!(self.0, 2, u16, from_be) & 0xfc00) | (session_id & 0x03ff);
+                write_offset!(self.0, 2, new_value, u16, to_be);
+            }
+        );
+    }
+}
+
+/// Returns the payload of `gre` if its protocol type is `protocol_type`.
+fn gre_payload(gre: GrePacket<'_>, protocol_type: EtherType) -> Option<&[u8]> {
+    if gre.protocol_type() == protocol_type {
+        gre.data().get(gre.header_len()..)
+    } else {
+        None
+    }
+}
+
+
+packet!(
+    /// An ERSPAN type II header, followed by the mirrored Ethernet frame.
+    Erspan2Packet,
+    MutErspan2Packet,
+    Erspan2PacketBuf,
+    8,
+    |_data| 8,
+    init |data| data[0] = 0x10 | (data[0] & 0x0f)
+);
+erspan_common!(Erspan2Packet, MutErspan2Packet);
+
+getters!(Erspan2Packet
+    /// Returns how the mirrored frame was encapsulated in the original source, such as with or
+    /// without an 802.1Q tag.
+    pub fn encapsulation_type(&self) -> u2 {
+        (read_offset!(self.0, 2, u8) >> 3) & 0x03
+    }
+
+    /// Returns the platform dependent index of the port the frame was mirrored from.
+    pub fn index(&self) -> u20 {
+        read_offset!(self.0, 4, u32, from_be) & 0x000f_ffff
+    }
+
+    /// Returns the mirrored Ethernet frame, or `None` if the payload is too short to hold an
+    /// Ethernet header.
+    pub fn inner_ethernet(&self) -> Option<EthernetPacket<'a>> {
+        EthernetPacket::new(&self.0[8..])
+    }
+);
+
+impl<'a> Erspan2Packet<'a> {
+    /// Interprets the payload of a GRE packet as ERSPAN type II. Returns `None` if the protocol
+    /// type is not `EtherType::ERSPAN_TYPE_II` or the payload is too short.
+    pub fn from_gre(gre: GrePacket<'a>) -> Option<Erspan2Packet<'a>> {
+        gre_payload(gre, EtherType::ERSPAN_TYPE_II).and_then(Erspan2Packet::new)
+    }
+}
+
+setters!(MutErspan2Packet
+    pub fn set_encapsulation_type(&mut self, encapsulation_type: u2) {
+        let new_byte = (read_offset!(self.0, 2, u8) & 0xe7) | ((encapsulation_type & 0x03) << 3);
+        write_offset!(self.0, 2, new_byte, u8);
+    }
+
+    /// Sets the 20 bit index. The reserved bits before it are left unchanged.
+    pub fn set_index(&mut self, index: u20) {
+        let new_value =
+            (read_offset!(self.0, 4, u32, from_be) & 0xfff0_0000) | (index & 0x000f_ffff);
+        write_offset!(self.0, 4, new_value, u32, to_be);
+    }
+);
+
+impl<'a> MutErspan2Packet<'a> {
+    /// Returns the mirrored Ethernet frame for modification, or `None` if the payload is too
+    /// short to hold an Ethernet header.
+    pub fn inner_ethernet(&mut self) -> Option<MutEthernetPacket<'_>> {
+        MutEthernetPacket::new(&mut self.0[8..])
+    }
+}
+
+
+packet!(
+    /// An ERSPAN type III header, followed by the mirrored frame. The header includes the eight
+    /// byte platform specific subheader if its flag is set.
+    Erspan3Packet,
+    MutErspan3Packet,
+    Erspan3PacketBuf,
+    12,
+    |data| if data[11] & 0x01 != 0 { 20 } else { 12 },
+    init |data| {
+        data[0] = 0x20 | (data[0] & 0x0f);
+        data[11] &= 0xfe;
+    }
+);
+erspan_common!(Erspan3Packet, MutErspan3Packet);
+
+getters!(Erspan3Packet
+    /// Returns the bad/short/oversized field, telling whether the mirrored frame had errors.
+    pub fn bso(&self) -> u2 {
+        (read_offset!(self.0, 2, u8) >> 3) & 0x03
+    }
+
+    /// Returns the timestamp of the mirrored frame, in units given by the granularity.
+    pub fn timestamp(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the security group tag of the mirrored frame.
+    pub fn sgt(&self) -> u16 {
+        read_offset!(self.0, 8, u16, from_be)
+    }
+
+    /// Returns true if the mirrored frame was an Ethernet frame with its 802.1Q tag preserved.
+    pub fn is_frame_preserved(&self) -> bool {
+        read_offset!(self.0, 10, u8) & 0x80 != 0
+    }
+
+    /// Returns the type of the mirrored frame, zero for an Ethernet frame and two for an IP
+    /// packet.
+    pub fn frame_type(&self) -> u5 {
+        (read_offset!(self.0, 10, u8) >> 2) & 0x1f
+    }
+
+    /// Returns the ID of the ERSPAN engine that mirrored the frame.
+    pub fn hardware_id(&self) -> u6 {
+        ((read_offset!(self.0, 10, u16, from_be) >> 4) & 0x3f) as u6
+    }
+
+    /// Returns true if the frame was mirrored on egress, false if on ingress.
+    pub fn is_egress(&self) -> bool {
+        read_offset!(self.0, 11, u8) & 0x08 != 0
+    }
+
+    /// Returns the timestamp granularity. Zero is 100 microseconds, one is 100 nanoseconds, two
+    /// is IEEE 1588 and three is user configured.
+    pub fn granularity(&self) -> u2 {
+        (read_offset!(self.0, 11, u8) >> 1) & 0x03
+    }
+
+    /// Returns true if the platform specific subheader is present.
+    pub fn has_platform_subheader(&self) -> bool {
+        read_offset!(self.0, 11, u8) & 0x01 != 0
+    }
+
+    /// Returns the platform specific subheader, or `None` if it is not present or the packet is
+    /// truncated.
+    pub fn platform_subheader(&self) -> Option<[u8; 8]> {
+        if self.has_platform_subheader() && self.0.len() >= 20 {
+            Some(read_offset!(self.0, 12, [u8; 8]))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the mirrored Ethernet frame, or `None` if the frame type is not Ethernet or the
+    /// payload is too short to hold an Ethernet header.
+    pub fn inner_ethernet(&self) -> Option<EthernetPacket<'a>> {
+        if self.frame_type() == 0 {
+            EthernetPacket::new(&self.0[self.header_len()..])
+        } else {
+            None
+        }
+    }
+);
+
+impl<'a> Erspan3Packet<'a> {
+    /// Interprets the payload of a GRE packet as ERSPAN type III. Returns `None` if the protocol
+    /// type is not `EtherType::ERSPAN_TYPE_III` or the payload is too short.
+    pub fn from_gre(gre: GrePacket<'a>) -> Option<Erspan3Packet<'a>> {
+        gre_payload(gre, EtherType::ERSPAN_TYPE_III).and_then(Erspan3Packet::new)
+    }
+}
+
+setters!(MutErspan3Packet
+    pub fn set_bso(&mut self, bso: u2) {
+        let new_byte = (read_offset!(self.0, 2, u8) & 0xe7) | ((bso & 0x03) << 3);
+        write_offset!(self.0, 2, new_byte, u8);
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: u32) {
+        write_offset!(self.0, 4, timestamp, u32, to_be);
+    }
+
+    pub fn set_sgt(&mut self, sgt: u16) {
+        write_offset!(self.0, 8, sgt, u16, to_be);
+    }
+
+    pub fn set_frame_preserved(&mut self, preserved: bool) {
+        let new_byte = (read_offset!(self.0, 10, u8) & 0x7f) | ((preserved as u8) << 7);
+        write_offset!(self.0, 10, new_byte, u8);
+    }
+
+    pub fn set_frame_type(&mut self, frame_type: u5) {
+        let new_byte = (read_offset!(self.0, 10, u8) & 0x83) | ((frame_type & 0x1f) << 2);
+        write_offset!(self.0, 10, new_byte, u8);
+    }
+
+    pub fn set_hardware_id(&mut self, hardware_id: u6) {
+        let new_value = (read_offset!(self.0, 10, u16, from_be) & 0xfc0f) |
+            (u16::from(hardware_id & 0x3f) << 4);
+        write_offset!(self.0, 10, new_value, u16, to_be);
+    }
+
+    pub fn set_egress(&mut self, egress: bool) {
+        let new_byte = (read_offset!(self.0, 11, u8) & 0xf7) | ((egress as u8) << 3);
+        write_offset!(self.0, 11, new_byte, u8);
+    }
+
+    pub fn set_granularity(&mut self, granularity: u2) {
+        let new_byte = (read_offset!(self.0, 11, u8) & 0xf9) | ((granularity & 0x03) << 1);
+        write_offset!(self.0, 11, new_byte, u8);
+    }
+);
+
+impl<'a> MutErspan3Packet<'a> {
+    /// Sets or clears the platform specific subheader flag and writes the subheader. Returns
+    /// false, leaving the packet unchanged, if `subheader` is given and the packet is shorter
+    /// than 20 bytes.
+    pub fn set_platform_subheader(&mut self, subheader: Option<[u8; 8]>) -> bool {
+        let flag_byte = read_offset!(self.0, 11, u8) & 0xfe;
+        match subheader {
+            Some(_) if self.0.len() < 20 => return false,
+            Some(subheader) => {
+                write_offset!(self.0, 12, subheader, [u8; 8]);
+                write_offset!(self.0, 11, flag_byte | 0x01, u8);
+            }
+            None => write_offset!(self.0, 11, flag_byte, u8),
+        }
+        true
+    }
+
+    /// Returns the mirrored Ethernet frame for modification, or `None` if the frame type is not
+    /// Ethernet or the payload is too short to hold an Ethernet header.
+    pub fn inner_ethernet(&mut self) -> Option<MutEthernetPacket<'_>> {
+        if self.as_immutable().frame_type() == 0 {
+            let header_len = self.header_len();
+            MutEthernetPacket::new(&mut self.0[header_len..])
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> fmt::Display for Erspan2Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ERSPAN-II session={} vlan={} index={}",
+            self.session_id(),
+            self.vlan(),
+            self.index()
+        )
+    }
+}
+
+impl<'a> fmt::Display for Erspan3Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ERSPAN-III session={} vlan={} timestamp={}",
+            self.session_id(),
+            self.vlan(),
+            self.timestamp()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gre::{Flags, MutGrePacket};
+
+    macro_rules! erspan2_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutErspan2Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    erspan2_setget_test!(version, set_version, 0x0f, 0, [0xf0]);
+    erspan2_setget_test!(vlan, set_vlan, 0x0abc, 0, [0x0a, 0xbc]);
+    erspan2_setget_test!(cos, set_cos, 0x07, 2, [0xe0]);
+    erspan2_setget_test!(encapsulation_type, set_encapsulation_type, 0x03, 2, [0x18]);
+    erspan2_setget_test!(is_truncated, set_truncated, true, 2, [0x04]);
+    erspan2_setget_test!(session_id, set_session_id, 0x03ff, 2, [0x03, 0xff]);
+    erspan2_setget_test!(index, set_index, 0x0abcde, 5, [0x0a, 0xbc, 0xde]);
+
+    #[test]
+    fn erspan2_from_gre() {
+        let mut data = [0; 8 + 8 + 14];
+        {
+            let mut gre = MutGrePacket::new(&mut data).unwrap();
+            gre.set_flags(Flags::SEQUENCE);
+            gre.set_protocol_type(EtherType::ERSPAN_TYPE_II);
+        }
+        {
+            let mut testee = MutErspan2Packet::new(&mut data[8..]).unwrap();
+            testee.set_version(1);
+            testee.set_session_id(0x123);
+            testee.inner_ethernet().unwrap().set_ether_type(EtherType::IPV4);
+        }
+        let testee = Erspan2Packet::from_gre(GrePacket::new(&data).unwrap()).unwrap();
+        assert_eq!(1, testee.version());
+        assert_eq!(0x123, testee.session_id());
+        assert_eq!(EtherType::IPV4, testee.inner_ethernet().unwrap().ether_type());
+
+        data[3] = 0xeb;
+        assert!(Erspan2Packet::from_gre(GrePacket::new(&data).unwrap()).is_none());
+        assert!(Erspan2Packet::from_gre(GrePacket::new(&data[..12]).unwrap()).is_none());
+    }
+
+    mod erspan3 {
+        use super::super::*;
+
+        macro_rules! erspan3_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutErspan3Packet, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        erspan3_setget_test!(version, set_version, 0x0f, 0, [0xf0]);
+        erspan3_setget_test!(session_id, set_session_id, 0x03ff, 2, [0x03, 0xff]);
+        erspan3_setget_test!(bso, set_bso, 0x03, 2, [0x18]);
+        erspan3_setget_test!(timestamp, set_timestamp, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 0x01]);
+        erspan3_setget_test!(sgt, set_sgt, 0xabcd, 8, [0xab, 0xcd]);
+        erspan3_setget_test!(is_frame_preserved, set_frame_preserved, true, 10, [0x80]);
+        erspan3_setget_test!(frame_type, set_frame_type, 0x1f, 10, [0x7c]);
+        erspan3_setget_test!(hardware_id, set_hardware_id, 0x3f, 10, [0x03, 0xf0]);
+        erspan3_setget_test!(is_egress, set_egress, true, 11, [0x08]);
+        erspan3_setget_test!(granularity, set_granularity, 0x03, 11, [0x06]);
+
+        #[test]
+        fn platform_subheader() {
+            let mut data = [0; 20 + 14];
+            {
+                let mut testee = MutErspan3Packet::new(&mut data).unwrap();
+                assert_eq!(12, testee.header_len());
+                assert!(testee.set_platform_subheader(Some([0xab; 8])));
+                assert_eq!(20, testee.header_len());
+                testee.inner_ethernet().unwrap().set_ether_type(EtherType::IPV6);
+            }
+            assert_eq!(0x01, data[11]);
+            let testee = Erspan3Packet::new(&data).unwrap();
+            assert_eq!(Some([0xab; 8]), testee.platform_subheader());
+            assert_eq!(EtherType::IPV6, testee.inner_ethernet().unwrap().ether_type());
+            assert_eq!(None, Erspan3Packet::new(&data[..16]).unwrap().platform_subheader());
+
+            data[10] = 0x08;
+            assert!(Erspan3Packet::new(&data).unwrap().inner_ethernet().is_none());
+        }
+
+        #[test]
+        fn erspan3_from_gre() {
+            let mut data = [0, 0, 0x22, 0xeb, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            assert!(Erspan3Packet::from_gre(GrePacket::new(&data).unwrap()).is_some());
+            assert!(Erspan2Packet::from_gre(GrePacket::new(&data).unwrap()).is_none());
+            data[3] = 0xea;
+            assert!(Erspan3Packet::from_gre(GrePacket::new(&data).unwrap()).is_none());
+        }
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Erspan2PacketBuf => |packet| packet.version() == 1,
+        Erspan3PacketBuf => |packet| packet.version() == 2 && !packet.has_platform_subheader(),
+    );
+}
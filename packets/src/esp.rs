@@ -0,0 +1,198 @@
+//! The IP Encapsulating Security Payload, RFC 4303.
+//!
+//! Only the SPI and sequence number are sent in the clear. They are followed by the encrypted
+//! payload and trailer, where the payload usually starts with an IV, and finally the integrity
+//! check value. The lengths of the IV and ICV depend on the algorithms of the security
+//! association, so they are given to the methods that locate these regions.
+
+use ip::Protocol;
+use std::fmt;
+
+/// The UDP port used for UDP encapsulated ESP when traversing NATs, RFC 3948. Shared with IKE.
+pub const NAT_T_UDP_PORT: u16 = 4500;
+
+packet!(
+    /// An ESP packet. The payload is everything after the SPI and sequence number.
+    EspPacket,
+    MutEspPacket,
+    EspPacketBuf,
+    8
+);
+
+getters!(EspPacket
+    /// Returns the security parameters index, identifying the security association.
+    pub fn spi(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+);
+
+impl<'a> EspPacket<'a> {
+    /// Returns the IV at the start of the payload, or `None` if the packet is too short.
+    pub fn iv(&self, iv_len: usize) -> Option<&'a [u8]> {
+        self.0.get(8..8 + iv_len)
+    }
+
+    /// Returns the encrypted payload and trailer, between the IV and the ICV, or `None` if the
+    /// packet is too short to hold both.
+    pub fn encrypted_data(&self, iv_len: usize, icv_len: usize) -> Option<&'a [u8]> {
+        let end = self.0.len().checked_sub(icv_len)?;
+        self.0.get(8 + iv_len..end)
+    }
+
+    /// Returns the integrity check value at the end of the packet, or `None` if the packet is
+    /// too short.
+    pub fn icv(&self, icv_len: usize) -> Option<&'a [u8]> {
+        let start = self.0.len().checked_sub(icv_len)?;
+        self.0.get(start.max(8)..).filter(|icv| icv.len() == icv_len)
+    }
+}
+
+setters!(MutEspPacket
+    pub fn set_spi(&mut self, spi: u32) {
+        write_offset!(self.0, 0, spi, u32, to_be);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 4, sequence_number, u32, to_be);
+    }
+);
+
+impl<'a> MutEspPacket<'a> {
+    /// Returns the encrypted payload and trailer for modification, such as in place encryption
+    /// or decryption. See the immutable version of this packet for details.
+    pub fn encrypted_data(&mut self, iv_len: usize, icv_len: usize) -> Option<&mut [u8]> {
+        let end = self.0.len().checked_sub(icv_len)?;
+        self.0.get_mut(8 + iv_len..end)
+    }
+}
+
+
+/// The plaintext of an ESP packet, split into its parts.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct EspTrailer<'a> {
+    /// The protected packet or transport layer payload.
+    pub payload: &'a [u8],
+    /// The padding, normally the bytes 1, 2, 3 and so on.
+    pub padding: &'a [u8],
+    /// The protocol of the payload. `Protocol(59)`, no next header, marks a dummy packet used
+    /// for traffic flow confidentiality.
+    pub next_header: Protocol,
+}
+
+impl<'a> EspTrailer<'a> {
+    /// Splits decrypted ESP data into payload, padding and next header, reading the pad length
+    /// and next header fields at its end. Returns `None` if `plaintext` is too short to hold
+    /// the trailer and the padding it claims.
+    pub fn parse(plaintext: &'a [u8]) -> Option<EspTrailer<'a>> {
+        let fields_offset = plaintext.len().checked_sub(2)?;
+        let pad_len = usize::from(plaintext[fields_offset]);
+        let padding_offset = fields_offset.checked_sub(pad_len)?;
+        Some(EspTrailer {
+            payload: &plaintext[..padding_offset],
+            padding: &plaintext[padding_offset..fields_offset],
+            next_header: Protocol(plaintext[fields_offset + 1]),
+        })
+    }
+}
+
+/// Returns the number of padding bytes needed after `payload_len` bytes of payload, to make the
+/// payload and trailer a multiple of `block_size` bytes. The alignment is never less than four
+/// bytes, as ESP requires the ICV to be four byte aligned.
+pub fn padding_len(payload_len: usize, block_size: usize) -> usize {
+    let alignment = block_size.max(4);
+    (alignment - (payload_len + 2) % alignment) % alignment
+}
+
+/// Writes the padding and trailer after `payload_len` bytes of payload at the start of `buffer`,
+/// ready for encryption with a cipher of `block_size` bytes. Returns the total length of the
+/// payload and trailer, or `None` if it does not fit in `buffer` or the padding would be longer
+/// than 255 bytes.
+pub fn write_trailer(
+    buffer: &mut [u8],
+    payload_len: usize,
+    block_size: usize,
+    next_header: Protocol,
+) -> Option<usize> {
+    let pad_len = padding_len(payload_len, block_size);
+    if pad_len > 255 {
+        return None;
+    }
+    let len = payload_len + pad_len + 2;
+    let trailer = buffer.get_mut(payload_len..len)?;
+    for (i, byte) in trailer[..pad_len].iter_mut().enumerate() {
+        *byte = (i + 1) as u8;
+    }
+    trailer[pad_len] = pad_len as u8;
+    trailer[pad_len + 1] = next_header.value();
+    Some(len)
+}
+
+impl<'a> fmt::Display for EspPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ESP spi={:#010x} seq={}",
+            self.spi(),
+            self.sequence_number()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! esp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutEspPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    esp_setget_test!(spi, set_spi, 0xabcdef01, 0, [0xab, 0xcd, 0xef, 0x01]);
+    esp_setget_test!(sequence_number, set_sequence_number, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 1]);
+
+    #[test]
+    fn regions() {
+        let mut data = [0; 8 + 8 + 16 + 12];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let testee = EspPacket::new(&data).unwrap();
+        assert_eq!(Some(&data[8..16]), testee.iv(8));
+        assert_eq!(Some(&data[16..32]), testee.encrypted_data(8, 12));
+        assert_eq!(Some(&data[32..]), testee.icv(12));
+        assert_eq!(None, testee.encrypted_data(32, 12));
+        assert_eq!(None, testee.icv(40));
+        assert_eq!(None, EspPacket::new(&data[..10]).unwrap().icv(4));
+    }
+
+    #[test]
+    fn trailer() {
+        assert_eq!(2, padding_len(12, 16));
+        assert_eq!(0, padding_len(14, 16));
+        assert_eq!(2, padding_len(0, 1));
+
+        let mut data = [0xff; 32];
+        let len = write_trailer(&mut data, 5, 8, Protocol::TCP).unwrap();
+        assert_eq!(8, len);
+        assert_eq!(&[1, 1, 6], &data[5..8]);
+        assert_eq!(None, write_trailer(&mut data, 31, 8, Protocol::TCP));
+
+        let trailer = EspTrailer::parse(&data[..len]).unwrap();
+        assert_eq!(&[0xff; 5], trailer.payload);
+        assert_eq!(&[1], trailer.padding);
+        assert_eq!(Protocol::TCP, trailer.next_header);
+        assert_eq!(None, EspTrailer::parse(&[1]));
+        assert_eq!(None, EspTrailer::parse(&[1, 2, 5, 6]));
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        EspPacketBuf => |packet| packet.encrypted_data(0, 0) == Some(packet.payload())
+    );
+}
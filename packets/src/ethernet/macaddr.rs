@@ -1,21 +1,23 @@
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
 use std::error::Error;
 use std::fmt;
+use std::net::Ipv6Addr;
 use std::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct MacAddrLengthError;
 
 impl fmt::Display for MacAddrLengthError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.description().fmt(f)
+        f.write_str("Given data not six bytes long")
     }
 }
 
-impl Error for MacAddrLengthError {
-    fn description(&self) -> &str {
-        "Given data not six bytes long"
-    }
-}
+impl Error for MacAddrLengthError {}
 
 /// A MAC address. Six bytes representing a link layer network address.
 #[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
@@ -48,6 +50,100 @@ impl MacAddr {
     pub fn from_bytes(b0: u8, b1: u8, b2: u8, b3: u8, b4: u8, b5: u8) -> MacAddr {
         MacAddr([b0, b1, b2, b3, b4, b5])
     }
+
+    /// Returns the organizationally unique identifier, the first three bytes of the address.
+    /// It identifies the vendor of universally administered addresses.
+    pub fn oui(&self) -> [u8; 3] {
+        [self.0[0], self.0[1], self.0[2]]
+    }
+
+    /// Returns the vendor of the address from the table in `oui`, or `None` if the address is
+    /// locally administered or its OUI is not in the table. The group bit is ignored, so
+    /// multicast addresses give the organization that assigned them.
+    #[cfg(feature = "oui")]
+    pub fn vendor(&self) -> Option<&'static str> {
+        if self.is_locally_administered() {
+            return None;
+        }
+        let mut oui = self.oui();
+        oui[0] &= !0x01;
+        super::oui::vendor(oui)
+    }
+
+    /// Returns true for the broadcast address, ff:ff:ff:ff:ff:ff.
+    pub fn is_broadcast(&self) -> bool {
+        *self == MacAddr::BROADCAST
+    }
+
+    /// Returns true for group addresses, those with the least significant bit of the first byte
+    /// set. This includes the broadcast address.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// Returns true for individual addresses, the ones that are not multicast.
+    pub fn is_unicast(&self) -> bool {
+        !self.is_multicast()
+    }
+
+    /// Returns true if the address is locally administered rather than assigned by a vendor,
+    /// meaning the second least significant bit of the first byte is set.
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    /// Returns true for addresses assigned by a vendor, the ones that are not locally
+    /// administered.
+    pub fn is_universal(&self) -> bool {
+        !self.is_locally_administered()
+    }
+
+    /// Returns true for the all zero address, 00:00:00:00:00:00.
+    pub fn is_unspecified(&self) -> bool {
+        self.0 == [0; 6]
+    }
+
+    /// Returns the modified EUI-64 of the address, as used for IPv6 interface identifiers by
+    /// RFC 4291 appendix A: ff:fe inserted after the OUI and the universal/local bit inverted.
+    pub fn to_eui64(&self) -> [u8; 8] {
+        let b = self.0;
+        [b[0] ^ 0x02, b[1], b[2], 0xff, 0xfe, b[3], b[4], b[5]]
+    }
+
+    /// Returns the address a modified EUI-64 was derived from, or `None` if it does not hold
+    /// ff:fe in its middle bytes and was thus not derived from a MAC address.
+    pub fn from_eui64(eui64: [u8; 8]) -> Option<MacAddr> {
+        if eui64[3] == 0xff && eui64[4] == 0xfe {
+            let e = eui64;
+            Some(MacAddr([e[0] ^ 0x02, e[1], e[2], e[5], e[6], e[7]]))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the IPv6 link-local address of an interface with this address, fe80::/64 with
+    /// the modified EUI-64 as interface identifier.
+    pub fn to_link_local(&self) -> Ipv6Addr {
+        self.to_slaac(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0))
+    }
+
+    /// Returns the address stateless address autoconfiguration (RFC 4862) forms from the /64
+    /// `prefix` and the modified EUI-64 of this address. The lower 64 bits of `prefix` are
+    /// ignored.
+    pub fn to_slaac(&self, prefix: Ipv6Addr) -> Ipv6Addr {
+        let mut octets = prefix.octets();
+        octets[8..].copy_from_slice(&self.to_eui64());
+        Ipv6Addr::from(octets)
+    }
+
+    /// Returns the MAC address embedded in the interface identifier of `addr`, the reverse of
+    /// `to_link_local` and `to_slaac`. Returns `None` if the identifier is not a modified
+    /// EUI-64 derived from a MAC address, as for privacy addresses.
+    pub fn from_ipv6(addr: &Ipv6Addr) -> Option<MacAddr> {
+        let mut eui64 = [0; 8];
+        eui64.copy_from_slice(&addr.octets()[8..]);
+        MacAddr::from_eui64(eui64)
+    }
 }
 
 impl AsRef<[u8]> for MacAddr {
@@ -77,22 +173,20 @@ impl fmt::Display for MacAddrParseError {
     }
 }
 
-impl Error for MacAddrParseError {
-    fn description(&self) -> &str {
-        "Invalid MAC address string"
-    }
-}
+impl Error for MacAddrParseError {}
 
 impl FromStr for MacAddr {
     type Err = MacAddrParseError;
 
+    /// Parses six hexadecimal bytes separated by colons, as written by `Display`, or by dashes.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         use std::num::ParseIntError;
 
+        let separator = if s.contains('-') { '-' } else { ':' };
         let bytes: Result<Vec<u8>, ParseIntError> =
-            s.split(":").map(|s| u8::from_str_radix(s, 16)).collect();
+            s.split(separator).map(|s| u8::from_str_radix(s, 16)).collect();
         match bytes {
-            Ok(ref bytes) if bytes.len() == 6 => Ok(Self::from_slice(&bytes)),
+            Ok(ref bytes) if bytes.len() == 6 => Ok(Self::from_slice(bytes)),
             _ => Err(MacAddrParseError(s.to_owned())),
         }
     }
@@ -112,7 +206,23 @@ impl fmt::Display for MacAddr {
 
 impl fmt::Debug for MacAddr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        (self as &fmt::Display).fmt(f)
+        (self as &dyn fmt::Display).fmt(f)
+    }
+}
+
+/// Serializes as the string produced by `Display`, the same way as the addresses in `std::net`.
+#[cfg(feature = "serde")]
+impl Serialize for MacAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for MacAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
     }
 }
 
@@ -163,5 +273,66 @@ mod tests {
     fn from_str() {
         let result = MacAddr::from_str("01:02:ff:ac:13:37");
         assert_eq!(result, Ok(MacAddr([0x01, 0x02, 0xff, 0xac, 0x13, 0x37])));
+        let result = MacAddr::from_str("01-02-FF-AC-13-37");
+        assert_eq!(result, Ok(MacAddr([0x01, 0x02, 0xff, 0xac, 0x13, 0x37])));
+        assert!(MacAddr::from_str("01-02-ff:ac-13-37").is_err());
+        assert!(MacAddr::from_str("01:02:ff:ac:13").is_err());
+    }
+
+    #[test]
+    fn predicates() {
+        assert!(MacAddr::BROADCAST.is_broadcast());
+        assert!(MacAddr::BROADCAST.is_multicast());
+        let multicast = MacAddr([0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]);
+        assert!(multicast.is_multicast() && !multicast.is_broadcast());
+        assert!(multicast.is_universal());
+        assert_eq!([0x01, 0x00, 0x5e], multicast.oui());
+        let local = MacAddr([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+        assert!(local.is_unicast() && local.is_locally_administered());
+        assert!(MacAddr::default().is_unspecified());
+        assert!(!local.is_unspecified());
+    }
+
+    #[test]
+    fn eui64() {
+        let mac = MacAddr([0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e]);
+        let eui64 = [0x02, 0x1b, 0x21, 0xff, 0xfe, 0x3c, 0x4d, 0x5e];
+        assert_eq!(eui64, mac.to_eui64());
+        assert_eq!(Some(mac), MacAddr::from_eui64(eui64));
+        let local = MacAddr([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+        assert_eq!(0x50, local.to_eui64()[0]);
+        assert_eq!(None, MacAddr::from_eui64([0x02, 0x1b, 0x21, 0xff, 0xff, 0x3c, 0x4d, 0x5e]));
+    }
+
+    #[test]
+    fn ipv6() {
+        let mac = MacAddr([0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e]);
+        let link_local: Ipv6Addr = "fe80::21b:21ff:fe3c:4d5e".parse().unwrap();
+        assert_eq!(link_local, mac.to_link_local());
+        let prefix = "2001:db8:1:2::ffff".parse().unwrap();
+        let global: Ipv6Addr = "2001:db8:1:2:21b:21ff:fe3c:4d5e".parse().unwrap();
+        assert_eq!(global, mac.to_slaac(prefix));
+        assert_eq!(Some(mac), MacAddr::from_ipv6(&global));
+        assert_eq!(None, MacAddr::from_ipv6(&"fe80::1".parse().unwrap()));
+    }
+
+    #[cfg(feature = "oui")]
+    #[test]
+    fn vendor() {
+        assert_eq!(Some("VMware"), MacAddr([0x00, 0x50, 0x56, 1, 2, 3]).vendor());
+        assert_eq!(None, MacAddr([0x02, 0x50, 0x56, 1, 2, 3]).vendor());
+        assert_eq!(Some("IANA"), MacAddr([0x01, 0x00, 0x5e, 0, 0, 0xfb]).vendor());
+    }
+
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        extern crate serde_json;
+
+        let mac = MacAddr([0x01, 0x02, 0xff, 0xac, 0x13, 0x37]);
+        assert_eq!("\"01:02:ff:ac:13:37\"", serde_json::to_string(&mac).unwrap());
+        assert_eq!(mac, serde_json::from_str("\"01:02:ff:ac:13:37\"").unwrap());
+        assert!(serde_json::from_str::<MacAddr>("\"01:02:ff\"").is_err());
     }
 }
@@ -1,37 +1,171 @@
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use builder::{self, Payload};
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 mod macaddr;
 pub use self::macaddr::*;
+#[cfg(feature = "oui")]
+pub mod oui;
+
+packet!(EthernetPacket, MutEthernetPacket, EthernetPacketBuf, 14);
 
-packet!(EthernetPacket, MutEthernetPacket, 14);
+fields!(EthernetPacket,
+    DESTINATION: 0, 48;
+    SOURCE: 48, 48;
+    ETHER_TYPE: 96, 16;
+);
 
 getters!(EthernetPacket
     pub fn destination(&self) -> MacAddr {
-        MacAddr::from_slice(&self.0[0..6])
+        MacAddr::from_slice(&self.0[fields::DESTINATION.byte_range()])
     }
 
     pub fn source(&self) -> MacAddr {
-        MacAddr::from_slice(&self.0[6..12])
+        MacAddr::from_slice(&self.0[fields::SOURCE.byte_range()])
     }
 
     pub fn ether_type(&self) -> EtherType {
-        EtherType(read_offset!(self.0, 12, u16, from_be))
+        EtherType(read_field!(self.0, fields::ETHER_TYPE, u16))
     }
 );
 
 setters!(MutEthernetPacket
     pub fn set_destination(&mut self, destination: MacAddr) {
-        self.0[0..6].copy_from_slice(destination.as_ref());
+        self.0[fields::DESTINATION.byte_range()].copy_from_slice(destination.as_ref());
     }
 
     pub fn set_source(&mut self, source: MacAddr) {
-        self.0[6..12].copy_from_slice(source.as_ref());
+        self.0[fields::SOURCE.byte_range()].copy_from_slice(source.as_ref());
     }
 
     pub fn set_ether_type(&mut self, ether_type: EtherType) {
-        write_offset!(self.0, 12, ether_type.value(), u16, to_be)
+        write_field!(self.0, fields::ETHER_TYPE, ether_type.value(), u16);
     }
 );
 
 
+/// Builds an Ethernet frame. See the `builder` module for an example.
+#[derive(Debug, Copy, Clone)]
+pub struct EthernetBuilder<P = &'static [u8]> {
+    destination: MacAddr,
+    source: MacAddr,
+    ether_type: Option<EtherType>,
+    payload: P,
+}
+
+impl EthernetBuilder {
+    /// Creates a builder for a frame with all zero addresses and an empty payload.
+    pub fn new() -> EthernetBuilder {
+        EthernetBuilder::default()
+    }
+}
+
+impl Default for EthernetBuilder {
+    fn default() -> EthernetBuilder {
+        EthernetBuilder {
+            destination: MacAddr::default(),
+            source: MacAddr::default(),
+            ether_type: None,
+            payload: &[],
+        }
+    }
+}
+
+impl<P: Payload> EthernetBuilder<P> {
+    pub fn destination(mut self, destination: MacAddr) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    pub fn source(mut self, source: MacAddr) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Sets the EtherType. Only needed when the payload is not a builder that knows its own
+    /// EtherType.
+    pub fn ether_type(mut self, ether_type: EtherType) -> Self {
+        self.ether_type = Some(ether_type);
+        self
+    }
+
+    pub fn payload<Q: Payload>(self, payload: Q) -> EthernetBuilder<Q> {
+        EthernetBuilder {
+            destination: self.destination,
+            source: self.source,
+            ether_type: self.ether_type,
+            payload,
+        }
+    }
+
+    /// Writes the frame to the start of `buffer`. Returns the length of the frame, or `None` if
+    /// it does not fit in `buffer`, the payload can't be encoded or the EtherType is not known.
+    pub fn build_into(&self, buffer: &mut [u8]) -> Option<usize> {
+        self.write(buffer, None)
+    }
+
+    /// Writes the frame to a newly allocated buffer. See `build_into`.
+    pub fn build(&self) -> Option<Vec<u8>> {
+        builder::build(self)
+    }
+}
+
+impl<P: Payload> Payload for EthernetBuilder<P> {
+    fn encoded_len(&self) -> usize {
+        EthernetPacket::MIN_LEN + self.payload.encoded_len()
+    }
+
+    fn write(&self, buffer: &mut [u8], _addresses: Option<(IpAddr, IpAddr)>) -> Option<usize> {
+        let ether_type = self.ether_type.or_else(|| self.payload.ether_type())?;
+        let buffer = buffer.get_mut(..self.encoded_len())?;
+        self.payload.write(&mut buffer[EthernetPacket::MIN_LEN..], None)?;
+        let mut packet = MutEthernetPacket::new(buffer)?;
+        packet.set_destination(self.destination);
+        packet.set_source(self.source);
+        packet.set_ether_type(ether_type);
+        Some(buffer.len())
+    }
+}
+
+
+/// The fields of an Ethernet header as a plain value, one field per getter on `EthernetPacket`.
+/// Implements `Serialize` and `Deserialize` with the `serde` feature enabled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EthernetHeader {
+    pub destination: MacAddr,
+    pub source: MacAddr,
+    pub ether_type: EtherType,
+}
+
+impl<'a> EthernetPacket<'a> {
+    /// Reads all fields of the header into an `EthernetHeader`.
+    pub fn to_header(&self) -> EthernetHeader {
+        EthernetHeader {
+            destination: self.destination(),
+            source: self.source(),
+            ether_type: self.ether_type(),
+        }
+    }
+}
+
+impl<'a> MutEthernetPacket<'a> {
+    /// Writes all fields of `header` to the header.
+    pub fn set_header(&mut self, header: &EthernetHeader) {
+        self.set_destination(header.destination);
+        self.set_source(header.source);
+        self.set_ether_type(header.ether_type);
+    }
+}
+
+
 /// A representation of the 16 bit EtherType header field of an Ethernet packet.
 ///
 /// A few select, commonly used, values are attached as associated constants. Their values are
@@ -39,24 +173,170 @@ setters!(MutEthernetPacket
 ///
 /// [IANA's website]: https://www.iana.org/assignments/ieee-802-numbers/ieee-802-numbers.xhtml
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EtherType(pub u16);
 
 impl EtherType {
     pub const IPV4: EtherType = EtherType(0x0800);
     pub const ARP: EtherType = EtherType(0x0806);
+    pub const WAKE_ON_LAN: EtherType = EtherType(0x0842);
+    pub const ERSPAN_TYPE_III: EtherType = EtherType(0x22EB);
+    pub const TRANSPARENT_ETHERNET_BRIDGING: EtherType = EtherType(0x6558);
+    pub const RARP: EtherType = EtherType(0x8035);
+    pub const VLAN: EtherType = EtherType(0x8100);
     pub const IPV6: EtherType = EtherType(0x86DD);
+    pub const SLOW_PROTOCOLS: EtherType = EtherType(0x8809);
+    pub const MPLS: EtherType = EtherType(0x8847);
+    pub const MPLS_MULTICAST: EtherType = EtherType(0x8848);
+    pub const PPPOE_DISCOVERY: EtherType = EtherType(0x8863);
+    pub const PPPOE_SESSION: EtherType = EtherType(0x8864);
+    pub const EAPOL: EtherType = EtherType(0x888E);
+    pub const QINQ: EtherType = EtherType(0x88A8);
+    pub const ERSPAN_TYPE_II: EtherType = EtherType(0x88BE);
+    pub const LLDP: EtherType = EtherType(0x88CC);
+    pub const MACSEC: EtherType = EtherType(0x88E5);
+    pub const PBB: EtherType = EtherType(0x88E7);
+    pub const PTP: EtherType = EtherType(0x88F7);
+    pub const FCOE: EtherType = EtherType(0x8906);
+
+    /// Every EtherType with a constant here together with its name, as used by `Display` and
+    /// `FromStr`.
+    pub const NAMED: &'static [(EtherType, &'static str)] = &[
+        (EtherType::IPV4, "IPv4"),
+        (EtherType::ARP, "ARP"),
+        (EtherType::WAKE_ON_LAN, "Wake-on-LAN"),
+        (EtherType::ERSPAN_TYPE_III, "ERSPAN-III"),
+        (EtherType::TRANSPARENT_ETHERNET_BRIDGING, "TEB"),
+        (EtherType::RARP, "RARP"),
+        (EtherType::VLAN, "VLAN"),
+        (EtherType::IPV6, "IPv6"),
+        (EtherType::SLOW_PROTOCOLS, "Slow-Protocols"),
+        (EtherType::MPLS, "MPLS"),
+        (EtherType::MPLS_MULTICAST, "MPLS-Multicast"),
+        (EtherType::PPPOE_DISCOVERY, "PPPoE-Discovery"),
+        (EtherType::PPPOE_SESSION, "PPPoE-Session"),
+        (EtherType::EAPOL, "EAPOL"),
+        (EtherType::QINQ, "QinQ"),
+        (EtherType::ERSPAN_TYPE_II, "ERSPAN-II"),
+        (EtherType::LLDP, "LLDP"),
+        (EtherType::MACSEC, "MACsec"),
+        (EtherType::PBB, "PBB"),
+        (EtherType::PTP, "PTP"),
+        (EtherType::FCOE, "FCoE"),
+    ];
 
     #[inline]
     pub fn value(&self) -> u16 {
         self.0
     }
+
+    /// Returns true if this is not an EtherType but the payload length of an IEEE 802.3 frame,
+    /// meaning a value of at most 1500. Such frames usually start with an LLC header.
+    pub fn is_length(&self) -> bool {
+        self.0 <= 1500
+    }
+
+    /// Returns the name of this EtherType if it has a constant here.
+    pub fn name(&self) -> Option<&'static str> {
+        EtherType::NAMED.iter().find(|&&(ether_type, _)| ether_type == *self).map(|&(_, name)| name)
+    }
+}
+
+
+impl<'a> fmt::Display for EthernetPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Ethernet {} -> {} type={} len={}",
+            self.source(),
+            self.destination(),
+            self.ether_type(),
+            self.0.len()
+        )
+    }
+}
+
+impl fmt::Display for EtherType {
+    /// Writes the name of the EtherTypes that have a constant here, and the hexadecimal value
+    /// otherwise.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#06x}", self.0),
+        }
+    }
 }
 
+impl FromStr for EtherType {
+    type Err = EtherTypeParseError;
+
+    /// Parses the names written by `Display`, ignoring case, or a hexadecimal value starting
+    /// with `0x`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(&(ether_type, _)) =
+            EtherType::NAMED.iter().find(|&&(_, name)| name.eq_ignore_ascii_case(s))
+        {
+            return Ok(ether_type);
+        }
+        s.strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+            .map(EtherType)
+            .ok_or_else(|| EtherTypeParseError(s.to_owned()))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct EtherTypeParseError(String);
+
+impl fmt::Display for EtherTypeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid EtherType string: {}", self.0)
+    }
+}
+
+impl Error for EtherTypeParseError {}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn display() {
+        let mut data = [0; 20];
+        data[..6].copy_from_slice(&MAC);
+        data[6..12].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+        data[12] = 0x08;
+        assert_eq!(
+            "Ethernet 02:00:00:00:00:01 -> ff:ff:ff:ff:ff:ff type=IPv4 len=20",
+            EthernetPacket::new(&data).unwrap().to_string()
+        );
+        assert_eq!("0x1234", EtherType(0x1234).to_string());
+        assert_eq!("PPPoE-Session", EtherType::PPPOE_SESSION.to_string());
+        let data = [2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        assert_eq!(
+            "EthernetPacket { destination: 02:00:00:00:00:00, source: 00:00:00:00:00:00, \
+             ether_type: EtherType(0), payload: [1] }",
+            format!("{:?}", EthernetPacket::new(&data).unwrap())
+        );
+    }
+
+    #[test]
+    fn ether_type_from_str() {
+        assert_eq!(Ok(EtherType::IPV6), "IPv6".parse());
+        assert_eq!(Ok(EtherType::MPLS_MULTICAST), "mpls-multicast".parse());
+        assert_eq!(Ok(EtherType(0x1234)), "0x1234".parse());
+        assert_eq!(Ok(EtherType::VLAN), "0X8100".parse());
+        assert!("Ethernet".parse::<EtherType>().is_err());
+        assert!("0x12345".parse::<EtherType>().is_err());
+        assert!("1234".parse::<EtherType>().is_err());
+        for &(ether_type, name) in EtherType::NAMED {
+            assert_eq!(Ok(ether_type), name.parse());
+            assert_eq!(Ok(ether_type), ether_type.to_string().parse());
+        }
+    }
+
     static MAC: [u8; 6] = [0xff; 6];
 
     macro_rules! eth_setget_test {
@@ -69,6 +349,26 @@ mod tests {
     eth_setget_test!(source, set_source, MacAddr(MAC), 6, MAC);
     eth_setget_test!(ether_type, set_ether_type, EtherType(0xffff), 12, [0xff; 2]);
 
+    #[test]
+    fn build() {
+        let builder = EthernetBuilder::new()
+            .destination(MacAddr::BROADCAST)
+            .source(MacAddr(MAC))
+            .payload([1, 2, 3]);
+        assert_eq!(None, builder.build());
+        let frame = builder.ether_type(EtherType::ARP).build().unwrap();
+        let testee = EthernetPacket::new(&frame).unwrap();
+        assert_eq!(MacAddr::BROADCAST, testee.destination());
+        assert_eq!(MacAddr(MAC), testee.source());
+        assert_eq!(EtherType::ARP, testee.ether_type());
+        assert_eq!(&[1, 2, 3], testee.payload());
+
+        let mut buffer = [0xff; 20];
+        assert_eq!(Some(17), builder.ether_type(EtherType::ARP).build_into(&mut buffer));
+        assert_eq!(frame[..], buffer[..17]);
+        assert_eq!(None, builder.ether_type(EtherType::ARP).build_into(&mut buffer[..16]));
+    }
+
     #[test]
     fn set_payload() {
         let mut backing_data = [0; 15];
@@ -78,4 +378,36 @@ mod tests {
         }
         assert_eq!(99, backing_data[14]);
     }
+
+
+    fn header() -> EthernetHeader {
+        EthernetHeader {
+            destination: MacAddr::BROADCAST,
+            source: MacAddr([0x02, 0, 0, 0, 0, 0x01]),
+            ether_type: EtherType::ARP,
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let mut buffer = [0; 14];
+        MutEthernetPacket::new(&mut buffer).unwrap().set_header(&header());
+        assert_eq!(header(), EthernetPacket::new(&buffer).unwrap().to_header());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn header_serde() {
+        extern crate serde_json;
+
+        let json = serde_json::to_string(&header()).unwrap();
+        assert_eq!(
+            "{\"destination\":\"ff:ff:ff:ff:ff:ff\",\"source\":\"02:00:00:00:00:01\",\
+             \"ether_type\":2054}",
+            json
+        );
+        assert_eq!(header(), serde_json::from_str(&json).unwrap());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, EthernetPacketBuf);
 }
@@ -0,0 +1,87 @@
+//! A table of organizationally unique identifiers, the first three bytes of universally
+//! administered MAC addresses, and the vendors they are assigned to. Enabled with the `oui`
+//! feature.
+//!
+//! The table is a selection of the [IEEE registry], covering the network equipment and
+//! virtualization vendors that are most often seen in captures. It is not complete.
+//!
+//! [IEEE registry]: https://regauth.standards.ieee.org/standards-ra-web/pub/view.html
+
+/// The vendors of the OUIs known here, ordered by OUI.
+pub const VENDORS: &[([u8; 3], &str)] = &[
+    ([0x00, 0x00, 0x00], "Xerox"),
+    ([0x00, 0x00, 0x0c], "Cisco"),
+    ([0x00, 0x00, 0x5e], "IANA"),
+    ([0x00, 0x03, 0x93], "Apple"),
+    ([0x00, 0x03, 0xba], "Sun Microsystems"),
+    ([0x00, 0x05, 0x69], "VMware"),
+    ([0x00, 0x05, 0x85], "Juniper Networks"),
+    ([0x00, 0x08, 0x9b], "QNAP Systems"),
+    ([0x00, 0x09, 0x0f], "Fortinet"),
+    ([0x00, 0x09, 0x5b], "Netgear"),
+    ([0x00, 0x0b, 0x86], "Aruba Networks"),
+    ([0x00, 0x0c, 0x29], "VMware"),
+    ([0x00, 0x0c, 0x42], "MikroTik"),
+    ([0x00, 0x0d, 0x3a], "Microsoft"),
+    ([0x00, 0x0d, 0x93], "Apple"),
+    ([0x00, 0x0e, 0xc6], "ASIX Electronics"),
+    ([0x00, 0x10, 0x18], "Broadcom"),
+    ([0x00, 0x11, 0x32], "Synology"),
+    ([0x00, 0x14, 0x22], "Dell"),
+    ([0x00, 0x14, 0x4f], "Oracle"),
+    ([0x00, 0x15, 0x5d], "Microsoft"),
+    ([0x00, 0x15, 0x6d], "Ubiquiti"),
+    ([0x00, 0x16, 0x3e], "Xensource"),
+    ([0x00, 0x17, 0x88], "Philips Lighting"),
+    ([0x00, 0x18, 0x0a], "Cisco Meraki"),
+    ([0x00, 0x1a, 0x11], "Google"),
+    ([0x00, 0x1b, 0x17], "Palo Alto Networks"),
+    ([0x00, 0x1b, 0x21], "Intel"),
+    ([0x00, 0x1c, 0x42], "Parallels"),
+    ([0x00, 0x1c, 0x7f], "Check Point"),
+    ([0x00, 0x50, 0x56], "VMware"),
+    ([0x00, 0x50, 0xf2], "Microsoft"),
+    ([0x00, 0x80, 0xc2], "IEEE 802.1"),
+    ([0x00, 0xa0, 0x98], "NetApp"),
+    ([0x00, 0xe0, 0x4c], "Realtek"),
+    ([0x00, 0xe0, 0xfc], "Huawei"),
+    ([0x08, 0x00, 0x27], "PCS Systemtechnik"),
+    ([0x18, 0xb4, 0x30], "Nest Labs"),
+    ([0x24, 0xa4, 0x3c], "Ubiquiti"),
+    ([0x3c, 0x5a, 0xb4], "Google"),
+    ([0x3c, 0xfd, 0xfe], "Intel"),
+    ([0x44, 0x65, 0x0d], "Amazon"),
+    ([0x50, 0xc7, 0xbf], "TP-Link"),
+    ([0xb8, 0x27, 0xeb], "Raspberry Pi Foundation"),
+    ([0xdc, 0xa6, 0x32], "Raspberry Pi Trading"),
+    ([0xe4, 0x5f, 0x01], "Raspberry Pi Trading"),
+    ([0xf0, 0x27, 0x2d], "Amazon"),
+];
+
+/// Returns the vendor `oui` is assigned to, if it is in the table.
+pub fn vendor(oui: [u8; 3]) -> Option<&'static str> {
+    VENDORS
+        .binary_search_by_key(&oui, |&(oui, _)| oui)
+        .ok()
+        .map(|index| VENDORS[index].1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted() {
+        for pair in VENDORS.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{:?}", pair[1].0);
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!(Some("VMware"), vendor([0x00, 0x50, 0x56]));
+        assert_eq!(Some("Xerox"), vendor([0, 0, 0]));
+        assert_eq!(None, vendor([0x00, 0x00, 0x01]));
+    }
+}
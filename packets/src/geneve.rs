@@ -0,0 +1,271 @@
+//! Generic Network Virtualization Encapsulation, RFC 8926.
+
+use ethernet::EtherType;
+use std::fmt;
+use types::*;
+
+/// The UDP destination port assigned to Geneve by IANA.
+pub const UDP_PORT: u16 = 6081;
+
+packet!(
+    GenevePacket,
+    MutGenevePacket,
+    GenevePacketBuf,
+    8,
+    |data| 8 + usize::from(data[0] & 0x3f) * 4,
+    init |data| data[0] = 0
+);
+
+getters!(GenevePacket
+    pub fn version(&self) -> u2 {
+        read_offset!(self.0, 0, u8) >> 6
+    }
+
+    /// Returns the length of the options in 32 bit words.
+    pub fn opt_len(&self) -> u6 {
+        read_offset!(self.0, 0, u8) & 0x3f
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 1, u8))
+    }
+
+    /// Returns the EtherType of the encapsulated payload. Ethernet frames are carried as
+    /// `EtherType::TRANSPARENT_ETHERNET_BRIDGING`.
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType(read_offset!(self.0, 2, u16, from_be))
+    }
+
+    /// Returns the 24 bit Virtual Network Identifier.
+    pub fn vni(&self) -> u24 {
+        read_offset!(self.0, 4, u24, from_be)
+    }
+
+    /// Returns an iterator over the options in the header.
+    pub fn options(&self) -> GeneveOptions<'a> {
+        GeneveOptions::new(&self.0[8..self.header_len()])
+    }
+);
+
+setters!(MutGenevePacket
+    pub fn set_version(&mut self, version: u2) {
+        let new_byte = (version << 6) | (read_offset!(self.0, 0, u8) & 0x3f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_opt_len(&mut self, opt_len: u6) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xc0) | (opt_len & 0x3f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_offset!(self.0, 1, flags.bits(), u8);
+    }
+
+    pub fn set_protocol_type(&mut self, protocol_type: EtherType) {
+        write_offset!(self.0, 2, protocol_type.value(), u16, to_be);
+    }
+
+    /// Sets the 24 bit Virtual Network Identifier. The highest byte of `vni` is ignored, and the
+    /// reserved byte following the field is left untouched.
+    pub fn set_vni(&mut self, vni: u24) {
+        write_offset!(self.0, 4, vni, u24, to_be);
+    }
+);
+
+
+packet!(
+    /// A single Geneve option. The header is the option header and data together, so the payload
+    /// is whatever follows the option.
+    GeneveOptionPacket,
+    MutGeneveOptionPacket,
+    GeneveOptionPacketBuf,
+    4,
+    |data| 4 + usize::from(data[3] & 0x1f) * 4,
+    init |data| data[3] &= 0xe0
+);
+
+getters!(GeneveOptionPacket
+    /// Returns the namespace of the option type.
+    pub fn option_class(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    pub fn option_type(&self) -> u8 {
+        read_offset!(self.0, 2, u8)
+    }
+
+    /// Returns true if the critical bit of the option type is set, meaning a receiver that does
+    /// not understand the option must drop the packet.
+    pub fn is_critical(&self) -> bool {
+        self.option_type() & 0x80 != 0
+    }
+
+    /// Returns the length of the option data in 32 bit words, excluding the option header.
+    pub fn length(&self) -> u5 {
+        read_offset!(self.0, 3, u8) & 0x1f
+    }
+
+    /// Returns the option data. Truncated if it does not fit in the backing slice.
+    pub fn option_data(&self) -> &'a [u8] {
+        &self.0[4..self.header_len()]
+    }
+);
+
+setters!(MutGeneveOptionPacket
+    pub fn set_option_class(&mut self, option_class: u16) {
+        write_offset!(self.0, 0, option_class, u16, to_be);
+    }
+
+    pub fn set_option_type(&mut self, option_type: u8) {
+        write_offset!(self.0, 2, option_type, u8);
+    }
+
+    pub fn set_length(&mut self, length: u5) {
+        let new_byte = (read_offset!(self.0, 3, u8) & 0xe0) | (length & 0x1f);
+        write_offset!(self.0, 3, new_byte, u8);
+    }
+);
+
+
+bitflags! {
+    /// Bitmasks for the flags in the second byte of a Geneve header.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "Control packet" position.
+        const OAM = 0b1000_0000;
+        /// A bitmask with a one in the "Critical options present" position.
+        const CRITICAL = 0b0100_0000;
+    }
+}
+
+
+/// Iterator over the options in a Geneve header. Iteration stops at the first option that does
+/// not fit in the data.
+#[derive(Clone)]
+pub struct GeneveOptions<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(GeneveOptions<'a>);
+
+impl<'a> GeneveOptions<'a> {
+    /// Creates an iterator over the options encoded in `data`.
+    pub fn new(data: &'a [u8]) -> GeneveOptions<'a> {
+        GeneveOptions { data }
+    }
+}
+
+impl<'a> Iterator for GeneveOptions<'a> {
+    type Item = GeneveOptionPacket<'a>;
+
+    fn next(&mut self) -> Option<GeneveOptionPacket<'a>> {
+        let len = match self.data.get(3) {
+            Some(&len) => 4 + usize::from(len & 0x1f) * 4,
+            None => {
+                self.data = &[];
+                return None;
+            }
+        };
+        if len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (option, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(GeneveOptionPacket(option))
+    }
+}
+
+impl<'a> fmt::Display for GenevePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Geneve vni={} type={}", self.vni(), self.protocol_type())
+    }
+}
+
+impl<'a> fmt::Display for GeneveOptionPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Geneve option class={:#06x} type={} len={}",
+            self.option_class(),
+            self.option_type(),
+            self.option_data().len()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! geneve_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutGenevePacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    geneve_setget_test!(version, set_version, 0b11, 0, [0xc0]);
+    geneve_setget_test!(opt_len, set_opt_len, 0x3f, 0, [0x3f]);
+    geneve_setget_test!(flags, set_flags, Flags::all(), 1, [0xc0]);
+    geneve_setget_test!(
+        protocol_type,
+        set_protocol_type,
+        EtherType::TRANSPARENT_ETHERNET_BRIDGING,
+        2,
+        [0x65, 0x58]
+    );
+    geneve_setget_test!(vni, set_vni, 0xabcdef, 4, [0xab, 0xcd, 0xef]);
+
+    mod option {
+        use super::super::*;
+
+        macro_rules! option_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutGeneveOptionPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        option_setget_test!(option_class, set_option_class, 0xabcd, 0, [0xab, 0xcd]);
+        option_setget_test!(option_type, set_option_type, 0xab, 2, [0xab]);
+        option_setget_test!(length, set_length, 0x1f, 3, [0x1f]);
+    }
+
+    #[test]
+    fn options() {
+        let data = [
+            0x03, 0x40, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, // Header, 12 bytes of options
+            0x01, 0x02, 0x80, 0x01, 0xde, 0xad, 0xbe, 0xef, // Critical option, 4 bytes of data
+            0x01, 0x03, 0x05, 0x00, // Option without data
+            0xab, // Payload
+        ];
+        let testee = GenevePacket::new(&data).unwrap();
+        assert_eq!(0, testee.version());
+        assert_eq!(20, testee.header_len());
+        assert_eq!(Flags::CRITICAL, testee.flags());
+        assert_eq!(1, testee.vni());
+        assert_eq!(&[0xab], testee.payload());
+
+        let options = testee.options().collect::<Vec<_>>();
+        assert_eq!(2, options.len());
+        assert_eq!(0x0102, options[0].option_class());
+        assert!(options[0].is_critical());
+        assert_eq!(&[0xde, 0xad, 0xbe, 0xef], options[0].option_data());
+        assert_eq!(0x0103, options[1].option_class());
+        assert!(!options[1].is_critical());
+        assert!(options[1].option_data().is_empty());
+    }
+
+    #[test]
+    fn truncated_option() {
+        let data = [0x01, 0x02, 0x80, 0x02, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(0, GeneveOptions::new(&data).count());
+        assert_eq!(0, GeneveOptions::new(&data[..3]).count());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        GenevePacketBuf => |packet| packet.version() == 0 && packet.opt_len() == 0,
+        GeneveOptionPacketBuf => |packet| packet.length() == 0,
+    );
+}
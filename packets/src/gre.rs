@@ -0,0 +1,292 @@
+//! Generic Routing Encapsulation, RFC 2784 with the key and sequence number extensions of
+//! RFC 2890.
+
+use checksum::Checksum;
+use ethernet::EtherType;
+use std::fmt;
+use types::*;
+
+packet!(
+    GrePacket,
+    MutGrePacket,
+    GrePacketBuf,
+    4,
+    |data| header_len(data[0]),
+    init |data| write_offset!(data, 0, [0, 0], [u8; 2])
+);
+
+getters!(GrePacket
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 0, u8))
+    }
+
+    pub fn version(&self) -> u3 {
+        read_offset!(self.0, 1, u8) & 0x07
+    }
+
+    /// Returns the EtherType of the encapsulated payload.
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType(read_offset!(self.0, 2, u16, from_be))
+    }
+
+    /// Returns the checksum, or `None` if the checksum present bit is not set or the field does
+    /// not fit in the backing slice.
+    pub fn checksum(&self) -> Option<u16> {
+        let offset = self.field_offset(Flags::CHECKSUM)?;
+        self.0.get(offset..offset + 2).map(|field| read_offset!(field, 0, u16, from_be))
+    }
+
+    /// Returns the key, or `None` if the key present bit is not set or the field does not fit
+    /// in the backing slice.
+    pub fn key(&self) -> Option<u32> {
+        let offset = self.field_offset(Flags::KEY)?;
+        self.0.get(offset..offset + 4).map(|field| read_offset!(field, 0, u32, from_be))
+    }
+
+    /// Returns the sequence number, or `None` if the sequence number present bit is not set or
+    /// the field does not fit in the backing slice.
+    pub fn sequence_number(&self) -> Option<u32> {
+        let offset = self.field_offset(Flags::SEQUENCE)?;
+        self.0.get(offset..offset + 4).map(|field| read_offset!(field, 0, u32, from_be))
+    }
+);
+
+impl<'a> GrePacket<'a> {
+    /// Computes the checksum of the entire backing slice, ignoring the current value of the
+    /// checksum field. The checksum present bit must be set for the result to be meaningful.
+    pub fn calculate_checksum(&self) -> u16 {
+        let mut checksum = Checksum::new();
+        checksum.add_bytes(&self.0[..4]);
+        if self.0.len() > 6 {
+            checksum.add_bytes(&self.0[6..]);
+        }
+        checksum.finish()
+    }
+
+    /// Returns the offset of the optional field given by `flag`, or `None` if its present bit
+    /// is not set.
+    fn field_offset(&self, flag: Flags) -> Option<usize> {
+        let flags = self.flags();
+        if !flags.contains(flag) {
+            return None;
+        }
+        let mut offset = 4;
+        for &field in &OPTIONAL_FIELDS {
+            if field == flag {
+                break;
+            }
+            if flags.contains(field) {
+                offset += 4;
+            }
+        }
+        Some(offset)
+    }
+}
+
+setters!(MutGrePacket
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_offset!(self.0, 0, flags.bits(), u8);
+    }
+
+    pub fn set_version(&mut self, version: u3) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xf8) | (version & 0x07);
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_protocol_type(&mut self, protocol_type: EtherType) {
+        write_offset!(self.0, 2, protocol_type.value(), u16, to_be);
+    }
+);
+
+impl<'a> MutGrePacket<'a> {
+    /// Sets the checksum and clears the reserved field following it. Returns false, leaving the
+    /// packet unchanged, if the checksum present bit is not set, or if the field does not fit
+    /// in the backing slice. Set the flags first.
+    pub fn set_checksum(&mut self, checksum: u16) -> bool {
+        match self.field(Flags::CHECKSUM) {
+            Some(field) => {
+                write_offset!(field, 0, checksum, u16, to_be);
+                write_offset!(field, 2, 0, u16);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the key. Returns false, leaving the packet unchanged, if the key present bit is not
+    /// set, or if the field does not fit in the backing slice. Set the flags first.
+    pub fn set_key(&mut self, key: u32) -> bool {
+        match self.field(Flags::KEY) {
+            Some(field) => {
+                write_offset!(field, 0, key, u32, to_be);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the sequence number. Returns false, leaving the packet unchanged, if the sequence
+    /// number present bit is not set, or if the field does not fit in the backing slice. Set
+    /// the flags first.
+    pub fn set_sequence_number(&mut self, sequence_number: u32) -> bool {
+        match self.field(Flags::SEQUENCE) {
+            Some(field) => {
+                write_offset!(field, 0, sequence_number, u32, to_be);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the four bytes of the optional field given by `flag`, or `None` if its present
+    /// bit is not set or it does not fit in the backing slice.
+    fn field(&mut self, flag: Flags) -> Option<&mut [u8]> {
+        let offset = self.as_immutable().field_offset(flag)?;
+        self.0.get_mut(offset..offset + 4)
+    }
+}
+
+/// Returns the header length given by the flags in the first byte of a GRE header.
+fn header_len(flags: u8) -> usize {
+    let flags = Flags::from_bits_truncate(flags);
+    4 + OPTIONAL_FIELDS.iter().filter(|&&field| flags.contains(field)).count() * 4
+}
+
+/// The flags signaling the presence of each optional four byte field, in the order the fields
+/// appear in the header. The checksum field includes the reserved field following it.
+const OPTIONAL_FIELDS: [Flags; 3] = [Flags::CHECKSUM, Flags::KEY, Flags::SEQUENCE];
+
+
+bitflags! {
+    /// Bitmasks for the flags in the first byte of a GRE header.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "Checksum Present" position.
+        const CHECKSUM = 0b1000_0000;
+        /// A bitmask with a one in the "Routing Present" position. Deprecated by RFC 2784.
+        const ROUTING = 0b0100_0000;
+        /// A bitmask with a one in the "Key Present" position.
+        const KEY = 0b0010_0000;
+        /// A bitmask with a one in the "Sequence Number Present" position.
+        const SEQUENCE = 0b0001_0000;
+    }
+}
+
+impl<'a> fmt::Display for GrePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GRE type={} version={}",
+            self.protocol_type(),
+            self.version()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! gre_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutGrePacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    gre_setget_test!(flags, set_flags, Flags::all(), 0, [0xf0]);
+    gre_setget_test!(version, set_version, 0x07, 1, [0x07]);
+    gre_setget_test!(
+        protocol_type,
+        set_protocol_type,
+        EtherType::IPV6,
+        2,
+        [0x86, 0xdd]
+    );
+
+    #[test]
+    fn no_optional_fields() {
+        let data = [0x00, 0x00, 0x08, 0x00, 0x45];
+        let testee = GrePacket::new(&data).unwrap();
+        assert_eq!(4, testee.header_len());
+        assert_eq!(EtherType::IPV4, testee.protocol_type());
+        assert_eq!(None, testee.checksum());
+        assert_eq!(None, testee.key());
+        assert_eq!(None, testee.sequence_number());
+        assert_eq!(&[0x45], testee.payload());
+    }
+
+    #[test]
+    fn all_optional_fields() {
+        let mut data = [0; 16 + 2];
+        {
+            let mut testee = MutGrePacket::new(&mut data).unwrap();
+            testee.set_flags(Flags::CHECKSUM | Flags::KEY | Flags::SEQUENCE);
+            testee.set_protocol_type(EtherType::IPV4);
+            assert!(testee.set_key(0xdeadbeef));
+            assert!(testee.set_sequence_number(7));
+            testee.payload().copy_from_slice(&[0xab, 0xcd]);
+            let checksum = testee.as_immutable().calculate_checksum();
+            assert!(testee.set_checksum(checksum));
+        }
+        let testee = GrePacket::new(&data).unwrap();
+        assert_eq!(16, testee.header_len());
+        assert_eq!(&[0xde, 0xad, 0xbe, 0xef], &data[8..12]);
+        assert_eq!(Some(0xdeadbeef), testee.key());
+        assert_eq!(Some(7), testee.sequence_number());
+        assert_eq!(Some(testee.calculate_checksum()), testee.checksum());
+
+        let mut sum = Checksum::new();
+        sum.add_bytes(&data);
+        assert_eq!(0, sum.finish());
+    }
+
+    #[test]
+    fn offset_depends_on_preceding_fields() {
+        let mut data = [0; 8];
+        {
+            let mut testee = MutGrePacket::new(&mut data).unwrap();
+            testee.set_flags(Flags::SEQUENCE);
+            assert!(testee.set_sequence_number(0x01020304));
+        }
+        assert_eq!(&[0x01, 0x02, 0x03, 0x04], &data[4..8]);
+        let testee = GrePacket::new(&data).unwrap();
+        assert_eq!(Some(0x01020304), testee.sequence_number());
+        assert_eq!(None, testee.key());
+    }
+
+    #[test]
+    fn truncated_optional_fields() {
+        let data = [0x30, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let testee = GrePacket::new(&data).unwrap();
+        assert_eq!(8, testee.header_len());
+        assert_eq!(Some(1), testee.key());
+        assert_eq!(None, testee.sequence_number());
+    }
+
+    #[test]
+    fn set_absent_fields() {
+        let mut data = [0; 8];
+        let mut testee = MutGrePacket::new(&mut data).unwrap();
+        assert!(!testee.set_checksum(1));
+        assert!(!testee.set_key(1));
+        assert!(!testee.set_sequence_number(1));
+        assert_eq!([0; 8], data);
+    }
+
+    #[test]
+    fn set_truncated_fields() {
+        let mut data = [0xb0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut testee = MutGrePacket::new(&mut data).unwrap();
+        assert!(testee.set_checksum(0x0102));
+        assert!(testee.set_key(0x0304_0506));
+        assert!(!testee.set_sequence_number(1));
+        let mut testee = MutGrePacket::new(&mut data[..10]).unwrap();
+        assert!(!testee.set_key(1));
+        assert_eq!([0xb0, 0, 0, 0, 1, 2, 0, 0, 3, 4, 5, 6], data);
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        GrePacketBuf => |packet| packet.flags().is_empty() && packet.version() == 0
+    );
+}
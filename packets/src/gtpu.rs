@@ -0,0 +1,380 @@
+//! The GPRS Tunnelling Protocol for the user plane, GTPv1-U, 3GPP TS 29.281. Carries user
+//! traffic between the nodes of mobile networks.
+
+use std::cmp;
+use std::fmt;
+use std::ops::Range;
+use types::*;
+
+/// The UDP port used by GTP-U.
+pub const UDP_PORT: u16 = 2152;
+
+packet!(
+    /// A GTP-U packet. The header includes the optional fields and extension headers, so for
+    /// `MessageType::G_PDU` the payload is the encapsulated user packet.
+    GtpuPacket,
+    MutGtpuPacket,
+    GtpuPacketBuf,
+    8,
+    |data| header_len(data),
+    len |data| 8 + usize::from(read_offset!(data, 2, u16, from_be)),
+    set_len |data, len| {
+        len - 8 <= usize::from(u16::MAX) && {
+            write_offset!(data, 2, (len - 8) as u16, u16, to_be);
+            true
+        }
+    },
+    init |data| {
+        // Version 1 of GTP, not GTP', without any of the optional fields.
+        data[0] = 0x20 | Flags::PROTOCOL_TYPE.bits();
+        MutGtpuPacket::set_len(data, data.len());
+    }
+);
+
+getters!(GtpuPacket
+    /// Returns the version, 1 for GTPv1.
+    pub fn version(&self) -> u3 {
+        read_offset!(self.0, 0, u8) >> 5
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 0, u8))
+    }
+
+    pub fn message_type(&self) -> MessageType {
+        MessageType(read_offset!(self.0, 1, u8))
+    }
+
+    /// Returns the length of everything following the first eight bytes of the header,
+    /// including the optional fields and extension headers.
+    pub fn length(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the tunnel endpoint identifier, chosen by the receiving end of the tunnel.
+    pub fn teid(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the sequence number, or `None` if the sequence number flag is not set.
+    pub fn sequence_number(&self) -> Option<u16> {
+        self.optional_field(Flags::SEQUENCE_NUMBER, 8..10)
+            .map(|field| read_offset!(field, 0, u16, from_be))
+    }
+
+    /// Returns the N-PDU number, or `None` if the N-PDU number flag is not set.
+    pub fn n_pdu_number(&self) -> Option<u8> {
+        self.optional_field(Flags::N_PDU_NUMBER, 10..11).map(|field| field[0])
+    }
+
+    /// Returns the type of the first extension header, or `None` if the extension header flag
+    /// is not set.
+    pub fn next_extension_header_type(&self) -> Option<ExtensionHeaderType> {
+        self.optional_field(Flags::EXTENSION_HEADER, 11..12)
+            .map(|field| ExtensionHeaderType(field[0]))
+    }
+
+    /// Returns an iterator over the extension headers.
+    pub fn extension_headers(&self) -> ExtensionHeaders<'a> {
+        match self.next_extension_header_type() {
+            Some(next_type) => ExtensionHeaders {
+                data: &self.0[12..self.header_len()],
+                next_type,
+            },
+            None => ExtensionHeaders { data: &[], next_type: ExtensionHeaderType::NO_MORE },
+        }
+    }
+);
+
+impl<'a> GtpuPacket<'a> {
+    fn optional_field(&self, flag: Flags, range: Range<usize>) -> Option<&'a [u8]> {
+        if self.flags().contains(flag) {
+            self.0.get(range)
+        } else {
+            None
+        }
+    }
+}
+
+setters!(MutGtpuPacket
+    pub fn set_version(&mut self, version: u3) {
+        let new_byte = (version << 5) | (read_offset!(self.0, 0, u8) & 0x1f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    /// Sets the flags, leaving the version and the reserved bit untouched. Setting any of the
+    /// extension header, sequence number and N-PDU number flags means the header has the four
+    /// bytes of optional fields.
+    pub fn set_flags(&mut self, flags: Flags) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xe8) | flags.bits();
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_message_type(&mut self, message_type: MessageType) {
+        write_offset!(self.0, 1, message_type.value(), u8);
+    }
+
+    pub fn set_length(&mut self, length: u16) {
+        write_offset!(self.0, 2, length, u16, to_be);
+    }
+
+    pub fn set_teid(&mut self, teid: u32) {
+        write_offset!(self.0, 4, teid, u32, to_be);
+    }
+);
+
+impl<'a> MutGtpuPacket<'a> {
+    /// Sets the sequence number and the sequence number flag. Returns false, leaving the packet
+    /// unchanged, if the optional fields do not fit in the backing slice.
+    pub fn set_sequence_number(&mut self, sequence_number: u16) -> bool {
+        self.0.len() >= 12 && {
+            write_offset!(self.0, 8, sequence_number, u16, to_be);
+            let flags = self.as_immutable().flags();
+            self.set_flags(flags | Flags::SEQUENCE_NUMBER);
+            true
+        }
+    }
+
+    /// Sets the N-PDU number and the N-PDU number flag. Returns false, leaving the packet
+    /// unchanged, if the optional fields do not fit in the backing slice.
+    pub fn set_n_pdu_number(&mut self, n_pdu_number: u8) -> bool {
+        self.0.len() >= 12 && {
+            write_offset!(self.0, 10, n_pdu_number, u8);
+            let flags = self.as_immutable().flags();
+            self.set_flags(flags | Flags::N_PDU_NUMBER);
+            true
+        }
+    }
+
+    /// Sets the type of the first extension header and the extension header flag. Returns
+    /// false, leaving the packet unchanged, if the optional fields do not fit in the backing
+    /// slice.
+    pub fn set_next_extension_header_type(&mut self, next_type: ExtensionHeaderType) -> bool {
+        self.0.len() >= 12 && {
+            write_offset!(self.0, 11, next_type.value(), u8);
+            let flags = self.as_immutable().flags();
+            self.set_flags(flags | Flags::EXTENSION_HEADER);
+            true
+        }
+    }
+}
+
+fn header_len(data: &[u8]) -> usize {
+    let flags = Flags::from_bits_truncate(data[0]);
+    if !flags.intersects(Flags::OPTIONAL_FIELDS) {
+        return 8;
+    }
+    let mut len = 12;
+    if flags.contains(Flags::EXTENSION_HEADER) {
+        let mut next_type = data.get(11).cloned().unwrap_or(0);
+        while next_type != 0 {
+            let extension_len = match data.get(len) {
+                Some(&words) if words > 0 => usize::from(words) * 4,
+                _ => break,
+            };
+            len += extension_len;
+            next_type = match data.get(len - 1) {
+                Some(&next_type) => next_type,
+                None => break,
+            };
+        }
+    }
+    cmp::min(len, data.len())
+}
+
+
+bitflags! {
+    /// Bitmasks for the flags in the first byte of the GTP-U header.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "Protocol Type" position, set for GTP and cleared for
+        /// GTP'.
+        const PROTOCOL_TYPE = 0x10;
+        /// A bitmask with a one in the "Extension Header" position.
+        const EXTENSION_HEADER = 0x04;
+        /// A bitmask with a one in the "Sequence Number" position.
+        const SEQUENCE_NUMBER = 0x02;
+        /// A bitmask with a one in the "N-PDU Number" position.
+        const N_PDU_NUMBER = 0x01;
+        /// A bitmask of the flags telling the optional fields are present.
+        const OPTIONAL_FIELDS = Self::EXTENSION_HEADER.bits | Self::SEQUENCE_NUMBER.bits |
+            Self::N_PDU_NUMBER.bits;
+    }
+}
+
+/// The type of a GTP-U message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    pub const ECHO_REQUEST: MessageType = MessageType(1);
+    pub const ECHO_RESPONSE: MessageType = MessageType(2);
+    pub const ERROR_INDICATION: MessageType = MessageType(26);
+    pub const SUPPORTED_EXTENSION_HEADERS_NOTIFICATION: MessageType = MessageType(31);
+    pub const END_MARKER: MessageType = MessageType(254);
+    /// A message carrying a user packet.
+    pub const G_PDU: MessageType = MessageType(255);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The type of a GTP-U extension header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExtensionHeaderType(pub u8);
+
+impl ExtensionHeaderType {
+    /// Marks the end of the extension headers.
+    pub const NO_MORE: ExtensionHeaderType = ExtensionHeaderType(0);
+    pub const UDP_PORT: ExtensionHeaderType = ExtensionHeaderType(0x40);
+    /// Carries the QoS flow identifier in 5G networks, 3GPP TS 38.415.
+    pub const PDU_SESSION_CONTAINER: ExtensionHeaderType = ExtensionHeaderType(0x85);
+    pub const PDCP_PDU_NUMBER: ExtensionHeaderType = ExtensionHeaderType(0xc0);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if a receiver not supporting this type must drop the packet, rather than
+    /// skip the extension header.
+    pub fn is_comprehension_required(&self) -> bool {
+        self.0 & 0x80 != 0
+    }
+}
+
+
+/// An extension header in a GTP-U packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExtensionHeader<'a> {
+    pub header_type: ExtensionHeaderType,
+    /// The content of the extension header, between the length and next type fields.
+    pub content: &'a [u8],
+}
+
+/// Iterator over the extension headers of a GTP-U packet. Iteration stops at the first
+/// extension header that does not fit in the data.
+#[derive(Clone)]
+pub struct ExtensionHeaders<'a> {
+    data: &'a [u8],
+    next_type: ExtensionHeaderType,
+}
+
+debug_items!(ExtensionHeaders<'a>);
+
+impl<'a> Iterator for ExtensionHeaders<'a> {
+    type Item = ExtensionHeader<'a>;
+
+    fn next(&mut self) -> Option<ExtensionHeader<'a>> {
+        if self.next_type == ExtensionHeaderType::NO_MORE {
+            return None;
+        }
+        let len = match self.data.first() {
+            Some(&words) if words > 0 && usize::from(words) * 4 <= self.data.len() => {
+                usize::from(words) * 4
+            }
+            _ => {
+                self.next_type = ExtensionHeaderType::NO_MORE;
+                return None;
+            }
+        };
+        let (extension, rest) = self.data.split_at(len);
+        self.data = rest;
+        let header_type = self.next_type;
+        self.next_type = ExtensionHeaderType(extension[len - 1]);
+        Some(ExtensionHeader { header_type, content: &extension[1..len - 1] })
+    }
+}
+
+impl<'a> fmt::Display for GtpuPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GTP-U type={} teid={:#010x} len={}",
+            self.message_type().value(),
+            self.teid(),
+            self.length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! gtpu_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutGtpuPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    gtpu_setget_test!(version, set_version, 1, 0, [0x20]);
+    gtpu_setget_test!(flags, set_flags, Flags::PROTOCOL_TYPE, 0, [0x10]);
+    gtpu_setget_test!(message_type, set_message_type, MessageType::G_PDU, 1, [0xff]);
+    gtpu_setget_test!(length, set_length, 0xabcd, 2, [0xab, 0xcd]);
+    gtpu_setget_test!(teid, set_teid, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 0x01]);
+
+    #[test]
+    fn optional_fields() {
+        let mut data = [0; 12];
+        {
+            let mut testee = MutGtpuPacket::new(&mut data).unwrap();
+            testee.set_version(1);
+            assert!(testee.set_sequence_number(0xabcd));
+            assert_eq!(12, testee.header_len());
+        }
+        assert_eq!([0x22, 0, 0, 0, 0, 0, 0, 0, 0xab, 0xcd, 0, 0], data);
+        let testee = GtpuPacket::new(&data).unwrap();
+        assert_eq!(Some(0xabcd), testee.sequence_number());
+        assert_eq!(None, testee.n_pdu_number());
+        assert_eq!(None, testee.next_extension_header_type());
+        assert_eq!(0, testee.extension_headers().count());
+        assert_eq!(8, GtpuPacket::new(&[0x30, 0xff, 0, 0, 0, 0, 0, 1]).unwrap().header_len());
+
+        let mut data = [0x30, 0xff, 0, 0, 0, 0, 0, 1];
+        assert!(!MutGtpuPacket::new(&mut data).unwrap().set_n_pdu_number(1));
+        assert_eq!(0x30, data[0]);
+    }
+
+    #[test]
+    fn extension_headers() {
+        let data = [
+            0x34, 0xff, 0, 16, 0, 0, 0, 1,
+            0, 0, 0, 0x85, // No sequence number, PDU session container next
+            1, 0x10, 0x09, 0x40, // PDU session container, UDP port next
+            1, 0x08, 0x68, 0x00, // UDP port, no more
+            0x45, 0x00,
+        ];
+        let testee = GtpuPacket::new(&data).unwrap();
+        assert_eq!(20, testee.header_len());
+        assert_eq!(&[0x45, 0x00], testee.payload());
+        assert_eq!(None, testee.sequence_number());
+        let extensions = testee.extension_headers().collect::<Vec<_>>();
+        assert_eq!(2, extensions.len());
+        assert_eq!(ExtensionHeaderType::PDU_SESSION_CONTAINER, extensions[0].header_type);
+        assert!(extensions[0].header_type.is_comprehension_required());
+        assert_eq!(&[0x10, 0x09], extensions[0].content);
+        assert_eq!(ExtensionHeaderType::UDP_PORT, extensions[1].header_type);
+        assert_eq!(&[0x08, 0x68], extensions[1].content);
+
+        let truncated = GtpuPacket::new(&data[..18]).unwrap();
+        assert_eq!(18, truncated.header_len());
+        assert_eq!(1, truncated.extension_headers().count());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 8 + 6];
+        let mut testee = MutGtpuPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[0x45, 0]));
+        assert_eq!(2, testee.as_immutable().length());
+        assert_eq!(&[0x45, 0], testee.as_immutable().payload());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        GtpuPacketBuf => |packet| packet.version() == 1 && packet.sequence_number().is_none()
+    );
+}
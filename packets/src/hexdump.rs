@@ -0,0 +1,205 @@
+//! Hexdumps of packet data, for debugging malformed frames.
+//!
+//! The layout is that of `hexdump -C`: an offset, sixteen bytes in hex and the same bytes as
+//! ASCII on every line. Ranges of bytes can be annotated with a label, which is printed below
+//! the line the range starts on, with the bytes it covers on that line marked. The field tables
+//! of the `layout` module can be used to label every field of a header at once.
+//!
+//! ```rust
+//! use rips_packets::hexdump::hexdump;
+//! use rips_packets::udp;
+//!
+//! let data = [0x30, 0x39, 0, 53, 0, 10, 0xab, 0xcd, b'h', b'i'];
+//! let dump = hexdump(&data).annotate_fields(0, udp::fields::NAMED).annotate(8..10, "payload");
+//! assert_eq!(
+//!     "00000000  30 39 00 35 00 0a ab cd  68 69                    |09.5....hi|\n\
+//!      \x20         ^^^^^ source_port\n\
+//!      \x20               ^^^^^ destination_port\n\
+//!      \x20                     ^^^^^ length\n\
+//!      \x20                           ^^^^^ checksum\n\
+//!      \x20                                  ^^^^^ payload\n\
+//!      0000000a\n",
+//!     dump.to_string()
+//! );
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use layout::Field;
+use std::cmp;
+use std::fmt;
+use std::ops::Range;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Returns a hexdump of `data` without annotations.
+pub fn hexdump(data: &[u8]) -> Hexdump<'_> {
+    Hexdump::new(data)
+}
+
+/// A label for a range of bytes in a `Hexdump`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Annotation {
+    pub bytes: Range<usize>,
+    pub label: String,
+}
+
+/// Formats a byte slice as a hexdump, with optional annotations, through its `Display`
+/// implementation.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Hexdump<'a> {
+    data: &'a [u8],
+    annotations: Vec<Annotation>,
+}
+
+impl<'a> Hexdump<'a> {
+    pub fn new(data: &'a [u8]) -> Hexdump<'a> {
+        Hexdump { data, annotations: Vec::new() }
+    }
+
+    /// Labels the bytes in `bytes`. Empty ranges and ranges starting after the data are never
+    /// shown.
+    pub fn annotate<S: Into<String>>(mut self, bytes: Range<usize>, label: S) -> Hexdump<'a> {
+        self.annotations.push(Annotation { bytes, label: label.into() });
+        self
+    }
+
+    /// Labels every field in `fields`, a table like `ipv4::fields::NAMED`, for a header starting
+    /// `offset` bytes into the data. The labels are the names of the fields in lower case, with
+    /// the bits they occupy for fields that do not fill their bytes.
+    pub fn annotate_fields(mut self, offset: usize, fields: &[(&str, Field)]) -> Hexdump<'a> {
+        for &(name, field) in fields {
+            let mut label = name.to_lowercase();
+            if field.start % 8 != 0 || field.width % 8 != 0 {
+                let start = field.start - field.byte_offset() * 8;
+                label = format!("{} (bits {}..{})", label, start, start + field.width);
+            }
+            let bytes = field.byte_range();
+            self = self.annotate(offset + bytes.start..offset + bytes.end, label);
+        }
+        self
+    }
+
+    /// Returns the annotations, in the order they were added.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+}
+
+/// Returns the column of the hex digits of the byte at `index` within a line.
+fn column(index: usize) -> usize {
+    10 + index * 3 + if index >= BYTES_PER_LINE / 2 { 1 } else { 0 }
+}
+
+impl<'a> fmt::Display for Hexdump<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (line, chunk) in self.data.chunks(BYTES_PER_LINE).enumerate() {
+            let start = line * BYTES_PER_LINE;
+            let mut text = format!("{:08x} ", start);
+            for (i, byte) in chunk.iter().enumerate() {
+                if i == BYTES_PER_LINE / 2 {
+                    text.push(' ');
+                }
+                text.push_str(&format!(" {:02x}", byte));
+            }
+            while text.len() < column(BYTES_PER_LINE) {
+                text.push(' ');
+            }
+            text.push_str(" |");
+            for &byte in chunk {
+                let printable = byte == b' ' || byte.is_ascii_graphic();
+                text.push(if printable { byte as char } else { '.' });
+            }
+            writeln!(f, "{}|", text)?;
+
+            let end = start + chunk.len();
+            for annotation in &self.annotations {
+                let bytes = &annotation.bytes;
+                if bytes.start >= bytes.end || bytes.start < start || bytes.start >= end {
+                    continue;
+                }
+                let first = bytes.start - start;
+                let last = cmp::min(bytes.end, end) - start - 1;
+                let marker = "^".repeat(column(last) + 2 - column(first));
+                let indent = column(first);
+                writeln!(f, "{:indent$}{} {}", "", marker, annotation.label, indent = indent)?;
+            }
+        }
+        writeln!(f, "{:08x}", self.data.len())
+    }
+}
+
+impl<'a> From<Hexdump<'a>> for String {
+    fn from(hexdump: Hexdump<'a>) -> String {
+        hexdump.to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv4;
+
+    #[test]
+    fn lines() {
+        let data: Vec<u8> = (0x20..0x33).collect();
+        assert_eq!(
+            "00000000  20 21 22 23 24 25 26 27  28 29 2a 2b 2c 2d 2e 2f  | !\"#$%&'()*+,-./|\n\
+             00000010  30 31 32                                          |012|\n\
+             00000013\n",
+            hexdump(&data).to_string()
+        );
+        assert_eq!("00000000\n", hexdump(&[]).to_string());
+        assert_eq!(
+            "00000000  00 7f ff                                          |...|\n00000003\n",
+            hexdump(&[0, 0x7f, 0xff]).to_string()
+        );
+    }
+
+    #[test]
+    fn annotations() {
+        let data = [0; 20];
+        let dump = hexdump(&data)
+            .annotate(6..10, "across the gap")
+            .annotate(14..18, "across lines")
+            .annotate(3..3, "empty")
+            .annotate(20..24, "outside");
+        assert_eq!(
+            "00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n\
+             \x20                           ^^^^^^^^^^^^ across the gap\n\
+             \x20                                                    ^^^^^ across lines\n\
+             00000010  00 00 00 00                                       |....|\n\
+             00000014\n",
+            dump.to_string()
+        );
+        assert_eq!(4, dump.annotations().len());
+    }
+
+    #[test]
+    fn field_labels() {
+        let data = [0; 20];
+        let dump = hexdump(&data).annotate_fields(14, ipv4::fields::NAMED);
+        let labels: Vec<&str> = dump.annotations().iter().map(|a| a.label.as_str()).collect();
+        assert_eq!("version (bits 0..4)", labels[0]);
+        assert_eq!("header_length (bits 4..8)", labels[1]);
+        assert_eq!("fragment_offset (bits 3..16)", labels[7]);
+        assert_eq!("source", labels[11]);
+        assert_eq!(26..30, dump.annotations()[11].bytes);
+    }
+
+    #[test]
+    fn packet_hexdump() {
+        let mut data = [0; 25];
+        data[0] = 0x45;
+        data[3] = 24;
+        let dump = ipv4::Ipv4Packet::new(&data).unwrap().hexdump();
+        assert_eq!(
+            &[
+                Annotation { bytes: 0..20, label: "header".to_owned() },
+                Annotation { bytes: 20..24, label: "payload".to_owned() },
+            ],
+            dump.annotations()
+        );
+    }
+}
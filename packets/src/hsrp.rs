@@ -0,0 +1,482 @@
+//! The Hot Standby Router Protocol, RFC 2281 for version 1. Version 2 is a Cisco extension.
+//!
+//! Version 1 packets have a fixed 20 byte layout. Version 2 packets are a sequence of TLVs,
+//! where the group state TLV carries what a version 1 packet does, with wider fields and
+//! support for IPv6. A version 1 packet starts with the version zero, while a version 2 packet
+//! starts with the type of its first TLV, normally a group state TLV.
+
+use ethernet::MacAddr;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The UDP port of HSRP over IPv4.
+pub const UDP_PORT: u16 = 1985;
+
+/// The UDP port of HSRP version 2 over IPv6.
+pub const IPV6_UDP_PORT: u16 = 2029;
+
+/// The multicast group version 1 packets are sent to, the all routers group.
+pub const V1_MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 2);
+
+/// The IPv4 multicast group version 2 packets are sent to.
+pub const V2_MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 102);
+
+/// The IPv6 multicast group version 2 packets are sent to.
+pub const V2_IPV6_MULTICAST_ADDRESS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x66);
+
+/// The authentication data used when none is configured, "cisco" padded with zeros.
+pub const DEFAULT_AUTHENTICATION: [u8; 8] = *b"cisco\0\0\0";
+
+packet!(
+    /// An HSRP version 1 packet.
+    HsrpPacket,
+    MutHsrpPacket,
+    HsrpPacketBuf,
+    20,
+    |_data| 20,
+    init |data| data[0] = 0
+);
+
+getters!(HsrpPacket
+    /// Returns the version, zero for version 1.
+    pub fn version(&self) -> u8 {
+        read_offset!(self.0, 0, u8)
+    }
+
+    pub fn op_code(&self) -> OpCode {
+        OpCode(read_offset!(self.0, 1, u8))
+    }
+
+    pub fn state(&self) -> State {
+        State(read_offset!(self.0, 2, u8))
+    }
+
+    /// Returns the interval between hello messages, in seconds.
+    pub fn hello_time(&self) -> u8 {
+        read_offset!(self.0, 3, u8)
+    }
+
+    /// Returns the time a hello message is valid, in seconds.
+    pub fn hold_time(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    /// Returns the priority of the sending router, higher values winning the election.
+    pub fn priority(&self) -> u8 {
+        read_offset!(self.0, 5, u8)
+    }
+
+    /// Returns the standby group number.
+    pub fn group(&self) -> u8 {
+        read_offset!(self.0, 6, u8)
+    }
+
+    pub fn reserved(&self) -> u8 {
+        read_offset!(self.0, 7, u8)
+    }
+
+    /// Returns the cleartext authentication data.
+    pub fn authentication(&self) -> [u8; 8] {
+        read_offset!(self.0, 8, [u8; 8])
+    }
+
+    /// Returns the virtual IP address of the group.
+    pub fn virtual_ip(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 16, [u8; 4]))
+    }
+);
+
+setters!(MutHsrpPacket
+    pub fn set_version(&mut self, version: u8) {
+        write_offset!(self.0, 0, version, u8);
+    }
+
+    pub fn set_op_code(&mut self, op_code: OpCode) {
+        write_offset!(self.0, 1, op_code.value(), u8);
+    }
+
+    pub fn set_state(&mut self, state: State) {
+        write_offset!(self.0, 2, state.value(), u8);
+    }
+
+    pub fn set_hello_time(&mut self, hello_time: u8) {
+        write_offset!(self.0, 3, hello_time, u8);
+    }
+
+    pub fn set_hold_time(&mut self, hold_time: u8) {
+        write_offset!(self.0, 4, hold_time, u8);
+    }
+
+    pub fn set_priority(&mut self, priority: u8) {
+        write_offset!(self.0, 5, priority, u8);
+    }
+
+    pub fn set_group(&mut self, group: u8) {
+        write_offset!(self.0, 6, group, u8);
+    }
+
+    pub fn set_reserved(&mut self, reserved: u8) {
+        write_offset!(self.0, 7, reserved, u8);
+    }
+
+    pub fn set_authentication(&mut self, authentication: [u8; 8]) {
+        write_offset!(self.0, 8, authentication, [u8; 8]);
+    }
+
+    pub fn set_virtual_ip(&mut self, virtual_ip: Ipv4Addr) {
+        write_offset!(self.0, 16, virtual_ip.octets(), [u8; 4]);
+    }
+);
+
+
+/// The operation of an HSRP packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OpCode(pub u8);
+
+impl OpCode {
+    pub const HELLO: OpCode = OpCode(0);
+    pub const COUP: OpCode = OpCode(1);
+    pub const RESIGN: OpCode = OpCode(2);
+    /// Only used in version 2.
+    pub const ADVERTISE: OpCode = OpCode(3);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The state of the router sending an HSRP packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct State(pub u8);
+
+impl State {
+    pub const INITIAL: State = State(0);
+    pub const LEARN: State = State(1);
+    pub const LISTEN: State = State(2);
+    pub const SPEAK: State = State(4);
+    pub const STANDBY: State = State(8);
+    pub const ACTIVE: State = State(16);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The type of an HSRP version 2 TLV.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TlvType(pub u8);
+
+impl TlvType {
+    pub const GROUP_STATE: TlvType = TlvType(1);
+    pub const INTERFACE_STATE: TlvType = TlvType(2);
+    pub const TEXT_AUTHENTICATION: TlvType = TlvType(3);
+    pub const MD5_AUTHENTICATION: TlvType = TlvType(4);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+/// A TLV in an HSRP version 2 packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Tlv<'a> {
+    pub tlv_type: TlvType,
+    pub data: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Returns the TLV as a group state TLV, or `None` if it is of another type or too short.
+    pub fn group_state(&self) -> Option<GroupStatePacket<'a>> {
+        if self.tlv_type == TlvType::GROUP_STATE {
+            GroupStatePacket::new(self.data)
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the TLVs of an HSRP version 2 packet. Iteration stops at the first TLV that
+/// does not fit in the data.
+#[derive(Clone)]
+pub struct Tlvs<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Tlvs<'a>);
+
+impl<'a> Tlvs<'a> {
+    /// Creates an iterator over the TLVs encoded in `data`, usually the whole UDP payload.
+    pub fn new(data: &'a [u8]) -> Tlvs<'a> {
+        Tlvs { data }
+    }
+}
+
+impl<'a> Iterator for Tlvs<'a> {
+    type Item = Tlv<'a>;
+
+    fn next(&mut self) -> Option<Tlv<'a>> {
+        if self.data.len() < 2 {
+            return None;
+        }
+        let len = 2 + usize::from(self.data[1]);
+        if len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (tlv, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(Tlv {
+            tlv_type: TlvType(tlv[0]),
+            data: &tlv[2..],
+        })
+    }
+}
+
+
+packet!(
+    /// The value of an HSRP version 2 group state TLV, without the type and length.
+    GroupStatePacket,
+    MutGroupStatePacket,
+    GroupStatePacketBuf,
+    40,
+    |_data| 40,
+    init |data| data[0] = 2
+);
+
+getters!(GroupStatePacket
+    /// Returns the version, 2 for version 2.
+    pub fn version(&self) -> u8 {
+        read_offset!(self.0, 0, u8)
+    }
+
+    pub fn op_code(&self) -> OpCode {
+        OpCode(read_offset!(self.0, 1, u8))
+    }
+
+    pub fn state(&self) -> State {
+        State(read_offset!(self.0, 2, u8))
+    }
+
+    /// Returns the version of the virtual IP address, 4 or 6.
+    pub fn ip_version(&self) -> u8 {
+        read_offset!(self.0, 3, u8)
+    }
+
+    /// Returns the standby group number.
+    pub fn group(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    /// Returns the identifier of the sending router, normally its MAC address.
+    pub fn identifier(&self) -> MacAddr {
+        MacAddr::from_slice(&self.0[6..12])
+    }
+
+    /// Returns the priority of the sending router, higher values winning the election.
+    pub fn priority(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns the interval between hello messages, in milliseconds.
+    pub fn hello_time(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns the time a hello message is valid, in milliseconds.
+    pub fn hold_time(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    /// Returns the virtual IP address of the group, or `None` if the IP version is neither 4
+    /// nor 6.
+    pub fn virtual_ip(&self) -> Option<IpAddr> {
+        match self.ip_version() {
+            4 => Some(IpAddr::V4(Ipv4Addr::from(read_offset!(self.0, 24, [u8; 4])))),
+            6 => Some(IpAddr::V6(Ipv6Addr::from(read_offset!(self.0, 24, [u8; 16])))),
+            _ => None,
+        }
+    }
+);
+
+setters!(MutGroupStatePacket
+    pub fn set_version(&mut self, version: u8) {
+        write_offset!(self.0, 0, version, u8);
+    }
+
+    pub fn set_op_code(&mut self, op_code: OpCode) {
+        write_offset!(self.0, 1, op_code.value(), u8);
+    }
+
+    pub fn set_state(&mut self, state: State) {
+        write_offset!(self.0, 2, state.value(), u8);
+    }
+
+    pub fn set_ip_version(&mut self, ip_version: u8) {
+        write_offset!(self.0, 3, ip_version, u8);
+    }
+
+    pub fn set_group(&mut self, group: u16) {
+        write_offset!(self.0, 4, group, u16, to_be);
+    }
+
+    pub fn set_identifier(&mut self, identifier: MacAddr) {
+        self.0[6..12].copy_from_slice(identifier.as_ref());
+    }
+
+    pub fn set_priority(&mut self, priority: u32) {
+        write_offset!(self.0, 12, priority, u32, to_be);
+    }
+
+    pub fn set_hello_time(&mut self, hello_time: u32) {
+        write_offset!(self.0, 16, hello_time, u32, to_be);
+    }
+
+    pub fn set_hold_time(&mut self, hold_time: u32) {
+        write_offset!(self.0, 20, hold_time, u32, to_be);
+    }
+
+    /// Sets the virtual IP address and the IP version. An IPv4 address is padded with zeros.
+    pub fn set_virtual_ip(&mut self, virtual_ip: IpAddr) {
+        match virtual_ip {
+            IpAddr::V4(address) => {
+                self.set_ip_version(4);
+                self.0[24..28].copy_from_slice(&address.octets());
+                for byte in &mut self.0[28..40] {
+                    *byte = 0;
+                }
+            }
+            IpAddr::V6(address) => {
+                self.set_ip_version(6);
+                self.0[24..40].copy_from_slice(&address.octets());
+            }
+        }
+    }
+);
+
+impl<'a> fmt::Display for HsrpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HSRP group={} state={} priority={} virtual_ip={}",
+            self.group(),
+            self.state().value(),
+            self.priority(),
+            self.virtual_ip()
+        )
+    }
+}
+
+impl<'a> fmt::Display for GroupStatePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HSRPv2 group={} state={} priority={}",
+            self.group(),
+            self.state().value(),
+            self.priority()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! hsrp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutHsrpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    hsrp_setget_test!(version, set_version, 0xab, 0, [0xab]);
+    hsrp_setget_test!(op_code, set_op_code, OpCode::RESIGN, 1, [2]);
+    hsrp_setget_test!(state, set_state, State::ACTIVE, 2, [16]);
+    hsrp_setget_test!(hello_time, set_hello_time, 0xab, 3, [0xab]);
+    hsrp_setget_test!(hold_time, set_hold_time, 0xab, 4, [0xab]);
+    hsrp_setget_test!(priority, set_priority, 0xab, 5, [0xab]);
+    hsrp_setget_test!(group, set_group, 0xab, 6, [0xab]);
+    hsrp_setget_test!(reserved, set_reserved, 0xab, 7, [0xab]);
+    hsrp_setget_test!(authentication, set_authentication, DEFAULT_AUTHENTICATION, 8, *b"cisco");
+    hsrp_setget_test!(
+        virtual_ip,
+        set_virtual_ip,
+        Ipv4Addr::new(10, 0, 0, 1),
+        16,
+        [10, 0, 0, 1]
+    );
+
+    #[test]
+    fn v2_tlvs() {
+        let mut data = [0; 2 + 40 + 2 + 4 + 2];
+        data[0] = TlvType::GROUP_STATE.value();
+        data[1] = 40;
+        {
+            let mut group_state = MutGroupStatePacket::new(&mut data[2..42]).unwrap();
+            group_state.set_version(2);
+            group_state.set_state(State::STANDBY);
+            group_state.set_group(1000);
+            group_state.set_virtual_ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        }
+        data[42..48].copy_from_slice(&[2, 4, 0, 1, 0, 0]);
+        data[48..].copy_from_slice(&[3, 8]);
+
+        let tlvs = Tlvs::new(&data).collect::<Vec<_>>();
+        assert_eq!(2, tlvs.len());
+        let group_state = tlvs[0].group_state().unwrap();
+        assert_eq!(State::STANDBY, group_state.state());
+        assert_eq!(1000, group_state.group());
+        assert_eq!(Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))), group_state.virtual_ip());
+        assert_eq!(TlvType::INTERFACE_STATE, tlvs[1].tlv_type);
+        assert_eq!(None, tlvs[1].group_state());
+    }
+
+    mod group_state {
+        use super::super::*;
+
+        macro_rules! group_state_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutGroupStatePacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        group_state_setget_test!(ip_version, set_ip_version, 6, 3, [6]);
+        group_state_setget_test!(group, set_group, 0xabcd, 4, [0xab, 0xcd]);
+        group_state_setget_test!(
+            identifier,
+            set_identifier,
+            MacAddr([1, 2, 3, 4, 5, 6]),
+            6,
+            [1, 2, 3, 4, 5, 6]
+        );
+        group_state_setget_test!(priority, set_priority, 0xabcdef01, 12, [0xab, 0xcd, 0xef, 1]);
+        group_state_setget_test!(hello_time, set_hello_time, 3000, 16, [0, 0, 0x0b, 0xb8]);
+        group_state_setget_test!(hold_time, set_hold_time, 10000, 20, [0, 0, 0x27, 0x10]);
+
+        #[test]
+        fn virtual_ip() {
+            let mut data = [0xff; 40];
+            let address = "2001:db8::1".parse().unwrap();
+            {
+                let mut testee = MutGroupStatePacket::new(&mut data).unwrap();
+                testee.set_virtual_ip(IpAddr::V6(address));
+                assert_eq!(Some(IpAddr::V6(address)), testee.as_immutable().virtual_ip());
+                testee.set_virtual_ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+            }
+            assert_eq!(&[10, 0, 0, 1], &data[24..28]);
+            assert!(data[28..].iter().all(|&byte| byte == 0));
+            data[3] = 5;
+            assert_eq!(None, GroupStatePacket::new(&data).unwrap().virtual_ip());
+        }
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        HsrpPacketBuf => |packet| packet.version() == 0,
+        GroupStatePacketBuf => |packet| packet.version() == 2,
+    );
+}
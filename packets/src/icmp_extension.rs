@@ -0,0 +1,378 @@
+//! Extension structures of multi-part ICMP messages, RFC 4884.
+//!
+//! A Destination Unreachable or Time Exceeded message can append an extension structure to the
+//! original datagram it quotes. The length field of the message then gives the length of the
+//! zero padded original datagram, at least 128 bytes, and the extension structure follows it.
+//! The structure is a header of its own followed by extension objects, such as the MPLS label
+//! stack of the packet (RFC 4950) or the interface it arrived on (RFC 5837).
+
+use checksum::Checksum;
+use mpls::MplsPacket;
+use std::cmp;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str;
+use types::*;
+
+/// The version of the extension structure defined by RFC 4884.
+pub const VERSION: u4 = 2;
+
+/// The minimal length of the original datagram in a message with extensions.
+pub const MIN_ORIGINAL_DATAGRAM_LEN: usize = 128;
+
+/// Returns the extension structure in the payload of an ICMP error message, everything after
+/// the first eight bytes, given the length field of the message in 32 bit words. Returns `None`
+/// if the message has no extensions.
+///
+/// Messages from implementations that predate RFC 4884 carry extensions without setting the
+/// length field. For those, a structure at the minimal offset of 128 bytes is accepted as long
+/// as its checksum is valid.
+pub fn extension(payload: &[u8], length: u8) -> Option<ExtensionPacket<'_>> {
+    let start = usize::from(length) * 4;
+    if length == 0 {
+        payload
+            .get(MIN_ORIGINAL_DATAGRAM_LEN..)
+            .and_then(ExtensionPacket::new)
+            .filter(|extension| {
+                extension.version() == VERSION &&
+                    extension.checksum() == extension.calculate_checksum()
+            })
+    } else if start >= MIN_ORIGINAL_DATAGRAM_LEN {
+        payload
+            .get(start..)
+            .and_then(ExtensionPacket::new)
+            .filter(|extension| extension.version() == VERSION)
+    } else {
+        None
+    }
+}
+
+/// Returns the original datagram in the payload of an ICMP error message, given the length
+/// field of the message in 32 bit words. This is the whole payload unless the message has
+/// extensions, see `extension`.
+pub fn original_datagram(payload: &[u8], length: u8) -> &[u8] {
+    let end = match length {
+        0 if extension(payload, 0).is_some() => MIN_ORIGINAL_DATAGRAM_LEN,
+        0 => payload.len(),
+        _ => cmp::min(usize::from(length) * 4, payload.len()),
+    };
+    &payload[..end]
+}
+
+
+packet!(
+    /// An ICMP extension structure. The payload holds the extension objects.
+    ExtensionPacket,
+    MutExtensionPacket,
+    ExtensionPacketBuf,
+    4,
+    |_data| 4,
+    init |data| write_offset!(data, 0, [0x20, 0], [u8; 2])
+);
+
+getters!(ExtensionPacket
+    pub fn version(&self) -> u4 {
+        read_offset!(self.0, 0, u8) >> 4
+    }
+
+    pub fn checksum(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Computes the checksum of the entire backing slice, ignoring the current value of the
+    /// checksum field.
+    pub fn calculate_checksum(&self) -> u16 {
+        let mut checksum = Checksum::new();
+        checksum.add_bytes(&self.0[..2]);
+        checksum.add_bytes(&self.0[4..]);
+        checksum.finish()
+    }
+
+    /// Returns an iterator over the extension objects.
+    pub fn objects(&self) -> ExtensionObjects<'a> {
+        ExtensionObjects { data: &self.0[4..] }
+    }
+);
+
+setters!(MutExtensionPacket
+    pub fn set_version(&mut self, version: u4) {
+        let new_byte = (version << 4) | (read_offset!(self.0, 0, u8) & 0x0f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        write_offset!(self.0, 2, checksum, u16, to_be);
+    }
+);
+
+
+/// The class of an extension object. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/icmp-parameters/icmp-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ClassNum(pub u8);
+
+impl ClassNum {
+    pub const MPLS_LABEL_STACK: ClassNum = ClassNum(1);
+    pub const INTERFACE_INFORMATION: ClassNum = ClassNum(2);
+    pub const INTERFACE_IDENTIFICATION: ClassNum = ClassNum(3);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// A single extension object.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExtensionObject<'a> {
+    pub class_num: ClassNum,
+    /// The type of the object within its class.
+    pub c_type: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> ExtensionObject<'a> {
+    /// Returns the MPLS label stack of the datagram that triggered the message, or `None` if
+    /// this is not an incoming MPLS label stack object.
+    pub fn mpls_label_stack(&self) -> Option<MplsPacket<'a>> {
+        if self.class_num == ClassNum::MPLS_LABEL_STACK && self.c_type == 1 {
+            MplsPacket::new(self.data)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the interface information in this object, or `None` if this is not an interface
+    /// information object or the object is truncated.
+    pub fn interface_information(&self) -> Option<InterfaceInformation<'a>> {
+        if self.class_num != ClassNum::INTERFACE_INFORMATION {
+            return None;
+        }
+        let mut data = self.data;
+        let mut take = |len: usize| {
+            let field = data.get(..len)?;
+            data = &data[len..];
+            Some(field)
+        };
+        let if_index = if self.c_type & 0x08 != 0 {
+            Some(read_offset!(take(4)?, 0, u32, from_be))
+        } else {
+            None
+        };
+        let address = if self.c_type & 0x04 != 0 {
+            let afi = read_offset!(take(4)?, 0, u16, from_be);
+            Some(match afi {
+                1 => IpAddr::V4(Ipv4Addr::from(read_offset!(take(4)?, 0, [u8; 4]))),
+                2 => IpAddr::V6(Ipv6Addr::from(read_offset!(take(16)?, 0, [u8; 16]))),
+                _ => return None,
+            })
+        } else {
+            None
+        };
+        let name = if self.c_type & 0x02 != 0 {
+            let len = usize::from(*take(1)?.first()?);
+            let name = take(len.checked_sub(1)?)?;
+            let end = name.iter().position(|&byte| byte == 0).unwrap_or(name.len());
+            Some(str::from_utf8(&name[..end]).ok()?)
+        } else {
+            None
+        };
+        let mtu = if self.c_type & 0x01 != 0 {
+            Some(read_offset!(take(4)?, 0, u32, from_be))
+        } else {
+            None
+        };
+        Some(InterfaceInformation {
+            role: InterfaceRole(self.c_type >> 6),
+            if_index,
+            address,
+            name,
+            mtu,
+        })
+    }
+}
+
+/// The role of the interface described by an interface information object.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct InterfaceRole(pub u2);
+
+impl InterfaceRole {
+    pub const INCOMING_IP_INTERFACE: InterfaceRole = InterfaceRole(0);
+    pub const SUB_IP_COMPONENT: InterfaceRole = InterfaceRole(1);
+    pub const OUTGOING_IP_INTERFACE: InterfaceRole = InterfaceRole(2);
+    pub const NEXT_HOP_IP_ADDRESS: InterfaceRole = InterfaceRole(3);
+
+    #[inline]
+    pub fn value(&self) -> u2 {
+        self.0
+    }
+}
+
+/// The contents of an interface information object, RFC 5837. Each field is only present if
+/// the object includes it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct InterfaceInformation<'a> {
+    pub role: InterfaceRole,
+    pub if_index: Option<u32>,
+    pub address: Option<IpAddr>,
+    /// The interface name, without the padding.
+    pub name: Option<&'a str>,
+    pub mtu: Option<u32>,
+}
+
+/// Iterator over the objects in an extension structure. Iteration stops at the first object
+/// whose length is invalid or does not fit in the data.
+#[derive(Clone)]
+pub struct ExtensionObjects<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(ExtensionObjects<'a>);
+
+impl<'a> Iterator for ExtensionObjects<'a> {
+    type Item = ExtensionObject<'a>;
+
+    fn next(&mut self) -> Option<ExtensionObject<'a>> {
+        if self.data.len() < 4 {
+            self.data = &[];
+            return None;
+        }
+        let len = usize::from(read_offset!(self.data, 0, u16, from_be));
+        if len < 4 || len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (object, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(ExtensionObject {
+            class_num: ClassNum(object[2]),
+            c_type: object[3],
+            data: &object[4..],
+        })
+    }
+}
+
+impl<'a> fmt::Display for ExtensionPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ICMP extension version={} objects={}",
+            self.version(),
+            self.objects().count()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! extension_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutExtensionPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    extension_setget_test!(version, set_version, 0xf, 0, [0xf0]);
+    extension_setget_test!(checksum, set_checksum, 0xabcd, 2, [0xab, 0xcd]);
+
+    /// Returns a payload with a 128 byte original datagram followed by an extension structure
+    /// holding an MPLS label stack object with two entries.
+    fn payload_with_mpls() -> Vec<u8> {
+        let mut payload = vec![0; 128];
+        payload[0] = 0x45;
+        payload.extend_from_slice(&[0x20, 0, 0, 0]);
+        payload.extend_from_slice(&[0, 12, 1, 1]);
+        payload.extend_from_slice(&[0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x21, 0x01]);
+        let checksum = ExtensionPacket::new(&payload[128..]).unwrap().calculate_checksum();
+        MutExtensionPacket::new(&mut payload[128..]).unwrap().set_checksum(checksum);
+        payload
+    }
+
+    #[test]
+    fn mpls_label_stack() {
+        let payload = payload_with_mpls();
+        let testee = extension(&payload, 32).unwrap();
+        assert_eq!(VERSION, testee.version());
+        let objects = testee.objects().collect::<Vec<_>>();
+        assert_eq!(1, objects.len());
+        assert_eq!(ClassNum::MPLS_LABEL_STACK, objects[0].class_num);
+        let stack = objects[0].mpls_label_stack().unwrap();
+        let labels = stack.entries().map(|entry| entry.label()).collect::<Vec<_>>();
+        assert_eq!(vec![16, 2], labels);
+        assert_eq!(None, objects[0].interface_information());
+        assert_eq!(128, original_datagram(&payload, 32).len());
+    }
+
+    #[test]
+    fn compatibility_without_length() {
+        let mut payload = payload_with_mpls();
+        assert!(extension(&payload, 0).is_some());
+        assert_eq!(128, original_datagram(&payload, 0).len());
+        // Without the length field only a structure with a valid checksum is accepted
+        payload[140] ^= 0xff;
+        assert!(extension(&payload, 0).is_none());
+        assert!(extension(&payload, 32).is_some());
+        assert_eq!(payload.len(), original_datagram(&payload, 0).len());
+    }
+
+    #[test]
+    fn no_extension() {
+        let payload = [0; 28];
+        assert!(extension(&payload, 0).is_none());
+        assert!(extension(&payload, 7).is_none());
+        assert_eq!(28, original_datagram(&payload, 0).len());
+        assert_eq!(20, original_datagram(&payload, 5).len());
+        // A length shorter than the minimal original datagram means no extensions
+        assert!(extension(&payload_with_mpls(), 31).is_none());
+    }
+
+    #[test]
+    fn interface_information() {
+        let data = [
+            0x00, 0x00, 0x00, 0x05, // ifIndex
+            0x00, 0x01, 0x00, 0x00, 192, 0, 2, 1, // IPv4 address
+            0x08, b'e', b't', b'h', b'0', 0, 0, 0, // Name
+            0x00, 0x00, 0x05, 0xdc, // MTU
+        ];
+        let object = ExtensionObject {
+            class_num: ClassNum::INTERFACE_INFORMATION,
+            c_type: 0x80 | 0x0f,
+            data: &data,
+        };
+        let expected = InterfaceInformation {
+            role: InterfaceRole::OUTGOING_IP_INTERFACE,
+            if_index: Some(5),
+            address: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+            name: Some("eth0"),
+            mtu: Some(1500),
+        };
+        assert_eq!(Some(expected), object.interface_information());
+
+        let only_mtu = ExtensionObject {
+            c_type: 0x01,
+            data: &data[20..],
+            ..object
+        };
+        let information = only_mtu.interface_information().unwrap();
+        assert_eq!(InterfaceRole::INCOMING_IP_INTERFACE, information.role);
+        assert_eq!((None, Some(1500)), (information.if_index, information.mtu));
+
+        let truncated = ExtensionObject {
+            data: &data[..23],
+            ..object
+        };
+        assert_eq!(None, truncated.interface_information());
+    }
+
+    #[test]
+    fn invalid_object_length() {
+        let data = [0x20, 0, 0, 0, 0, 8, 1, 1, 0, 0, 0x01, 0x00, 0, 3, 2, 0];
+        let testee = ExtensionPacket::new(&data).unwrap();
+        assert_eq!(1, testee.objects().count());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, ExtensionPacketBuf => |packet| packet.version() == 2);
+}
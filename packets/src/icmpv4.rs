@@ -0,0 +1,563 @@
+use checksum::Checksum;
+use icmp_extension::{self, ExtensionPacket};
+use ipv4::Ipv4Packet;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// Implements the fields common to all ICMPv4 messages, and the checksum calculation over the
+/// whole message, for a pair of ICMPv4 packet types.
+macro_rules! icmpv4_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn icmp_type(&self) -> Icmpv4Type {
+                Icmpv4Type(read_offset!(self.0, 0, u8))
+            }
+
+            pub fn checksum(&self) -> u16 {
+                read_offset!(self.0, 2, u16, from_be)
+            }
+
+            /// Computes the checksum of the entire backing slice, ignoring the current value of
+            /// the checksum field.
+            pub fn calculate_checksum(&self) -> u16 {
+                let mut checksum = Checksum::new();
+                checksum.add_bytes(&self.0[..2]);
+                checksum.add_bytes(&self.0[4..]);
+                checksum.finish()
+            }
+
+            /// Returns true if the checksum field holds the checksum of the entire backing
+            /// slice.
+            pub fn verify_checksum(&self) -> bool {
+                $crate::checksum::internet_checksum(self.0) == 0
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_icmp_type(&mut self, icmp_type: Icmpv4Type) {
+                write_offset!(self.0, 0, icmp_type.value(), u8);
+            }
+
+            pub fn set_checksum(&mut self, checksum: u16) {
+                write_offset!(self.0, 2, checksum, u16, to_be);
+            }
+        );
+    }
+}
+
+/// Sets the type field of the message in `data` to `icmp_type`, if given, and then the checksum
+/// field to the checksum of the message.
+fn init_message(data: &mut [u8], icmp_type: Option<Icmpv4Type>) {
+    if let Some(icmp_type) = icmp_type {
+        write_offset!(data, 0, icmp_type.value(), u8);
+    }
+    let checksum = Icmpv4Packet(data).calculate_checksum();
+    write_offset!(data, 2, checksum, u16, to_be);
+}
+
+packet!(
+    Icmpv4Packet,
+    MutIcmpv4Packet,
+    Icmpv4PacketBuf,
+    8,
+    |_data| 8,
+    init |data| init_message(data, None)
+);
+icmpv4_common!(Icmpv4Packet, MutIcmpv4Packet);
+
+getters!(Icmpv4Packet
+    pub fn code(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns the four type specific bytes following the checksum.
+    pub fn rest_of_header(&self) -> &[u8] {
+        &self.0[4..8]
+    }
+);
+
+setters!(MutIcmpv4Packet
+    pub fn set_code(&mut self, code: u8) {
+        write_offset!(self.0, 1, code, u8);
+    }
+);
+
+
+packet!(
+    /// An Echo Request or Echo Reply message, as used by ping. The two share the same layout and
+    /// are told apart by `icmp_type`.
+    EchoPacket,
+    MutEchoPacket,
+    EchoPacketBuf,
+    8,
+    |_data| 8,
+    init |data| init_message(data, Some(Icmpv4Type::ECHO_REQUEST))
+);
+icmpv4_common!(EchoPacket, MutEchoPacket);
+
+getters!(EchoPacket
+    pub fn code(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    pub fn identifier(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    pub fn sequence_number(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+);
+
+setters!(MutEchoPacket
+    pub fn set_code(&mut self, code: u8) {
+        write_offset!(self.0, 1, code, u8);
+    }
+
+    pub fn set_identifier(&mut self, identifier: u16) {
+        write_offset!(self.0, 4, identifier, u16, to_be);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u16) {
+        write_offset!(self.0, 6, sequence_number, u16, to_be);
+    }
+);
+
+
+packet!(
+    /// A Destination Unreachable message. The payload holds the IP header and at least the first
+    /// eight payload bytes of the datagram that could not be delivered.
+    DestinationUnreachablePacket,
+    MutDestinationUnreachablePacket,
+    DestinationUnreachablePacketBuf,
+    8,
+    |_data| 8,
+    init |data| {
+        // Without extensions, so that the length field is zero.
+        write_offset!(data, 5, 0u8, u8);
+        init_message(data, Some(Icmpv4Type::DESTINATION_UNREACHABLE));
+    }
+);
+icmpv4_common!(DestinationUnreachablePacket, MutDestinationUnreachablePacket);
+
+getters!(DestinationUnreachablePacket
+    pub fn code(&self) -> DestinationUnreachableCode {
+        DestinationUnreachableCode(read_offset!(self.0, 1, u8))
+    }
+
+    /// Returns the length of the original datagram in 32 bit words, or zero if the message has
+    /// no extensions, RFC 4884.
+    pub fn length(&self) -> u8 {
+        read_offset!(self.0, 5, u8)
+    }
+
+    /// Returns the MTU of the next hop. Only valid for the
+    /// `DestinationUnreachableCode::FRAGMENTATION_NEEDED` code.
+    pub fn next_hop_mtu(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    /// Returns the header of the datagram that triggered this message, or `None` if the
+    /// payload is too short to hold an IPv4 header. The backing slice ends before any
+    /// extensions.
+    pub fn original_datagram(&self) -> Option<Ipv4Packet<'a>> {
+        Ipv4Packet::new(icmp_extension::original_datagram(&self.0[8..], self.length()))
+    }
+
+    /// Returns the extension structure following the original datagram, or `None` if the
+    /// message has no extensions.
+    pub fn extension(&self) -> Option<ExtensionPacket<'a>> {
+        icmp_extension::extension(&self.0[8..], self.length())
+    }
+);
+
+setters!(MutDestinationUnreachablePacket
+    pub fn set_code(&mut self, code: DestinationUnreachableCode) {
+        write_offset!(self.0, 1, code.value(), u8);
+    }
+
+    pub fn set_length(&mut self, length: u8) {
+        write_offset!(self.0, 5, length, u8);
+    }
+
+    pub fn set_next_hop_mtu(&mut self, next_hop_mtu: u16) {
+        write_offset!(self.0, 6, next_hop_mtu, u16, to_be);
+    }
+);
+
+
+packet!(
+    /// A Time Exceeded message. The payload holds the start of the datagram that was discarded.
+    TimeExceededPacket,
+    MutTimeExceededPacket,
+    TimeExceededPacketBuf,
+    8,
+    |_data| 8,
+    init |data| init_message(data, Some(Icmpv4Type::TIME_EXCEEDED))
+);
+icmpv4_common!(TimeExceededPacket, MutTimeExceededPacket);
+
+getters!(TimeExceededPacket
+    pub fn code(&self) -> TimeExceededCode {
+        TimeExceededCode(read_offset!(self.0, 1, u8))
+    }
+
+    /// Returns the length of the original datagram in 32 bit words, or zero if the message has
+    /// no extensions, RFC 4884.
+    pub fn length(&self) -> u8 {
+        read_offset!(self.0, 5, u8)
+    }
+
+    /// Returns the header of the datagram that triggered this message, or `None` if the
+    /// payload is too short to hold an IPv4 header. The backing slice ends before any
+    /// extensions.
+    pub fn original_datagram(&self) -> Option<Ipv4Packet<'a>> {
+        Ipv4Packet::new(icmp_extension::original_datagram(&self.0[8..], self.length()))
+    }
+
+    /// Returns the extension structure following the original datagram, or `None` if the
+    /// message has no extensions.
+    pub fn extension(&self) -> Option<ExtensionPacket<'a>> {
+        icmp_extension::extension(&self.0[8..], self.length())
+    }
+);
+
+setters!(MutTimeExceededPacket
+    pub fn set_code(&mut self, code: TimeExceededCode) {
+        write_offset!(self.0, 1, code.value(), u8);
+    }
+
+    pub fn set_length(&mut self, length: u8) {
+        write_offset!(self.0, 5, length, u8);
+    }
+);
+
+
+packet!(
+    /// A Redirect message, telling the sender of the original datagram to use another gateway.
+    RedirectPacket,
+    MutRedirectPacket,
+    RedirectPacketBuf,
+    8,
+    |_data| 8,
+    init |data| init_message(data, Some(Icmpv4Type::REDIRECT))
+);
+icmpv4_common!(RedirectPacket, MutRedirectPacket);
+
+getters!(RedirectPacket
+    pub fn code(&self) -> RedirectCode {
+        RedirectCode(read_offset!(self.0, 1, u8))
+    }
+
+    pub fn gateway(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+    }
+
+    /// Returns the header of the datagram that triggered this message, or `None` if the
+    /// payload is too short to hold an IPv4 header.
+    pub fn original_datagram(&self) -> Option<Ipv4Packet<'a>> {
+        Ipv4Packet::new(&self.0[8..])
+    }
+);
+
+setters!(MutRedirectPacket
+    pub fn set_code(&mut self, code: RedirectCode) {
+        write_offset!(self.0, 1, code.value(), u8);
+    }
+
+    pub fn set_gateway(&mut self, gateway: Ipv4Addr) {
+        write_offset!(self.0, 4, gateway.octets(), [u8; 4]);
+    }
+);
+
+
+/// The 8 bit type field of an ICMPv4 message. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/icmp-parameters/icmp-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Icmpv4Type(pub u8);
+
+impl Icmpv4Type {
+    pub const ECHO_REPLY: Icmpv4Type = Icmpv4Type(0);
+    pub const DESTINATION_UNREACHABLE: Icmpv4Type = Icmpv4Type(3);
+    pub const REDIRECT: Icmpv4Type = Icmpv4Type(5);
+    pub const ECHO_REQUEST: Icmpv4Type = Icmpv4Type(8);
+    pub const TIME_EXCEEDED: Icmpv4Type = Icmpv4Type(11);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The code field of a Destination Unreachable message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DestinationUnreachableCode(pub u8);
+
+impl DestinationUnreachableCode {
+    pub const NET_UNREACHABLE: DestinationUnreachableCode = DestinationUnreachableCode(0);
+    pub const HOST_UNREACHABLE: DestinationUnreachableCode = DestinationUnreachableCode(1);
+    pub const PROTOCOL_UNREACHABLE: DestinationUnreachableCode = DestinationUnreachableCode(2);
+    pub const PORT_UNREACHABLE: DestinationUnreachableCode = DestinationUnreachableCode(3);
+    pub const FRAGMENTATION_NEEDED: DestinationUnreachableCode = DestinationUnreachableCode(4);
+    pub const SOURCE_ROUTE_FAILED: DestinationUnreachableCode = DestinationUnreachableCode(5);
+    pub const ADMINISTRATIVELY_PROHIBITED: DestinationUnreachableCode =
+        DestinationUnreachableCode(13);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The code field of a Time Exceeded message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TimeExceededCode(pub u8);
+
+impl TimeExceededCode {
+    pub const TTL_EXCEEDED: TimeExceededCode = TimeExceededCode(0);
+    pub const FRAGMENT_REASSEMBLY_TIME_EXCEEDED: TimeExceededCode = TimeExceededCode(1);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The code field of a Redirect message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RedirectCode(pub u8);
+
+impl RedirectCode {
+    pub const NETWORK: RedirectCode = RedirectCode(0);
+    pub const HOST: RedirectCode = RedirectCode(1);
+    pub const TOS_AND_NETWORK: RedirectCode = RedirectCode(2);
+    pub const TOS_AND_HOST: RedirectCode = RedirectCode(3);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+impl<'a> fmt::Display for Icmpv4Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ICMPv4 type={} code={}", self.icmp_type().value(), self.code())
+    }
+}
+
+impl<'a> fmt::Display for EchoPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ICMPv4 echo type={} id={} seq={}",
+            self.icmp_type().value(),
+            self.identifier(),
+            self.sequence_number()
+        )
+    }
+}
+
+impl<'a> fmt::Display for DestinationUnreachablePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ICMPv4 destination unreachable code={}",
+            self.code().value()
+        )
+    }
+}
+
+impl<'a> fmt::Display for TimeExceededPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ICMPv4 time exceeded code={}", self.code().value())
+    }
+}
+
+impl<'a> fmt::Display for RedirectPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ICMPv4 redirect code={} gateway={}",
+            self.code().value(),
+            self.gateway()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ip::Protocol;
+
+    #[test]
+    fn display() {
+        let data = [8, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!("ICMPv4 type=8 code=0", Icmpv4Packet::new(&data).unwrap().to_string());
+    }
+
+    #[test]
+    fn debug() {
+        let data = [8, 0, 0x12, 0x34, 0, 1, 0, 2, 0xff];
+        assert_eq!(
+            "EchoPacket { icmp_type: Icmpv4Type(8), checksum: 4660, calculate_checksum: 63739, \
+             verify_checksum: false, code: 0, identifier: 1, sequence_number: 2, payload: [255] }",
+            format!("{:?}", EchoPacket::new(&data).unwrap())
+        );
+    }
+
+    macro_rules! icmpv4_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIcmpv4Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    icmpv4_setget_test!(icmp_type, set_icmp_type, Icmpv4Type(0xff), 0, [0xff]);
+    icmpv4_setget_test!(code, set_code, 0xff, 1, [0xff]);
+    icmpv4_setget_test!(checksum, set_checksum, 0xabcd, 2, [0xab, 0xcd]);
+
+    mod echo {
+        use super::super::*;
+
+        macro_rules! echo_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutEchoPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        echo_setget_test!(identifier, set_identifier, 0xabcd, 4, [0xab, 0xcd]);
+        echo_setget_test!(sequence_number, set_sequence_number, 0xabcd, 6, [0xab, 0xcd]);
+    }
+
+    #[test]
+    fn too_short_slice() {
+        assert!(Icmpv4Packet::new(&[0; 7]).is_none());
+        assert!(EchoPacket::new(&[0; 7]).is_none());
+    }
+
+    #[test]
+    fn build_echo_request() {
+        let mut data = [0; 8 + 4];
+        {
+            let mut testee = MutEchoPacket::new(&mut data).unwrap();
+            testee.set_icmp_type(Icmpv4Type::ECHO_REQUEST);
+            testee.set_identifier(0x1234);
+            testee.set_sequence_number(1);
+            testee.payload().copy_from_slice(b"ping");
+            let checksum = testee.as_immutable().calculate_checksum();
+            testee.set_checksum(checksum);
+        }
+        let testee = EchoPacket::new(&data).unwrap();
+        assert_eq!(Icmpv4Type::ECHO_REQUEST, testee.icmp_type());
+        assert_eq!(0, testee.code());
+        assert_eq!(b"ping", testee.payload());
+        // Summing a message with a correct checksum gives zero
+        let mut sum = Checksum::new();
+        sum.add_bytes(&data);
+        assert_eq!(0, sum.finish());
+    }
+
+    #[test]
+    fn checksum_ignores_checksum_field() {
+        let mut data = [8, 0, 0, 0, 0, 1, 0, 2];
+        let before = Icmpv4Packet::new(&data).unwrap().calculate_checksum();
+        data[2] = 0xff;
+        assert_eq!(before, Icmpv4Packet::new(&data).unwrap().calculate_checksum());
+    }
+
+    #[test]
+    fn verify_checksum() {
+        let mut data = [8, 0, 0, 0, 0, 1, 0, 2, b'p'];
+        assert!(!EchoPacket::new(&data).unwrap().verify_checksum());
+        let checksum = EchoPacket::new(&data).unwrap().calculate_checksum();
+        MutEchoPacket::new(&mut data).unwrap().set_checksum(checksum);
+        assert!(EchoPacket::new(&data).unwrap().verify_checksum());
+        data[8] = b'q';
+        assert!(!Icmpv4Packet::new(&data).unwrap().verify_checksum());
+    }
+
+    #[test]
+    fn port_unreachable() {
+        let mut data = [0; 8 + 20 + 8];
+        data[0] = 3;
+        data[1] = 3;
+        data[8] = 0x45;
+        data[8 + 9] = 17;
+        let testee = DestinationUnreachablePacket::new(&data).unwrap();
+        assert_eq!(Icmpv4Type::DESTINATION_UNREACHABLE, testee.icmp_type());
+        assert_eq!(DestinationUnreachableCode::PORT_UNREACHABLE, testee.code());
+        let original = testee.original_datagram().unwrap();
+        assert_eq!(4, original.version());
+        assert_eq!(Protocol::UDP, original.protocol());
+    }
+
+    #[test]
+    fn fragmentation_needed() {
+        let mut data = [0; 8];
+        {
+            let mut testee = MutDestinationUnreachablePacket::new(&mut data).unwrap();
+            testee.set_code(DestinationUnreachableCode::FRAGMENTATION_NEEDED);
+            testee.set_next_hop_mtu(1400);
+        }
+        assert_eq!(&[0, 4, 0, 0, 0, 0, 0x05, 0x78], &data);
+        let testee = DestinationUnreachablePacket::new(&data).unwrap();
+        assert!(testee.original_datagram().is_none());
+    }
+
+    #[test]
+    fn time_exceeded() {
+        let mut data = [0; 8 + 20];
+        data[0] = 11;
+        data[1] = 1;
+        data[8] = 0x45;
+        let testee = TimeExceededPacket::new(&data).unwrap();
+        assert_eq!(
+            TimeExceededCode::FRAGMENT_REASSEMBLY_TIME_EXCEEDED,
+            testee.code()
+        );
+        assert!(testee.original_datagram().is_some());
+        assert!(testee.extension().is_none());
+    }
+
+    #[test]
+    fn time_exceeded_with_extension() {
+        let mut data = vec![0; 8 + 128 + 4 + 8];
+        data[0] = 11;
+        data[8] = 0x45;
+        data[136] = 0x20;
+        data[140..].copy_from_slice(&[0, 8, 1, 1, 0x00, 0x01, 0x01, 0x40]);
+        MutTimeExceededPacket::new(&mut data).unwrap().set_length(32);
+        let testee = TimeExceededPacket::new(&data).unwrap();
+        assert_eq!(32, testee.length());
+        assert_eq!(128, testee.original_datagram().unwrap().data().len());
+        let extension = testee.extension().unwrap();
+        let object = extension.objects().next().unwrap();
+        assert_eq!(16, object.mpls_label_stack().unwrap().label());
+    }
+
+    #[test]
+    fn redirect() {
+        let mut data = [0; 8];
+        {
+            let mut testee = MutRedirectPacket::new(&mut data).unwrap();
+            testee.set_icmp_type(Icmpv4Type::REDIRECT);
+            testee.set_code(RedirectCode::HOST);
+            testee.set_gateway(Ipv4Addr::new(192, 168, 0, 1));
+        }
+        assert_eq!(&[5, 1, 0, 0, 192, 168, 0, 1], &data);
+        let testee = RedirectPacket::new(&data).unwrap();
+        assert_eq!(RedirectCode::HOST, testee.code());
+        assert_eq!(Ipv4Addr::new(192, 168, 0, 1), testee.gateway());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Icmpv4PacketBuf => |packet| packet.verify_checksum(),
+        EchoPacketBuf => |packet| packet.icmp_type() == Icmpv4Type::ECHO_REQUEST,
+        DestinationUnreachablePacketBuf => |packet| packet.length() == 0,
+        TimeExceededPacketBuf => |packet| packet.verify_checksum(),
+        RedirectPacketBuf => |packet| packet.verify_checksum(),
+    );
+}
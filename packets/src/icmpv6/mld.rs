@@ -0,0 +1,566 @@
+//! Multicast Listener Discovery, version 1 (RFC 2710) and version 2 (RFC 3810).
+
+use super::{init_message, Icmpv6Type};
+use std::cmp;
+use std::fmt;
+use std::net::Ipv6Addr;
+use types::*;
+
+packet!(
+    /// An MLDv1 message. Queries, reports and done messages all share this format, and are told
+    /// apart by their ICMPv6 type. An MLDv2 query is at least 28 bytes long, while a version 1
+    /// query is exactly 24.
+    MldPacket,
+    MutMldPacket,
+    MldPacketBuf,
+    24,
+    |_data| 24,
+    init |data| init_message(data, Icmpv6Type::MULTICAST_LISTENER_QUERY)
+);
+icmpv6_common!(MldPacket, MutMldPacket);
+
+getters!(MldPacket
+    /// Returns the maximum time allowed before sending a responding report, in milliseconds.
+    pub fn max_response_delay(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    pub fn multicast_address(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_offset!(self.0, 8, [u8; 16]))
+    }
+);
+
+setters!(MutMldPacket
+    pub fn set_max_response_delay(&mut self, max_response_delay: u16) {
+        write_offset!(self.0, 4, max_response_delay, u16, to_be);
+    }
+
+    pub fn set_multicast_address(&mut self, multicast_address: Ipv6Addr) {
+        write_offset!(self.0, 8, multicast_address.octets(), [u8; 16]);
+    }
+);
+
+
+packet!(
+    /// An MLDv2 Multicast Listener Query.
+    Mldv2QueryPacket,
+    MutMldv2QueryPacket,
+    Mldv2QueryPacketBuf,
+    28,
+    |data| 28 + usize::from(read_offset!(data, 26, u16, from_be)) * 16,
+    init |data| {
+        init_message(data, Icmpv6Type::MULTICAST_LISTENER_QUERY);
+        write_offset!(data, 26, 0u16, u16);
+    }
+);
+icmpv6_common!(Mldv2QueryPacket, MutMldv2QueryPacket);
+
+getters!(Mldv2QueryPacket
+    /// Returns the raw maximum response code. See `max_response_delay` for the decoded value.
+    pub fn max_response_code(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    /// Returns the maximum response delay in milliseconds, decoded from the maximum response
+    /// code. Codes of 32768 and above are a floating point value with a 3 bit exponent and a 12
+    /// bit mantissa.
+    pub fn max_response_delay(&self) -> u32 {
+        let code = self.max_response_code();
+        if code < 0x8000 {
+            u32::from(code)
+        } else {
+            let exponent = (code >> 12) & 0x7;
+            let mantissa = u32::from(code & 0x0fff);
+            (mantissa | 0x1000) << (exponent + 3)
+        }
+    }
+
+    pub fn multicast_address(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_offset!(self.0, 8, [u8; 16]))
+    }
+
+    /// Returns the S flag, telling multicast routers to suppress their timer updates.
+    pub fn suppress_router_processing(&self) -> bool {
+        read_offset!(self.0, 24, u8) & 0x08 != 0
+    }
+
+    /// Returns the Querier's Robustness Variable.
+    pub fn qrv(&self) -> u3 {
+        read_offset!(self.0, 24, u8) & 0x07
+    }
+
+    /// Returns the raw Querier's Query Interval Code. See `query_interval` for the decoded value.
+    pub fn qqic(&self) -> u8 {
+        read_offset!(self.0, 25, u8)
+    }
+
+    /// Returns the querier's query interval in seconds, decoded from the QQIC field. Codes of
+    /// 128 and above are a floating point value with a 3 bit exponent and a 4 bit mantissa.
+    pub fn query_interval(&self) -> u32 {
+        let code = self.qqic();
+        if code < 0x80 {
+            u32::from(code)
+        } else {
+            let exponent = (code >> 4) & 0x7;
+            let mantissa = u32::from(code & 0x0f);
+            (mantissa | 0x10) << (exponent + 3)
+        }
+    }
+
+    pub fn number_of_sources(&self) -> u16 {
+        read_offset!(self.0, 26, u16, from_be)
+    }
+
+    /// Returns an iterator over the source addresses in the query. Only the addresses that fit
+    /// in the backing slice are included.
+    pub fn sources(&self) -> SourceAddresses<'a> {
+        SourceAddresses { data: &self.0[28..self.header_len()] }
+    }
+);
+
+setters!(MutMldv2QueryPacket
+    pub fn set_max_response_code(&mut self, max_response_code: u16) {
+        write_offset!(self.0, 4, max_response_code, u16, to_be);
+    }
+
+    pub fn set_multicast_address(&mut self, multicast_address: Ipv6Addr) {
+        write_offset!(self.0, 8, multicast_address.octets(), [u8; 16]);
+    }
+
+    pub fn set_suppress_router_processing(&mut self, suppress_router_processing: bool) {
+        let flags = read_offset!(self.0, 24, u8) & !0x08;
+        let flag: u8 = if suppress_router_processing { 0x08 } else { 0 };
+        write_offset!(self.0, 24, flags | flag, u8);
+    }
+
+    pub fn set_qrv(&mut self, qrv: u3) {
+        let flags = read_offset!(self.0, 24, u8) & !0x07;
+        write_offset!(self.0, 24, flags | (qrv & 0x07), u8);
+    }
+
+    pub fn set_qqic(&mut self, qqic: u8) {
+        write_offset!(self.0, 25, qqic, u8);
+    }
+
+    pub fn set_number_of_sources(&mut self, number_of_sources: u16) {
+        write_offset!(self.0, 26, number_of_sources, u16, to_be);
+    }
+);
+
+impl<'a> MutMldv2QueryPacket<'a> {
+    /// Writes `source` at `index` in the list of source addresses. Returns false if the backing
+    /// slice is too short to hold the address.
+    pub fn set_source(&mut self, index: u16, source: Ipv6Addr) -> bool {
+        let offset = 28 + usize::from(index) * 16;
+        offset + 16 <= self.0.len() && {
+            write_offset!(self.0, offset, source.octets(), [u8; 16]);
+            true
+        }
+    }
+}
+
+
+packet!(
+    /// An MLDv2 Multicast Listener Report. The multicast address records follow the 8 byte
+    /// header, and can be iterated with `records`.
+    Mldv2ReportPacket,
+    MutMldv2ReportPacket,
+    Mldv2ReportPacketBuf,
+    8,
+    |_data| 8,
+    init |data| {
+        init_message(data, Icmpv6Type::MULTICAST_LISTENER_REPORT_V2);
+        write_offset!(data, 6, 0u16, u16);
+    }
+);
+icmpv6_common!(Mldv2ReportPacket, MutMldv2ReportPacket);
+
+getters!(Mldv2ReportPacket
+    pub fn number_of_records(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    /// Returns an iterator over the multicast address records in the report.
+    pub fn records(&self) -> MulticastAddressRecords<'a> {
+        MulticastAddressRecords {
+            data: &self.0[8..],
+            remaining: self.number_of_records(),
+        }
+    }
+);
+
+setters!(MutMldv2ReportPacket
+    pub fn set_number_of_records(&mut self, number_of_records: u16) {
+        write_offset!(self.0, 6, number_of_records, u16, to_be);
+    }
+);
+
+
+packet!(
+    /// A multicast address record in an MLDv2 report. The header covers the whole record,
+    /// including source addresses and auxiliary data, so the payload is whatever follows it.
+    MulticastAddressRecordPacket,
+    MutMulticastAddressRecordPacket,
+    MulticastAddressRecordPacketBuf,
+    20,
+    |data| record_len(data),
+    init |data| write_offset!(data, 1, [0, 0, 0], [u8; 3])
+);
+
+getters!(MulticastAddressRecordPacket
+    pub fn record_type(&self) -> MulticastAddressRecordType {
+        MulticastAddressRecordType(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the length of the auxiliary data in units of 32 bit words.
+    pub fn aux_data_len(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    pub fn number_of_sources(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    pub fn multicast_address(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_offset!(self.0, 4, [u8; 16]))
+    }
+
+    /// Returns an iterator over the source addresses in the record. Only the addresses that fit
+    /// in the backing slice are included.
+    pub fn sources(&self) -> SourceAddresses<'a> {
+        let end = cmp::min(self.sources_end(), self.0.len());
+        SourceAddresses { data: &self.0[20..end] }
+    }
+
+    /// Returns the auxiliary data following the source addresses. Truncated if it does not fit
+    /// in the backing slice.
+    pub fn auxiliary_data(&self) -> &'a [u8] {
+        let start = cmp::min(self.sources_end(), self.0.len());
+        &self.0[start..self.header_len()]
+    }
+);
+
+impl<'a> MulticastAddressRecordPacket<'a> {
+    fn sources_end(&self) -> usize {
+        20 + usize::from(self.number_of_sources()) * 16
+    }
+}
+
+setters!(MutMulticastAddressRecordPacket
+    pub fn set_record_type(&mut self, record_type: MulticastAddressRecordType) {
+        write_offset!(self.0, 0, record_type.value(), u8);
+    }
+
+    pub fn set_aux_data_len(&mut self, aux_data_len: u8) {
+        write_offset!(self.0, 1, aux_data_len, u8);
+    }
+
+    pub fn set_number_of_sources(&mut self, number_of_sources: u16) {
+        write_offset!(self.0, 2, number_of_sources, u16, to_be);
+    }
+
+    pub fn set_multicast_address(&mut self, multicast_address: Ipv6Addr) {
+        write_offset!(self.0, 4, multicast_address.octets(), [u8; 16]);
+    }
+);
+
+impl<'a> MutMulticastAddressRecordPacket<'a> {
+    /// Writes `source` at `index` in the list of source addresses. Returns false if the backing
+    /// slice is too short to hold the address.
+    pub fn set_source(&mut self, index: u16, source: Ipv6Addr) -> bool {
+        let offset = 20 + usize::from(index) * 16;
+        offset + 16 <= self.0.len() && {
+            write_offset!(self.0, offset, source.octets(), [u8; 16]);
+            true
+        }
+    }
+}
+
+/// Returns the full length of the multicast address record at the start of `data`.
+fn record_len(data: &[u8]) -> usize {
+    let number_of_sources = usize::from(read_offset!(data, 2, u16, from_be));
+    20 + number_of_sources * 16 + usize::from(data[1]) * 4
+}
+
+
+/// The 8 bit record type of a multicast address record.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MulticastAddressRecordType(pub u8);
+
+impl MulticastAddressRecordType {
+    pub const MODE_IS_INCLUDE: MulticastAddressRecordType = MulticastAddressRecordType(1);
+    pub const MODE_IS_EXCLUDE: MulticastAddressRecordType = MulticastAddressRecordType(2);
+    pub const CHANGE_TO_INCLUDE_MODE: MulticastAddressRecordType = MulticastAddressRecordType(3);
+    pub const CHANGE_TO_EXCLUDE_MODE: MulticastAddressRecordType = MulticastAddressRecordType(4);
+    pub const ALLOW_NEW_SOURCES: MulticastAddressRecordType = MulticastAddressRecordType(5);
+    pub const BLOCK_OLD_SOURCES: MulticastAddressRecordType = MulticastAddressRecordType(6);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+/// Iterator over a list of IPv6 source addresses in an MLDv2 message.
+#[derive(Clone)]
+pub struct SourceAddresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(SourceAddresses<'a>);
+
+impl<'a> Iterator for SourceAddresses<'a> {
+    type Item = Ipv6Addr;
+
+    fn next(&mut self) -> Option<Ipv6Addr> {
+        if self.data.len() < 16 {
+            return None;
+        }
+        let (source, rest) = self.data.split_at(16);
+        self.data = rest;
+        Some(Ipv6Addr::from(read_offset!(source, 0, [u8; 16])))
+    }
+}
+
+/// Iterator over the multicast address records in an MLDv2 report. Iteration stops after the
+/// number of records given in the report, or at the first record that does not fit in the data.
+#[derive(Clone)]
+pub struct MulticastAddressRecords<'a> {
+    data: &'a [u8],
+    remaining: u16,
+}
+
+debug_items!(MulticastAddressRecords<'a>);
+
+impl<'a> Iterator for MulticastAddressRecords<'a> {
+    type Item = MulticastAddressRecordPacket<'a>;
+
+    fn next(&mut self) -> Option<MulticastAddressRecordPacket<'a>> {
+        if self.remaining == 0 ||
+            self.data.len() < MulticastAddressRecordPacket::MIN_LEN ||
+            record_len(self.data) > self.data.len()
+        {
+            self.data = &[];
+            return None;
+        }
+        let (record, rest) = self.data.split_at(record_len(self.data));
+        self.data = rest;
+        self.remaining -= 1;
+        Some(MulticastAddressRecordPacket(record))
+    }
+}
+
+impl<'a> fmt::Display for MldPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MLD type={} group={}",
+            self.icmp_type().value(),
+            self.multicast_address()
+        )
+    }
+}
+
+impl<'a> fmt::Display for Mldv2QueryPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MLDv2 query group={} sources={}",
+            self.multicast_address(),
+            self.number_of_sources()
+        )
+    }
+}
+
+impl<'a> fmt::Display for Mldv2ReportPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MLDv2 report records={}", self.number_of_records())
+    }
+}
+
+impl<'a> fmt::Display for MulticastAddressRecordPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MLDv2 record type={} group={} sources={}",
+            self.record_type().value(),
+            self.multicast_address(),
+            self.number_of_sources()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! mld_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutMldPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    mld_setget_test!(max_response_delay, set_max_response_delay, 0xabcd, 4, [0xab, 0xcd]);
+    mld_setget_test!(
+        multicast_address,
+        set_multicast_address,
+        Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb),
+        8,
+        [0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfb]
+    );
+
+    mod query {
+        use super::super::*;
+
+        macro_rules! query_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutMldv2QueryPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        query_setget_test!(max_response_code, set_max_response_code, 0xabcd, 4, [0xab, 0xcd]);
+        query_setget_test!(
+            suppress_router_processing,
+            set_suppress_router_processing,
+            true,
+            24,
+            [0x08]
+        );
+        query_setget_test!(qrv, set_qrv, 0x07, 24, [0x07]);
+        query_setget_test!(qqic, set_qqic, 0xab, 25, [0xab]);
+        query_setget_test!(number_of_sources, set_number_of_sources, 0xabcd, 26, [0xab, 0xcd]);
+    }
+
+    mod record {
+        use super::super::*;
+
+        macro_rules! record_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(
+                    MutMulticastAddressRecordPacket,
+                    $name,
+                    $set_name,
+                    $value,
+                    $offset,
+                    $expected
+                );
+            }
+        }
+
+        record_setget_test!(
+            record_type,
+            set_record_type,
+            MulticastAddressRecordType::BLOCK_OLD_SOURCES,
+            0,
+            [6]
+        );
+        record_setget_test!(aux_data_len, set_aux_data_len, 0xab, 1, [0xab]);
+        record_setget_test!(number_of_sources, set_number_of_sources, 0xabcd, 2, [0xab, 0xcd]);
+    }
+
+    #[test]
+    fn query_flags_are_independent() {
+        let mut data = [0; 28];
+        let mut testee = MutMldv2QueryPacket::new(&mut data).unwrap();
+        testee.set_qrv(2);
+        testee.set_suppress_router_processing(true);
+        assert_eq!(2, testee.as_immutable().qrv());
+        testee.set_suppress_router_processing(false);
+        assert_eq!(2, testee.as_immutable().qrv());
+        assert!(!testee.as_immutable().suppress_router_processing());
+    }
+
+    #[test]
+    fn query_decoded_times() {
+        let mut data = [0; 28];
+        {
+            let mut testee = MutMldv2QueryPacket::new(&mut data).unwrap();
+            testee.set_max_response_code(10000);
+            testee.set_qqic(125);
+        }
+        let testee = Mldv2QueryPacket::new(&data).unwrap();
+        assert_eq!(10000, testee.max_response_delay());
+        assert_eq!(125, testee.query_interval());
+
+        data[4..6].copy_from_slice(&[0x80, 0x01]);
+        data[25] = 0x81;
+        let testee = Mldv2QueryPacket::new(&data).unwrap();
+        assert_eq!(0x1001 << 3, testee.max_response_delay());
+        assert_eq!(0x11 << 3, testee.query_interval());
+    }
+
+    #[test]
+    fn query_sources() {
+        let first = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let second = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+        let mut data = [0; 28 + 2 * 16];
+        {
+            let mut testee = MutMldv2QueryPacket::new(&mut data).unwrap();
+            testee.set_number_of_sources(2);
+            assert!(testee.set_source(0, first));
+            assert!(testee.set_source(1, second));
+            assert!(!testee.set_source(2, first));
+            assert!(!testee.set_source(u16::MAX, first));
+        }
+        let testee = Mldv2QueryPacket::new(&data).unwrap();
+        assert_eq!(60, testee.header_len());
+        assert_eq!(vec![first, second], testee.sources().collect::<Vec<_>>());
+
+        let truncated = Mldv2QueryPacket::new(&data[..50]).unwrap();
+        assert_eq!(vec![first], truncated.sources().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn report_records() {
+        let group = Ipv6Addr::new(0xff05, 0, 0, 0, 0, 0, 0, 0x1234);
+        let source = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let mut data = [0; 8 + 20 + 16 + 4 + 20];
+        data[0] = 143;
+        data[7] = 2;
+        {
+            let mut record = MutMulticastAddressRecordPacket::new(&mut data[8..]).unwrap();
+            record.set_record_type(MulticastAddressRecordType::MODE_IS_INCLUDE);
+            record.set_aux_data_len(1);
+            record.set_number_of_sources(1);
+            record.set_multicast_address(group);
+            assert!(record.set_source(0, source));
+            assert!(!record.set_source(3, source));
+        }
+        data[44..48].copy_from_slice(&[1, 2, 3, 4]);
+        data[48] = MulticastAddressRecordType::CHANGE_TO_EXCLUDE_MODE.value();
+
+        let testee = Mldv2ReportPacket::new(&data).unwrap();
+        let records = testee.records().collect::<Vec<_>>();
+        assert_eq!(2, records.len());
+        assert_eq!(MulticastAddressRecordType::MODE_IS_INCLUDE, records[0].record_type());
+        assert_eq!(group, records[0].multicast_address());
+        assert_eq!(vec![source], records[0].sources().collect::<Vec<_>>());
+        assert_eq!(&[1, 2, 3, 4], records[0].auxiliary_data());
+        assert_eq!(
+            MulticastAddressRecordType::CHANGE_TO_EXCLUDE_MODE,
+            records[1].record_type()
+        );
+        assert_eq!(0, records[1].sources().count());
+    }
+
+    #[test]
+    fn report_records_stop() {
+        let mut data = [0; 8 + 20 + 20];
+        data[7] = 1;
+        assert_eq!(1, Mldv2ReportPacket::new(&data).unwrap().records().count());
+
+        data[7] = 2;
+        data[8 + 3] = 2;
+        assert_eq!(0, Mldv2ReportPacket::new(&data).unwrap().records().count());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        MldPacketBuf => |packet| packet.code() == 0,
+        Mldv2QueryPacketBuf => |packet| packet.number_of_sources() == 0,
+        Mldv2ReportPacketBuf => |packet| packet.number_of_records() == 0,
+        MulticastAddressRecordPacketBuf => |packet| packet.aux_data_len() == 0,
+    );
+}
@@ -0,0 +1,218 @@
+use checksum::{self, Checksum};
+use ip::Protocol;
+use std::fmt;
+use std::net::Ipv6Addr;
+
+/// Implements the fields common to all ICMPv6 messages, and the checksum calculation over the
+/// pseudo header and the whole message, for a pair of ICMPv6 packet types.
+macro_rules! icmpv6_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn icmp_type(&self) -> $crate::icmpv6::Icmpv6Type {
+                $crate::icmpv6::Icmpv6Type(read_offset!(self.0, 0, u8))
+            }
+
+            pub fn code(&self) -> u8 {
+                read_offset!(self.0, 1, u8)
+            }
+
+            pub fn checksum(&self) -> u16 {
+                read_offset!(self.0, 2, u16, from_be)
+            }
+        );
+
+        impl<'a> $name<'a> {
+            /// Computes the checksum of this message when sent between the given addresses. The
+            /// checksum covers an IPv6 pseudo header and the entire backing slice, so the slice
+            /// must hold exactly the ICMPv6 message. The current value of the checksum field is
+            /// ignored.
+            pub fn calculate_checksum(
+                &self,
+                source: ::std::net::Ipv6Addr,
+                destination: ::std::net::Ipv6Addr,
+            ) -> u16 {
+                $crate::icmpv6::calculate_checksum(self.0, source, destination)
+            }
+
+            /// Returns true if the checksum field is valid for this message sent between the
+            /// given addresses. The backing slice must hold exactly the ICMPv6 message.
+            pub fn verify_checksum(
+                &self,
+                source: ::std::net::Ipv6Addr,
+                destination: ::std::net::Ipv6Addr,
+            ) -> bool {
+                $crate::icmpv6::verify_checksum(self.0, source, destination)
+            }
+        }
+
+        setters!($mut_name
+            pub fn set_icmp_type(&mut self, icmp_type: $crate::icmpv6::Icmpv6Type) {
+                write_offset!(self.0, 0, icmp_type.value(), u8);
+            }
+
+            pub fn set_code(&mut self, code: u8) {
+                write_offset!(self.0, 1, code, u8);
+            }
+
+            pub fn set_checksum(&mut self, checksum: u16) {
+                write_offset!(self.0, 2, checksum, u16, to_be);
+            }
+        );
+    }
+}
+
+mod mld;
+mod ndp;
+pub use self::mld::*;
+pub use self::ndp::*;
+
+packet!(Icmpv6Packet, MutIcmpv6Packet, Icmpv6PacketBuf, 4);
+icmpv6_common!(Icmpv6Packet, MutIcmpv6Packet);
+
+fn calculate_checksum(data: &[u8], source: Ipv6Addr, destination: Ipv6Addr) -> u16 {
+    let mut checksum = Checksum::new();
+    checksum::add_ipv6_pseudo_header(
+        &mut checksum,
+        source,
+        destination,
+        Protocol::ICMPV6,
+        data.len(),
+    );
+    checksum.add_bytes(&data[..2]);
+    checksum.add_bytes(&data[4..]);
+    checksum.finish()
+}
+
+fn verify_checksum(data: &[u8], source: Ipv6Addr, destination: Ipv6Addr) -> bool {
+    let mut checksum = Checksum::new();
+    checksum::add_ipv6_pseudo_header(
+        &mut checksum,
+        source,
+        destination,
+        Protocol::ICMPV6,
+        data.len(),
+    );
+    checksum.add_bytes(data);
+    checksum.finish() == 0
+}
+
+/// Sets the type field of the message in `data` to `icmp_type` and the code field to zero.
+fn init_message(data: &mut [u8], icmp_type: Icmpv6Type) {
+    write_offset!(data, 0, [icmp_type.value(), 0], [u8; 2]);
+}
+
+
+/// The 8 bit type field of an ICMPv6 message. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/icmpv6-parameters/icmpv6-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Icmpv6Type(pub u8);
+
+impl Icmpv6Type {
+    pub const DESTINATION_UNREACHABLE: Icmpv6Type = Icmpv6Type(1);
+    pub const PACKET_TOO_BIG: Icmpv6Type = Icmpv6Type(2);
+    pub const TIME_EXCEEDED: Icmpv6Type = Icmpv6Type(3);
+    pub const PARAMETER_PROBLEM: Icmpv6Type = Icmpv6Type(4);
+    pub const ECHO_REQUEST: Icmpv6Type = Icmpv6Type(128);
+    pub const ECHO_REPLY: Icmpv6Type = Icmpv6Type(129);
+    pub const MULTICAST_LISTENER_QUERY: Icmpv6Type = Icmpv6Type(130);
+    pub const MULTICAST_LISTENER_REPORT: Icmpv6Type = Icmpv6Type(131);
+    pub const MULTICAST_LISTENER_DONE: Icmpv6Type = Icmpv6Type(132);
+    pub const ROUTER_SOLICITATION: Icmpv6Type = Icmpv6Type(133);
+    pub const ROUTER_ADVERTISEMENT: Icmpv6Type = Icmpv6Type(134);
+    pub const NEIGHBOR_SOLICITATION: Icmpv6Type = Icmpv6Type(135);
+    pub const NEIGHBOR_ADVERTISEMENT: Icmpv6Type = Icmpv6Type(136);
+    pub const REDIRECT: Icmpv6Type = Icmpv6Type(137);
+    pub const MULTICAST_LISTENER_REPORT_V2: Icmpv6Type = Icmpv6Type(143);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns true for error messages, meaning types below 128.
+    pub fn is_error(&self) -> bool {
+        self.0 < 128
+    }
+}
+
+
+impl<'a> fmt::Display for Icmpv6Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ICMPv6 type={} code={}", self.icmp_type().value(), self.code())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let data = [135, 0, 0, 0];
+        assert_eq!("ICMPv6 type=135 code=0", Icmpv6Packet::new(&data).unwrap().to_string());
+    }
+
+    macro_rules! icmpv6_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIcmpv6Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    icmpv6_setget_test!(icmp_type, set_icmp_type, Icmpv6Type(0xff), 0, [0xff]);
+    icmpv6_setget_test!(code, set_code, 0xff, 1, [0xff]);
+    icmpv6_setget_test!(checksum, set_checksum, 0xabcd, 2, [0xab, 0xcd]);
+
+    #[test]
+    fn too_short_slice() {
+        assert!(Icmpv6Packet::new(&[0; 3]).is_none());
+    }
+
+    #[test]
+    fn is_error() {
+        assert!(Icmpv6Type::PACKET_TOO_BIG.is_error());
+        assert!(!Icmpv6Type::ECHO_REQUEST.is_error());
+    }
+
+    #[test]
+    fn echo_request_checksum() {
+        // Echo request from fe80::1 to ff02::1 with identifier 0x1234 and sequence number 1
+        let source = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let destination = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+        let mut data = [128, 0, 0, 0, 0x12, 0x34, 0x00, 0x01];
+        let checksum = Icmpv6Packet::new(&data)
+            .unwrap()
+            .calculate_checksum(source, destination);
+        MutIcmpv6Packet::new(&mut data).unwrap().set_checksum(checksum);
+
+        let mut sum = Checksum::new();
+        checksum::add_ipv6_pseudo_header(&mut sum, source, destination, Protocol::ICMPV6, 8);
+        sum.add_bytes(&data);
+        assert_eq!(0, sum.finish());
+        // The checksum depends on the addresses
+        assert_ne!(
+            checksum,
+            Icmpv6Packet::new(&data)
+                .unwrap()
+                .calculate_checksum(source, Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2))
+        );
+    }
+
+    #[test]
+    fn verify_checksum() {
+        let source = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let destination = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+        let mut data = [128, 0, 0, 0, 0x12, 0x34, 0x00, 0x01];
+        assert!(!Icmpv6Packet::new(&data).unwrap().verify_checksum(source, destination));
+        let checksum = Icmpv6Packet::new(&data)
+            .unwrap()
+            .calculate_checksum(source, destination);
+        MutIcmpv6Packet::new(&mut data).unwrap().set_checksum(checksum);
+        let testee = Icmpv6Packet::new(&data).unwrap();
+        assert!(testee.verify_checksum(source, destination));
+        assert!(!testee.verify_checksum(source, Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2)));
+    }
+
+    arbitrary_test!(arbitrary_well_formed, Icmpv6PacketBuf);
+}
@@ -0,0 +1,684 @@
+//! Neighbor Discovery Protocol messages, RFC 4861.
+
+use ethernet::MacAddr;
+use super::{init_message, Icmpv6Type};
+use std::fmt;
+use std::net::Ipv6Addr;
+
+packet!(
+    /// A Router Solicitation message.
+    RouterSolicitationPacket,
+    MutRouterSolicitationPacket,
+    RouterSolicitationPacketBuf,
+    8,
+    |_data| 8,
+    init |data| init_message(data, Icmpv6Type::ROUTER_SOLICITATION)
+);
+icmpv6_common!(RouterSolicitationPacket, MutRouterSolicitationPacket);
+
+getters!(RouterSolicitationPacket
+    pub fn options(&self) -> NdpOptions<'a> {
+        NdpOptions::new(&self.0[8..])
+    }
+);
+
+
+packet!(
+    /// A Router Advertisement message.
+    RouterAdvertisementPacket,
+    MutRouterAdvertisementPacket,
+    RouterAdvertisementPacketBuf,
+    16,
+    |_data| 16,
+    init |data| init_message(data, Icmpv6Type::ROUTER_ADVERTISEMENT)
+);
+icmpv6_common!(RouterAdvertisementPacket, MutRouterAdvertisementPacket);
+
+getters!(RouterAdvertisementPacket
+    pub fn cur_hop_limit(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    pub fn flags(&self) -> RouterAdvertisementFlags {
+        RouterAdvertisementFlags::from_bits_truncate(read_offset!(self.0, 5, u8))
+    }
+
+    /// Returns the lifetime of the router as a default router, in seconds.
+    pub fn router_lifetime(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    /// Returns the reachable time in milliseconds.
+    pub fn reachable_time(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns the retransmission timer in milliseconds.
+    pub fn retrans_timer(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    pub fn options(&self) -> NdpOptions<'a> {
+        NdpOptions::new(&self.0[16..])
+    }
+);
+
+setters!(MutRouterAdvertisementPacket
+    pub fn set_cur_hop_limit(&mut self, cur_hop_limit: u8) {
+        write_offset!(self.0, 4, cur_hop_limit, u8);
+    }
+
+    pub fn set_flags(&mut self, flags: RouterAdvertisementFlags) {
+        write_offset!(self.0, 5, flags.bits(), u8);
+    }
+
+    pub fn set_router_lifetime(&mut self, router_lifetime: u16) {
+        write_offset!(self.0, 6, router_lifetime, u16, to_be);
+    }
+
+    pub fn set_reachable_time(&mut self, reachable_time: u32) {
+        write_offset!(self.0, 8, reachable_time, u32, to_be);
+    }
+
+    pub fn set_retrans_timer(&mut self, retrans_timer: u32) {
+        write_offset!(self.0, 12, retrans_timer, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A Neighbor Solicitation message.
+    NeighborSolicitationPacket,
+    MutNeighborSolicitationPacket,
+    NeighborSolicitationPacketBuf,
+    24,
+    |_data| 24,
+    init |data| init_message(data, Icmpv6Type::NEIGHBOR_SOLICITATION)
+);
+icmpv6_common!(NeighborSolicitationPacket, MutNeighborSolicitationPacket);
+
+getters!(NeighborSolicitationPacket
+    pub fn target_address(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_offset!(self.0, 8, [u8; 16]))
+    }
+
+    pub fn options(&self) -> NdpOptions<'a> {
+        NdpOptions::new(&self.0[24..])
+    }
+);
+
+setters!(MutNeighborSolicitationPacket
+    pub fn set_target_address(&mut self, target_address: Ipv6Addr) {
+        write_offset!(self.0, 8, target_address.octets(), [u8; 16]);
+    }
+);
+
+
+packet!(
+    /// A Neighbor Advertisement message.
+    NeighborAdvertisementPacket,
+    MutNeighborAdvertisementPacket,
+    NeighborAdvertisementPacketBuf,
+    24,
+    |_data| 24,
+    init |data| init_message(data, Icmpv6Type::NEIGHBOR_ADVERTISEMENT)
+);
+icmpv6_common!(NeighborAdvertisementPacket, MutNeighborAdvertisementPacket);
+
+getters!(NeighborAdvertisementPacket
+    pub fn flags(&self) -> NeighborAdvertisementFlags {
+        NeighborAdvertisementFlags::from_bits_truncate(read_offset!(self.0, 4, u8))
+    }
+
+    pub fn target_address(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_offset!(self.0, 8, [u8; 16]))
+    }
+
+    pub fn options(&self) -> NdpOptions<'a> {
+        NdpOptions::new(&self.0[24..])
+    }
+);
+
+setters!(MutNeighborAdvertisementPacket
+    pub fn set_flags(&mut self, flags: NeighborAdvertisementFlags) {
+        write_offset!(self.0, 4, flags.bits(), u8);
+    }
+
+    pub fn set_target_address(&mut self, target_address: Ipv6Addr) {
+        write_offset!(self.0, 8, target_address.octets(), [u8; 16]);
+    }
+);
+
+
+packet!(
+    /// The Prefix Information option, carried in Router Advertisements. The view covers the
+    /// entire option, including the type and length fields.
+    PrefixInformationPacket,
+    MutPrefixInformationPacket,
+    PrefixInformationPacketBuf,
+    32,
+    |_data| 32,
+    init |data| MutPrefixInformationPacket(data).set_option_header()
+);
+
+getters!(PrefixInformationPacket
+    pub fn prefix_length(&self) -> u8 {
+        read_offset!(self.0, 2, u8)
+    }
+
+    pub fn flags(&self) -> PrefixInformationFlags {
+        PrefixInformationFlags::from_bits_truncate(read_offset!(self.0, 3, u8))
+    }
+
+    /// Returns how long the prefix is valid for on-link determination, in seconds.
+    pub fn valid_lifetime(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns how long addresses generated from the prefix remain preferred, in seconds.
+    pub fn preferred_lifetime(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    pub fn prefix(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_offset!(self.0, 16, [u8; 16]))
+    }
+);
+
+setters!(MutPrefixInformationPacket
+    pub fn set_prefix_length(&mut self, prefix_length: u8) {
+        write_offset!(self.0, 2, prefix_length, u8);
+    }
+
+    pub fn set_flags(&mut self, flags: PrefixInformationFlags) {
+        write_offset!(self.0, 3, flags.bits(), u8);
+    }
+
+    pub fn set_valid_lifetime(&mut self, valid_lifetime: u32) {
+        write_offset!(self.0, 4, valid_lifetime, u32, to_be);
+    }
+
+    pub fn set_preferred_lifetime(&mut self, preferred_lifetime: u32) {
+        write_offset!(self.0, 8, preferred_lifetime, u32, to_be);
+    }
+
+    pub fn set_prefix(&mut self, prefix: Ipv6Addr) {
+        write_offset!(self.0, 16, prefix.octets(), [u8; 16]);
+    }
+);
+
+impl<'a> MutPrefixInformationPacket<'a> {
+    /// Sets the type and length fields to the values of a Prefix Information option.
+    pub fn set_option_header(&mut self) {
+        write_offset!(self.0, 0, NdpOptionType::PREFIX_INFORMATION.value(), u8);
+        write_offset!(self.0, 1, 4, u8);
+    }
+}
+
+
+bitflags! {
+    /// Bitmasks for the flags field in a Router Advertisement.
+    pub struct RouterAdvertisementFlags: u8 {
+        /// Addresses are available via DHCPv6.
+        const MANAGED = 0b1000_0000;
+        /// Other configuration is available via DHCPv6.
+        const OTHER = 0b0100_0000;
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags field in a Neighbor Advertisement.
+    pub struct NeighborAdvertisementFlags: u8 {
+        /// The sender is a router.
+        const ROUTER = 0b1000_0000;
+        /// The advertisement was sent in response to a Neighbor Solicitation.
+        const SOLICITED = 0b0100_0000;
+        /// The advertisement should override an existing cache entry.
+        const OVERRIDE = 0b0010_0000;
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags field in a Prefix Information option.
+    pub struct PrefixInformationFlags: u8 {
+        /// The prefix can be used for on-link determination.
+        const ON_LINK = 0b1000_0000;
+        /// The prefix can be used for stateless address autoconfiguration.
+        const AUTONOMOUS = 0b0100_0000;
+    }
+}
+
+
+/// The 8 bit type field of an NDP option. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/icmpv6-parameters/icmpv6-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NdpOptionType(pub u8);
+
+impl NdpOptionType {
+    pub const SOURCE_LINK_LAYER_ADDRESS: NdpOptionType = NdpOptionType(1);
+    pub const TARGET_LINK_LAYER_ADDRESS: NdpOptionType = NdpOptionType(2);
+    pub const PREFIX_INFORMATION: NdpOptionType = NdpOptionType(3);
+    pub const REDIRECTED_HEADER: NdpOptionType = NdpOptionType(4);
+    pub const MTU: NdpOptionType = NdpOptionType(5);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// A single option in a Neighbor Discovery message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NdpOption<'a> {
+    /// The Ethernet address of the sender of the message.
+    SourceLinkLayerAddress(MacAddr),
+    /// The Ethernet address of the target of the message.
+    TargetLinkLayerAddress(MacAddr),
+    /// Information about an on-link prefix.
+    PrefixInformation(PrefixInformationPacket<'a>),
+    /// The MTU of the link.
+    Mtu(u32),
+    /// Any other option, or one of the above with an unexpected length. Holds the option data
+    /// following the type and length fields.
+    Other(NdpOptionType, &'a [u8]),
+}
+
+impl<'a> NdpOption<'a> {
+    /// Returns the number of bytes this option occupies when encoded, or `None` if it can't be
+    /// encoded because an `Other` option is not two bytes short of a multiple of eight bytes
+    /// long, or longer than the length field can express.
+    pub fn encoded_len(&self) -> Option<usize> {
+        match *self {
+            NdpOption::SourceLinkLayerAddress(_) |
+            NdpOption::TargetLinkLayerAddress(_) |
+            NdpOption::Mtu(_) => Some(8),
+            NdpOption::PrefixInformation(_) => Some(32),
+            NdpOption::Other(_, data) => {
+                let len = data.len() + 2;
+                if len.is_multiple_of(8) && len <= 255 * 8 {
+                    Some(len)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Encodes this option into the beginning of `buffer`. Returns the number of bytes written,
+    /// or `None` if the option does not fit in `buffer` or can't be encoded.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len()?;
+        if buffer.len() < len {
+            return None;
+        }
+        let option_type = match *self {
+            NdpOption::SourceLinkLayerAddress(mac) => {
+                buffer[2..8].copy_from_slice(mac.as_ref());
+                NdpOptionType::SOURCE_LINK_LAYER_ADDRESS
+            }
+            NdpOption::TargetLinkLayerAddress(mac) => {
+                buffer[2..8].copy_from_slice(mac.as_ref());
+                NdpOptionType::TARGET_LINK_LAYER_ADDRESS
+            }
+            NdpOption::PrefixInformation(prefix_information) => {
+                buffer[2..32].copy_from_slice(&prefix_information.data()[2..32]);
+                NdpOptionType::PREFIX_INFORMATION
+            }
+            NdpOption::Mtu(mtu) => {
+                write_offset!(buffer, 2, 0u16, u16);
+                write_offset!(buffer, 4, mtu, u32, to_be);
+                NdpOptionType::MTU
+            }
+            NdpOption::Other(option_type, data) => {
+                buffer[2..len].copy_from_slice(data);
+                option_type
+            }
+        };
+        buffer[0] = option_type.value();
+        buffer[1] = (len / 8) as u8;
+        Some(len)
+    }
+}
+
+/// Iterator over the options in a Neighbor Discovery message. Iteration stops at the first
+/// option with a length of zero or that does not fit in the data.
+#[derive(Clone)]
+pub struct NdpOptions<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(NdpOptions<'a>);
+
+impl<'a> NdpOptions<'a> {
+    /// Creates an iterator over the options encoded in `data`.
+    pub fn new(data: &'a [u8]) -> NdpOptions<'a> {
+        NdpOptions { data }
+    }
+}
+
+impl<'a> Iterator for NdpOptions<'a> {
+    type Item = NdpOption<'a>;
+
+    fn next(&mut self) -> Option<NdpOption<'a>> {
+        let len = match self.data.get(1) {
+            Some(&len) if len > 0 && usize::from(len) * 8 <= self.data.len() => {
+                usize::from(len) * 8
+            }
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (option, rest) = self.data.split_at(len);
+        self.data = rest;
+
+        let option_type = NdpOptionType(option[0]);
+        Some(match (option_type, len) {
+            (NdpOptionType::SOURCE_LINK_LAYER_ADDRESS, 8) => {
+                NdpOption::SourceLinkLayerAddress(MacAddr::from_slice(&option[2..8]))
+            }
+            (NdpOptionType::TARGET_LINK_LAYER_ADDRESS, 8) => {
+                NdpOption::TargetLinkLayerAddress(MacAddr::from_slice(&option[2..8]))
+            }
+            (NdpOptionType::PREFIX_INFORMATION, 32) => {
+                NdpOption::PrefixInformation(PrefixInformationPacket(option))
+            }
+            (NdpOptionType::MTU, 8) => NdpOption::Mtu(read_offset!(option, 4, u32, from_be)),
+            _ => NdpOption::Other(option_type, &option[2..]),
+        })
+    }
+}
+
+impl<'a> fmt::Display for RouterSolicitationPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NDP router solicitation options={}",
+            self.options().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for RouterAdvertisementPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NDP router advertisement hop_limit={} lifetime={}",
+            self.cur_hop_limit(),
+            self.router_lifetime()
+        )
+    }
+}
+
+impl<'a> fmt::Display for NeighborSolicitationPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NDP neighbor solicitation target={}",
+            self.target_address()
+        )
+    }
+}
+
+impl<'a> fmt::Display for NeighborAdvertisementPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NDP neighbor advertisement target={}",
+            self.target_address()
+        )
+    }
+}
+
+impl<'a> fmt::Display for PrefixInformationPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NDP prefix {}/{} valid={} preferred={}",
+            self.prefix(),
+            self.prefix_length(),
+            self.valid_lifetime(),
+            self.preferred_lifetime()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icmpv6::Icmpv6Type;
+
+    static MAC: MacAddr = MacAddr([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+
+    mod router_advertisement {
+        use super::super::*;
+
+        macro_rules! ra_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(
+                    MutRouterAdvertisementPacket,
+                    $name,
+                    $set_name,
+                    $value,
+                    $offset,
+                    $expected
+                );
+            }
+        }
+
+        ra_setget_test!(cur_hop_limit, set_cur_hop_limit, 0xff, 4, [0xff]);
+        ra_setget_test!(flags, set_flags, RouterAdvertisementFlags::all(), 5, [0xc0]);
+        ra_setget_test!(router_lifetime, set_router_lifetime, 0xabcd, 6, [0xab, 0xcd]);
+        ra_setget_test!(
+            reachable_time,
+            set_reachable_time,
+            0xdeadbeef,
+            8,
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        ra_setget_test!(
+            retrans_timer,
+            set_retrans_timer,
+            0xdeadbeef,
+            12,
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+    }
+
+    mod neighbor_advertisement {
+        use super::super::*;
+
+        macro_rules! na_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(
+                    MutNeighborAdvertisementPacket,
+                    $name,
+                    $set_name,
+                    $value,
+                    $offset,
+                    $expected
+                );
+            }
+        }
+
+        na_setget_test!(flags, set_flags, NeighborAdvertisementFlags::all(), 4, [0xe0]);
+        na_setget_test!(
+            target_address,
+            set_target_address,
+            Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0xabcd),
+            8,
+            [0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xab, 0xcd]
+        );
+    }
+
+    mod prefix_information {
+        use super::super::*;
+
+        macro_rules! pi_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(
+                    MutPrefixInformationPacket,
+                    $name,
+                    $set_name,
+                    $value,
+                    $offset,
+                    $expected
+                );
+            }
+        }
+
+        pi_setget_test!(prefix_length, set_prefix_length, 64, 2, [64]);
+        pi_setget_test!(flags, set_flags, PrefixInformationFlags::all(), 3, [0xc0]);
+        pi_setget_test!(
+            valid_lifetime,
+            set_valid_lifetime,
+            0xdeadbeef,
+            4,
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        pi_setget_test!(
+            preferred_lifetime,
+            set_preferred_lifetime,
+            0xdeadbeef,
+            8,
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        pi_setget_test!(
+            prefix,
+            set_prefix,
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0),
+            16,
+            [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn neighbor_solicitation() {
+        let target = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 2);
+        let mut data = [0; 24 + 8];
+        {
+            let mut testee = MutNeighborSolicitationPacket::new(&mut data).unwrap();
+            testee.set_icmp_type(Icmpv6Type::NEIGHBOR_SOLICITATION);
+            testee.set_target_address(target);
+            assert_eq!(
+                Some(8),
+                NdpOption::SourceLinkLayerAddress(MAC).write(testee.payload())
+            );
+        }
+        assert_eq!(&[1, 1, 0x02, 0, 0, 0, 0, 0x01], &data[24..]);
+
+        let testee = NeighborSolicitationPacket::new(&data).unwrap();
+        assert_eq!(Icmpv6Type::NEIGHBOR_SOLICITATION, testee.icmp_type());
+        assert_eq!(target, testee.target_address());
+        assert_eq!(
+            vec![NdpOption::SourceLinkLayerAddress(MAC)],
+            testee.options().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn neighbor_advertisement() {
+        let mut data = [0; 24 + 8];
+        data[0] = 136;
+        data[4] = 0x60;
+        data[24..].copy_from_slice(&[2, 1, 0x02, 0, 0, 0, 0, 0x01]);
+        let testee = NeighborAdvertisementPacket::new(&data).unwrap();
+        assert_eq!(
+            NeighborAdvertisementFlags::SOLICITED | NeighborAdvertisementFlags::OVERRIDE,
+            testee.flags()
+        );
+        assert_eq!(
+            vec![NdpOption::TargetLinkLayerAddress(MAC)],
+            testee.options().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn router_advertisement_options() {
+        let prefix = Ipv6Addr::new(0x2001, 0xdb8, 1, 2, 0, 0, 0, 0);
+        let mut data = [0; 16 + 8 + 32 + 8];
+        data[0] = 134;
+        {
+            let mut offset = 16;
+            offset += NdpOption::SourceLinkLayerAddress(MAC).write(&mut data[offset..]).unwrap();
+            {
+                let mut prefix_information =
+                    MutPrefixInformationPacket::new(&mut data[offset..]).unwrap();
+                prefix_information.set_option_header();
+                prefix_information.set_prefix_length(64);
+                prefix_information.set_flags(PrefixInformationFlags::all());
+                prefix_information.set_valid_lifetime(86400);
+                prefix_information.set_prefix(prefix);
+            }
+            offset += 32;
+            NdpOption::Mtu(1500).write(&mut data[offset..]).unwrap();
+        }
+
+        let testee = RouterAdvertisementPacket::new(&data).unwrap();
+        let options = testee.options().collect::<Vec<_>>();
+        assert_eq!(3, options.len());
+        assert_eq!(NdpOption::SourceLinkLayerAddress(MAC), options[0]);
+        match options[1] {
+            NdpOption::PrefixInformation(prefix_information) => {
+                assert_eq!(64, prefix_information.prefix_length());
+                assert_eq!(86400, prefix_information.valid_lifetime());
+                assert_eq!(prefix, prefix_information.prefix());
+            }
+            ref option => panic!("Unexpected option {:?}", option),
+        }
+        assert_eq!(NdpOption::Mtu(1500), options[2]);
+    }
+
+    #[test]
+    fn write_and_read_prefix_information() {
+        let data = [
+            3, 4, 48, 0x40, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let option = NdpOptions::new(&data).next().unwrap();
+        let mut buffer = [0xff; 32];
+        assert_eq!(Some(32), option.write(&mut buffer));
+        assert_eq!(data, buffer);
+    }
+
+    #[test]
+    fn zero_length_option() {
+        let data = [1, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(None, NdpOptions::new(&data).next());
+    }
+
+    #[test]
+    fn truncated_option() {
+        let data = [1, 2, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(None, NdpOptions::new(&data).next());
+    }
+
+    #[test]
+    fn unknown_option() {
+        let data = [14, 1, 1, 2, 3, 4, 5, 6];
+        let option = NdpOptions::new(&data).next().unwrap();
+        assert_eq!(NdpOption::Other(NdpOptionType(14), &data[2..]), option);
+        let mut buffer = [0; 8];
+        assert_eq!(Some(8), option.write(&mut buffer));
+        assert_eq!(data, buffer);
+    }
+
+    #[test]
+    fn unencodable_other_option() {
+        let mut buffer = [0; 16];
+        assert_eq!(None, NdpOption::Other(NdpOptionType(14), &[0; 5]).write(&mut buffer));
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        RouterSolicitationPacketBuf => |packet| packet.code() == 0,
+        RouterAdvertisementPacketBuf => |packet| {
+            packet.icmp_type() == Icmpv6Type::ROUTER_ADVERTISEMENT
+        },
+        NeighborSolicitationPacketBuf => |packet| packet.code() == 0,
+        NeighborAdvertisementPacketBuf => |packet| packet.code() == 0,
+        PrefixInformationPacketBuf => |packet| packet.data()[..2] == [3, 4],
+    );
+}
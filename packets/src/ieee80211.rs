@@ -0,0 +1,696 @@
+//! IEEE 802.11 MAC frames, as found after a Radiotap header in wireless captures.
+//!
+//! The MAC header starts with a frame control field giving the type and subtype of the frame,
+//! which decide which of the remaining header fields are present. Multi-byte fields are little
+//! endian. Captures may include the four byte frame check sequence at the end of the frame, see
+//! `radiotap::Flags::FCS`; strip it before creating a view, or it is read as part of the body.
+
+use alloc::string::String;
+use ethernet::MacAddr;
+use llc::LlcPacket;
+use std::fmt;
+use types::*;
+
+macro_rules! ieee80211_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            /// Returns the protocol version, always zero.
+            pub fn protocol_version(&self) -> u2 {
+                read_offset!(self.0, 0, u8) & 0x03
+            }
+
+            pub fn frame_type(&self) -> FrameType {
+                FrameType((read_offset!(self.0, 0, u8) >> 2) & 0x03)
+            }
+
+            /// Returns the subtype, whose meaning depends on the frame type. See
+            /// `ManagementSubtype`, `ControlSubtype` and `DataSubtype`.
+            pub fn subtype(&self) -> u4 {
+                read_offset!(self.0, 0, u8) >> 4
+            }
+
+            pub fn flags(&self) -> FrameControlFlags {
+                FrameControlFlags::from_bits_truncate(read_offset!(self.0, 1, u8))
+            }
+
+            /// Returns the duration in microseconds, or the association ID in PS-Poll frames.
+            pub fn duration(&self) -> u16 {
+                u16::from_le(read_offset!(self.0, 2, u16))
+            }
+
+            /// Returns the first address, the receiver of the frame.
+            pub fn address1(&self) -> MacAddr {
+                MacAddr::from_slice(&self.0[4..10])
+            }
+
+            /// Returns the second address, the transmitter of the frame, or `None` for
+            /// control frames without it.
+            pub fn address2(&self) -> Option<MacAddr> {
+                self.field(10, 16).map(MacAddr::from_slice)
+            }
+
+            /// Returns the third address, its meaning depending on the DS flags, or `None` for
+            /// control frames.
+            pub fn address3(&self) -> Option<MacAddr> {
+                self.field(16, 22).map(MacAddr::from_slice)
+            }
+
+            /// Returns the sequence control field, or `None` for control frames.
+            pub fn sequence_control(&self) -> Option<u16> {
+                self.field(22, 24).map(|field| u16::from_le(read_offset!(field, 0, u16)))
+            }
+
+            pub fn fragment_number(&self) -> Option<u4> {
+                self.sequence_control().map(|sequence_control| sequence_control as u8 & 0x0f)
+            }
+
+            pub fn sequence_number(&self) -> Option<u12> {
+                self.sequence_control().map(|sequence_control| sequence_control >> 4)
+            }
+
+            /// Returns the fourth address, only present in data frames with both the to DS
+            /// and from DS flags set.
+            pub fn address4(&self) -> Option<MacAddr> {
+                if self.is_four_address() {
+                    self.field(24, 30).map(MacAddr::from_slice)
+                } else {
+                    None
+                }
+            }
+
+            /// Returns the QoS control field, only present in QoS data frames.
+            pub fn qos_control(&self) -> Option<u16> {
+                if self.is_qos_data() {
+                    let offset = if self.is_four_address() { 30 } else { 24 };
+                    self.field(offset, offset + 2)
+                        .map(|field| u16::from_le(read_offset!(field, 0, u16)))
+                } else {
+                    None
+                }
+            }
+        );
+
+        impl<'a> $name<'a> {
+            /// Returns the bytes from `start` to `end` if they are part of the MAC header.
+            fn field(&self, start: usize, end: usize) -> Option<&'a [u8]> {
+                if end <= self.header_len() {
+                    Some(&self.0[start..end])
+                } else {
+                    None
+                }
+            }
+
+            fn is_four_address(&self) -> bool {
+                self.frame_type() == FrameType::DATA
+                    && self.flags().contains(FrameControlFlags::TO_DS | FrameControlFlags::FROM_DS)
+            }
+
+            fn is_qos_data(&self) -> bool {
+                self.frame_type() == FrameType::DATA && self.subtype() & 0x08 != 0
+            }
+        }
+
+        setters!($mut_name
+            pub fn set_protocol_version(&mut self, protocol_version: u2) {
+                let byte = (read_offset!(self.0, 0, u8) & 0xfc) | (protocol_version & 0x03);
+                write_offset!(self.0, 0, byte, u8);
+            }
+
+            pub fn set_frame_type(&mut self, frame_type: FrameType) {
+                let frame_type = (frame_type.value() & 0x03) << 2;
+                let byte = (read_offset!(self.0, 0, u8) & 0xf3) | frame_type;
+                write_offset!(self.0, 0, byte, u8);
+            }
+
+            pub fn set_subtype(&mut self, subtype: u4) {
+                let byte = (read_offset!(self.0, 0, u8) & 0x0f) | (subtype << 4);
+                write_offset!(self.0, 0, byte, u8);
+            }
+
+            pub fn set_flags(&mut self, flags: FrameControlFlags) {
+                write_offset!(self.0, 1, flags.bits(), u8);
+            }
+
+            pub fn set_duration(&mut self, duration: u16) {
+                write_offset!(self.0, 2, duration.to_le(), u16);
+            }
+
+            pub fn set_address1(&mut self, address1: MacAddr) {
+                self.0[4..10].copy_from_slice(address1.as_ref());
+            }
+
+            pub fn set_address2(&mut self, address2: MacAddr) {
+                self.0[10..16].copy_from_slice(address2.as_ref());
+            }
+
+            pub fn set_address3(&mut self, address3: MacAddr) {
+                self.0[16..22].copy_from_slice(address3.as_ref());
+            }
+
+            pub fn set_sequence_control(&mut self, sequence_control: u16) {
+                write_offset!(self.0, 22, sequence_control.to_le(), u16);
+            }
+
+            pub fn set_address4(&mut self, address4: MacAddr) {
+                self.0[24..30].copy_from_slice(address4.as_ref());
+            }
+        );
+    }
+}
+
+packet!(
+    /// An 802.11 MAC frame. The header covers all MAC header fields present for the type of
+    /// frame, and the payload is the frame body.
+    Ieee80211Packet,
+    MutIeee80211Packet,
+    Ieee80211PacketBuf,
+    10,
+    |data| mac_header_len(data),
+    init |data| init_frame(data, FrameType::CONTROL, ControlSubtype::ACK.0)
+);
+ieee80211_common!(Ieee80211Packet, MutIeee80211Packet);
+
+impl<'a> Ieee80211Packet<'a> {
+    /// Returns the frame as a beacon, or `None` if it is another frame or too short.
+    pub fn beacon(&self) -> Option<BeaconPacket<'a>> {
+        self.as_management(ManagementSubtype::BEACON).and_then(BeaconPacket::new)
+    }
+
+    /// Returns the frame as a probe request, or `None` if it is another frame.
+    pub fn probe_request(&self) -> Option<ProbeRequestPacket<'a>> {
+        self.as_management(ManagementSubtype::PROBE_REQUEST).and_then(ProbeRequestPacket::new)
+    }
+
+    /// Returns the frame as a probe response, which shares the layout of beacons, or `None`
+    /// if it is another frame or too short.
+    pub fn probe_response(&self) -> Option<BeaconPacket<'a>> {
+        self.as_management(ManagementSubtype::PROBE_RESPONSE).and_then(BeaconPacket::new)
+    }
+
+    /// Returns the LLC header at the start of the body of a data frame, or `None` if this is
+    /// not a data frame with an unencrypted body.
+    pub fn llc(&self) -> Option<LlcPacket<'a>> {
+        let flags = self.flags();
+        if self.frame_type() == FrameType::DATA && self.subtype() & 0x04 == 0
+            && !flags.contains(FrameControlFlags::PROTECTED)
+        {
+            LlcPacket::new(&self.0[self.header_len()..])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the data if this is a management frame of the given subtype. Frames with an HT
+    /// control field are not supported by the management frame views.
+    fn as_management(&self, subtype: ManagementSubtype) -> Option<&'a [u8]> {
+        if self.frame_type() == FrameType::MANAGEMENT && self.subtype() == subtype.value()
+            && !self.flags().contains(FrameControlFlags::ORDER)
+        {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the length of the MAC header of the frame in `data`, from its frame control field.
+fn mac_header_len(data: &[u8]) -> usize {
+    let frame_type = FrameType((data[0] >> 2) & 0x03);
+    let subtype = data[0] >> 4;
+    let flags = FrameControlFlags::from_bits_truncate(data[1]);
+    let ht_control = if flags.contains(FrameControlFlags::ORDER) { 4 } else { 0 };
+    match frame_type {
+        FrameType::CONTROL => match ControlSubtype(subtype) {
+            ControlSubtype::CTS | ControlSubtype::ACK => 10,
+            _ => 16,
+        },
+        FrameType::DATA => {
+            let mut len = 24;
+            if flags.contains(FrameControlFlags::TO_DS | FrameControlFlags::FROM_DS) {
+                len += 6;
+            }
+            if subtype & 0x08 != 0 {
+                len += 2 + ht_control;
+            }
+            len
+        }
+        _ => 24 + ht_control,
+    }
+}
+
+/// Sets the frame control field of the frame in `data` to protocol version zero and the given
+/// type and subtype, and clears the order flag so that the header has no HT control field.
+fn init_frame(data: &mut [u8], frame_type: FrameType, subtype: u4) {
+    data[0] = (subtype << 4) | (frame_type.0 << 2);
+    data[1] &= !FrameControlFlags::ORDER.bits();
+}
+
+
+packet!(
+    /// A beacon or probe response frame. The payload holds the information elements.
+    BeaconPacket,
+    MutBeaconPacket,
+    BeaconPacketBuf,
+    36,
+    |_data| 36,
+    init |data| init_frame(data, FrameType::MANAGEMENT, ManagementSubtype::BEACON.0)
+);
+ieee80211_common!(BeaconPacket, MutBeaconPacket);
+
+getters!(BeaconPacket
+    /// Returns the value of the timing synchronization function timer of the sender, in
+    /// microseconds.
+    pub fn timestamp(&self) -> u64 {
+        u64::from_le(read_offset!(self.0, 24, u64))
+    }
+
+    /// Returns the interval between beacons, in time units of 1024 microseconds.
+    pub fn beacon_interval(&self) -> u16 {
+        u16::from_le(read_offset!(self.0, 32, u16))
+    }
+
+    pub fn capability_information(&self) -> CapabilityInformation {
+        CapabilityInformation::from_bits_truncate(u16::from_le(read_offset!(self.0, 34, u16)))
+    }
+
+    /// Returns an iterator over the information elements.
+    pub fn elements(&self) -> Elements<'a> {
+        Elements::new(&self.0[36..])
+    }
+
+    /// Returns the SSID of the network, empty for hidden networks, or `None` if there is no
+    /// SSID element.
+    pub fn ssid(&self) -> Option<&'a [u8]> {
+        self.elements().filter_map(|element| element.ssid()).next()
+    }
+
+    /// Returns the current channel, from the DS parameter set element.
+    pub fn channel(&self) -> Option<u8> {
+        self.elements().filter_map(|element| element.channel()).next()
+    }
+);
+
+setters!(MutBeaconPacket
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        write_offset!(self.0, 24, timestamp.to_le(), u64);
+    }
+
+    pub fn set_beacon_interval(&mut self, beacon_interval: u16) {
+        write_offset!(self.0, 32, beacon_interval.to_le(), u16);
+    }
+
+    pub fn set_capability_information(&mut self, capability_information: CapabilityInformation) {
+        write_offset!(self.0, 34, capability_information.bits().to_le(), u16);
+    }
+);
+
+
+packet!(
+    /// A probe request frame. The payload holds the information elements.
+    ProbeRequestPacket,
+    MutProbeRequestPacket,
+    ProbeRequestPacketBuf,
+    24,
+    |_data| 24,
+    init |data| init_frame(data, FrameType::MANAGEMENT, ManagementSubtype::PROBE_REQUEST.0)
+);
+ieee80211_common!(ProbeRequestPacket, MutProbeRequestPacket);
+
+getters!(ProbeRequestPacket
+    /// Returns an iterator over the information elements.
+    pub fn elements(&self) -> Elements<'a> {
+        Elements::new(&self.0[24..])
+    }
+
+    /// Returns the SSID probed for, empty for the wildcard SSID, or `None` if there is no SSID
+    /// element.
+    pub fn ssid(&self) -> Option<&'a [u8]> {
+        self.elements().filter_map(|element| element.ssid()).next()
+    }
+);
+
+
+/// Represents the type of an 802.11 frame.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FrameType(pub u2);
+
+impl FrameType {
+    pub const MANAGEMENT: FrameType = FrameType(0);
+    pub const CONTROL: FrameType = FrameType(1);
+    pub const DATA: FrameType = FrameType(2);
+    pub const EXTENSION: FrameType = FrameType(3);
+
+    #[inline]
+    pub fn value(&self) -> u2 {
+        self.0
+    }
+}
+
+/// Represents the subtype of a management frame.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ManagementSubtype(pub u4);
+
+impl ManagementSubtype {
+    pub const ASSOCIATION_REQUEST: ManagementSubtype = ManagementSubtype(0);
+    pub const ASSOCIATION_RESPONSE: ManagementSubtype = ManagementSubtype(1);
+    pub const REASSOCIATION_REQUEST: ManagementSubtype = ManagementSubtype(2);
+    pub const REASSOCIATION_RESPONSE: ManagementSubtype = ManagementSubtype(3);
+    pub const PROBE_REQUEST: ManagementSubtype = ManagementSubtype(4);
+    pub const PROBE_RESPONSE: ManagementSubtype = ManagementSubtype(5);
+    pub const BEACON: ManagementSubtype = ManagementSubtype(8);
+    pub const ATIM: ManagementSubtype = ManagementSubtype(9);
+    pub const DISASSOCIATION: ManagementSubtype = ManagementSubtype(10);
+    pub const AUTHENTICATION: ManagementSubtype = ManagementSubtype(11);
+    pub const DEAUTHENTICATION: ManagementSubtype = ManagementSubtype(12);
+    pub const ACTION: ManagementSubtype = ManagementSubtype(13);
+
+    #[inline]
+    pub fn value(&self) -> u4 {
+        self.0
+    }
+}
+
+/// Represents the subtype of a control frame.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ControlSubtype(pub u4);
+
+impl ControlSubtype {
+    pub const BLOCK_ACK_REQUEST: ControlSubtype = ControlSubtype(8);
+    pub const BLOCK_ACK: ControlSubtype = ControlSubtype(9);
+    pub const PS_POLL: ControlSubtype = ControlSubtype(10);
+    pub const RTS: ControlSubtype = ControlSubtype(11);
+    pub const CTS: ControlSubtype = ControlSubtype(12);
+    pub const ACK: ControlSubtype = ControlSubtype(13);
+    pub const CF_END: ControlSubtype = ControlSubtype(14);
+
+    #[inline]
+    pub fn value(&self) -> u4 {
+        self.0
+    }
+}
+
+/// Represents the subtype of a data frame. Subtypes with `QOS_DATA` set carry a QoS control
+/// field, and subtypes with `NULL` set carry no body.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DataSubtype(pub u4);
+
+impl DataSubtype {
+    pub const DATA: DataSubtype = DataSubtype(0);
+    pub const NULL: DataSubtype = DataSubtype(4);
+    pub const QOS_DATA: DataSubtype = DataSubtype(8);
+    pub const QOS_NULL: DataSubtype = DataSubtype(12);
+
+    #[inline]
+    pub fn value(&self) -> u4 {
+        self.0
+    }
+}
+
+/// Represents the ID of an information element.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ElementId(pub u8);
+
+impl ElementId {
+    pub const SSID: ElementId = ElementId(0);
+    pub const SUPPORTED_RATES: ElementId = ElementId(1);
+    pub const DS_PARAMETER_SET: ElementId = ElementId(3);
+    pub const TIM: ElementId = ElementId(5);
+    pub const COUNTRY: ElementId = ElementId(7);
+    pub const HT_CAPABILITIES: ElementId = ElementId(45);
+    pub const RSN: ElementId = ElementId(48);
+    pub const EXTENDED_SUPPORTED_RATES: ElementId = ElementId(50);
+    pub const HT_OPERATION: ElementId = ElementId(61);
+    pub const VENDOR_SPECIFIC: ElementId = ElementId(221);
+    pub const EXTENSION: ElementId = ElementId(255);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags in the second byte of the frame control field.
+    pub struct FrameControlFlags: u8 {
+        /// A bitmask with a one in the "To DS" position.
+        const TO_DS = 0x01;
+        /// A bitmask with a one in the "From DS" position.
+        const FROM_DS = 0x02;
+        /// A bitmask with a one in the "More Fragments" position.
+        const MORE_FRAGMENTS = 0x04;
+        /// A bitmask with a one in the "Retry" position.
+        const RETRY = 0x08;
+        /// A bitmask with a one in the "Power Management" position.
+        const POWER_MANAGEMENT = 0x10;
+        /// A bitmask with a one in the "More Data" position.
+        const MORE_DATA = 0x20;
+        /// A bitmask with a one in the "Protected Frame" position, set when the body is
+        /// encrypted.
+        const PROTECTED = 0x40;
+        /// A bitmask with a one in the "+HTC/Order" position, set in QoS data and management
+        /// frames when an HT control field is present.
+        const ORDER = 0x80;
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the capability information field of beacons and probe responses.
+    pub struct CapabilityInformation: u16 {
+        /// A bitmask with a one in the "ESS" position, set by access points.
+        const ESS = 0x0001;
+        /// A bitmask with a one in the "IBSS" position, set in ad hoc networks.
+        const IBSS = 0x0002;
+        /// A bitmask with a one in the "Privacy" position.
+        const PRIVACY = 0x0010;
+        /// A bitmask with a one in the "Short Preamble" position.
+        const SHORT_PREAMBLE = 0x0020;
+        /// A bitmask with a one in the "Spectrum Management" position.
+        const SPECTRUM_MANAGEMENT = 0x0100;
+        /// A bitmask with a one in the "QoS" position.
+        const QOS = 0x0200;
+        /// A bitmask with a one in the "Short Slot Time" position.
+        const SHORT_SLOT_TIME = 0x0400;
+        /// A bitmask with a one in the "Radio Measurement" position.
+        const RADIO_MEASUREMENT = 0x1000;
+    }
+}
+
+
+/// An information element in the body of a management frame.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Element<'a> {
+    pub id: ElementId,
+    pub data: &'a [u8],
+}
+
+impl<'a> Element<'a> {
+    /// Returns the SSID of an SSID element, or `None` if this is another element.
+    pub fn ssid(&self) -> Option<&'a [u8]> {
+        if self.id == ElementId::SSID {
+            Some(self.data)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the channel of a DS parameter set element, or `None` if this is another element
+    /// or it is malformed.
+    pub fn channel(&self) -> Option<u8> {
+        match (self.id, self.data) {
+            (ElementId::DS_PARAMETER_SET, &[channel]) => Some(channel),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the information elements of a management frame. Iteration stops at the first
+/// element that does not fit in the data.
+#[derive(Clone)]
+pub struct Elements<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Elements<'a>);
+
+impl<'a> Elements<'a> {
+    /// Creates an iterator over the information elements encoded in `data`.
+    pub fn new(data: &'a [u8]) -> Elements<'a> {
+        Elements { data }
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Element<'a>;
+
+    fn next(&mut self) -> Option<Element<'a>> {
+        let len = match self.data.get(1) {
+            Some(&len) if 2 + usize::from(len) <= self.data.len() => 2 + usize::from(len),
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (element, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(Element {
+            id: ElementId(element[0]),
+            data: &element[2..],
+        })
+    }
+}
+
+impl<'a> fmt::Display for Ieee80211Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "802.11 type={} subtype={} address1={}",
+            self.frame_type().value(),
+            self.subtype(),
+            self.address1()
+        )
+    }
+}
+
+impl<'a> fmt::Display for BeaconPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ssid = self.ssid().map(String::from_utf8_lossy).unwrap_or_default();
+        write!(
+            f,
+            "802.11 beacon ssid={:?} interval={}",
+            ssid,
+            self.beacon_interval()
+        )
+    }
+}
+
+impl<'a> fmt::Display for ProbeRequestPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ssid = self.ssid().map(String::from_utf8_lossy).unwrap_or_default();
+        write!(f, "802.11 probe request ssid={:?}", ssid)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! ieee80211_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIeee80211Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ieee80211_setget_test!(protocol_version, set_protocol_version, 3, 0, [0x03]);
+    ieee80211_setget_test!(frame_type, set_frame_type, FrameType::EXTENSION, 0, [0x0c]);
+    ieee80211_setget_test!(subtype, set_subtype, 0x0f, 0, [0xf0]);
+    ieee80211_setget_test!(flags, set_flags, FrameControlFlags::RETRY, 1, [0x08]);
+    ieee80211_setget_test!(duration, set_duration, 0xabcd, 2, [0xcd, 0xab]);
+    ieee80211_setget_test!(
+        address1,
+        set_address1,
+        MacAddr::from_bytes(1, 2, 3, 4, 5, 6),
+        4,
+        [1, 2, 3, 4, 5, 6]
+    );
+
+    #[test]
+    fn ack() {
+        let data = [0xd4, 0, 0, 0, 1, 2, 3, 4, 5, 6];
+        let testee = Ieee80211Packet::new(&data).unwrap();
+        assert_eq!(FrameType::CONTROL, testee.frame_type());
+        assert_eq!(ControlSubtype::ACK.value(), testee.subtype());
+        assert_eq!(10, testee.header_len());
+        assert_eq!(MacAddr::from_bytes(1, 2, 3, 4, 5, 6), testee.address1());
+        assert_eq!(None, testee.address2());
+        assert_eq!(None, testee.sequence_control());
+    }
+
+    #[test]
+    fn four_address_qos_data() {
+        let mut data = [0; 32 + 8];
+        {
+            let mut testee = MutIeee80211Packet::new(&mut data).unwrap();
+            testee.set_frame_type(FrameType::DATA);
+            testee.set_subtype(DataSubtype::QOS_DATA.value());
+            testee.set_flags(FrameControlFlags::TO_DS | FrameControlFlags::FROM_DS);
+            testee.set_address2(MacAddr::from_bytes(2, 2, 2, 2, 2, 2));
+            testee.set_address3(MacAddr::from_bytes(3, 3, 3, 3, 3, 3));
+            testee.set_sequence_control(0x1234);
+            testee.set_address4(MacAddr::from_bytes(4, 4, 4, 4, 4, 4));
+        }
+        data[30] = 0x05;
+        data[32..].copy_from_slice(&[0xaa, 0xaa, 0x03, 0, 0, 0, 0x08, 0x00]);
+
+        let testee = Ieee80211Packet::new(&data).unwrap();
+        assert_eq!(32, testee.header_len());
+        assert_eq!(&[0x34, 0x12], &data[22..24]);
+        assert_eq!(Some(0x123), testee.sequence_number());
+        assert_eq!(Some(4), testee.fragment_number());
+        assert_eq!(Some(MacAddr::from_bytes(2, 2, 2, 2, 2, 2)), testee.address2());
+        assert_eq!(Some(MacAddr::from_bytes(3, 3, 3, 3, 3, 3)), testee.address3());
+        assert_eq!(Some(MacAddr::from_bytes(4, 4, 4, 4, 4, 4)), testee.address4());
+        assert_eq!(Some(5), testee.qos_control());
+        assert_eq!(Some(0xaa), testee.llc().map(|llc| llc.dsap()));
+        assert!(testee.beacon().is_none());
+
+        data[1] = 0x41;
+        let testee = Ieee80211Packet::new(&data).unwrap();
+        assert_eq!(26, testee.header_len());
+        assert_eq!(None, testee.address4());
+        assert!(testee.llc().is_none());
+    }
+
+    mod beacon {
+        use super::super::*;
+
+        macro_rules! beacon_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutBeaconPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        beacon_setget_test!(
+            timestamp,
+            set_timestamp,
+            0x0102030405060708,
+            24,
+            [8, 7, 6, 5, 4, 3, 2, 1]
+        );
+        beacon_setget_test!(beacon_interval, set_beacon_interval, 100, 32, [100, 0]);
+        beacon_setget_test!(
+            capability_information,
+            set_capability_information,
+            CapabilityInformation::ESS | CapabilityInformation::QOS,
+            34,
+            [0x01, 0x02]
+        );
+
+        #[test]
+        fn elements() {
+            let mut data = [0; 36 + 6 + 3 + 3];
+            data[0] = 0x80;
+            data[36..].copy_from_slice(&[0, 4, b'r', b'i', b'p', b's', 3, 1, 6, 1, 8, 0x82]);
+            let frame = Ieee80211Packet::new(&data).unwrap();
+            assert_eq!(24, frame.header_len());
+            let testee = frame.beacon().unwrap();
+            assert_eq!(Some(&b"rips"[..]), testee.ssid());
+            assert_eq!(Some(6), testee.channel());
+            assert_eq!(2, testee.elements().count());
+            assert!(frame.probe_response().is_none());
+
+            data[0] = 0x40;
+            let testee = Ieee80211Packet::new(&data).unwrap().probe_request().unwrap();
+            assert_eq!(Some(&b""[..]), testee.ssid());
+        }
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Ieee80211PacketBuf => |packet| packet.protocol_version() == 0,
+        BeaconPacketBuf => |packet| Ieee80211Packet::new(packet.data()).unwrap().beacon().is_some(),
+        ProbeRequestPacketBuf => |packet| {
+            Ieee80211Packet::new(packet.data()).unwrap().probe_request().is_some()
+        },
+    );
+}
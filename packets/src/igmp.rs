@@ -0,0 +1,617 @@
+//! Internet Group Management Protocol, version 2 (RFC 2236) and version 3 (RFC 3376).
+
+use checksum::Checksum;
+use std::cmp;
+use std::fmt;
+use std::net::Ipv4Addr;
+use types::*;
+
+/// Implements the fields common to all IGMP messages, and the checksum calculation over the
+/// whole message, for a pair of IGMP packet types.
+macro_rules! igmp_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn igmp_type(&self) -> IgmpType {
+                IgmpType(read_offset!(self.0, 0, u8))
+            }
+
+            pub fn checksum(&self) -> u16 {
+                read_offset!(self.0, 2, u16, from_be)
+            }
+
+            /// Computes the checksum of the entire backing slice, ignoring the current value of
+            /// the checksum field.
+            pub fn calculate_checksum(&self) -> u16 {
+                let mut checksum = Checksum::new();
+                checksum.add_bytes(&self.0[..2]);
+                checksum.add_bytes(&self.0[4..]);
+                checksum.finish()
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_igmp_type(&mut self, igmp_type: IgmpType) {
+                write_offset!(self.0, 0, igmp_type.value(), u8);
+            }
+
+            pub fn set_checksum(&mut self, checksum: u16) {
+                write_offset!(self.0, 2, checksum, u16, to_be);
+            }
+        );
+    }
+}
+
+/// Sets the type field of the message in `data` to `igmp_type`, if given, and then the checksum
+/// field to the checksum of the message.
+fn init_message(data: &mut [u8], igmp_type: Option<IgmpType>) {
+    if let Some(igmp_type) = igmp_type {
+        write_offset!(data, 0, igmp_type.value(), u8);
+    }
+    let checksum = IgmpPacket(data).calculate_checksum();
+    write_offset!(data, 2, checksum, u16, to_be);
+}
+
+packet!(
+    /// An IGMPv1 or IGMPv2 message. Queries, reports and leave messages all share this format,
+    /// and are told apart by their type. An IGMPv3 query is at least 12 bytes long, while older
+    /// queries are exactly 8.
+    IgmpPacket,
+    MutIgmpPacket,
+    IgmpPacketBuf,
+    8,
+    |_data| 8,
+    init |data| init_message(data, None)
+);
+igmp_common!(IgmpPacket, MutIgmpPacket);
+
+getters!(IgmpPacket
+    /// Returns the maximum time allowed before sending a responding report, in units of 1/10
+    /// second. Always zero in IGMPv1.
+    pub fn max_resp_time(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    pub fn group_address(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+    }
+);
+
+setters!(MutIgmpPacket
+    pub fn set_max_resp_time(&mut self, max_resp_time: u8) {
+        write_offset!(self.0, 1, max_resp_time, u8);
+    }
+
+    pub fn set_group_address(&mut self, group_address: Ipv4Addr) {
+        write_offset!(self.0, 4, group_address.octets(), [u8; 4]);
+    }
+);
+
+
+packet!(
+    /// An IGMPv3 Membership Query.
+    Igmpv3QueryPacket,
+    MutIgmpv3QueryPacket,
+    Igmpv3QueryPacketBuf,
+    12,
+    |data| 12 + usize::from(read_offset!(data, 10, u16, from_be)) * 4,
+    init |data| {
+        write_offset!(data, 10, 0u16, u16);
+        init_message(data, Some(IgmpType::MEMBERSHIP_QUERY));
+    }
+);
+igmp_common!(Igmpv3QueryPacket, MutIgmpv3QueryPacket);
+
+getters!(Igmpv3QueryPacket
+    /// Returns the raw maximum response code. See `max_resp_time` for the decoded value.
+    pub fn max_resp_code(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns the maximum response time in units of 1/10 second, decoded from the maximum
+    /// response code. Codes of 128 and above are a floating point value with a 3 bit exponent
+    /// and a 4 bit mantissa.
+    pub fn max_resp_time(&self) -> u32 {
+        decode_code(self.max_resp_code())
+    }
+
+    pub fn group_address(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+    }
+
+    /// Returns the S flag, telling multicast routers to suppress their timer updates.
+    pub fn suppress_router_processing(&self) -> bool {
+        read_offset!(self.0, 8, u8) & 0x08 != 0
+    }
+
+    /// Returns the Querier's Robustness Variable.
+    pub fn qrv(&self) -> u3 {
+        read_offset!(self.0, 8, u8) & 0x07
+    }
+
+    /// Returns the raw Querier's Query Interval Code. See `query_interval` for the decoded value.
+    pub fn qqic(&self) -> u8 {
+        read_offset!(self.0, 9, u8)
+    }
+
+    /// Returns the querier's query interval in seconds, decoded from the QQIC field in the same
+    /// way as the maximum response code.
+    pub fn query_interval(&self) -> u32 {
+        decode_code(self.qqic())
+    }
+
+    pub fn number_of_sources(&self) -> u16 {
+        read_offset!(self.0, 10, u16, from_be)
+    }
+
+    /// Returns an iterator over the source addresses in the query. Only the addresses that fit
+    /// in the backing slice are included.
+    pub fn sources(&self) -> SourceAddresses<'a> {
+        SourceAddresses { data: &self.0[12..self.header_len()] }
+    }
+);
+
+setters!(MutIgmpv3QueryPacket
+    pub fn set_max_resp_code(&mut self, max_resp_code: u8) {
+        write_offset!(self.0, 1, max_resp_code, u8);
+    }
+
+    pub fn set_group_address(&mut self, group_address: Ipv4Addr) {
+        write_offset!(self.0, 4, group_address.octets(), [u8; 4]);
+    }
+
+    pub fn set_suppress_router_processing(&mut self, suppress_router_processing: bool) {
+        let flags = read_offset!(self.0, 8, u8) & !0x08;
+        let flag: u8 = if suppress_router_processing { 0x08 } else { 0 };
+        write_offset!(self.0, 8, flags | flag, u8);
+    }
+
+    pub fn set_qrv(&mut self, qrv: u3) {
+        let flags = read_offset!(self.0, 8, u8) & !0x07;
+        write_offset!(self.0, 8, flags | (qrv & 0x07), u8);
+    }
+
+    pub fn set_qqic(&mut self, qqic: u8) {
+        write_offset!(self.0, 9, qqic, u8);
+    }
+
+    pub fn set_number_of_sources(&mut self, number_of_sources: u16) {
+        write_offset!(self.0, 10, number_of_sources, u16, to_be);
+    }
+);
+
+impl<'a> MutIgmpv3QueryPacket<'a> {
+    /// Writes `source` at `index` in the list of source addresses. Returns false if the backing
+    /// slice is too short to hold the address.
+    pub fn set_source(&mut self, index: u16, source: Ipv4Addr) -> bool {
+        let offset = 12 + usize::from(index) * 4;
+        offset + 4 <= self.0.len() && {
+            write_offset!(self.0, offset, source.octets(), [u8; 4]);
+            true
+        }
+    }
+}
+
+/// Decodes an 8 bit maximum response code or QQIC, which above 127 is a floating point value.
+fn decode_code(code: u8) -> u32 {
+    if code < 0x80 {
+        u32::from(code)
+    } else {
+        let exponent = (code >> 4) & 0x7;
+        let mantissa = u32::from(code & 0x0f);
+        (mantissa | 0x10) << (exponent + 3)
+    }
+}
+
+
+packet!(
+    /// An IGMPv3 Membership Report. The group records follow the 8 byte header, and can be
+    /// iterated with `records`.
+    Igmpv3ReportPacket,
+    MutIgmpv3ReportPacket,
+    Igmpv3ReportPacketBuf,
+    8,
+    |_data| 8,
+    init |data| {
+        write_offset!(data, 6, 0u16, u16);
+        init_message(data, Some(IgmpType::V3_MEMBERSHIP_REPORT));
+    }
+);
+igmp_common!(Igmpv3ReportPacket, MutIgmpv3ReportPacket);
+
+getters!(Igmpv3ReportPacket
+    pub fn number_of_group_records(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    /// Returns an iterator over the group records in the report.
+    pub fn records(&self) -> GroupRecords<'a> {
+        GroupRecords {
+            data: &self.0[8..],
+            remaining: self.number_of_group_records(),
+        }
+    }
+);
+
+setters!(MutIgmpv3ReportPacket
+    pub fn set_number_of_group_records(&mut self, number_of_group_records: u16) {
+        write_offset!(self.0, 6, number_of_group_records, u16, to_be);
+    }
+);
+
+
+packet!(
+    /// A group record in an IGMPv3 report. The header covers the whole record, including source
+    /// addresses and auxiliary data, so the payload is whatever follows it.
+    GroupRecordPacket,
+    MutGroupRecordPacket,
+    GroupRecordPacketBuf,
+    8,
+    |data| record_len(data),
+    init |data| write_offset!(data, 1, [0, 0, 0], [u8; 3])
+);
+
+getters!(GroupRecordPacket
+    pub fn record_type(&self) -> GroupRecordType {
+        GroupRecordType(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the length of the auxiliary data in units of 32 bit words.
+    pub fn aux_data_len(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    pub fn number_of_sources(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    pub fn multicast_address(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+    }
+
+    /// Returns an iterator over the source addresses in the record. Only the addresses that fit
+    /// in the backing slice are included.
+    pub fn sources(&self) -> SourceAddresses<'a> {
+        let end = cmp::min(self.sources_end(), self.0.len());
+        SourceAddresses { data: &self.0[8..end] }
+    }
+
+    /// Returns the auxiliary data following the source addresses. Truncated if it does not fit
+    /// in the backing slice.
+    pub fn auxiliary_data(&self) -> &'a [u8] {
+        let start = cmp::min(self.sources_end(), self.0.len());
+        &self.0[start..self.header_len()]
+    }
+);
+
+impl<'a> GroupRecordPacket<'a> {
+    fn sources_end(&self) -> usize {
+        8 + usize::from(self.number_of_sources()) * 4
+    }
+}
+
+setters!(MutGroupRecordPacket
+    pub fn set_record_type(&mut self, record_type: GroupRecordType) {
+        write_offset!(self.0, 0, record_type.value(), u8);
+    }
+
+    pub fn set_aux_data_len(&mut self, aux_data_len: u8) {
+        write_offset!(self.0, 1, aux_data_len, u8);
+    }
+
+    pub fn set_number_of_sources(&mut self, number_of_sources: u16) {
+        write_offset!(self.0, 2, number_of_sources, u16, to_be);
+    }
+
+    pub fn set_multicast_address(&mut self, multicast_address: Ipv4Addr) {
+        write_offset!(self.0, 4, multicast_address.octets(), [u8; 4]);
+    }
+);
+
+impl<'a> MutGroupRecordPacket<'a> {
+    /// Writes `source` at `index` in the list of source addresses. Returns false if the backing
+    /// slice is too short to hold the address.
+    pub fn set_source(&mut self, index: u16, source: Ipv4Addr) -> bool {
+        let offset = 8 + usize::from(index) * 4;
+        offset + 4 <= self.0.len() && {
+            write_offset!(self.0, offset, source.octets(), [u8; 4]);
+            true
+        }
+    }
+}
+
+/// Returns the full length of the group record at the start of `data`.
+fn record_len(data: &[u8]) -> usize {
+    let number_of_sources = usize::from(read_offset!(data, 2, u16, from_be));
+    8 + number_of_sources * 4 + usize::from(data[1]) * 4
+}
+
+
+/// The 8 bit type field of an IGMP message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct IgmpType(pub u8);
+
+impl IgmpType {
+    pub const MEMBERSHIP_QUERY: IgmpType = IgmpType(0x11);
+    pub const V1_MEMBERSHIP_REPORT: IgmpType = IgmpType(0x12);
+    pub const V2_MEMBERSHIP_REPORT: IgmpType = IgmpType(0x16);
+    pub const LEAVE_GROUP: IgmpType = IgmpType(0x17);
+    pub const V3_MEMBERSHIP_REPORT: IgmpType = IgmpType(0x22);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The 8 bit record type of an IGMPv3 group record.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct GroupRecordType(pub u8);
+
+impl GroupRecordType {
+    pub const MODE_IS_INCLUDE: GroupRecordType = GroupRecordType(1);
+    pub const MODE_IS_EXCLUDE: GroupRecordType = GroupRecordType(2);
+    pub const CHANGE_TO_INCLUDE_MODE: GroupRecordType = GroupRecordType(3);
+    pub const CHANGE_TO_EXCLUDE_MODE: GroupRecordType = GroupRecordType(4);
+    pub const ALLOW_NEW_SOURCES: GroupRecordType = GroupRecordType(5);
+    pub const BLOCK_OLD_SOURCES: GroupRecordType = GroupRecordType(6);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+/// Iterator over a list of IPv4 source addresses in an IGMPv3 message.
+#[derive(Clone)]
+pub struct SourceAddresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(SourceAddresses<'a>);
+
+impl<'a> Iterator for SourceAddresses<'a> {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Ipv4Addr> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (source, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(Ipv4Addr::from(read_offset!(source, 0, [u8; 4])))
+    }
+}
+
+/// Iterator over the group records in an IGMPv3 report. Iteration stops after the number of
+/// records given in the report, or at the first record that does not fit in the data.
+#[derive(Clone)]
+pub struct GroupRecords<'a> {
+    data: &'a [u8],
+    remaining: u16,
+}
+
+debug_items!(GroupRecords<'a>);
+
+impl<'a> Iterator for GroupRecords<'a> {
+    type Item = GroupRecordPacket<'a>;
+
+    fn next(&mut self) -> Option<GroupRecordPacket<'a>> {
+        if self.remaining == 0 ||
+            self.data.len() < GroupRecordPacket::MIN_LEN ||
+            record_len(self.data) > self.data.len()
+        {
+            self.data = &[];
+            return None;
+        }
+        let (record, rest) = self.data.split_at(record_len(self.data));
+        self.data = rest;
+        self.remaining -= 1;
+        Some(GroupRecordPacket(record))
+    }
+}
+
+impl<'a> fmt::Display for IgmpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IGMP type={:#04x} group={}",
+            self.igmp_type().value(),
+            self.group_address()
+        )
+    }
+}
+
+impl<'a> fmt::Display for Igmpv3QueryPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IGMPv3 query group={} sources={}",
+            self.group_address(),
+            self.number_of_sources()
+        )
+    }
+}
+
+impl<'a> fmt::Display for Igmpv3ReportPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IGMPv3 report records={}",
+            self.number_of_group_records()
+        )
+    }
+}
+
+impl<'a> fmt::Display for GroupRecordPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IGMPv3 record type={} group={} sources={}",
+            self.record_type().value(),
+            self.multicast_address(),
+            self.number_of_sources()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! igmp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIgmpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    igmp_setget_test!(igmp_type, set_igmp_type, IgmpType::LEAVE_GROUP, 0, [0x17]);
+    igmp_setget_test!(max_resp_time, set_max_resp_time, 0xab, 1, [0xab]);
+    igmp_setget_test!(checksum, set_checksum, 0xabcd, 2, [0xab, 0xcd]);
+    igmp_setget_test!(
+        group_address,
+        set_group_address,
+        Ipv4Addr::new(224, 0, 0, 251),
+        4,
+        [224, 0, 0, 251]
+    );
+
+    mod query {
+        use super::super::*;
+
+        macro_rules! query_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutIgmpv3QueryPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        query_setget_test!(max_resp_code, set_max_resp_code, 0xab, 1, [0xab]);
+        query_setget_test!(
+            suppress_router_processing,
+            set_suppress_router_processing,
+            true,
+            8,
+            [0x08]
+        );
+        query_setget_test!(qrv, set_qrv, 0x07, 8, [0x07]);
+        query_setget_test!(qqic, set_qqic, 0xab, 9, [0xab]);
+        query_setget_test!(number_of_sources, set_number_of_sources, 0xabcd, 10, [0xab, 0xcd]);
+    }
+
+    mod record {
+        use super::super::*;
+
+        macro_rules! record_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutGroupRecordPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        record_setget_test!(
+            record_type,
+            set_record_type,
+            GroupRecordType::ALLOW_NEW_SOURCES,
+            0,
+            [5]
+        );
+        record_setget_test!(aux_data_len, set_aux_data_len, 0xab, 1, [0xab]);
+        record_setget_test!(number_of_sources, set_number_of_sources, 0xabcd, 2, [0xab, 0xcd]);
+        record_setget_test!(
+            multicast_address,
+            set_multicast_address,
+            Ipv4Addr::new(239, 1, 2, 3),
+            4,
+            [239, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn v2_report_checksum() {
+        // IGMPv2 report for 224.0.0.251
+        let data = [0x16, 0x00, 0x09, 0x04, 0xe0, 0x00, 0x00, 0xfb];
+        let testee = IgmpPacket::new(&data).unwrap();
+        assert_eq!(IgmpType::V2_MEMBERSHIP_REPORT, testee.igmp_type());
+        assert_eq!(testee.checksum(), testee.calculate_checksum());
+    }
+
+    #[test]
+    fn query_decoded_times() {
+        let mut data = [0; 12];
+        data[1] = 100;
+        data[9] = 125;
+        let testee = Igmpv3QueryPacket::new(&data).unwrap();
+        assert_eq!(100, testee.max_resp_time());
+        assert_eq!(125, testee.query_interval());
+
+        data[1] = 0x81;
+        let testee = Igmpv3QueryPacket::new(&data).unwrap();
+        assert_eq!(0x11 << 3, testee.max_resp_time());
+    }
+
+    #[test]
+    fn query_sources() {
+        let mut data = [0; 12 + 2 * 4];
+        {
+            let mut testee = MutIgmpv3QueryPacket::new(&mut data).unwrap();
+            testee.set_igmp_type(IgmpType::MEMBERSHIP_QUERY);
+            testee.set_number_of_sources(2);
+            assert!(testee.set_source(0, Ipv4Addr::new(10, 0, 0, 1)));
+            assert!(testee.set_source(1, Ipv4Addr::new(10, 0, 0, 2)));
+        }
+        let testee = Igmpv3QueryPacket::new(&data).unwrap();
+        assert_eq!(20, testee.header_len());
+        assert_eq!(
+            vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)],
+            testee.sources().collect::<Vec<_>>()
+        );
+        let truncated = Igmpv3QueryPacket::new(&data[..18]).unwrap();
+        assert_eq!(1, truncated.sources().count());
+    }
+
+    #[test]
+    fn report_records() {
+        let mut data = [0; 8 + 8 + 4 + 4 + 8];
+        data[0] = 0x22;
+        data[7] = 2;
+        {
+            let mut record = MutGroupRecordPacket::new(&mut data[8..]).unwrap();
+            record.set_record_type(GroupRecordType::MODE_IS_EXCLUDE);
+            record.set_aux_data_len(1);
+            record.set_number_of_sources(1);
+            record.set_multicast_address(Ipv4Addr::new(239, 0, 0, 1));
+            assert!(record.set_source(0, Ipv4Addr::new(192, 168, 0, 1)));
+        }
+        data[20..24].copy_from_slice(&[1, 2, 3, 4]);
+        data[24] = GroupRecordType::BLOCK_OLD_SOURCES.value();
+
+        let testee = Igmpv3ReportPacket::new(&data).unwrap();
+        let records = testee.records().collect::<Vec<_>>();
+        assert_eq!(2, records.len());
+        assert_eq!(GroupRecordType::MODE_IS_EXCLUDE, records[0].record_type());
+        assert_eq!(Ipv4Addr::new(239, 0, 0, 1), records[0].multicast_address());
+        assert_eq!(
+            vec![Ipv4Addr::new(192, 168, 0, 1)],
+            records[0].sources().collect::<Vec<_>>()
+        );
+        assert_eq!(&[1, 2, 3, 4], records[0].auxiliary_data());
+        assert_eq!(GroupRecordType::BLOCK_OLD_SOURCES, records[1].record_type());
+    }
+
+    #[test]
+    fn report_records_stop() {
+        let mut data = [0; 8 + 8];
+        data[7] = 2;
+        assert_eq!(1, Igmpv3ReportPacket::new(&data).unwrap().records().count());
+        data[8 + 3] = 1;
+        assert_eq!(0, Igmpv3ReportPacket::new(&data).unwrap().records().count());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        IgmpPacketBuf => |packet| packet.checksum() == packet.calculate_checksum(),
+        Igmpv3QueryPacketBuf => |packet| packet.number_of_sources() == 0,
+        Igmpv3ReportPacketBuf => |packet| packet.number_of_group_records() == 0,
+        GroupRecordPacketBuf => |packet| packet.aux_data_len() == 0,
+    );
+}
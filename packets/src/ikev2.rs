@@ -0,0 +1,370 @@
+//! The Internet Key Exchange Protocol Version 2, RFC 7296.
+//!
+//! An IKE message is a fixed header followed by a chain of payloads. Each payload starts with a
+//! generic header naming the type of the payload after it, with the type of the first payload
+//! given by the IKE header.
+
+use std::cmp;
+use std::fmt;
+use types::*;
+
+/// The UDP port used by IKE. When a NAT is detected IKE moves to `esp::NAT_T_UDP_PORT`, where
+/// its messages are prefixed by four zero bytes to tell them from ESP packets.
+pub const UDP_PORT: u16 = 500;
+
+packet!(
+    /// An IKE message. The payload is the payload chain, see `payloads`.
+    Ikev2Packet,
+    MutIkev2Packet,
+    Ikev2PacketBuf,
+    28,
+    |_data| 28,
+    len |data| read_offset!(data, 24, u32, from_be) as usize,
+    set_len |data, len| {
+        len <= u32::MAX as usize && {
+            write_offset!(data, 24, len as u32, u32, to_be);
+            true
+        }
+    },
+    init |data| {
+        write_offset!(data, 17, 0x20u8, u8);
+        MutIkev2Packet::set_len(data, data.len());
+    }
+);
+
+getters!(Ikev2Packet
+    /// Returns the SPI chosen by the initiator of the IKE SA.
+    pub fn initiator_spi(&self) -> u64 {
+        read_offset!(self.0, 0, u64, from_be)
+    }
+
+    /// Returns the SPI chosen by the responder of the IKE SA, zero in the first message.
+    pub fn responder_spi(&self) -> u64 {
+        read_offset!(self.0, 8, u64, from_be)
+    }
+
+    /// Returns the type of the first payload.
+    pub fn next_payload(&self) -> PayloadType {
+        PayloadType(read_offset!(self.0, 16, u8))
+    }
+
+    /// Returns the major version, 2 for IKEv2.
+    pub fn major_version(&self) -> u4 {
+        read_offset!(self.0, 17, u8) >> 4
+    }
+
+    pub fn minor_version(&self) -> u4 {
+        read_offset!(self.0, 17, u8) & 0x0f
+    }
+
+    pub fn exchange_type(&self) -> ExchangeType {
+        ExchangeType(read_offset!(self.0, 18, u8))
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 19, u8))
+    }
+
+    pub fn message_id(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    /// Returns the length of the whole message, including this header.
+    pub fn length(&self) -> u32 {
+        read_offset!(self.0, 24, u32, from_be)
+    }
+
+    /// Returns an iterator over the payload chain, limited to the message length given by the
+    /// header.
+    pub fn payloads(&self) -> Payloads<'a> {
+        let end = cmp::min(self.length() as usize, self.0.len());
+        Payloads::new(self.0.get(28..end).unwrap_or(&[]), self.next_payload())
+    }
+);
+
+setters!(MutIkev2Packet
+    pub fn set_initiator_spi(&mut self, initiator_spi: u64) {
+        write_offset!(self.0, 0, initiator_spi, u64, to_be);
+    }
+
+    pub fn set_responder_spi(&mut self, responder_spi: u64) {
+        write_offset!(self.0, 8, responder_spi, u64, to_be);
+    }
+
+    pub fn set_next_payload(&mut self, next_payload: PayloadType) {
+        write_offset!(self.0, 16, next_payload.value(), u8);
+    }
+
+    pub fn set_major_version(&mut self, major_version: u4) {
+        let new_byte = (major_version << 4) | (read_offset!(self.0, 17, u8) & 0x0f);
+        write_offset!(self.0, 17, new_byte, u8);
+    }
+
+    pub fn set_minor_version(&mut self, minor_version: u4) {
+        let new_byte = (read_offset!(self.0, 17, u8) & 0xf0) | (minor_version & 0x0f);
+        write_offset!(self.0, 17, new_byte, u8);
+    }
+
+    pub fn set_exchange_type(&mut self, exchange_type: ExchangeType) {
+        write_offset!(self.0, 18, exchange_type.value(), u8);
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_offset!(self.0, 19, flags.bits(), u8);
+    }
+
+    pub fn set_message_id(&mut self, message_id: u32) {
+        write_offset!(self.0, 20, message_id, u32, to_be);
+    }
+
+    pub fn set_length(&mut self, length: u32) {
+        write_offset!(self.0, 24, length, u32, to_be);
+    }
+);
+
+
+/// The type of an IKE payload.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PayloadType(pub u8);
+
+impl PayloadType {
+    /// Marks the end of the payload chain.
+    pub const NO_NEXT_PAYLOAD: PayloadType = PayloadType(0);
+    pub const SECURITY_ASSOCIATION: PayloadType = PayloadType(33);
+    pub const KEY_EXCHANGE: PayloadType = PayloadType(34);
+    pub const IDENTIFICATION_INITIATOR: PayloadType = PayloadType(35);
+    pub const IDENTIFICATION_RESPONDER: PayloadType = PayloadType(36);
+    pub const CERTIFICATE: PayloadType = PayloadType(37);
+    pub const CERTIFICATE_REQUEST: PayloadType = PayloadType(38);
+    pub const AUTHENTICATION: PayloadType = PayloadType(39);
+    pub const NONCE: PayloadType = PayloadType(40);
+    pub const NOTIFY: PayloadType = PayloadType(41);
+    pub const DELETE: PayloadType = PayloadType(42);
+    pub const VENDOR_ID: PayloadType = PayloadType(43);
+    pub const TRAFFIC_SELECTOR_INITIATOR: PayloadType = PayloadType(44);
+    pub const TRAFFIC_SELECTOR_RESPONDER: PayloadType = PayloadType(45);
+    /// Holds the remaining payloads in encrypted form.
+    pub const ENCRYPTED: PayloadType = PayloadType(46);
+    pub const CONFIGURATION: PayloadType = PayloadType(47);
+    pub const EAP: PayloadType = PayloadType(48);
+    /// An encrypted fragment of a message, RFC 7383.
+    pub const ENCRYPTED_FRAGMENT: PayloadType = PayloadType(53);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The type of an IKE exchange.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExchangeType(pub u8);
+
+impl ExchangeType {
+    pub const IKE_SA_INIT: ExchangeType = ExchangeType(34);
+    pub const IKE_AUTH: ExchangeType = ExchangeType(35);
+    pub const CREATE_CHILD_SA: ExchangeType = ExchangeType(36);
+    pub const INFORMATIONAL: ExchangeType = ExchangeType(37);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags field of the IKE header.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "Initiator" position, set by the original initiator of
+        /// the IKE SA.
+        const INITIATOR = 0x08;
+        /// A bitmask with a one in the "Version" position, telling the sender supports a higher
+        /// major version.
+        const VERSION = 0x10;
+        /// A bitmask with a one in the "Response" position.
+        const RESPONSE = 0x20;
+    }
+}
+
+
+/// A payload in an IKE message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Payload<'a> {
+    pub payload_type: PayloadType,
+    /// The critical bit, telling the receiver to reject the message if it does not understand
+    /// the payload type.
+    pub critical: bool,
+    /// The type of the payload following this one. For encrypted payloads, the type of the
+    /// first payload inside it.
+    pub next_payload: PayloadType,
+    /// The payload content following the generic payload header.
+    pub data: &'a [u8],
+}
+
+/// Iterator over the payload chain of an IKE message. Iteration stops at the end of the chain,
+/// after an encrypted payload, as that is always the last one, or at the first payload that
+/// does not fit in the data.
+#[derive(Clone)]
+pub struct Payloads<'a> {
+    data: &'a [u8],
+    next_payload: PayloadType,
+}
+
+debug_items!(Payloads<'a>);
+
+impl<'a> Payloads<'a> {
+    /// Creates an iterator over the payloads in `data`, the first of which is of type
+    /// `first_payload`.
+    pub fn new(data: &'a [u8], first_payload: PayloadType) -> Payloads<'a> {
+        Payloads { data, next_payload: first_payload }
+    }
+}
+
+impl<'a> Iterator for Payloads<'a> {
+    type Item = Payload<'a>;
+
+    fn next(&mut self) -> Option<Payload<'a>> {
+        if self.next_payload == PayloadType::NO_NEXT_PAYLOAD || self.data.len() < 4 {
+            return None;
+        }
+        let len = usize::from(read_offset!(self.data, 2, u16, from_be));
+        if len < 4 || len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (payload, rest) = self.data.split_at(len);
+        let payload = Payload {
+            payload_type: self.next_payload,
+            critical: payload[1] & 0x80 != 0,
+            next_payload: PayloadType(payload[0]),
+            data: &payload[4..],
+        };
+        self.data = rest;
+        self.next_payload = match payload.payload_type {
+            PayloadType::ENCRYPTED | PayloadType::ENCRYPTED_FRAGMENT => {
+                PayloadType::NO_NEXT_PAYLOAD
+            }
+            _ => payload.next_payload,
+        };
+        Some(payload)
+    }
+}
+
+/// Encodes a payload with a generic payload header into the beginning of `buffer`. Returns the
+/// number of bytes written, or `None` if the payload does not fit in `buffer` or is too long to
+/// encode.
+pub fn write_payload(
+    buffer: &mut [u8],
+    next_payload: PayloadType,
+    critical: bool,
+    data: &[u8],
+) -> Option<usize> {
+    let len = 4 + data.len();
+    if len > usize::from(u16::MAX) {
+        return None;
+    }
+    let payload = buffer.get_mut(..len)?;
+    payload[0] = next_payload.value();
+    payload[1] = (critical as u8) << 7;
+    write_offset!(payload, 2, len as u16, u16, to_be);
+    payload[4..].copy_from_slice(data);
+    Some(len)
+}
+
+impl<'a> fmt::Display for Ikev2Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IKEv2 exchange={} id={} len={}",
+            self.exchange_type().value(),
+            self.message_id(),
+            self.length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! ikev2_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIkev2Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ikev2_setget_test!(
+        initiator_spi,
+        set_initiator_spi,
+        0x0102030405060708,
+        0,
+        [1, 2, 3, 4, 5, 6, 7, 8]
+    );
+    ikev2_setget_test!(
+        responder_spi,
+        set_responder_spi,
+        0x0102030405060708,
+        8,
+        [1, 2, 3, 4, 5, 6, 7, 8]
+    );
+    ikev2_setget_test!(next_payload, set_next_payload, PayloadType::NONCE, 16, [40]);
+    ikev2_setget_test!(major_version, set_major_version, 2, 17, [0x20]);
+    ikev2_setget_test!(minor_version, set_minor_version, 0x0f, 17, [0x0f]);
+    ikev2_setget_test!(exchange_type, set_exchange_type, ExchangeType::IKE_AUTH, 18, [35]);
+    ikev2_setget_test!(flags, set_flags, Flags::INITIATOR | Flags::RESPONSE, 19, [0x28]);
+    ikev2_setget_test!(message_id, set_message_id, 0xabcdef01, 20, [0xab, 0xcd, 0xef, 0x01]);
+    ikev2_setget_test!(length, set_length, 0xabcdef01, 24, [0xab, 0xcd, 0xef, 0x01]);
+
+    #[test]
+    fn payload_chain() {
+        let mut data = [0; 28 + 8 + 6 + 4 + 4];
+        let len = {
+            let mut offset = 28;
+            offset += write_payload(&mut data[offset..], PayloadType::NOTIFY, false, &[1; 4])
+                .unwrap();
+            offset += write_payload(&mut data[offset..], PayloadType::ENCRYPTED, true, &[2; 2])
+                .unwrap();
+            offset += write_payload(&mut data[offset..], PayloadType::NONCE, false, &[]).unwrap();
+            let mut testee = MutIkev2Packet::new(&mut data).unwrap();
+            testee.set_next_payload(PayloadType::NONCE);
+            testee.set_major_version(2);
+            testee.set_exchange_type(ExchangeType::IKE_SA_INIT);
+            testee.set_flags(Flags::INITIATOR);
+            testee.set_length(offset as u32);
+            offset
+        };
+        assert_eq!(46, len);
+
+        let testee = Ikev2Packet::new(&data).unwrap();
+        let payloads = testee.payloads().collect::<Vec<_>>();
+        assert_eq!(3, payloads.len());
+        assert_eq!(PayloadType::NONCE, payloads[0].payload_type);
+        assert_eq!(&[1; 4], payloads[0].data);
+        assert_eq!(PayloadType::NOTIFY, payloads[1].payload_type);
+        assert!(payloads[1].critical);
+        assert_eq!(PayloadType::ENCRYPTED, payloads[2].payload_type);
+        assert_eq!(PayloadType::NONCE, payloads[2].next_payload);
+    }
+
+    #[test]
+    fn truncated_payload_chain() {
+        let data = [PayloadType::NOTIFY.value(), 0, 0, 6, 1, 2, 0, 0, 0, 8, 1, 2];
+        let payloads = Payloads::new(&data, PayloadType::NONCE).collect::<Vec<_>>();
+        assert_eq!(1, payloads.len());
+        assert_eq!(PayloadType::NOTIFY, payloads[0].next_payload);
+        assert_eq!(0, Payloads::new(&data, PayloadType::NO_NEXT_PAYLOAD).count());
+        assert_eq!(0, Payloads::new(&[0, 0, 0, 2], PayloadType::NONCE).count());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 28 + 4];
+        let mut testee = MutIkev2Packet::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[1, 2]));
+        assert_eq!(30, testee.as_immutable().length());
+        assert_eq!(&[1, 2], testee.as_immutable().payload());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, Ikev2PacketBuf => |packet| packet.major_version() == 2);
+}
@@ -1,27 +1,421 @@
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// Represents the eight bit header field in IPv4/IPv6 that defines what protocol the payload has.
-/// See [this list] for the full definition.
 ///
-/// [this list]: https://en.wikipedia.org/wiki/List_of_IP_protocol_numbers
+/// Every number assigned by IANA has a constant here, named after its keyword in [the
+/// registry]. The numbers without a keyword, like 61 for "any host internal protocol", have
+/// none.
+///
+/// [the registry]: https://www.iana.org/assignments/protocol-numbers/protocol-numbers.xhtml
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Protocol(pub u8);
 
 impl Protocol {
+    pub const HOPOPT: Protocol = Protocol(0);
     pub const ICMP: Protocol = Protocol(1);
+    pub const IGMP: Protocol = Protocol(2);
+    pub const GGP: Protocol = Protocol(3);
+    pub const IPV4: Protocol = Protocol(4);
+    pub const ST: Protocol = Protocol(5);
     pub const TCP: Protocol = Protocol(6);
+    pub const CBT: Protocol = Protocol(7);
+    pub const EGP: Protocol = Protocol(8);
+    pub const IGP: Protocol = Protocol(9);
+    pub const BBN_RCC_MON: Protocol = Protocol(10);
+    pub const NVP_II: Protocol = Protocol(11);
+    pub const PUP: Protocol = Protocol(12);
+    pub const ARGUS: Protocol = Protocol(13);
+    pub const EMCON: Protocol = Protocol(14);
+    pub const XNET: Protocol = Protocol(15);
+    pub const CHAOS: Protocol = Protocol(16);
     pub const UDP: Protocol = Protocol(17);
+    pub const MUX: Protocol = Protocol(18);
+    pub const DCN_MEAS: Protocol = Protocol(19);
+    pub const HMP: Protocol = Protocol(20);
+    pub const PRM: Protocol = Protocol(21);
+    pub const XNS_IDP: Protocol = Protocol(22);
+    pub const TRUNK_1: Protocol = Protocol(23);
+    pub const TRUNK_2: Protocol = Protocol(24);
+    pub const LEAF_1: Protocol = Protocol(25);
+    pub const LEAF_2: Protocol = Protocol(26);
+    pub const RDP: Protocol = Protocol(27);
+    pub const IRTP: Protocol = Protocol(28);
+    pub const ISO_TP4: Protocol = Protocol(29);
+    pub const NETBLT: Protocol = Protocol(30);
+    pub const MFE_NSP: Protocol = Protocol(31);
+    pub const MERIT_INP: Protocol = Protocol(32);
+    pub const DCCP: Protocol = Protocol(33);
+    pub const THREE_PC: Protocol = Protocol(34);
+    pub const IDPR: Protocol = Protocol(35);
+    pub const XTP: Protocol = Protocol(36);
+    pub const DDP: Protocol = Protocol(37);
+    pub const IDPR_CMTP: Protocol = Protocol(38);
+    pub const TP_PLUS_PLUS: Protocol = Protocol(39);
+    pub const IL: Protocol = Protocol(40);
+    pub const IPV6: Protocol = Protocol(41);
+    pub const SDRP: Protocol = Protocol(42);
+    pub const IPV6_ROUTE: Protocol = Protocol(43);
+    pub const IPV6_FRAG: Protocol = Protocol(44);
+    pub const IDRP: Protocol = Protocol(45);
+    pub const RSVP: Protocol = Protocol(46);
+    pub const GRE: Protocol = Protocol(47);
+    pub const DSR: Protocol = Protocol(48);
+    pub const BNA: Protocol = Protocol(49);
+    pub const ESP: Protocol = Protocol(50);
+    pub const AH: Protocol = Protocol(51);
+    pub const I_NLSP: Protocol = Protocol(52);
+    pub const SWIPE: Protocol = Protocol(53);
+    pub const NARP: Protocol = Protocol(54);
+    pub const MIN_IPV4: Protocol = Protocol(55);
+    pub const TLSP: Protocol = Protocol(56);
+    pub const SKIP: Protocol = Protocol(57);
+    pub const ICMPV6: Protocol = Protocol(58);
+    pub const IPV6_NONXT: Protocol = Protocol(59);
+    pub const IPV6_OPTS: Protocol = Protocol(60);
+    pub const CFTP: Protocol = Protocol(62);
+    pub const SAT_EXPAK: Protocol = Protocol(64);
+    pub const KRYPTOLAN: Protocol = Protocol(65);
+    pub const RVD: Protocol = Protocol(66);
+    pub const IPPC: Protocol = Protocol(67);
+    pub const SAT_MON: Protocol = Protocol(69);
+    pub const VISA: Protocol = Protocol(70);
+    pub const IPCV: Protocol = Protocol(71);
+    pub const CPNX: Protocol = Protocol(72);
+    pub const CPHB: Protocol = Protocol(73);
+    pub const WSN: Protocol = Protocol(74);
+    pub const PVP: Protocol = Protocol(75);
+    pub const BR_SAT_MON: Protocol = Protocol(76);
+    pub const SUN_ND: Protocol = Protocol(77);
+    pub const WB_MON: Protocol = Protocol(78);
+    pub const WB_EXPAK: Protocol = Protocol(79);
+    pub const ISO_IP: Protocol = Protocol(80);
+    pub const VMTP: Protocol = Protocol(81);
+    pub const SECURE_VMTP: Protocol = Protocol(82);
+    pub const VINES: Protocol = Protocol(83);
+    pub const IPTM: Protocol = Protocol(84);
+    pub const NSFNET_IGP: Protocol = Protocol(85);
+    pub const DGP: Protocol = Protocol(86);
+    pub const TCF: Protocol = Protocol(87);
+    pub const EIGRP: Protocol = Protocol(88);
+    pub const OSPF: Protocol = Protocol(89);
+    pub const SPRITE_RPC: Protocol = Protocol(90);
+    pub const LARP: Protocol = Protocol(91);
+    pub const MTP: Protocol = Protocol(92);
+    pub const AX25: Protocol = Protocol(93);
+    pub const IPIP: Protocol = Protocol(94);
+    pub const MICP: Protocol = Protocol(95);
+    pub const SCC_SP: Protocol = Protocol(96);
+    pub const ETHERIP: Protocol = Protocol(97);
+    pub const ENCAP: Protocol = Protocol(98);
+    pub const GMTP: Protocol = Protocol(100);
+    pub const IFMP: Protocol = Protocol(101);
+    pub const PNNI: Protocol = Protocol(102);
+    pub const PIM: Protocol = Protocol(103);
+    pub const ARIS: Protocol = Protocol(104);
+    pub const SCPS: Protocol = Protocol(105);
+    pub const QNX: Protocol = Protocol(106);
+    pub const AN: Protocol = Protocol(107);
+    pub const IPCOMP: Protocol = Protocol(108);
+    pub const SNP: Protocol = Protocol(109);
+    pub const COMPAQ_PEER: Protocol = Protocol(110);
+    pub const IPX_IN_IP: Protocol = Protocol(111);
+    pub const VRRP: Protocol = Protocol(112);
+    pub const PGM: Protocol = Protocol(113);
+    pub const L2TP: Protocol = Protocol(115);
+    pub const DDX: Protocol = Protocol(116);
+    pub const IATP: Protocol = Protocol(117);
+    pub const STP: Protocol = Protocol(118);
+    pub const SRP: Protocol = Protocol(119);
+    pub const UTI: Protocol = Protocol(120);
+    pub const SMP: Protocol = Protocol(121);
+    pub const SM: Protocol = Protocol(122);
+    pub const PTP: Protocol = Protocol(123);
+    pub const ISIS_OVER_IPV4: Protocol = Protocol(124);
+    pub const FIRE: Protocol = Protocol(125);
+    pub const CRTP: Protocol = Protocol(126);
+    pub const CRUDP: Protocol = Protocol(127);
+    pub const SSCOPMCE: Protocol = Protocol(128);
+    pub const IPLT: Protocol = Protocol(129);
+    pub const SPS: Protocol = Protocol(130);
+    pub const PIPE: Protocol = Protocol(131);
+    pub const SCTP: Protocol = Protocol(132);
+    pub const FC: Protocol = Protocol(133);
+    pub const RSVP_E2E_IGNORE: Protocol = Protocol(134);
+    pub const MOBILITY_HEADER: Protocol = Protocol(135);
+    pub const UDPLITE: Protocol = Protocol(136);
+    pub const MPLS_IN_IP: Protocol = Protocol(137);
+    pub const MANET: Protocol = Protocol(138);
+    pub const HIP: Protocol = Protocol(139);
+    pub const SHIM6: Protocol = Protocol(140);
+    pub const WESP: Protocol = Protocol(141);
+    pub const ROHC: Protocol = Protocol(142);
+    pub const ETHERNET: Protocol = Protocol(143);
+    pub const AGGFRAG: Protocol = Protocol(144);
+    pub const NSH: Protocol = Protocol(145);
     pub const RESERVED: Protocol = Protocol(255);
 
+    /// Every protocol with a constant here together with its keyword, as used by `Display` and
+    /// `FromStr`. The keywords are those of the IANA registry, with spaces replaced by dashes,
+    /// except that ICMPv6 is used for IPv6-ICMP.
+    pub const NAMED: &'static [(Protocol, &'static str)] = &[
+        (Protocol::HOPOPT, "HOPOPT"),
+        (Protocol::ICMP, "ICMP"),
+        (Protocol::IGMP, "IGMP"),
+        (Protocol::GGP, "GGP"),
+        (Protocol::IPV4, "IPv4"),
+        (Protocol::ST, "ST"),
+        (Protocol::TCP, "TCP"),
+        (Protocol::CBT, "CBT"),
+        (Protocol::EGP, "EGP"),
+        (Protocol::IGP, "IGP"),
+        (Protocol::BBN_RCC_MON, "BBN-RCC-MON"),
+        (Protocol::NVP_II, "NVP-II"),
+        (Protocol::PUP, "PUP"),
+        (Protocol::ARGUS, "ARGUS"),
+        (Protocol::EMCON, "EMCON"),
+        (Protocol::XNET, "XNET"),
+        (Protocol::CHAOS, "CHAOS"),
+        (Protocol::UDP, "UDP"),
+        (Protocol::MUX, "MUX"),
+        (Protocol::DCN_MEAS, "DCN-MEAS"),
+        (Protocol::HMP, "HMP"),
+        (Protocol::PRM, "PRM"),
+        (Protocol::XNS_IDP, "XNS-IDP"),
+        (Protocol::TRUNK_1, "TRUNK-1"),
+        (Protocol::TRUNK_2, "TRUNK-2"),
+        (Protocol::LEAF_1, "LEAF-1"),
+        (Protocol::LEAF_2, "LEAF-2"),
+        (Protocol::RDP, "RDP"),
+        (Protocol::IRTP, "IRTP"),
+        (Protocol::ISO_TP4, "ISO-TP4"),
+        (Protocol::NETBLT, "NETBLT"),
+        (Protocol::MFE_NSP, "MFE-NSP"),
+        (Protocol::MERIT_INP, "MERIT-INP"),
+        (Protocol::DCCP, "DCCP"),
+        (Protocol::THREE_PC, "3PC"),
+        (Protocol::IDPR, "IDPR"),
+        (Protocol::XTP, "XTP"),
+        (Protocol::DDP, "DDP"),
+        (Protocol::IDPR_CMTP, "IDPR-CMTP"),
+        (Protocol::TP_PLUS_PLUS, "TP++"),
+        (Protocol::IL, "IL"),
+        (Protocol::IPV6, "IPv6"),
+        (Protocol::SDRP, "SDRP"),
+        (Protocol::IPV6_ROUTE, "IPv6-Route"),
+        (Protocol::IPV6_FRAG, "IPv6-Frag"),
+        (Protocol::IDRP, "IDRP"),
+        (Protocol::RSVP, "RSVP"),
+        (Protocol::GRE, "GRE"),
+        (Protocol::DSR, "DSR"),
+        (Protocol::BNA, "BNA"),
+        (Protocol::ESP, "ESP"),
+        (Protocol::AH, "AH"),
+        (Protocol::I_NLSP, "I-NLSP"),
+        (Protocol::SWIPE, "SWIPE"),
+        (Protocol::NARP, "NARP"),
+        (Protocol::MIN_IPV4, "Min-IPv4"),
+        (Protocol::TLSP, "TLSP"),
+        (Protocol::SKIP, "SKIP"),
+        (Protocol::ICMPV6, "ICMPv6"),
+        (Protocol::IPV6_NONXT, "IPv6-NoNxt"),
+        (Protocol::IPV6_OPTS, "IPv6-Opts"),
+        (Protocol::CFTP, "CFTP"),
+        (Protocol::SAT_EXPAK, "SAT-EXPAK"),
+        (Protocol::KRYPTOLAN, "KRYPTOLAN"),
+        (Protocol::RVD, "RVD"),
+        (Protocol::IPPC, "IPPC"),
+        (Protocol::SAT_MON, "SAT-MON"),
+        (Protocol::VISA, "VISA"),
+        (Protocol::IPCV, "IPCV"),
+        (Protocol::CPNX, "CPNX"),
+        (Protocol::CPHB, "CPHB"),
+        (Protocol::WSN, "WSN"),
+        (Protocol::PVP, "PVP"),
+        (Protocol::BR_SAT_MON, "BR-SAT-MON"),
+        (Protocol::SUN_ND, "SUN-ND"),
+        (Protocol::WB_MON, "WB-MON"),
+        (Protocol::WB_EXPAK, "WB-EXPAK"),
+        (Protocol::ISO_IP, "ISO-IP"),
+        (Protocol::VMTP, "VMTP"),
+        (Protocol::SECURE_VMTP, "SECURE-VMTP"),
+        (Protocol::VINES, "VINES"),
+        (Protocol::IPTM, "IPTM"),
+        (Protocol::NSFNET_IGP, "NSFNET-IGP"),
+        (Protocol::DGP, "DGP"),
+        (Protocol::TCF, "TCF"),
+        (Protocol::EIGRP, "EIGRP"),
+        (Protocol::OSPF, "OSPFIGP"),
+        (Protocol::SPRITE_RPC, "Sprite-RPC"),
+        (Protocol::LARP, "LARP"),
+        (Protocol::MTP, "MTP"),
+        (Protocol::AX25, "AX.25"),
+        (Protocol::IPIP, "IPIP"),
+        (Protocol::MICP, "MICP"),
+        (Protocol::SCC_SP, "SCC-SP"),
+        (Protocol::ETHERIP, "ETHERIP"),
+        (Protocol::ENCAP, "ENCAP"),
+        (Protocol::GMTP, "GMTP"),
+        (Protocol::IFMP, "IFMP"),
+        (Protocol::PNNI, "PNNI"),
+        (Protocol::PIM, "PIM"),
+        (Protocol::ARIS, "ARIS"),
+        (Protocol::SCPS, "SCPS"),
+        (Protocol::QNX, "QNX"),
+        (Protocol::AN, "A/N"),
+        (Protocol::IPCOMP, "IPComp"),
+        (Protocol::SNP, "SNP"),
+        (Protocol::COMPAQ_PEER, "Compaq-Peer"),
+        (Protocol::IPX_IN_IP, "IPX-in-IP"),
+        (Protocol::VRRP, "VRRP"),
+        (Protocol::PGM, "PGM"),
+        (Protocol::L2TP, "L2TP"),
+        (Protocol::DDX, "DDX"),
+        (Protocol::IATP, "IATP"),
+        (Protocol::STP, "STP"),
+        (Protocol::SRP, "SRP"),
+        (Protocol::UTI, "UTI"),
+        (Protocol::SMP, "SMP"),
+        (Protocol::SM, "SM"),
+        (Protocol::PTP, "PTP"),
+        (Protocol::ISIS_OVER_IPV4, "ISIS-over-IPv4"),
+        (Protocol::FIRE, "FIRE"),
+        (Protocol::CRTP, "CRTP"),
+        (Protocol::CRUDP, "CRUDP"),
+        (Protocol::SSCOPMCE, "SSCOPMCE"),
+        (Protocol::IPLT, "IPLT"),
+        (Protocol::SPS, "SPS"),
+        (Protocol::PIPE, "PIPE"),
+        (Protocol::SCTP, "SCTP"),
+        (Protocol::FC, "FC"),
+        (Protocol::RSVP_E2E_IGNORE, "RSVP-E2E-IGNORE"),
+        (Protocol::MOBILITY_HEADER, "Mobility-Header"),
+        (Protocol::UDPLITE, "UDPLite"),
+        (Protocol::MPLS_IN_IP, "MPLS-in-IP"),
+        (Protocol::MANET, "manet"),
+        (Protocol::HIP, "HIP"),
+        (Protocol::SHIM6, "Shim6"),
+        (Protocol::WESP, "WESP"),
+        (Protocol::ROHC, "ROHC"),
+        (Protocol::ETHERNET, "Ethernet"),
+        (Protocol::AGGFRAG, "AGGFRAG"),
+        (Protocol::NSH, "NSH"),
+        (Protocol::RESERVED, "Reserved"),
+    ];
+
     /// Returns the numeric representation of this protocol.
     #[inline]
     pub fn value(&self) -> u8 {
         self.0
     }
 
+    /// Returns the keyword of this protocol if it has a constant here.
+    pub fn name(&self) -> Option<&'static str> {
+        Protocol::NAMED.iter().find(|&&(protocol, _)| protocol == *self).map(|&(_, name)| name)
+    }
+
     pub fn is_unassigned(&self) -> bool {
-        self.0 >= 143 && self.0 <= 252
+        self.0 >= 146 && self.0 <= 252
     }
 
     pub fn is_experimental(&self) -> bool {
         self.0 >= 253 && self.0 <= 254
     }
+
+    /// Returns true for the transport protocols, the ones carrying port numbers: TCP, UDP,
+    /// DCCP, SCTP and UDP-Lite.
+    pub fn is_transport(&self) -> bool {
+        matches!(
+            *self,
+            Protocol::TCP | Protocol::UDP | Protocol::DCCP | Protocol::SCTP | Protocol::UDPLITE
+        )
+    }
+}
+
+impl fmt::Display for Protocol {
+    /// Writes the keyword of the protocols that have a constant here, and the number otherwise.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = ProtocolParseError;
+
+    /// Parses the keywords written by `Display`, ignoring case, or a decimal number.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(&(protocol, _)) =
+            Protocol::NAMED.iter().find(|&&(_, name)| name.eq_ignore_ascii_case(s))
+        {
+            return Ok(protocol);
+        }
+        s.parse().map(Protocol).map_err(|_| ProtocolParseError(s.to_owned()))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ProtocolParseError(String);
+
+impl fmt::Display for ProtocolParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid IP protocol string: {}", self.0)
+    }
+}
+
+impl Error for ProtocolParseError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!("TCP", Protocol::TCP.to_string());
+        assert_eq!("ICMPv6", Protocol::ICMPV6.to_string());
+        assert_eq!("OSPFIGP", Protocol::OSPF.to_string());
+        assert_eq!("61", Protocol(61).to_string());
+        assert_eq!("200", Protocol(200).to_string());
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Ok(Protocol::UDP), "UDP".parse());
+        assert_eq!(Ok(Protocol::IPV6_FRAG), "ipv6-frag".parse());
+        assert_eq!(Ok(Protocol(61)), "61".parse());
+        assert!("256".parse::<Protocol>().is_err());
+        assert!("QUIC".parse::<Protocol>().is_err());
+        for &(protocol, name) in Protocol::NAMED {
+            assert_eq!(Ok(protocol), name.parse());
+        }
+    }
+
+    #[test]
+    fn registry_complete() {
+        let unnamed = [61, 63, 68, 99, 114, 253, 254];
+        for value in 0..=255u8 {
+            let protocol = Protocol(value);
+            let expect_name = !protocol.is_unassigned() && !unnamed.contains(&value);
+            assert_eq!(expect_name, protocol.name().is_some(), "{}", value);
+        }
+        assert_eq!(142, Protocol::NAMED.len());
+    }
+
+    #[test]
+    fn classes() {
+        assert!(Protocol::SCTP.is_transport());
+        assert!(!Protocol::ICMP.is_transport());
+        assert!(Protocol(146).is_unassigned());
+        assert!(!Protocol::NSH.is_unassigned());
+        assert!(Protocol(253).is_experimental());
+    }
 }
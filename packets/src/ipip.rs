@@ -0,0 +1,166 @@
+//! IP in IP encapsulation, RFC 2003, and IPv6 in IPv4 tunneling (6in4), RFC 4213.
+//!
+//! Both carry a complete inner IP packet as the payload of an outer IPv4 header, with the outer
+//! protocol field set to `Protocol::IPV4` or `Protocol::IPV6`.
+
+use ip::Protocol;
+use ipv4::{Flags, Ipv4Packet, MutIpv4Packet};
+use ipv6::Ipv6Packet;
+use std::net::Ipv4Addr;
+
+/// The TTL of the outer header written by `write_ipip` and `write_6in4`.
+pub const DEFAULT_TTL: u8 = 64;
+
+/// The inner packet of an IP in IP or 6in4 tunnel.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum InnerPacket<'a> {
+    Ipv4(Ipv4Packet<'a>),
+    Ipv6(Ipv6Packet<'a>),
+}
+
+/// Returns the packet tunneled in `outer`, or `None` if `outer` does not carry an IPv4 or IPv6
+/// packet, or the inner packet is too short or of the wrong IP version.
+pub fn inner_packet<'a>(outer: Ipv4Packet<'a>) -> Option<InnerPacket<'a>> {
+    let payload = &outer.data()[outer.header_len()..];
+    match outer.protocol() {
+        Protocol::IPV4 => Ipv4Packet::new(payload)
+            .filter(|inner| inner.version() == 4)
+            .map(InnerPacket::Ipv4),
+        Protocol::IPV6 => Ipv6Packet::new(payload)
+            .filter(|inner| inner.version() == 6)
+            .map(InnerPacket::Ipv6),
+        _ => None,
+    }
+}
+
+/// Encodes an outer IPv4 header followed by the whole backing slice of `inner` into the
+/// beginning of `buffer`. The type of service and don't fragment flag are copied from the inner
+/// header, as suggested by RFC 2003. Returns the number of bytes written, or `None` if the
+/// packet does not fit in `buffer` or is too long for an IPv4 packet.
+pub fn write_ipip(
+    buffer: &mut [u8],
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    inner: Ipv4Packet,
+) -> Option<usize> {
+    let tos = (inner.dscp() << 2) | inner.ecn();
+    let flags = inner.flags() & Flags::DF;
+    write_outer(buffer, source, destination, Protocol::IPV4, tos, flags, inner.data())
+}
+
+/// Encodes an outer IPv4 header followed by the whole backing slice of `inner` into the
+/// beginning of `buffer`. The type of service is copied from the inner traffic class. Returns
+/// the number of bytes written, or `None` if the packet does not fit in `buffer` or is too long
+/// for an IPv4 packet.
+pub fn write_6in4(
+    buffer: &mut [u8],
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    inner: Ipv6Packet,
+) -> Option<usize> {
+    let tos = inner.traffic_class();
+    write_outer(buffer, source, destination, Protocol::IPV6, tos, Flags::empty(), inner.data())
+}
+
+fn write_outer(
+    buffer: &mut [u8],
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    protocol: Protocol,
+    tos: u8,
+    flags: Flags,
+    inner: &[u8],
+) -> Option<usize> {
+    let len = 20 + inner.len();
+    if len > usize::from(u16::MAX) {
+        return None;
+    }
+    let packet = buffer.get_mut(..len)?;
+    for byte in &mut packet[..20] {
+        *byte = 0;
+    }
+    packet[20..].copy_from_slice(inner);
+    let mut outer = MutIpv4Packet::new(packet)?;
+    outer.set_version(4);
+    outer.set_header_length(5);
+    outer.set_dscp(tos >> 2);
+    outer.set_ecn(tos & 0x03);
+    outer.set_total_length(len as u16);
+    outer.set_flags(flags);
+    outer.set_ttl(DEFAULT_TTL);
+    outer.set_protocol(protocol);
+    outer.set_source(source);
+    outer.set_destination(destination);
+    outer.update_checksum();
+    Some(len)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv6::MutIpv6Packet;
+
+    #[test]
+    fn ipip_round_trip() {
+        let mut inner = [0; 24];
+        {
+            let mut testee = MutIpv4Packet::new(&mut inner).unwrap();
+            testee.set_version(4);
+            testee.set_header_length(5);
+            testee.set_dscp(46);
+            testee.set_flags(Flags::DF);
+            testee.set_ttl(1);
+        }
+        let mut buffer = [0; 64];
+        let source = Ipv4Addr::new(192, 0, 2, 1);
+        let destination = Ipv4Addr::new(198, 51, 100, 1);
+        let inner = Ipv4Packet::new(&inner).unwrap();
+        let len = write_ipip(&mut buffer, source, destination, inner).unwrap();
+        assert_eq!(44, len);
+
+        let outer = Ipv4Packet::new(&buffer[..len]).unwrap();
+        assert_eq!(Protocol::IPV4, outer.protocol());
+        assert_eq!(44, outer.total_length());
+        assert_eq!(46, outer.dscp());
+        assert_eq!(Flags::DF, outer.flags());
+        assert_eq!(DEFAULT_TTL, outer.ttl());
+        assert_eq!(destination, outer.destination());
+        assert!(outer.is_checksum_valid());
+        match inner_packet(outer) {
+            Some(InnerPacket::Ipv4(packet)) => assert_eq!(1, packet.ttl()),
+            other => panic!("Unexpected inner packet {:?}", other),
+        }
+    }
+
+    #[test]
+    fn six_in_four_round_trip() {
+        let mut inner = [0; 40];
+        {
+            let mut testee = MutIpv6Packet::new(&mut inner).unwrap();
+            testee.set_version(6);
+            testee.set_traffic_class(0xb9);
+            testee.set_hop_limit(5);
+        }
+        let mut buffer = [0; 60];
+        let unspecified = Ipv4Addr::new(0, 0, 0, 0);
+        let inner = Ipv6Packet::new(&inner).unwrap();
+        let len = write_6in4(&mut buffer, unspecified, unspecified, inner).unwrap();
+        assert_eq!(60, len);
+        assert_eq!(None, write_6in4(&mut buffer[..59], unspecified, unspecified, inner));
+
+        let outer = Ipv4Packet::new(&buffer).unwrap();
+        assert_eq!(Protocol::IPV6, outer.protocol());
+        assert_eq!((0x2e, 0x01), (outer.dscp(), outer.ecn()));
+        match inner_packet(outer) {
+            Some(InnerPacket::Ipv6(packet)) => assert_eq!(5, packet.hop_limit()),
+            other => panic!("Unexpected inner packet {:?}", other),
+        }
+
+        // The version of the inner packet must match the protocol
+        buffer[9] = Protocol::IPV4.value();
+        assert_eq!(None, inner_packet(Ipv4Packet::new(&buffer).unwrap()));
+        buffer[9] = Protocol::UDP.value();
+        assert_eq!(None, inner_packet(Ipv4Packet::new(&buffer).unwrap()));
+    }
+}
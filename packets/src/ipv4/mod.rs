@@ -0,0 +1,993 @@
+use alloc::vec::Vec;
+use std::cmp;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use types::*;
+use builder::{self, Payload};
+use checksum::{self, Checksum};
+use error::{self, ParseError};
+use ethernet::EtherType;
+use ip::Protocol;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+mod options;
+pub use self::options::*;
+
+packet!(
+    Ipv4Packet,
+    MutIpv4Packet,
+    Ipv4PacketBuf,
+    20,
+    |data| usize::from(data[0] & 0x0f) * 4,
+    len |data| usize::from(read_offset!(data, 2, u16, from_be)),
+    set_len |data, len| {
+        len <= usize::from(u16::MAX) && {
+            let mut packet = MutIpv4Packet(data);
+            packet.set_total_length(len as u16);
+            packet.update_checksum();
+            true
+        }
+    },
+    init |data| {
+        data[0] = 0x45;
+        write_offset!(data, 2, data.len() as u16, u16, to_be);
+        MutIpv4Packet(data).update_checksum();
+    }
+);
+
+fields!(Ipv4Packet,
+    VERSION: 0, 4;
+    HEADER_LENGTH: 4, 4;
+    DSCP: 8, 6;
+    ECN: 14, 2;
+    TOTAL_LENGTH: 16, 16;
+    IDENTIFICATION: 32, 16;
+    FLAGS: 48, 3;
+    FRAGMENT_OFFSET: 51, 13;
+    TTL: 64, 8;
+    PROTOCOL: 72, 8;
+    HEADER_CHECKSUM: 80, 16;
+    SOURCE: 96, 32;
+    DESTINATION: 128, 32;
+);
+
+getters!(Ipv4Packet
+    pub fn version(&self) -> u4 {
+        read_field!(self.0, fields::VERSION, u8)
+    }
+
+    pub fn header_length(&self) -> u4 {
+        read_field!(self.0, fields::HEADER_LENGTH, u8)
+    }
+
+    pub fn dscp(&self) -> u6 {
+        read_field!(self.0, fields::DSCP, u8)
+    }
+
+    pub fn ecn(&self) -> u2 {
+        read_field!(self.0, fields::ECN, u8)
+    }
+
+    pub fn total_length(&self) -> u16 {
+        read_field!(self.0, fields::TOTAL_LENGTH, u16)
+    }
+
+    pub fn identification(&self) -> u16 {
+        read_field!(self.0, fields::IDENTIFICATION, u16)
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_field!(self.0, fields::FLAGS, u8))
+    }
+
+    pub fn dont_fragment(&self) -> bool {
+        self.flags().contains(Flags::DF)
+    }
+
+    pub fn more_fragments(&self) -> bool {
+        self.flags().contains(Flags::MF)
+    }
+
+    pub fn fragment_offset(&self) -> u13 {
+        read_field!(self.0, fields::FRAGMENT_OFFSET, u16)
+    }
+
+    pub fn ttl(&self) -> u8 {
+        read_field!(self.0, fields::TTL, u8)
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        Protocol(read_field!(self.0, fields::PROTOCOL, u8))
+    }
+
+    pub fn header_checksum(&self) -> u16 {
+        read_field!(self.0, fields::HEADER_CHECKSUM, u16)
+    }
+
+    pub fn source(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_field!(self.0, fields::SOURCE, u32))
+    }
+
+    pub fn destination(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_field!(self.0, fields::DESTINATION, u32))
+    }
+
+    /// Computes the checksum of the header, ignoring the current value of the header checksum
+    /// field.
+    pub fn calculate_checksum(&self) -> u16 {
+        let header = self.header();
+        let mut checksum = Checksum::new();
+        checksum.add_bytes(&header[..10]);
+        checksum.add_bytes(&header[12..]);
+        checksum.finish()
+    }
+
+    /// Returns true if the header checksum field holds the checksum of the header.
+    pub fn is_checksum_valid(&self) -> bool {
+        checksum::internet_checksum(self.header()) == 0
+    }
+
+    /// Returns an iterator over the options in the header.
+    pub fn options(&self) -> Ipv4Options<'a> {
+        Ipv4Options::new(&self.0[Self::MIN_LEN..self.header_len()])
+    }
+
+    /// Returns the raw options part of the header. Everything between the fixed header and the
+    /// offset given by `header_length`.
+    pub fn options_raw(&self) -> &[u8] {
+        &self.0[Self::MIN_LEN..self.header_len()]
+    }
+);
+
+impl<'a> Ipv4Packet<'a> {
+    /// Returns the bytes in the backing slice after the end of the packet given by the total
+    /// length field, such as the padding of a short Ethernet frame. Empty if the total length
+    /// covers the whole slice or more.
+    pub fn padding(&self) -> &'a [u8] {
+        &self.0[Ipv4Packet::packet_end(self.0)..]
+    }
+
+    /// Returns the packet narrowed to end where the total length field says, so that `data`
+    /// holds only the packet, along with the bytes after it as given by `padding`. A total
+    /// length shorter than the header is treated as covering just the header, and one longer
+    /// than the backing slice leaves the slice as it is.
+    ///
+    /// ```rust
+    /// use rips_packets::ethernet::EthernetPacket;
+    /// use rips_packets::ipv4::Ipv4Packet;
+    ///
+    /// // A 28 byte IPv4 packet in an Ethernet frame padded to the minimum of 60 bytes.
+    /// let mut frame = [0; 60];
+    /// frame[14] = 0x45;
+    /// frame[17] = 28;
+    /// let ethernet = EthernetPacket::new(&frame).unwrap();
+    /// let (ip, padding) = Ipv4Packet::new(ethernet.payload()).unwrap().split_padding();
+    /// assert_eq!(28, ip.data().len());
+    /// assert_eq!(18, padding.len());
+    /// ```
+    pub fn split_padding(&self) -> (Ipv4Packet<'a>, &'a [u8]) {
+        let (packet, padding) = self.0.split_at(Ipv4Packet::packet_end(self.0));
+        (Ipv4Packet(packet), padding)
+    }
+
+    /// Returns the packet narrowed to end where the total length field says. See
+    /// `split_padding`.
+    pub fn trimmed(&self) -> Ipv4Packet<'a> {
+        self.split_padding().0
+    }
+
+    /// Creates a new immutable packet like `new`, but also validates it. The version must be 4,
+    /// the header length must cover the fixed header, the total length must cover the header
+    /// and both must fit in `data`. Lastly the header checksum must be valid.
+    pub fn parse(data: &'a [u8]) -> Result<Ipv4Packet<'a>, ParseError> {
+        error::check_len(data, Self::MIN_LEN)?;
+        let packet = Ipv4Packet(data);
+        if packet.version() != 4 {
+            return Err(ParseError::InvalidVersion(packet.version()));
+        }
+        let header_len = usize::from(packet.header_length()) * 4;
+        if header_len < Self::MIN_LEN || header_len > data.len() {
+            return Err(ParseError::InvalidHeaderLength(header_len));
+        }
+        let total_length = usize::from(packet.total_length());
+        if total_length < header_len || total_length > data.len() {
+            return Err(ParseError::InvalidLength(total_length));
+        }
+        if !packet.is_checksum_valid() {
+            return Err(ParseError::InvalidChecksum {
+                expected: packet.calculate_checksum(),
+                actual: packet.header_checksum(),
+            });
+        }
+        Ok(packet)
+    }
+}
+
+setters!(MutIpv4Packet
+    pub fn set_version(&mut self, version: u4) {
+        write_field!(self.0, fields::VERSION, version, u8);
+    }
+
+    pub fn set_header_length(&mut self, header_length: u4) {
+        write_field!(self.0, fields::HEADER_LENGTH, header_length, u8);
+    }
+
+    pub fn set_dscp(&mut self, dscp: u6) {
+        write_field!(self.0, fields::DSCP, dscp, u8);
+    }
+
+    pub fn set_ecn(&mut self, ecn: u2) {
+        write_field!(self.0, fields::ECN, ecn, u8);
+    }
+
+    pub fn set_total_length(&mut self, total_length: u16) {
+        write_field!(self.0, fields::TOTAL_LENGTH, total_length, u16);
+    }
+
+    pub fn set_identification(&mut self, identification: u16) {
+        write_field!(self.0, fields::IDENTIFICATION, identification, u16);
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_field!(self.0, fields::FLAGS, flags.bits(), u8);
+    }
+
+    pub fn set_fragment_offset(&mut self, fragment_offset: u13) {
+        write_field!(self.0, fields::FRAGMENT_OFFSET, fragment_offset, u16);
+    }
+
+    pub fn set_ttl(&mut self, ttl: u8) {
+        write_field!(self.0, fields::TTL, ttl, u8);
+    }
+
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        write_field!(self.0, fields::PROTOCOL, protocol.value(), u8);
+    }
+
+    pub fn set_header_checksum(&mut self, checksum: u16) {
+        write_field!(self.0, fields::HEADER_CHECKSUM, checksum, u16);
+    }
+
+    pub fn set_source(&mut self, source: Ipv4Addr) {
+        write_field!(self.0, fields::SOURCE, u32::from(source), u32);
+    }
+
+    pub fn set_destination(&mut self, destination: Ipv4Addr) {
+        write_field!(self.0, fields::DESTINATION, u32::from(destination), u32);
+    }
+);
+
+impl<'a> MutIpv4Packet<'a> {
+    /// Computes the checksum of the header and writes it to the header checksum field. Call
+    /// this after the last change to the header.
+    pub fn update_checksum(&mut self) {
+        let checksum = self.as_immutable().calculate_checksum();
+        self.set_header_checksum(checksum);
+    }
+
+    /// Adjusts the header checksum for a change of the header bytes `old` to `new`, without
+    /// summing the rest of the header again. The bytes must start at an even offset in the
+    /// header, which holds for every field wider than a byte. Call it before or after writing
+    /// the new value, both work. See `checksum::update`.
+    ///
+    /// For a single byte field, pass it together with its neighbour in the same 16 bit word,
+    /// e.g. the TTL and protocol.
+    pub fn update_checksum_for(&mut self, old: &[u8], new: &[u8]) {
+        let checksum = checksum::update(self.as_immutable().header_checksum(), old, new);
+        self.set_header_checksum(checksum);
+    }
+
+    /// Decrements the TTL as a router does before forwarding, and adjusts the header checksum
+    /// for the change as in RFC 1624. Returns true if the TTL reached zero, or already was zero
+    /// in which case it is left unchanged. Such a packet must be dropped rather than forwarded,
+    /// normally answered with an ICMP Time Exceeded message.
+    pub fn decrement_ttl(&mut self) -> bool {
+        let (ttl, protocol) = (self.as_immutable().ttl(), self.as_immutable().protocol().value());
+        if ttl == 0 {
+            return true;
+        }
+        self.set_ttl(ttl - 1);
+        self.update_checksum_for(&[ttl, protocol], &[ttl - 1, protocol]);
+        ttl == 1
+    }
+
+    /// Returns a mutable slice to the raw options part of the header. Everything between the
+    /// fixed header and the offset given by `header_length`.
+    pub fn options_raw(&mut self) -> &mut [u8] {
+        let header_len = self.header_len();
+        &mut self.0[Self::MIN_LEN..header_len]
+    }
+
+    /// Encodes `options`, padded to a multiple of four bytes, after the fixed header and updates
+    /// the header length to match. Returns the resulting header length, or `None` if the options
+    /// do not fit in the backing slice or in the 40 bytes available for options.
+    ///
+    /// The payload is not moved, the options overwrite whatever follows the fixed header in the
+    /// backing slice, so set the options before writing the payload. The total length and the
+    /// header checksum are left as they are, update them afterwards with `set_total_length` and
+    /// `update_checksum`.
+    pub fn set_options(&mut self, options: &[Ipv4Option]) -> Option<usize> {
+        let end = cmp::min(self.0.len(), MAX_HEADER_LEN);
+        let header_len = Self::MIN_LEN + write_options(&mut self.0[Self::MIN_LEN..end], options)?;
+        self.set_header_length((header_len / 4) as u4);
+        Some(header_len)
+    }
+}
+
+/// The largest header the header length field can describe.
+const MAX_HEADER_LEN: usize = 60;
+
+
+/// Builds an IPv4 packet without options. The version, header length, total length and header
+/// checksum are filled in when building. See the `builder` module for an example.
+#[derive(Debug, Copy, Clone)]
+pub struct Ipv4Builder<P = &'static [u8]> {
+    dscp: u6,
+    ecn: u2,
+    identification: u16,
+    flags: Flags,
+    ttl: u8,
+    protocol: Option<Protocol>,
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    payload: P,
+}
+
+impl Ipv4Builder {
+    /// Creates a builder for a packet with a TTL of 64, all other fields zero and an empty
+    /// payload.
+    pub fn new() -> Ipv4Builder {
+        Ipv4Builder::default()
+    }
+}
+
+impl Default for Ipv4Builder {
+    fn default() -> Ipv4Builder {
+        Ipv4Builder {
+            dscp: 0,
+            ecn: 0,
+            identification: 0,
+            flags: Flags::empty(),
+            ttl: 64,
+            protocol: None,
+            source: Ipv4Addr::UNSPECIFIED,
+            destination: Ipv4Addr::UNSPECIFIED,
+            payload: &[],
+        }
+    }
+}
+
+impl<P: Payload> Ipv4Builder<P> {
+    pub fn dscp(mut self, dscp: u6) -> Self {
+        self.dscp = dscp;
+        self
+    }
+
+    pub fn ecn(mut self, ecn: u2) -> Self {
+        self.ecn = ecn;
+        self
+    }
+
+    pub fn identification(mut self, identification: u16) -> Self {
+        self.identification = identification;
+        self
+    }
+
+    pub fn flags(mut self, flags: Flags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the protocol. Only needed when the payload is not a builder that knows its own
+    /// protocol.
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub fn source(mut self, source: Ipv4Addr) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn destination(mut self, destination: Ipv4Addr) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    pub fn payload<Q: Payload>(self, payload: Q) -> Ipv4Builder<Q> {
+        Ipv4Builder {
+            dscp: self.dscp,
+            ecn: self.ecn,
+            identification: self.identification,
+            flags: self.flags,
+            ttl: self.ttl,
+            protocol: self.protocol,
+            source: self.source,
+            destination: self.destination,
+            payload,
+        }
+    }
+
+    /// Writes the packet to the start of `buffer`. Returns the length of the packet, or `None`
+    /// if it does not fit in `buffer` or in the total length field, the payload can't be
+    /// encoded or the protocol is not known.
+    pub fn build_into(&self, buffer: &mut [u8]) -> Option<usize> {
+        self.write(buffer, None)
+    }
+
+    /// Writes the packet to a newly allocated buffer. See `build_into`.
+    pub fn build(&self) -> Option<Vec<u8>> {
+        builder::build(self)
+    }
+}
+
+impl<P: Payload> Payload for Ipv4Builder<P> {
+    fn encoded_len(&self) -> usize {
+        Ipv4Packet::MIN_LEN + self.payload.encoded_len()
+    }
+
+    fn write(&self, buffer: &mut [u8], _addresses: Option<(IpAddr, IpAddr)>) -> Option<usize> {
+        let protocol = self.protocol.or_else(|| self.payload.protocol())?;
+        let len = self.encoded_len();
+        if len > usize::from(u16::MAX) {
+            return None;
+        }
+        let buffer = buffer.get_mut(..len)?;
+        let addresses = (IpAddr::V4(self.source), IpAddr::V4(self.destination));
+        self.payload.write(&mut buffer[Ipv4Packet::MIN_LEN..], Some(addresses))?;
+        for byte in &mut buffer[..Ipv4Packet::MIN_LEN] {
+            *byte = 0;
+        }
+        let mut packet = MutIpv4Packet::new(buffer)?;
+        packet.set_version(4);
+        packet.set_header_length(5);
+        packet.set_dscp(self.dscp);
+        packet.set_ecn(self.ecn);
+        packet.set_total_length(len as u16);
+        packet.set_identification(self.identification);
+        packet.set_flags(self.flags);
+        packet.set_ttl(self.ttl);
+        packet.set_protocol(protocol);
+        packet.set_source(self.source);
+        packet.set_destination(self.destination);
+        packet.update_checksum();
+        Some(len)
+    }
+
+    fn ether_type(&self) -> Option<EtherType> {
+        Some(EtherType::IPV4)
+    }
+
+    fn protocol(&self) -> Option<Protocol> {
+        Some(Protocol::IPV4)
+    }
+}
+
+
+/// The fields of an IPv4 header as a plain value, one field per getter on `Ipv4Packet`. Options
+/// are not included. With the `serde` feature enabled it implements `Serialize` and
+/// `Deserialize`, for keeping headers in configuration files and test fixtures.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ipv4Header {
+    pub version: u4,
+    pub header_length: u4,
+    pub dscp: u6,
+    pub ecn: u2,
+    pub total_length: u16,
+    pub identification: u16,
+    pub flags: Flags,
+    pub fragment_offset: u13,
+    pub ttl: u8,
+    pub protocol: Protocol,
+    pub header_checksum: u16,
+    pub source: Ipv4Addr,
+    pub destination: Ipv4Addr,
+}
+
+impl<'a> Ipv4Packet<'a> {
+    /// Reads all fields of the fixed header into an `Ipv4Header`.
+    pub fn to_header(&self) -> Ipv4Header {
+        Ipv4Header {
+            version: self.version(),
+            header_length: self.header_length(),
+            dscp: self.dscp(),
+            ecn: self.ecn(),
+            total_length: self.total_length(),
+            identification: self.identification(),
+            flags: self.flags(),
+            fragment_offset: self.fragment_offset(),
+            ttl: self.ttl(),
+            protocol: self.protocol(),
+            header_checksum: self.header_checksum(),
+            source: self.source(),
+            destination: self.destination(),
+        }
+    }
+}
+
+impl<'a> MutIpv4Packet<'a> {
+    /// Writes all fields of `header` to the fixed header, as they are. Lengths and the checksum
+    /// are not recomputed and the options are left untouched.
+    pub fn set_header(&mut self, header: &Ipv4Header) {
+        self.set_version(header.version);
+        self.set_header_length(header.header_length);
+        self.set_dscp(header.dscp);
+        self.set_ecn(header.ecn);
+        self.set_total_length(header.total_length);
+        self.set_identification(header.identification);
+        self.set_flags(header.flags);
+        self.set_fragment_offset(header.fragment_offset);
+        self.set_ttl(header.ttl);
+        self.set_protocol(header.protocol);
+        self.set_header_checksum(header.header_checksum);
+        self.set_source(header.source);
+        self.set_destination(header.destination);
+    }
+}
+
+
+bitflags! {
+    /// Bitmasks for the three bit flags field in IPv4
+    pub struct Flags: u3 {
+        /// A bitmask with a one in the "Reserved" position.
+        const RESERVED = 0b100;
+        /// A bitmask with a one in the "Don't fragment" position.
+        const DF = 0b010;
+        /// A bitmask with a one in the "More fragments" position.
+        const MF = 0b001;
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Flags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bits().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Flags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = u8::deserialize(deserializer)?;
+        Flags::from_bits(bits)
+            .ok_or_else(|| de::Error::custom(format!("invalid IPv4 flags {:#05b}", bits)))
+    }
+}
+
+
+impl<'a> fmt::Display for Ipv4Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IPv4 {} -> {} proto={} ttl={} len={}",
+            self.source(),
+            self.destination(),
+            self.protocol(),
+            self.ttl(),
+            self.total_length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let data = [
+            0x45, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00, 0x00, 0x40, 0x11, 0x00, 0x00, 192, 168, 1, 2,
+            10, 0, 0, 1,
+        ];
+        assert_eq!(
+            "IPv4 192.168.1.2 -> 10.0.0.1 proto=UDP ttl=64 len=120",
+            Ipv4Packet::new(&data).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn min_length() {
+        assert_eq!(Ipv4Packet::MIN_LEN, 20);
+    }
+
+    #[test]
+    fn too_short_slice() {
+        assert!(Ipv4Packet::new(&[0; 19]).is_none());
+    }
+
+    #[test]
+    fn build() {
+        let builder = Ipv4Builder::new()
+            .identification(0x1c46)
+            .flags(Flags::DF)
+            .source(Ipv4Addr::new(172, 16, 10, 99))
+            .destination(Ipv4Addr::new(172, 16, 10, 12))
+            .payload([0; 40]);
+        assert_eq!(None, builder.build());
+        let data = builder.protocol(Protocol::TCP).build().unwrap();
+        assert_eq!(
+            [
+                0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0xb1, 0xe6, 0xac,
+                0x10, 0x0a, 0x63, 0xac, 0x10, 0x0a, 0x0c,
+            ],
+            data[..20]
+        );
+        assert_eq!(60, data.len());
+
+        let inner = Ipv4Builder::new().protocol(Protocol::UDP);
+        let outer = Ipv4Builder::new().payload(inner).build().unwrap();
+        let testee = Ipv4Packet::new(&outer).unwrap();
+        assert_eq!(Protocol::IPV4, testee.protocol());
+        assert_eq!(40, testee.total_length());
+        assert!(testee.is_checksum_valid());
+
+        let large = Ipv4Builder::new().protocol(Protocol::UDP).payload(vec![0; 0x10000 - 20]);
+        assert_eq!(None, large.build());
+    }
+
+    #[test]
+    fn parse() {
+        let mut data = [
+            0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0xb1, 0xe6, 0xac, 0x10,
+            0x0a, 0x63, 0xac, 0x10, 0x0a, 0x0c,
+        ].to_vec();
+        assert_eq!(Err(ParseError::InvalidLength(60)), Ipv4Packet::parse(&data));
+        data.resize(60, 0);
+        assert!(Ipv4Packet::parse(&data).is_ok());
+        assert_eq!(
+            Err(ParseError::TooShort { min_len: 20, len: 19 }),
+            Ipv4Packet::parse(&data[..19])
+        );
+
+        let mut invalid = data.clone();
+        invalid[0] = 0x65;
+        assert_eq!(Err(ParseError::InvalidVersion(6)), Ipv4Packet::parse(&invalid));
+        invalid[0] = 0x44;
+        assert_eq!(Err(ParseError::InvalidHeaderLength(16)), Ipv4Packet::parse(&invalid));
+        invalid[0] = 0x4f;
+        assert!(Ipv4Packet::parse(&invalid[..59]).is_err());
+        let mut invalid = data.clone();
+        invalid[8] = 0x3f;
+        assert_eq!(
+            Err(ParseError::InvalidChecksum { expected: 0xb2e6, actual: 0xb1e6 }),
+            Ipv4Packet::parse(&invalid)
+        );
+    }
+
+    #[test]
+    fn owned_buffer() {
+        let mut testee = Ipv4PacketBuf::new_with_payload_len(8);
+        assert_eq!(4, testee.packet().version());
+        assert_eq!(5, testee.packet().header_length());
+        assert_eq!(28, testee.packet().total_length());
+        assert_eq!(&[0; 8], testee.packet().payload());
+        testee.packet_mut().set_ttl(64);
+        let data = testee.into_inner();
+        assert_eq!(28, data.len());
+        assert_eq!(64, Ipv4Packet::new(&data).unwrap().ttl());
+        assert!(Ipv4PacketBuf::new(vec![0; 19]).is_none());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 30];
+        {
+            let mut testee = MutIpv4Packet::new(&mut data).unwrap();
+            testee.set_header_length(5);
+            assert!(testee.set_payload(&[1, 2, 3]));
+            assert!(!testee.set_payload(&[0; 11]));
+            testee.payload_mut()[2] = 4;
+        }
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(23, testee.total_length());
+        assert_eq!(&[1, 2, 4], testee.payload());
+        assert!(testee.is_checksum_valid());
+    }
+
+    #[test]
+    fn owned_set_payload() {
+        let mut testee = Ipv4PacketBuf::new_with_payload_len(8);
+        assert!(testee.set_payload(b"hello, world"));
+        assert_eq!(32, testee.packet().total_length());
+        assert_eq!(b"hello, world", testee.packet().payload());
+        assert!(testee.set_payload(b"hi"));
+        assert_eq!(22, testee.packet().total_length());
+        assert!(testee.packet().is_checksum_valid());
+        assert!(!testee.set_payload(&vec![0; 65516]));
+        assert_eq!(22, testee.into_inner().len());
+    }
+
+    #[test]
+    fn exactly_20_bytes_slice() {
+        let packet = Ipv4Packet::new(&[1; 20]).expect("Ipv4Packet to accept 20 bytes");
+        assert_eq!(packet.data(), &[1; 20]);
+        assert_eq!(packet.header(), &[1; 20]);
+        assert!(packet.payload().is_empty());
+    }
+
+    #[test]
+    fn correct_payload() {
+        let mut data = vec![2; 19];
+        data.push(3);
+        data.push(4);
+        let packet = Ipv4Packet::new(&data[..]).expect("Ipv4Packet to accept 21 bytes");
+        assert_eq!(packet.data(), &data[..]);
+        assert_eq!(packet.header(), &data[..20]);
+        assert_eq!(packet.payload(), &[4]);
+    }
+
+    #[test]
+    fn checksum() {
+        // A header captured from the wire
+        let mut data = [
+            0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0xb1, 0xe6, 0xac, 0x10,
+            0x0a, 0x63, 0xac, 0x10, 0x0a, 0x0c,
+        ];
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(0xb1e6, testee.calculate_checksum());
+        assert!(testee.is_checksum_valid());
+
+        {
+            let mut testee = MutIpv4Packet::new(&mut data).unwrap();
+            testee.set_ttl(63);
+            assert!(!testee.as_immutable().is_checksum_valid());
+            testee.update_checksum();
+        }
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert!(testee.is_checksum_valid());
+        assert_eq!(0xb2e6, testee.header_checksum());
+    }
+
+    #[test]
+    fn incremental_checksum() {
+        let mut data = [
+            0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0xb1, 0xe6, 0xac, 0x10,
+            0x0a, 0x63, 0xac, 0x10, 0x0a, 0x0c,
+        ];
+        {
+            let mut testee = MutIpv4Packet::new(&mut data).unwrap();
+            testee.set_ttl(63);
+            testee.update_checksum_for(&[64, 6], &[63, 6]);
+            let old = testee.as_immutable().source();
+            let new = Ipv4Addr::new(192, 0, 2, 1);
+            testee.set_source(new);
+            testee.update_checksum_for(&old.octets(), &new.octets());
+        }
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert!(testee.is_checksum_valid());
+        assert_eq!(testee.calculate_checksum(), testee.header_checksum());
+    }
+
+    #[test]
+    fn decrement_ttl() {
+        let mut data = [
+            0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x02, 0x06, 0xb1, 0xe6, 0xac, 0x10,
+            0x0a, 0x63, 0xac, 0x10, 0x0a, 0x0c,
+        ];
+        let mut testee = MutIpv4Packet::new(&mut data).unwrap();
+        testee.update_checksum();
+        assert!(!testee.decrement_ttl());
+        assert_eq!(1, testee.as_immutable().ttl());
+        assert!(testee.as_immutable().is_checksum_valid());
+        assert!(testee.decrement_ttl());
+        assert_eq!(0, testee.as_immutable().ttl());
+        assert!(testee.as_immutable().is_checksum_valid());
+        let checksum = testee.as_immutable().header_checksum();
+        assert!(testee.decrement_ttl());
+        assert_eq!(0, testee.as_immutable().ttl());
+        assert_eq!(checksum, testee.as_immutable().header_checksum());
+    }
+
+    #[test]
+    fn options_and_payload() {
+        let mut data = [0; 27];
+        data[0] = 0x46;
+        data[3] = 26;
+        data[20..24].copy_from_slice(&[0x94, 0x04, 0x00, 0x00]);
+        data[24..].copy_from_slice(&[7, 8, 9]);
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(24, testee.header_len());
+        assert_eq!(&[0x94, 0x04, 0x00, 0x00], testee.options_raw());
+        assert_eq!(vec![Ipv4Option::RouterAlert(0)], testee.options().collect::<Vec<_>>());
+        assert_eq!(&[7, 8], testee.payload());
+        assert_eq!(&[9], testee.padding());
+    }
+
+    #[test]
+    fn split_padding() {
+        let mut data = [0xee; 46];
+        data[..20].copy_from_slice(&[0; 20]);
+        data[0] = 0x45;
+        data[3] = 24;
+        let testee = Ipv4Packet::new(&data).unwrap();
+        let (packet, padding) = testee.split_padding();
+        assert_eq!(&data[..24], packet.data());
+        assert_eq!(&data[24..], padding);
+        assert_eq!(packet, testee.trimmed());
+        assert_eq!(testee.payload(), packet.payload());
+        assert!(packet.padding().is_empty());
+
+        // A total length below the header length keeps the header, one beyond the slice
+        // keeps the slice.
+        data[3] = 4;
+        assert_eq!(20, Ipv4Packet::new(&data).unwrap().trimmed().data().len());
+        data[3] = 100;
+        let (packet, padding) = Ipv4Packet::new(&data).unwrap().split_padding();
+        assert_eq!(46, packet.data().len());
+        assert!(padding.is_empty());
+    }
+
+    #[test]
+    fn payload_ends_at_total_length() {
+        let mut data = [0; 30];
+        data[0] = 0x45;
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert!(testee.payload().is_empty());
+        assert_eq!(&[0; 10], testee.padding());
+        data[3] = 40;
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(&[0; 10], testee.payload());
+        assert!(testee.padding().is_empty());
+        data[3] = 24;
+        assert_eq!(4, MutIpv4Packet::new(&mut data).unwrap().payload().len());
+    }
+
+    #[test]
+    fn invalid_header_length() {
+        let mut data = [0; 24];
+        data[0] = 0x42;
+        assert_eq!(20, Ipv4Packet::new(&data).unwrap().header_len());
+        data[0] = 0x4f;
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(24, testee.header_len());
+        assert!(testee.payload().is_empty());
+    }
+
+    #[test]
+    fn set_options() {
+        let mut data = [0xff; 40];
+        {
+            let mut testee = MutIpv4Packet::new(&mut data).unwrap();
+            testee.set_version(4);
+            testee.set_header_length(5);
+            testee.set_total_length(40);
+            testee.update_checksum();
+            assert_eq!(Some(24), testee.set_options(&[Ipv4Option::RouterAlert(0)]));
+        }
+        // The options overwrite the start of the payload
+        assert_eq!([0x94, 0x04, 0, 0, 0xff], data[20..25]);
+
+        let testee = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(6, testee.header_length());
+        assert_eq!(vec![Ipv4Option::RouterAlert(0)], testee.options().collect::<Vec<_>>());
+        assert_eq!(40, testee.total_length());
+        assert!(!testee.is_checksum_valid());
+
+        let mut testee = MutIpv4Packet::new(&mut data).unwrap();
+        testee.update_checksum();
+        assert!(testee.as_immutable().is_checksum_valid());
+    }
+
+    #[test]
+    fn set_options_too_long() {
+        let mut data = [0; 100];
+        let mut testee = MutIpv4Packet::new(&mut data).unwrap();
+        assert_eq!(None, testee.set_options(&[Ipv4Option::Nop; 41]));
+        assert_eq!(Some(60), testee.set_options(&[Ipv4Option::Nop; 40]));
+    }
+
+    macro_rules! ipv4_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIpv4Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ipv4_setget_test!(version, set_version, 0xf, 0, [0xf0]);
+    ipv4_setget_test!(header_length, set_header_length, 0xf, 0, [0x0f]);
+    ipv4_setget_test!(dscp, set_dscp, 0x3f, 1, [0xfc]);
+    ipv4_setget_test!(ecn, set_ecn, 0x3, 1, [0x3]);
+    ipv4_setget_test!(total_length, set_total_length, 0xffbf, 2, [0xff, 0xbf]);
+    ipv4_setget_test!(identification, set_identification, 0xffaf, 4, [0xff, 0xaf]);
+    ipv4_setget_test!(flags, set_flags, Flags::all(), 6, [0xe0]);
+    ipv4_setget_test!(
+        fragment_offset,
+        set_fragment_offset,
+        0x1faf,
+        6,
+        [0x1f, 0xaf]
+    );
+    ipv4_setget_test!(ttl, set_ttl, 0xff, 8, [0xff]);
+    ipv4_setget_test!(protocol, set_protocol, Protocol(0xff), 9, [0xff]);
+    ipv4_setget_test!(
+        header_checksum,
+        set_header_checksum,
+        0xfeff,
+        10,
+        [0xfe, 0xff]
+    );
+    ipv4_setget_test!(
+        source,
+        set_source,
+        Ipv4Addr::new(192, 168, 15, 1),
+        12,
+        [192, 168, 15, 1]
+    );
+    ipv4_setget_test!(
+        destination,
+        set_destination,
+        Ipv4Addr::new(168, 254, 99, 88),
+        16,
+        [168, 254, 99, 88]
+    );
+
+    #[test]
+    fn getters_alternating_bits() {
+        let backing_data = [0b1010_1010; 20];
+        let testee = Ipv4Packet::new(&backing_data).unwrap();
+        assert_eq!(0b1010, testee.version());
+        assert_eq!(0b1010, testee.header_length());
+        assert_eq!(0b101010, testee.dscp());
+        assert_eq!(0b10, testee.ecn());
+        assert_eq!(0b1010_1010_1010_1010, testee.total_length());
+        assert_eq!(0b1010_1010_1010_1010, testee.identification());
+        assert_eq!(Flags::RESERVED | Flags::MF, testee.flags());
+        assert!(!testee.dont_fragment());
+        assert!(testee.more_fragments());
+        assert_eq!(0b0_1010_1010_1010, testee.fragment_offset());
+    }
+
+    fn header() -> Ipv4Header {
+        Ipv4Header {
+            version: 4,
+            header_length: 5,
+            dscp: 10,
+            ecn: 1,
+            total_length: 28,
+            identification: 0x1234,
+            flags: Flags::DF,
+            fragment_offset: 0,
+            ttl: 64,
+            protocol: Protocol::UDP,
+            header_checksum: 0xabcd,
+            source: Ipv4Addr::new(10, 0, 0, 1),
+            destination: Ipv4Addr::new(10, 0, 0, 2),
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let mut buffer = [0; 20];
+        MutIpv4Packet::new(&mut buffer).unwrap().set_header(&header());
+        assert_eq!(header(), Ipv4Packet::new(&buffer).unwrap().to_header());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn header_serde() {
+        extern crate serde_json;
+
+        let json = serde_json::to_string(&header()).unwrap();
+        assert_eq!(
+            "{\"version\":4,\"header_length\":5,\"dscp\":10,\"ecn\":1,\"total_length\":28,\
+             \"identification\":4660,\"flags\":2,\"fragment_offset\":0,\"ttl\":64,\"protocol\":17,\
+             \"header_checksum\":43981,\"source\":\"10.0.0.1\",\"destination\":\"10.0.0.2\"}",
+            json
+        );
+        assert_eq!(header(), serde_json::from_str(&json).unwrap());
+        assert!(serde_json::from_str::<Flags>("8").is_err());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Ipv4PacketBuf => |packet| Ipv4Packet::parse(packet.data()).is_ok()
+    );
+}
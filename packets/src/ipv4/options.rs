@@ -0,0 +1,459 @@
+use std::net::Ipv4Addr;
+use types::*;
+
+/// The 8 bit type field of an IPv4 option, made up of the copied flag, the option class and the
+/// option number. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/ip-parameters/ip-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OptionType(pub u8);
+
+impl OptionType {
+    pub const EOL: OptionType = OptionType(0);
+    pub const NOP: OptionType = OptionType(1);
+    pub const RECORD_ROUTE: OptionType = OptionType(7);
+    pub const TIMESTAMP: OptionType = OptionType(68);
+    pub const SECURITY: OptionType = OptionType(130);
+    pub const LOOSE_SOURCE_ROUTE: OptionType = OptionType(131);
+    pub const STREAM_ID: OptionType = OptionType(136);
+    pub const STRICT_SOURCE_ROUTE: OptionType = OptionType(137);
+    pub const ROUTER_ALERT: OptionType = OptionType(148);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if the option must be copied into all fragments of the packet.
+    pub fn is_copied(&self) -> bool {
+        self.0 & 0x80 != 0
+    }
+
+    /// Returns the option class, zero for control and two for debugging and measurement.
+    pub fn class(&self) -> u2 {
+        (self.0 >> 5) & 0x03
+    }
+
+    /// Returns the option number within its class.
+    pub fn number(&self) -> u5 {
+        self.0 & 0x1f
+    }
+}
+
+/// A single option in an IPv4 header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Ipv4Option<'a> {
+    /// End of option list.
+    Eol,
+    /// No operation, used for padding between options.
+    Nop,
+    /// Record route, RFC 791.
+    RecordRoute(Route<'a>),
+    /// Internet timestamp, RFC 791.
+    Timestamp(Timestamp<'a>),
+    /// Loose source and record route, RFC 791.
+    LooseSourceRoute(Route<'a>),
+    /// Strict source and record route, RFC 791.
+    StrictSourceRoute(Route<'a>),
+    /// Router alert with its value, zero meaning that routers shall examine the packet, RFC 2113.
+    RouterAlert(u16),
+    /// Any other option, or one of the above with an invalid length, with its raw data.
+    Other(OptionType, &'a [u8]),
+}
+
+impl<'a> Ipv4Option<'a> {
+    /// Returns the number of bytes this option occupies when encoded.
+    pub fn encoded_len(&self) -> usize {
+        match *self {
+            Ipv4Option::Eol | Ipv4Option::Nop => 1,
+            Ipv4Option::RecordRoute(ref route) |
+            Ipv4Option::LooseSourceRoute(ref route) |
+            Ipv4Option::StrictSourceRoute(ref route) => 3 + route.data.len(),
+            Ipv4Option::Timestamp(ref timestamp) => 4 + timestamp.data.len(),
+            Ipv4Option::RouterAlert(_) => 4,
+            Ipv4Option::Other(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Encodes this option into the beginning of `buffer`. Returns the number of bytes written,
+    /// or `None` if the option does not fit in `buffer` or is longer than 255 bytes.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len();
+        if buffer.len() < len || len > 255 {
+            return None;
+        }
+        match *self {
+            Ipv4Option::Eol => buffer[0] = OptionType::EOL.value(),
+            Ipv4Option::Nop => buffer[0] = OptionType::NOP.value(),
+            Ipv4Option::RecordRoute(ref route) => route.write(OptionType::RECORD_ROUTE, buffer),
+            Ipv4Option::Timestamp(ref timestamp) => {
+                buffer[0] = OptionType::TIMESTAMP.value();
+                buffer[2] = timestamp.pointer;
+                buffer[3] = (timestamp.overflow << 4) | (timestamp.flag.value() & 0x0f);
+                buffer[4..len].copy_from_slice(timestamp.data);
+            }
+            Ipv4Option::LooseSourceRoute(ref route) => {
+                route.write(OptionType::LOOSE_SOURCE_ROUTE, buffer)
+            }
+            Ipv4Option::StrictSourceRoute(ref route) => {
+                route.write(OptionType::STRICT_SOURCE_ROUTE, buffer)
+            }
+            Ipv4Option::RouterAlert(value) => {
+                buffer[0] = OptionType::ROUTER_ALERT.value();
+                write_offset!(buffer, 2, value, u16, to_be);
+            }
+            Ipv4Option::Other(option_type, data) => {
+                buffer[0] = option_type.value();
+                buffer[2..len].copy_from_slice(data);
+            }
+        }
+        if len > 1 {
+            buffer[1] = len as u8;
+        }
+        Some(len)
+    }
+}
+
+/// The route data of a record route or source route option.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Route<'a> {
+    /// The one based offset, from the start of the option, of the next address to record or
+    /// route to.
+    pub pointer: u8,
+    /// The raw route data, a list of addresses.
+    pub data: &'a [u8],
+}
+
+impl<'a> Route<'a> {
+    /// Returns an iterator over all addresses in the route data, including the empty slots of a
+    /// record route option.
+    pub fn addresses(&self) -> Addresses<'a> {
+        Addresses { data: self.data }
+    }
+
+    /// Returns an iterator over the addresses before the pointer, those already recorded or
+    /// routed to.
+    pub fn recorded(&self) -> Addresses<'a> {
+        let len = usize::from(self.pointer).saturating_sub(4).min(self.data.len());
+        Addresses { data: &self.data[..len] }
+    }
+
+    fn write(&self, option_type: OptionType, buffer: &mut [u8]) {
+        buffer[0] = option_type.value();
+        buffer[2] = self.pointer;
+        buffer[3..3 + self.data.len()].copy_from_slice(self.data);
+    }
+}
+
+/// The data of a timestamp option.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Timestamp<'a> {
+    /// The one based offset, from the start of the option, of the next free entry.
+    pub pointer: u8,
+    /// The number of hosts that could not register a timestamp for lack of space.
+    pub overflow: u4,
+    pub flag: TimestampFlag,
+    /// The raw entries, timestamps or pairs of address and timestamp depending on `flag`.
+    pub data: &'a [u8],
+}
+
+impl<'a> Timestamp<'a> {
+    /// Returns an iterator over the entries before the pointer, those already filled in.
+    pub fn entries(&self) -> TimestampEntries<'a> {
+        let len = usize::from(self.pointer).saturating_sub(5).min(self.data.len());
+        TimestampEntries {
+            data: &self.data[..len],
+            with_address: self.flag != TimestampFlag::TIMESTAMPS_ONLY,
+        }
+    }
+}
+
+/// The 4 bit flag field of a timestamp option, telling what its entries contain.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TimestampFlag(pub u4);
+
+impl TimestampFlag {
+    pub const TIMESTAMPS_ONLY: TimestampFlag = TimestampFlag(0);
+    pub const WITH_ADDRESSES: TimestampFlag = TimestampFlag(1);
+    pub const PRESPECIFIED_ADDRESSES: TimestampFlag = TimestampFlag(3);
+
+    #[inline]
+    pub fn value(&self) -> u4 {
+        self.0
+    }
+}
+
+/// A filled in entry of a timestamp option.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TimestampEntry {
+    /// The address of the host registering the timestamp, absent with the
+    /// `TimestampFlag::TIMESTAMPS_ONLY` flag.
+    pub address: Option<Ipv4Addr>,
+    /// Milliseconds since midnight UT, unless the high bit is set.
+    pub timestamp: u32,
+}
+
+/// Encodes `options` into the beginning of `buffer`, followed by an end of option list and zero
+/// padding up to the next multiple of four bytes. Returns the number of bytes written, or `None`
+/// if the options and padding do not fit in `buffer`.
+pub fn write_options(buffer: &mut [u8], options: &[Ipv4Option]) -> Option<usize> {
+    let mut offset = 0;
+    for option in options {
+        offset += option.write(&mut buffer[offset..])?;
+    }
+    let len = offset.div_ceil(4) * 4;
+    if buffer.len() < len {
+        return None;
+    }
+    for byte in &mut buffer[offset..len] {
+        *byte = OptionType::EOL.value();
+    }
+    Some(len)
+}
+
+/// Iterator over the options in an IPv4 header. Iteration stops after an end of option list,
+/// or at the first option that does not fit in the data.
+#[derive(Clone)]
+pub struct Ipv4Options<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ipv4Options<'a>);
+
+impl<'a> Ipv4Options<'a> {
+    /// Creates an iterator over the options encoded in `data`.
+    pub fn new(data: &'a [u8]) -> Ipv4Options<'a> {
+        Ipv4Options { data }
+    }
+}
+
+impl<'a> Iterator for Ipv4Options<'a> {
+    type Item = Ipv4Option<'a>;
+
+    fn next(&mut self) -> Option<Ipv4Option<'a>> {
+        let (&option_type, rest) = self.data.split_first()?;
+        let option_type = OptionType(option_type);
+        match option_type {
+            OptionType::EOL => {
+                self.data = &[];
+                return Some(Ipv4Option::Eol);
+            }
+            OptionType::NOP => {
+                self.data = rest;
+                return Some(Ipv4Option::Nop);
+            }
+            _ => (),
+        }
+
+        let len = match rest.first() {
+            Some(&len) if len >= 2 && usize::from(len) <= self.data.len() => usize::from(len),
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (option, rest) = self.data.split_at(len);
+        self.data = rest;
+        let value = &option[2..];
+
+        let route = || Route {
+            pointer: value[0],
+            data: &value[1..],
+        };
+        Some(match (option_type, len) {
+            (OptionType::RECORD_ROUTE, 3..=255) => Ipv4Option::RecordRoute(route()),
+            (OptionType::TIMESTAMP, 4..=255) => Ipv4Option::Timestamp(Timestamp {
+                pointer: value[0],
+                overflow: value[1] >> 4,
+                flag: TimestampFlag(value[1] & 0x0f),
+                data: &value[2..],
+            }),
+            (OptionType::LOOSE_SOURCE_ROUTE, 3..=255) => Ipv4Option::LooseSourceRoute(route()),
+            (OptionType::STRICT_SOURCE_ROUTE, 3..=255) => Ipv4Option::StrictSourceRoute(route()),
+            (OptionType::ROUTER_ALERT, 4) => {
+                Ipv4Option::RouterAlert(read_offset!(value, 0, u16, from_be))
+            }
+            _ => Ipv4Option::Other(option_type, value),
+        })
+    }
+}
+
+/// Iterator over the addresses in the data of a route option. A trailing partial address is
+/// ignored.
+#[derive(Clone)]
+pub struct Addresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Addresses<'a>);
+
+impl<'a> Iterator for Addresses<'a> {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Ipv4Addr> {
+        if self.data.len() < 4 {
+            self.data = &[];
+            return None;
+        }
+        let (address, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(Ipv4Addr::from(read_offset!(address, 0, [u8; 4])))
+    }
+}
+
+/// Iterator over the filled in entries of a timestamp option. A trailing partial entry is
+/// ignored.
+#[derive(Clone)]
+pub struct TimestampEntries<'a> {
+    data: &'a [u8],
+    with_address: bool,
+}
+
+debug_items!(TimestampEntries<'a>);
+
+impl<'a> Iterator for TimestampEntries<'a> {
+    type Item = TimestampEntry;
+
+    fn next(&mut self) -> Option<TimestampEntry> {
+        let len = if self.with_address { 8 } else { 4 };
+        if self.data.len() < len {
+            self.data = &[];
+            return None;
+        }
+        let (entry, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(if self.with_address {
+            TimestampEntry {
+                address: Some(Ipv4Addr::from(read_offset!(entry, 0, [u8; 4]))),
+                timestamp: read_offset!(entry, 4, u32, from_be),
+            }
+        } else {
+            TimestampEntry {
+                address: None,
+                timestamp: read_offset!(entry, 0, u32, from_be),
+            }
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_type() {
+        let testee = OptionType::LOOSE_SOURCE_ROUTE;
+        assert!(testee.is_copied());
+        assert_eq!(0, testee.class());
+        assert_eq!(3, testee.number());
+        assert!(!OptionType::TIMESTAMP.is_copied());
+        assert_eq!(2, OptionType::TIMESTAMP.class());
+    }
+
+    #[test]
+    fn record_route() {
+        let data = [
+            0x07, 0x0b, 0x08, // Record route, one of two slots filled
+            192, 0, 2, 1, 0, 0, 0, 0,
+            0x94, 0x04, 0x00, 0x00, // Router alert
+            0x00, // EOL
+        ];
+        let options = Ipv4Options::new(&data).collect::<Vec<_>>();
+        assert_eq!(3, options.len());
+        match options[0] {
+            Ipv4Option::RecordRoute(route) => {
+                assert_eq!(8, route.pointer);
+                assert_eq!(2, route.addresses().count());
+                assert_eq!(
+                    vec![Ipv4Addr::new(192, 0, 2, 1)],
+                    route.recorded().collect::<Vec<_>>()
+                );
+            }
+            other => panic!("Unexpected option {:?}", other),
+        }
+        assert_eq!(Ipv4Option::RouterAlert(0), options[1]);
+        assert_eq!(Ipv4Option::Eol, options[2]);
+    }
+
+    #[test]
+    fn timestamp() {
+        let data = [
+            0x44, 0x14, 0x0d, 0x11, // Timestamp, one of two entries filled, overflow 1
+            192, 0, 2, 1, 0, 0, 0x01, 0x00,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let timestamp = match Ipv4Options::new(&data).next() {
+            Some(Ipv4Option::Timestamp(timestamp)) => timestamp,
+            other => panic!("Unexpected option {:?}", other),
+        };
+        assert_eq!(1, timestamp.overflow);
+        assert_eq!(TimestampFlag::WITH_ADDRESSES, timestamp.flag);
+        let entry = TimestampEntry {
+            address: Some(Ipv4Addr::new(192, 0, 2, 1)),
+            timestamp: 0x100,
+        };
+        assert_eq!(vec![entry], timestamp.entries().collect::<Vec<_>>());
+
+        let only = Timestamp {
+            flag: TimestampFlag::TIMESTAMPS_ONLY,
+            ..timestamp
+        };
+        assert_eq!(2, only.entries().count());
+        assert_eq!(None, only.entries().next().unwrap().address);
+    }
+
+    #[test]
+    fn invalid_length_is_other() {
+        let data = [0x94, 0x03, 0x00, 0x07, 0x02];
+        assert_eq!(
+            vec![
+                Ipv4Option::Other(OptionType::ROUTER_ALERT, &[0x00]),
+                Ipv4Option::Other(OptionType::RECORD_ROUTE, &[]),
+            ],
+            Ipv4Options::new(&data).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn truncated_option() {
+        let data = [0x01, 0x07, 0x0b, 0x04];
+        let mut options = Ipv4Options::new(&data);
+        assert_eq!(Some(Ipv4Option::Nop), options.next());
+        assert_eq!(None, options.next());
+        assert_eq!(None, Ipv4Options::new(&[0x94, 0x01]).next());
+    }
+
+    #[test]
+    fn write_read_roundtrip() {
+        let slots = [0; 8];
+        let entries = [0; 4];
+        let options = [
+            Ipv4Option::RecordRoute(Route { pointer: 4, data: &slots }),
+            Ipv4Option::Nop,
+            Ipv4Option::Timestamp(Timestamp {
+                pointer: 5,
+                overflow: 0,
+                flag: TimestampFlag::TIMESTAMPS_ONLY,
+                data: &entries,
+            }),
+            Ipv4Option::StrictSourceRoute(Route { pointer: 4, data: &[10, 0, 0, 1] }),
+            Ipv4Option::RouterAlert(0),
+            Ipv4Option::Other(OptionType::STREAM_ID, &[0x12, 0x34]),
+        ];
+        let mut buffer = [0xff; 40];
+        let len = write_options(&mut buffer, &options).unwrap();
+        assert_eq!(36, len);
+        let mut expected = options.to_vec();
+        expected.push(Ipv4Option::Eol);
+        assert_eq!(expected, Ipv4Options::new(&buffer[..len]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn write_too_short() {
+        let mut buffer = [0; 5];
+        assert_eq!(Some(4), write_options(&mut buffer, &[Ipv4Option::RouterAlert(0)]));
+        assert_eq!(None, write_options(&mut buffer, &[Ipv4Option::RouterAlert(0); 2]));
+        assert_eq!(None, write_options(&mut buffer, &[Ipv4Option::Nop; 5]));
+    }
+}
@@ -0,0 +1,141 @@
+use ip::Protocol;
+use std::cmp;
+use std::fmt;
+use super::{ext_header_len, write_options, Ipv6Option, Ipv6Options, MAX_EXT_HEADER_LEN};
+
+packet!(
+    Ipv6DestinationOptionsPacket,
+    MutIpv6DestinationOptionsPacket,
+    Ipv6DestinationOptionsPacketBuf,
+    8,
+    |data| ext_header_len(data),
+    // Eight bytes long, with a PadN option filling the space after the length field.
+    init |data| write_offset!(data, 1, [0, 1, 4, 0, 0, 0, 0], [u8; 7])
+);
+
+getters!(Ipv6DestinationOptionsPacket
+    pub fn next_header(&self) -> Protocol {
+        Protocol(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the length of the header in 8 byte units, not including the first 8 bytes.
+    pub fn hdr_ext_len(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns an iterator over the options in this header.
+    pub fn options(&self) -> Ipv6Options<'a> {
+        Ipv6Options::new(&self.0[2..self.header_len()])
+    }
+);
+
+setters!(MutIpv6DestinationOptionsPacket
+    pub fn set_next_header(&mut self, next_header: Protocol) {
+        write_offset!(self.0, 0, next_header.value(), u8);
+    }
+
+    pub fn set_hdr_ext_len(&mut self, hdr_ext_len: u8) {
+        write_offset!(self.0, 1, hdr_ext_len, u8);
+    }
+);
+
+impl<'a> MutIpv6DestinationOptionsPacket<'a> {
+    /// Encodes `options`, padded to a multiple of eight bytes, after the first two bytes of the
+    /// header and updates the header extension length to match. Returns the resulting header
+    /// length, or `None` if the options do not fit in the backing slice or in the 2048 bytes the
+    /// header extension length can describe.
+    pub fn set_options(&mut self, options: &[Ipv6Option]) -> Option<usize> {
+        let end = cmp::min(self.0.len(), MAX_EXT_HEADER_LEN);
+        let header_len = 2 + write_options(&mut self.0[2..end], options)?;
+        self.set_hdr_ext_len((header_len / 8 - 1) as u8);
+        Some(header_len)
+    }
+}
+
+impl<'a> fmt::Display for Ipv6DestinationOptionsPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IPv6 destination options next={} len={}",
+            self.next_header(),
+            self.header_len()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! destination_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIpv6DestinationOptionsPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    destination_setget_test!(next_header, set_next_header, Protocol(0xff), 0, [0xff]);
+    destination_setget_test!(hdr_ext_len, set_hdr_ext_len, 0xff, 1, [0xff]);
+
+    #[test]
+    fn too_short_slice() {
+        assert!(Ipv6DestinationOptionsPacket::new(&[0; 7]).is_none());
+    }
+
+    #[test]
+    fn header_len() {
+        let mut data = [0; 20];
+        data[1] = 1;
+        let testee = Ipv6DestinationOptionsPacket::new(&data).unwrap();
+        assert_eq!(16, testee.header_len());
+        assert_eq!(&data[..16], testee.header());
+        assert_eq!(&data[16..], testee.payload());
+    }
+
+    #[test]
+    fn truncated_header() {
+        let mut data = [0; 12];
+        data[1] = 1;
+        let testee = Ipv6DestinationOptionsPacket::new(&data).unwrap();
+        assert_eq!(12, testee.header_len());
+        assert!(testee.payload().is_empty());
+    }
+
+    #[test]
+    fn options() {
+        let data = [17, 0, 0x05, 0x02, 0x00, 0x00, 0x01, 0x00, 0xff];
+        let testee = Ipv6DestinationOptionsPacket::new(&data).unwrap();
+        assert_eq!(Protocol::UDP, testee.next_header());
+        assert_eq!(
+            vec![Ipv6Option::RouterAlert(0), Ipv6Option::PadN(2)],
+            testee.options().collect::<Vec<_>>()
+        );
+        assert_eq!(&[0xff], testee.payload());
+    }
+
+    #[test]
+    fn set_options() {
+        let mut data = [0; 20];
+        let header_len = MutIpv6DestinationOptionsPacket::new(&mut data)
+            .unwrap()
+            .set_options(&[Ipv6Option::JumboPayload(100_000), Ipv6Option::Pad1])
+            .unwrap();
+        assert_eq!(16, header_len);
+
+        let testee = Ipv6DestinationOptionsPacket::new(&data).unwrap();
+        assert_eq!(1, testee.hdr_ext_len());
+        assert_eq!(
+            vec![
+                Ipv6Option::JumboPayload(100_000),
+                Ipv6Option::Pad1,
+                Ipv6Option::PadN(7),
+            ],
+            testee.options().collect::<Vec<_>>()
+        );
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Ipv6DestinationOptionsPacketBuf => |packet| packet.options().eq(vec![Ipv6Option::PadN(6)])
+    );
+}
@@ -0,0 +1,100 @@
+use ip::Protocol;
+use std::fmt;
+use types::*;
+
+packet!(Ipv6FragmentPacket, MutIpv6FragmentPacket, Ipv6FragmentPacketBuf, 8);
+
+getters!(Ipv6FragmentPacket
+    pub fn next_header(&self) -> Protocol {
+        Protocol(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the offset of the fragment payload in 8 byte units, relative to the start of the
+    /// fragmentable part of the original packet.
+    pub fn fragment_offset(&self) -> u13 {
+        read_field!(self.0, bits!(2, 0, 13), u16)
+    }
+
+    pub fn more_fragments(&self) -> bool {
+        read_field!(self.0, bits!(3, 7, 1), u8) != 0
+    }
+
+    pub fn identification(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+);
+
+setters!(MutIpv6FragmentPacket
+    pub fn set_next_header(&mut self, next_header: Protocol) {
+        write_offset!(self.0, 0, next_header.value(), u8);
+    }
+
+    pub fn set_fragment_offset(&mut self, fragment_offset: u13) {
+        write_field!(self.0, bits!(2, 0, 13), fragment_offset, u16);
+    }
+
+    pub fn set_more_fragments(&mut self, more_fragments: bool) {
+        write_field!(self.0, bits!(3, 7, 1), u8::from(more_fragments), u8);
+    }
+
+    pub fn set_identification(&mut self, identification: u32) {
+        write_offset!(self.0, 4, identification, u32, to_be);
+    }
+);
+
+impl<'a> fmt::Display for Ipv6FragmentPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IPv6 fragment id={:#x} offset={} more={} next={}",
+            self.identification(),
+            self.fragment_offset(),
+            self.more_fragments(),
+            self.next_header()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fragment_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIpv6FragmentPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    fragment_setget_test!(next_header, set_next_header, Protocol(0xff), 0, [0xff]);
+    fragment_setget_test!(
+        fragment_offset,
+        set_fragment_offset,
+        0x1fff,
+        2,
+        [0xff, 0xf8]
+    );
+    fragment_setget_test!(more_fragments, set_more_fragments, true, 3, [0x01]);
+    fragment_setget_test!(
+        identification,
+        set_identification,
+        0xdeadbeef,
+        4,
+        [0xde, 0xad, 0xbe, 0xef]
+    );
+
+    #[test]
+    fn getters_alternating_bits() {
+        let backing_data = [0b1010_1010; 8];
+        let testee = Ipv6FragmentPacket::new(&backing_data).unwrap();
+        assert_eq!(0b1_0101_0101_0101, testee.fragment_offset());
+        assert!(!testee.more_fragments());
+    }
+
+    #[test]
+    fn too_short_slice() {
+        assert!(Ipv6FragmentPacket::new(&[0; 7]).is_none());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, Ipv6FragmentPacketBuf);
+}
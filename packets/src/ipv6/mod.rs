@@ -0,0 +1,507 @@
+use alloc::vec::Vec;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use types::*;
+use builder::{self, Payload};
+use error::{self, ParseError};
+use ethernet::EtherType;
+use ip::Protocol;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+mod options;
+pub use self::options::*;
+
+mod hop_by_hop;
+pub use self::hop_by_hop::*;
+
+mod routing;
+pub use self::routing::*;
+
+mod fragment;
+pub use self::fragment::*;
+
+mod destination;
+pub use self::destination::*;
+
+packet!(
+    Ipv6Packet,
+    MutIpv6Packet,
+    Ipv6PacketBuf,
+    40,
+    |_data| 40,
+    // A payload length of zero is used by jumbograms, RFC 2675, which give the length in a
+    // hop-by-hop option instead.
+    len |data| match read_offset!(data, 4, u16, from_be) {
+        0 => data.len(),
+        length => 40 + usize::from(length),
+    },
+    set_len |data, len| {
+        len - 40 <= usize::from(u16::MAX) && {
+            MutIpv6Packet(data).set_payload_length((len - 40) as u16);
+            true
+        }
+    },
+    init |data| {
+        data[0] = 0x60;
+        write_offset!(data, 4, (data.len() - 40) as u16, u16, to_be);
+    }
+);
+
+fields!(Ipv6Packet,
+    VERSION: 0, 4;
+    TRAFFIC_CLASS: 4, 8;
+    FLOW_LABEL: 12, 20;
+    PAYLOAD_LENGTH: 32, 16;
+    NEXT_HEADER: 48, 8;
+    HOP_LIMIT: 56, 8;
+    SOURCE: 64, 128;
+    DESTINATION: 192, 128;
+);
+
+getters!(Ipv6Packet
+    pub fn version(&self) -> u4 {
+        read_field!(self.0, fields::VERSION, u8)
+    }
+
+    pub fn traffic_class(&self) -> u8 {
+        read_field!(self.0, fields::TRAFFIC_CLASS, u16) as u8
+    }
+
+    pub fn flow_label(&self) -> u20 {
+        read_field!(self.0, fields::FLOW_LABEL, u32)
+    }
+
+    pub fn payload_length(&self) -> u16 {
+        read_field!(self.0, fields::PAYLOAD_LENGTH, u16)
+    }
+
+    pub fn next_header(&self) -> Protocol {
+        Protocol(read_field!(self.0, fields::NEXT_HEADER, u8))
+    }
+
+    pub fn hop_limit(&self) -> u8 {
+        read_field!(self.0, fields::HOP_LIMIT, u8)
+    }
+
+    pub fn source(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_field!(self.0, fields::SOURCE, u128))
+    }
+
+    pub fn destination(&self) -> Ipv6Addr {
+        Ipv6Addr::from(read_field!(self.0, fields::DESTINATION, u128))
+    }
+);
+
+impl<'a> Ipv6Packet<'a> {
+    /// Creates a new immutable packet like `new`, but also validates it. The version must be 6
+    /// and the payload length must fit in `data`.
+    pub fn parse(data: &'a [u8]) -> Result<Ipv6Packet<'a>, ParseError> {
+        error::check_len(data, Self::MIN_LEN)?;
+        let packet = Ipv6Packet(data);
+        if packet.version() != 6 {
+            return Err(ParseError::InvalidVersion(packet.version()));
+        }
+        let len = Self::MIN_LEN + usize::from(packet.payload_length());
+        if len > data.len() {
+            return Err(ParseError::InvalidLength(len));
+        }
+        Ok(packet)
+    }
+}
+
+setters!(MutIpv6Packet
+    pub fn set_version(&mut self, version: u4) {
+        write_field!(self.0, fields::VERSION, version, u8);
+    }
+
+    pub fn set_traffic_class(&mut self, traffic_class: u8) {
+        write_field!(self.0, fields::TRAFFIC_CLASS, u16::from(traffic_class), u16);
+    }
+
+    pub fn set_flow_label(&mut self, flow_label: u20) {
+        write_field!(self.0, fields::FLOW_LABEL, flow_label, u32);
+    }
+
+    pub fn set_payload_length(&mut self, payload_length: u16) {
+        write_field!(self.0, fields::PAYLOAD_LENGTH, payload_length, u16);
+    }
+
+    pub fn set_next_header(&mut self, protocol: Protocol) {
+        write_field!(self.0, fields::NEXT_HEADER, protocol.value(), u8);
+    }
+
+    pub fn set_hop_limit(&mut self, hop_limit: u8) {
+        write_field!(self.0, fields::HOP_LIMIT, hop_limit, u8);
+    }
+
+    pub fn set_source(&mut self, source: Ipv6Addr) {
+        write_field!(self.0, fields::SOURCE, u128::from(source), u128);
+    }
+
+    pub fn set_destination(&mut self, destination: Ipv6Addr) {
+        write_field!(self.0, fields::DESTINATION, u128::from(destination), u128);
+    }
+);
+
+
+/// Builds an IPv6 packet. The version and payload length are filled in when building. See the
+/// `builder` module for an example.
+#[derive(Debug, Copy, Clone)]
+pub struct Ipv6Builder<P = &'static [u8]> {
+    traffic_class: u8,
+    flow_label: u20,
+    next_header: Option<Protocol>,
+    hop_limit: u8,
+    source: Ipv6Addr,
+    destination: Ipv6Addr,
+    payload: P,
+}
+
+impl Ipv6Builder {
+    /// Creates a builder for a packet with a hop limit of 64, all other fields zero and an
+    /// empty payload.
+    pub fn new() -> Ipv6Builder {
+        Ipv6Builder::default()
+    }
+}
+
+impl Default for Ipv6Builder {
+    fn default() -> Ipv6Builder {
+        Ipv6Builder {
+            traffic_class: 0,
+            flow_label: 0,
+            next_header: None,
+            hop_limit: 64,
+            source: Ipv6Addr::UNSPECIFIED,
+            destination: Ipv6Addr::UNSPECIFIED,
+            payload: &[],
+        }
+    }
+}
+
+impl<P: Payload> Ipv6Builder<P> {
+    pub fn traffic_class(mut self, traffic_class: u8) -> Self {
+        self.traffic_class = traffic_class;
+        self
+    }
+
+    pub fn flow_label(mut self, flow_label: u20) -> Self {
+        self.flow_label = flow_label;
+        self
+    }
+
+    /// Sets the next header. Only needed when the payload is not a builder that knows its own
+    /// protocol.
+    pub fn next_header(mut self, next_header: Protocol) -> Self {
+        self.next_header = Some(next_header);
+        self
+    }
+
+    pub fn hop_limit(mut self, hop_limit: u8) -> Self {
+        self.hop_limit = hop_limit;
+        self
+    }
+
+    pub fn source(mut self, source: Ipv6Addr) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn destination(mut self, destination: Ipv6Addr) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    pub fn payload<Q: Payload>(self, payload: Q) -> Ipv6Builder<Q> {
+        Ipv6Builder {
+            traffic_class: self.traffic_class,
+            flow_label: self.flow_label,
+            next_header: self.next_header,
+            hop_limit: self.hop_limit,
+            source: self.source,
+            destination: self.destination,
+            payload,
+        }
+    }
+
+    /// Writes the packet to the start of `buffer`. Returns the length of the packet, or `None`
+    /// if it does not fit in `buffer`, the payload does not fit in the payload length field or
+    /// can't be encoded, or the next header is not known.
+    pub fn build_into(&self, buffer: &mut [u8]) -> Option<usize> {
+        self.write(buffer, None)
+    }
+
+    /// Writes the packet to a newly allocated buffer. See `build_into`.
+    pub fn build(&self) -> Option<Vec<u8>> {
+        builder::build(self)
+    }
+}
+
+impl<P: Payload> Payload for Ipv6Builder<P> {
+    fn encoded_len(&self) -> usize {
+        Ipv6Packet::MIN_LEN + self.payload.encoded_len()
+    }
+
+    fn write(&self, buffer: &mut [u8], _addresses: Option<(IpAddr, IpAddr)>) -> Option<usize> {
+        let next_header = self.next_header.or_else(|| self.payload.protocol())?;
+        let payload_len = self.payload.encoded_len();
+        if payload_len > usize::from(u16::MAX) {
+            return None;
+        }
+        let buffer = buffer.get_mut(..self.encoded_len())?;
+        let addresses = (IpAddr::V6(self.source), IpAddr::V6(self.destination));
+        self.payload.write(&mut buffer[Ipv6Packet::MIN_LEN..], Some(addresses))?;
+        let mut packet = MutIpv6Packet::new(buffer)?;
+        packet.set_version(6);
+        packet.set_traffic_class(self.traffic_class);
+        packet.set_flow_label(self.flow_label);
+        packet.set_payload_length(payload_len as u16);
+        packet.set_next_header(next_header);
+        packet.set_hop_limit(self.hop_limit);
+        packet.set_source(self.source);
+        packet.set_destination(self.destination);
+        Some(buffer.len())
+    }
+
+    fn ether_type(&self) -> Option<EtherType> {
+        Some(EtherType::IPV6)
+    }
+
+    fn protocol(&self) -> Option<Protocol> {
+        Some(Protocol::IPV6)
+    }
+}
+
+
+/// The fields of an IPv6 header as a plain value, one field per getter on `Ipv6Packet`.
+/// Extension headers are not included. Implements `Serialize` and `Deserialize` with the `serde`
+/// feature enabled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ipv6Header {
+    pub version: u4,
+    pub traffic_class: u8,
+    pub flow_label: u20,
+    pub payload_length: u16,
+    pub next_header: Protocol,
+    pub hop_limit: u8,
+    pub source: Ipv6Addr,
+    pub destination: Ipv6Addr,
+}
+
+impl<'a> Ipv6Packet<'a> {
+    /// Reads all fields of the fixed header into an `Ipv6Header`.
+    pub fn to_header(&self) -> Ipv6Header {
+        Ipv6Header {
+            version: self.version(),
+            traffic_class: self.traffic_class(),
+            flow_label: self.flow_label(),
+            payload_length: self.payload_length(),
+            next_header: self.next_header(),
+            hop_limit: self.hop_limit(),
+            source: self.source(),
+            destination: self.destination(),
+        }
+    }
+}
+
+impl<'a> MutIpv6Packet<'a> {
+    /// Writes all fields of `header` to the fixed header, as they are. The payload length is not
+    /// recomputed.
+    pub fn set_header(&mut self, header: &Ipv6Header) {
+        self.set_version(header.version);
+        self.set_traffic_class(header.traffic_class);
+        self.set_flow_label(header.flow_label);
+        self.set_payload_length(header.payload_length);
+        self.set_next_header(header.next_header);
+        self.set_hop_limit(header.hop_limit);
+        self.set_source(header.source);
+        self.set_destination(header.destination);
+    }
+}
+
+
+/// Returns the length of an extension header that encodes its length in the second byte as the
+/// number of 8 byte units, not counting the first 8 bytes.
+fn ext_header_len(data: &[u8]) -> usize {
+    (usize::from(data[1]) + 1) * 8
+}
+
+/// The largest extension header the length field read by `ext_header_len` can describe.
+const MAX_EXT_HEADER_LEN: usize = 256 * 8;
+
+
+impl<'a> fmt::Display for Ipv6Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IPv6 {} -> {} next={} hlim={} len={}",
+            self.source(),
+            self.destination(),
+            self.next_header(),
+            self.hop_limit(),
+            self.payload_length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let data = Ipv6Builder::new()
+            .source(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1))
+            .destination(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1))
+            .next_header(Protocol::ICMPV6)
+            .hop_limit(255)
+            .payload([0; 8])
+            .build()
+            .unwrap();
+        assert_eq!(
+            "IPv6 fe80::1 -> ff02::1 next=ICMPv6 hlim=255 len=8",
+            Ipv6Packet::new(&data).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn min_length() {
+        assert_eq!(Ipv6Packet::MIN_LEN, 40);
+    }
+
+    #[test]
+    fn too_short_slice() {
+        assert!(Ipv6Packet::new(&[0; 39]).is_none());
+    }
+
+    #[test]
+    fn build() {
+        let source = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let destination = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+        let mut buffer = [0xff; 48];
+        let len = Ipv6Builder::new()
+            .traffic_class(0xab)
+            .flow_label(0x12345)
+            .next_header(Protocol::UDP)
+            .hop_limit(255)
+            .source(source)
+            .destination(destination)
+            .payload(b"payload")
+            .build_into(&mut buffer)
+            .unwrap();
+        assert_eq!(47, len);
+        let testee = Ipv6Packet::new(&buffer[..len]).unwrap();
+        assert_eq!(6, testee.version());
+        assert_eq!(0xab, testee.traffic_class());
+        assert_eq!(0x12345, testee.flow_label());
+        assert_eq!(7, testee.payload_length());
+        assert_eq!(Protocol::UDP, testee.next_header());
+        assert_eq!(255, testee.hop_limit());
+        assert_eq!(source, testee.source());
+        assert_eq!(destination, testee.destination());
+        assert_eq!(b"payload", testee.payload());
+        assert_eq!(None, Ipv6Builder::new().build());
+    }
+
+    #[test]
+    fn parse() {
+        let mut data = [0; 42];
+        data[0] = 0x60;
+        data[5] = 2;
+        assert!(Ipv6Packet::parse(&data).is_ok());
+        assert_eq!(Err(ParseError::InvalidLength(42)), Ipv6Packet::parse(&data[..41]));
+        assert_eq!(
+            Err(ParseError::TooShort { min_len: 40, len: 39 }),
+            Ipv6Packet::parse(&data[..39])
+        );
+        data[0] = 0x40;
+        assert_eq!(Err(ParseError::InvalidVersion(4)), Ipv6Packet::parse(&data));
+    }
+
+    #[test]
+    fn owned_buffer() {
+        let testee = Ipv6PacketBuf::new_with_payload_len(4);
+        assert_eq!(6, testee.packet().version());
+        assert_eq!(4, testee.packet().payload_length());
+        assert_eq!(&[0; 4], testee.packet().payload());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut testee = Ipv6PacketBuf::new_with_payload_len(4);
+        assert!(testee.set_payload(&[1, 2, 3, 4, 5, 6]));
+        assert_eq!(6, testee.packet().payload_length());
+        assert_eq!(&[1, 2, 3, 4, 5, 6], testee.packet().payload());
+        let mut data = [0; 44];
+        let mut testee = MutIpv6Packet::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[1]));
+        assert_eq!(1, testee.as_immutable().payload_length());
+        assert_eq!(&[1], testee.as_immutable().payload());
+        assert!(!testee.set_payload(&[0; 5]));
+    }
+
+    macro_rules! ipv6_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIpv6Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ipv6_setget_test!(version, set_version, 0xf, 0, [0xf0]);
+    ipv6_setget_test!(traffic_class, set_traffic_class, 0xff, 0, [0x0f, 0xf0]);
+    ipv6_setget_test!(flow_label, set_flow_label, 0xfffff, 1, [0x0f, 0xff, 0xff]);
+    ipv6_setget_test!(payload_length, set_payload_length, 0xabcd, 4, [0xab, 0xcd]);
+    ipv6_setget_test!(next_header, set_next_header, Protocol(123), 6, [123]);
+    ipv6_setget_test!(hop_limit, set_hop_limit, 0x65, 7, [0x65]);
+    ipv6_setget_test!(
+        source,
+        set_source,
+        Ipv6Addr::new(0x2001, 1, 2, 3, 4, 5, 6, 0xabcd),
+        8,
+        [0x20, 0x01, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0xab, 0xcd]
+    );
+    ipv6_setget_test!(
+        destination,
+        set_destination,
+        Ipv6Addr::new(0x2001, 1, 2, 3, 4, 5, 6, 0x1234),
+        24,
+        [0x20, 0x01, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0x12, 0x34]
+    );
+
+    #[test]
+    fn getters_alternating_bits() {
+        let backing_data = [0b1010_1010; 40];
+        let testee = Ipv6Packet::new(&backing_data).unwrap();
+        assert_eq!(0b1010, testee.version());
+        assert_eq!(0b1010_1010, testee.traffic_class());
+        assert_eq!(0b1010_1010_1010_1010_1010, testee.flow_label());
+        assert_eq!(0b1010_1010_1010_1010, testee.payload_length());
+        assert_eq!(Protocol(0b1010_1010), testee.next_header());
+        assert_eq!(0b1010_1010, testee.hop_limit());
+    }
+
+
+    #[test]
+    fn header_round_trip() {
+        let header = Ipv6Header {
+            version: 6,
+            traffic_class: 0xb8,
+            flow_label: 0x12345,
+            payload_length: 8,
+            next_header: Protocol::UDP,
+            hop_limit: 64,
+            source: Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1),
+            destination: Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2),
+        };
+        let mut buffer = [0; 40];
+        MutIpv6Packet::new(&mut buffer).unwrap().set_header(&header);
+        assert_eq!(header, Ipv6Packet::new(&buffer).unwrap().to_header());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Ipv6PacketBuf => |packet| Ipv6Packet::parse(packet.data()).is_ok()
+    );
+}
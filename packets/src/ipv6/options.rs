@@ -0,0 +1,283 @@
+use types::*;
+
+/// The 8 bit option type of an option in a Hop-by-Hop or Destination Options extension header.
+/// See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/ipv6-parameters/ipv6-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OptionType(pub u8);
+
+impl OptionType {
+    pub const PAD1: OptionType = OptionType(0x00);
+    pub const PADN: OptionType = OptionType(0x01);
+    pub const ROUTER_ALERT: OptionType = OptionType(0x05);
+    pub const JUMBO_PAYLOAD: OptionType = OptionType(0xC2);
+
+    /// Returns the numeric representation of this option type.
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns the two highest order bits, which tell what a node that does not recognize the
+    /// option must do with the packet.
+    pub fn action(&self) -> u2 {
+        self.0 >> 6
+    }
+
+    /// Returns true if the option data may change en route to the final destination.
+    pub fn may_change(&self) -> bool {
+        self.0 & 0x20 != 0
+    }
+}
+
+/// A single option in a Hop-by-Hop or Destination Options extension header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Ipv6Option<'a> {
+    /// A single byte of padding.
+    Pad1,
+    /// Padding occupying the given number of bytes, including the type and length bytes.
+    PadN(usize),
+    /// Router Alert option with the given value.
+    RouterAlert(u16),
+    /// Jumbo Payload option with the given payload length.
+    JumboPayload(u32),
+    /// Any other option, or one of the above with an invalid length, with its raw data.
+    Other(OptionType, &'a [u8]),
+}
+
+impl<'a> Ipv6Option<'a> {
+    /// Returns the number of bytes this option occupies when encoded.
+    pub fn encoded_len(&self) -> usize {
+        match *self {
+            Ipv6Option::Pad1 => 1,
+            Ipv6Option::PadN(len) => len,
+            Ipv6Option::RouterAlert(_) => 4,
+            Ipv6Option::JumboPayload(_) => 6,
+            Ipv6Option::Other(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Encodes this option into the beginning of `buffer`. Returns the number of bytes written,
+    /// or `None` if the option does not fit in `buffer` or cannot be encoded, as is the case for
+    /// `PadN` shorter than two bytes or option data longer than 255 bytes.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len();
+        if buffer.len() < len {
+            return None;
+        }
+        match *self {
+            Ipv6Option::Pad1 => buffer[0] = OptionType::PAD1.value(),
+            Ipv6Option::PadN(len) => {
+                if !(2..=257).contains(&len) {
+                    return None;
+                }
+                buffer[0] = OptionType::PADN.value();
+                buffer[1] = (len - 2) as u8;
+                for byte in &mut buffer[2..len] {
+                    *byte = 0;
+                }
+            }
+            Ipv6Option::RouterAlert(value) => {
+                buffer[0] = OptionType::ROUTER_ALERT.value();
+                buffer[1] = 2;
+                write_offset!(buffer, 2, value, u16, to_be);
+            }
+            Ipv6Option::JumboPayload(payload_length) => {
+                buffer[0] = OptionType::JUMBO_PAYLOAD.value();
+                buffer[1] = 4;
+                write_offset!(buffer, 2, payload_length, u32, to_be);
+            }
+            Ipv6Option::Other(option_type, data) => {
+                if data.len() > 255 {
+                    return None;
+                }
+                buffer[0] = option_type.value();
+                buffer[1] = data.len() as u8;
+                buffer[2..len].copy_from_slice(data);
+            }
+        }
+        Some(len)
+    }
+}
+
+/// Encodes `options` into the beginning of `buffer`, followed by the padding needed to make the
+/// encoded options end on an 8 byte boundary of the extension header. `buffer` is expected to
+/// start right after the next header and header extension length fields, so the total length of
+/// the encoded options will be a multiple of eight, minus two. Returns the number of bytes
+/// written, or `None` if the options and padding do not fit in `buffer`.
+pub fn write_options(buffer: &mut [u8], options: &[Ipv6Option]) -> Option<usize> {
+    let mut offset = 0;
+    for option in options {
+        offset += option.write(&mut buffer[offset..])?;
+    }
+    let padding = match (8 - (offset + 2) % 8) % 8 {
+        0 => 0,
+        1 => Ipv6Option::Pad1.write(&mut buffer[offset..])?,
+        len => Ipv6Option::PadN(len).write(&mut buffer[offset..])?,
+    };
+    Some(offset + padding)
+}
+
+/// Iterator over the TLV encoded options in a Hop-by-Hop or Destination Options extension
+/// header. Iteration stops at the first option that does not fit in the data.
+#[derive(Clone)]
+pub struct Ipv6Options<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ipv6Options<'a>);
+
+impl<'a> Ipv6Options<'a> {
+    /// Creates an iterator over the options encoded in `data`.
+    pub fn new(data: &'a [u8]) -> Ipv6Options<'a> {
+        Ipv6Options { data }
+    }
+}
+
+impl<'a> Iterator for Ipv6Options<'a> {
+    type Item = Ipv6Option<'a>;
+
+    fn next(&mut self) -> Option<Ipv6Option<'a>> {
+        let (&option_type, rest) = self.data.split_first()?;
+        let option_type = OptionType(option_type);
+        if option_type == OptionType::PAD1 {
+            self.data = rest;
+            return Some(Ipv6Option::Pad1);
+        }
+
+        let len = match rest.split_first() {
+            Some((&len, _)) if usize::from(len) < rest.len() => usize::from(len),
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (value, rest) = rest[1..].split_at(len);
+        self.data = rest;
+
+        Some(match (option_type, len) {
+            (OptionType::PADN, _) => Ipv6Option::PadN(len + 2),
+            (OptionType::ROUTER_ALERT, 2) => {
+                Ipv6Option::RouterAlert(read_offset!(value, 0, u16, from_be))
+            }
+            (OptionType::JUMBO_PAYLOAD, 4) => {
+                Ipv6Option::JumboPayload(read_offset!(value, 0, u32, from_be))
+            }
+            _ => Ipv6Option::Other(option_type, value),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_type_bits() {
+        assert_eq!(0b11, OptionType::JUMBO_PAYLOAD.action());
+        assert!(!OptionType::JUMBO_PAYLOAD.may_change());
+        assert_eq!(0b00, OptionType(0x3f).action());
+        assert!(OptionType(0x3f).may_change());
+    }
+
+    #[test]
+    fn all_known_options() {
+        let data = [
+            0x00, // Pad1
+            0x01, 0x01, 0x00, // PadN, three bytes in total
+            0x05, 0x02, 0x00, 0x02, // Router Alert, RSVP
+            0xc2, 0x04, 0x00, 0x01, 0x00, 0x00, // Jumbo Payload, 65536 bytes
+            0x1e, 0x01, 0xab, // Unknown option
+        ];
+        let options = Ipv6Options::new(&data).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Ipv6Option::Pad1,
+                Ipv6Option::PadN(3),
+                Ipv6Option::RouterAlert(2),
+                Ipv6Option::JumboPayload(65536),
+                Ipv6Option::Other(OptionType(0x1e), &[0xab]),
+            ],
+            options
+        );
+    }
+
+    #[test]
+    fn invalid_length_is_other() {
+        let data = [0x05, 0x01, 0x00];
+        let mut options = Ipv6Options::new(&data);
+        assert_eq!(
+            Some(Ipv6Option::Other(OptionType::ROUTER_ALERT, &[0x00])),
+            options.next()
+        );
+        assert_eq!(None, options.next());
+    }
+
+    #[test]
+    fn truncated_option() {
+        let data = [0x00, 0x05, 0x02, 0x00];
+        let mut options = Ipv6Options::new(&data);
+        assert_eq!(Some(Ipv6Option::Pad1), options.next());
+        assert_eq!(None, options.next());
+        assert_eq!(None, options.next());
+    }
+
+    #[test]
+    fn missing_length() {
+        assert_eq!(None, Ipv6Options::new(&[0x01]).next());
+    }
+
+    #[test]
+    fn write_all_known_options() {
+        let options = [
+            Ipv6Option::Pad1,
+            Ipv6Option::PadN(3),
+            Ipv6Option::RouterAlert(2),
+            Ipv6Option::JumboPayload(65536),
+            Ipv6Option::Other(OptionType(0x1e), &[0xab]),
+        ];
+        let mut buffer = [0xff; 17];
+        let mut offset = 0;
+        for option in &options {
+            offset += option.write(&mut buffer[offset..]).unwrap();
+        }
+        assert_eq!(17, offset);
+        assert_eq!(options.to_vec(), Ipv6Options::new(&buffer).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn write_invalid_options() {
+        let mut buffer = [0; 300];
+        assert_eq!(None, Ipv6Option::PadN(1).write(&mut buffer));
+        assert_eq!(None, Ipv6Option::RouterAlert(0).write(&mut buffer[..3]));
+        assert_eq!(
+            None,
+            Ipv6Option::Other(OptionType(0x1e), &[0; 256]).write(&mut buffer)
+        );
+    }
+
+    #[test]
+    fn write_options_padding() {
+        let mut buffer = [0xff; 14];
+        let len = write_options(&mut buffer, &[Ipv6Option::RouterAlert(0)]).unwrap();
+        assert_eq!(6, len);
+        assert_eq!(&[0x05, 0x02, 0x00, 0x00, 0x01, 0x00], &buffer[..6]);
+
+        let len = write_options(&mut buffer, &[Ipv6Option::PadN(5)]).unwrap();
+        assert_eq!(6, len);
+        assert_eq!(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x00], &buffer[..6]);
+
+        let len = write_options(&mut buffer, &[Ipv6Option::JumboPayload(0), Ipv6Option::Pad1])
+            .unwrap();
+        assert_eq!(14, len);
+        assert_eq!(Some(Ipv6Option::PadN(7)), Ipv6Options::new(&buffer[7..]).next());
+    }
+
+    #[test]
+    fn write_options_too_short() {
+        let mut buffer = [0; 5];
+        assert_eq!(None, write_options(&mut buffer, &[Ipv6Option::RouterAlert(0)]));
+    }
+}
@@ -0,0 +1,239 @@
+use ip::Protocol;
+use std::cmp;
+use std::fmt;
+use std::net::Ipv6Addr;
+use super::ext_header_len;
+
+packet!(
+    Ipv6RoutingPacket,
+    MutIpv6RoutingPacket,
+    Ipv6RoutingPacketBuf,
+    8,
+    |data| ext_header_len(data),
+    init |data| {
+        // Eight bytes long, so there is no room for any address left to visit.
+        write_offset!(data, 1, 0u8, u8);
+        write_offset!(data, 3, 0u8, u8);
+    }
+);
+
+getters!(Ipv6RoutingPacket
+    pub fn next_header(&self) -> Protocol {
+        Protocol(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the length of the header in 8 byte units, not including the first 8 bytes.
+    pub fn hdr_ext_len(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    pub fn routing_type(&self) -> RoutingType {
+        RoutingType(read_offset!(self.0, 2, u8))
+    }
+
+    pub fn segments_left(&self) -> u8 {
+        read_offset!(self.0, 3, u8)
+    }
+
+    /// Returns the index of the last element in the segment list. Only valid in a Segment
+    /// Routing Header.
+    pub fn last_entry(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    /// Returns the flags field. Only valid in a Segment Routing Header.
+    pub fn flags(&self) -> u8 {
+        read_offset!(self.0, 5, u8)
+    }
+
+    /// Returns the tag field. Only valid in a Segment Routing Header.
+    pub fn tag(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    /// Returns an iterator over the segment list of a Segment Routing Header. The list is
+    /// encoded in reverse order, so the first element is the final segment of the path.
+    /// Iteration stops early if the header is too short to hold `last_entry() + 1` segments.
+    pub fn segments(&self) -> Segments<'a> {
+        let list_len = (usize::from(self.last_entry()) + 1) * 16;
+        let end = cmp::min(8 + list_len, self.header_len());
+        Segments { data: &self.0[8..end] }
+    }
+);
+
+impl<'a> Ipv6RoutingPacket<'a> {
+    /// Returns the segment at `index` in the segment list of a Segment Routing Header, or `None`
+    /// if it does not fit in the header.
+    pub fn segment(&self, index: u8) -> Option<Ipv6Addr> {
+        self.segments().nth(usize::from(index))
+    }
+}
+
+setters!(MutIpv6RoutingPacket
+    pub fn set_next_header(&mut self, next_header: Protocol) {
+        write_offset!(self.0, 0, next_header.value(), u8);
+    }
+
+    pub fn set_hdr_ext_len(&mut self, hdr_ext_len: u8) {
+        write_offset!(self.0, 1, hdr_ext_len, u8);
+    }
+
+    pub fn set_routing_type(&mut self, routing_type: RoutingType) {
+        write_offset!(self.0, 2, routing_type.value(), u8);
+    }
+
+    pub fn set_segments_left(&mut self, segments_left: u8) {
+        write_offset!(self.0, 3, segments_left, u8);
+    }
+
+    pub fn set_last_entry(&mut self, last_entry: u8) {
+        write_offset!(self.0, 4, last_entry, u8);
+    }
+
+    pub fn set_flags(&mut self, flags: u8) {
+        write_offset!(self.0, 5, flags, u8);
+    }
+
+    pub fn set_tag(&mut self, tag: u16) {
+        write_offset!(self.0, 6, tag, u16, to_be);
+    }
+);
+
+impl<'a> MutIpv6RoutingPacket<'a> {
+    /// Writes `segment` at `index` in the segment list of a Segment Routing Header. Returns
+    /// false if the backing slice is too short to hold the segment.
+    pub fn set_segment(&mut self, index: u8, segment: Ipv6Addr) -> bool {
+        let offset = 8 + usize::from(index) * 16;
+        offset + 16 <= self.0.len() && {
+            write_offset!(self.0, offset, segment.octets(), [u8; 16]);
+            true
+        }
+    }
+}
+
+
+/// Iterator over the IPv6 addresses in the segment list of a Segment Routing Header.
+#[derive(Clone)]
+pub struct Segments<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Segments<'a>);
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Ipv6Addr;
+
+    fn next(&mut self) -> Option<Ipv6Addr> {
+        if self.data.len() < 16 {
+            return None;
+        }
+        let (segment, rest) = self.data.split_at(16);
+        self.data = rest;
+        Some(Ipv6Addr::from(read_offset!(segment, 0, [u8; 16])))
+    }
+}
+
+
+/// The 8 bit routing type field of an IPv6 Routing header. See [IANA's list] for the full
+/// definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/ipv6-parameters/ipv6-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RoutingType(pub u8);
+
+impl RoutingType {
+    pub const SOURCE_ROUTE: RoutingType = RoutingType(0);
+    pub const NIMROD: RoutingType = RoutingType(1);
+    pub const TYPE_2: RoutingType = RoutingType(2);
+    pub const RPL: RoutingType = RoutingType(3);
+    pub const SEGMENT_ROUTING: RoutingType = RoutingType(4);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl<'a> fmt::Display for Ipv6RoutingPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IPv6 routing type={} segments_left={} next={}",
+            self.routing_type().value(),
+            self.segments_left(),
+            self.next_header()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! routing_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIpv6RoutingPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    routing_setget_test!(next_header, set_next_header, Protocol(0xff), 0, [0xff]);
+    routing_setget_test!(hdr_ext_len, set_hdr_ext_len, 0xff, 1, [0xff]);
+    routing_setget_test!(routing_type, set_routing_type, RoutingType(0xff), 2, [0xff]);
+    routing_setget_test!(segments_left, set_segments_left, 0xff, 3, [0xff]);
+    routing_setget_test!(last_entry, set_last_entry, 0xff, 4, [0xff]);
+    routing_setget_test!(flags, set_flags, 0xff, 5, [0xff]);
+    routing_setget_test!(tag, set_tag, 0xabcd, 6, [0xab, 0xcd]);
+
+    fn segment(i: u16) -> Ipv6Addr {
+        Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, i)
+    }
+
+    #[test]
+    fn build_and_read_srh() {
+        let mut data = [0; 8 + 2 * 16];
+        {
+            let mut testee = MutIpv6RoutingPacket::new(&mut data).unwrap();
+            testee.set_next_header(Protocol::UDP);
+            testee.set_hdr_ext_len(4);
+            testee.set_routing_type(RoutingType::SEGMENT_ROUTING);
+            testee.set_segments_left(1);
+            testee.set_last_entry(1);
+            assert!(testee.set_segment(0, segment(1)));
+            assert!(testee.set_segment(1, segment(2)));
+        }
+        assert_eq!(&[17, 4, 4, 1, 1, 0, 0, 0], &data[..8]);
+        assert_eq!(&segment(1).octets(), &data[8..24]);
+
+        let testee = Ipv6RoutingPacket::new(&data).unwrap();
+        assert_eq!(RoutingType::SEGMENT_ROUTING, testee.routing_type());
+        assert_eq!(40, testee.header_len());
+        assert_eq!(
+            vec![segment(1), segment(2)],
+            testee.segments().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(segment(2)), testee.segment(1));
+        assert_eq!(None, testee.segment(2));
+    }
+
+    #[test]
+    fn truncated_segment_list() {
+        let mut data = [0; 8 + 16 + 8];
+        data[1] = 4;
+        data[4] = 1;
+        let testee = Ipv6RoutingPacket::new(&data).unwrap();
+        assert_eq!(1, testee.segments().count());
+    }
+
+    #[test]
+    fn set_segment_out_of_bounds() {
+        let mut data = [0; 8 + 16];
+        assert!(!MutIpv6RoutingPacket::new(&mut data).unwrap().set_segment(1, segment(1)));
+        assert_eq!([0; 8 + 16], data);
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        Ipv6RoutingPacketBuf => |packet| packet.segments_left() == 0 && packet.segment(0).is_none()
+    );
+}
@@ -0,0 +1,917 @@
+//! Intermediate System to Intermediate System routing, ISO 10589 and RFC 1195.
+//!
+//! IS-IS PDUs are carried directly over the data link layer, in LLC frames with the `LLC_SAP`
+//! service access point. All PDUs start with the same eight byte header, followed by fixed
+//! fields depending on the PDU type and a list of TLVs. `IsisPacket` gives access to the header,
+//! and its conversion methods to views of the hello and link state PDUs. All views include the
+//! header, and honor the PDU length field when iterating over the TLVs.
+//!
+//! Only system IDs of the default length of six bytes are supported.
+
+use checksum;
+use ethernet::MacAddr;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::str;
+use types::*;
+
+/// The LLC service access point of IS-IS, used as both DSAP and SSAP.
+pub const LLC_SAP: u8 = 0xfe;
+
+/// The multicast address all level 1 intermediate systems listen on.
+pub const ALL_L1_ISS: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x14]);
+
+/// The multicast address all level 2 intermediate systems listen on.
+pub const ALL_L2_ISS: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x15]);
+
+/// The value of the intradomain routing protocol discriminator of IS-IS PDUs.
+pub const PROTOCOL_DISCRIMINATOR: u8 = 0x83;
+
+macro_rules! isis_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn protocol_discriminator(&self) -> u8 {
+                read_offset!(self.0, 0, u8)
+            }
+
+            /// Returns the length of the common header and the type specific fixed fields, in
+            /// bytes.
+            pub fn length_indicator(&self) -> u8 {
+                read_offset!(self.0, 1, u8)
+            }
+
+            pub fn version_protocol_id_extension(&self) -> u8 {
+                read_offset!(self.0, 2, u8)
+            }
+
+            /// Returns the length of system IDs, where zero means the default of six bytes.
+            pub fn id_length(&self) -> u8 {
+                read_offset!(self.0, 3, u8)
+            }
+
+            pub fn pdu_type(&self) -> PduType {
+                PduType(read_offset!(self.0, 4, u8) & 0x1f)
+            }
+
+            pub fn version(&self) -> u8 {
+                read_offset!(self.0, 5, u8)
+            }
+
+            /// Returns the number of area addresses supported, where zero means three.
+            pub fn maximum_area_addresses(&self) -> u8 {
+                read_offset!(self.0, 7, u8)
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_protocol_discriminator(&mut self, protocol_discriminator: u8) {
+                write_offset!(self.0, 0, protocol_discriminator, u8);
+            }
+
+            pub fn set_length_indicator(&mut self, length_indicator: u8) {
+                write_offset!(self.0, 1, length_indicator, u8);
+            }
+
+            pub fn set_version_protocol_id_extension(&mut self, extension: u8) {
+                write_offset!(self.0, 2, extension, u8);
+            }
+
+            pub fn set_id_length(&mut self, id_length: u8) {
+                write_offset!(self.0, 3, id_length, u8);
+            }
+
+            pub fn set_pdu_type(&mut self, pdu_type: PduType) {
+                write_offset!(self.0, 4, pdu_type.value() & 0x1f, u8);
+            }
+
+            pub fn set_version(&mut self, version: u8) {
+                write_offset!(self.0, 5, version, u8);
+            }
+
+            pub fn set_maximum_area_addresses(&mut self, maximum_area_addresses: u8) {
+                write_offset!(self.0, 7, maximum_area_addresses, u8);
+            }
+        );
+    }
+}
+
+/// Sets the fields of the common header in `data` that have only one valid value, and the type
+/// and length indicator of PDUs of type `pdu_type` with `len` bytes of header, if given.
+fn init_header(data: &mut [u8], pdu_type: Option<(PduType, u8)>) {
+    data[0] = PROTOCOL_DISCRIMINATOR;
+    write_offset!(data, 2, [1, 0], [u8; 2]);
+    data[5] = 1;
+    if let Some((pdu_type, len)) = pdu_type {
+        write_offset!(data, 1, len, u8);
+        write_offset!(data, 4, pdu_type.value(), u8);
+    }
+}
+
+packet!(
+    /// The header common to all IS-IS PDUs. Use one of the conversion methods to get a view of
+    /// the PDU type specific fields.
+    IsisPacket,
+    MutIsisPacket,
+    IsisPacketBuf,
+    8,
+    |_data| 8,
+    init |data| init_header(data, None)
+);
+isis_common!(IsisPacket, MutIsisPacket);
+
+impl<'a> IsisPacket<'a> {
+    /// Returns the PDU as a LAN hello, or `None` if it is of another type, too short or uses
+    /// system IDs of another length.
+    pub fn lan_hello(&self) -> Option<LanHelloPacket<'a>> {
+        self.as_type(&[PduType::L1_LAN_HELLO, PduType::L2_LAN_HELLO])
+            .and_then(LanHelloPacket::new)
+    }
+
+    /// Returns the PDU as a point-to-point hello, or `None` if it is of another type, too short
+    /// or uses system IDs of another length.
+    pub fn p2p_hello(&self) -> Option<P2pHelloPacket<'a>> {
+        self.as_type(&[PduType::P2P_HELLO]).and_then(P2pHelloPacket::new)
+    }
+
+    /// Returns the PDU as a link state PDU, or `None` if it is of another type, too short or
+    /// uses system IDs of another length.
+    pub fn lsp(&self) -> Option<LspPacket<'a>> {
+        self.as_type(&[PduType::L1_LSP, PduType::L2_LSP]).and_then(LspPacket::new)
+    }
+
+    fn as_type(&self, pdu_types: &[PduType]) -> Option<&'a [u8]> {
+        let id_length = self.id_length();
+        if pdu_types.contains(&self.pdu_type()) && (id_length == 0 || id_length == 6) {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the TLVs of the PDU in `data`, from `offset` up to the PDU length.
+fn tlv_data(data: &[u8], offset: usize, pdu_length: u16) -> &[u8] {
+    let end = data.len().min(usize::from(pdu_length));
+    data.get(offset..end).unwrap_or(&[])
+}
+
+
+packet!(
+    /// A hello PDU on a broadcast circuit. The payload holds the TLVs, see `tlvs`.
+    LanHelloPacket,
+    MutLanHelloPacket,
+    LanHelloPacketBuf,
+    27,
+    |_data| 27,
+    init |data| {
+        init_header(data, Some((PduType::L1_LAN_HELLO, 27)));
+        write_offset!(data, 17, data.len() as u16, u16, to_be);
+    }
+);
+isis_common!(LanHelloPacket, MutLanHelloPacket);
+
+getters!(LanHelloPacket
+    pub fn circuit_type(&self) -> CircuitType {
+        CircuitType(read_offset!(self.0, 8, u8) & 0x03)
+    }
+
+    pub fn source_id(&self) -> [u8; 6] {
+        read_offset!(self.0, 9, [u8; 6])
+    }
+
+    /// Returns the number of seconds without a hello before the sender is declared down.
+    pub fn holding_time(&self) -> u16 {
+        read_offset!(self.0, 15, u16, from_be)
+    }
+
+    /// Returns the length of the whole PDU in bytes, including the header.
+    pub fn pdu_length(&self) -> u16 {
+        read_offset!(self.0, 17, u16, from_be)
+    }
+
+    /// Returns the priority of the sender in the designated intermediate system election.
+    pub fn priority(&self) -> u7 {
+        read_offset!(self.0, 19, u8) & 0x7f
+    }
+
+    /// Returns the ID of the LAN, the system ID of the designated intermediate system followed
+    /// by its pseudonode ID.
+    pub fn lan_id(&self) -> [u8; 7] {
+        read_offset!(self.0, 20, [u8; 7])
+    }
+
+    /// Returns an iterator over the TLVs in the PDU.
+    pub fn tlvs(&self) -> Tlvs<'a> {
+        Tlvs { data: tlv_data(self.0, 27, self.pdu_length()) }
+    }
+);
+
+setters!(MutLanHelloPacket
+    pub fn set_circuit_type(&mut self, circuit_type: CircuitType) {
+        write_offset!(self.0, 8, circuit_type.value() & 0x03, u8);
+    }
+
+    pub fn set_source_id(&mut self, source_id: [u8; 6]) {
+        write_offset!(self.0, 9, source_id, [u8; 6]);
+    }
+
+    pub fn set_holding_time(&mut self, holding_time: u16) {
+        write_offset!(self.0, 15, holding_time, u16, to_be);
+    }
+
+    pub fn set_pdu_length(&mut self, pdu_length: u16) {
+        write_offset!(self.0, 17, pdu_length, u16, to_be);
+    }
+
+    pub fn set_priority(&mut self, priority: u7) {
+        write_offset!(self.0, 19, priority & 0x7f, u8);
+    }
+
+    pub fn set_lan_id(&mut self, lan_id: [u8; 7]) {
+        write_offset!(self.0, 20, lan_id, [u8; 7]);
+    }
+);
+
+
+packet!(
+    /// A hello PDU on a point-to-point circuit. The payload holds the TLVs, see `tlvs`.
+    P2pHelloPacket,
+    MutP2pHelloPacket,
+    P2pHelloPacketBuf,
+    20,
+    |_data| 20,
+    init |data| {
+        init_header(data, Some((PduType::P2P_HELLO, 20)));
+        write_offset!(data, 17, data.len() as u16, u16, to_be);
+    }
+);
+isis_common!(P2pHelloPacket, MutP2pHelloPacket);
+
+getters!(P2pHelloPacket
+    pub fn circuit_type(&self) -> CircuitType {
+        CircuitType(read_offset!(self.0, 8, u8) & 0x03)
+    }
+
+    pub fn source_id(&self) -> [u8; 6] {
+        read_offset!(self.0, 9, [u8; 6])
+    }
+
+    /// Returns the number of seconds without a hello before the sender is declared down.
+    pub fn holding_time(&self) -> u16 {
+        read_offset!(self.0, 15, u16, from_be)
+    }
+
+    /// Returns the length of the whole PDU in bytes, including the header.
+    pub fn pdu_length(&self) -> u16 {
+        read_offset!(self.0, 17, u16, from_be)
+    }
+
+    pub fn local_circuit_id(&self) -> u8 {
+        read_offset!(self.0, 19, u8)
+    }
+
+    /// Returns an iterator over the TLVs in the PDU.
+    pub fn tlvs(&self) -> Tlvs<'a> {
+        Tlvs { data: tlv_data(self.0, 20, self.pdu_length()) }
+    }
+);
+
+setters!(MutP2pHelloPacket
+    pub fn set_circuit_type(&mut self, circuit_type: CircuitType) {
+        write_offset!(self.0, 8, circuit_type.value() & 0x03, u8);
+    }
+
+    pub fn set_source_id(&mut self, source_id: [u8; 6]) {
+        write_offset!(self.0, 9, source_id, [u8; 6]);
+    }
+
+    pub fn set_holding_time(&mut self, holding_time: u16) {
+        write_offset!(self.0, 15, holding_time, u16, to_be);
+    }
+
+    pub fn set_pdu_length(&mut self, pdu_length: u16) {
+        write_offset!(self.0, 17, pdu_length, u16, to_be);
+    }
+
+    pub fn set_local_circuit_id(&mut self, local_circuit_id: u8) {
+        write_offset!(self.0, 19, local_circuit_id, u8);
+    }
+);
+
+
+packet!(
+    /// A link state PDU, describing the adjacencies and reachable prefixes of an intermediate
+    /// system. The payload holds the TLVs, see `tlvs`.
+    LspPacket,
+    MutLspPacket,
+    LspPacketBuf,
+    27,
+    |_data| 27,
+    init |data| {
+        init_header(data, Some((PduType::L1_LSP, 27)));
+        write_offset!(data, 8, data.len() as u16, u16, to_be);
+        if let Some(checksum) = LspPacket(data).calculate_checksum() {
+            write_offset!(data, 24, checksum, u16, to_be);
+        }
+    }
+);
+isis_common!(LspPacket, MutLspPacket);
+
+getters!(LspPacket
+    /// Returns the length of the whole PDU in bytes, including the header.
+    pub fn pdu_length(&self) -> u16 {
+        read_offset!(self.0, 8, u16, from_be)
+    }
+
+    /// Returns the number of seconds before the LSP expires.
+    pub fn remaining_lifetime(&self) -> u16 {
+        read_offset!(self.0, 10, u16, from_be)
+    }
+
+    /// Returns the ID of the LSP, the system ID of the originator followed by the pseudonode
+    /// ID and the fragment number.
+    pub fn lsp_id(&self) -> [u8; 8] {
+        read_offset!(self.0, 12, [u8; 8])
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    pub fn checksum(&self) -> u16 {
+        read_offset!(self.0, 24, u16, from_be)
+    }
+
+    pub fn flags(&self) -> LspFlags {
+        LspFlags::from_bits_truncate(read_offset!(self.0, 26, u8))
+    }
+
+    /// Returns the type of the originating intermediate system, from the low two bits of the
+    /// flags field.
+    pub fn is_type(&self) -> CircuitType {
+        CircuitType(read_offset!(self.0, 26, u8) & 0x03)
+    }
+
+    /// Returns an iterator over the TLVs in the PDU.
+    pub fn tlvs(&self) -> Tlvs<'a> {
+        Tlvs { data: tlv_data(self.0, 27, self.pdu_length()) }
+    }
+);
+
+impl<'a> LspPacket<'a> {
+    /// Computes the Fletcher checksum of the LSP, covering everything from the LSP ID up to the
+    /// PDU length. The backing slice must hold the entire PDU, and the current value of the
+    /// checksum field is ignored. Returns `None` if the PDU is truncated.
+    pub fn calculate_checksum(&self) -> Option<u16> {
+        let data = self.0.get(12..usize::from(self.pdu_length()))?;
+        Some(checksum::fletcher_checksum(data, 12))
+    }
+}
+
+setters!(MutLspPacket
+    pub fn set_pdu_length(&mut self, pdu_length: u16) {
+        write_offset!(self.0, 8, pdu_length, u16, to_be);
+    }
+
+    pub fn set_remaining_lifetime(&mut self, remaining_lifetime: u16) {
+        write_offset!(self.0, 10, remaining_lifetime, u16, to_be);
+    }
+
+    pub fn set_lsp_id(&mut self, lsp_id: [u8; 8]) {
+        write_offset!(self.0, 12, lsp_id, [u8; 8]);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 20, sequence_number, u32, to_be);
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        write_offset!(self.0, 24, checksum, u16, to_be);
+    }
+
+    pub fn set_flags(&mut self, flags: LspFlags) {
+        let new_byte = flags.bits() | (read_offset!(self.0, 26, u8) & 0x03);
+        write_offset!(self.0, 26, new_byte, u8);
+    }
+
+    pub fn set_is_type(&mut self, is_type: CircuitType) {
+        let new_byte = (read_offset!(self.0, 26, u8) & 0xfc) | (is_type.value() & 0x03);
+        write_offset!(self.0, 26, new_byte, u8);
+    }
+);
+
+
+/// The PDU type field of an IS-IS PDU.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PduType(pub u5);
+
+impl PduType {
+    pub const L1_LAN_HELLO: PduType = PduType(15);
+    pub const L2_LAN_HELLO: PduType = PduType(16);
+    pub const P2P_HELLO: PduType = PduType(17);
+    pub const L1_LSP: PduType = PduType(18);
+    pub const L2_LSP: PduType = PduType(20);
+    pub const L1_CSNP: PduType = PduType(24);
+    pub const L2_CSNP: PduType = PduType(25);
+    pub const L1_PSNP: PduType = PduType(26);
+    pub const L2_PSNP: PduType = PduType(27);
+
+    #[inline]
+    pub fn value(&self) -> u5 {
+        self.0
+    }
+}
+
+/// The routing levels of a circuit or an intermediate system.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CircuitType(pub u2);
+
+impl CircuitType {
+    pub const LEVEL_1: CircuitType = CircuitType(1);
+    pub const LEVEL_2: CircuitType = CircuitType(2);
+    pub const LEVEL_1_2: CircuitType = CircuitType(3);
+
+    #[inline]
+    pub fn value(&self) -> u2 {
+        self.0
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags of a link state PDU, other than the IS type in the low two bits.
+    pub struct LspFlags: u8 {
+        /// A bitmask with a one in the "Partition repair" position.
+        const PARTITION_REPAIR = 0x80;
+        /// A bitmask with a one in the "Attached, error metric" position.
+        const ATTACHED_ERROR = 0x40;
+        /// A bitmask with a one in the "Attached, expense metric" position.
+        const ATTACHED_EXPENSE = 0x20;
+        /// A bitmask with a one in the "Attached, delay metric" position.
+        const ATTACHED_DELAY = 0x10;
+        /// A bitmask with a one in the "Attached, default metric" position.
+        const ATTACHED_DEFAULT = 0x08;
+        /// A bitmask with a one in the "LSP database overload" position.
+        const OVERLOAD = 0x04;
+    }
+}
+
+/// The type of a TLV. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/isis-tlv-codepoints/isis-tlv-codepoints.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TlvType(pub u8);
+
+impl TlvType {
+    pub const AREA_ADDRESSES: TlvType = TlvType(1);
+    pub const IS_REACHABILITY: TlvType = TlvType(2);
+    pub const IS_NEIGHBORS: TlvType = TlvType(6);
+    pub const PADDING: TlvType = TlvType(8);
+    pub const LSP_ENTRIES: TlvType = TlvType(9);
+    pub const AUTHENTICATION: TlvType = TlvType(10);
+    pub const EXTENDED_IS_REACHABILITY: TlvType = TlvType(22);
+    pub const IP_INTERNAL_REACHABILITY: TlvType = TlvType(128);
+    pub const PROTOCOLS_SUPPORTED: TlvType = TlvType(129);
+    pub const IP_EXTERNAL_REACHABILITY: TlvType = TlvType(130);
+    pub const IP_INTERFACE_ADDRESS: TlvType = TlvType(132);
+    pub const TE_ROUTER_ID: TlvType = TlvType(134);
+    pub const EXTENDED_IP_REACHABILITY: TlvType = TlvType(135);
+    pub const DYNAMIC_HOSTNAME: TlvType = TlvType(137);
+    pub const IPV6_INTERFACE_ADDRESS: TlvType = TlvType(232);
+    pub const IPV6_REACHABILITY: TlvType = TlvType(236);
+    pub const P2P_ADJACENCY_STATE: TlvType = TlvType(240);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// A single TLV in an IS-IS PDU.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Tlv<'a> {
+    pub tlv_type: TlvType,
+    pub value: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Returns an iterator over the area addresses, or `None` if this is not an area addresses
+    /// TLV.
+    pub fn area_addresses(&self) -> Option<AreaAddresses<'a>> {
+        self.value_if(TlvType::AREA_ADDRESSES).map(|data| AreaAddresses { data })
+    }
+
+    /// Returns the NLPIDs of the supported network layer protocols, or `None` if this is not a
+    /// protocols supported TLV.
+    pub fn protocols_supported(&self) -> Option<&'a [u8]> {
+        self.value_if(TlvType::PROTOCOLS_SUPPORTED)
+    }
+
+    /// Returns an iterator over the IPv4 addresses of the interface, or `None` if this is not
+    /// an IP interface address TLV.
+    pub fn ip_interface_addresses(&self) -> Option<Ipv4Addresses<'a>> {
+        self.value_if(TlvType::IP_INTERFACE_ADDRESS).map(|data| Ipv4Addresses { data })
+    }
+
+    /// Returns an iterator over the LAN addresses of the neighbors heard on a broadcast
+    /// circuit, or `None` if this is not an IS neighbors TLV.
+    pub fn is_neighbors(&self) -> Option<MacAddrs<'a>> {
+        self.value_if(TlvType::IS_NEIGHBORS).map(|data| MacAddrs { data })
+    }
+
+    /// Returns the hostname of the originator, or `None` if this is not a dynamic hostname TLV
+    /// or the name is not valid UTF-8.
+    pub fn hostname(&self) -> Option<&'a str> {
+        self.value_if(TlvType::DYNAMIC_HOSTNAME)
+            .and_then(|data| str::from_utf8(data).ok())
+    }
+
+    fn value_if(&self, tlv_type: TlvType) -> Option<&'a [u8]> {
+        if self.tlv_type == tlv_type {
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+}
+
+/// Encodes a TLV into the beginning of `buffer`. Returns the number of bytes written, or `None`
+/// if the TLV does not fit in `buffer` or `value` is longer than 255 bytes.
+pub fn write_tlv(buffer: &mut [u8], tlv_type: TlvType, value: &[u8]) -> Option<usize> {
+    let len = 2 + value.len();
+    if value.len() > 255 || buffer.len() < len {
+        return None;
+    }
+    buffer[0] = tlv_type.value();
+    buffer[1] = value.len() as u8;
+    buffer[2..len].copy_from_slice(value);
+    Some(len)
+}
+
+/// Iterator over the TLVs in an IS-IS PDU. Iteration stops at the first TLV that does not fit
+/// in the data.
+#[derive(Clone)]
+pub struct Tlvs<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Tlvs<'a>);
+
+impl<'a> Iterator for Tlvs<'a> {
+    type Item = Tlv<'a>;
+
+    fn next(&mut self) -> Option<Tlv<'a>> {
+        if self.data.len() < 2 {
+            self.data = &[];
+            return None;
+        }
+        let len = 2 + usize::from(self.data[1]);
+        if len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (tlv, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(Tlv {
+            tlv_type: TlvType(tlv[0]),
+            value: &tlv[2..],
+        })
+    }
+}
+
+/// Iterator over the area addresses in an area addresses TLV, each given without its length
+/// byte. Iteration stops at the first address that does not fit in the data.
+#[derive(Clone)]
+pub struct AreaAddresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(AreaAddresses<'a>);
+
+impl<'a> Iterator for AreaAddresses<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let (&len, rest) = self.data.split_first()?;
+        let len = usize::from(len);
+        if len > rest.len() {
+            self.data = &[];
+            return None;
+        }
+        let (address, rest) = rest.split_at(len);
+        self.data = rest;
+        Some(address)
+    }
+}
+
+/// Iterator over a list of IPv4 addresses in a TLV. A trailing partial address is ignored.
+#[derive(Clone)]
+pub struct Ipv4Addresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ipv4Addresses<'a>);
+
+impl<'a> Iterator for Ipv4Addresses<'a> {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Ipv4Addr> {
+        if self.data.len() < 4 {
+            self.data = &[];
+            return None;
+        }
+        let (address, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(Ipv4Addr::from(read_offset!(address, 0, [u8; 4])))
+    }
+}
+
+/// Iterator over a list of MAC addresses in a TLV. A trailing partial address is ignored.
+#[derive(Clone)]
+pub struct MacAddrs<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(MacAddrs<'a>);
+
+impl<'a> Iterator for MacAddrs<'a> {
+    type Item = MacAddr;
+
+    fn next(&mut self) -> Option<MacAddr> {
+        if self.data.len() < 6 {
+            self.data = &[];
+            return None;
+        }
+        let (address, rest) = self.data.split_at(6);
+        self.data = rest;
+        Some(MacAddr(read_offset!(address, 0, [u8; 6])))
+    }
+}
+
+/// Formats a system ID, with the pseudonode and fragment number of a LAN or LSP ID following it,
+/// in the usual `1921.6800.1001.00-00` notation.
+struct SystemId<'a>(&'a [u8]);
+
+impl<'a> fmt::Display for SystemId<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (system, rest) = self.0.split_at(6);
+        for (i, pair) in system.chunks(2).enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{:02x}{:02x}", pair[0], pair[1])?;
+        }
+        for (i, byte) in rest.iter().enumerate() {
+            write!(f, "{}{:02x}", if i == 0 { '.' } else { '-' }, byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for IsisPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IS-IS type={}", self.pdu_type().value())
+    }
+}
+
+impl<'a> fmt::Display for LanHelloPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IS-IS LAN hello source={} lan={} priority={} holding_time={}",
+            SystemId(&self.source_id()),
+            SystemId(&self.lan_id()),
+            self.priority(),
+            self.holding_time()
+        )
+    }
+}
+
+impl<'a> fmt::Display for P2pHelloPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IS-IS P2P hello source={} holding_time={}",
+            SystemId(&self.source_id()),
+            self.holding_time()
+        )
+    }
+}
+
+impl<'a> fmt::Display for LspPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IS-IS LSP id={} seq={:#x} lifetime={}",
+            SystemId(&self.lsp_id()),
+            self.sequence_number(),
+            self.remaining_lifetime()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! isis_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIsisPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    isis_setget_test!(
+        protocol_discriminator,
+        set_protocol_discriminator,
+        0x83,
+        0,
+        [0x83]
+    );
+    isis_setget_test!(length_indicator, set_length_indicator, 27, 1, [27]);
+    isis_setget_test!(id_length, set_id_length, 6, 3, [6]);
+    isis_setget_test!(pdu_type, set_pdu_type, PduType::L2_PSNP, 4, [27]);
+    isis_setget_test!(version, set_version, 1, 5, [1]);
+    isis_setget_test!(maximum_area_addresses, set_maximum_area_addresses, 3, 7, [3]);
+
+    mod lan_hello {
+        use super::super::*;
+
+        macro_rules! lan_hello_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutLanHelloPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        lan_hello_setget_test!(
+            circuit_type,
+            set_circuit_type,
+            CircuitType::LEVEL_1_2,
+            8,
+            [3]
+        );
+        lan_hello_setget_test!(source_id, set_source_id, [1, 2, 3, 4, 5, 6], 9, [1, 2, 3, 4, 5, 6]);
+        lan_hello_setget_test!(holding_time, set_holding_time, 0xabcd, 15, [0xab, 0xcd]);
+        lan_hello_setget_test!(pdu_length, set_pdu_length, 0xabcd, 17, [0xab, 0xcd]);
+        lan_hello_setget_test!(priority, set_priority, 0x7f, 19, [0x7f]);
+        lan_hello_setget_test!(
+            lan_id,
+            set_lan_id,
+            [1, 2, 3, 4, 5, 6, 7],
+            20,
+            [1, 2, 3, 4, 5, 6, 7]
+        );
+    }
+
+    mod lsp {
+        use super::super::*;
+
+        macro_rules! lsp_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutLspPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        lsp_setget_test!(pdu_length, set_pdu_length, 0xabcd, 8, [0xab, 0xcd]);
+        lsp_setget_test!(remaining_lifetime, set_remaining_lifetime, 0xabcd, 10, [0xab, 0xcd]);
+        lsp_setget_test!(
+            lsp_id,
+            set_lsp_id,
+            [1, 2, 3, 4, 5, 6, 7, 8],
+            12,
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        lsp_setget_test!(
+            sequence_number,
+            set_sequence_number,
+            0x01020304,
+            20,
+            [1, 2, 3, 4]
+        );
+        lsp_setget_test!(checksum, set_checksum, 0xabcd, 24, [0xab, 0xcd]);
+        lsp_setget_test!(flags, set_flags, LspFlags::all(), 26, [0xfc]);
+        lsp_setget_test!(is_type, set_is_type, CircuitType::LEVEL_1_2, 26, [0x03]);
+    }
+
+    fn header(data: &mut [u8], pdu_type: PduType, length_indicator: u8) {
+        let mut testee = MutIsisPacket::new(data).unwrap();
+        testee.set_protocol_discriminator(PROTOCOL_DISCRIMINATOR);
+        testee.set_length_indicator(length_indicator);
+        testee.set_version_protocol_id_extension(1);
+        testee.set_pdu_type(pdu_type);
+        testee.set_version(1);
+    }
+
+    #[test]
+    fn lan_hello() {
+        let mut data = [0; 27 + 8 + 8 + 4];
+        header(&mut data, PduType::L1_LAN_HELLO, 27);
+        {
+            let mut testee = MutLanHelloPacket::new(&mut data).unwrap();
+            testee.set_circuit_type(CircuitType::LEVEL_1);
+            testee.set_source_id([0, 0, 0, 0, 0, 1]);
+            testee.set_holding_time(30);
+            testee.set_pdu_length(27 + 8 + 8);
+            testee.set_priority(64);
+        }
+        write_tlv(&mut data[27..], TlvType::AREA_ADDRESSES, &[3, 0x49, 0x00, 0x01, 1, 0x02])
+            .unwrap();
+        write_tlv(&mut data[35..], TlvType::IS_NEIGHBORS, &[2, 0, 0, 0, 0, 1]).unwrap();
+        // Not covered by the PDU length
+        data[43..].copy_from_slice(&[TlvType::PADDING.value(), 2, 0, 0]);
+
+        let isis = IsisPacket::new(&data).unwrap();
+        assert!(isis.lsp().is_none());
+        assert!(isis.p2p_hello().is_none());
+        let testee = isis.lan_hello().unwrap();
+        assert_eq!(CircuitType::LEVEL_1, testee.circuit_type());
+        assert_eq!(64, testee.priority());
+        let tlvs = testee.tlvs().collect::<Vec<_>>();
+        assert_eq!(2, tlvs.len());
+        let areas = tlvs[0].area_addresses().unwrap().collect::<Vec<_>>();
+        assert_eq!(vec![&[0x49, 0x00, 0x01][..], &[0x02][..]], areas);
+        let neighbors = tlvs[1].is_neighbors().unwrap().collect::<Vec<_>>();
+        assert_eq!(vec![MacAddr([2, 0, 0, 0, 0, 1])], neighbors);
+        assert!(tlvs[1].area_addresses().is_none());
+
+        // Longer system IDs are not supported
+        data[3] = 8;
+        assert!(IsisPacket::new(&data).unwrap().lan_hello().is_none());
+    }
+
+    #[test]
+    fn p2p_hello() {
+        let mut data = [0; 20 + 3];
+        header(&mut data, PduType::P2P_HELLO, 20);
+        {
+            let mut testee = MutP2pHelloPacket::new(&mut data).unwrap();
+            testee.set_circuit_type(CircuitType::LEVEL_2);
+            testee.set_pdu_length(23);
+            testee.set_local_circuit_id(7);
+        }
+        write_tlv(&mut data[20..], TlvType::PROTOCOLS_SUPPORTED, &[0xcc]).unwrap();
+        let testee = IsisPacket::new(&data).unwrap().p2p_hello().unwrap();
+        assert_eq!(7, testee.local_circuit_id());
+        let tlv = testee.tlvs().next().unwrap();
+        assert_eq!(Some(&[0xcc][..]), tlv.protocols_supported());
+    }
+
+    #[test]
+    fn lsp() {
+        let mut data = [0; 27 + 8 + 6 + 2];
+        header(&mut data, PduType::L2_LSP, 27);
+        {
+            let mut testee = MutLspPacket::new(&mut data).unwrap();
+            testee.set_pdu_length(27 + 8 + 6);
+            testee.set_remaining_lifetime(1200);
+            testee.set_lsp_id([0, 0, 0, 0, 0, 1, 0, 0]);
+            testee.set_sequence_number(1);
+            testee.set_flags(LspFlags::OVERLOAD);
+            testee.set_is_type(CircuitType::LEVEL_1_2);
+        }
+        write_tlv(&mut data[27..], TlvType::DYNAMIC_HOSTNAME, b"r1.lab").unwrap();
+        write_tlv(&mut data[35..], TlvType::IP_INTERFACE_ADDRESS, &[10, 0, 0, 1]).unwrap();
+        {
+            let checksum = LspPacket::new(&data).unwrap().calculate_checksum().unwrap();
+            MutLspPacket::new(&mut data).unwrap().set_checksum(checksum);
+        }
+        let (c0, c1) = data[12..41].iter().fold((0u32, 0u32), |(c0, c1), &byte| {
+            let c0 = (c0 + u32::from(byte)) % 255;
+            (c0, (c1 + c0) % 255)
+        });
+        assert_eq!((0, 0), (c0, c1));
+
+        let testee = IsisPacket::new(&data).unwrap().lsp().unwrap();
+        assert_eq!(LspFlags::OVERLOAD, testee.flags());
+        assert_eq!(CircuitType::LEVEL_1_2, testee.is_type());
+        let expected = "IS-IS LSP id=0000.0000.0001.00-00 seq=0x1 lifetime=1200";
+        assert_eq!(expected, testee.to_string());
+        // The remaining lifetime is not covered
+        let checksum = testee.checksum();
+        data[11] = 0;
+        let testee = LspPacket::new(&data).unwrap();
+        assert_eq!(Some(checksum), testee.calculate_checksum());
+        let tlvs = testee.tlvs().collect::<Vec<_>>();
+        assert_eq!(Some("r1.lab"), tlvs[0].hostname());
+        let addresses = tlvs[1].ip_interface_addresses().unwrap().collect::<Vec<_>>();
+        assert_eq!(vec![Ipv4Addr::new(10, 0, 0, 1)], addresses);
+        assert_eq!(None, LspPacket::new(&data[..40]).unwrap().calculate_checksum());
+    }
+
+    #[test]
+    fn truncated_tlv() {
+        let data = [1, 2, 0, 132, 8, 10, 0];
+        let mut tlvs = Tlvs { data: &data };
+        assert_eq!(Some(TlvType::AREA_ADDRESSES), tlvs.next().map(|tlv| tlv.tlv_type));
+        assert_eq!(None, tlvs.next());
+        let areas = AreaAddresses { data: &[1, 0x49, 3, 0] };
+        assert_eq!(1, areas.count());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        IsisPacketBuf => |packet| packet.protocol_discriminator() == PROTOCOL_DISCRIMINATOR,
+        LanHelloPacketBuf => |packet| IsisPacket::new(packet.data()).unwrap().lan_hello().is_some(),
+        P2pHelloPacketBuf => |packet| IsisPacket::new(packet.data()).unwrap().p2p_hello().is_some(),
+        LspPacketBuf => |packet| usize::from(packet.pdu_length()) == packet.data().len(),
+    );
+}
@@ -0,0 +1,641 @@
+//! Rendering dissected packets as JSON, enabled with the `json` feature.
+//!
+//! `ToJson` renders a packet as an object with a single key naming the protocol, holding the
+//! header fields. The payload is dissected further when the protocol and the next layer are
+//! known, and otherwise rendered as a hexadecimal string. An Ethernet frame carrying UDP thus
+//! renders as `{"ethernet":{..., "payload":{"ipv4":{..., "payload":{"udp":{...}}}}}}`.
+//!
+//! Every header `Layers` decodes renders this way, and so does a `Layer` itself. The bytes a
+//! `Layer::Payload` holds render as `{"payload":"..."}`.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use arp::ArpPacket;
+use ethernet::{EtherType, EthernetPacket};
+use gre::{self, GrePacket};
+use icmpv4::Icmpv4Packet;
+use icmpv6::Icmpv6Packet;
+use ip::Protocol;
+use ipv4::{self, Ipv4Packet};
+use ipv6::{
+    Ipv6DestinationOptionsPacket, Ipv6FragmentPacket, Ipv6HopByHopPacket, Ipv6Packet,
+    Ipv6RoutingPacket, RoutingType,
+};
+use layer::Layer;
+use mpls::MplsPacket;
+use std::fmt::{Display, Write};
+use tcp::{self, TcpPacket};
+use udp::UdpPacket;
+use vlan::VlanPacket;
+use vxlan::{self, VxlanPacket};
+
+/// A packet that can be rendered as a JSON object.
+pub trait ToJson {
+    /// Appends the JSON object representing this packet to `out`.
+    fn write_json(&self, out: &mut String);
+
+    /// Returns the JSON object representing this packet.
+    fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+}
+
+/// Writes the members of a JSON object with a single key, the name of a protocol.
+struct Object<'a> {
+    out: &'a mut String,
+    first: bool,
+    end: &'static str,
+}
+
+impl<'a> Object<'a> {
+    fn new(out: &'a mut String, protocol: &str) -> Object<'a> {
+        out.push_str("{\"");
+        out.push_str(protocol);
+        out.push_str("\":{");
+        Object { out, first: true, end: "}}" }
+    }
+
+    /// Starts a plain object, not wrapped in a protocol key, such as an element of an array.
+    fn plain(out: &'a mut String) -> Object<'a> {
+        out.push('{');
+        Object { out, first: true, end: "}" }
+    }
+
+    fn key(&mut self, key: &str) -> &mut String {
+        if !self.first {
+            self.out.push(',');
+        }
+        self.first = false;
+        self.out.push('"');
+        self.out.push_str(key);
+        self.out.push_str("\":");
+        self.out
+    }
+
+    fn number<T: Display>(&mut self, key: &str, value: T) -> &mut Self {
+        let _ = write!(self.key(key), "{}", value);
+        self
+    }
+
+    fn boolean(&mut self, key: &str, value: bool) -> &mut Self {
+        self.key(key).push_str(if value { "true" } else { "false" });
+        self
+    }
+
+    fn string<T: Display>(&mut self, key: &str, value: T) -> &mut Self {
+        write_string(self.key(key), &value.to_string());
+        self
+    }
+
+    fn strings<I>(&mut self, key: &str, values: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Display,
+    {
+        let out = self.key(key);
+        out.push('[');
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_string(out, &value.to_string());
+        }
+        out.push(']');
+        self
+    }
+
+    fn flags(&mut self, key: &str, names: &[&str]) -> &mut Self {
+        self.strings(key, names)
+    }
+
+    /// Writes an array of plain objects, one for each item, with the members `write` gives it.
+    fn objects<I, F>(&mut self, key: &str, items: I, mut write: F) -> &mut Self
+    where
+        I: IntoIterator,
+        F: FnMut(&mut Object, I::Item),
+    {
+        let out = self.key(key);
+        out.push('[');
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let mut object = Object::plain(out);
+            write(&mut object, item);
+            object.finish();
+        }
+        out.push(']');
+        self
+    }
+
+    fn hex(&mut self, key: &str, data: &[u8]) -> &mut Self {
+        let out = self.key(key);
+        out.push('"');
+        for byte in data {
+            let _ = write!(out, "{:02x}", byte);
+        }
+        out.push('"');
+        self
+    }
+
+    fn payload(&mut self, packet: Option<&dyn ToJson>, data: &[u8]) -> &mut Self {
+        match packet {
+            Some(packet) => packet.write_json(self.key("payload")),
+            None => {
+                self.hex("payload", data);
+            }
+        }
+        self
+    }
+
+    fn finish(&mut self) {
+        self.out.push_str(self.end);
+    }
+}
+
+/// Returns the names of the flags that `contains` reports as set, in the order given.
+fn flag_names<T, F>(names: &[(T, &'static str)], contains: F) -> Vec<&'static str>
+where
+    T: Copy,
+    F: Fn(T) -> bool,
+{
+    names.iter().filter(|&&(flag, _)| contains(flag)).map(|&(_, name)| name).collect()
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Writes the payload member, dissected as the protocol given by `ether_type` if it is known.
+fn with_ether_payload(ether_type: EtherType, data: &[u8], object: &mut Object) {
+    match ether_type {
+        EtherType::IPV4 => object.payload(Ipv4Packet::new(data).as_ref().map(as_dyn), data),
+        EtherType::IPV6 => object.payload(Ipv6Packet::new(data).as_ref().map(as_dyn), data),
+        EtherType::ARP => object.payload(ArpPacket::new(data).as_ref().map(as_dyn), data),
+        EtherType::VLAN | EtherType::QINQ => {
+            object.payload(VlanPacket::new(data).as_ref().map(as_dyn), data)
+        }
+        EtherType::MPLS | EtherType::MPLS_MULTICAST => {
+            object.payload(MplsPacket::new(data).as_ref().map(as_dyn), data)
+        }
+        EtherType::TRANSPARENT_ETHERNET_BRIDGING => {
+            object.payload(EthernetPacket::new(data).as_ref().map(as_dyn), data)
+        }
+        _ => object.payload(None, data),
+    };
+}
+
+/// Writes the payload member, dissected as IPv4 or IPv6 as told by its version field.
+fn with_ip_version_payload(data: &[u8], object: &mut Object) {
+    match data.first().map(|byte| byte >> 4) {
+        Some(4) => with_ip_payload(Protocol::IPV4, data, object),
+        Some(6) => with_ip_payload(Protocol::IPV6, data, object),
+        _ => {
+            object.payload(None, data);
+        }
+    }
+}
+
+fn with_ip_payload(protocol: Protocol, data: &[u8], object: &mut Object) {
+    match protocol {
+        Protocol::TCP => object.payload(TcpPacket::new(data).as_ref().map(as_dyn), data),
+        Protocol::UDP => object.payload(UdpPacket::new(data).as_ref().map(as_dyn), data),
+        Protocol::ICMP => object.payload(Icmpv4Packet::new(data).as_ref().map(as_dyn), data),
+        Protocol::ICMPV6 => object.payload(Icmpv6Packet::new(data).as_ref().map(as_dyn), data),
+        Protocol::IPV4 => object.payload(Ipv4Packet::new(data).as_ref().map(as_dyn), data),
+        Protocol::IPV6 => object.payload(Ipv6Packet::new(data).as_ref().map(as_dyn), data),
+        Protocol::HOPOPT => {
+            object.payload(Ipv6HopByHopPacket::new(data).as_ref().map(as_dyn), data)
+        }
+        Protocol::IPV6_ROUTE => {
+            object.payload(Ipv6RoutingPacket::new(data).as_ref().map(as_dyn), data)
+        }
+        Protocol::IPV6_FRAG => {
+            object.payload(Ipv6FragmentPacket::new(data).as_ref().map(as_dyn), data)
+        }
+        Protocol::IPV6_OPTS => {
+            object.payload(Ipv6DestinationOptionsPacket::new(data).as_ref().map(as_dyn), data)
+        }
+        Protocol::GRE => object.payload(GrePacket::new(data).as_ref().map(as_dyn), data),
+        _ => object.payload(None, data),
+    };
+}
+
+fn as_dyn<T: ToJson>(packet: &T) -> &dyn ToJson {
+    packet
+}
+
+impl<'a> ToJson for EthernetPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "ethernet");
+        object
+            .string("destination", self.destination())
+            .string("source", self.source())
+            .string("ether_type", self.ether_type());
+        with_ether_payload(self.ether_type(), self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for VlanPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "vlan");
+        object
+            .number("pcp", self.pcp())
+            .boolean("dei", self.dei())
+            .number("vid", self.vid())
+            .string("ether_type", self.ether_type());
+        with_ether_payload(self.ether_type(), self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for ArpPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out, "arp")
+            .number("hardware_type", self.hardware_type().value())
+            .string("protocol_type", self.protocol_type())
+            .number("operation", self.operation().value())
+            .string("sender_mac_addr", self.sender_mac_addr())
+            .string("sender_ip_addr", self.sender_ip_addr())
+            .string("target_mac_addr", self.target_mac_addr())
+            .string("target_ip_addr", self.target_ip_addr())
+            .finish();
+    }
+}
+
+impl<'a> ToJson for Ipv4Packet<'a> {
+    fn write_json(&self, out: &mut String) {
+        let flags = self.flags();
+        let names = [
+            (ipv4::Flags::RESERVED, "RESERVED"),
+            (ipv4::Flags::DF, "DF"),
+            (ipv4::Flags::MF, "MF"),
+        ];
+        let names = flag_names(&names, |flag| flags.contains(flag));
+        let mut object = Object::new(out, "ipv4");
+        object
+            .number("version", self.version())
+            .number("header_length", self.header_length())
+            .number("dscp", self.dscp())
+            .number("ecn", self.ecn())
+            .number("total_length", self.total_length())
+            .number("identification", self.identification())
+            .flags("flags", &names)
+            .number("fragment_offset", self.fragment_offset())
+            .number("ttl", self.ttl())
+            .string("protocol", self.protocol())
+            .number("header_checksum", self.header_checksum())
+            .string("source", self.source())
+            .string("destination", self.destination());
+        if self.fragment_offset() == 0 {
+            with_ip_payload(self.protocol(), self.payload(), &mut object);
+        } else {
+            object.payload(None, self.payload());
+        }
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for Ipv6Packet<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "ipv6");
+        object
+            .number("version", self.version())
+            .number("traffic_class", self.traffic_class())
+            .number("flow_label", self.flow_label())
+            .number("payload_length", self.payload_length())
+            .string("next_header", self.next_header())
+            .number("hop_limit", self.hop_limit())
+            .string("source", self.source())
+            .string("destination", self.destination());
+        with_ip_payload(self.next_header(), self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for MplsPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "mpls");
+        object.objects("entries", self.entries(), |object, entry| {
+            object
+                .number("label", entry.label())
+                .number("tc", entry.tc())
+                .boolean("bottom_of_stack", entry.bottom_of_stack())
+                .number("ttl", entry.ttl());
+        });
+        with_ip_version_payload(self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for Ipv6HopByHopPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "ipv6_hop_by_hop");
+        object
+            .string("next_header", self.next_header())
+            .number("hdr_ext_len", self.hdr_ext_len())
+            .hex("options", &self.header()[2..]);
+        with_ip_payload(self.next_header(), self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for Ipv6RoutingPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "ipv6_routing");
+        object
+            .string("next_header", self.next_header())
+            .number("hdr_ext_len", self.hdr_ext_len())
+            .number("routing_type", self.routing_type().value())
+            .number("segments_left", self.segments_left());
+        if self.routing_type() == RoutingType::SEGMENT_ROUTING {
+            object
+                .number("last_entry", self.last_entry())
+                .number("flags", self.flags())
+                .number("tag", self.tag())
+                .strings("segments", self.segments());
+        } else {
+            object.hex("data", &self.header()[4..]);
+        }
+        with_ip_payload(self.next_header(), self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for Ipv6FragmentPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "ipv6_fragment");
+        object
+            .string("next_header", self.next_header())
+            .number("fragment_offset", self.fragment_offset())
+            .boolean("more_fragments", self.more_fragments())
+            .number("identification", self.identification());
+        if self.fragment_offset() == 0 {
+            with_ip_payload(self.next_header(), self.payload(), &mut object);
+        } else {
+            object.payload(None, self.payload());
+        }
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for Ipv6DestinationOptionsPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "ipv6_destination_options");
+        object
+            .string("next_header", self.next_header())
+            .number("hdr_ext_len", self.hdr_ext_len())
+            .hex("options", &self.header()[2..]);
+        with_ip_payload(self.next_header(), self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for GrePacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let flags = self.flags();
+        let names = [
+            (gre::Flags::CHECKSUM, "CHECKSUM"),
+            (gre::Flags::ROUTING, "ROUTING"),
+            (gre::Flags::KEY, "KEY"),
+            (gre::Flags::SEQUENCE, "SEQUENCE"),
+        ];
+        let names = flag_names(&names, |flag| flags.contains(flag));
+        let mut object = Object::new(out, "gre");
+        object
+            .flags("flags", &names)
+            .number("version", self.version())
+            .string("protocol_type", self.protocol_type());
+        if let Some(checksum) = self.checksum() {
+            object.number("checksum", checksum);
+        }
+        if let Some(key) = self.key() {
+            object.number("key", key);
+        }
+        if let Some(sequence_number) = self.sequence_number() {
+            object.number("sequence_number", sequence_number);
+        }
+        with_ether_payload(self.protocol_type(), self.payload(), &mut object);
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for TcpPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let flags = self.flags();
+        let names = [
+            (tcp::Flags::NS, "NS"),
+            (tcp::Flags::CWR, "CWR"),
+            (tcp::Flags::ECE, "ECE"),
+            (tcp::Flags::URG, "URG"),
+            (tcp::Flags::ACK, "ACK"),
+            (tcp::Flags::PSH, "PSH"),
+            (tcp::Flags::RST, "RST"),
+            (tcp::Flags::SYN, "SYN"),
+            (tcp::Flags::FIN, "FIN"),
+        ];
+        let names = flag_names(&names, |flag| flags.contains(flag));
+        Object::new(out, "tcp")
+            .number("source_port", self.source_port())
+            .number("destination_port", self.destination_port())
+            .number("sequence_number", self.sequence_number())
+            .number("acknowledgment_number", self.acknowledgment_number())
+            .number("data_offset", self.data_offset())
+            .flags("flags", &names)
+            .number("window_size", self.window_size())
+            .number("checksum", self.checksum())
+            .number("urgent_pointer", self.urgent_pointer())
+            .hex("options", self.options_raw())
+            .payload(None, self.payload())
+            .finish();
+    }
+}
+
+impl<'a> ToJson for UdpPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = Object::new(out, "udp");
+        object
+            .number("source_port", self.source_port())
+            .number("destination_port", self.destination_port())
+            .number("length", self.length())
+            .number("checksum", self.checksum());
+        if self.destination_port() == vxlan::UDP_PORT {
+            object.payload(VxlanPacket::new(self.payload()).as_ref().map(as_dyn), self.payload());
+        } else {
+            object.payload(None, self.payload());
+        }
+        object.finish();
+    }
+}
+
+impl<'a> ToJson for VxlanPacket<'a> {
+    fn write_json(&self, out: &mut String) {
+        let flags = self.flags();
+        let names = flag_names(&[(vxlan::Flags::VNI, "VNI")], |flag| flags.contains(flag));
+        Object::new(out, "vxlan")
+            .flags("flags", &names)
+            .number("vni", self.vni())
+            .payload(EthernetPacket::new(self.payload()).as_ref().map(as_dyn), self.payload())
+            .finish();
+    }
+}
+
+impl<'a> ToJson for Icmpv4Packet<'a> {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out, "icmpv4")
+            .number("type", self.icmp_type().value())
+            .number("code", self.code())
+            .number("checksum", self.checksum())
+            .hex("rest_of_header", self.rest_of_header())
+            .payload(None, self.payload())
+            .finish();
+    }
+}
+
+impl<'a> ToJson for Icmpv6Packet<'a> {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out, "icmpv6")
+            .number("type", self.icmp_type().value())
+            .number("code", self.code())
+            .number("checksum", self.checksum())
+            .payload(None, self.payload())
+            .finish();
+    }
+}
+
+impl<'a> ToJson for Layer<'a> {
+    fn write_json(&self, out: &mut String) {
+        match *self {
+            Layer::Ethernet(ref packet) => packet.write_json(out),
+            Layer::Vlan(ref packet) => packet.write_json(out),
+            Layer::Arp(ref packet) => packet.write_json(out),
+            Layer::Mpls(ref packet) => packet.write_json(out),
+            Layer::Ipv4(ref packet) => packet.write_json(out),
+            Layer::Ipv6(ref packet) => packet.write_json(out),
+            Layer::Ipv6HopByHop(ref packet) => packet.write_json(out),
+            Layer::Ipv6Routing(ref packet) => packet.write_json(out),
+            Layer::Ipv6Fragment(ref packet) => packet.write_json(out),
+            Layer::Ipv6DestinationOptions(ref packet) => packet.write_json(out),
+            Layer::Gre(ref packet) => packet.write_json(out),
+            Layer::Tcp(ref packet) => packet.write_json(out),
+            Layer::Udp(ref packet) => packet.write_json(out),
+            Layer::Vxlan(ref packet) => packet.write_json(out),
+            Layer::Icmpv4(ref packet) => packet.write_json(out),
+            Layer::Icmpv6(ref packet) => packet.write_json(out),
+            Layer::Payload(data) => Object::plain(out).hex("payload", data).finish(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{EthernetBuilder, MacAddr};
+    use ipv4::Ipv4Builder;
+    use layer::Layers;
+    use std::net::Ipv4Addr;
+    use udp::UdpBuilder;
+
+    #[test]
+    fn nested_layers() {
+        let udp = UdpBuilder::new().source_port(1234).destination_port(53).payload(b"hi");
+        let ipv4 = Ipv4Builder::new()
+            .flags(ipv4::Flags::DF)
+            .source(Ipv4Addr::new(10, 0, 0, 1))
+            .destination(Ipv4Addr::new(10, 0, 0, 2))
+            .payload(udp);
+        let frame = EthernetBuilder::new()
+            .source(MacAddr([0x02, 0, 0, 0, 0, 0x01]))
+            .destination(MacAddr::BROADCAST)
+            .payload(ipv4)
+            .build()
+            .unwrap();
+        let expected = concat!(
+            r#"{"ethernet":{"destination":"ff:ff:ff:ff:ff:ff","source":"02:00:00:00:00:01","#,
+            r#""ether_type":"IPv4","payload":{"ipv4":{"version":4,"header_length":5,"dscp":0,"#,
+            r#""ecn":0,"total_length":30,"identification":0,"flags":["DF"],"#,
+            r#""fragment_offset":0,"ttl":64,"protocol":"UDP","header_checksum":9933,"#,
+            r#""source":"10.0.0.1","destination":"10.0.0.2","payload":{"udp":{"#,
+            r#""source_port":1234,"destination_port":53,"length":10,"checksum":32359,"#,
+            r#""payload":"6869"}}}}}}"#
+        );
+        assert_eq!(expected, EthernetPacket::new(&frame).unwrap().to_json());
+    }
+
+    #[test]
+    fn unknown_payload() {
+        let data = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x12, 0x34, 0xab];
+        assert_eq!(
+            concat!(
+                r#"{"ethernet":{"destination":"00:00:00:00:00:00","#,
+                r#""source":"00:00:00:00:00:00","ether_type":"0x1234","payload":"ab"}}"#
+            ),
+            EthernetPacket::new(&data).unwrap().to_json()
+        );
+    }
+
+    #[test]
+    fn vxlan_tunnel() {
+        let mut vxlan = vec![0x08, 0, 0, 0, 0, 0, 0x2a, 0];
+        vxlan.extend_from_slice(&[0xff; 12]);
+        vxlan.extend_from_slice(&[0x12, 0x34, 0xab]);
+        let datagram =
+            UdpBuilder::new().source_port(1).destination_port(vxlan::UDP_PORT).payload(vxlan);
+        let datagram = datagram.build().unwrap();
+        assert_eq!(
+            concat!(
+                r#"{"udp":{"source_port":1,"destination_port":4789,"length":31,"checksum":0,"#,
+                r#""payload":{"vxlan":{"flags":["VNI"],"vni":42,"payload":{"ethernet":{"#,
+                r#""destination":"ff:ff:ff:ff:ff:ff","source":"ff:ff:ff:ff:ff:ff","#,
+                r#""ether_type":"0x1234","payload":"ab"}}}}}}"#
+            ),
+            UdpPacket::new(&datagram).unwrap().to_json()
+        );
+    }
+
+    #[test]
+    fn mpls_ipv6_fragment() {
+        let mut data = vec![0x00, 0x01, 0x01, 0x40];
+        data.extend_from_slice(&[0x60, 0, 0, 0, 0, 10, 44, 64]);
+        data.extend_from_slice(&[0; 15]);
+        data.push(1);
+        data.extend_from_slice(&[0; 15]);
+        data.push(2);
+        data.extend_from_slice(&[17, 0, 0, 0x11, 0, 0, 0, 7, 0xab, 0xcd]);
+        let expected = concat!(
+            r#"{"mpls":{"entries":[{"label":16,"tc":0,"bottom_of_stack":true,"ttl":64}],"#,
+            r#""payload":{"ipv6":{"version":6,"traffic_class":0,"flow_label":0,"#,
+            r#""payload_length":10,"next_header":"IPv6-Frag","hop_limit":64,"source":"::1","#,
+            r#""destination":"::2","payload":{"ipv6_fragment":{"next_header":"UDP","#,
+            r#""fragment_offset":2,"more_fragments":true,"identification":7,"#,
+            r#""payload":"abcd"}}}}}}"#
+        );
+        assert_eq!(expected, MplsPacket::new(&data).unwrap().to_json());
+
+        let layers: Vec<String> = Layers::from_ip(&data[4..]).map(|l| l.to_json()).collect();
+        assert_eq!(3, layers.len());
+        assert_eq!(r#"{"payload":"abcd"}"#, layers[2]);
+    }
+
+    #[test]
+    fn escape() {
+        let mut out = String::new();
+        write_string(&mut out, "a\"b\\c\n");
+        assert_eq!(r#""a\"b\\c\u000a""#, out);
+    }
+}
@@ -0,0 +1,361 @@
+//! Layer Two Tunneling Protocol version 2, RFC 2661.
+
+use std::fmt;
+use types::*;
+
+/// The UDP port assigned to L2TP by IANA.
+pub const UDP_PORT: u16 = 1701;
+
+packet!(
+    /// An L2TPv2 header. Which optional fields are present is given by the flags, and the header
+    /// includes the offset padding, so the payload of a data message is the PPP frame.
+    L2tpPacket,
+    MutL2tpPacket,
+    L2tpPacketBuf,
+    6,
+    |data| header_len(data),
+    len |data| length_offset(data).map_or(data.len(), |offset| {
+        usize::from(read_offset!(data, offset, u16, from_be))
+    }),
+    set_len |data, len| match length_offset(data) {
+        Some(offset) => len <= usize::from(u16::MAX) && {
+            write_offset!(data, offset, len as u16, u16, to_be);
+            true
+        },
+        None => true,
+    },
+    init |data| {
+        // A version 2 data message without any of the optional fields.
+        data[0] &= Flags::PRIORITY.bits();
+        data[1] = 2;
+    }
+);
+
+getters!(L2tpPacket
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 0, u8))
+    }
+
+    pub fn version(&self) -> u4 {
+        read_offset!(self.0, 1, u8) & 0x0f
+    }
+
+    /// Returns the total length of the message in bytes, or `None` if the length bit is not set
+    /// or the field does not fit in the backing slice.
+    pub fn length(&self) -> Option<u16> {
+        self.optional_field(Field::Length)
+    }
+
+    /// Returns the tunnel ID, or `None` if it does not fit in the backing slice.
+    pub fn tunnel_id(&self) -> Option<u16> {
+        self.optional_field(Field::TunnelId)
+    }
+
+    /// Returns the session ID, or `None` if it does not fit in the backing slice.
+    pub fn session_id(&self) -> Option<u16> {
+        self.optional_field(Field::SessionId)
+    }
+
+    /// Returns the sequence number of this message, or `None` if the sequence bit is not set or
+    /// the field does not fit in the backing slice.
+    pub fn ns(&self) -> Option<u16> {
+        self.optional_field(Field::Ns)
+    }
+
+    /// Returns the sequence number expected in the next message, or `None` if the sequence bit
+    /// is not set or the field does not fit in the backing slice.
+    pub fn nr(&self) -> Option<u16> {
+        self.optional_field(Field::Nr)
+    }
+
+    /// Returns the number of padding bytes between the header and the payload, or `None` if the
+    /// offset bit is not set or the field does not fit in the backing slice.
+    pub fn offset_size(&self) -> Option<u16> {
+        self.optional_field(Field::OffsetSize)
+    }
+);
+
+impl<'a> L2tpPacket<'a> {
+    /// Returns true for control messages, false for data messages.
+    pub fn is_control(&self) -> bool {
+        self.flags().contains(Flags::TYPE)
+    }
+
+    fn optional_field(&self, field: Field) -> Option<u16> {
+        let offset = field_offset(self.flags(), field)?;
+        self.0.get(offset..offset + 2).map(|field| read_offset!(field, 0, u16, from_be))
+    }
+}
+
+setters!(MutL2tpPacket
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_offset!(self.0, 0, flags.bits(), u8);
+    }
+
+    pub fn set_version(&mut self, version: u4) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xf0) | (version & 0x0f);
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+);
+
+impl<'a> MutL2tpPacket<'a> {
+    /// Sets the length field. Returns false, leaving the packet unchanged, if the length bit is not
+    /// set, or if the field does not fit in the backing slice. Set the flags first.
+    pub fn set_length(&mut self, length: u16) -> bool {
+        self.set_optional_field(Field::Length, length)
+    }
+
+    /// Sets the tunnel ID. Returns false if the field does not fit in the backing slice.
+    pub fn set_tunnel_id(&mut self, tunnel_id: u16) -> bool {
+        self.set_optional_field(Field::TunnelId, tunnel_id)
+    }
+
+    /// Sets the session ID. Returns false if the field does not fit in the backing slice.
+    pub fn set_session_id(&mut self, session_id: u16) -> bool {
+        self.set_optional_field(Field::SessionId, session_id)
+    }
+
+    /// Sets the sequence number of this message. Returns false, leaving the packet unchanged, if
+    /// the sequence bit is not set, or if the field does not fit in the backing slice. Set the
+    /// flags first.
+    pub fn set_ns(&mut self, ns: u16) -> bool {
+        self.set_optional_field(Field::Ns, ns)
+    }
+
+    /// Sets the sequence number expected in the next message. Returns false, leaving the packet
+    /// unchanged, if the sequence bit is not set, or if the field does not fit in the backing
+    /// slice. Set the flags first.
+    pub fn set_nr(&mut self, nr: u16) -> bool {
+        self.set_optional_field(Field::Nr, nr)
+    }
+
+    /// Sets the offset size. The padding itself is not touched. Returns false, leaving the packet
+    /// unchanged, if the offset bit is not set, or if the field does not fit in the backing slice.
+    /// Set the flags first.
+    pub fn set_offset_size(&mut self, offset_size: u16) -> bool {
+        self.set_optional_field(Field::OffsetSize, offset_size)
+    }
+
+    fn set_optional_field(&mut self, field: Field, value: u16) -> bool {
+        let flags = self.as_immutable().flags();
+        match field_offset(flags, field).and_then(|offset| self.0.get_mut(offset..offset + 2)) {
+            Some(field) => {
+                write_offset!(field, 0, value, u16, to_be);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The two byte fields following the flags and version, in the order they appear in the header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Field {
+    Length,
+    TunnelId,
+    SessionId,
+    Ns,
+    Nr,
+    OffsetSize,
+}
+
+impl Field {
+    const ALL: [Field; 6] = [
+        Field::Length,
+        Field::TunnelId,
+        Field::SessionId,
+        Field::Ns,
+        Field::Nr,
+        Field::OffsetSize,
+    ];
+
+    fn is_present(self, flags: Flags) -> bool {
+        match self {
+            Field::Length => flags.contains(Flags::LENGTH),
+            Field::TunnelId | Field::SessionId => true,
+            Field::Ns | Field::Nr => flags.contains(Flags::SEQUENCE),
+            Field::OffsetSize => flags.contains(Flags::OFFSET),
+        }
+    }
+}
+
+/// Returns the offset of `field`, or `None` if it is not present according to `flags`.
+fn field_offset(flags: Flags, field: Field) -> Option<usize> {
+    if !field.is_present(flags) {
+        return None;
+    }
+    let preceding = Field::ALL
+        .iter()
+        .take_while(|&&other| other != field)
+        .filter(|other| other.is_present(flags))
+        .count();
+    Some(2 + preceding * 2)
+}
+
+/// Returns the offset of the length field of the header at the start of `data`, or `None` if it
+/// has none.
+fn length_offset(data: &[u8]) -> Option<usize> {
+    field_offset(Flags::from_bits_truncate(data[0]), Field::Length)
+}
+
+/// Returns the length of the header at the start of `data`, including the offset padding.
+fn header_len(data: &[u8]) -> usize {
+    let flags = Flags::from_bits_truncate(data[0]);
+    let fields = Field::ALL.iter().filter(|field| field.is_present(flags)).count();
+    let padding = field_offset(flags, Field::OffsetSize)
+        .and_then(|offset| data.get(offset..offset + 2))
+        .map_or(0, |field| usize::from(read_offset!(field, 0, u16, from_be)));
+    2 + fields * 2 + padding
+}
+
+
+bitflags! {
+    /// Bitmasks for the flags in the first byte of an L2TP header.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "Type" position, set for control messages.
+        const TYPE = 0b1000_0000;
+        /// A bitmask with a one in the "Length" position.
+        const LENGTH = 0b0100_0000;
+        /// A bitmask with a one in the "Sequence" position.
+        const SEQUENCE = 0b0000_1000;
+        /// A bitmask with a one in the "Offset" position.
+        const OFFSET = 0b0000_0010;
+        /// A bitmask with a one in the "Priority" position.
+        const PRIORITY = 0b0000_0001;
+    }
+}
+
+impl<'a> fmt::Display for L2tpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.is_control() { "control" } else { "data" };
+        write!(f, "L2TPv{} {}", self.version(), kind)?;
+        if let (Some(tunnel_id), Some(session_id)) = (self.tunnel_id(), self.session_id()) {
+            write!(f, " tunnel={} session={}", tunnel_id, session_id)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! l2tp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutL2tpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    l2tp_setget_test!(flags, set_flags, Flags::all(), 0, [0xcb]);
+    l2tp_setget_test!(version, set_version, 0xf, 1, [0x0f]);
+
+    #[test]
+    fn minimal_data_message() {
+        let data = [0x00, 0x02, 0x00, 0x01, 0x00, 0x02, 0xff, 0x03];
+        let testee = L2tpPacket::new(&data).unwrap();
+        assert!(!testee.is_control());
+        assert_eq!(2, testee.version());
+        assert_eq!(6, testee.header_len());
+        assert_eq!(None, testee.length());
+        assert_eq!(Some(1), testee.tunnel_id());
+        assert_eq!(Some(2), testee.session_id());
+        assert_eq!(None, testee.ns());
+        assert_eq!(None, testee.offset_size());
+        assert_eq!(&[0xff, 0x03], testee.payload());
+    }
+
+    #[test]
+    fn all_optional_fields() {
+        let mut data = [0; 16 + 2 + 1];
+        {
+            let mut testee = MutL2tpPacket::new(&mut data).unwrap();
+            testee.set_flags(Flags::LENGTH | Flags::SEQUENCE | Flags::OFFSET);
+            testee.set_version(2);
+            assert!(testee.set_length(19));
+            assert!(testee.set_tunnel_id(10));
+            assert!(testee.set_session_id(20));
+            assert!(testee.set_ns(3));
+            assert!(testee.set_nr(4));
+            assert!(testee.set_offset_size(2));
+        }
+        assert_eq!(
+            &[0x4a, 0x02, 0, 19, 0, 10, 0, 20, 0, 3, 0, 4, 0, 2],
+            &data[..14]
+        );
+        let testee = L2tpPacket::new(&data).unwrap();
+        assert_eq!(Some(19), testee.length());
+        assert_eq!(Some(10), testee.tunnel_id());
+        assert_eq!(Some(20), testee.session_id());
+        assert_eq!(Some(3), testee.ns());
+        assert_eq!(Some(4), testee.nr());
+        assert_eq!(Some(2), testee.offset_size());
+        assert_eq!(16, testee.header_len());
+        assert_eq!(3, testee.payload().len());
+        assert_eq!("L2TPv2 data tunnel=10 session=20", testee.to_string());
+    }
+
+    #[test]
+    fn control_message_with_length() {
+        let data = [0xc8, 0x02, 0x00, 0x0c, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let testee = L2tpPacket::new(&data).unwrap();
+        assert!(testee.is_control());
+        assert_eq!(Some(12), testee.length());
+        assert_eq!(Some(5), testee.tunnel_id());
+        assert_eq!(Some(1), testee.nr());
+    }
+
+    #[test]
+    fn truncated_fields() {
+        let data = [0x48, 0x02, 0x00, 0x0c, 0x00, 0x05, 0x00];
+        let testee = L2tpPacket::new(&data).unwrap();
+        assert_eq!(Some(5), testee.tunnel_id());
+        assert_eq!(None, testee.session_id());
+        assert_eq!(None, testee.ns());
+    }
+
+    #[test]
+    fn set_absent_fields() {
+        let mut data = [0; 8];
+        let mut testee = MutL2tpPacket::new(&mut data).unwrap();
+        assert!(!testee.set_length(8));
+        assert!(!testee.set_ns(1));
+        assert!(!testee.set_nr(1));
+        assert!(!testee.set_offset_size(1));
+        assert_eq!([0; 8], data);
+    }
+
+    #[test]
+    fn set_truncated_fields() {
+        let mut data = [0x48, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut testee = MutL2tpPacket::new(&mut data[..7]).unwrap();
+        assert!(testee.set_tunnel_id(5));
+        assert!(!testee.set_session_id(6));
+        let mut testee = MutL2tpPacket::new(&mut data[..11]).unwrap();
+        assert!(testee.set_session_id(6));
+        assert!(testee.set_ns(7));
+        assert!(!testee.set_nr(8));
+        assert_eq!([0x48, 0x02, 0, 0, 0, 5, 0, 6, 0, 7, 0, 0], data);
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 8 + 4];
+        data[0] = Flags::LENGTH.bits();
+        let mut testee = MutL2tpPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[0xff, 0x03]));
+        assert_eq!(Some(10), testee.as_immutable().length());
+        assert_eq!(&[0xff, 0x03], testee.as_immutable().payload());
+
+        // Without a length field the payload still ends with the slice
+        let mut data = [0; 6 + 4];
+        let mut testee = MutL2tpPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[0xff, 0x03]));
+        assert_eq!(4, testee.as_immutable().payload().len());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        L2tpPacketBuf => |packet| packet.version() == 2 && packet.tunnel_id().is_some()
+    );
+}
@@ -0,0 +1,293 @@
+//! The Link Aggregation Control Protocol, IEEE 802.3ad. LACPDUs are sent as slow protocol frames,
+//! using `EtherType::SLOW_PROTOCOLS` and the `SLOW_PROTOCOLS_MULTICAST` destination.
+
+use ethernet::MacAddr;
+use std::fmt;
+
+/// The destination address of all slow protocol frames.
+pub const SLOW_PROTOCOLS_MULTICAST: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x02]);
+
+/// The slow protocol subtype identifying LACP.
+pub const SUBTYPE_LACP: u8 = 1;
+
+packet!(
+    /// A Link Aggregation Control Protocol data unit, version 1.
+    LacpPacket,
+    MutLacpPacket,
+    LacpPacketBuf,
+    110,
+    |_data| 110,
+    init |data| MutLacpPacket(data).set_lacp_defaults()
+);
+
+getters!(LacpPacket
+    pub fn subtype(&self) -> u8 {
+        read_offset!(self.0, 0, u8)
+    }
+
+    pub fn version(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns the information the sender has about its own end of the link.
+    pub fn actor(&self) -> PortInfoPacket<'a> {
+        PortInfoPacket(&self.0[2..22])
+    }
+
+    /// Returns the information the sender has about the other end of the link.
+    pub fn partner(&self) -> PortInfoPacket<'a> {
+        PortInfoPacket(&self.0[22..42])
+    }
+
+    /// Returns the maximum delay, in tens of microseconds, between receiving a frame and
+    /// delivering it or discarding it.
+    pub fn collector_max_delay(&self) -> u16 {
+        read_offset!(self.0, 44, u16, from_be)
+    }
+);
+
+setters!(MutLacpPacket
+    pub fn set_subtype(&mut self, subtype: u8) {
+        write_offset!(self.0, 0, subtype, u8);
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        write_offset!(self.0, 1, version, u8);
+    }
+
+    pub fn set_collector_max_delay(&mut self, collector_max_delay: u16) {
+        write_offset!(self.0, 44, collector_max_delay, u16, to_be);
+    }
+);
+
+impl<'a> MutLacpPacket<'a> {
+    /// Returns the actor information for modification.
+    pub fn actor(&mut self) -> MutPortInfoPacket<'_> {
+        MutPortInfoPacket(&mut self.0[2..22])
+    }
+
+    /// Returns the partner information for modification.
+    pub fn partner(&mut self) -> MutPortInfoPacket<'_> {
+        MutPortInfoPacket(&mut self.0[22..42])
+    }
+
+    /// Sets the subtype and version, and the type and length of every TLV, to the values of a
+    /// version 1 LACPDU. The reserved fields are zeroed, the information fields are left as is.
+    pub fn set_lacp_defaults(&mut self) {
+        self.set_subtype(SUBTYPE_LACP);
+        self.set_version(1);
+        for &(offset, tlv_type, len) in &[(2, 1, 20), (22, 2, 20), (42, 3, 16), (58, 0, 0)] {
+            self.0[offset] = tlv_type;
+            self.0[offset + 1] = len;
+        }
+        for reserved in &[19..22, 39..42, 46..58, 60..110] {
+            for byte in &mut self.0[reserved.clone()] {
+                *byte = 0;
+            }
+        }
+    }
+}
+
+
+packet!(
+    /// The actor or partner information TLV of an LACPDU, describing one end of a link.
+    PortInfoPacket,
+    MutPortInfoPacket,
+    PortInfoPacketBuf,
+    20,
+    |_data| 20,
+    init |data| write_offset!(data, 0, [1, 20], [u8; 2])
+);
+
+getters!(PortInfoPacket
+    pub fn tlv_type(&self) -> u8 {
+        read_offset!(self.0, 0, u8)
+    }
+
+    pub fn length(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    pub fn system_priority(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    pub fn system(&self) -> MacAddr {
+        MacAddr::from_slice(&self.0[4..10])
+    }
+
+    pub fn key(&self) -> u16 {
+        read_offset!(self.0, 10, u16, from_be)
+    }
+
+    pub fn port_priority(&self) -> u16 {
+        read_offset!(self.0, 12, u16, from_be)
+    }
+
+    pub fn port(&self) -> u16 {
+        read_offset!(self.0, 14, u16, from_be)
+    }
+
+    pub fn state(&self) -> State {
+        State::from_bits_truncate(read_offset!(self.0, 16, u8))
+    }
+);
+
+setters!(MutPortInfoPacket
+    pub fn set_tlv_type(&mut self, tlv_type: u8) {
+        write_offset!(self.0, 0, tlv_type, u8);
+    }
+
+    pub fn set_length(&mut self, length: u8) {
+        write_offset!(self.0, 1, length, u8);
+    }
+
+    pub fn set_system_priority(&mut self, system_priority: u16) {
+        write_offset!(self.0, 2, system_priority, u16, to_be);
+    }
+
+    pub fn set_system(&mut self, system: MacAddr) {
+        self.0[4..10].copy_from_slice(system.as_ref());
+    }
+
+    pub fn set_key(&mut self, key: u16) {
+        write_offset!(self.0, 10, key, u16, to_be);
+    }
+
+    pub fn set_port_priority(&mut self, port_priority: u16) {
+        write_offset!(self.0, 12, port_priority, u16, to_be);
+    }
+
+    pub fn set_port(&mut self, port: u16) {
+        write_offset!(self.0, 14, port, u16, to_be);
+    }
+
+    pub fn set_state(&mut self, state: State) {
+        write_offset!(self.0, 16, state.bits(), u8);
+    }
+);
+
+
+bitflags! {
+    /// Bitmasks for the state field of the actor and partner information.
+    pub struct State: u8 {
+        /// Active LACP, as opposed to only responding to the partner.
+        const LACP_ACTIVITY = 0b0000_0001;
+        /// Short timeout, asking the partner to transmit every second instead of every 30.
+        const LACP_TIMEOUT = 0b0000_0010;
+        /// The link may be aggregated with others.
+        const AGGREGATION = 0b0000_0100;
+        /// The link is allocated to the right aggregator.
+        const SYNCHRONIZATION = 0b0000_1000;
+        /// Collection of incoming frames is enabled.
+        const COLLECTING = 0b0001_0000;
+        /// Distribution of outgoing frames is enabled.
+        const DISTRIBUTING = 0b0010_0000;
+        /// The partner information is administrative defaults, not received from the partner.
+        const DEFAULTED = 0b0100_0000;
+        /// The receive state machine is in the expired state.
+        const EXPIRED = 0b1000_0000;
+    }
+}
+
+impl<'a> fmt::Display for LacpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LACP actor={}/{} partner={}/{}",
+            self.actor().system(),
+            self.actor().port(),
+            self.partner().system(),
+            self.partner().port()
+        )
+    }
+}
+
+impl<'a> fmt::Display for PortInfoPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LACP port system={} key={} port={} state={:#04x}",
+            self.system(),
+            self.key(),
+            self.port(),
+            self.state().bits()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static MAC: [u8; 6] = [0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e];
+
+    macro_rules! lacp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutLacpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    lacp_setget_test!(subtype, set_subtype, 0xab, 0, [0xab]);
+    lacp_setget_test!(version, set_version, 0xab, 1, [0xab]);
+    lacp_setget_test!(collector_max_delay, set_collector_max_delay, 0xabcd, 44, [0xab, 0xcd]);
+
+    mod port_info {
+        use super::super::*;
+        use super::MAC;
+
+        macro_rules! port_info_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutPortInfoPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        port_info_setget_test!(tlv_type, set_tlv_type, 0xab, 0, [0xab]);
+        port_info_setget_test!(length, set_length, 0xab, 1, [0xab]);
+        port_info_setget_test!(system_priority, set_system_priority, 0xabcd, 2, [0xab, 0xcd]);
+        port_info_setget_test!(system, set_system, MacAddr(MAC), 4, MAC);
+        port_info_setget_test!(key, set_key, 0xabcd, 10, [0xab, 0xcd]);
+        port_info_setget_test!(port_priority, set_port_priority, 0xabcd, 12, [0xab, 0xcd]);
+        port_info_setget_test!(port, set_port, 0xabcd, 14, [0xab, 0xcd]);
+        port_info_setget_test!(state, set_state, State::all(), 16, [0xff]);
+    }
+
+    #[test]
+    fn actor_and_partner() {
+        let mut data = [0xff; 110];
+        {
+            let mut testee = MutLacpPacket::new(&mut data).unwrap();
+            testee.set_lacp_defaults();
+            testee.actor().set_system(MacAddr(MAC));
+            testee.actor().set_key(13);
+            testee
+                .actor()
+                .set_state(State::LACP_ACTIVITY | State::AGGREGATION | State::SYNCHRONIZATION);
+            testee.partner().set_port(7);
+        }
+        assert_eq!(&[0x01, 0x01, 0x01, 0x14], &data[..4]);
+        assert_eq!(&[0x00; 3], &data[19..22]);
+        assert_eq!(&[0x02, 0x14], &data[22..24]);
+        assert_eq!(&[0x03, 0x10, 0xff, 0xff, 0x00], &data[42..47]);
+        assert_eq!(&[0x00; 52], &data[58..]);
+
+        let testee = LacpPacket::new(&data).unwrap();
+        assert_eq!(SUBTYPE_LACP, testee.subtype());
+        assert_eq!(1, testee.actor().tlv_type());
+        assert_eq!(20, testee.actor().length());
+        assert_eq!(MacAddr(MAC), testee.actor().system());
+        assert_eq!(13, testee.actor().key());
+        assert!(testee.actor().state().contains(State::SYNCHRONIZATION));
+        assert!(!testee.actor().state().contains(State::COLLECTING));
+        assert_eq!(2, testee.partner().tlv_type());
+        assert_eq!(7, testee.partner().port());
+        assert_eq!(0xffff, testee.collector_max_delay());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        LacpPacketBuf => |packet| packet.version() == 1 && packet.actor().length() == 20,
+        PortInfoPacketBuf => |packet| packet.length() == 20,
+    );
+}
@@ -0,0 +1,324 @@
+//! Iterating over the layers of a frame. `Layers` decodes one header at a time, picking the
+//! protocol of the next header from the EtherType, IP protocol or well known port of the
+//! current one, and yields each as a `Layer`. This makes it possible to write generic code
+//! that looks for a layer anywhere in a frame, including inside tunnels:
+//!
+//! ```rust
+//! extern crate rips_packets;
+//!
+//! use rips_packets::ethernet::EthernetBuilder;
+//! use rips_packets::ipv4::Ipv4Builder;
+//! use rips_packets::layer::{Layer, Layers};
+//! use rips_packets::udp::UdpBuilder;
+//!
+//! fn main() {
+//!     let udp = UdpBuilder::new().destination_port(53).payload(b"query");
+//!     let ipv4 = Ipv4Builder::new().payload(udp);
+//!     let frame = EthernetBuilder::new().payload(ipv4).build().unwrap();
+//!
+//!     let is_dns = Layers::from_ethernet(&frame).any(|layer| match layer {
+//!         Layer::Udp(udp) => udp.destination_port() == 53,
+//!         _ => false,
+//!     });
+//!     assert!(is_dns);
+//! }
+//! ```
+
+use arp::ArpPacket;
+use ethernet::{EtherType, EthernetPacket};
+use gre::GrePacket;
+use icmpv4::Icmpv4Packet;
+use icmpv6::Icmpv6Packet;
+use ip::Protocol;
+use ipv4::Ipv4Packet;
+use ipv6::{
+    Ipv6DestinationOptionsPacket, Ipv6FragmentPacket, Ipv6HopByHopPacket, Ipv6Packet,
+    Ipv6RoutingPacket,
+};
+use mpls::MplsPacket;
+use tcp::TcpPacket;
+use udp::UdpPacket;
+use vlan::VlanPacket;
+use vxlan::{self, VxlanPacket};
+
+/// One header of a frame, as yielded by `Layers`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Layer<'a> {
+    Ethernet(EthernetPacket<'a>),
+    Vlan(VlanPacket<'a>),
+    Arp(ArpPacket<'a>),
+    Mpls(MplsPacket<'a>),
+    Ipv4(Ipv4Packet<'a>),
+    Ipv6(Ipv6Packet<'a>),
+    Ipv6HopByHop(Ipv6HopByHopPacket<'a>),
+    Ipv6Routing(Ipv6RoutingPacket<'a>),
+    Ipv6Fragment(Ipv6FragmentPacket<'a>),
+    Ipv6DestinationOptions(Ipv6DestinationOptionsPacket<'a>),
+    Gre(GrePacket<'a>),
+    Tcp(TcpPacket<'a>),
+    Udp(UdpPacket<'a>),
+    Vxlan(VxlanPacket<'a>),
+    Icmpv4(Icmpv4Packet<'a>),
+    Icmpv6(Icmpv6Packet<'a>),
+    /// The bytes after the last header that could be decoded. Never empty.
+    Payload(&'a [u8]),
+}
+
+/// What the data of a `Layers` iterator is expected to start with.
+#[derive(Debug, Copy, Clone)]
+enum Next {
+    Ethernet,
+    /// A header identified by an EtherType. For VLAN tags the data starts at the TPID.
+    EtherType(EtherType),
+    /// An IPv4 or IPv6 header, told apart by the version field.
+    Ip,
+    Protocol(Protocol),
+    Vxlan,
+    Payload,
+}
+
+/// An iterator over the layers of a frame. See the module documentation.
+#[derive(Clone)]
+pub struct Layers<'a> {
+    data: &'a [u8],
+    next: Option<Next>,
+}
+
+debug_items!(Layers<'a>);
+
+impl<'a> Layers<'a> {
+    /// Returns an iterator over the layers of the Ethernet frame in `data`.
+    pub fn from_ethernet(data: &'a [u8]) -> Layers<'a> {
+        Layers { data, next: Some(Next::Ethernet) }
+    }
+
+    /// Returns an iterator over the layers of the IPv4 or IPv6 packet in `data`.
+    pub fn from_ip(data: &'a [u8]) -> Layers<'a> {
+        Layers { data, next: Some(Next::Ip) }
+    }
+
+    /// Decodes the header at the start of `data` as `next`, returning it together with the data
+    /// following it and what that data holds.
+    fn decode(&self, next: Next) -> Option<(Layer<'a>, &'a [u8], Next)> {
+        let data = self.data;
+        match next {
+            Next::Ethernet => {
+                let packet = EthernetPacket::new(data)?;
+                let ether_type = packet.ether_type();
+                let rest = match ether_type {
+                    EtherType::VLAN | EtherType::QINQ => &data[12..],
+                    _ => packet.payload(),
+                };
+                Some((Layer::Ethernet(packet), rest, Next::EtherType(ether_type)))
+            }
+            Next::EtherType(EtherType::VLAN) | Next::EtherType(EtherType::QINQ) => {
+                let packet = VlanPacket::new(data)?;
+                let ether_type = packet.ether_type();
+                let rest = match ether_type {
+                    EtherType::VLAN | EtherType::QINQ => &data[4..],
+                    _ => packet.payload(),
+                };
+                Some((Layer::Vlan(packet), rest, Next::EtherType(ether_type)))
+            }
+            Next::EtherType(EtherType::ARP) => {
+                let packet = ArpPacket::new(data)?;
+                Some((Layer::Arp(packet), packet.payload(), Next::Payload))
+            }
+            Next::EtherType(EtherType::MPLS) | Next::EtherType(EtherType::MPLS_MULTICAST) => {
+                let packet = MplsPacket::new(data)?;
+                Some((Layer::Mpls(packet), packet.payload(), Next::Ip))
+            }
+            Next::EtherType(EtherType::TRANSPARENT_ETHERNET_BRIDGING) => {
+                self.decode(Next::Ethernet)
+            }
+            Next::EtherType(EtherType::IPV4) | Next::Protocol(Protocol::IPV4) => {
+                let packet = Ipv4Packet::new(data)?;
+                let next = if packet.fragment_offset() == 0 {
+                    Next::Protocol(packet.protocol())
+                } else {
+                    Next::Payload
+                };
+                Some((Layer::Ipv4(packet), packet.payload(), next))
+            }
+            Next::EtherType(EtherType::IPV6) | Next::Protocol(Protocol::IPV6) => {
+                let packet = Ipv6Packet::new(data)?;
+                Some((Layer::Ipv6(packet), packet.payload(), Next::Protocol(packet.next_header())))
+            }
+            Next::Ip => match data.first().map(|byte| byte >> 4) {
+                Some(4) => self.decode(Next::Protocol(Protocol::IPV4)),
+                Some(6) => self.decode(Next::Protocol(Protocol::IPV6)),
+                _ => None,
+            },
+            Next::Protocol(Protocol::HOPOPT) => {
+                let packet = Ipv6HopByHopPacket::new(data)?;
+                let next = Next::Protocol(packet.next_header());
+                Some((Layer::Ipv6HopByHop(packet), packet.payload(), next))
+            }
+            Next::Protocol(Protocol::IPV6_ROUTE) => {
+                let packet = Ipv6RoutingPacket::new(data)?;
+                let next = Next::Protocol(packet.next_header());
+                Some((Layer::Ipv6Routing(packet), packet.payload(), next))
+            }
+            Next::Protocol(Protocol::IPV6_FRAG) => {
+                let packet = Ipv6FragmentPacket::new(data)?;
+                let next = if packet.fragment_offset() == 0 {
+                    Next::Protocol(packet.next_header())
+                } else {
+                    Next::Payload
+                };
+                Some((Layer::Ipv6Fragment(packet), packet.payload(), next))
+            }
+            Next::Protocol(Protocol::IPV6_OPTS) => {
+                let packet = Ipv6DestinationOptionsPacket::new(data)?;
+                let next = Next::Protocol(packet.next_header());
+                Some((Layer::Ipv6DestinationOptions(packet), packet.payload(), next))
+            }
+            Next::Protocol(Protocol::GRE) => {
+                let packet = GrePacket::new(data)?;
+                let next = Next::EtherType(packet.protocol_type());
+                Some((Layer::Gre(packet), packet.payload(), next))
+            }
+            Next::Protocol(Protocol::TCP) => {
+                let packet = TcpPacket::new(data)?;
+                Some((Layer::Tcp(packet), packet.payload(), Next::Payload))
+            }
+            Next::Protocol(Protocol::UDP) => {
+                let packet = UdpPacket::new(data)?;
+                let next = if packet.destination_port() == vxlan::UDP_PORT {
+                    Next::Vxlan
+                } else {
+                    Next::Payload
+                };
+                Some((Layer::Udp(packet), packet.payload(), next))
+            }
+            Next::Vxlan => {
+                let packet = VxlanPacket::new(data)?;
+                Some((Layer::Vxlan(packet), packet.payload(), Next::Ethernet))
+            }
+            Next::Protocol(Protocol::ICMP) => {
+                let packet = Icmpv4Packet::new(data)?;
+                Some((Layer::Icmpv4(packet), packet.payload(), Next::Payload))
+            }
+            Next::Protocol(Protocol::ICMPV6) => {
+                let packet = Icmpv6Packet::new(data)?;
+                Some((Layer::Icmpv6(packet), packet.payload(), Next::Payload))
+            }
+            Next::EtherType(_) | Next::Protocol(_) | Next::Payload => None,
+        }
+    }
+}
+
+impl<'a> Iterator for Layers<'a> {
+    type Item = Layer<'a>;
+
+    fn next(&mut self) -> Option<Layer<'a>> {
+        let next = self.next.take()?;
+        match self.decode(next) {
+            Some((layer, rest, next)) => {
+                self.data = rest;
+                self.next = Some(next);
+                Some(layer)
+            }
+            None if self.data.is_empty() => None,
+            None => Some(Layer::Payload(self.data)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::EthernetBuilder;
+    use ipv4::Ipv4Builder;
+    use ipv6::Ipv6Builder;
+    use udp::UdpBuilder;
+
+    fn names(data: &[u8]) -> Vec<&'static str> {
+        Layers::from_ethernet(data)
+            .map(|layer| match layer {
+                Layer::Ethernet(_) => "ethernet",
+                Layer::Vlan(_) => "vlan",
+                Layer::Arp(_) => "arp",
+                Layer::Mpls(_) => "mpls",
+                Layer::Ipv4(_) => "ipv4",
+                Layer::Ipv6(_) => "ipv6",
+                Layer::Ipv6HopByHop(_) => "hop-by-hop",
+                Layer::Ipv6Routing(_) => "routing",
+                Layer::Ipv6Fragment(_) => "fragment",
+                Layer::Ipv6DestinationOptions(_) => "destination options",
+                Layer::Gre(_) => "gre",
+                Layer::Tcp(_) => "tcp",
+                Layer::Udp(_) => "udp",
+                Layer::Vxlan(_) => "vxlan",
+                Layer::Icmpv4(_) => "icmpv4",
+                Layer::Icmpv6(_) => "icmpv6",
+                Layer::Payload(_) => "payload",
+            })
+            .collect()
+    }
+
+    fn ssh_segment() -> Vec<u8> {
+        let mut tcp = vec![0; 20];
+        tcp[2..4].copy_from_slice(&[0, 22]);
+        tcp[12] = 0x50;
+        tcp
+    }
+
+    #[test]
+    fn ipv4_udp() {
+        let udp = UdpBuilder::new().destination_port(53).payload(b"query");
+        let frame = EthernetBuilder::new().payload(Ipv4Builder::new().payload(udp)).build();
+        let frame = frame.unwrap();
+        assert_eq!(vec!["ethernet", "ipv4", "udp", "payload"], names(&frame));
+        assert_eq!(Some(Layer::Payload(b"query")), Layers::from_ethernet(&frame).last());
+    }
+
+    #[test]
+    fn vxlan_tunnel_with_vlan() {
+        let ipv6 = Ipv6Builder::new().next_header(Protocol::TCP).payload(ssh_segment());
+        let inner = EthernetBuilder::new().payload(ipv6).build().unwrap();
+        let mut vxlan = vec![0x08, 0, 0, 0, 0, 0, 0x2a, 0];
+        vxlan.extend_from_slice(&inner);
+        let udp = UdpBuilder::new().destination_port(vxlan::UDP_PORT).payload(vxlan);
+        let ipv4 = Ipv4Builder::new().payload(udp).build().unwrap();
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x81, 0x00, 0x00, 0x0a, 0x08, 0x00]);
+        frame.extend_from_slice(&ipv4);
+
+        assert_eq!(
+            vec!["ethernet", "vlan", "ipv4", "udp", "vxlan", "ethernet", "ipv6", "tcp"],
+            names(&frame)
+        );
+        let to_ssh = Layers::from_ethernet(&frame).any(|layer| match layer {
+            Layer::Tcp(tcp) => tcp.destination_port() == 22,
+            _ => false,
+        });
+        assert!(to_ssh);
+    }
+
+    #[test]
+    fn from_ip() {
+        let ipv4 = Ipv4Builder::new().protocol(Protocol::TCP).payload(ssh_segment());
+        let mut mpls = vec![0, 0, 0x11, 0x40];
+        mpls.extend_from_slice(&ipv4.build().unwrap());
+        let frame = EthernetBuilder::new().ether_type(EtherType::MPLS).payload(mpls).build();
+        assert_eq!(vec!["ethernet", "mpls", "ipv4", "tcp"], names(&frame.unwrap()));
+
+        let ipv4 = ipv4.build().unwrap();
+        assert_eq!(2, Layers::from_ip(&ipv4).count());
+        assert_eq!(vec![Layer::Payload(&[0x70][..])], Layers::from_ip(&[0x70]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unknown_and_truncated() {
+        let frame = EthernetBuilder::new().ether_type(EtherType(0x1234)).payload(b"x").build();
+        assert_eq!(vec!["ethernet", "payload"], names(&frame.unwrap()));
+
+        let udp = UdpBuilder::new().payload(b"query");
+        let frame = EthernetBuilder::new().payload(Ipv4Builder::new().payload(udp)).build();
+        assert_eq!(vec!["ethernet", "ipv4", "payload"], names(&frame.unwrap()[..38]));
+        assert_eq!(vec!["payload"], names(&[0; 13]));
+        assert_eq!(None, Layers::from_ethernet(&[]).next());
+    }
+}
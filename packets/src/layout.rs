@@ -0,0 +1,178 @@
+//! Header layouts described as tables of fields and checked at compile time.
+//!
+//! A protocol with a table has a `fields` module listing the position of every field in its
+//! fixed header as a `Field`, such as `ipv4::fields::TOTAL_LENGTH`. The getters and setters read
+//! and write through these, so the byte offset, shift and mask of every access is a constant.
+//! The same table is verified when the crate is compiled: it fails to build if two fields
+//! overlap, or if a field lies beyond the minimum length of its packet and could thus be read
+//! from a slice that is too short.
+
+use std::ops::Range;
+
+/// The position of a field in a header, in bits from the start of the header. The bits are
+/// numbered from the most significant bit of the first byte, as in the header diagrams of the
+/// RFCs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Field {
+    /// The number of the first bit of the field.
+    pub start: usize,
+    /// The number of bits in the field.
+    pub width: usize,
+}
+
+impl Field {
+    pub const fn new(start: usize, width: usize) -> Field {
+        Field { start, width }
+    }
+
+    /// Returns the number of the bit after the last bit of the field.
+    pub const fn end(&self) -> usize {
+        self.start + self.width
+    }
+
+    /// Returns the offset of the first byte holding a part of the field.
+    pub const fn byte_offset(&self) -> usize {
+        self.start / 8
+    }
+
+    /// Returns the offset of the byte after the last byte holding a part of the field.
+    pub const fn byte_end(&self) -> usize {
+        self.end().div_ceil(8)
+    }
+
+    /// Returns the range of bytes holding a part of the field, for fields that are read as
+    /// bytes rather than as an integer.
+    pub const fn byte_range(&self) -> Range<usize> {
+        self.byte_offset()..self.byte_end()
+    }
+
+    /// Returns true if the two fields share at least one bit.
+    pub const fn overlaps(&self, other: &Field) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+
+    /// Returns true if the field can be read as a big endian integer of `size` bytes starting at
+    /// `byte_offset`.
+    pub const fn fits(&self, size: usize) -> bool {
+        self.width > 0 && self.start % 8 + self.width <= size * 8
+    }
+
+    /// Returns how far the field is shifted up from the least significant bit when read as an
+    /// integer of `size` bytes starting at `byte_offset`.
+    pub const fn shift(&self, size: usize) -> usize {
+        size * 8 - self.start % 8 - self.width
+    }
+
+    /// Returns a mask with the lowest `width` bits set.
+    pub const fn mask(&self) -> u128 {
+        if self.width >= 128 {
+            u128::MAX
+        } else {
+            (1 << self.width) - 1
+        }
+    }
+}
+
+/// Returns the number of bytes needed to hold all of `fields`.
+pub const fn len(fields: &[Field]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        if fields[i].byte_end() > len {
+            len = fields[i].byte_end();
+        }
+        i += 1;
+    }
+    len
+}
+
+/// Returns true if any two of `fields` overlap.
+pub const fn any_overlap(fields: &[Field]) -> bool {
+    let mut i = 0;
+    while i < fields.len() {
+        let mut j = i + 1;
+        while j < fields.len() {
+            if fields[i].overlaps(&fields[j]) {
+                return true;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Panics if any two of `fields` overlap or if they do not fit in `min_len` bytes. Evaluated
+/// in a constant, the panic becomes a compile error.
+pub const fn check(fields: &[Field], min_len: usize) {
+    assert!(!any_overlap(fields), "Overlapping fields in header layout");
+    assert!(len(fields) <= min_len, "Header layout longer than the minimum packet length");
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSION: Field = Field::new(0, 4);
+    const HEADER_LENGTH: Field = Field::new(4, 4);
+    const FLAGS: Field = Field::new(48, 3);
+    const FRAGMENT_OFFSET: Field = Field::new(51, 13);
+
+    #[test]
+    fn positions() {
+        assert_eq!(6, FLAGS.byte_offset());
+        assert_eq!(7, FLAGS.byte_end());
+        assert_eq!(8, FRAGMENT_OFFSET.byte_end());
+        assert_eq!(6..8, FRAGMENT_OFFSET.byte_range());
+        assert_eq!(5, FLAGS.shift(1));
+        assert_eq!(0, FRAGMENT_OFFSET.shift(2));
+        assert_eq!(0x1fff, FRAGMENT_OFFSET.mask());
+        assert_eq!(u128::MAX, Field::new(64, 128).mask());
+        assert!(FLAGS.fits(1));
+        assert!(!FRAGMENT_OFFSET.fits(1));
+    }
+
+    #[test]
+    fn overlap() {
+        assert!(!VERSION.overlaps(&HEADER_LENGTH));
+        assert!(!FLAGS.overlaps(&FRAGMENT_OFFSET));
+        assert!(Field::new(3, 2).overlaps(&HEADER_LENGTH));
+        assert!(!any_overlap(&[VERSION, HEADER_LENGTH, FLAGS, FRAGMENT_OFFSET]));
+        assert!(any_overlap(&[VERSION, FLAGS, Field::new(0, 8)]));
+    }
+
+    #[test]
+    fn check_valid() {
+        const FIELDS: &[Field] = &[VERSION, HEADER_LENGTH, FLAGS, FRAGMENT_OFFSET];
+        const _: () = check(FIELDS, 8);
+        assert_eq!(8, len(FIELDS));
+    }
+
+    #[test]
+    fn fixed_headers_fully_described() {
+        use ethernet::{self, EthernetPacket};
+        use ipv4::{self, Ipv4Packet};
+        use ipv6::{self, Ipv6Packet};
+        use tcp::{self, TcpPacket};
+        use udp::{self, UdpPacket};
+
+        assert_eq!(EthernetPacket::MIN_LEN, len(ethernet::fields::ALL));
+        assert_eq!(Ipv4Packet::MIN_LEN, len(ipv4::fields::ALL));
+        assert_eq!(Ipv6Packet::MIN_LEN, len(ipv6::fields::ALL));
+        assert_eq!(TcpPacket::MIN_LEN, len(tcp::fields::ALL));
+        assert_eq!(UdpPacket::MIN_LEN, len(udp::fields::ALL));
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_too_long() {
+        check(&[VERSION, FRAGMENT_OFFSET], 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_overlapping() {
+        check(&[VERSION, Field::new(2, 4)], 20);
+    }
+}
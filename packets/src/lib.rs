@@ -71,20 +71,120 @@
 //!
 //! Compiling `rips-packets` takes under a second on a modern computer, whereas `pnet_packet` take
 //! well over a minute on the same hardware.
+//!
+//! # `no_std`
+//!
+//! Disabling the default `std` feature makes the crate `no_std`, it then only depends on `core`
+//! and `alloc`. Everything except a few conversions to and from types that only exist in `std`,
+//! such as `ntp::Timestamp::to_system_time`, is available without it.
+//!
+//! # Unsafe code
+//!
+//! The getters and setters read and write the backing slice with safe, bounds checked code, so
+//! a field outside of the slice makes them panic instead of reading or writing out of bounds.
+//! The crate denies `unsafe_code`, and the only places allowing it are the `new_unchecked`
+//! constructors, which are only `unsafe` to leave out the length check, and the vectorized
+//! checksum code in `checksum`, which uses CPU intrinsics.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![deny(unsafe_code)]
+
+#[cfg(not(any(feature = "std", test)))]
+extern crate core as std;
+#[cfg_attr(not(any(feature = "std", test)), macro_use)]
+extern crate alloc;
+
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
 #[macro_use]
 extern crate bitflags;
+#[cfg(feature = "pnet")]
+extern crate pnet_packet;
+#[macro_use]
+extern crate rips_packets_macros;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 #[macro_use]
 mod macros;
+mod access;
+
+/// Checksum algorithms, mainly the internet checksum of RFC 1071.
+pub mod checksum;
 
 /// Link layer primitives.
 pub mod ethernet;
 
+pub mod ah;
 pub mod arp;
+pub mod bgp;
+pub mod builder;
+pub mod chain;
+pub mod dccp;
+pub mod dhcp;
+pub mod dhcpv6;
+pub mod diff;
+pub mod dns;
+pub mod eapol;
+pub mod error;
+pub mod erspan;
+pub mod esp;
+pub mod geneve;
+pub mod gre;
+pub mod gtpu;
+pub mod hexdump;
+pub mod hsrp;
+pub mod icmp_extension;
+pub mod icmpv4;
+pub mod icmpv6;
+pub mod ieee80211;
+pub mod igmp;
+pub mod ikev2;
 pub mod ip;
+pub mod ipip;
 pub mod ipv4;
 pub mod ipv6;
+pub mod isis;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod l2tp;
+pub mod layer;
+pub mod layout;
+pub mod lacp;
+pub mod llc;
+pub mod lldp;
+pub mod modbus;
+pub mod mpls;
+pub mod nat;
+pub mod netflow;
+pub mod ntp;
+pub mod nvgre;
+pub mod ospf;
+#[cfg(feature = "pnet")]
+pub mod pnet;
+pub mod ports;
+pub mod ppp;
+pub mod pppoe;
+pub mod quic;
+pub mod radiotap;
+pub mod rip;
+pub mod rtcp;
+pub mod rtp;
+pub mod sctp;
+pub mod sflow;
+pub mod sixlowpan;
+pub mod sliced;
+pub mod stp;
+pub mod tcp;
+pub mod teredo;
+pub mod tftp;
+pub mod tls;
+pub mod udp;
+pub mod udp_lite;
+pub mod vlan;
+pub mod vrrp;
+pub mod vxlan;
+pub mod wireguard;
 
 
 /// Bit field type aliases.
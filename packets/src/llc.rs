@@ -0,0 +1,260 @@
+//! IEEE 802.2 Logical Link Control and the Subnetwork Access Protocol. IEEE 802.3 frames carry a
+//! length instead of an EtherType, followed by an LLC header. A SNAP header after the LLC header
+//! then gives the EtherType of the payload.
+
+use ethernet::{EtherType, EthernetPacket};
+use std::cmp;
+use std::fmt;
+
+packet!(
+    /// An LLC header. The control field is one byte for unnumbered frames and two bytes for
+    /// information and supervisory frames.
+    LlcPacket,
+    MutLlcPacket,
+    LlcPacketBuf,
+    3,
+    |data| if data[2] & 0x03 == 0x03 { 3 } else { 4 },
+    init |data| data[2] |= 0x03
+);
+
+getters!(LlcPacket
+    /// Returns the destination service access point.
+    pub fn dsap(&self) -> u8 {
+        read_offset!(self.0, 0, u8)
+    }
+
+    /// Returns the source service access point.
+    pub fn ssap(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns the control field. Only the first byte is used for unnumbered frames, while
+    /// information and supervisory frames have the second byte in the low bits. A missing
+    /// second byte is read as zero.
+    pub fn control(&self) -> u16 {
+        if self.is_unnumbered() {
+            u16::from(self.0[2])
+        } else {
+            let low = self.0.get(3).cloned().unwrap_or(0);
+            u16::from_be_bytes([self.0[2], low])
+        }
+    }
+
+    /// Returns true for unnumbered frames, with a one byte control field.
+    pub fn is_unnumbered(&self) -> bool {
+        self.0[2] & 0x03 == 0x03
+    }
+
+    /// Returns the SNAP header following this header, or `None` if this is not an unnumbered
+    /// information frame addressed to the SNAP service access point, or the payload is too
+    /// short.
+    pub fn snap(&self) -> Option<SnapPacket<'a>> {
+        if self.dsap() == SNAP_SAP && self.ssap() == SNAP_SAP && self.control() == UI {
+            SnapPacket::new(&self.0[3..])
+        } else {
+            None
+        }
+    }
+);
+
+impl<'a> LlcPacket<'a> {
+    /// Returns the LLC header of an IEEE 802.3 frame, or `None` if the frame carries an
+    /// EtherType, or the payload is too short. The packet is trimmed to the length field, so
+    /// any Ethernet padding is left out.
+    pub fn from_ethernet(ethernet: EthernetPacket<'a>) -> Option<LlcPacket<'a>> {
+        let length = ethernet.ether_type();
+        if !length.is_length() {
+            return None;
+        }
+        let payload = &ethernet.data()[14..];
+        LlcPacket::new(&payload[..cmp::min(usize::from(length.value()), payload.len())])
+    }
+}
+
+setters!(MutLlcPacket
+    pub fn set_dsap(&mut self, dsap: u8) {
+        write_offset!(self.0, 0, dsap, u8);
+    }
+
+    pub fn set_ssap(&mut self, ssap: u8) {
+        write_offset!(self.0, 1, ssap, u8);
+    }
+
+    /// Sets the control field. Values below 0x100 are written as a one byte control field,
+    /// others as two bytes. The value must agree with the frame format given by its lowest two
+    /// bits. Panics if a two byte field does not fit in the backing slice.
+    pub fn set_control(&mut self, control: u16) {
+        if control < 0x100 {
+            write_offset!(self.0, 2, control as u8, u8);
+        } else {
+            write_offset!(self.0[2..4], 0, control, u16, to_be);
+        }
+    }
+);
+
+impl<'a> MutLlcPacket<'a> {
+    /// Sets up an unnumbered information frame to the SNAP service access point, meaning the
+    /// header is followed by a SNAP header.
+    pub fn set_snap(&mut self) {
+        self.set_dsap(SNAP_SAP);
+        self.set_ssap(SNAP_SAP);
+        self.set_control(UI);
+    }
+}
+
+/// The service access point identifying SNAP.
+pub const SNAP_SAP: u8 = 0xaa;
+
+/// The control field of an unnumbered information frame.
+pub const UI: u16 = 0x03;
+
+
+packet!(SnapPacket, MutSnapPacket, SnapPacketBuf, 5);
+
+getters!(SnapPacket
+    /// Returns the organizationally unique identifier. Zero means the protocol ID is an
+    /// EtherType.
+    pub fn oui(&self) -> [u8; 3] {
+        read_offset!(self.0, 0, [u8; 3])
+    }
+
+    pub fn protocol_id(&self) -> u16 {
+        read_offset!(self.0, 3, u16, from_be)
+    }
+
+    /// Returns the protocol ID as an EtherType, or `None` if the OUI is not zero, meaning the
+    /// protocol ID is defined by the organization.
+    pub fn ether_type(&self) -> Option<EtherType> {
+        if self.oui() == [0; 3] {
+            Some(EtherType(self.protocol_id()))
+        } else {
+            None
+        }
+    }
+);
+
+setters!(MutSnapPacket
+    pub fn set_oui(&mut self, oui: [u8; 3]) {
+        write_offset!(self.0, 0, oui, [u8; 3]);
+    }
+
+    pub fn set_protocol_id(&mut self, protocol_id: u16) {
+        write_offset!(self.0, 3, protocol_id, u16, to_be);
+    }
+
+    /// Sets the OUI to zero and the protocol ID to `ether_type`.
+    pub fn set_ether_type(&mut self, ether_type: EtherType) {
+        self.set_oui([0; 3]);
+        self.set_protocol_id(ether_type.value());
+    }
+);
+
+impl<'a> fmt::Display for LlcPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LLC dsap={:#04x} ssap={:#04x} control={:#x}",
+            self.dsap(),
+            self.ssap(),
+            self.control()
+        )
+    }
+}
+
+impl<'a> fmt::Display for SnapPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let oui = self.oui();
+        write!(
+            f,
+            "SNAP oui={:02x}:{:02x}:{:02x} protocol={:#06x}",
+            oui[0],
+            oui[1],
+            oui[2],
+            self.protocol_id()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv4::Ipv4Packet;
+
+    macro_rules! llc_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutLlcPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    llc_setget_test!(dsap, set_dsap, 0xab, 0, [0xab]);
+    llc_setget_test!(ssap, set_ssap, 0xab, 1, [0xab]);
+    llc_setget_test!(control, set_control, 0x03, 2, [0x03]);
+
+    mod snap {
+        use super::super::*;
+
+        macro_rules! snap_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutSnapPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        snap_setget_test!(oui, set_oui, [0x00, 0x00, 0x0c], 0, [0x00, 0x00, 0x0c]);
+        snap_setget_test!(protocol_id, set_protocol_id, 0xabcd, 3, [0xab, 0xcd]);
+
+        #[test]
+        fn set_ether_type() {
+            let mut data = [0xff; 5];
+            MutSnapPacket::new(&mut data).unwrap().set_ether_type(EtherType::ARP);
+            assert_eq!([0, 0, 0, 0x08, 0x06], data);
+            assert_eq!(Some(EtherType::ARP), SnapPacket::new(&data).unwrap().ether_type());
+        }
+    }
+
+    #[test]
+    fn two_byte_control() {
+        let data = [0xf0, 0xf0, 0x02, 0x05, 0xff];
+        let testee = LlcPacket::new(&data).unwrap();
+        assert!(!testee.is_unnumbered());
+        assert_eq!(0x0205, testee.control());
+        assert_eq!(4, testee.header_len());
+        assert!(testee.snap().is_none());
+
+        let mut data = [0; 4];
+        MutLlcPacket::new(&mut data).unwrap().set_control(0x0205);
+        assert_eq!([0, 0, 0x02, 0x05], data);
+    }
+
+    #[test]
+    fn ieee_802_3_frame_to_ipv4() {
+        let mut data = [0; 14 + 8 + 20 + 4];
+        data[12..14].copy_from_slice(&[0, 8 + 20]);
+        {
+            let mut llc = MutLlcPacket::new(&mut data[14..]).unwrap();
+            llc.set_snap();
+            MutSnapPacket::new(&mut llc.data()[3..]).unwrap().set_ether_type(EtherType::IPV4);
+        }
+        data[22] = 0x45;
+
+        let ethernet = EthernetPacket::new(&data).unwrap();
+        assert!(ethernet.ether_type().is_length());
+        let llc = LlcPacket::from_ethernet(ethernet).unwrap();
+        assert_eq!(28, llc.data().len());
+        let snap = llc.snap().unwrap();
+        assert_eq!(Some(EtherType::IPV4), snap.ether_type());
+        let ipv4 = Ipv4Packet::new(snap.payload()).unwrap();
+        assert_eq!(4, ipv4.version());
+    }
+
+    #[test]
+    fn ethernet_ii_frame() {
+        let mut data = [0; 14 + 8];
+        data[12..14].copy_from_slice(&[0x08, 0x00]);
+        assert!(LlcPacket::from_ethernet(EthernetPacket::new(&data).unwrap()).is_none());
+        let testee = SnapPacket::new(&[0x00, 0x00, 0x0c, 0x20, 0x00]).unwrap();
+        assert_eq!(None, testee.ether_type());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, LlcPacketBuf => |packet| packet.is_unnumbered());
+}
@@ -0,0 +1,484 @@
+//! The Link Layer Discovery Protocol, IEEE 802.1AB. An LLDP data unit is a list of TLVs, starting
+//! with the mandatory Chassis ID, Port ID and Time To Live TLVs and terminated by an End TLV.
+
+use ethernet::MacAddr;
+use std::fmt;
+
+/// The destination address of LLDP frames that should not be forwarded by any kind of bridge.
+pub const NEAREST_BRIDGE: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]);
+
+packet!(
+    /// An LLDP data unit, carried as the payload of an Ethernet frame with the LLDP EtherType.
+    /// The header is the list of TLVs up to and including the End TLV, so the payload is any
+    /// Ethernet padding that follows.
+    LldpPacket,
+    MutLldpPacket,
+    LldpPacketBuf,
+    2,
+    |data| tlvs_len(data),
+    init |data| write_offset!(data, 0, 0u16, u16)
+);
+
+getters!(LldpPacket
+    /// Returns an iterator over the TLVs in this data unit.
+    pub fn tlvs(&self) -> LldpTlvs<'a> {
+        LldpTlvs::new(self.0)
+    }
+);
+
+/// Returns the length of the TLVs at the start of `data`, up to and including the End TLV or the
+/// first TLV that does not fit.
+fn tlvs_len(data: &[u8]) -> usize {
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..offset + 2) {
+        let header = read_offset!(header, 0, u16, from_be);
+        offset += 2 + usize::from(header & 0x1ff);
+        if header >> 9 == u16::from(TlvType::END.value()) {
+            break;
+        }
+    }
+    offset
+}
+
+
+/// The 7 bit type of an LLDP TLV.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TlvType(pub u8);
+
+impl TlvType {
+    pub const END: TlvType = TlvType(0);
+    pub const CHASSIS_ID: TlvType = TlvType(1);
+    pub const PORT_ID: TlvType = TlvType(2);
+    pub const TTL: TlvType = TlvType(3);
+    pub const PORT_DESCRIPTION: TlvType = TlvType(4);
+    pub const SYSTEM_NAME: TlvType = TlvType(5);
+    pub const SYSTEM_DESCRIPTION: TlvType = TlvType(6);
+    pub const SYSTEM_CAPABILITIES: TlvType = TlvType(7);
+    pub const MANAGEMENT_ADDRESS: TlvType = TlvType(8);
+    pub const ORGANIZATIONALLY_SPECIFIC: TlvType = TlvType(127);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The subtype of a Chassis ID TLV, telling how to interpret the ID.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ChassisIdSubtype(pub u8);
+
+impl ChassisIdSubtype {
+    pub const CHASSIS_COMPONENT: ChassisIdSubtype = ChassisIdSubtype(1);
+    pub const INTERFACE_ALIAS: ChassisIdSubtype = ChassisIdSubtype(2);
+    pub const PORT_COMPONENT: ChassisIdSubtype = ChassisIdSubtype(3);
+    pub const MAC_ADDRESS: ChassisIdSubtype = ChassisIdSubtype(4);
+    pub const NETWORK_ADDRESS: ChassisIdSubtype = ChassisIdSubtype(5);
+    pub const INTERFACE_NAME: ChassisIdSubtype = ChassisIdSubtype(6);
+    pub const LOCALLY_ASSIGNED: ChassisIdSubtype = ChassisIdSubtype(7);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The subtype of a Port ID TLV, telling how to interpret the ID.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PortIdSubtype(pub u8);
+
+impl PortIdSubtype {
+    pub const INTERFACE_ALIAS: PortIdSubtype = PortIdSubtype(1);
+    pub const PORT_COMPONENT: PortIdSubtype = PortIdSubtype(2);
+    pub const MAC_ADDRESS: PortIdSubtype = PortIdSubtype(3);
+    pub const NETWORK_ADDRESS: PortIdSubtype = PortIdSubtype(4);
+    pub const INTERFACE_NAME: PortIdSubtype = PortIdSubtype(5);
+    pub const AGENT_CIRCUIT_ID: PortIdSubtype = PortIdSubtype(6);
+    pub const LOCALLY_ASSIGNED: PortIdSubtype = PortIdSubtype(7);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The content of a Management Address TLV.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ManagementAddress<'a> {
+    /// The IANA address family number of `address`, 1 for IPv4 and 2 for IPv6.
+    pub address_subtype: u8,
+    /// The address itself, between 1 and 31 bytes long.
+    pub address: &'a [u8],
+    /// How `interface_number` is assigned, 2 for ifIndex and 3 for system port number.
+    pub interface_subtype: u8,
+    pub interface_number: u32,
+    /// An optional SNMP object identifier for the hardware component, at most 128 bytes long.
+    pub oid: &'a [u8],
+}
+
+/// A single TLV in an LLDP data unit. Strings are left as bytes, since they are not required to
+/// be valid UTF-8.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LldpTlv<'a> {
+    /// Marks the end of the data unit.
+    End,
+    ChassisId(ChassisIdSubtype, &'a [u8]),
+    PortId(PortIdSubtype, &'a [u8]),
+    /// The number of seconds the receiver should keep the information, zero meaning it should be
+    /// removed right away.
+    Ttl(u16),
+    PortDescription(&'a [u8]),
+    SystemName(&'a [u8]),
+    SystemDescription(&'a [u8]),
+    ManagementAddress(ManagementAddress<'a>),
+    /// Any other TLV, or one of the above with an unexpected length. Holds the value following
+    /// the type and length fields.
+    Other(TlvType, &'a [u8]),
+}
+
+impl<'a> LldpTlv<'a> {
+    /// Returns the number of bytes this TLV occupies when encoded, or `None` if it can't be
+    /// encoded because its value is longer than the 9 bit length field can express, or an ID,
+    /// management address or OID has an invalid length.
+    pub fn encoded_len(&self) -> Option<usize> {
+        let value_len = match *self {
+            LldpTlv::End => 0,
+            LldpTlv::ChassisId(_, id) | LldpTlv::PortId(_, id) => {
+                if id.is_empty() || id.len() > 255 {
+                    return None;
+                }
+                1 + id.len()
+            }
+            LldpTlv::Ttl(_) => 2,
+            LldpTlv::PortDescription(value) |
+            LldpTlv::SystemName(value) |
+            LldpTlv::SystemDescription(value) |
+            LldpTlv::Other(_, value) => value.len(),
+            LldpTlv::ManagementAddress(address) => {
+                if address.address.is_empty() || address.address.len() > 31 ||
+                    address.oid.len() > 128
+                {
+                    return None;
+                }
+                2 + address.address.len() + 5 + 1 + address.oid.len()
+            }
+        };
+        if value_len <= 0x1ff {
+            Some(2 + value_len)
+        } else {
+            None
+        }
+    }
+
+    /// Encodes this TLV into the beginning of `buffer`. Returns the number of bytes written, or
+    /// `None` if the TLV does not fit in `buffer` or can't be encoded.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len()?;
+        if buffer.len() < len {
+            return None;
+        }
+        let tlv_type = match *self {
+            LldpTlv::End => TlvType::END,
+            LldpTlv::ChassisId(subtype, id) => {
+                buffer[2] = subtype.value();
+                buffer[3..len].copy_from_slice(id);
+                TlvType::CHASSIS_ID
+            }
+            LldpTlv::PortId(subtype, id) => {
+                buffer[2] = subtype.value();
+                buffer[3..len].copy_from_slice(id);
+                TlvType::PORT_ID
+            }
+            LldpTlv::Ttl(ttl) => {
+                write_offset!(buffer, 2, ttl, u16, to_be);
+                TlvType::TTL
+            }
+            LldpTlv::PortDescription(value) => {
+                buffer[2..len].copy_from_slice(value);
+                TlvType::PORT_DESCRIPTION
+            }
+            LldpTlv::SystemName(value) => {
+                buffer[2..len].copy_from_slice(value);
+                TlvType::SYSTEM_NAME
+            }
+            LldpTlv::SystemDescription(value) => {
+                buffer[2..len].copy_from_slice(value);
+                TlvType::SYSTEM_DESCRIPTION
+            }
+            LldpTlv::ManagementAddress(address) => {
+                let address_end = 4 + address.address.len();
+                buffer[2] = 1 + address.address.len() as u8;
+                buffer[3] = address.address_subtype;
+                buffer[4..address_end].copy_from_slice(address.address);
+                buffer[address_end] = address.interface_subtype;
+                write_offset!(
+                    buffer[address_end + 1..address_end + 5],
+                    0,
+                    address.interface_number,
+                    u32,
+                    to_be
+                );
+                buffer[address_end + 5] = address.oid.len() as u8;
+                buffer[address_end + 6..len].copy_from_slice(address.oid);
+                TlvType::MANAGEMENT_ADDRESS
+            }
+            LldpTlv::Other(tlv_type, value) => {
+                buffer[2..len].copy_from_slice(value);
+                tlv_type
+            }
+        };
+        let header = (u16::from(tlv_type.value()) << 9) | (len - 2) as u16;
+        write_offset!(buffer, 0, header, u16, to_be);
+        Some(len)
+    }
+}
+
+/// Decodes the value of a Management Address TLV, or returns `None` if the lengths inside it
+/// are inconsistent with the length of the TLV.
+fn read_management_address(value: &[u8]) -> Option<ManagementAddress<'_>> {
+    let address_string_len = usize::from(*value.first()?);
+    if address_string_len < 2 {
+        return None;
+    }
+    let address_end = 1 + address_string_len;
+    let interface = value.get(address_end..address_end + 6)?;
+    let oid = &value[address_end + 6..];
+    if usize::from(interface[5]) != oid.len() {
+        return None;
+    }
+    Some(ManagementAddress {
+        address_subtype: value[1],
+        address: &value[2..address_end],
+        interface_subtype: interface[0],
+        interface_number: read_offset!(interface, 1, u32, from_be),
+        oid,
+    })
+}
+
+/// Iterator over the TLVs in an LLDP data unit. Iteration stops after an End TLV, or at the
+/// first TLV that does not fit in the data.
+#[derive(Clone)]
+pub struct LldpTlvs<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(LldpTlvs<'a>);
+
+impl<'a> LldpTlvs<'a> {
+    /// Creates an iterator over the TLVs encoded in `data`.
+    pub fn new(data: &'a [u8]) -> LldpTlvs<'a> {
+        LldpTlvs { data }
+    }
+}
+
+impl<'a> Iterator for LldpTlvs<'a> {
+    type Item = LldpTlv<'a>;
+
+    fn next(&mut self) -> Option<LldpTlv<'a>> {
+        if self.data.len() < 2 {
+            self.data = &[];
+            return None;
+        }
+        let header = read_offset!(self.data, 0, u16, from_be);
+        let tlv_type = TlvType((header >> 9) as u8);
+        let len = 2 + usize::from(header & 0x1ff);
+        if len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let value = &self.data[2..len];
+        self.data = if tlv_type == TlvType::END { &[] } else { &self.data[len..] };
+
+        Some(match (tlv_type, value.len()) {
+            (TlvType::END, 0) => LldpTlv::End,
+            (TlvType::CHASSIS_ID, 2..=256) => {
+                LldpTlv::ChassisId(ChassisIdSubtype(value[0]), &value[1..])
+            }
+            (TlvType::PORT_ID, 2..=256) => LldpTlv::PortId(PortIdSubtype(value[0]), &value[1..]),
+            (TlvType::TTL, 2) => LldpTlv::Ttl(read_offset!(value, 0, u16, from_be)),
+            (TlvType::PORT_DESCRIPTION, _) => LldpTlv::PortDescription(value),
+            (TlvType::SYSTEM_NAME, _) => LldpTlv::SystemName(value),
+            (TlvType::SYSTEM_DESCRIPTION, _) => LldpTlv::SystemDescription(value),
+            (TlvType::MANAGEMENT_ADDRESS, _) => match read_management_address(value) {
+                Some(address) => LldpTlv::ManagementAddress(address),
+                None => LldpTlv::Other(tlv_type, value),
+            },
+            _ => LldpTlv::Other(tlv_type, value),
+        })
+    }
+}
+
+
+/// Writes an LLDP data unit into a buffer. Creating the builder writes the mandatory TLVs,
+/// optional TLVs are then appended with `push` and the data unit is terminated with `finish`.
+#[derive(Debug)]
+pub struct LldpBuilder<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> LldpBuilder<'a> {
+    /// Creates a builder writing to the beginning of `buffer`, starting the data unit with the
+    /// Chassis ID, Port ID and Time To Live TLVs. Returns `None` if they do not fit in `buffer`
+    /// or can't be encoded.
+    pub fn new(
+        buffer: &'a mut [u8],
+        chassis_id_subtype: ChassisIdSubtype,
+        chassis_id: &[u8],
+        port_id_subtype: PortIdSubtype,
+        port_id: &[u8],
+        ttl: u16,
+    ) -> Option<LldpBuilder<'a>> {
+        let mut builder = LldpBuilder { buffer, len: 0 };
+        builder.push(&LldpTlv::ChassisId(chassis_id_subtype, chassis_id))?;
+        builder.push(&LldpTlv::PortId(port_id_subtype, port_id))?;
+        builder.push(&LldpTlv::Ttl(ttl))?;
+        Some(builder)
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if nothing has been written. Never the case for a builder that was created
+    /// successfully, since the mandatory TLVs are always present.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends `tlv` to the data unit. Returns the number of bytes written so far, or `None` if
+    /// the TLV does not fit in the rest of the buffer or can't be encoded.
+    pub fn push(&mut self, tlv: &LldpTlv) -> Option<usize> {
+        self.len += tlv.write(&mut self.buffer[self.len..])?;
+        Some(self.len)
+    }
+
+    /// Terminates the data unit with an End TLV. Returns the total length of the data unit, or
+    /// `None` if the End TLV does not fit in the rest of the buffer.
+    pub fn finish(mut self) -> Option<usize> {
+        self.push(&LldpTlv::End)
+    }
+}
+
+impl<'a> fmt::Display for LldpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LLDP tlvs={}", self.tlvs().count())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CAPTURE: [u8; 38] = [
+        0x02, 0x07, 0x04, 0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e, // Chassis ID, MAC address
+        0x04, 0x05, 0x05, b'e', b't', b'h', b'0', // Port ID, interface name
+        0x06, 0x02, 0x00, 0x78, // TTL 120
+        0x0a, 0x02, b's', b'w', // System name
+        0x10, 0x0c, 0x05, 0x01, 0x0a, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00, // Mgmt
+    ];
+
+    #[test]
+    fn parse_tlvs() {
+        let mut data = CAPTURE.to_vec();
+        data.extend_from_slice(&[0x00, 0x00, 0xff, 0xff]);
+        let testee = LldpPacket::new(&data).unwrap();
+        assert_eq!(40, testee.header_len());
+        assert_eq!(&[0xff, 0xff], testee.payload());
+        assert_eq!(
+            vec![
+                LldpTlv::ChassisId(ChassisIdSubtype::MAC_ADDRESS, &CAPTURE[3..9]),
+                LldpTlv::PortId(PortIdSubtype::INTERFACE_NAME, b"eth0"),
+                LldpTlv::Ttl(120),
+                LldpTlv::SystemName(b"sw"),
+                LldpTlv::ManagementAddress(ManagementAddress {
+                    address_subtype: 1,
+                    address: &[10, 0, 0, 1],
+                    interface_subtype: 2,
+                    interface_number: 3,
+                    oid: &[],
+                }),
+                LldpTlv::End,
+            ],
+            testee.tlvs().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn malformed_tlvs() {
+        let data = [0x06, 0x01, 0x00, 0x10, 0x01, 0x01, 0x00];
+        assert_eq!(
+            vec![
+                LldpTlv::Other(TlvType::TTL, &[0x00]),
+                LldpTlv::Other(TlvType::MANAGEMENT_ADDRESS, &[0x01]),
+            ],
+            LldpTlvs::new(&data[..6]).collect::<Vec<_>>()
+        );
+        assert_eq!(1, LldpTlvs::new(&[0x06, 0x01, 0x00, 0x02, 0x02]).count());
+    }
+
+    #[test]
+    fn build() {
+        let mut buffer = [0; 64];
+        let len = {
+            let mut builder = LldpBuilder::new(
+                &mut buffer,
+                ChassisIdSubtype::MAC_ADDRESS,
+                &CAPTURE[3..9],
+                PortIdSubtype::INTERFACE_NAME,
+                b"eth0",
+                120,
+            ).unwrap();
+            assert_eq!(20, builder.len());
+            builder.push(&LldpTlv::SystemName(b"sw")).unwrap();
+            builder
+                .push(&LldpTlv::ManagementAddress(ManagementAddress {
+                    address_subtype: 1,
+                    address: &[10, 0, 0, 1],
+                    interface_subtype: 2,
+                    interface_number: 3,
+                    oid: &[],
+                }))
+                .unwrap();
+            builder.finish().unwrap()
+        };
+        assert_eq!(40, len);
+        assert_eq!(&CAPTURE[..], &buffer[..38]);
+        assert_eq!(&[0, 0], &buffer[38..40]);
+    }
+
+    #[test]
+    fn build_out_of_space() {
+        let mut buffer = [0; 21];
+        {
+            let mut builder = LldpBuilder::new(
+                &mut buffer,
+                ChassisIdSubtype::LOCALLY_ASSIGNED,
+                &[1; 6],
+                PortIdSubtype::LOCALLY_ASSIGNED,
+                &[2; 4],
+                0,
+            ).unwrap();
+            assert_eq!(None, builder.push(&LldpTlv::SystemName(b"x")));
+            assert_eq!(None, builder.finish());
+        }
+        assert!(
+            LldpBuilder::new(
+                &mut buffer,
+                ChassisIdSubtype::LOCALLY_ASSIGNED,
+                &[1; 10],
+                PortIdSubtype::LOCALLY_ASSIGNED,
+                &[2; 4],
+                0,
+            ).is_none()
+        );
+        assert_eq!(None, LldpTlv::SystemDescription(&[0; 512]).encoded_len());
+        assert_eq!(None, LldpTlv::PortId(PortIdSubtype::LOCALLY_ASSIGNED, &[]).encoded_len());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        LldpPacketBuf => |packet| matches!(packet.tlvs().next(), Some(LldpTlv::End))
+    );
+}
@@ -1,8 +1,87 @@
 macro_rules! packet {
-    ($name:ident, $mut_name:ident, $min_len:expr) => {
-        #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    ($(#[$doc:meta])* $name:ident, $mut_name:ident, $buf_name:ident, $min_len:expr) => {
+        packet!($(#[$doc])* $name, $mut_name, $buf_name, $min_len, |_data| $min_len);
+    };
+    (
+        $(#[$doc:meta])*
+        $name:ident, $mut_name:ident, $buf_name:ident, $min_len:expr, |$data:ident| $header_len:expr
+    ) => {
+        packet!(
+            $(#[$doc])*
+            $name, $mut_name, $buf_name, $min_len, |$data| $header_len, init |_data| {}
+        );
+    };
+    (
+        $(#[$doc:meta])*
+        $name:ident, $mut_name:ident, $buf_name:ident,
+        $min_len:expr, |$data:ident| $header_len:expr, init |$init_data:ident| $init:expr
+    ) => {
+        packet!(
+            $(#[$doc])*
+            $name, $mut_name, $buf_name, $min_len, |$data| $header_len,
+            len |data| data.len(), init |$init_data| $init
+        );
+    };
+    (
+        $(#[$doc:meta])*
+        $name:ident, $mut_name:ident, $buf_name:ident,
+        $min_len:expr, |$data:ident| $header_len:expr,
+        len |$len_data:ident| $len:expr, init |$init_data:ident| $init:expr
+    ) => {
+        packet!(
+            $(#[$doc])*
+            $name, $mut_name, $buf_name, $min_len, |$data| $header_len,
+            len |$len_data| $len, set_len |_data, _len| true, init |$init_data| $init
+        );
+    };
+    (
+        $(#[$doc:meta])*
+        $name:ident, $mut_name:ident, $buf_name:ident,
+        $min_len:expr, |$data:ident| $header_len:expr,
+        len |$len_data:ident| $len:expr,
+        set_len |$set_len_data:ident, $set_len_len:ident| $set_len:expr
+    ) => {
+        packet!(
+            $(#[$doc])*
+            $name, $mut_name, $buf_name, $min_len, |$data| $header_len,
+            len |$len_data| $len, set_len |$set_len_data, $set_len_len| $set_len,
+            init |data| {
+                $mut_name::set_len(data, data.len());
+            }
+        );
+    };
+    (
+        $(#[$doc:meta])*
+        $name:ident, $mut_name:ident, $buf_name:ident,
+        $min_len:expr, |$data:ident| $header_len:expr,
+        set_len |$set_len_data:ident, $set_len_len:ident| $set_len:expr,
+        init |$init_data:ident| $init:expr
+    ) => {
+        packet!(
+            $(#[$doc])*
+            $name, $mut_name, $buf_name, $min_len, |$data| $header_len,
+            len |data| data.len(), set_len |$set_len_data, $set_len_len| $set_len,
+            init |$init_data| $init
+        );
+    };
+    (
+        $(#[$doc:meta])*
+        $name:ident, $mut_name:ident, $buf_name:ident,
+        $min_len:expr, |$data:ident| $header_len:expr,
+        len |$len_data:ident| $len:expr,
+        set_len |$set_len_data:ident, $set_len_len:ident| $set_len:expr,
+        init |$init_data:ident| $init:expr
+    ) => {
+        $(#[$doc])*
+        #[derive(Copy, Clone, Eq, PartialEq, Hash)]
         pub struct $name<'a>(&'a [u8]);
+        $(#[$doc])*
         pub struct $mut_name<'a>(&'a mut [u8]);
+        $(#[$doc])*
+        ///
+        /// This is the owned version, backed by a `Vec<u8>` by default or by any other buffer.
+        #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+        pub struct $buf_name<B = ::alloc::vec::Vec<u8>>(B);
 
         impl<'a> $name<'a> {
             /// The minimum number of bytes in this type of packet. Usually equal to the
@@ -22,8 +101,14 @@ macro_rules! packet {
             }
 
             /// Creates a new immutable packet based on the given backing slice without checking
-            /// its length first. If the slice is too short, a subsequent read from a field might
-            /// result in invalid memory access.
+            /// its length first.
+            ///
+            /// # Safety
+            ///
+            /// The slice must be at least `MIN_LEN` bytes long. Field accesses are bounds
+            /// checked, so if the slice is too short they panic rather than access invalid
+            /// memory.
+            #[allow(unsafe_code)]
             #[inline]
             pub unsafe fn new_unchecked(data: &'a [u8]) -> $name<'a> {
                 $name(data)
@@ -31,22 +116,94 @@ macro_rules! packet {
 
             /// Returns a reference to the slice backing this packet.
             #[inline]
-            pub fn data(&self) -> &[u8] {
+            pub fn data(&self) -> &'a [u8] {
                 self.0
             }
 
+            /// Returns the length of the header. For protocols with a fixed size header this is
+            /// `MIN_LEN`. For protocols with variable size headers it is read from the header
+            /// itself, but is never less than `MIN_LEN` or more than the length of the backing
+            /// slice.
+            #[inline]
+            pub fn header_len(&self) -> usize {
+                ::std::cmp::min($name::declared_header_len(self.0), self.0.len())
+            }
+
+            /// Returns the header length given by the header at the start of `data`, which must
+            /// be at least `MIN_LEN` bytes long, without limiting it to the length of `data`.
+            #[inline]
+            fn declared_header_len(data: &[u8]) -> usize {
+                let $data: &[u8] = data;
+                ::std::cmp::max($header_len, $min_len)
+            }
+
             /// Returns a slice to the part of the backing data that represents the header.
-            /// This is simply everything up until `min_len()`.
+            /// This is simply everything up until `header_len()`.
+            #[inline]
+            pub fn header(&self) -> &'a [u8] {
+                &self.0[..self.header_len()]
+            }
+
+            /// Returns a slice to the payload part of the backing data. This is everything after
+            /// the header, up to the end of the backing slice or, for protocols with a field
+            /// giving the length of the packet that `set_payload` keeps up to date, up to the end
+            /// given by that field.
             #[inline]
-            pub fn header(&self) -> &[u8] {
-                &self.0[..$min_len]
+            pub fn payload(&self) -> &'a [u8] {
+                &self.0[self.header_len()..$name::packet_end(self.0)]
+            }
+
+            /// Creates a packet from the start of `chain` and returns it together with the rest
+            /// of the chain, which holds the payload. The packet only covers the header. It is
+            /// borrowed from the chain if the header lies within its first slice, and is
+            /// otherwise copied into `scratch`. Returns `None` if the chain is shorter than the
+            /// header, or if the header does not fit in `scratch` when it has to be copied.
+            ///
+            /// Headers whose length is found by scanning them, rather than read from a field,
+            /// are only found in full when they are not split. See the `chain` module.
+            pub fn from_chain(
+                chain: ::chain::Chain<'a>,
+                scratch: &'a mut [u8],
+            ) -> Option<($name<'a>, ::chain::Chain<'a>)> {
+                let first = chain.first();
+                let data = if first.len() >= $min_len
+                    && $name::declared_header_len(first) <= first.len()
+                {
+                    first
+                } else {
+                    let mut len = $min_len;
+                    loop {
+                        if len > scratch.len() || !chain.copy_to(0, &mut scratch[..len]) {
+                            return None;
+                        }
+                        let header_len = $name::declared_header_len(&scratch[..len]);
+                        if header_len <= len {
+                            break;
+                        }
+                        len = header_len;
+                    }
+                    &scratch[..len]
+                };
+                let header_len = $name::declared_header_len(data);
+                Some(($name(&data[..header_len]), chain.skip(header_len)))
+            }
+
+            /// Returns a hexdump of the backing slice, with the header and payload annotated.
+            /// Further annotations, such as the fields of the header, can be added to it.
+            pub fn hexdump(&self) -> ::hexdump::Hexdump<'a> {
+                let header_len = self.header_len();
+                ::hexdump::Hexdump::new(self.0)
+                    .annotate(0..header_len, "header")
+                    .annotate(header_len..header_len + self.payload().len(), "payload")
             }
 
-            /// Returns a slice to the payload part of the backing data. This is simply everything
-            /// after the header.
+            /// Returns the offset in `data` where the packet ends. Never less than the header
+            /// length or more than the length of `data`.
             #[inline]
-            pub fn payload(&self) -> &[u8] {
-                &self.0[$min_len..]
+            fn packet_end(data: &[u8]) -> usize {
+                let header_len = $name(data).header_len();
+                let $len_data: &[u8] = data;
+                ::std::cmp::min(::std::cmp::max($len, header_len), data.len())
             }
         }
 
@@ -68,8 +225,14 @@ macro_rules! packet {
             }
 
             /// Creates a new mutable packet based on the given backing slice without checking
-            /// its length first. If the slice is too short, a subsequent read from a field might
-            /// result in invalid memory access.
+            /// its length first.
+            ///
+            /// # Safety
+            ///
+            /// The slice must be at least `MIN_LEN` bytes long. Field accesses are bounds
+            /// checked, so if the slice is too short they panic rather than access invalid
+            /// memory.
+            #[allow(unsafe_code)]
             #[inline]
             pub unsafe fn new_unchecked(data: &'a mut [u8]) -> $mut_name<'a> {
                 $mut_name(data)
@@ -78,7 +241,7 @@ macro_rules! packet {
             /// Returns an immutable version of the same packet and backed by the same byte slice.
             /// Used to access the getters.
             #[inline]
-            pub fn as_immutable(&'a self) -> $name<'a> {
+            pub fn as_immutable<'b>(&'b self) -> $name<'b> {
                 $name(&self.0[..])
             }
 
@@ -88,25 +251,196 @@ macro_rules! packet {
                 self.0
             }
 
+            /// Consumes the packet and returns the slice backing it, with its full lifetime.
+            #[inline]
+            pub fn into_data(self) -> &'a mut [u8] {
+                self.0
+            }
+
+            /// Returns the length of the header. See the immutable version of this packet for
+            /// details.
+            #[inline]
+            pub fn header_len(&self) -> usize {
+                let $data: &[u8] = &self.0[..];
+                ::std::cmp::min(::std::cmp::max($header_len, $min_len), self.0.len())
+            }
+
             /// Returns a mutable slice to the part of the backing data that represents the header.
-            /// This is simply everything up until `min_len()`.
+            /// This is simply everything up until `header_len()`.
             #[inline]
             pub fn header(&mut self) -> &mut [u8] {
-                &mut self.0[..$min_len]
+                let header_len = self.header_len();
+                &mut self.0[..header_len]
             }
 
-            /// Returns a mutable slice to the payload part of the backing data. This is simply
-            /// everything after the header.
+            /// Returns a mutable slice to the payload part of the backing data. See the immutable
+            /// version of this packet for details.
             #[inline]
             pub fn payload(&mut self) -> &mut [u8] {
-                &mut self.0[$min_len..]
+                let header_len = self.header_len();
+                let end = $name::packet_end(&self.0[..]);
+                &mut self.0[header_len..end]
+            }
+
+            /// Returns a mutable slice to the payload part of the backing data. An alias of
+            /// `payload`, named to go with `set_payload`.
+            #[inline]
+            pub fn payload_mut(&mut self) -> &mut [u8] {
+                self.payload()
+            }
+
+            /// Copies `payload` into the backing data directly after the header. For protocols
+            /// with a field holding the length of the packet or of its payload, such as IPv4,
+            /// IPv6 and UDP, the field is set to cover exactly the new payload. The IPv4 header
+            /// checksum is updated along with it, while transport checksums, which cover the
+            /// addresses of the IP header, are left to the caller. For protocols without a
+            /// length field the payload still ends with the backing slice.
+            ///
+            /// Returns false, leaving the packet unchanged, if the payload does not fit in the
+            /// backing slice or its length can not be held by the length field.
+            pub fn set_payload(&mut self, payload: &[u8]) -> bool {
+                let header_len = self.header_len();
+                let len = header_len + payload.len();
+                if len > self.0.len() || !$mut_name::set_len(&mut self.0[..], len) {
+                    return false;
+                }
+                self.0[header_len..len].copy_from_slice(payload);
+                true
+            }
+
+            /// Sets the length field, if any, for a packet of `len` bytes. Returns false,
+            /// without changing anything, if the field can not hold the length.
+            #[inline]
+            fn set_len($set_len_data: &mut [u8], $set_len_len: usize) -> bool {
+                $set_len
+            }
+        }
+
+        impl<'a> ::std::fmt::Debug for $name<'a> {
+            /// Shows the value returned by every getter, followed by the payload.
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                #[allow(unused_imports)]
+                use ::macros::{DebugCommonFields, DebugFields};
+                let mut debug = f.debug_struct(stringify!($name));
+                self.debug_common_fields(&mut debug);
+                self.debug_fields(&mut debug);
+                debug.field("payload", &self.payload()).finish()
+            }
+        }
+
+        impl<'a> ::std::fmt::Debug for $mut_name<'a> {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                ::std::fmt::Debug::fmt(&self.as_immutable(), f)
+            }
+        }
+
+        impl $buf_name {
+            /// Allocates a new packet with room for a header of `MIN_LEN` bytes followed by
+            /// `payload_len` bytes of payload. Everything is zeroed, except for fields like the
+            /// version that have only one sensible value and lengths covering the payload.
+            pub fn new_with_payload_len(payload_len: usize) -> $buf_name {
+                let mut data = vec![0; $min_len + payload_len];
+                Self::init(&mut data);
+                $buf_name(data)
+            }
+
+            #[inline]
+            fn init($init_data: &mut [u8]) {
+                $init
+            }
+
+            /// Replaces the payload with `payload`, growing or shrinking the buffer to end right
+            /// after it. See `set_payload` on the mutable view for how length fields are
+            /// updated. Returns false, leaving the packet unchanged, if the length is too large
+            /// for the length field.
+            pub fn set_payload(&mut self, payload: &[u8]) -> bool {
+                let len = self.packet().header_len() + payload.len();
+                let old_len = self.0.len();
+                if len > old_len {
+                    self.0.resize(len, 0);
+                }
+                if !self.packet_mut().set_payload(payload) {
+                    self.0.truncate(old_len);
+                    return false;
+                }
+                self.0.truncate(len);
+                true
+            }
+        }
+
+        #[cfg(feature = "arbitrary")]
+        impl<'u> ::arbitrary::Arbitrary<'u> for $buf_name {
+            /// Generates a packet of random bytes with a random payload length. The fields
+            /// that `new_with_payload_len` initializes, like versions and lengths, are then
+            /// set in the same way, making the packet well formed. Any bytes after the end
+            /// given by the length fields, such as those a length counted in 32 bit words can
+            /// not cover, are cut off.
+            fn arbitrary(u: &mut ::arbitrary::Unstructured<'u>) -> ::arbitrary::Result<Self> {
+                let max_payload_len = usize::from(u16::MAX) - $min_len;
+                let payload_len = ::std::cmp::min(u.arbitrary_len::<u8>()?, max_payload_len);
+                let mut data = vec![0; $min_len + payload_len];
+                u.fill_buffer(&mut data)?;
+                Self::init(&mut data);
+                let end = ::std::cmp::max($name::packet_end(&data), $min_len);
+                data.truncate(end);
+                Ok($buf_name(data))
+            }
+        }
+
+        impl<B: AsRef<[u8]>> $buf_name<B> {
+            /// The minimum number of bytes in this type of packet. Usually equal to the
+            /// header size of the protocol.
+            pub const MIN_LEN: usize = $min_len;
+
+            /// Creates a new owned packet backed by the given buffer. Returns `None` if the
+            /// buffer is shorter than the minimum length of this packet.
+            #[inline]
+            pub fn new(data: B) -> Option<$buf_name<B>> {
+                if data.as_ref().len() >= $min_len {
+                    Some($buf_name(data))
+                } else {
+                    None
+                }
+            }
+
+            /// Returns an immutable view of the packet. Used to access the getters.
+            #[inline]
+            pub fn packet(&self) -> $name<'_> {
+                $name(self.0.as_ref())
+            }
+
+            /// Returns the buffer backing this packet.
+            #[inline]
+            pub fn into_inner(self) -> B {
+                self.0
+            }
+        }
+
+        impl<B: AsRef<[u8]> + AsMut<[u8]>> $buf_name<B> {
+            /// Returns a mutable view of the packet. Used to access the setters.
+            #[inline]
+            pub fn packet_mut(&mut self) -> $mut_name<'_> {
+                $mut_name(self.0.as_mut())
             }
         }
     }
 }
 
+/// Defines the getters of packet type `$pkg`, and a `debug_fields` method listing them for its
+/// `Debug` implementation. Getters generated by another macro, such as the ones shared by
+/// several packet types through `icmpv4_common!` or the ones `#[packet]` derives from the
+/// fields of a struct, are given as `getters!(common $pkg ...)` instead. Those are listed by a
+/// `debug_common_fields` method, so that a packet type can also have getters of its own.
+/// Headers with a fixed layout are better declared with `#[packet]`, which generates plain
+/// field getters and setters from the struct.
 macro_rules! getters {
-    ($pkg:ident
+    (common $pkg:ident $($getters:tt)*) => {
+        getters!(@impl debug_common_fields, $pkg $($getters)*);
+    };
+    ($pkg:ident $($getters:tt)*) => {
+        getters!(@impl debug_fields, $pkg $($getters)*);
+    };
+    (@impl $debug:ident, $pkg:ident
     $(
         $(#[$doc: meta])*
         pub fn $name:ident(&$selff:ident) -> $type:ty $body:block
@@ -117,10 +451,30 @@ macro_rules! getters {
             pub fn $name(&$selff) -> $type {
                 $body
             })*
+
+            /// Adds the value of every getter above to `debug`.
+            fn $debug(&self, debug: &mut ::std::fmt::DebugStruct<'_, '_>) {
+                $(debug.field(stringify!($name), &self.$name());)*
+            }
         }
     }
 }
 
+/// Fallback for packet types without getters of their own, see `getters!`. Inherent methods
+/// take precedence over trait methods, so this is only used where `getters!` defined none.
+pub trait DebugFields {
+    fn debug_fields(&self, _debug: &mut ::std::fmt::DebugStruct<'_, '_>) {}
+}
+
+impl<T: ?Sized> DebugFields for T {}
+
+/// Fallback for packet types without common getters, see `getters!`.
+pub trait DebugCommonFields {
+    fn debug_common_fields(&self, _debug: &mut ::std::fmt::DebugStruct<'_, '_>) {}
+}
+
+impl<T: ?Sized> DebugCommonFields for T {}
+
 macro_rules! setters {
     ($pkg:ident
     $(
@@ -138,24 +492,129 @@ macro_rules! setters {
 }
 
 
+/// Implements `Debug` for the iterator `$iter` by listing the items it yields, so that the
+/// `Debug` output of a packet shows its options and records rather than their encoding.
+macro_rules! debug_items {
+    ($iter:ident<$($lifetime:lifetime),*>) => {
+        impl<$($lifetime),*> ::std::fmt::Debug for $iter<$($lifetime),*> {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                f.debug_list().entries(self.clone()).finish()
+            }
+        }
+    };
+}
+
+/// Reads a value of type `$type` from `$buff` at byte `$offset`, in native byte order, or with
+/// `from_be` as a big endian integer. `u24` and `u48` can only be read as big endian integers,
+/// from three and six bytes into the lowest bytes of a `u32` and a `u64`. The value is read
+/// through the safe, bounds checked functions of the `access` module.
 macro_rules! read_offset {
-    ($buff:expr, $offset:expr, $type:ty) => {{
-        let ptr = &$buff[$offset];
-        unsafe { *(ptr as *const _ as *const $type) }
-    }};
-    ($buff:expr, $offset:expr, $type:ident, from_be) => {{
+    ($buff:expr, $offset:expr, u24, from_be) => {
+        ::access::read_u24(&$buff[..], $offset)
+    };
+    ($buff:expr, $offset:expr, u48, from_be) => {
+        ::access::read_u48(&$buff[..], $offset)
+    };
+    ($buff:expr, $offset:expr, $type:ty) => {
+        ::access::read::<$type>(&$buff[..], $offset)
+    };
+    ($buff:expr, $offset:expr, $type:ident, from_be) => {
         $type::from_be(read_offset!($buff, $offset, $type))
-    }}
+    };
 }
 
+/// Writes `$value` of type `$type` to `$buff` at byte `$offset`. The inverse of `read_offset!`.
+/// For `u24` and `u48` the highest bytes of the `u32` or `u64` are ignored, and only three or
+/// six bytes are written.
 macro_rules! write_offset {
+    ($buff:expr, $offset:expr, $value:expr, u24, to_be) => {{
+        let (offset, value): (usize, u32) = ($offset, $value);
+        ::access::write_u24(&mut $buff[..], offset, value)
+    }};
+    ($buff:expr, $offset:expr, $value:expr, u48, to_be) => {{
+        let (offset, value): (usize, u64) = ($offset, $value);
+        ::access::write_u48(&mut $buff[..], offset, value)
+    }};
     ($buff:expr, $offset:expr, $value:expr, $type:ty) => {{
-        let ptr = (&mut $buff[$offset]) as *mut _ as *mut $type;
-        unsafe { *ptr = $value };
+        let (offset, value): (usize, $type) = ($offset, $value);
+        ::access::write(&mut $buff[..], offset, value)
     }};
-    ($buff:expr, $offset:expr, $value:expr, $type:ident, to_be) => {{
+    ($buff:expr, $offset:expr, $value:expr, $type:ident, to_be) => {
         write_offset!($buff, $offset, $type::to_be($value), $type)
-    }}
+    };
+}
+
+/// Declares the layout of the fixed header of `$packet` as a public `fields` module with one
+/// `layout::Field` per field, given as its first bit and width in bits, and checks the layout
+/// at compile time. See the `layout` module.
+macro_rules! fields {
+    ($packet:ident, $($name:ident: $start:expr, $width:expr;)*) => {
+        /// The positions of the fields in the fixed header. See the `layout` module.
+        pub mod fields {
+            use layout::Field;
+
+            $(pub const $name: Field = Field::new($start, $width);)*
+
+            /// All fields, in the order they appear in the header.
+            pub const ALL: &[Field] = &[$($name),*];
+
+            /// All fields with their names, in the order they appear in the header.
+            pub const NAMED: &[(&str, Field)] = &[$((stringify!($name), $name)),*];
+        }
+
+        const _: () = ::layout::check(fields::ALL, $packet::MIN_LEN);
+
+        impl<'a> $packet<'a> {
+            /// Compares this packet with `other` field by field, and the bytes beyond the
+            /// fields as ranges of bytes. See the `diff` module.
+            pub fn diff(&self, other: &$packet) -> ::alloc::vec::Vec<::diff::Difference> {
+                ::diff::diff(self.0, other.0, fields::NAMED)
+            }
+        }
+    };
+}
+
+/// Returns the `layout::Field` of `$width` bits starting at bit `$bit` of byte `$byte`, with the
+/// bits of a byte numbered from the most significant as in the RFC diagrams. For reading and
+/// writing fields with `read_field!` and `write_field!` in headers without a `fields` table,
+/// e.g. `read_field!(self.0, bits!(2, 0, 3), u8)` for the top three bits of the third byte.
+/// A bit offset outside of the byte fails to compile.
+macro_rules! bits {
+    ($byte:expr, $bit:expr, $width:expr) => {{
+        const _: () = assert!($bit < 8, "Bit offset outside of the byte");
+        ::layout::Field::new($byte * 8 + $bit, $width)
+    }};
+}
+
+/// Reads the `layout::Field` `$field` from `$buff` as the big endian integer type `$type`. The
+/// offset, shift and mask are all computed at compile time, where it is also checked that the
+/// field fits in `$type`.
+macro_rules! read_field {
+    ($buff:expr, $field:expr, $type:ident) => {{
+        const FIELD: ::layout::Field = $field;
+        const SIZE: usize = ::std::mem::size_of::<$type>();
+        const START: usize = FIELD.byte_offset();
+        const _: () = assert!(FIELD.fits(SIZE), "Field does not fit in the type it is read as");
+        let value = ::access::read::<$type>(&$buff[..], START);
+        ($type::from_be(value) >> FIELD.shift(SIZE)) & FIELD.mask() as $type
+    }};
+}
+
+/// Writes `$value`, of the integer type `$type`, to the `layout::Field` `$field` in `$buff`,
+/// leaving the bits around it untouched. The inverse of `read_field!`.
+macro_rules! write_field {
+    ($buff:expr, $field:expr, $value:expr, $type:ident) => {{
+        const FIELD: ::layout::Field = $field;
+        const SIZE: usize = ::std::mem::size_of::<$type>();
+        const START: usize = FIELD.byte_offset();
+        const SHIFT: usize = FIELD.shift(SIZE);
+        const MASK: $type = (FIELD.mask() << SHIFT) as $type;
+        const _: () = assert!(FIELD.fits(SIZE), "Field does not fit in the type it is written as");
+        let value: $type = $value;
+        let old = $type::from_be(::access::read::<$type>(&$buff[..], START));
+        let new = (old & !MASK) | ((value << SHIFT) & MASK);
+        ::access::write::<$type>(&mut $buff[..], START, new.to_be());
+    }};
 }
 
 /// Creates a test for the `$set_name` setter and `$name` getter of packet type
@@ -189,3 +648,90 @@ macro_rules! setget_test {
         }
     }
 }
+
+/// Generates a test named `$name`, run with the `arbitrary` feature, that generates packets of
+/// each `$buf` type from 32 different byte strings. It checks that the header has its minimum
+/// length and that the length fields cover the whole packet, and then that `$check` holds.
+#[allow(unused_macros)]
+macro_rules! arbitrary_test {
+    ($name:ident, $($buf:ident $(=> |$packet:ident| $check:expr)?),+ $(,)?) => {
+        #[cfg(feature = "arbitrary")]
+        #[test]
+        fn $name() {
+            use arbitrary::{Arbitrary, Unstructured};
+
+            for seed in 0..32u8 {
+                let bytes: Vec<u8> =
+                    (0..=255u8).map(|i| i.wrapping_mul(seed | 1) ^ seed).collect();
+                $(
+                    let buf = <$buf>::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+                    let packet = buf.packet();
+                    assert_eq!(<$buf>::MIN_LEN, packet.header_len(), stringify!($buf));
+                    assert_eq!(
+                        packet.data().len(),
+                        packet.header_len() + packet.payload().len(),
+                        stringify!($buf)
+                    );
+                    $(
+                        let $packet = packet;
+                        assert!($check, stringify!($buf));
+                    )?
+                )+
+            }
+        }
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use types::*;
+
+    #[test]
+    fn offsets_24_bit() {
+        let mut data = [0xaa; 5];
+        write_offset!(data, 1, 0xff12_3456, u24, to_be);
+        assert_eq!([0xaa, 0x12, 0x34, 0x56, 0xaa], data);
+        let value: u24 = read_offset!(data, 1, u24, from_be);
+        assert_eq!(0x12_3456, value);
+    }
+
+    #[test]
+    fn offsets_48_bit() {
+        let mut data = [0xaa; 8];
+        write_offset!(data, 1, 0xffff_0102_0304_0506, u48, to_be);
+        assert_eq!([0xaa, 1, 2, 3, 4, 5, 6, 0xaa], data);
+        let value: u48 = read_offset!(data, 1, u48, from_be);
+        assert_eq!(0x0102_0304_0506, value);
+    }
+
+    #[test]
+    fn offsets_128_bit() {
+        let mut data = [0u8; 17];
+        write_offset!(data, 1, 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10, u128, to_be);
+        assert_eq!([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16], data);
+        let value = read_offset!(data, 1, u128, from_be);
+        assert_eq!(0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10, value);
+    }
+
+    #[test]
+    fn bits() {
+        const FLAGS: ::layout::Field = bits!(6, 0, 3);
+        assert_eq!(::layout::Field::new(48, 3), FLAGS);
+        let mut data = [0u8; 4];
+        write_field!(data, bits!(1, 3, 9), 0x1ff, u16);
+        assert_eq!([0, 0x1f, 0xf0, 0], data);
+        assert_eq!(0x1ff, read_field!(data, bits!(1, 3, 9), u16));
+        assert_eq!(0x1f, read_field!(data, bits!(1, 0, 8), u8));
+        write_field!(data, bits!(1, 4, 4), 0, u8);
+        assert_eq!([0, 0x10, 0xf0, 0], data);
+    }
+
+    #[test]
+    #[should_panic]
+    fn offsets_24_bit_out_of_bounds() {
+        let data = [0; 4];
+        let offset = ::std::hint::black_box(2);
+        let _ = read_offset!(data, offset, u24, from_be);
+    }
+}
@@ -0,0 +1,241 @@
+//! Modbus over TCP, the Modbus Messaging on TCP/IP Implementation Guide v1.0b.
+//!
+//! Each Modbus request or response on a TCP connection starts with a seven byte MBAP
+//! (Modbus Application Protocol) header, followed by the protocol data unit, a function code
+//! and its data. Exception responses set the high bit of the function code of the request and
+//! carry a single exception code.
+
+use std::cmp;
+use std::fmt;
+
+/// The TCP port Modbus servers listen on.
+pub const TCP_PORT: u16 = 502;
+
+packet!(
+    /// A Modbus/TCP message. The header is the MBAP header and the function code, and the
+    /// payload is the rest of the PDU, see `pdu`.
+    ModbusPacket,
+    MutModbusPacket,
+    ModbusPacketBuf,
+    8,
+    |_data| 8,
+    len |data| 6 + usize::from(read_offset!(data, 4, u16, from_be)),
+    set_len |data, len| {
+        len - 6 <= usize::from(u16::MAX) && {
+            write_offset!(data, 4, (len - 6) as u16, u16, to_be);
+            true
+        }
+    },
+    init |data| {
+        write_offset!(data, 2, 0u16, u16);
+        MutModbusPacket::set_len(data, data.len());
+    }
+);
+
+getters!(ModbusPacket
+    /// Returns the transaction identifier, copied from the request into its response.
+    pub fn transaction_id(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    /// Returns the protocol identifier, zero for Modbus.
+    pub fn protocol_id(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the number of bytes following this field, the unit identifier and the PDU.
+    pub fn length(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    /// Returns the unit identifier, addressing a device behind a gateway to a serial line.
+    pub fn unit_id(&self) -> u8 {
+        read_offset!(self.0, 6, u8)
+    }
+
+    /// Returns the function code, with the exception bit set in exception responses.
+    pub fn function_code(&self) -> FunctionCode {
+        FunctionCode(read_offset!(self.0, 7, u8))
+    }
+
+    /// Returns true if this is an exception response.
+    pub fn is_exception(&self) -> bool {
+        self.function_code().value() & 0x80 != 0
+    }
+
+    /// Returns the exception code of an exception response, or `None` if this is not an
+    /// exception response or the code is missing.
+    pub fn exception_code(&self) -> Option<ExceptionCode> {
+        if self.is_exception() {
+            self.pdu_data().first().map(|&code| ExceptionCode(code))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the PDU, the function code and its data, limited to the length given by the
+    /// header.
+    pub fn pdu(&self) -> &'a [u8] {
+        let end = cmp::min(6 + usize::from(self.length()), self.0.len());
+        self.0.get(7..end).unwrap_or(&[])
+    }
+
+    /// Returns the data of the PDU following the function code.
+    pub fn pdu_data(&self) -> &'a [u8] {
+        self.pdu().get(1..).unwrap_or(&[])
+    }
+);
+
+setters!(MutModbusPacket
+    pub fn set_transaction_id(&mut self, transaction_id: u16) {
+        write_offset!(self.0, 0, transaction_id, u16, to_be);
+    }
+
+    pub fn set_protocol_id(&mut self, protocol_id: u16) {
+        write_offset!(self.0, 2, protocol_id, u16, to_be);
+    }
+
+    pub fn set_length(&mut self, length: u16) {
+        write_offset!(self.0, 4, length, u16, to_be);
+    }
+
+    pub fn set_unit_id(&mut self, unit_id: u8) {
+        write_offset!(self.0, 6, unit_id, u8);
+    }
+
+    pub fn set_function_code(&mut self, function_code: FunctionCode) {
+        write_offset!(self.0, 7, function_code.value(), u8);
+    }
+);
+
+
+/// Represents a Modbus function code.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FunctionCode(pub u8);
+
+impl FunctionCode {
+    pub const READ_COILS: FunctionCode = FunctionCode(1);
+    pub const READ_DISCRETE_INPUTS: FunctionCode = FunctionCode(2);
+    pub const READ_HOLDING_REGISTERS: FunctionCode = FunctionCode(3);
+    pub const READ_INPUT_REGISTERS: FunctionCode = FunctionCode(4);
+    pub const WRITE_SINGLE_COIL: FunctionCode = FunctionCode(5);
+    pub const WRITE_SINGLE_REGISTER: FunctionCode = FunctionCode(6);
+    pub const READ_EXCEPTION_STATUS: FunctionCode = FunctionCode(7);
+    pub const DIAGNOSTICS: FunctionCode = FunctionCode(8);
+    pub const WRITE_MULTIPLE_COILS: FunctionCode = FunctionCode(15);
+    pub const WRITE_MULTIPLE_REGISTERS: FunctionCode = FunctionCode(16);
+    pub const REPORT_SERVER_ID: FunctionCode = FunctionCode(17);
+    pub const MASK_WRITE_REGISTER: FunctionCode = FunctionCode(22);
+    pub const READ_WRITE_MULTIPLE_REGISTERS: FunctionCode = FunctionCode(23);
+    pub const ENCAPSULATED_INTERFACE_TRANSPORT: FunctionCode = FunctionCode(43);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns the function code with the exception bit cleared, the code of the request an
+    /// exception response answers.
+    pub fn without_exception(&self) -> FunctionCode {
+        FunctionCode(self.0 & 0x7f)
+    }
+
+    /// Returns the function code of the exception response to a request with this code.
+    pub fn to_exception(&self) -> FunctionCode {
+        FunctionCode(self.0 | 0x80)
+    }
+}
+
+/// Represents the exception code of a Modbus exception response.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExceptionCode(pub u8);
+
+impl ExceptionCode {
+    pub const ILLEGAL_FUNCTION: ExceptionCode = ExceptionCode(1);
+    pub const ILLEGAL_DATA_ADDRESS: ExceptionCode = ExceptionCode(2);
+    pub const ILLEGAL_DATA_VALUE: ExceptionCode = ExceptionCode(3);
+    pub const SERVER_DEVICE_FAILURE: ExceptionCode = ExceptionCode(4);
+    pub const ACKNOWLEDGE: ExceptionCode = ExceptionCode(5);
+    pub const SERVER_DEVICE_BUSY: ExceptionCode = ExceptionCode(6);
+    pub const MEMORY_PARITY_ERROR: ExceptionCode = ExceptionCode(8);
+    pub const GATEWAY_PATH_UNAVAILABLE: ExceptionCode = ExceptionCode(10);
+    pub const GATEWAY_TARGET_FAILED: ExceptionCode = ExceptionCode(11);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl<'a> fmt::Display for ModbusPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Modbus transaction={} unit={} function={}",
+            self.transaction_id(),
+            self.unit_id(),
+            self.function_code().value()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! modbus_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutModbusPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    modbus_setget_test!(transaction_id, set_transaction_id, 0xabcd, 0, [0xab, 0xcd]);
+    modbus_setget_test!(protocol_id, set_protocol_id, 0xabcd, 2, [0xab, 0xcd]);
+    modbus_setget_test!(length, set_length, 0xabcd, 4, [0xab, 0xcd]);
+    modbus_setget_test!(unit_id, set_unit_id, 0xab, 6, [0xab]);
+    modbus_setget_test!(
+        function_code,
+        set_function_code,
+        FunctionCode::WRITE_MULTIPLE_REGISTERS,
+        7,
+        [16]
+    );
+
+    #[test]
+    fn read_holding_registers() {
+        let data = [0, 1, 0, 0, 0, 6, 0x11, 0x03, 0x00, 0x6b, 0x00, 0x03, 0xff];
+        let testee = ModbusPacket::new(&data).unwrap();
+        assert_eq!(8, testee.header_len());
+        assert_eq!(FunctionCode::READ_HOLDING_REGISTERS, testee.function_code());
+        assert!(!testee.is_exception());
+        assert_eq!(None, testee.exception_code());
+        assert_eq!(&[0x03, 0x00, 0x6b, 0x00, 0x03], testee.pdu());
+        assert_eq!(&[0x00, 0x6b, 0x00, 0x03], testee.pdu_data());
+        // The payload ends with the length field, leaving the trailing byte out
+        assert_eq!(testee.pdu_data(), testee.payload());
+    }
+
+    #[test]
+    fn exception_response() {
+        let data = [0, 1, 0, 0, 0, 3, 0x11, 0x83, 0x02];
+        let testee = ModbusPacket::new(&data).unwrap();
+        assert!(testee.is_exception());
+        let function_code = testee.function_code().without_exception();
+        assert_eq!(FunctionCode::READ_HOLDING_REGISTERS, function_code);
+        assert_eq!(testee.function_code(), function_code.to_exception());
+        assert_eq!(Some(ExceptionCode::ILLEGAL_DATA_ADDRESS), testee.exception_code());
+        assert_eq!(None, ModbusPacket::new(&data[..8]).unwrap().exception_code());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 8 + 6];
+        let mut testee = MutModbusPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[0x00, 0x6b, 0x00, 0x03]));
+        assert_eq!(6, testee.as_immutable().length());
+        assert_eq!(&[0x00, 0x6b, 0x00, 0x03], testee.as_immutable().payload());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, ModbusPacketBuf => |packet| packet.protocol_id() == 0);
+}
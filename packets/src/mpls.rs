@@ -0,0 +1,367 @@
+//! Multiprotocol Label Switching label stacks, RFC 3032.
+
+use std::fmt;
+use types::*;
+
+#[packet(header_len = |data| stack_len(data), init = |data| data[2] |= 0x01)]
+/// An MPLS label stack. The accessors refer to the top entry of the stack, and the header
+/// spans every entry up to and including the one with the bottom of stack bit set.
+pub struct Mpls {
+    label: u20,
+    /// Returns the traffic class of the top entry.
+    tc: u3,
+    bottom_of_stack: bool,
+    ttl: u8,
+}
+
+getters!(MplsPacket
+    /// Returns an iterator over the entries in the label stack, starting with the top entry.
+    pub fn entries(&self) -> LabelStackEntries<'a> {
+        LabelStackEntries { data: &self.0[..self.header_len()] }
+    }
+);
+
+impl<'a> MplsPacket<'a> {
+    /// Returns the top entry of the stack.
+    pub fn top(&self) -> LabelStackEntry {
+        LabelStackEntry(read_offset!(self.0, 0, u32, from_be))
+    }
+}
+
+impl<'a> MutMplsPacket<'a> {
+    /// Writes `entry` at `index` in the label stack, where index zero is the top entry. Returns
+    /// false if the backing slice is too short to hold the entry.
+    pub fn set_entry(&mut self, index: usize, entry: LabelStackEntry) -> bool {
+        match index.checked_mul(4) {
+            Some(offset) if offset.checked_add(4).is_some_and(|end| end <= self.0.len()) => {
+                write_offset!(self.0, offset, entry.value(), u32, to_be);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the length of the label stack at the start of `data`. If no entry has the bottom of
+/// stack bit set, every complete entry in `data` is counted.
+fn stack_len(data: &[u8]) -> usize {
+    LabelStackEntries { data }.count() * 4
+}
+
+
+/// A single 32 bit label stack entry.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct LabelStackEntry(pub u32);
+
+impl LabelStackEntry {
+    /// Label value signaling that the label stack must be popped and the packet forwarded based
+    /// on its IPv4 header.
+    pub const IPV4_EXPLICIT_NULL: u20 = 0;
+    /// Label value signaling that the packet is an OAM alert.
+    pub const ROUTER_ALERT: u20 = 1;
+    /// Label value signaling that the label stack must be popped and the packet forwarded based
+    /// on its IPv6 header.
+    pub const IPV6_EXPLICIT_NULL: u20 = 2;
+    /// Label value that a router may advertise to ask for the label to be popped before the
+    /// packet reaches it. Never present on the wire.
+    pub const IMPLICIT_NULL: u20 = 3;
+
+    /// Creates an entry from its parts. Bits outside the width of each field are ignored.
+    pub fn new(label: u20, tc: u3, bottom_of_stack: bool, ttl: u8) -> LabelStackEntry {
+        LabelStackEntry(0)
+            .with_label(label)
+            .with_tc(tc)
+            .with_bottom_of_stack(bottom_of_stack)
+            .with_ttl(ttl)
+    }
+
+    #[inline]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+
+    pub fn label(&self) -> u20 {
+        self.0 >> 12
+    }
+
+    /// Returns the traffic class, previously known as the experimental bits.
+    pub fn tc(&self) -> u3 {
+        ((self.0 >> 9) & 0x7) as u3
+    }
+
+    pub fn bottom_of_stack(&self) -> bool {
+        self.0 & 0x100 != 0
+    }
+
+    pub fn ttl(&self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Returns a copy of this entry with the label replaced.
+    pub fn with_label(self, label: u20) -> LabelStackEntry {
+        LabelStackEntry((self.0 & 0x0000_0fff) | (label << 12))
+    }
+
+    /// Returns a copy of this entry with the traffic class replaced.
+    pub fn with_tc(self, tc: u3) -> LabelStackEntry {
+        LabelStackEntry((self.0 & !0x0000_0e00) | (u32::from(tc & 0x7) << 9))
+    }
+
+    /// Returns a copy of this entry with the bottom of stack bit replaced.
+    pub fn with_bottom_of_stack(self, bottom_of_stack: bool) -> LabelStackEntry {
+        LabelStackEntry((self.0 & !0x0000_0100) | (u32::from(bottom_of_stack) << 8))
+    }
+
+    /// Returns a copy of this entry with the TTL replaced.
+    pub fn with_ttl(self, ttl: u8) -> LabelStackEntry {
+        LabelStackEntry((self.0 & !0x0000_00ff) | u32::from(ttl))
+    }
+}
+
+
+/// Iterator over the entries in an MPLS label stack. Iteration stops after the entry with the
+/// bottom of stack bit set, or when the data runs out.
+#[derive(Clone)]
+pub struct LabelStackEntries<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(LabelStackEntries<'a>);
+
+impl<'a> Iterator for LabelStackEntries<'a> {
+    type Item = LabelStackEntry;
+
+    fn next(&mut self) -> Option<LabelStackEntry> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let entry = LabelStackEntry(read_offset!(self.data, 0, u32, from_be));
+        self.data = if entry.bottom_of_stack() { &[] } else { &self.data[4..] };
+        Some(entry)
+    }
+}
+
+
+/// Pushes and pops label stack entries in front of a packet, using free space before the packet
+/// in the same buffer.
+///
+/// The builder tracks where the stack currently starts in the buffer and how many entries it
+/// holds, and maintains the bottom of stack bit, so that only the first entry pushed onto a
+/// packet without labels gets it set.
+#[derive(Debug)]
+pub struct MplsStackBuilder<'a> {
+    buffer: &'a mut [u8],
+    start: usize,
+    depth: usize,
+}
+
+impl<'a> MplsStackBuilder<'a> {
+    /// Creates a builder for a packet without a label stack, starting at `start` in `buffer`.
+    /// Everything before `start` is available for pushing labels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is beyond the end of `buffer`.
+    pub fn with_payload(buffer: &'a mut [u8], start: usize) -> MplsStackBuilder<'a> {
+        assert!(start <= buffer.len(), "start is beyond the end of the buffer");
+        MplsStackBuilder { buffer, start, depth: 0 }
+    }
+
+    /// Creates a builder for an existing label stack starting at `start` in `buffer`. Returns
+    /// `None` if there is no complete label stack at `start`, meaning no entry has the bottom of
+    /// stack bit set.
+    pub fn with_stack(buffer: &'a mut [u8], start: usize) -> Option<MplsStackBuilder<'a>> {
+        let mut depth = 0;
+        let mut complete = false;
+        for entry in (LabelStackEntries { data: buffer.get(start..)? }) {
+            depth += 1;
+            complete = entry.bottom_of_stack();
+        }
+        if !complete {
+            return None;
+        }
+        Some(MplsStackBuilder { buffer, start, depth })
+    }
+
+    /// Returns the offset in the buffer where the packet, including any label stack, starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the number of entries in the label stack.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Pushes `entry` onto the top of the stack, setting its bottom of stack bit if the stack is
+    /// empty and clearing it otherwise. Returns the new start of the packet, or `None` if there
+    /// is not enough space before the packet.
+    pub fn push(&mut self, entry: LabelStackEntry) -> Option<usize> {
+        if self.start < 4 {
+            return None;
+        }
+        self.start -= 4;
+        let entry = entry.with_bottom_of_stack(self.depth == 0);
+        write_offset!(self.buffer, self.start, entry.value(), u32, to_be);
+        self.depth += 1;
+        Some(self.start)
+    }
+
+    /// Pops the top entry off the stack and returns it, or `None` if the stack is empty.
+    pub fn pop(&mut self) -> Option<LabelStackEntry> {
+        if self.depth == 0 {
+            return None;
+        }
+        let entry = LabelStackEntry(read_offset!(self.buffer, self.start, u32, from_be));
+        self.start += 4;
+        self.depth -= 1;
+        Some(entry)
+    }
+
+    /// Returns the label stack, or `None` if it is empty.
+    pub fn stack(&self) -> Option<MplsPacket<'_>> {
+        if self.depth == 0 {
+            None
+        } else {
+            MplsPacket::new(&self.buffer[self.start..])
+        }
+    }
+
+    /// Returns the packet following the label stack.
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer[self.start + self.depth * 4..]
+    }
+}
+
+impl<'a> fmt::Display for MplsPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MPLS label={} ttl={} depth={}",
+            self.label(),
+            self.ttl(),
+            self.entries().count()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! mpls_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutMplsPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    mpls_setget_test!(label, set_label, 0xfffff, 0, [0xff, 0xff, 0xf0]);
+    mpls_setget_test!(tc, set_tc, 0x7, 2, [0x0e]);
+    mpls_setget_test!(bottom_of_stack, set_bottom_of_stack, true, 2, [0x01]);
+    mpls_setget_test!(ttl, set_ttl, 0xff, 3, [0xff]);
+
+    #[test]
+    fn entry_fields() {
+        let entry = LabelStackEntry::new(16, 5, true, 64);
+        assert_eq!(0x0001_0b40, entry.value());
+        assert_eq!(16, entry.label());
+        assert_eq!(5, entry.tc());
+        assert!(entry.bottom_of_stack());
+        assert_eq!(64, entry.ttl());
+        assert_eq!(0xfffff, LabelStackEntry::new(!0, 0, false, 0).label());
+    }
+
+    #[test]
+    fn iterate_stack() {
+        let data = [
+            0x00, 0x01, 0x00, 0x40, // Label 16, TTL 64
+            0x00, 0x02, 0x11, 0x3f, // Label 33, bottom of stack, TTL 63
+            0x45, 0x00, 0x00, 0x00, // IPv4 payload
+        ];
+        let testee = MplsPacket::new(&data).unwrap();
+        assert_eq!(16, testee.label());
+        assert_eq!(8, testee.header_len());
+        assert_eq!(&data[8..], testee.payload());
+        assert_eq!(
+            vec![
+                LabelStackEntry::new(16, 0, false, 64),
+                LabelStackEntry::new(33, 0, true, 63),
+            ],
+            testee.entries().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn unterminated_stack() {
+        let data = [0x00, 0x01, 0x00, 0x40, 0x00, 0x02, 0x10];
+        let testee = MplsPacket::new(&data).unwrap();
+        assert_eq!(1, testee.entries().count());
+        assert_eq!(4, testee.header_len());
+    }
+
+    #[test]
+    fn set_entry() {
+        let mut data = [0; 8];
+        let mut testee = MutMplsPacket::new(&mut data).unwrap();
+        assert!(testee.set_entry(1, LabelStackEntry::new(33, 0, true, 63)));
+        assert!(!testee.set_entry(2, LabelStackEntry::default()));
+        assert!(!testee.set_entry(usize::MAX / 4, LabelStackEntry::default()));
+        assert!(!testee.set_entry(usize::MAX, LabelStackEntry::default()));
+        assert_eq!([0, 0, 0, 0, 0x00, 0x02, 0x11, 0x3f], data);
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let mut buffer = [0; 8 + 2];
+        buffer[8..].copy_from_slice(&[0x45, 0x00]);
+        let mut builder = MplsStackBuilder::with_payload(&mut buffer, 8);
+        assert!(builder.stack().is_none());
+        assert_eq!(Some(4), builder.push(LabelStackEntry::new(100, 0, false, 64)));
+        assert_eq!(Some(0), builder.push(LabelStackEntry::new(200, 0, true, 64)));
+        assert_eq!(None, builder.push(LabelStackEntry::default()));
+        assert_eq!(2, builder.depth());
+        {
+            let stack = builder.stack().unwrap();
+            assert_eq!(200, stack.label());
+            assert!(!stack.bottom_of_stack());
+            assert_eq!(
+                vec![200, 100],
+                stack.entries().map(|entry| entry.label()).collect::<Vec<_>>()
+            );
+            assert_eq!(&[0x45, 0x00], stack.payload());
+        }
+
+        assert_eq!(200, builder.pop().unwrap().label());
+        let bottom = builder.pop().unwrap();
+        assert_eq!(100, bottom.label());
+        assert!(bottom.bottom_of_stack());
+        assert_eq!(None, builder.pop());
+        assert_eq!(8, builder.start());
+        assert_eq!(&[0x45, 0x00], builder.payload());
+    }
+
+    #[test]
+    fn builder_with_existing_stack() {
+        let mut buffer = [
+            0x00, 0x00, 0x00, 0x00, // Free space
+            0x00, 0x01, 0x01, 0x40, // Label 16, bottom of stack
+            0x60, // IPv6 payload
+        ];
+        {
+            let mut builder = MplsStackBuilder::with_stack(&mut buffer, 4).unwrap();
+            assert_eq!(1, builder.depth());
+            builder.push(LabelStackEntry::new(17, 0, true, 64)).unwrap();
+        }
+        let testee = MplsPacket::new(&buffer).unwrap();
+        assert_eq!(
+            vec![false, true],
+            testee.entries().map(|entry| entry.bottom_of_stack()).collect::<Vec<_>>()
+        );
+
+        let mut unterminated = [0x00, 0x01, 0x00, 0x40];
+        assert!(MplsStackBuilder::with_stack(&mut unterminated, 0).is_none());
+    }
+
+    arbitrary_test!(arbitrary_well_formed, MplsPacketBuf => |packet| packet.bottom_of_stack());
+}
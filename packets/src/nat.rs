@@ -0,0 +1,383 @@
+//! Rewriting the addresses and ports of packets in place, as done by network address
+//! translation.
+//!
+//! Changing an address invalidates the IPv4 header checksum and the checksum of every transport
+//! protocol that covers a pseudo header, and changing a port invalidates the transport
+//! checksum. The functions here adjust all of them incrementally as in RFC 1624, so the rest of
+//! the packet is never summed again and a packet that arrived with a bad checksum still has a
+//! bad checksum after the rewrite. The transport protocols handled are TCP, UDP, UDP-Lite, DCCP
+//! and, over IPv6, ICMPv6. A UDP checksum of zero means the sender did not compute one and is
+//! left as zero.
+//!
+//! ```rust
+//! use rips_packets::ipv4::{Ipv4Builder, Ipv4Packet, MutIpv4Packet};
+//! use rips_packets::nat::{self, Side};
+//! use rips_packets::udp::{UdpBuilder, UdpPacket};
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! let udp = UdpBuilder::new().source_port(40000).destination_port(53).payload(b"query");
+//! let mut data = Ipv4Builder::new()
+//!     .source(Ipv4Addr::new(192, 168, 0, 2))
+//!     .destination(Ipv4Addr::new(198, 51, 100, 1))
+//!     .payload(udp)
+//!     .build()
+//!     .unwrap();
+//!
+//! let public = Ipv4Addr::new(203, 0, 113, 7);
+//! let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+//! nat::rewrite_ipv4(&mut packet, Side::Source, public, Some(61000)).unwrap();
+//!
+//! let packet = Ipv4Packet::new(&data).unwrap();
+//! assert!(packet.is_checksum_valid());
+//! let udp = UdpPacket::new(packet.payload()).unwrap();
+//! assert_eq!(61000, udp.source_port());
+//! assert!(udp.verify_checksum(IpAddr::V4(public), IpAddr::V4(packet.destination())));
+//! ```
+
+use checksum;
+use ip::Protocol;
+use ipv4::MutIpv4Packet;
+use ipv6::MutIpv6Packet;
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Which end of a packet to rewrite.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Side {
+    Source,
+    Destination,
+}
+
+/// The reason a rewrite was refused. The packet is left unchanged when an error is returned.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NatError {
+    /// A port was given, but the payload is not of a transport protocol with ports handled
+    /// here.
+    NoPorts(Protocol),
+    /// The payload does not hold the transport header that must be updated, because it is
+    /// truncated or the packet is a fragment other than the first.
+    MissingTransportHeader,
+}
+
+impl fmt::Display for NatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NatError::NoPorts(protocol) => write!(f, "No ports to rewrite in {}", protocol),
+            NatError::MissingTransportHeader => f.write_str("Transport header missing"),
+        }
+    }
+}
+
+impl Error for NatError {}
+
+/// Sets the address of `side` of an IPv4 packet to `address`, and the port of the same side to
+/// `port` if given. Updates the header checksum and the transport checksum to match.
+///
+/// Fragments other than the first carry no transport header, so only their address is
+/// rewritten, and giving a port for them is an error.
+pub fn rewrite_ipv4(
+    packet: &mut MutIpv4Packet,
+    side: Side,
+    address: Ipv4Addr,
+    port: Option<u16>,
+) -> Result<(), NatError> {
+    let (old, transport) = {
+        let packet = packet.as_immutable();
+        let old = match side {
+            Side::Source => packet.source(),
+            Side::Destination => packet.destination(),
+        };
+        let transport = if packet.fragment_offset() == 0 {
+            Transport::find(packet.protocol(), false, packet.payload(), port)?
+        } else if port.is_some() {
+            return Err(NatError::MissingTransportHeader);
+        } else {
+            None
+        };
+        (old, transport)
+    };
+
+    match side {
+        Side::Source => packet.set_source(address),
+        Side::Destination => packet.set_destination(address),
+    }
+    packet.update_checksum_for(&old.octets(), &address.octets());
+    if let Some(transport) = transport {
+        transport.rewrite(packet.payload(), side, &old.octets(), &address.octets(), port);
+    }
+    Ok(())
+}
+
+/// Sets the address of `side` of an IPv6 packet to `address`, and the port of the same side to
+/// `port` if given. Updates the transport checksum to match. The transport header must follow
+/// the fixed header directly, as packets with extension headers are rewritten as if their
+/// payload was of an unknown protocol.
+pub fn rewrite_ipv6(
+    packet: &mut MutIpv6Packet,
+    side: Side,
+    address: Ipv6Addr,
+    port: Option<u16>,
+) -> Result<(), NatError> {
+    let (old, transport) = {
+        let packet = packet.as_immutable();
+        let old = match side {
+            Side::Source => packet.source(),
+            Side::Destination => packet.destination(),
+        };
+        (old, Transport::find(packet.next_header(), true, packet.payload(), port)?)
+    };
+
+    match side {
+        Side::Source => packet.set_source(address),
+        Side::Destination => packet.set_destination(address),
+    }
+    if let Some(transport) = transport {
+        transport.rewrite(packet.payload(), side, &old.octets(), &address.octets(), port);
+    }
+    Ok(())
+}
+
+/// The position of the checksum in a transport header whose checksum covers the addresses.
+struct Transport {
+    checksum: usize,
+    /// True for UDP, where a checksum of zero means there is no checksum.
+    optional: bool,
+}
+
+impl Transport {
+    /// Returns the checksum position for `protocol`, or `None` if its payload needs no update.
+    /// Fails if `port` is given and the protocol has no ports, or if `payload` is too short.
+    fn find(
+        protocol: Protocol,
+        ipv6: bool,
+        payload: &[u8],
+        port: Option<u16>,
+    ) -> Result<Option<Transport>, NatError> {
+        let (checksum, ports) = match protocol {
+            Protocol::TCP => (16, true),
+            Protocol::UDP | Protocol::UDPLITE | Protocol::DCCP => (6, true),
+            Protocol::ICMPV6 if ipv6 => (2, false),
+            _ if port.is_some() => return Err(NatError::NoPorts(protocol)),
+            _ => return Ok(None),
+        };
+        if port.is_some() && !ports {
+            return Err(NatError::NoPorts(protocol));
+        }
+        if payload.len() < checksum + 2 {
+            return Err(NatError::MissingTransportHeader);
+        }
+        let optional = protocol == Protocol::UDP;
+        Ok(Some(Transport { checksum, optional }))
+    }
+
+    /// Writes `port` to the header in `payload` and adjusts the checksum for it and for the
+    /// change of address from `old` to `new`.
+    fn rewrite(&self, payload: &mut [u8], side: Side, old: &[u8], new: &[u8], port: Option<u16>) {
+        let field = self.checksum..self.checksum + 2;
+        let mut checksum = u16::from_be_bytes([payload[field.start], payload[field.start + 1]]);
+        let unused = self.optional && checksum == 0;
+        checksum = checksum::update(checksum, old, new);
+        if let Some(port) = port {
+            let offset = match side {
+                Side::Source => 0,
+                Side::Destination => 2,
+            };
+            let old = [payload[offset], payload[offset + 1]];
+            let new = port.to_be_bytes();
+            payload[offset..offset + 2].copy_from_slice(&new);
+            checksum = checksum::update(checksum, &old, &new);
+        }
+        if !unused {
+            if self.optional && checksum == 0 {
+                checksum = 0xffff;
+            }
+            payload[field].copy_from_slice(&checksum.to_be_bytes());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icmpv6::{Icmpv6Packet, MutIcmpv6Packet};
+    use ipv4::{Ipv4Builder, Ipv4Packet};
+    use ipv6::{Ipv6Builder, Ipv6Packet};
+    use std::net::IpAddr;
+    use tcp::{MutTcpPacket, TcpPacket};
+    use udp::{UdpBuilder, UdpPacket};
+
+    const PRIVATE: Ipv4Addr = Ipv4Addr::new(192, 168, 0, 2);
+    const SERVER: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 1);
+    const PUBLIC: Ipv4Addr = Ipv4Addr::new(203, 0, 113, 7);
+
+    fn tcp_over_ipv4() -> Vec<u8> {
+        let mut segment = [0; 24];
+        segment[..4].copy_from_slice(&[0x9c, 0x40, 0x00, 0x50]);
+        segment[12] = 0x50;
+        segment[20..].copy_from_slice(b"GET ");
+        MutTcpPacket::new(&mut segment)
+            .unwrap()
+            .update_checksum(IpAddr::V4(PRIVATE), IpAddr::V4(SERVER));
+        Ipv4Builder::new()
+            .source(PRIVATE)
+            .destination(SERVER)
+            .protocol(Protocol::TCP)
+            .payload(segment)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn tcp_source() {
+        let mut data = tcp_over_ipv4();
+        let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+        rewrite_ipv4(&mut packet, Side::Source, PUBLIC, Some(61000)).unwrap();
+
+        let packet = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(PUBLIC, packet.source());
+        assert!(packet.is_checksum_valid());
+        let tcp = TcpPacket::new(packet.payload()).unwrap();
+        assert_eq!(61000, tcp.source_port());
+        assert_eq!(80, tcp.destination_port());
+        assert!(tcp.verify_checksum(IpAddr::V4(PUBLIC), IpAddr::V4(SERVER)));
+    }
+
+    #[test]
+    fn tcp_destination_address_only() {
+        let mut data = tcp_over_ipv4();
+        let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+        rewrite_ipv4(&mut packet, Side::Destination, PUBLIC, None).unwrap();
+
+        let packet = Ipv4Packet::new(&data).unwrap();
+        assert!(packet.is_checksum_valid());
+        let tcp = TcpPacket::new(packet.payload()).unwrap();
+        assert_eq!(80, tcp.destination_port());
+        assert!(tcp.verify_checksum(IpAddr::V4(PRIVATE), IpAddr::V4(PUBLIC)));
+    }
+
+    #[test]
+    fn udp_without_checksum() {
+        let udp = UdpBuilder::new().source_port(40000).destination_port(53).payload(b"hi");
+        let mut data = Ipv4Builder::new().source(PRIVATE).payload(udp).build().unwrap();
+        data[20 + 6..20 + 8].copy_from_slice(&[0, 0]);
+        let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+        rewrite_ipv4(&mut packet, Side::Source, PUBLIC, Some(1)).unwrap();
+
+        let packet = Ipv4Packet::new(&data).unwrap();
+        assert!(packet.is_checksum_valid());
+        let udp = UdpPacket::new(packet.payload()).unwrap();
+        assert_eq!(1, udp.source_port());
+        assert_eq!(0, udp.checksum());
+    }
+
+    #[test]
+    fn udp_checksum_never_zero() {
+        let udp = UdpBuilder::new().source_port(40000).destination_port(53).payload(b"hi");
+        let mut data = Ipv4Builder::new().source(PRIVATE).payload(udp).build().unwrap();
+        // Pick the new port so that the checksum becomes zero, sent as all ones.
+        let port = {
+            let udp = UdpPacket::new(&data[20..]).unwrap();
+            checksum::update(udp.checksum(), &[0x9c, 0x40], &[0, 0])
+        };
+        let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+        rewrite_ipv4(&mut packet, Side::Source, PRIVATE, Some(port)).unwrap();
+
+        let udp = UdpPacket::new(&data[20..]).unwrap();
+        assert_eq!(port, udp.source_port());
+        assert_eq!(0xffff, udp.checksum());
+        assert!(udp.verify_checksum(IpAddr::V4(PRIVATE), IpAddr::V4(Ipv4Addr::UNSPECIFIED)));
+    }
+
+    #[test]
+    fn ipv6() {
+        let source = "fd00::2".parse().unwrap();
+        let destination = "2001:db8::1".parse().unwrap();
+        let public = "2001:db8:ffff::7".parse().unwrap();
+        let udp = UdpBuilder::new().source_port(40000).destination_port(53).payload(b"hi");
+        let mut data = Ipv6Builder::new()
+            .source(source)
+            .destination(destination)
+            .payload(udp)
+            .build()
+            .unwrap();
+        let mut packet = MutIpv6Packet::new(&mut data).unwrap();
+        rewrite_ipv6(&mut packet, Side::Source, public, Some(61000)).unwrap();
+
+        let packet = Ipv6Packet::new(&data).unwrap();
+        assert_eq!(public, packet.source());
+        let udp = UdpPacket::new(packet.payload()).unwrap();
+        assert_eq!(61000, udp.source_port());
+        assert!(udp.verify_checksum(IpAddr::V6(public), IpAddr::V6(destination)));
+    }
+
+    #[test]
+    fn icmpv6() {
+        let source = "fd00::2".parse().unwrap();
+        let destination = "2001:db8::1".parse().unwrap();
+        let public = "2001:db8:ffff::7".parse().unwrap();
+        let mut echo = [128, 0, 0, 0, 0x12, 0x34, 0x00, 0x01];
+        let checksum = Icmpv6Packet::new(&echo).unwrap().calculate_checksum(source, destination);
+        MutIcmpv6Packet::new(&mut echo).unwrap().set_checksum(checksum);
+        let mut data = Ipv6Builder::new()
+            .source(source)
+            .destination(destination)
+            .next_header(Protocol::ICMPV6)
+            .payload(echo)
+            .build()
+            .unwrap();
+        let mut packet = MutIpv6Packet::new(&mut data).unwrap();
+        assert_eq!(
+            Err(NatError::NoPorts(Protocol::ICMPV6)),
+            rewrite_ipv6(&mut packet, Side::Source, public, Some(1))
+        );
+        rewrite_ipv6(&mut packet, Side::Source, public, None).unwrap();
+
+        let icmp = Icmpv6Packet::new(&data[40..]).unwrap();
+        assert!(icmp.verify_checksum(public, destination));
+    }
+
+    #[test]
+    fn refused() {
+        let mut data = Ipv4Builder::new()
+            .protocol(Protocol::ICMP)
+            .payload([8, 0, 0, 0, 0, 0, 0, 0])
+            .build()
+            .unwrap();
+        let original = data.clone();
+        let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+        assert_eq!(
+            Err(NatError::NoPorts(Protocol::ICMP)),
+            rewrite_ipv4(&mut packet, Side::Source, PUBLIC, Some(1))
+        );
+        assert_eq!(original, data);
+
+        let mut data = tcp_over_ipv4();
+        data.truncate(20 + 10);
+        data[3] = 30;
+        let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+        assert_eq!(
+            Err(NatError::MissingTransportHeader),
+            rewrite_ipv4(&mut packet, Side::Source, PUBLIC, None)
+        );
+        assert_eq!("Transport header missing", NatError::MissingTransportHeader.to_string());
+        assert_eq!("No ports to rewrite in ICMP", NatError::NoPorts(Protocol::ICMP).to_string());
+    }
+
+    #[test]
+    fn later_fragment() {
+        let mut data = tcp_over_ipv4();
+        data[6..8].copy_from_slice(&[0x00, 0x10]);
+        MutIpv4Packet::new(&mut data).unwrap().update_checksum();
+        let original = data.clone();
+        let mut packet = MutIpv4Packet::new(&mut data).unwrap();
+        assert_eq!(
+            Err(NatError::MissingTransportHeader),
+            rewrite_ipv4(&mut packet, Side::Source, PUBLIC, Some(1))
+        );
+        rewrite_ipv4(&mut packet, Side::Source, PUBLIC, None).unwrap();
+        assert!(Ipv4Packet::new(&data).unwrap().is_checksum_valid());
+        assert_eq!(original[20..], data[20..]);
+    }
+}
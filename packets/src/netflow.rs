@@ -0,0 +1,1119 @@
+//! Flow export protocols: NetFlow version 5, NetFlow version 9, RFC 3954, and IPFIX, RFC 7011.
+//!
+//! NetFlow v5 records have a fixed layout. NetFlow v9 and IPFIX messages instead carry sets of
+//! templates and sets of data records, where a data record can only be decoded with the
+//! template it refers to. Templates are sent periodically and apply to later messages from the
+//! same exporter and observation domain, so collectors keep them in a `TemplateCache`.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use std::cmp;
+use std::net::Ipv4Addr;
+use std::slice;
+
+use ip::Protocol;
+use std::fmt;
+use types::*;
+
+/// The UDP port commonly used for NetFlow. Not assigned by IANA, so exporters vary.
+pub const NETFLOW_UDP_PORT: u16 = 2055;
+
+/// The port assigned to IPFIX over UDP, TCP and SCTP.
+pub const IPFIX_PORT: u16 = 4739;
+
+/// The ID of a NetFlow v9 template set.
+pub const V9_TEMPLATE_SET_ID: u16 = 0;
+
+/// The ID of a NetFlow v9 options template set.
+pub const V9_OPTIONS_TEMPLATE_SET_ID: u16 = 1;
+
+/// The ID of an IPFIX template set.
+pub const IPFIX_TEMPLATE_SET_ID: u16 = 2;
+
+/// The ID of an IPFIX options template set.
+pub const IPFIX_OPTIONS_TEMPLATE_SET_ID: u16 = 3;
+
+/// The lowest set ID of a data set. Data sets have the ID of the template describing them.
+pub const MIN_DATA_SET_ID: u16 = 256;
+
+/// The field length indicating a variable length field in IPFIX.
+pub const VARIABLE_LENGTH: u16 = 65535;
+
+
+packet!(
+    /// A NetFlow version 5 export packet. The payload holds `count` flow records of 48 bytes.
+    NetflowV5Packet,
+    MutNetflowV5Packet,
+    NetflowV5PacketBuf,
+    24,
+    |_data| 24,
+    init |data| {
+        // The count covers the records that fit in the payload.
+        let count = (data.len() - 24) / 48;
+        write_offset!(data, 0, 5u16, u16, to_be);
+        write_offset!(data, 2, count as u16, u16, to_be);
+    }
+);
+
+getters!(NetflowV5Packet
+    pub fn version(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    /// Returns the number of flow records in the packet.
+    pub fn count(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the time since the exporter booted, in milliseconds.
+    pub fn sys_uptime(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    pub fn unix_secs(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    pub fn unix_nsecs(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns the total number of flows exported before this packet.
+    pub fn flow_sequence(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    pub fn engine_type(&self) -> u8 {
+        read_offset!(self.0, 20, u8)
+    }
+
+    pub fn engine_id(&self) -> u8 {
+        read_offset!(self.0, 21, u8)
+    }
+
+    /// Returns the sampling mode, stored in the upper two bits of the sampling field.
+    pub fn sampling_mode(&self) -> u2 {
+        read_offset!(self.0, 22, u8) >> 6
+    }
+
+    /// Returns the 14 bit sampling interval.
+    pub fn sampling_interval(&self) -> u16 {
+        read_offset!(self.0, 22, u16, from_be) & 0x3fff
+    }
+
+    /// Returns an iterator over the flow records.
+    pub fn records(&self) -> NetflowV5Records<'a> {
+        NetflowV5Records {
+            data: &self.0[24..],
+            remaining: self.count(),
+        }
+    }
+);
+
+setters!(MutNetflowV5Packet
+    pub fn set_version(&mut self, version: u16) {
+        write_offset!(self.0, 0, version, u16, to_be);
+    }
+
+    pub fn set_count(&mut self, count: u16) {
+        write_offset!(self.0, 2, count, u16, to_be);
+    }
+
+    pub fn set_sys_uptime(&mut self, sys_uptime: u32) {
+        write_offset!(self.0, 4, sys_uptime, u32, to_be);
+    }
+
+    pub fn set_unix_secs(&mut self, unix_secs: u32) {
+        write_offset!(self.0, 8, unix_secs, u32, to_be);
+    }
+
+    pub fn set_unix_nsecs(&mut self, unix_nsecs: u32) {
+        write_offset!(self.0, 12, unix_nsecs, u32, to_be);
+    }
+
+    pub fn set_flow_sequence(&mut self, flow_sequence: u32) {
+        write_offset!(self.0, 16, flow_sequence, u32, to_be);
+    }
+
+    pub fn set_engine_type(&mut self, engine_type: u8) {
+        write_offset!(self.0, 20, engine_type, u8);
+    }
+
+    pub fn set_engine_id(&mut self, engine_id: u8) {
+        write_offset!(self.0, 21, engine_id, u8);
+    }
+
+    pub fn set_sampling_mode(&mut self, sampling_mode: u2) {
+        let new_byte = (sampling_mode << 6) | (read_offset!(self.0, 22, u8) & 0x3f);
+        write_offset!(self.0, 22, new_byte, u8);
+    }
+
+    /// Sets the 14 bit sampling interval. The upper two bits of `sampling_interval` are
+    /// ignored.
+    pub fn set_sampling_interval(&mut self, sampling_interval: u16) {
+        let new_value =
+            (read_offset!(self.0, 22, u16, from_be) & 0xc000) | (sampling_interval & 0x3fff);
+        write_offset!(self.0, 22, new_value, u16, to_be);
+    }
+);
+
+/// Iterator over the flow records of a NetFlow v5 packet. Iteration stops after the number of
+/// records given in the header, or at the first record that does not fit in the data.
+#[derive(Clone)]
+pub struct NetflowV5Records<'a> {
+    data: &'a [u8],
+    remaining: u16,
+}
+
+debug_items!(NetflowV5Records<'a>);
+
+impl<'a> Iterator for NetflowV5Records<'a> {
+    type Item = NetflowV5RecordPacket<'a>;
+
+    fn next(&mut self) -> Option<NetflowV5RecordPacket<'a>> {
+        if self.remaining == 0 || self.data.len() < 48 {
+            return None;
+        }
+        self.remaining -= 1;
+        let (record, rest) = self.data.split_at(48);
+        self.data = rest;
+        NetflowV5RecordPacket::new(record)
+    }
+}
+
+
+packet!(
+    /// A NetFlow version 5 flow record.
+    NetflowV5RecordPacket,
+    MutNetflowV5RecordPacket,
+    NetflowV5RecordPacketBuf,
+    48
+);
+
+getters!(NetflowV5RecordPacket
+    pub fn source(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 0, [u8; 4]))
+    }
+
+    pub fn destination(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+    }
+
+    pub fn next_hop(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 8, [u8; 4]))
+    }
+
+    /// Returns the SNMP index of the input interface.
+    pub fn input(&self) -> u16 {
+        read_offset!(self.0, 12, u16, from_be)
+    }
+
+    /// Returns the SNMP index of the output interface.
+    pub fn output(&self) -> u16 {
+        read_offset!(self.0, 14, u16, from_be)
+    }
+
+    pub fn packets(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns the number of layer 3 bytes in the flow.
+    pub fn octets(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    /// Returns the system uptime at the start of the flow, in milliseconds.
+    pub fn first(&self) -> u32 {
+        read_offset!(self.0, 24, u32, from_be)
+    }
+
+    /// Returns the system uptime at the last packet of the flow, in milliseconds.
+    pub fn last(&self) -> u32 {
+        read_offset!(self.0, 28, u32, from_be)
+    }
+
+    pub fn src_port(&self) -> u16 {
+        read_offset!(self.0, 32, u16, from_be)
+    }
+
+    pub fn dst_port(&self) -> u16 {
+        read_offset!(self.0, 34, u16, from_be)
+    }
+
+    /// Returns the union of the TCP flags of all packets in the flow.
+    pub fn tcp_flags(&self) -> u8 {
+        read_offset!(self.0, 37, u8)
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        Protocol(read_offset!(self.0, 38, u8))
+    }
+
+    pub fn tos(&self) -> u8 {
+        read_offset!(self.0, 39, u8)
+    }
+
+    pub fn src_as(&self) -> u16 {
+        read_offset!(self.0, 40, u16, from_be)
+    }
+
+    pub fn dst_as(&self) -> u16 {
+        read_offset!(self.0, 42, u16, from_be)
+    }
+
+    pub fn src_mask(&self) -> u8 {
+        read_offset!(self.0, 44, u8)
+    }
+
+    pub fn dst_mask(&self) -> u8 {
+        read_offset!(self.0, 45, u8)
+    }
+);
+
+setters!(MutNetflowV5RecordPacket
+    pub fn set_source(&mut self, source: Ipv4Addr) {
+        write_offset!(self.0, 0, source.octets(), [u8; 4]);
+    }
+
+    pub fn set_destination(&mut self, destination: Ipv4Addr) {
+        write_offset!(self.0, 4, destination.octets(), [u8; 4]);
+    }
+
+    pub fn set_next_hop(&mut self, next_hop: Ipv4Addr) {
+        write_offset!(self.0, 8, next_hop.octets(), [u8; 4]);
+    }
+
+    pub fn set_input(&mut self, input: u16) {
+        write_offset!(self.0, 12, input, u16, to_be);
+    }
+
+    pub fn set_output(&mut self, output: u16) {
+        write_offset!(self.0, 14, output, u16, to_be);
+    }
+
+    pub fn set_packets(&mut self, packets: u32) {
+        write_offset!(self.0, 16, packets, u32, to_be);
+    }
+
+    pub fn set_octets(&mut self, octets: u32) {
+        write_offset!(self.0, 20, octets, u32, to_be);
+    }
+
+    pub fn set_first(&mut self, first: u32) {
+        write_offset!(self.0, 24, first, u32, to_be);
+    }
+
+    pub fn set_last(&mut self, last: u32) {
+        write_offset!(self.0, 28, last, u32, to_be);
+    }
+
+    pub fn set_src_port(&mut self, src_port: u16) {
+        write_offset!(self.0, 32, src_port, u16, to_be);
+    }
+
+    pub fn set_dst_port(&mut self, dst_port: u16) {
+        write_offset!(self.0, 34, dst_port, u16, to_be);
+    }
+
+    pub fn set_tcp_flags(&mut self, tcp_flags: u8) {
+        write_offset!(self.0, 37, tcp_flags, u8);
+    }
+
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        write_offset!(self.0, 38, protocol.value(), u8);
+    }
+
+    pub fn set_tos(&mut self, tos: u8) {
+        write_offset!(self.0, 39, tos, u8);
+    }
+
+    pub fn set_src_as(&mut self, src_as: u16) {
+        write_offset!(self.0, 40, src_as, u16, to_be);
+    }
+
+    pub fn set_dst_as(&mut self, dst_as: u16) {
+        write_offset!(self.0, 42, dst_as, u16, to_be);
+    }
+
+    pub fn set_src_mask(&mut self, src_mask: u8) {
+        write_offset!(self.0, 44, src_mask, u8);
+    }
+
+    pub fn set_dst_mask(&mut self, dst_mask: u8) {
+        write_offset!(self.0, 45, dst_mask, u8);
+    }
+);
+
+
+packet!(
+    /// A NetFlow version 9 export packet. The payload holds template and data flowsets.
+    NetflowV9Packet,
+    MutNetflowV9Packet,
+    NetflowV9PacketBuf,
+    20,
+    |_data| 20,
+    init |data| write_offset!(data, 0, 9u16, u16, to_be)
+);
+
+getters!(NetflowV9Packet
+    pub fn version(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    /// Returns the total number of template and data records in the packet.
+    pub fn count(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the time since the exporter booted, in milliseconds.
+    pub fn sys_uptime(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    pub fn unix_secs(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns the ID of the exporting observation domain. Templates are scoped to it.
+    pub fn source_id(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns an iterator over the flowsets in the packet.
+    pub fn sets(&self) -> Sets<'a> {
+        Sets { data: &self.0[20..] }
+    }
+);
+
+setters!(MutNetflowV9Packet
+    pub fn set_version(&mut self, version: u16) {
+        write_offset!(self.0, 0, version, u16, to_be);
+    }
+
+    pub fn set_count(&mut self, count: u16) {
+        write_offset!(self.0, 2, count, u16, to_be);
+    }
+
+    pub fn set_sys_uptime(&mut self, sys_uptime: u32) {
+        write_offset!(self.0, 4, sys_uptime, u32, to_be);
+    }
+
+    pub fn set_unix_secs(&mut self, unix_secs: u32) {
+        write_offset!(self.0, 8, unix_secs, u32, to_be);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 12, sequence_number, u32, to_be);
+    }
+
+    pub fn set_source_id(&mut self, source_id: u32) {
+        write_offset!(self.0, 16, source_id, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// An IPFIX message. The payload holds template and data sets.
+    IpfixPacket,
+    MutIpfixPacket,
+    IpfixPacketBuf,
+    16,
+    |_data| 16,
+    init |data| {
+        write_offset!(data, 0, 10u16, u16, to_be);
+        write_offset!(data, 2, data.len() as u16, u16, to_be);
+    }
+);
+
+getters!(IpfixPacket
+    /// Returns the version, 10 for IPFIX.
+    pub fn version(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    /// Returns the length of the message in bytes, including this header.
+    pub fn length(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the time the message was exported, in seconds since the Unix epoch.
+    pub fn export_time(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the total number of data records sent before this message.
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns the ID of the observation domain. Templates are scoped to it.
+    pub fn observation_domain_id(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns an iterator over the sets in the message, limited to the message length.
+    pub fn sets(&self) -> Sets<'a> {
+        let end = cmp::min(cmp::max(usize::from(self.length()), 16), self.0.len());
+        Sets { data: &self.0[16..end] }
+    }
+);
+
+setters!(MutIpfixPacket
+    pub fn set_version(&mut self, version: u16) {
+        write_offset!(self.0, 0, version, u16, to_be);
+    }
+
+    pub fn set_length(&mut self, length: u16) {
+        write_offset!(self.0, 2, length, u16, to_be);
+    }
+
+    pub fn set_export_time(&mut self, export_time: u32) {
+        write_offset!(self.0, 4, export_time, u32, to_be);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 8, sequence_number, u32, to_be);
+    }
+
+    pub fn set_observation_domain_id(&mut self, observation_domain_id: u32) {
+        write_offset!(self.0, 12, observation_domain_id, u32, to_be);
+    }
+);
+
+
+/// A NetFlow v9 flowset or IPFIX set. Both consist of a set ID and a length, followed by
+/// records.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Set<'a> {
+    pub id: u16,
+    /// The records in the set, including any padding after them.
+    pub data: &'a [u8],
+}
+
+impl<'a> Set<'a> {
+    /// Returns true if this is a data set, holding records described by the template with
+    /// the same ID as the set.
+    pub fn is_data_set(&self) -> bool {
+        self.id >= MIN_DATA_SET_ID
+    }
+
+    /// Returns an iterator over the templates in a template or options template set. Returns
+    /// `None` for data sets and sets of unknown type.
+    pub fn templates(&self) -> Option<TemplateRecords<'a>> {
+        match self.id {
+            V9_TEMPLATE_SET_ID |
+            V9_OPTIONS_TEMPLATE_SET_ID |
+            IPFIX_TEMPLATE_SET_ID |
+            IPFIX_OPTIONS_TEMPLATE_SET_ID => Some(TemplateRecords {
+                data: self.data,
+                set_id: self.id,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the sets of a NetFlow v9 packet or IPFIX message. Iteration stops at the
+/// first set that does not fit in the data.
+#[derive(Clone)]
+pub struct Sets<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Sets<'a>);
+
+impl<'a> Iterator for Sets<'a> {
+    type Item = Set<'a>;
+
+    fn next(&mut self) -> Option<Set<'a>> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let id = read_offset!(self.data, 0, u16, from_be);
+        let len = usize::from(read_offset!(self.data, 2, u16, from_be));
+        if len < 4 || len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (set, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(Set { id, data: &set[4..] })
+    }
+}
+
+
+/// An information element, the type of a field in a template. NetFlow v9 calls these field
+/// types. The constants are the elements common to NetFlow v9 and IPFIX.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FieldType(pub u16);
+
+impl FieldType {
+    pub const OCTET_DELTA_COUNT: FieldType = FieldType(1);
+    pub const PACKET_DELTA_COUNT: FieldType = FieldType(2);
+    pub const PROTOCOL_IDENTIFIER: FieldType = FieldType(4);
+    pub const IP_CLASS_OF_SERVICE: FieldType = FieldType(5);
+    pub const TCP_CONTROL_BITS: FieldType = FieldType(6);
+    pub const SOURCE_TRANSPORT_PORT: FieldType = FieldType(7);
+    pub const SOURCE_IPV4_ADDRESS: FieldType = FieldType(8);
+    pub const INGRESS_INTERFACE: FieldType = FieldType(10);
+    pub const DESTINATION_TRANSPORT_PORT: FieldType = FieldType(11);
+    pub const DESTINATION_IPV4_ADDRESS: FieldType = FieldType(12);
+    pub const EGRESS_INTERFACE: FieldType = FieldType(14);
+    pub const IP_NEXT_HOP_IPV4_ADDRESS: FieldType = FieldType(15);
+    pub const FLOW_END_SYS_UP_TIME: FieldType = FieldType(21);
+    pub const FLOW_START_SYS_UP_TIME: FieldType = FieldType(22);
+    pub const SOURCE_IPV6_ADDRESS: FieldType = FieldType(27);
+    pub const DESTINATION_IPV6_ADDRESS: FieldType = FieldType(28);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// A field specifier in a template.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TemplateField {
+    pub field_type: FieldType,
+    /// The length of the field in data records, or `VARIABLE_LENGTH` if each record encodes
+    /// it.
+    pub length: u16,
+    /// The enterprise number of an IPFIX enterprise specific information element.
+    pub enterprise: Option<u32>,
+}
+
+impl TemplateField {
+    pub fn is_variable_length(&self) -> bool {
+        self.length == VARIABLE_LENGTH
+    }
+}
+
+/// A template, describing the fields of the data records in data sets with the template ID.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Template {
+    pub id: u16,
+    /// The number of scope fields at the start of `fields`. Zero unless this is an options
+    /// template.
+    pub scope_field_count: usize,
+    pub fields: Vec<TemplateField>,
+}
+
+impl Template {
+    /// Returns true if the template has no fields. In IPFIX such a template withdraws the
+    /// template with the same ID.
+    pub fn is_withdrawal(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the length of a data record described by this template, or `None` if the
+    /// template has variable length fields.
+    pub fn record_len(&self) -> Option<usize> {
+        self.fields.iter().try_fold(0, |len, field| {
+            if field.is_variable_length() {
+                None
+            } else {
+                Some(len + usize::from(field.length))
+            }
+        })
+    }
+
+    /// Returns an iterator over the data records of a data set described by this template.
+    /// The set ID is not checked.
+    pub fn data_records<'a, 't>(&'t self, set: Set<'a>) -> DataRecords<'a, 't> {
+        DataRecords {
+            template: self,
+            data: set.data,
+        }
+    }
+}
+
+/// Iterator over the templates of a template or options template set. Iteration stops at
+/// the padding at the end of the set, or at the first template that does not fit in the data.
+#[derive(Clone)]
+pub struct TemplateRecords<'a> {
+    data: &'a [u8],
+    set_id: u16,
+}
+
+debug_items!(TemplateRecords<'a>);
+
+impl<'a> TemplateRecords<'a> {
+    fn parse(&self) -> Option<(Template, usize)> {
+        let data = self.data;
+        let id = read_offset!(data.get(..4)?, 0, u16, from_be);
+        let second = read_offset!(data, 2, u16, from_be);
+        let (mut offset, field_count, scope_field_count) = match self.set_id {
+            V9_OPTIONS_TEMPLATE_SET_ID => {
+                let option_len = usize::from(read_offset!(data.get(..6)?, 4, u16, from_be));
+                let scope_len = usize::from(second);
+                (6, (scope_len + option_len) / 4, scope_len / 4)
+            }
+            IPFIX_OPTIONS_TEMPLATE_SET_ID if second != 0 => {
+                let scope_count = usize::from(read_offset!(data.get(..6)?, 4, u16, from_be));
+                (6, usize::from(second), scope_count)
+            }
+            _ => (4, usize::from(second), 0),
+        };
+        let is_ipfix = self.set_id == IPFIX_TEMPLATE_SET_ID ||
+            self.set_id == IPFIX_OPTIONS_TEMPLATE_SET_ID;
+        let mut fields = Vec::with_capacity(field_count);
+        for _ in 0..field_count {
+            let specifier = data.get(offset..offset + 4)?;
+            let raw_type = read_offset!(specifier, 0, u16, from_be);
+            let length = read_offset!(specifier, 2, u16, from_be);
+            offset += 4;
+            let (field_type, enterprise) = if is_ipfix && raw_type & 0x8000 != 0 {
+                let enterprise = data.get(offset..offset + 4)?;
+                offset += 4;
+                (raw_type & 0x7fff, Some(read_offset!(enterprise, 0, u32, from_be)))
+            } else {
+                (raw_type, None)
+            };
+            fields.push(TemplateField {
+                field_type: FieldType(field_type),
+                length,
+                enterprise,
+            });
+        }
+        let template = Template {
+            id,
+            scope_field_count,
+            fields,
+        };
+        Some((template, offset))
+    }
+}
+
+impl<'a> Iterator for TemplateRecords<'a> {
+    type Item = Template;
+
+    fn next(&mut self) -> Option<Template> {
+        // Template IDs below 256 are reserved, so an ID of zero is padding.
+        if self.data.len() < 4 || read_offset!(self.data, 0, u16, from_be) == 0 {
+            return None;
+        }
+        match self.parse() {
+            Some((template, len)) => {
+                self.data = &self.data[len..];
+                Some(template)
+            }
+            None => {
+                self.data = &[];
+                None
+            }
+        }
+    }
+}
+
+
+/// A data record, decoded with its template.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DataRecord<'a, 't> {
+    template: &'t Template,
+    data: &'a [u8],
+}
+
+impl<'a, 't> DataRecord<'a, 't> {
+    pub fn template(&self) -> &'t Template {
+        self.template
+    }
+
+    /// Returns the encoded record.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns an iterator over the fields of the record and their values.
+    pub fn fields(&self) -> DataFields<'a, 't> {
+        DataFields {
+            fields: self.template.fields.iter(),
+            data: self.data,
+        }
+    }
+
+    /// Returns the value of the first field of type `field_type` that is not enterprise
+    /// specific, or `None` if the template has no such field.
+    pub fn get(&self, field_type: FieldType) -> Option<&'a [u8]> {
+        self.fields()
+            .find(|&(field, _)| field.field_type == field_type && field.enterprise.is_none())
+            .map(|(_, value)| value)
+    }
+}
+
+/// Iterator over the fields of a data record and their values.
+#[derive(Clone)]
+pub struct DataFields<'a, 't> {
+    fields: slice::Iter<'t, TemplateField>,
+    data: &'a [u8],
+}
+
+debug_items!(DataFields<'a, 't>);
+
+impl<'a, 't> Iterator for DataFields<'a, 't> {
+    type Item = (&'t TemplateField, &'a [u8]);
+
+    fn next(&mut self) -> Option<(&'t TemplateField, &'a [u8])> {
+        let field = self.fields.next()?;
+        let (value, rest) = split_field(self.data, field)?;
+        self.data = rest;
+        Some((field, value))
+    }
+}
+
+/// Iterator over the data records of a data set. Iteration stops at the padding at the end of
+/// the set, that is once the remaining data is too short for a record.
+#[derive(Clone)]
+pub struct DataRecords<'a, 't> {
+    template: &'t Template,
+    data: &'a [u8],
+}
+
+debug_items!(DataRecords<'a, 't>);
+
+impl<'a, 't> Iterator for DataRecords<'a, 't> {
+    type Item = DataRecord<'a, 't>;
+
+    fn next(&mut self) -> Option<DataRecord<'a, 't>> {
+        let mut rest = self.data;
+        for field in &self.template.fields {
+            match split_field(rest, field) {
+                Some((_, after)) => rest = after,
+                None => {
+                    self.data = &[];
+                    return None;
+                }
+            }
+        }
+        // A template without fields, or with only fields of length zero, describes records
+        // that take up no bytes. Those would be returned forever.
+        if rest.len() == self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (record, _) = self.data.split_at(self.data.len() - rest.len());
+        self.data = rest;
+        Some(DataRecord {
+            template: self.template,
+            data: record,
+        })
+    }
+}
+
+/// Splits the value of `field` off the start of `data`, decoding the length prefix of variable
+/// length fields.
+fn split_field<'a>(data: &'a [u8], field: &TemplateField) -> Option<(&'a [u8], &'a [u8])> {
+    let (len, data) = if field.is_variable_length() {
+        match *data.first()? {
+            255 => {
+                let len = read_offset!(data.get(..3)?, 1, u16, from_be);
+                (usize::from(len), &data[3..])
+            }
+            len => (usize::from(len), &data[1..]),
+        }
+    } else {
+        (usize::from(field.length), data)
+    };
+    if len > data.len() {
+        None
+    } else {
+        Some(data.split_at(len))
+    }
+}
+
+/// Decodes an unsigned integer field. Exporters may send integers in fewer bytes than their
+/// natural size, so any length up to eight bytes is accepted. Returns `None` for longer fields.
+pub fn read_unsigned(value: &[u8]) -> Option<u64> {
+    if value.len() > 8 {
+        return None;
+    }
+    Some(value.iter().fold(0, |acc, &byte| (acc << 8) | u64::from(byte)))
+}
+
+
+/// The templates received from exporters, keyed by observation domain and template ID. Use one
+/// cache per exporter address, as observation domains are only unique per exporter.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateCache {
+    templates: BTreeMap<(u32, u16), Template>,
+}
+
+impl TemplateCache {
+    pub fn new() -> TemplateCache {
+        TemplateCache::default()
+    }
+
+    /// Stores `template` for `domain`, returning the template it replaced, if any.
+    pub fn insert(&mut self, domain: u32, template: Template) -> Option<Template> {
+        self.templates.insert((domain, template.id), template)
+    }
+
+    pub fn get(&self, domain: u32, id: u16) -> Option<&Template> {
+        self.templates.get(&(domain, id))
+    }
+
+    pub fn remove(&mut self, domain: u32, id: u16) -> Option<Template> {
+        self.templates.remove(&(domain, id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Stores the templates of a template or options template set. Withdrawals remove the
+    /// template from the cache. Returns the number of templates stored or withdrawn, zero for
+    /// sets of other types.
+    pub fn learn(&mut self, domain: u32, set: Set<'_>) -> usize {
+        let mut count = 0;
+        for template in set.templates().into_iter().flatten() {
+            if template.is_withdrawal() {
+                self.remove(domain, template.id);
+            } else {
+                self.insert(domain, template);
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns an iterator over the records of a data set, decoded with the template for the
+    /// set ID. Returns `None` if the set is not a data set or its template is not known.
+    pub fn data_records<'a, 't>(
+        &'t self,
+        domain: u32,
+        set: Set<'a>,
+    ) -> Option<DataRecords<'a, 't>> {
+        if !set.is_data_set() {
+            return None;
+        }
+        self.get(domain, set.id).map(|template| template.data_records(set))
+    }
+}
+
+impl<'a> fmt::Display for NetflowV5Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NetFlow v5 count={} seq={}",
+            self.count(),
+            self.flow_sequence()
+        )
+    }
+}
+
+impl<'a> fmt::Display for NetflowV5RecordPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NetFlow v5 record {}:{} -> {}:{} proto={} packets={} octets={}",
+            self.source(),
+            self.src_port(),
+            self.destination(),
+            self.dst_port(),
+            self.protocol(),
+            self.packets(),
+            self.octets()
+        )
+    }
+}
+
+impl<'a> fmt::Display for NetflowV9Packet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NetFlow v9 source={} seq={} count={}",
+            self.source_id(),
+            self.sequence_number(),
+            self.count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for IpfixPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IPFIX domain={} seq={} len={}",
+            self.observation_domain_id(),
+            self.sequence_number(),
+            self.length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! netflow_v5_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutNetflowV5Packet, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    netflow_v5_setget_test!(version, set_version, 5, 0, [0, 5]);
+    netflow_v5_setget_test!(count, set_count, 0xabcd, 2, [0xab, 0xcd]);
+    netflow_v5_setget_test!(unix_nsecs, set_unix_nsecs, 0xabcdef01, 12, [0xab, 0xcd, 0xef, 1]);
+    netflow_v5_setget_test!(engine_id, set_engine_id, 0xab, 21, [0xab]);
+    netflow_v5_setget_test!(sampling_mode, set_sampling_mode, 0x03, 22, [0xc0]);
+    netflow_v5_setget_test!(sampling_interval, set_sampling_interval, 0x3fff, 22, [0x3f, 0xff]);
+
+    #[test]
+    fn v5_records() {
+        let mut data = [0; 24 + 48 * 2 + 10];
+        MutNetflowV5Packet::new(&mut data).unwrap().set_count(3);
+        {
+            let mut record = MutNetflowV5RecordPacket::new(&mut data[24 + 48..]).unwrap();
+            record.set_source(Ipv4Addr::new(10, 0, 0, 1));
+            record.set_protocol(Protocol::UDP);
+            record.set_dst_port(53);
+        }
+        let testee = NetflowV5Packet::new(&data).unwrap();
+        let records = testee.records().collect::<Vec<_>>();
+        assert_eq!(2, records.len());
+        assert_eq!(Ipv4Addr::new(10, 0, 0, 1), records[1].source());
+        assert_eq!(Protocol::UDP, records[1].protocol());
+        assert_eq!(53, records[1].dst_port());
+    }
+
+    #[test]
+    fn v9_templates_and_data() {
+        let data = [
+            0, 9, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 42,
+            // Template flowset with template 256: source address and packet count
+            0, 0, 0, 16, 1, 0, 0, 2, 0, 8, 0, 4, 0, 2, 0, 4,
+            // Data flowset with two records and two bytes of padding
+            1, 0, 0, 22, 10, 0, 0, 1, 0, 0, 0, 5, 10, 0, 0, 2, 0, 0, 0, 6, 0, 0,
+        ];
+        let testee = NetflowV9Packet::new(&data).unwrap();
+        assert_eq!(42, testee.source_id());
+        let sets = testee.sets().collect::<Vec<_>>();
+        assert_eq!(2, sets.len());
+
+        let mut cache = TemplateCache::new();
+        assert!(cache.data_records(42, sets[1]).is_none());
+        assert_eq!(1, cache.learn(42, sets[0]));
+        assert_eq!(0, cache.learn(42, sets[1]));
+        let template = cache.get(42, 256).unwrap();
+        assert_eq!(Some(8), template.record_len());
+        assert_eq!(FieldType::SOURCE_IPV4_ADDRESS, template.fields[0].field_type);
+        assert!(cache.data_records(43, sets[1]).is_none());
+
+        let records = cache.data_records(42, sets[1]).unwrap().collect::<Vec<_>>();
+        assert_eq!(2, records.len());
+        assert_eq!(Some(&[10, 0, 0, 2][..]), records[1].get(FieldType::SOURCE_IPV4_ADDRESS));
+        let packets = records[1].get(FieldType::PACKET_DELTA_COUNT).unwrap();
+        assert_eq!(Some(6), read_unsigned(packets));
+        assert_eq!(None, records[1].get(FieldType::OCTET_DELTA_COUNT));
+    }
+
+    #[test]
+    fn v9_options_template() {
+        let set = Set {
+            id: V9_OPTIONS_TEMPLATE_SET_ID,
+            data: &[1, 1, 0, 4, 0, 8, 0, 1, 0, 4, 0, 34, 0, 4, 0, 36, 0, 2, 0, 0],
+        };
+        let templates = set.templates().unwrap().collect::<Vec<_>>();
+        assert_eq!(1, templates.len());
+        assert_eq!(257, templates[0].id);
+        assert_eq!(1, templates[0].scope_field_count);
+        assert_eq!(3, templates[0].fields.len());
+        assert_eq!(Some(10), templates[0].record_len());
+    }
+
+    #[test]
+    fn ipfix_enterprise_and_variable_length() {
+        let data = [
+            0, 10, 0, 55, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 7,
+            // Template set with template 300: an enterprise field and a variable length field
+            0, 2, 0, 20, 1, 44, 0, 2, 0x80, 5, 0, 2, 0, 0, 0x9e, 0x65, 0, 82, 0xff, 0xff,
+            // Data set with two records of variable length and padding too short for a third
+            1, 44, 0, 19, 0xab, 0xcd, 3, b'e', b't', b'h', 0xab, 0xcd, 255, 0, 2, b'l', b'o', 0,
+            0,
+            // Trailing bytes beyond the message length
+            1, 2, 3, 4,
+        ];
+        let testee = IpfixPacket::new(&data).unwrap();
+        assert_eq!(10, testee.version());
+        let sets = testee.sets().collect::<Vec<_>>();
+        assert_eq!(2, sets.len());
+
+        let mut cache = TemplateCache::new();
+        cache.learn(testee.observation_domain_id(), sets[0]);
+        let template = cache.get(7, 300).unwrap();
+        assert_eq!(Some(40549), template.fields[0].enterprise);
+        assert_eq!(FieldType(5), template.fields[0].field_type);
+        assert!(template.fields[1].is_variable_length());
+        assert_eq!(None, template.record_len());
+
+        let records = cache.data_records(7, sets[1]).unwrap().collect::<Vec<_>>();
+        assert_eq!(2, records.len());
+        let fields = records[0].fields().collect::<Vec<_>>();
+        assert_eq!(&[0xab, 0xcd], fields[0].1);
+        assert_eq!(b"eth", fields[1].1);
+        assert_eq!(Some(&b"lo"[..]), records[1].get(FieldType(82)));
+        assert_eq!(None, records[1].get(FieldType(5)));
+    }
+
+    #[test]
+    fn ipfix_withdrawal() {
+        let mut cache = TemplateCache::new();
+        let template = Set {
+            id: IPFIX_TEMPLATE_SET_ID,
+            data: &[1, 0, 0, 1, 0, 4, 0, 1],
+        };
+        assert_eq!(1, cache.learn(1, template));
+        assert_eq!(1, cache.len());
+        let withdrawal = Set {
+            id: IPFIX_TEMPLATE_SET_ID,
+            data: &[1, 0, 0, 0],
+        };
+        assert_eq!(1, cache.learn(1, withdrawal));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn zero_length_fields() {
+        let mut cache = TemplateCache::new();
+        let template = Set {
+            id: IPFIX_TEMPLATE_SET_ID,
+            data: &[1, 0, 0, 1, 0, 8, 0, 0],
+        };
+        assert_eq!(1, cache.learn(1, template));
+        let data = Set { id: 256, data: &[1, 2, 3, 4] };
+        assert_eq!(0, cache.data_records(1, data).unwrap().take(2).count());
+        let empty = Template { id: 257, scope_field_count: 0, fields: Vec::new() };
+        assert_eq!(0, empty.data_records(data).count());
+    }
+
+    #[test]
+    fn truncated_sets() {
+        let data = [0, 0, 0, 4, 0, 0, 0, 12, 1, 0, 0, 2];
+        let mut sets = Sets { data: &data };
+        assert_eq!(Some(Set { id: 0, data: &[] }), sets.next());
+        assert_eq!(None, sets.next());
+        assert_eq!(None, sets.next());
+
+        let set = Set { id: V9_TEMPLATE_SET_ID, data: &[1, 0, 0, 2, 0, 8, 0, 4] };
+        assert_eq!(None, set.templates().unwrap().next());
+        assert!(Set { id: 256, data: &[] }.templates().is_none());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        NetflowV5PacketBuf => |packet| {
+            packet.version() == 5 && packet.records().count() == usize::from(packet.count())
+        },
+        NetflowV5RecordPacketBuf,
+        NetflowV9PacketBuf => |packet| packet.version() == 9,
+        IpfixPacketBuf => |packet| usize::from(packet.length()) == packet.data().len(),
+    );
+}
@@ -0,0 +1,403 @@
+//! The Network Time Protocol version 4, RFC 5905, and its simple subset SNTP, RFC 4330.
+
+use std::fmt;
+use std::time::Duration;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+use types::*;
+
+/// The UDP port used by NTP, both by clients and servers.
+pub const UDP_PORT: u16 = 123;
+
+/// The number of seconds from the NTP epoch, 1900-01-01, to the Unix epoch.
+#[cfg(feature = "std")]
+const UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+/// The number of seconds in one NTP era.
+const ERA_SECONDS: u64 = 1 << 32;
+
+packet!(
+    /// An NTP packet. The header does not include extension fields or the authenticator, these
+    /// are left in the payload.
+    NtpPacket,
+    MutNtpPacket,
+    NtpPacketBuf,
+    48,
+    |_data| 48,
+    init |data| write_field!(data, bits!(0, 2, 3), 4, u8)
+);
+
+getters!(NtpPacket
+    pub fn leap_indicator(&self) -> LeapIndicator {
+        LeapIndicator(read_field!(self.0, bits!(0, 0, 2), u8))
+    }
+
+    pub fn version(&self) -> u3 {
+        read_field!(self.0, bits!(0, 2, 3), u8)
+    }
+
+    pub fn mode(&self) -> Mode {
+        Mode(read_field!(self.0, bits!(0, 5, 3), u8))
+    }
+
+    /// Returns the stratum, zero meaning unspecified or a kiss-o'-death message and one a
+    /// primary server.
+    pub fn stratum(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns the maximum interval between messages, as log2 seconds.
+    pub fn poll(&self) -> i8 {
+        read_offset!(self.0, 2, i8)
+    }
+
+    /// Returns the precision of the system clock, as log2 seconds.
+    pub fn precision(&self) -> i8 {
+        read_offset!(self.0, 3, i8)
+    }
+
+    /// Returns the round trip delay to the reference clock in the NTP short format, seconds as
+    /// 16.16 fixed point. See `short_to_duration`.
+    pub fn root_delay(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the dispersion to the reference clock in the NTP short format, seconds as 16.16
+    /// fixed point. See `short_to_duration`.
+    pub fn root_dispersion(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns the reference ID. For stratum one this is an ASCII code for the reference clock,
+    /// for kiss-o'-death messages the kiss code, and otherwise an IPv4 address or, for IPv6,
+    /// the first bytes of an MD5 hash of the address.
+    pub fn reference_id(&self) -> [u8; 4] {
+        read_offset!(self.0, 12, [u8; 4])
+    }
+
+    /// Returns the time the system clock was last set or corrected.
+    pub fn reference_timestamp(&self) -> Timestamp {
+        Timestamp(read_offset!(self.0, 16, u64, from_be))
+    }
+
+    /// Returns the time the request left the client, as copied into a reply by the server.
+    pub fn origin_timestamp(&self) -> Timestamp {
+        Timestamp(read_offset!(self.0, 24, u64, from_be))
+    }
+
+    /// Returns the time the request arrived at the server.
+    pub fn receive_timestamp(&self) -> Timestamp {
+        Timestamp(read_offset!(self.0, 32, u64, from_be))
+    }
+
+    /// Returns the time this packet left the sender.
+    pub fn transmit_timestamp(&self) -> Timestamp {
+        Timestamp(read_offset!(self.0, 40, u64, from_be))
+    }
+);
+
+setters!(MutNtpPacket
+    pub fn set_leap_indicator(&mut self, leap_indicator: LeapIndicator) {
+        write_field!(self.0, bits!(0, 0, 2), leap_indicator.value(), u8);
+    }
+
+    pub fn set_version(&mut self, version: u3) {
+        write_field!(self.0, bits!(0, 2, 3), version, u8);
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        write_field!(self.0, bits!(0, 5, 3), mode.value(), u8);
+    }
+
+    pub fn set_stratum(&mut self, stratum: u8) {
+        write_offset!(self.0, 1, stratum, u8);
+    }
+
+    pub fn set_poll(&mut self, poll: i8) {
+        write_offset!(self.0, 2, poll, i8);
+    }
+
+    pub fn set_precision(&mut self, precision: i8) {
+        write_offset!(self.0, 3, precision, i8);
+    }
+
+    pub fn set_root_delay(&mut self, root_delay: u32) {
+        write_offset!(self.0, 4, root_delay, u32, to_be);
+    }
+
+    pub fn set_root_dispersion(&mut self, root_dispersion: u32) {
+        write_offset!(self.0, 8, root_dispersion, u32, to_be);
+    }
+
+    pub fn set_reference_id(&mut self, reference_id: [u8; 4]) {
+        write_offset!(self.0, 12, reference_id, [u8; 4]);
+    }
+
+    pub fn set_reference_timestamp(&mut self, reference_timestamp: Timestamp) {
+        write_offset!(self.0, 16, reference_timestamp.value(), u64, to_be);
+    }
+
+    pub fn set_origin_timestamp(&mut self, origin_timestamp: Timestamp) {
+        write_offset!(self.0, 24, origin_timestamp.value(), u64, to_be);
+    }
+
+    pub fn set_receive_timestamp(&mut self, receive_timestamp: Timestamp) {
+        write_offset!(self.0, 32, receive_timestamp.value(), u64, to_be);
+    }
+
+    pub fn set_transmit_timestamp(&mut self, transmit_timestamp: Timestamp) {
+        write_offset!(self.0, 40, transmit_timestamp.value(), u64, to_be);
+    }
+);
+
+impl<'a> MutNtpPacket<'a> {
+    /// Sets up an SNTP client request. Zeroes all header fields except version, set to 4, mode,
+    /// set to client, and the transmit timestamp, set to `transmit_timestamp`. The server copies
+    /// the transmit timestamp into the origin timestamp of its reply.
+    pub fn set_client_request(&mut self, transmit_timestamp: Timestamp) {
+        for byte in &mut self.0[..48] {
+            *byte = 0;
+        }
+        self.set_version(4);
+        self.set_mode(Mode::CLIENT);
+        self.set_transmit_timestamp(transmit_timestamp);
+    }
+}
+
+
+/// The 2 bit leap indicator, warning of a leap second at the end of the current day.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct LeapIndicator(pub u8);
+
+impl LeapIndicator {
+    pub const NO_WARNING: LeapIndicator = LeapIndicator(0);
+    /// The last minute of the day has 61 seconds.
+    pub const LAST_MINUTE_61: LeapIndicator = LeapIndicator(1);
+    /// The last minute of the day has 59 seconds.
+    pub const LAST_MINUTE_59: LeapIndicator = LeapIndicator(2);
+    /// The clock is not synchronized.
+    pub const UNSYNCHRONIZED: LeapIndicator = LeapIndicator(3);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The 3 bit association mode of an NTP packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Mode(pub u8);
+
+impl Mode {
+    pub const RESERVED: Mode = Mode(0);
+    pub const SYMMETRIC_ACTIVE: Mode = Mode(1);
+    pub const SYMMETRIC_PASSIVE: Mode = Mode(2);
+    pub const CLIENT: Mode = Mode(3);
+    pub const SERVER: Mode = Mode(4);
+    pub const BROADCAST: Mode = Mode(5);
+    pub const CONTROL: Mode = Mode(6);
+    pub const PRIVATE: Mode = Mode(7);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// A 64 bit NTP timestamp, seconds since the NTP epoch, 1900-01-01, as 32.32 fixed point. Zero
+/// means the time is unknown.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    #[inline]
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the whole seconds part.
+    pub fn seconds(&self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    /// Returns the fractional seconds part, in units of 2^-32 seconds.
+    pub fn fraction(&self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Returns the time since the start of the NTP era the timestamp is in.
+    pub fn to_duration(&self) -> Duration {
+        let nanos = (u64::from(self.fraction()) * 1_000_000_000) >> 32;
+        Duration::new(u64::from(self.seconds()), nanos as u32)
+    }
+
+    /// Creates a timestamp from the time since the start of an NTP era, rounding down to the
+    /// timestamp resolution. Returns `None` if `duration` does not fit in one era.
+    pub fn from_duration(duration: Duration) -> Option<Timestamp> {
+        if duration.as_secs() >= ERA_SECONDS {
+            return None;
+        }
+        let fraction = (u64::from(duration.subsec_nanos()) << 32) / 1_000_000_000;
+        Some(Timestamp((duration.as_secs() << 32) | fraction))
+    }
+
+    /// Converts the timestamp to a system time. As in RFC 4330, timestamps with the highest
+    /// bit cleared are taken to be in the era starting 2036, making the covered range 1968 to
+    /// 2104. Returns `None` if the time can't be represented by `SystemTime`.
+    #[cfg(feature = "std")]
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let mut seconds = Duration::new(u64::from(self.seconds()), 0);
+        if self.seconds() & 0x8000_0000 == 0 {
+            seconds += Duration::from_secs(ERA_SECONDS);
+        }
+        let since_epoch = seconds + Duration::new(0, self.to_duration().subsec_nanos());
+        let unix_epoch_offset = Duration::from_secs(UNIX_EPOCH_OFFSET);
+        if since_epoch >= unix_epoch_offset {
+            UNIX_EPOCH.checked_add(since_epoch - unix_epoch_offset)
+        } else {
+            UNIX_EPOCH.checked_sub(unix_epoch_offset - since_epoch)
+        }
+    }
+
+    /// Creates a timestamp from a system time, the reverse of `to_system_time`. Returns `None`
+    /// if `time` is outside the range 1968 to 2104.
+    #[cfg(feature = "std")]
+    pub fn from_system_time(time: SystemTime) -> Option<Timestamp> {
+        let unix_epoch_offset = Duration::from_secs(UNIX_EPOCH_OFFSET);
+        let since_epoch = match time.duration_since(UNIX_EPOCH) {
+            Ok(since_unix_epoch) => unix_epoch_offset + since_unix_epoch,
+            Err(error) => unix_epoch_offset.checked_sub(error.duration())?,
+        };
+        let era_start = Duration::from_secs(ERA_SECONDS / 2);
+        if since_epoch < era_start {
+            return None;
+        }
+        let in_era = if since_epoch.as_secs() >= ERA_SECONDS {
+            since_epoch - Duration::from_secs(ERA_SECONDS)
+        } else {
+            since_epoch
+        };
+        let timestamp = Timestamp::from_duration(in_era)?;
+        if since_epoch.as_secs() >= ERA_SECONDS && timestamp.seconds() & 0x8000_0000 != 0 {
+            return None;
+        }
+        Some(timestamp)
+    }
+}
+
+/// Converts a value in the NTP short format, seconds as 16.16 fixed point, to a duration.
+pub fn short_to_duration(value: u32) -> Duration {
+    let nanos = (u64::from(value & 0xffff) * 1_000_000_000) >> 16;
+    Duration::new(u64::from(value >> 16), nanos as u32)
+}
+
+impl<'a> fmt::Display for NtpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NTP version={} mode={} stratum={}",
+            self.version(),
+            self.mode().value(),
+            self.stratum()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! ntp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutNtpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ntp_setget_test!(leap_indicator, set_leap_indicator, LeapIndicator(3), 0, [0xc0]);
+    ntp_setget_test!(version, set_version, 7, 0, [0x38]);
+    ntp_setget_test!(mode, set_mode, Mode::PRIVATE, 0, [0x07]);
+    ntp_setget_test!(stratum, set_stratum, 0xab, 1, [0xab]);
+    ntp_setget_test!(poll, set_poll, -6, 2, [0xfa]);
+    ntp_setget_test!(precision, set_precision, -20, 3, [0xec]);
+    ntp_setget_test!(root_delay, set_root_delay, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 0x01]);
+    ntp_setget_test!(
+        root_dispersion,
+        set_root_dispersion,
+        0xabcdef01,
+        8,
+        [0xab, 0xcd, 0xef, 0x01]
+    );
+    ntp_setget_test!(reference_id, set_reference_id, *b"GPS\0", 12, *b"GPS\0");
+    ntp_setget_test!(
+        reference_timestamp,
+        set_reference_timestamp,
+        Timestamp(0x0102030405060708),
+        16,
+        [1, 2, 3, 4, 5, 6, 7, 8]
+    );
+    ntp_setget_test!(
+        origin_timestamp,
+        set_origin_timestamp,
+        Timestamp(0x0102030405060708),
+        24,
+        [1, 2, 3, 4, 5, 6, 7, 8]
+    );
+    ntp_setget_test!(
+        receive_timestamp,
+        set_receive_timestamp,
+        Timestamp(0x0102030405060708),
+        32,
+        [1, 2, 3, 4, 5, 6, 7, 8]
+    );
+    ntp_setget_test!(
+        transmit_timestamp,
+        set_transmit_timestamp,
+        Timestamp(0x0102030405060708),
+        40,
+        [1, 2, 3, 4, 5, 6, 7, 8]
+    );
+
+    #[test]
+    fn client_request() {
+        let mut data = [0xff; 48];
+        let timestamp = Timestamp(0x0102030405060708);
+        MutNtpPacket::new(&mut data).unwrap().set_client_request(timestamp);
+        assert_eq!(0x23, data[0]);
+        assert!(data[1..40].iter().all(|&byte| byte == 0));
+        let testee = NtpPacket::new(&data).unwrap();
+        assert_eq!(LeapIndicator::NO_WARNING, testee.leap_indicator());
+        assert_eq!(4, testee.version());
+        assert_eq!(Mode::CLIENT, testee.mode());
+        assert_eq!(timestamp, testee.transmit_timestamp());
+    }
+
+    #[test]
+    fn timestamp_duration() {
+        let timestamp = Timestamp(0x0000_0002_8000_0000);
+        assert_eq!(Duration::from_millis(2500), timestamp.to_duration());
+        assert_eq!(Some(timestamp), Timestamp::from_duration(Duration::from_millis(2500)));
+        assert_eq!(None, Timestamp::from_duration(Duration::from_secs(ERA_SECONDS)));
+        assert_eq!(Duration::from_millis(1250), short_to_duration(0x0001_4000));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn timestamp_system_time() {
+        let unix_epoch = Timestamp(UNIX_EPOCH_OFFSET << 32);
+        assert_eq!(Some(UNIX_EPOCH), unix_epoch.to_system_time());
+        assert_eq!(Some(unix_epoch), Timestamp::from_system_time(UNIX_EPOCH));
+
+        // 2040-01-01, in the second era
+        let time = UNIX_EPOCH + Duration::from_secs(2_208_988_800);
+        let timestamp = Timestamp::from_system_time(time).unwrap();
+        assert_eq!(0x0754_fd00, timestamp.seconds());
+        assert_eq!(Some(time), timestamp.to_system_time());
+
+        assert_eq!(None, Timestamp::from_system_time(UNIX_EPOCH - Duration::from_secs(1 << 30)));
+        let year_2110 = UNIX_EPOCH + Duration::from_secs(4_418_064_000);
+        assert_eq!(None, Timestamp::from_system_time(year_2110));
+    }
+
+    arbitrary_test!(arbitrary_well_formed, NtpPacketBuf => |packet| packet.version() == 4);
+}
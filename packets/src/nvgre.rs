@@ -0,0 +1,165 @@
+//! Network Virtualization using Generic Routing Encapsulation, RFC 7637. An NVGRE header is a
+//! GRE header with only the key present, where the key holds a Virtual Subnet ID and a FlowID.
+
+use ethernet::{EtherType, EthernetPacket, MutEthernetPacket};
+use gre::{Flags, GrePacket};
+use std::fmt;
+use types::*;
+
+packet!(
+    NvgrePacket,
+    MutNvgrePacket,
+    NvgrePacketBuf,
+    8,
+    |_data| 8,
+    init |data| {
+        write_offset!(data, 0, [Flags::KEY.bits(), 0], [u8; 2]);
+        write_offset!(data, 2, EtherType::TRANSPARENT_ETHERNET_BRIDGING.value(), u16, to_be);
+    }
+);
+
+getters!(NvgrePacket
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 0, u8))
+    }
+
+    pub fn version(&self) -> u3 {
+        read_offset!(self.0, 1, u8) & 0x07
+    }
+
+    /// Returns the EtherType of the encapsulated payload. Always
+    /// `EtherType::TRANSPARENT_ETHERNET_BRIDGING` for valid NVGRE packets.
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType(read_offset!(self.0, 2, u16, from_be))
+    }
+
+    /// Returns the 24 bit Virtual Subnet ID, the upper three bytes of the GRE key.
+    pub fn vsid(&self) -> u24 {
+        read_offset!(self.0, 4, u24, from_be)
+    }
+
+    /// Returns the FlowID, the lowest byte of the GRE key.
+    pub fn flow_id(&self) -> u8 {
+        read_offset!(self.0, 7, u8)
+    }
+
+    /// Returns the encapsulated Ethernet frame, or `None` if the payload is too short to hold
+    /// an Ethernet header.
+    pub fn inner_ethernet(&self) -> Option<EthernetPacket<'a>> {
+        EthernetPacket::new(&self.0[8..])
+    }
+);
+
+impl<'a> NvgrePacket<'a> {
+    /// Interprets a GRE packet as NVGRE. Returns `None` unless the key is the only optional
+    /// field present and the protocol type is Transparent Ethernet Bridging.
+    pub fn from_gre(gre: GrePacket<'a>) -> Option<NvgrePacket<'a>> {
+        let optional_fields = gre.flags() & (Flags::CHECKSUM | Flags::KEY | Flags::SEQUENCE);
+        if optional_fields == Flags::KEY &&
+            gre.protocol_type() == EtherType::TRANSPARENT_ETHERNET_BRIDGING
+        {
+            NvgrePacket::new(gre.data())
+        } else {
+            None
+        }
+    }
+}
+
+setters!(MutNvgrePacket
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_offset!(self.0, 0, flags.bits(), u8);
+    }
+
+    pub fn set_version(&mut self, version: u3) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xf8) | (version & 0x07);
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_protocol_type(&mut self, protocol_type: EtherType) {
+        write_offset!(self.0, 2, protocol_type.value(), u16, to_be);
+    }
+
+    /// Sets the 24 bit Virtual Subnet ID. The highest byte of `vsid` is ignored.
+    pub fn set_vsid(&mut self, vsid: u24) {
+        write_offset!(self.0, 4, vsid, u24, to_be);
+    }
+
+    pub fn set_flow_id(&mut self, flow_id: u8) {
+        write_offset!(self.0, 7, flow_id, u8);
+    }
+);
+
+impl<'a> MutNvgrePacket<'a> {
+    /// Sets the flags and protocol type to the values required by NVGRE. That is only the key
+    /// present bit set, version zero and a protocol type of Transparent Ethernet Bridging.
+    pub fn set_nvgre_defaults(&mut self) {
+        self.set_flags(Flags::KEY);
+        self.set_version(0);
+        self.set_protocol_type(EtherType::TRANSPARENT_ETHERNET_BRIDGING);
+    }
+
+    /// Returns the encapsulated Ethernet frame for modification, or `None` if the payload is too
+    /// short to hold an Ethernet header.
+    pub fn inner_ethernet(&mut self) -> Option<MutEthernetPacket<'_>> {
+        MutEthernetPacket::new(&mut self.0[8..])
+    }
+}
+
+impl<'a> fmt::Display for NvgrePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NVGRE vsid={} flow={}", self.vsid(), self.flow_id())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! nvgre_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutNvgrePacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    nvgre_setget_test!(flags, set_flags, Flags::KEY, 0, [0x20]);
+    nvgre_setget_test!(version, set_version, 0x07, 1, [0x07]);
+    nvgre_setget_test!(vsid, set_vsid, 0xabcdef, 4, [0xab, 0xcd, 0xef]);
+    nvgre_setget_test!(flow_id, set_flow_id, 0xab, 7, [0xab]);
+
+    #[test]
+    fn vsid_and_flow_id_are_gre_key() {
+        let mut data = [0; 8 + 14];
+        {
+            let mut testee = MutNvgrePacket::new(&mut data).unwrap();
+            testee.set_nvgre_defaults();
+            testee.set_vsid(0x123456);
+            testee.set_flow_id(0x78);
+        }
+        let gre = GrePacket::new(&data).unwrap();
+        assert_eq!(8, gre.header_len());
+        assert_eq!(Some(0x12345678), gre.key());
+
+        let testee = NvgrePacket::from_gre(gre).unwrap();
+        assert_eq!(0x123456, testee.vsid());
+        assert_eq!(0x78, testee.flow_id());
+        assert!(testee.inner_ethernet().is_some());
+    }
+
+    #[test]
+    fn not_nvgre() {
+        let mut data = [0x20, 0x00, 0x08, 0x00, 0, 0, 0, 0];
+        assert!(NvgrePacket::from_gre(GrePacket::new(&data).unwrap()).is_none());
+        data[0] = 0x30;
+        data[2..4].copy_from_slice(&[0x65, 0x58]);
+        assert!(NvgrePacket::from_gre(GrePacket::new(&data).unwrap()).is_none());
+        data[0] = 0x20;
+        assert!(NvgrePacket::from_gre(GrePacket::new(&data).unwrap()).is_some());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        NvgrePacketBuf => |packet| NvgrePacket::from_gre(GrePacket::new(packet.data()).unwrap())
+            .is_some()
+    );
+}
@@ -0,0 +1,989 @@
+//! Open Shortest Path First version 2, RFC 2328.
+//!
+//! All OSPF packets start with the same 24 byte header, followed by a body depending on the
+//! packet type. `OspfPacket` gives access to the header, and its conversion methods to views of
+//! the type specific bodies. All views include the header, and honor the packet length field
+//! when iterating over the lists in the body.
+
+use checksum::{self, Checksum};
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// The multicast group all OSPF routers listen on.
+pub const ALL_SPF_ROUTERS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 5);
+
+/// The multicast group the designated routers listen on.
+pub const ALL_D_ROUTERS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 6);
+
+macro_rules! ospf_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn version(&self) -> u8 {
+                read_offset!(self.0, 0, u8)
+            }
+
+            pub fn packet_type(&self) -> PacketType {
+                PacketType(read_offset!(self.0, 1, u8))
+            }
+
+            /// Returns the length of the OSPF packet in bytes, including the header.
+            pub fn packet_length(&self) -> u16 {
+                read_offset!(self.0, 2, u16, from_be)
+            }
+
+            /// Returns the router ID of the sending router.
+            pub fn router_id(&self) -> Ipv4Addr {
+                Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+            }
+
+            pub fn area_id(&self) -> Ipv4Addr {
+                Ipv4Addr::from(read_offset!(self.0, 8, [u8; 4]))
+            }
+
+            pub fn checksum(&self) -> u16 {
+                read_offset!(self.0, 12, u16, from_be)
+            }
+
+            pub fn auth_type(&self) -> AuthType {
+                AuthType(read_offset!(self.0, 14, u16, from_be))
+            }
+
+            pub fn authentication(&self) -> [u8; 8] {
+                read_offset!(self.0, 16, [u8; 8])
+            }
+
+            /// Computes the checksum of the packet, covering the bytes given by the packet
+            /// length except the authentication field. The current value of the checksum field
+            /// is ignored. Not used with cryptographic authentication.
+            pub fn calculate_checksum(&self) -> u16 {
+                let data = packet_data(self.0);
+                let mut checksum = Checksum::new();
+                checksum.add_bytes(&data[..12]);
+                checksum.add_bytes(&data[14..16]);
+                checksum.add_bytes(&data[24..]);
+                checksum.finish()
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_version(&mut self, version: u8) {
+                write_offset!(self.0, 0, version, u8);
+            }
+
+            pub fn set_packet_type(&mut self, packet_type: PacketType) {
+                write_offset!(self.0, 1, packet_type.value(), u8);
+            }
+
+            pub fn set_packet_length(&mut self, packet_length: u16) {
+                write_offset!(self.0, 2, packet_length, u16, to_be);
+            }
+
+            pub fn set_router_id(&mut self, router_id: Ipv4Addr) {
+                write_offset!(self.0, 4, router_id.octets(), [u8; 4]);
+            }
+
+            pub fn set_area_id(&mut self, area_id: Ipv4Addr) {
+                write_offset!(self.0, 8, area_id.octets(), [u8; 4]);
+            }
+
+            pub fn set_checksum(&mut self, checksum: u16) {
+                write_offset!(self.0, 12, checksum, u16, to_be);
+            }
+
+            pub fn set_auth_type(&mut self, auth_type: AuthType) {
+                write_offset!(self.0, 14, auth_type.value(), u16, to_be);
+            }
+
+            pub fn set_authentication(&mut self, authentication: [u8; 8]) {
+                write_offset!(self.0, 16, authentication, [u8; 8]);
+            }
+        );
+    }
+}
+
+packet!(
+    /// The header common to all OSPF packets. Use one of the conversion methods to get a view
+    /// of the packet type specific fields.
+    OspfPacket,
+    MutOspfPacket,
+    OspfPacketBuf,
+    24,
+    |_data| 24,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, None)
+);
+ospf_common!(OspfPacket, MutOspfPacket);
+
+impl<'a> OspfPacket<'a> {
+    /// Returns the packet as a hello packet, or `None` if it is of another type or too short.
+    pub fn hello(&self) -> Option<HelloPacket<'a>> {
+        self.as_type(PacketType::HELLO).and_then(HelloPacket::new)
+    }
+
+    /// Returns the packet as a database description, or `None` if it is of another type or
+    /// too short.
+    pub fn database_description(&self) -> Option<DatabaseDescriptionPacket<'a>> {
+        self.as_type(PacketType::DATABASE_DESCRIPTION).and_then(DatabaseDescriptionPacket::new)
+    }
+
+    /// Returns the packet as a link state request, or `None` if it is of another type.
+    pub fn link_state_request(&self) -> Option<LinkStateRequestPacket<'a>> {
+        self.as_type(PacketType::LINK_STATE_REQUEST).and_then(LinkStateRequestPacket::new)
+    }
+
+    /// Returns the packet as a link state update, or `None` if it is of another type or too
+    /// short.
+    pub fn link_state_update(&self) -> Option<LinkStateUpdatePacket<'a>> {
+        self.as_type(PacketType::LINK_STATE_UPDATE).and_then(LinkStateUpdatePacket::new)
+    }
+
+    /// Returns the packet as a link state acknowledgment, or `None` if it is of another type.
+    pub fn link_state_ack(&self) -> Option<LinkStateAckPacket<'a>> {
+        self.as_type(PacketType::LINK_STATE_ACK).and_then(LinkStateAckPacket::new)
+    }
+
+    fn as_type(&self, packet_type: PacketType) -> Option<&'a [u8]> {
+        if self.packet_type() == packet_type {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the part of `data` covered by the packet length field of the OSPF packet it holds,
+/// or all of it if the packet is truncated. Never shorter than the header.
+fn packet_data(data: &[u8]) -> &[u8] {
+    let len = usize::from(read_offset!(data, 2, u16, from_be)).max(24);
+    &data[..data.len().min(len)]
+}
+
+/// Returns the body of the OSPF packet in `data` from `offset`, limited by the packet length.
+fn body(data: &[u8], offset: usize) -> &[u8] {
+    packet_data(data).get(offset..).unwrap_or(&[])
+}
+
+/// Returns the length of the OSPF packet in `data` given by its packet length field.
+fn packet_len(data: &[u8]) -> usize {
+    usize::from(read_offset!(data, 2, u16, from_be))
+}
+
+/// Sets the packet length field of the OSPF packet in `data` to `len`. Returns false if it does
+/// not fit.
+fn set_packet_len(data: &mut [u8], len: usize) -> bool {
+    len <= usize::from(u16::MAX) && {
+        write_offset!(data, 2, len as u16, u16, to_be);
+        true
+    }
+}
+
+/// Sets the version to 2, the type to `packet_type` if given, and the packet length field to
+/// the length of the OSPF packet in `data`, and then the checksum. Uses no authentication.
+fn init_packet(data: &mut [u8], packet_type: Option<PacketType>) {
+    write_offset!(data, 0, 2u8, u8);
+    if let Some(packet_type) = packet_type {
+        write_offset!(data, 1, packet_type.value(), u8);
+    }
+    set_packet_len(data, data.len());
+    write_offset!(data, 14, AuthType::NULL.value(), u16, to_be);
+    let checksum = OspfPacket(data).calculate_checksum();
+    write_offset!(data, 12, checksum, u16, to_be);
+}
+
+
+packet!(
+    /// A hello packet, sent periodically to discover and keep neighbors. The payload holds the
+    /// router IDs of the neighbors heard from recently, see `neighbors`.
+    HelloPacket,
+    MutHelloPacket,
+    HelloPacketBuf,
+    44,
+    |_data| 44,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, Some(PacketType::HELLO))
+);
+ospf_common!(HelloPacket, MutHelloPacket);
+
+getters!(HelloPacket
+    pub fn network_mask(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 24, [u8; 4]))
+    }
+
+    /// Returns the interval between hello packets, in seconds.
+    pub fn hello_interval(&self) -> u16 {
+        read_offset!(self.0, 28, u16, from_be)
+    }
+
+    pub fn options(&self) -> Options {
+        Options::from_bits_truncate(read_offset!(self.0, 30, u8))
+    }
+
+    /// Returns the priority of the router in the designated router election. Zero means the
+    /// router never becomes designated router.
+    pub fn router_priority(&self) -> u8 {
+        read_offset!(self.0, 31, u8)
+    }
+
+    /// Returns the number of seconds without a hello before a router is declared down.
+    pub fn router_dead_interval(&self) -> u32 {
+        read_offset!(self.0, 32, u32, from_be)
+    }
+
+    pub fn designated_router(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 36, [u8; 4]))
+    }
+
+    pub fn backup_designated_router(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 40, [u8; 4]))
+    }
+
+    /// Returns an iterator over the router IDs of the neighbors.
+    pub fn neighbors(&self) -> Neighbors<'a> {
+        Neighbors { data: body(self.0, 44) }
+    }
+);
+
+setters!(MutHelloPacket
+    pub fn set_network_mask(&mut self, network_mask: Ipv4Addr) {
+        write_offset!(self.0, 24, network_mask.octets(), [u8; 4]);
+    }
+
+    pub fn set_hello_interval(&mut self, hello_interval: u16) {
+        write_offset!(self.0, 28, hello_interval, u16, to_be);
+    }
+
+    pub fn set_options(&mut self, options: Options) {
+        write_offset!(self.0, 30, options.bits(), u8);
+    }
+
+    pub fn set_router_priority(&mut self, router_priority: u8) {
+        write_offset!(self.0, 31, router_priority, u8);
+    }
+
+    pub fn set_router_dead_interval(&mut self, router_dead_interval: u32) {
+        write_offset!(self.0, 32, router_dead_interval, u32, to_be);
+    }
+
+    pub fn set_designated_router(&mut self, designated_router: Ipv4Addr) {
+        write_offset!(self.0, 36, designated_router.octets(), [u8; 4]);
+    }
+
+    pub fn set_backup_designated_router(&mut self, backup_designated_router: Ipv4Addr) {
+        write_offset!(self.0, 40, backup_designated_router.octets(), [u8; 4]);
+    }
+);
+
+impl<'a> MutHelloPacket<'a> {
+    /// Writes `neighbors` after the fixed fields and sets the packet length to cover them.
+    /// Returns false, leaving the packet unchanged, if the backing slice is too short to hold
+    /// the neighbors or the packet length does not fit in its field.
+    pub fn set_neighbors(&mut self, neighbors: &[Ipv4Addr]) -> bool {
+        let len = 44 + neighbors.len() * 4;
+        if len > self.0.len() || len > usize::from(u16::MAX) {
+            return false;
+        }
+        for (neighbor, chunk) in neighbors.iter().zip(self.0[44..len].chunks_mut(4)) {
+            chunk.copy_from_slice(&neighbor.octets());
+        }
+        self.set_packet_length(len as u16);
+        true
+    }
+}
+
+
+packet!(
+    /// A database description packet, describing the link state database with a list of LSA
+    /// headers during adjacency forming.
+    DatabaseDescriptionPacket,
+    MutDatabaseDescriptionPacket,
+    DatabaseDescriptionPacketBuf,
+    32,
+    |_data| 32,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, Some(PacketType::DATABASE_DESCRIPTION))
+);
+ospf_common!(DatabaseDescriptionPacket, MutDatabaseDescriptionPacket);
+
+getters!(DatabaseDescriptionPacket
+    /// Returns the largest IP packet the interface can send without fragmentation.
+    pub fn interface_mtu(&self) -> u16 {
+        read_offset!(self.0, 24, u16, from_be)
+    }
+
+    pub fn options(&self) -> Options {
+        Options::from_bits_truncate(read_offset!(self.0, 26, u8))
+    }
+
+    pub fn flags(&self) -> DatabaseDescriptionFlags {
+        DatabaseDescriptionFlags::from_bits_truncate(read_offset!(self.0, 27, u8))
+    }
+
+    pub fn dd_sequence_number(&self) -> u32 {
+        read_offset!(self.0, 28, u32, from_be)
+    }
+
+    /// Returns an iterator over the LSA headers in the packet.
+    pub fn lsa_headers(&self) -> LsaHeaders<'a> {
+        LsaHeaders { data: body(self.0, 32) }
+    }
+);
+
+setters!(MutDatabaseDescriptionPacket
+    pub fn set_interface_mtu(&mut self, interface_mtu: u16) {
+        write_offset!(self.0, 24, interface_mtu, u16, to_be);
+    }
+
+    pub fn set_options(&mut self, options: Options) {
+        write_offset!(self.0, 26, options.bits(), u8);
+    }
+
+    pub fn set_flags(&mut self, flags: DatabaseDescriptionFlags) {
+        write_offset!(self.0, 27, flags.bits(), u8);
+    }
+
+    pub fn set_dd_sequence_number(&mut self, dd_sequence_number: u32) {
+        write_offset!(self.0, 28, dd_sequence_number, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A link state request packet, requesting the LSAs listed in the payload.
+    LinkStateRequestPacket,
+    MutLinkStateRequestPacket,
+    LinkStateRequestPacketBuf,
+    24,
+    |_data| 24,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, Some(PacketType::LINK_STATE_REQUEST))
+);
+ospf_common!(LinkStateRequestPacket, MutLinkStateRequestPacket);
+
+getters!(LinkStateRequestPacket
+    /// Returns an iterator over the requested LSAs.
+    pub fn requests(&self) -> LsaIdentifiers<'a> {
+        LsaIdentifiers { data: body(self.0, 24) }
+    }
+);
+
+
+packet!(
+    /// A link state update packet, flooding complete LSAs.
+    LinkStateUpdatePacket,
+    MutLinkStateUpdatePacket,
+    LinkStateUpdatePacketBuf,
+    28,
+    |_data| 28,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| {
+        write_offset!(data, 24, 0u32, u32);
+        init_packet(data, Some(PacketType::LINK_STATE_UPDATE));
+    }
+);
+ospf_common!(LinkStateUpdatePacket, MutLinkStateUpdatePacket);
+
+getters!(LinkStateUpdatePacket
+    pub fn lsa_count(&self) -> u32 {
+        read_offset!(self.0, 24, u32, from_be)
+    }
+
+    /// Returns an iterator over the LSAs in the packet. Each LSA is a view of its header,
+    /// with its body as payload.
+    pub fn lsas(&self) -> Lsas<'a> {
+        Lsas {
+            data: body(self.0, 28),
+            remaining: self.lsa_count(),
+        }
+    }
+);
+
+setters!(MutLinkStateUpdatePacket
+    pub fn set_lsa_count(&mut self, lsa_count: u32) {
+        write_offset!(self.0, 24, lsa_count, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A link state acknowledgment packet, acknowledging the LSAs whose headers it lists.
+    LinkStateAckPacket,
+    MutLinkStateAckPacket,
+    LinkStateAckPacketBuf,
+    24,
+    |_data| 24,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, Some(PacketType::LINK_STATE_ACK))
+);
+ospf_common!(LinkStateAckPacket, MutLinkStateAckPacket);
+
+getters!(LinkStateAckPacket
+    /// Returns an iterator over the headers of the acknowledged LSAs.
+    pub fn lsa_headers(&self) -> LsaHeaders<'a> {
+        LsaHeaders { data: body(self.0, 24) }
+    }
+);
+
+
+packet!(
+    /// A link state advertisement header. When part of a link state update, the backing slice
+    /// covers the whole LSA, and the payload is the body of the LSA.
+    LsaHeaderPacket,
+    MutLsaHeaderPacket,
+    LsaHeaderPacketBuf,
+    20,
+    |_data| 20,
+    init |data| {
+        write_offset!(data, 18, data.len() as u16, u16, to_be);
+        if let Some(checksum) = LsaHeaderPacket(data).calculate_checksum() {
+            write_offset!(data, 16, checksum, u16, to_be);
+        }
+    }
+);
+
+getters!(LsaHeaderPacket
+    /// Returns the time since the LSA was originated, in seconds.
+    pub fn ls_age(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    pub fn options(&self) -> Options {
+        Options::from_bits_truncate(read_offset!(self.0, 2, u8))
+    }
+
+    pub fn ls_type(&self) -> LsaType {
+        LsaType(read_offset!(self.0, 3, u8))
+    }
+
+    /// Returns the link state ID, identifying the part of the network described by the LSA.
+    /// Its meaning depends on the LSA type.
+    pub fn link_state_id(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+    }
+
+    /// Returns the router ID of the router that originated the LSA.
+    pub fn advertising_router(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 8, [u8; 4]))
+    }
+
+    /// Returns the sequence number, a signed value increasing with each new instance of the
+    /// LSA.
+    pub fn ls_sequence_number(&self) -> i32 {
+        read_offset!(self.0, 12, i32, from_be)
+    }
+
+    pub fn ls_checksum(&self) -> u16 {
+        read_offset!(self.0, 16, u16, from_be)
+    }
+
+    /// Returns the length of the LSA in bytes, including the header.
+    pub fn length(&self) -> u16 {
+        read_offset!(self.0, 18, u16, from_be)
+    }
+
+    /// Returns the identifier of the LSA, the fields telling LSAs apart.
+    pub fn identifier(&self) -> LsaIdentifier {
+        LsaIdentifier {
+            ls_type: self.ls_type(),
+            link_state_id: self.link_state_id(),
+            advertising_router: self.advertising_router(),
+        }
+    }
+);
+
+impl<'a> LsaHeaderPacket<'a> {
+    /// Computes the Fletcher checksum of the LSA, covering everything but the age, up to the
+    /// length given in the header. The backing slice must hold the entire LSA, and the current
+    /// value of the checksum field is ignored. Returns `None` if the LSA is truncated.
+    pub fn calculate_checksum(&self) -> Option<u16> {
+        let length = usize::from(self.length());
+        let data = self.0.get(2..length)?;
+        Some(checksum::fletcher_checksum(data, 14))
+    }
+}
+
+setters!(MutLsaHeaderPacket
+    pub fn set_ls_age(&mut self, ls_age: u16) {
+        write_offset!(self.0, 0, ls_age, u16, to_be);
+    }
+
+    pub fn set_options(&mut self, options: Options) {
+        write_offset!(self.0, 2, options.bits(), u8);
+    }
+
+    pub fn set_ls_type(&mut self, ls_type: LsaType) {
+        write_offset!(self.0, 3, ls_type.value(), u8);
+    }
+
+    pub fn set_link_state_id(&mut self, link_state_id: Ipv4Addr) {
+        write_offset!(self.0, 4, link_state_id.octets(), [u8; 4]);
+    }
+
+    pub fn set_advertising_router(&mut self, advertising_router: Ipv4Addr) {
+        write_offset!(self.0, 8, advertising_router.octets(), [u8; 4]);
+    }
+
+    pub fn set_ls_sequence_number(&mut self, ls_sequence_number: i32) {
+        write_offset!(self.0, 12, ls_sequence_number, i32, to_be);
+    }
+
+    pub fn set_ls_checksum(&mut self, ls_checksum: u16) {
+        write_offset!(self.0, 16, ls_checksum, u16, to_be);
+    }
+
+    pub fn set_length(&mut self, length: u16) {
+        write_offset!(self.0, 18, length, u16, to_be);
+    }
+);
+
+
+/// Represents the type of an OSPF packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PacketType(pub u8);
+
+impl PacketType {
+    pub const HELLO: PacketType = PacketType(1);
+    pub const DATABASE_DESCRIPTION: PacketType = PacketType(2);
+    pub const LINK_STATE_REQUEST: PacketType = PacketType(3);
+    pub const LINK_STATE_UPDATE: PacketType = PacketType(4);
+    pub const LINK_STATE_ACK: PacketType = PacketType(5);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the authentication type of an OSPF packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AuthType(pub u16);
+
+impl AuthType {
+    pub const NULL: AuthType = AuthType(0);
+    pub const SIMPLE_PASSWORD: AuthType = AuthType(1);
+    pub const CRYPTOGRAPHIC: AuthType = AuthType(2);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Represents the type of a link state advertisement.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct LsaType(pub u8);
+
+impl LsaType {
+    pub const ROUTER: LsaType = LsaType(1);
+    pub const NETWORK: LsaType = LsaType(2);
+    pub const SUMMARY_NETWORK: LsaType = LsaType(3);
+    pub const SUMMARY_ASBR: LsaType = LsaType(4);
+    pub const AS_EXTERNAL: LsaType = LsaType(5);
+    pub const NSSA: LsaType = LsaType(7);
+    pub const OPAQUE_LINK_LOCAL: LsaType = LsaType(9);
+    pub const OPAQUE_AREA_LOCAL: LsaType = LsaType(10);
+    pub const OPAQUE_AS: LsaType = LsaType(11);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the options field of hello packets, database descriptions and LSAs.
+    pub struct Options: u8 {
+        /// A bitmask with a one in the "DN" position, used to prevent loops with MPLS VPNs.
+        const DN = 0x80;
+        /// A bitmask with a one in the "O" position, set when opaque LSAs are supported.
+        const O = 0x40;
+        /// A bitmask with a one in the "DC" position, set when demand circuits are supported.
+        const DC = 0x20;
+        /// A bitmask with a one in the "EA" position.
+        const EA = 0x10;
+        /// A bitmask with a one in the "N/P" position, for NSSA support.
+        const NP = 0x08;
+        /// A bitmask with a one in the "MC" position, set when multicast is supported.
+        const MC = 0x04;
+        /// A bitmask with a one in the "E" position, set when AS external LSAs are flooded.
+        const E = 0x02;
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags of a database description packet.
+    pub struct DatabaseDescriptionFlags: u8 {
+        /// A bitmask with a one in the "I" position, set in the first packet of the exchange.
+        const INIT = 0x04;
+        /// A bitmask with a one in the "M" position, set when more packets follow.
+        const MORE = 0x02;
+        /// A bitmask with a one in the "MS" position, set by the master of the exchange.
+        const MASTER = 0x01;
+    }
+}
+
+/// The fields identifying an LSA, as listed in link state requests.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct LsaIdentifier {
+    pub ls_type: LsaType,
+    pub link_state_id: Ipv4Addr,
+    pub advertising_router: Ipv4Addr,
+}
+
+
+/// Iterator over the router IDs of the neighbors in a hello packet.
+#[derive(Clone)]
+pub struct Neighbors<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Neighbors<'a>);
+
+impl<'a> Iterator for Neighbors<'a> {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Ipv4Addr> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (neighbor, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(Ipv4Addr::from(read_offset!(neighbor, 0, [u8; 4])))
+    }
+}
+
+/// Iterator over a list of LSA headers, in database descriptions and acknowledgments.
+#[derive(Clone)]
+pub struct LsaHeaders<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(LsaHeaders<'a>);
+
+impl<'a> Iterator for LsaHeaders<'a> {
+    type Item = LsaHeaderPacket<'a>;
+
+    fn next(&mut self) -> Option<LsaHeaderPacket<'a>> {
+        if self.data.len() < 20 {
+            return None;
+        }
+        let (header, rest) = self.data.split_at(20);
+        self.data = rest;
+        LsaHeaderPacket::new(header)
+    }
+}
+
+/// Iterator over the requested LSAs in a link state request.
+#[derive(Clone)]
+pub struct LsaIdentifiers<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(LsaIdentifiers<'a>);
+
+impl<'a> Iterator for LsaIdentifiers<'a> {
+    type Item = LsaIdentifier;
+
+    fn next(&mut self) -> Option<LsaIdentifier> {
+        if self.data.len() < 12 {
+            return None;
+        }
+        let (request, rest) = self.data.split_at(12);
+        self.data = rest;
+        Some(LsaIdentifier {
+            ls_type: LsaType(read_offset!(request, 3, u8)),
+            link_state_id: Ipv4Addr::from(read_offset!(request, 4, [u8; 4])),
+            advertising_router: Ipv4Addr::from(read_offset!(request, 8, [u8; 4])),
+        })
+    }
+}
+
+/// Iterator over the LSAs in a link state update. Iteration stops after the number of LSAs
+/// given in the packet, or at the first LSA that does not fit in the data.
+#[derive(Clone)]
+pub struct Lsas<'a> {
+    data: &'a [u8],
+    remaining: u32,
+}
+
+debug_items!(Lsas<'a>);
+
+impl<'a> Iterator for Lsas<'a> {
+    type Item = LsaHeaderPacket<'a>;
+
+    fn next(&mut self) -> Option<LsaHeaderPacket<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let len = usize::from(LsaHeaderPacket::new(self.data)?.length());
+        if len < 20 || len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        self.remaining -= 1;
+        let (lsa, rest) = self.data.split_at(len);
+        self.data = rest;
+        LsaHeaderPacket::new(lsa)
+    }
+}
+
+impl<'a> fmt::Display for OspfPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OSPFv{} type={} router={} area={}",
+            self.version(),
+            self.packet_type().value(),
+            self.router_id(),
+            self.area_id()
+        )
+    }
+}
+
+impl<'a> fmt::Display for HelloPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OSPF hello router={} area={} dr={} bdr={} neighbors={}",
+            self.router_id(),
+            self.area_id(),
+            self.designated_router(),
+            self.backup_designated_router(),
+            self.neighbors().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for DatabaseDescriptionPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OSPF database description router={} seq={}",
+            self.router_id(),
+            self.dd_sequence_number()
+        )
+    }
+}
+
+impl<'a> fmt::Display for LinkStateRequestPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OSPF link state request router={} requests={}",
+            self.router_id(),
+            self.requests().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for LinkStateUpdatePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OSPF link state update router={} lsas={}",
+            self.router_id(),
+            self.lsa_count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for LinkStateAckPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OSPF link state ack router={} lsas={}",
+            self.router_id(),
+            self.lsa_headers().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for LsaHeaderPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OSPF LSA type={} id={} router={} seq={:#x} age={}",
+            self.ls_type().value(),
+            self.link_state_id(),
+            self.advertising_router(),
+            self.ls_sequence_number(),
+            self.ls_age()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! ospf_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutOspfPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ospf_setget_test!(version, set_version, 2, 0, [2]);
+    ospf_setget_test!(packet_type, set_packet_type, PacketType::LINK_STATE_ACK, 1, [5]);
+    ospf_setget_test!(packet_length, set_packet_length, 0xabcd, 2, [0xab, 0xcd]);
+    ospf_setget_test!(router_id, set_router_id, Ipv4Addr::new(1, 2, 3, 4), 4, [1, 2, 3, 4]);
+    ospf_setget_test!(area_id, set_area_id, Ipv4Addr::new(1, 2, 3, 4), 8, [1, 2, 3, 4]);
+    ospf_setget_test!(checksum, set_checksum, 0xabcd, 12, [0xab, 0xcd]);
+    ospf_setget_test!(auth_type, set_auth_type, AuthType::CRYPTOGRAPHIC, 14, [0, 2]);
+    ospf_setget_test!(authentication, set_authentication, [0xab; 8], 16, [0xab; 8]);
+
+    #[test]
+    fn hello() {
+        let mut data = [0; 44 + 8 + 4];
+        let neighbors = [Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(10, 0, 0, 3)];
+        {
+            let mut testee = MutHelloPacket::new(&mut data).unwrap();
+            testee.set_version(2);
+            testee.set_packet_type(PacketType::HELLO);
+            testee.set_network_mask(Ipv4Addr::new(255, 255, 255, 0));
+            testee.set_hello_interval(10);
+            testee.set_options(Options::E);
+            testee.set_router_dead_interval(40);
+            assert!(testee.set_neighbors(&neighbors));
+            let checksum = testee.as_immutable().calculate_checksum();
+            testee.set_checksum(checksum);
+        }
+        assert_eq!(&[0, 52], &data[2..4]);
+        let mut sum = Checksum::new();
+        sum.add_bytes(&data[..16]);
+        sum.add_bytes(&data[24..52]);
+        assert_eq!(0, sum.finish());
+
+        let testee = OspfPacket::new(&data).unwrap().hello().unwrap();
+        assert_eq!(10, testee.hello_interval());
+        assert_eq!(Options::E, testee.options());
+        assert_eq!(neighbors.to_vec(), testee.neighbors().collect::<Vec<_>>());
+        assert!(OspfPacket::new(&data).unwrap().database_description().is_none());
+    }
+
+    #[test]
+    fn database_description() {
+        let mut data = [0; 32 + 20 * 2];
+        data[1] = PacketType::DATABASE_DESCRIPTION.value();
+        data[2..4].copy_from_slice(&[0, 72]);
+        data[27] = 0x07;
+        data[35] = LsaType::NETWORK.value();
+        data[55] = LsaType::AS_EXTERNAL.value();
+        let testee = OspfPacket::new(&data).unwrap().database_description().unwrap();
+        assert_eq!(DatabaseDescriptionFlags::all(), testee.flags());
+        let types = testee.lsa_headers().map(|header| header.ls_type()).collect::<Vec<_>>();
+        assert_eq!(vec![LsaType::NETWORK, LsaType::AS_EXTERNAL], types);
+
+        data[3] = 60;
+        let testee = DatabaseDescriptionPacket::new(&data).unwrap();
+        assert_eq!(1, testee.lsa_headers().count());
+    }
+
+    #[test]
+    fn link_state_request() {
+        let data = [
+            2, 3, 0, 36, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 1, 10, 0, 0, 1, 10, 0, 0, 2,
+        ];
+        let testee = OspfPacket::new(&data).unwrap().link_state_request().unwrap();
+        let requests = testee.requests().collect::<Vec<_>>();
+        assert_eq!(
+            vec![LsaIdentifier {
+                ls_type: LsaType::ROUTER,
+                link_state_id: Ipv4Addr::new(10, 0, 0, 1),
+                advertising_router: Ipv4Addr::new(10, 0, 0, 2),
+            }],
+            requests
+        );
+    }
+
+    #[test]
+    fn link_state_update() {
+        let mut data = [0; 28 + 24 + 20 + 4];
+        data[1] = PacketType::LINK_STATE_UPDATE.value();
+        data[2..4].copy_from_slice(&[0, 76]);
+        MutLinkStateUpdatePacket::new(&mut data).unwrap().set_lsa_count(3);
+        {
+            let mut lsa = MutLsaHeaderPacket::new(&mut data[28..52]).unwrap();
+            lsa.set_ls_type(LsaType::ROUTER);
+            lsa.set_ls_sequence_number(i32::MIN + 1);
+            lsa.set_length(24);
+        }
+        MutLsaHeaderPacket::new(&mut data[52..72]).unwrap().set_length(20);
+
+        let testee = OspfPacket::new(&data).unwrap().link_state_update().unwrap();
+        let lsas = testee.lsas().collect::<Vec<_>>();
+        assert_eq!(2, lsas.len());
+        assert_eq!(i32::MIN + 1, lsas[0].ls_sequence_number());
+        assert_eq!(4, lsas[0].payload().len());
+        assert_eq!(LsaType::ROUTER, lsas[0].identifier().ls_type);
+    }
+
+    mod lsa_header {
+        use super::super::*;
+
+        macro_rules! lsa_header_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutLsaHeaderPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        lsa_header_setget_test!(ls_age, set_ls_age, 0xabcd, 0, [0xab, 0xcd]);
+        lsa_header_setget_test!(options, set_options, Options::O | Options::E, 2, [0x42]);
+        lsa_header_setget_test!(ls_type, set_ls_type, LsaType::NSSA, 3, [7]);
+        lsa_header_setget_test!(
+            ls_sequence_number,
+            set_ls_sequence_number,
+            -2,
+            12,
+            [0xff, 0xff, 0xff, 0xfe]
+        );
+        lsa_header_setget_test!(ls_checksum, set_ls_checksum, 0xabcd, 16, [0xab, 0xcd]);
+        lsa_header_setget_test!(length, set_length, 0xabcd, 18, [0xab, 0xcd]);
+
+        #[test]
+        fn checksum() {
+            // A router LSA with a single stub network link
+            let mut data = [
+                0, 1, 0x22, 1, 10, 0, 0, 1, 10, 0, 0, 1, 0x80, 0, 0, 1, 0, 0, 0, 36,
+                0, 0, 0, 1, 10, 0, 0, 0, 255, 255, 255, 0, 3, 0, 0, 10,
+            ];
+            let checksum = LsaHeaderPacket::new(&data).unwrap().calculate_checksum().unwrap();
+            MutLsaHeaderPacket::new(&mut data).unwrap().set_ls_checksum(checksum);
+            let (c0, c1) = data[2..].iter().fold((0u32, 0u32), |(c0, c1), &byte| {
+                let c0 = (c0 + u32::from(byte)) % 255;
+                (c0, (c1 + c0) % 255)
+            });
+            assert_eq!((0, 0), (c0, c1));
+            // The age is not covered
+            data[1] = 100;
+            assert_eq!(Some(checksum), LsaHeaderPacket::new(&data).unwrap().calculate_checksum());
+            assert_eq!(None, LsaHeaderPacket::new(&data[..30]).unwrap().calculate_checksum());
+        }
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 24 + 8];
+        let mut testee = MutLinkStateRequestPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[1, 2, 3, 4]));
+        assert_eq!(28, testee.as_immutable().packet_length());
+        assert_eq!(&[1, 2, 3, 4], testee.as_immutable().payload());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        OspfPacketBuf => |packet| packet.checksum() == packet.calculate_checksum(),
+        HelloPacketBuf => |packet| OspfPacket::new(packet.data()).unwrap().hello().is_some(),
+        DatabaseDescriptionPacketBuf => |packet| packet.version() == 2,
+        LinkStateRequestPacketBuf => |packet| packet.version() == 2,
+        LinkStateUpdatePacketBuf => |packet| packet.lsa_count() == 0,
+        LinkStateAckPacketBuf => |packet| packet.version() == 2,
+        LsaHeaderPacketBuf => |packet| Some(packet.ls_checksum()) == packet.calculate_checksum(),
+    );
+}
@@ -0,0 +1,117 @@
+//! Conversions between the packet views of this crate and the corresponding types in
+//! [`pnet_packet`](https://docs.rs/pnet_packet). Both wrap a byte slice, so converting in either
+//! direction only moves the slice over; nothing is copied or parsed again.
+//!
+//! Views of this crate convert into pnet packets by value. pnet packets convert by reference,
+//! since the slice can only be borrowed from them, unlike `into_data` of the mutable views here.
+//!
+//! ```rust
+//! extern crate pnet_packet;
+//! extern crate rips_packets;
+//!
+//! use pnet_packet::Packet;
+//! use rips_packets::ipv4::Ipv4Packet;
+//!
+//! fn main() {
+//!     let mut data = [0; 20];
+//!     data[0] = 0x45;
+//!     let packet = Ipv4Packet::new(&data).unwrap();
+//!     let pnet_packet = pnet_packet::ipv4::Ipv4Packet::from(packet);
+//!     assert_eq!(4, pnet_packet.get_version());
+//!     assert_eq!(Ipv4Packet::from(&pnet_packet), packet);
+//!     assert_eq!(&data[..], pnet_packet.packet());
+//! }
+//! ```
+
+use arp::{ArpPacket, MutArpPacket};
+use ethernet::{EthernetPacket, MutEthernetPacket};
+use icmpv6::{Icmpv6Packet, MutIcmpv6Packet};
+use ipv4::{Ipv4Packet, MutIpv4Packet};
+use ipv6::{Ipv6Packet, MutIpv6Packet};
+use pnet_packet::arp::{ArpPacket as PnetArpPacket, MutableArpPacket};
+use pnet_packet::ethernet::{EthernetPacket as PnetEthernetPacket, MutableEthernetPacket};
+use pnet_packet::icmpv6::{Icmpv6Packet as PnetIcmpv6Packet, MutableIcmpv6Packet};
+use pnet_packet::ipv4::{Ipv4Packet as PnetIpv4Packet, MutableIpv4Packet};
+use pnet_packet::ipv6::{Ipv6Packet as PnetIpv6Packet, MutableIpv6Packet};
+use pnet_packet::tcp::{MutableTcpPacket, TcpPacket as PnetTcpPacket};
+use pnet_packet::udp::{MutableUdpPacket, UdpPacket as PnetUdpPacket};
+use pnet_packet::{MutablePacket, Packet};
+use tcp::{MutTcpPacket, TcpPacket};
+use udp::{MutUdpPacket, UdpPacket};
+
+/// Implements the conversions for a pair of packet types with the same minimum length, which
+/// makes the conversions infallible.
+macro_rules! pnet_conversions {
+    ($name:ident, $mut_name:ident, $pnet_name:ident, $pnet_mut_name:ident) => {
+        impl<'a> From<$name<'a>> for $pnet_name<'a> {
+            fn from(packet: $name<'a>) -> $pnet_name<'a> {
+                $pnet_name::new(packet.data()).expect("Same minimum length")
+            }
+        }
+
+        impl<'a> From<$mut_name<'a>> for $pnet_mut_name<'a> {
+            fn from(packet: $mut_name<'a>) -> $pnet_mut_name<'a> {
+                $pnet_mut_name::new(packet.into_data()).expect("Same minimum length")
+            }
+        }
+
+        impl<'a, 'p> From<&'a $pnet_name<'p>> for $name<'a> {
+            fn from(packet: &'a $pnet_name<'p>) -> $name<'a> {
+                $name::new(packet.packet()).expect("Same minimum length")
+            }
+        }
+
+        impl<'a, 'p> From<&'a mut $pnet_mut_name<'p>> for $mut_name<'a> {
+            fn from(packet: &'a mut $pnet_mut_name<'p>) -> $mut_name<'a> {
+                $mut_name::new(packet.packet_mut()).expect("Same minimum length")
+            }
+        }
+    };
+}
+
+pnet_conversions!(EthernetPacket, MutEthernetPacket, PnetEthernetPacket, MutableEthernetPacket);
+pnet_conversions!(ArpPacket, MutArpPacket, PnetArpPacket, MutableArpPacket);
+pnet_conversions!(Ipv4Packet, MutIpv4Packet, PnetIpv4Packet, MutableIpv4Packet);
+pnet_conversions!(Ipv6Packet, MutIpv6Packet, PnetIpv6Packet, MutableIpv6Packet);
+pnet_conversions!(TcpPacket, MutTcpPacket, PnetTcpPacket, MutableTcpPacket);
+pnet_conversions!(UdpPacket, MutUdpPacket, PnetUdpPacket, MutableUdpPacket);
+pnet_conversions!(Icmpv6Packet, MutIcmpv6Packet, PnetIcmpv6Packet, MutableIcmpv6Packet);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn same_minimum_lengths() {
+        assert_eq!(EthernetPacket::MIN_LEN, PnetEthernetPacket::minimum_packet_size());
+        assert_eq!(ArpPacket::MIN_LEN, PnetArpPacket::minimum_packet_size());
+        assert_eq!(Ipv4Packet::MIN_LEN, PnetIpv4Packet::minimum_packet_size());
+        assert_eq!(Ipv6Packet::MIN_LEN, PnetIpv6Packet::minimum_packet_size());
+        assert_eq!(TcpPacket::MIN_LEN, PnetTcpPacket::minimum_packet_size());
+        assert_eq!(UdpPacket::MIN_LEN, PnetUdpPacket::minimum_packet_size());
+        assert_eq!(Icmpv6Packet::MIN_LEN, PnetIcmpv6Packet::minimum_packet_size());
+    }
+
+    #[test]
+    fn mutable_round_trip() {
+        let mut data = [0; 20];
+        {
+            let mut packet = MutableIpv4Packet::from(MutIpv4Packet::new(&mut data).unwrap());
+            packet.set_ttl(64);
+            MutIpv4Packet::from(&mut packet).set_source(Ipv4Addr::new(10, 0, 0, 1));
+        }
+        let packet = Ipv4Packet::new(&data).unwrap();
+        assert_eq!(64, packet.ttl());
+        assert_eq!(Ipv4Addr::new(10, 0, 0, 1), PnetIpv4Packet::from(packet).get_source());
+    }
+
+    #[test]
+    fn no_copy() {
+        let data = [0; 8];
+        let packet = PnetUdpPacket::from(UdpPacket::new(&data).unwrap());
+        assert_eq!(data.as_ptr(), packet.packet().as_ptr());
+        assert_eq!(data.as_ptr(), UdpPacket::from(&packet).data().as_ptr());
+    }
+}
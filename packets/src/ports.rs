@@ -0,0 +1,230 @@
+//! Well known port numbers and the names of the services using them.
+//!
+//! The constants are the ports assigned by IANA, or in common use, for the services. The table
+//! `SERVICES` maps a port and transport protocol to a short service name for printing dissected
+//! packets, and `port` looks a name up the other way.
+//!
+//! ```rust
+//! use rips_packets::ip::Protocol;
+//! use rips_packets::ports;
+//!
+//! assert_eq!(Some("DNS"), ports::service(53, Protocol::UDP));
+//! assert_eq!(Some("HTTPS"), ports::flow_service(51234, 443, Protocol::TCP));
+//! assert_eq!(Some(ports::NTP), ports::port("ntp", Protocol::UDP));
+//! ```
+
+use ip::Protocol;
+
+pub const FTP_DATA: u16 = 20;
+pub const FTP: u16 = 21;
+pub const SSH: u16 = 22;
+pub const TELNET: u16 = 23;
+pub const SMTP: u16 = 25;
+pub const DNS: u16 = 53;
+pub const DHCP_SERVER: u16 = 67;
+pub const DHCP_CLIENT: u16 = 68;
+pub const TFTP: u16 = 69;
+pub const HTTP: u16 = 80;
+pub const KERBEROS: u16 = 88;
+pub const POP3: u16 = 110;
+pub const NTP: u16 = 123;
+pub const NETBIOS_NS: u16 = 137;
+pub const NETBIOS_DGM: u16 = 138;
+pub const NETBIOS_SSN: u16 = 139;
+pub const IMAP: u16 = 143;
+pub const SNMP: u16 = 161;
+pub const SNMP_TRAP: u16 = 162;
+pub const BGP: u16 = 179;
+pub const LDAP: u16 = 389;
+pub const HTTPS: u16 = 443;
+pub const SMB: u16 = 445;
+pub const ISAKMP: u16 = 500;
+pub const MODBUS: u16 = 502;
+pub const SYSLOG: u16 = 514;
+pub const RIP: u16 = 520;
+pub const RIPNG: u16 = 521;
+pub const DHCPV6_CLIENT: u16 = 546;
+pub const DHCPV6_SERVER: u16 = 547;
+pub const SUBMISSION: u16 = 587;
+pub const LDAPS: u16 = 636;
+pub const IMAPS: u16 = 993;
+pub const POP3S: u16 = 995;
+pub const L2TP: u16 = 1701;
+pub const RADIUS: u16 = 1812;
+pub const RADIUS_ACCOUNTING: u16 = 1813;
+pub const HSRP: u16 = 1985;
+pub const HSRP_IPV6: u16 = 2029;
+pub const NETFLOW: u16 = 2055;
+pub const GTP_U: u16 = 2152;
+pub const MYSQL: u16 = 3306;
+pub const RDP: u16 = 3389;
+pub const TEREDO: u16 = 3544;
+pub const DIAMETER: u16 = 3868;
+pub const IPSEC_NAT_T: u16 = 4500;
+pub const IPFIX: u16 = 4739;
+pub const VXLAN: u16 = 4789;
+pub const SIP: u16 = 5060;
+pub const SIPS: u16 = 5061;
+pub const MDNS: u16 = 5353;
+pub const POSTGRESQL: u16 = 5432;
+pub const GENEVE: u16 = 6081;
+pub const SFLOW: u16 = 6343;
+pub const HTTP_ALT: u16 = 8080;
+pub const WIREGUARD: u16 = 51820;
+
+/// The service name of every port and protocol pair known here, ordered by port.
+pub const SERVICES: &[(u16, Protocol, &str)] = &[
+    (FTP_DATA, Protocol::TCP, "FTP-Data"),
+    (FTP, Protocol::TCP, "FTP"),
+    (SSH, Protocol::TCP, "SSH"),
+    (SSH, Protocol::UDP, "SSH"),
+    (SSH, Protocol::SCTP, "SSH"),
+    (TELNET, Protocol::TCP, "Telnet"),
+    (SMTP, Protocol::TCP, "SMTP"),
+    (DNS, Protocol::TCP, "DNS"),
+    (DNS, Protocol::UDP, "DNS"),
+    (DHCP_SERVER, Protocol::UDP, "DHCP-Server"),
+    (DHCP_CLIENT, Protocol::UDP, "DHCP-Client"),
+    (TFTP, Protocol::UDP, "TFTP"),
+    (HTTP, Protocol::TCP, "HTTP"),
+    (HTTP, Protocol::UDP, "HTTP"),
+    (HTTP, Protocol::SCTP, "HTTP"),
+    (KERBEROS, Protocol::TCP, "Kerberos"),
+    (KERBEROS, Protocol::UDP, "Kerberos"),
+    (POP3, Protocol::TCP, "POP3"),
+    (NTP, Protocol::UDP, "NTP"),
+    (NETBIOS_NS, Protocol::UDP, "NetBIOS-NS"),
+    (NETBIOS_DGM, Protocol::UDP, "NetBIOS-DGM"),
+    (NETBIOS_SSN, Protocol::TCP, "NetBIOS-SSN"),
+    (IMAP, Protocol::TCP, "IMAP"),
+    (SNMP, Protocol::UDP, "SNMP"),
+    (SNMP_TRAP, Protocol::UDP, "SNMP-Trap"),
+    (BGP, Protocol::TCP, "BGP"),
+    (LDAP, Protocol::TCP, "LDAP"),
+    (HTTPS, Protocol::TCP, "HTTPS"),
+    (HTTPS, Protocol::UDP, "HTTPS"),
+    (HTTPS, Protocol::SCTP, "HTTPS"),
+    (SMB, Protocol::TCP, "SMB"),
+    (ISAKMP, Protocol::UDP, "ISAKMP"),
+    (MODBUS, Protocol::TCP, "Modbus"),
+    (SYSLOG, Protocol::UDP, "Syslog"),
+    (RIP, Protocol::UDP, "RIP"),
+    (RIPNG, Protocol::UDP, "RIPng"),
+    (DHCPV6_CLIENT, Protocol::UDP, "DHCPv6-Client"),
+    (DHCPV6_SERVER, Protocol::UDP, "DHCPv6-Server"),
+    (SUBMISSION, Protocol::TCP, "Submission"),
+    (LDAPS, Protocol::TCP, "LDAPS"),
+    (IMAPS, Protocol::TCP, "IMAPS"),
+    (POP3S, Protocol::TCP, "POP3S"),
+    (L2TP, Protocol::UDP, "L2TP"),
+    (RADIUS, Protocol::UDP, "RADIUS"),
+    (RADIUS_ACCOUNTING, Protocol::UDP, "RADIUS-Accounting"),
+    (HSRP, Protocol::UDP, "HSRP"),
+    (HSRP_IPV6, Protocol::UDP, "HSRPv6"),
+    (NETFLOW, Protocol::UDP, "NetFlow"),
+    (GTP_U, Protocol::UDP, "GTP-U"),
+    (MYSQL, Protocol::TCP, "MySQL"),
+    (RDP, Protocol::TCP, "RDP"),
+    (TEREDO, Protocol::UDP, "Teredo"),
+    (DIAMETER, Protocol::TCP, "Diameter"),
+    (DIAMETER, Protocol::SCTP, "Diameter"),
+    (IPSEC_NAT_T, Protocol::UDP, "IPsec-NAT-T"),
+    (IPFIX, Protocol::TCP, "IPFIX"),
+    (IPFIX, Protocol::UDP, "IPFIX"),
+    (IPFIX, Protocol::SCTP, "IPFIX"),
+    (VXLAN, Protocol::UDP, "VXLAN"),
+    (SIP, Protocol::TCP, "SIP"),
+    (SIP, Protocol::UDP, "SIP"),
+    (SIP, Protocol::SCTP, "SIP"),
+    (SIPS, Protocol::TCP, "SIPS"),
+    (SIPS, Protocol::SCTP, "SIPS"),
+    (MDNS, Protocol::UDP, "mDNS"),
+    (POSTGRESQL, Protocol::TCP, "PostgreSQL"),
+    (GENEVE, Protocol::UDP, "Geneve"),
+    (SFLOW, Protocol::UDP, "sFlow"),
+    (HTTP_ALT, Protocol::TCP, "HTTP-Alt"),
+    (WIREGUARD, Protocol::UDP, "WireGuard"),
+];
+
+/// Returns the name of the service using `port` over `protocol`, such as "DNS" for 53 over UDP.
+pub fn service(port: u16, protocol: Protocol) -> Option<&'static str> {
+    SERVICES
+        .iter()
+        .find(|&&(p, proto, _)| p == port && proto == protocol)
+        .map(|&(_, _, name)| name)
+}
+
+/// Returns the name of the service of a flow between the two ports. The destination port is
+/// tried first, as the ports of clients are usually ephemeral, then the source port for replies.
+pub fn flow_service(source: u16, destination: u16, protocol: Protocol) -> Option<&'static str> {
+    service(destination, protocol).or_else(|| service(source, protocol))
+}
+
+/// Returns the port of the service called `name` over `protocol`, ignoring case.
+pub fn port(name: &str, protocol: Protocol) -> Option<u16> {
+    SERVICES
+        .iter()
+        .find(|&&(_, proto, n)| proto == protocol && n.eq_ignore_ascii_case(name))
+        .map(|&(port, _, _)| port)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        assert_eq!(Some("DHCP-Server"), service(DHCP_SERVER, Protocol::UDP));
+        assert_eq!(Some("SIP"), service(5060, Protocol::SCTP));
+        assert_eq!(None, service(NTP, Protocol::TCP));
+        assert_eq!(None, service(1, Protocol::TCP));
+        assert_eq!(Some(DNS), port("dns", Protocol::TCP));
+        assert_eq!(None, port("ntp", Protocol::TCP));
+        assert_eq!(Some("DNS"), flow_service(DNS, 40000, Protocol::UDP));
+        assert_eq!(None, flow_service(40000, 40001, Protocol::UDP));
+    }
+
+    #[test]
+    fn table() {
+        for (i, &(port, protocol, name)) in SERVICES.iter().enumerate() {
+            assert!(protocol.is_transport());
+            assert_eq!(Some(port), super::port(name, protocol));
+            if let Some(&(next, next_protocol, _)) = SERVICES.get(i + 1) {
+                assert!(port < next || (port == next && protocol != next_protocol));
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_modules() {
+        use {dhcp, dhcpv6, dns, esp, geneve, gtpu, hsrp, ikev2, l2tp, modbus, netflow, ntp};
+        use {bgp, rip, sflow, teredo, tftp, tls, vxlan, wireguard};
+
+        assert_eq!(dhcp::SERVER_PORT, DHCP_SERVER);
+        assert_eq!(dhcp::CLIENT_PORT, DHCP_CLIENT);
+        assert_eq!(dhcpv6::SERVER_PORT, DHCPV6_SERVER);
+        assert_eq!(dhcpv6::CLIENT_PORT, DHCPV6_CLIENT);
+        assert_eq!(dns::UDP_PORT, DNS);
+        assert_eq!(dns::MDNS_UDP_PORT, MDNS);
+        assert_eq!(esp::NAT_T_UDP_PORT, IPSEC_NAT_T);
+        assert_eq!(geneve::UDP_PORT, GENEVE);
+        assert_eq!(gtpu::UDP_PORT, GTP_U);
+        assert_eq!(hsrp::UDP_PORT, HSRP);
+        assert_eq!(hsrp::IPV6_UDP_PORT, HSRP_IPV6);
+        assert_eq!(ikev2::UDP_PORT, ISAKMP);
+        assert_eq!(l2tp::UDP_PORT, L2TP);
+        assert_eq!(modbus::TCP_PORT, MODBUS);
+        assert_eq!(netflow::NETFLOW_UDP_PORT, NETFLOW);
+        assert_eq!(netflow::IPFIX_PORT, IPFIX);
+        assert_eq!(ntp::UDP_PORT, NTP);
+        assert_eq!(bgp::TCP_PORT, BGP);
+        assert_eq!(rip::UDP_PORT, RIP);
+        assert_eq!(sflow::UDP_PORT, SFLOW);
+        assert_eq!(teredo::UDP_PORT, TEREDO);
+        assert_eq!(tftp::UDP_PORT, TFTP);
+        assert_eq!(tls::HTTPS_PORT, HTTPS);
+        assert_eq!(vxlan::UDP_PORT, VXLAN);
+        assert_eq!(wireguard::DEFAULT_UDP_PORT, WIREGUARD);
+    }
+}
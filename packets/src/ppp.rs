@@ -0,0 +1,162 @@
+//! The Point-to-Point Protocol, RFC 1661.
+
+use std::fmt;
+
+packet!(
+    /// A PPP frame, starting at the protocol field. In PPPoE the frame starts right there, while
+    /// HDLC-like framing, as used by L2TP, puts an address and control field in front of it. See
+    /// `new_hdlc` for that case.
+    PppPacket,
+    MutPppPacket,
+    PppPacketBuf,
+    1,
+    |data| if is_compressed(data) { 1 } else { 2 },
+    init |data| data[0] |= 0x01
+);
+
+getters!(PppPacket
+    /// Returns the protocol of the payload. Handles protocol field compression, where protocols
+    /// below 0x100 are sent as a single byte. If an uncompressed protocol field is truncated to a
+    /// single byte, the missing byte is read as zero.
+    pub fn protocol(&self) -> PppProtocol {
+        if is_compressed(self.0) {
+            PppProtocol(u16::from(self.0[0]))
+        } else {
+            let low = self.0.get(1).cloned().unwrap_or(0);
+            PppProtocol(u16::from_be_bytes([self.0[0], low]))
+        }
+    }
+
+    /// Returns true if the protocol field is compressed to a single byte.
+    pub fn is_protocol_compressed(&self) -> bool {
+        is_compressed(self.0)
+    }
+);
+
+impl<'a> PppPacket<'a> {
+    /// Creates a packet from a frame using HDLC-like framing, skipping the address and control
+    /// fields if present. They may be left out when address and control field compression is
+    /// negotiated. Returns `None` if the rest of the frame is too short.
+    pub fn new_hdlc(data: &'a [u8]) -> Option<PppPacket<'a>> {
+        if data.starts_with(&HDLC_ADDRESS_CONTROL) {
+            PppPacket::new(&data[2..])
+        } else {
+            PppPacket::new(data)
+        }
+    }
+}
+
+setters!(MutPppPacket
+    /// Sets the protocol, always using the uncompressed two byte form. Panics if the backing
+    /// slice is a single byte.
+    pub fn set_protocol(&mut self, protocol: PppProtocol) {
+        write_offset!(self.0[..2], 0, protocol.value(), u16, to_be);
+    }
+);
+
+/// The all-stations address followed by the unnumbered information control field.
+pub const HDLC_ADDRESS_CONTROL: [u8; 2] = [0xff, 0x03];
+
+/// Protocol numbers have an even most significant byte and an odd least significant byte, so an
+/// odd first byte means the most significant byte has been left out.
+fn is_compressed(data: &[u8]) -> bool {
+    data[0] & 0x01 != 0
+}
+
+
+/// The 16 bit protocol field of a PPP frame. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/ppp-numbers/ppp-numbers.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PppProtocol(pub u16);
+
+impl PppProtocol {
+    pub const IPV4: PppProtocol = PppProtocol(0x0021);
+    pub const IPV6: PppProtocol = PppProtocol(0x0057);
+    pub const MPLS: PppProtocol = PppProtocol(0x0281);
+    /// Internet Protocol Control Protocol.
+    pub const IPCP: PppProtocol = PppProtocol(0x8021);
+    /// IPv6 Control Protocol.
+    pub const IPV6CP: PppProtocol = PppProtocol(0x8057);
+    /// Link Control Protocol.
+    pub const LCP: PppProtocol = PppProtocol(0xc021);
+    /// Password Authentication Protocol.
+    pub const PAP: PppProtocol = PppProtocol(0xc023);
+    /// Challenge Handshake Authentication Protocol.
+    pub const CHAP: PppProtocol = PppProtocol(0xc223);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+
+    /// Returns true for network control protocols such as IPCP, meaning values in the range
+    /// 0x8000 to 0xbfff.
+    pub fn is_network_control(&self) -> bool {
+        (0x8000..0xc000).contains(&self.0)
+    }
+}
+
+impl<'a> fmt::Display for PppPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PPP protocol={:#06x}", self.protocol().value())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! ppp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutPppPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    ppp_setget_test!(protocol, set_protocol, PppProtocol::LCP, 0, [0xc0, 0x21]);
+
+    #[test]
+    fn uncompressed_protocol() {
+        let data = [0x00, 0x21, 0x45];
+        let testee = PppPacket::new(&data).unwrap();
+        assert_eq!(PppProtocol::IPV4, testee.protocol());
+        assert!(!testee.is_protocol_compressed());
+        assert_eq!(&[0x45], testee.payload());
+    }
+
+    #[test]
+    fn compressed_protocol() {
+        let data = [0x57, 0x60];
+        let testee = PppPacket::new(&data).unwrap();
+        assert_eq!(PppProtocol::IPV6, testee.protocol());
+        assert!(testee.is_protocol_compressed());
+        assert_eq!(&[0x60], testee.payload());
+        assert_eq!(PppProtocol::IPV6, PppPacket::new(&data[..1]).unwrap().protocol());
+    }
+
+    #[test]
+    fn truncated_protocol() {
+        let testee = PppPacket::new(&[0xc0]).unwrap();
+        assert_eq!(PppProtocol(0xc000), testee.protocol());
+        assert_eq!(1, testee.header_len());
+    }
+
+    #[test]
+    fn hdlc_framing() {
+        let data = [0xff, 0x03, 0x80, 0x21, 0x01];
+        let testee = PppPacket::new_hdlc(&data).unwrap();
+        assert_eq!(PppProtocol::IPCP, testee.protocol());
+        assert!(testee.protocol().is_network_control());
+        assert_eq!(&[0x01], testee.payload());
+
+        let testee = PppPacket::new_hdlc(&data[2..]).unwrap();
+        assert_eq!(PppProtocol::IPCP, testee.protocol());
+        assert!(PppPacket::new_hdlc(&data[..2]).is_none());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        PppPacketBuf => |packet| packet.is_protocol_compressed()
+    );
+}
@@ -0,0 +1,308 @@
+//! PPP over Ethernet, RFC 2516. Discovery packets carry a list of tags, while session packets
+//! carry a PPP frame.
+
+use std::cmp;
+use std::fmt;
+use types::*;
+
+packet!(
+    PppoePacket,
+    MutPppoePacket,
+    PppoePacketBuf,
+    6,
+    |_data| 6,
+    len |data| 6 + usize::from(read_offset!(data, 4, u16, from_be)),
+    set_len |data, len| len - 6 <= usize::from(u16::MAX) && {
+        write_offset!(data, 4, (len - 6) as u16, u16, to_be);
+        true
+    },
+    init |data| {
+        data[0] = 0x11;
+        MutPppoePacket::set_len(data, data.len());
+    }
+);
+
+getters!(PppoePacket
+    pub fn version(&self) -> u4 {
+        read_offset!(self.0, 0, u8) >> 4
+    }
+
+    pub fn pppoe_type(&self) -> u4 {
+        read_offset!(self.0, 0, u8) & 0x0f
+    }
+
+    pub fn code(&self) -> Code {
+        Code(read_offset!(self.0, 1, u8))
+    }
+
+    pub fn session_id(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the length of the PPPoE payload, not including the PPPoE header.
+    pub fn length(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    /// Returns the part of the payload given by the length field, without any Ethernet padding.
+    /// Truncated if the length field is longer than the backing slice.
+    pub fn pppoe_payload(&self) -> &'a [u8] {
+        let end = cmp::min(6 + usize::from(self.length()), self.0.len());
+        &self.0[6..end]
+    }
+
+    /// Returns an iterator over the tags of a discovery packet.
+    pub fn tags(&self) -> PppoeTags<'a> {
+        PppoeTags::new(self.pppoe_payload())
+    }
+);
+
+setters!(MutPppoePacket
+    pub fn set_version(&mut self, version: u4) {
+        let new_byte = (version << 4) | (read_offset!(self.0, 0, u8) & 0x0f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_pppoe_type(&mut self, pppoe_type: u4) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xf0) | (pppoe_type & 0x0f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_code(&mut self, code: Code) {
+        write_offset!(self.0, 1, code.value(), u8);
+    }
+
+    pub fn set_session_id(&mut self, session_id: u16) {
+        write_offset!(self.0, 2, session_id, u16, to_be);
+    }
+
+    pub fn set_length(&mut self, length: u16) {
+        write_offset!(self.0, 4, length, u16, to_be);
+    }
+);
+
+impl<'a> MutPppoePacket<'a> {
+    /// Encodes `tags` after the header and updates the length field to match. Returns the
+    /// length of the encoded tags, or `None` if they do not fit in the backing slice.
+    pub fn set_tags(&mut self, tags: &[PppoeTag]) -> Option<usize> {
+        let len = write_tags(&mut self.0[6..], tags)?;
+        if len > usize::from(u16::MAX) {
+            return None;
+        }
+        self.set_length(len as u16);
+        Some(len)
+    }
+}
+
+
+/// The 8 bit code field of a PPPoE header, giving the discovery stage or session data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Code(pub u8);
+
+impl Code {
+    pub const SESSION_DATA: Code = Code(0x00);
+    /// PPPoE Active Discovery Offer.
+    pub const PADO: Code = Code(0x07);
+    /// PPPoE Active Discovery Initiation.
+    pub const PADI: Code = Code(0x09);
+    /// PPPoE Active Discovery Request.
+    pub const PADR: Code = Code(0x19);
+    /// PPPoE Active Discovery Session-confirmation.
+    pub const PADS: Code = Code(0x65);
+    /// PPPoE Active Discovery Terminate.
+    pub const PADT: Code = Code(0xa7);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The 16 bit type of a PPPoE discovery tag.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TagType(pub u16);
+
+impl TagType {
+    pub const END_OF_LIST: TagType = TagType(0x0000);
+    pub const SERVICE_NAME: TagType = TagType(0x0101);
+    pub const AC_NAME: TagType = TagType(0x0102);
+    pub const HOST_UNIQ: TagType = TagType(0x0103);
+    pub const AC_COOKIE: TagType = TagType(0x0104);
+    pub const VENDOR_SPECIFIC: TagType = TagType(0x0105);
+    pub const RELAY_SESSION_ID: TagType = TagType(0x0110);
+    pub const SERVICE_NAME_ERROR: TagType = TagType(0x0201);
+    pub const AC_SYSTEM_ERROR: TagType = TagType(0x0202);
+    pub const GENERIC_ERROR: TagType = TagType(0x0203);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// A single tag in a PPPoE discovery packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PppoeTag<'a> {
+    pub tag_type: TagType,
+    pub value: &'a [u8],
+}
+
+impl<'a> PppoeTag<'a> {
+    pub fn new(tag_type: TagType, value: &'a [u8]) -> PppoeTag<'a> {
+        PppoeTag { tag_type, value }
+    }
+
+    /// Returns the number of bytes this tag occupies when encoded.
+    pub fn encoded_len(&self) -> usize {
+        4 + self.value.len()
+    }
+
+    /// Encodes this tag into the beginning of `buffer`. Returns the number of bytes written, or
+    /// `None` if the tag does not fit in `buffer` or its value is longer than 65535 bytes.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len();
+        if buffer.len() < len || self.value.len() > usize::from(u16::MAX) {
+            return None;
+        }
+        write_offset!(buffer, 0, self.tag_type.value(), u16, to_be);
+        write_offset!(buffer, 2, self.value.len() as u16, u16, to_be);
+        buffer[4..len].copy_from_slice(self.value);
+        Some(len)
+    }
+}
+
+/// Encodes `tags` one after another into the beginning of `buffer`. Returns the number of bytes
+/// written, or `None` if the tags do not fit in `buffer`.
+pub fn write_tags(buffer: &mut [u8], tags: &[PppoeTag]) -> Option<usize> {
+    let mut offset = 0;
+    for tag in tags {
+        offset += tag.write(&mut buffer[offset..])?;
+    }
+    Some(offset)
+}
+
+/// Iterator over the tags in a PPPoE discovery packet. Iteration stops after an end of list tag,
+/// or at the first tag that does not fit in the data.
+#[derive(Clone)]
+pub struct PppoeTags<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(PppoeTags<'a>);
+
+impl<'a> PppoeTags<'a> {
+    /// Creates an iterator over the tags encoded in `data`.
+    pub fn new(data: &'a [u8]) -> PppoeTags<'a> {
+        PppoeTags { data }
+    }
+}
+
+impl<'a> Iterator for PppoeTags<'a> {
+    type Item = PppoeTag<'a>;
+
+    fn next(&mut self) -> Option<PppoeTag<'a>> {
+        if self.data.len() < 4 {
+            self.data = &[];
+            return None;
+        }
+        let tag_type = TagType(read_offset!(self.data, 0, u16, from_be));
+        let len = 4 + usize::from(read_offset!(self.data, 2, u16, from_be));
+        if len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let value = &self.data[4..len];
+        self.data = if tag_type == TagType::END_OF_LIST { &[] } else { &self.data[len..] };
+        Some(PppoeTag::new(tag_type, value))
+    }
+}
+
+impl<'a> fmt::Display for PppoePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PPPoE code={:#04x} session={:#06x} len={}",
+            self.code().value(),
+            self.session_id(),
+            self.length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! pppoe_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutPppoePacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    pppoe_setget_test!(version, set_version, 0xf, 0, [0xf0]);
+    pppoe_setget_test!(pppoe_type, set_pppoe_type, 0xf, 0, [0x0f]);
+    pppoe_setget_test!(code, set_code, Code::PADT, 1, [0xa7]);
+    pppoe_setget_test!(session_id, set_session_id, 0xabcd, 2, [0xab, 0xcd]);
+    pppoe_setget_test!(length, set_length, 0xabcd, 4, [0xab, 0xcd]);
+
+    #[test]
+    fn padi_tags() {
+        let data = [
+            0x11, 0x09, 0x00, 0x00, 0x00, 0x0c, // PADI, 12 bytes of tags
+            0x01, 0x01, 0x00, 0x00, // Empty Service-Name
+            0x01, 0x03, 0x00, 0x04, 0xde, 0xad, 0xbe, 0xef, // Host-Uniq
+            0x00, 0x00, // Ethernet padding
+        ];
+        let testee = PppoePacket::new(&data).unwrap();
+        assert_eq!(1, testee.version());
+        assert_eq!(1, testee.pppoe_type());
+        assert_eq!(Code::PADI, testee.code());
+        assert_eq!(12, testee.pppoe_payload().len());
+        assert_eq!(
+            vec![
+                PppoeTag::new(TagType::SERVICE_NAME, &[]),
+                PppoeTag::new(TagType::HOST_UNIQ, &[0xde, 0xad, 0xbe, 0xef]),
+            ],
+            testee.tags().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn stop_at_end_of_list_and_truncation() {
+        let data = [0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00];
+        assert_eq!(1, PppoeTags::new(&data).count());
+        let data = [0x01, 0x01, 0x00, 0x05, 0x00];
+        assert_eq!(0, PppoeTags::new(&data).count());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut testee = PppoePacketBuf::new_with_payload_len(0);
+        assert!(testee.set_payload(&[0xc0, 0x21, 1, 2]));
+        assert_eq!(4, testee.packet().length());
+        assert_eq!(&[0xc0, 0x21, 1, 2], testee.packet().payload());
+    }
+
+    #[test]
+    fn set_tags() {
+        let mut data = [0; 6 + 12];
+        let tags = [
+            PppoeTag::new(TagType::AC_NAME, b"ac"),
+            PppoeTag::new(TagType::AC_COOKIE, &[1, 2]),
+        ];
+        {
+            let mut testee = MutPppoePacket::new(&mut data).unwrap();
+            assert_eq!(Some(12), testee.set_tags(&tags));
+            assert_eq!(None, testee.set_tags(&[PppoeTag::new(TagType::AC_NAME, &[0; 9])]));
+        }
+        let testee = PppoePacket::new(&data).unwrap();
+        assert_eq!(12, testee.length());
+        assert_eq!(tags.to_vec(), testee.tags().collect::<Vec<_>>());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        PppoePacketBuf => |packet| packet.version() == 1 && packet.pppoe_type() == 1
+    );
+}
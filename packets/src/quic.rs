@@ -0,0 +1,238 @@
+//! The version independent properties of QUIC, RFC 8999. These are enough to tell long headers
+//! from short headers, read the version and extract connection IDs, without knowing anything
+//! about the QUIC version in use. Everything else, including most of the first byte, is version
+//! specific and in QUIC version 1 protected by header protection.
+
+use std::fmt;
+use std::ops::Range;
+
+packet!(
+    /// A QUIC packet, with either a long or a short header. For long headers the header covers
+    /// the invariant fields up to and including the source connection ID. The length of the
+    /// destination connection ID of short headers is not encoded in the packet, so for those
+    /// the header is only the first byte.
+    QuicPacket,
+    MutQuicPacket,
+    QuicPacketBuf,
+    1,
+    |data| long_header_len(data).unwrap_or(1),
+    // A short header, with the fixed bit set.
+    init |data| data[0] = (data[0] & 0x7f) | 0x40
+);
+
+getters!(QuicPacket
+    /// Returns true for long headers, used during connection establishment, and false for
+    /// short headers.
+    pub fn is_long_header(&self) -> bool {
+        read_offset!(self.0, 0, u8) & 0x80 != 0
+    }
+
+    /// Returns the version of a long header packet, or `None` for short headers or if the
+    /// packet is too short.
+    pub fn version(&self) -> Option<Version> {
+        let version = self.long_header_field(1..5)?;
+        Some(Version(read_offset!(version, 0, u32, from_be)))
+    }
+
+    /// Returns the length of the destination connection ID of a long header packet, or `None`
+    /// for short headers or if the packet is too short.
+    pub fn dcid_len(&self) -> Option<u8> {
+        self.long_header_field(5..6).map(|len| len[0])
+    }
+
+    /// Returns the destination connection ID of a long header packet, or `None` for short
+    /// headers or if the packet is too short. See `short_header_dcid` for short headers.
+    pub fn dcid(&self) -> Option<&'a [u8]> {
+        let len = usize::from(self.dcid_len()?);
+        self.long_header_field(6..6 + len)
+    }
+
+    /// Returns the length of the source connection ID of a long header packet, or `None` for
+    /// short headers or if the packet is too short.
+    pub fn scid_len(&self) -> Option<u8> {
+        let offset = 6 + usize::from(self.dcid_len()?);
+        self.long_header_field(offset..offset + 1).map(|len| len[0])
+    }
+
+    /// Returns the source connection ID of a long header packet, or `None` for short headers or
+    /// if the packet is too short.
+    pub fn scid(&self) -> Option<&'a [u8]> {
+        let offset = 7 + usize::from(self.dcid_len()?);
+        let len = usize::from(self.scid_len()?);
+        self.long_header_field(offset..offset + len)
+    }
+
+    /// Returns an iterator over the versions supported by the server, or `None` if this is not
+    /// a version negotiation packet or the packet is too short.
+    pub fn supported_versions(&self) -> Option<SupportedVersions<'a>> {
+        if self.version()? != Version::NEGOTIATION {
+            return None;
+        }
+        let offset = long_header_len(self.0)?;
+        Some(SupportedVersions { data: self.0.get(offset..)? })
+    }
+);
+
+impl<'a> QuicPacket<'a> {
+    /// Returns the destination connection ID of a short header packet, given its length, or
+    /// `None` for long headers or if the packet is too short. Endpoints know the length of the
+    /// connection IDs they issued, while middleboxes must learn it from the long header packets
+    /// of the connection.
+    pub fn short_header_dcid(&self, len: usize) -> Option<&'a [u8]> {
+        if self.is_long_header() {
+            None
+        } else {
+            self.0.get(1..1 + len)
+        }
+    }
+
+    /// Returns the destination connection ID given the length to use for short headers,
+    /// whichever header type the packet has.
+    pub fn dcid_with_short_len(&self, short_len: usize) -> Option<&'a [u8]> {
+        if self.is_long_header() {
+            self.dcid()
+        } else {
+            self.short_header_dcid(short_len)
+        }
+    }
+
+    fn long_header_field(&self, range: Range<usize>) -> Option<&'a [u8]> {
+        if self.is_long_header() {
+            self.0.get(range)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the length of the invariant part of a long header, or `None` if `data` does not
+/// start with a long header or is too short to tell. The length may exceed that of `data`.
+fn long_header_len(data: &[u8]) -> Option<usize> {
+    if data[0] & 0x80 == 0 {
+        return None;
+    }
+    let scid_len_offset = 6 + usize::from(*data.get(5)?);
+    Some(scid_len_offset + 1 + usize::from(*data.get(scid_len_offset)?))
+}
+
+
+/// A QUIC version.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Version(pub u32);
+
+impl Version {
+    /// Used by version negotiation packets, listing the versions a server supports.
+    pub const NEGOTIATION: Version = Version(0);
+    /// QUIC version 1, RFC 9000.
+    pub const V1: Version = Version(1);
+    /// QUIC version 2, RFC 9369.
+    pub const V2: Version = Version(0x6b33_43cf);
+
+    #[inline]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true for the versions of the form 0x?a?a?a?a reserved to exercise version
+    /// negotiation.
+    pub fn is_reserved(&self) -> bool {
+        self.0 & 0x0f0f_0f0f == 0x0a0a_0a0a
+    }
+}
+
+/// Iterator over the versions listed in a version negotiation packet.
+#[derive(Clone)]
+pub struct SupportedVersions<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(SupportedVersions<'a>);
+
+impl<'a> Iterator for SupportedVersions<'a> {
+    type Item = Version;
+
+    fn next(&mut self) -> Option<Version> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (version, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(Version(read_offset!(version, 0, u32, from_be)))
+    }
+}
+
+impl<'a> fmt::Display for QuicPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.version() {
+            Some(version) => write!(f, "QUIC long version={:#010x}", version.value()),
+            None => write!(f, "QUIC short"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static INITIAL: [u8; 20] = [
+        0xc3, 0, 0, 0, 1, // Long header, version 1
+        8, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08, // Destination connection ID
+        2, 0xab, 0xcd, // Source connection ID
+        0x00, 0x41, 0x03, // Token length and length, version specific
+    ];
+
+    #[test]
+    fn long_header() {
+        let testee = QuicPacket::new(&INITIAL).unwrap();
+        assert!(testee.is_long_header());
+        assert_eq!(Some(Version::V1), testee.version());
+        assert_eq!(Some(8), testee.dcid_len());
+        assert_eq!(Some(&INITIAL[6..14]), testee.dcid());
+        assert_eq!(Some(2), testee.scid_len());
+        assert_eq!(Some(&[0xab, 0xcd][..]), testee.scid());
+        assert_eq!(17, testee.header_len());
+        assert!(testee.supported_versions().is_none());
+        assert_eq!(None, testee.short_header_dcid(8));
+        assert_eq!(testee.dcid(), testee.dcid_with_short_len(4));
+        assert_eq!("QUIC long version=0x00000001", testee.to_string());
+    }
+
+    #[test]
+    fn truncated_long_header() {
+        let testee = QuicPacket::new(&INITIAL[..15]).unwrap();
+        assert_eq!(Some(&INITIAL[6..14]), testee.dcid());
+        assert_eq!(Some(2), testee.scid_len());
+        assert_eq!(None, testee.scid());
+        assert_eq!(15, testee.header_len());
+        assert_eq!(None, QuicPacket::new(&INITIAL[..3]).unwrap().version());
+    }
+
+    #[test]
+    fn short_header() {
+        let data = [0x41, 1, 2, 3, 4, 0xff];
+        let testee = QuicPacket::new(&data).unwrap();
+        assert!(!testee.is_long_header());
+        assert_eq!(None, testee.version());
+        assert_eq!(None, testee.dcid());
+        assert_eq!(1, testee.header_len());
+        assert_eq!(Some(&[1, 2, 3, 4][..]), testee.short_header_dcid(4));
+        assert_eq!(None, testee.short_header_dcid(8));
+        assert_eq!("QUIC short", testee.to_string());
+    }
+
+    #[test]
+    fn version_negotiation() {
+        let data = [0x80, 0, 0, 0, 0, 1, 0xaa, 1, 0xbb, 0, 0, 0, 1, 0x1a, 0x2a, 0x3a, 0x4a];
+        let testee = QuicPacket::new(&data).unwrap();
+        let versions = testee.supported_versions().unwrap().collect::<Vec<_>>();
+        assert_eq!(vec![Version::V1, Version(0x1a2a_3a4a)], versions);
+        assert!(versions[1].is_reserved());
+        assert!(!Version::V2.is_reserved());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        QuicPacketBuf => |packet| !packet.is_long_header()
+    );
+}
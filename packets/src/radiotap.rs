@@ -0,0 +1,481 @@
+//! The Radiotap header, prepended to 802.11 frames by capturing drivers, see radiotap.org.
+//!
+//! Unlike most protocols Radiotap is little endian. The header holds one or more present
+//! bitmaps followed by the fields they announce, in bit order, each aligned to its natural
+//! alignment relative to the start of the header. Fields whose size is unknown can't be
+//! skipped, so `Fields` stops at the first unknown field. Vendor namespaces carry their own
+//! length and are always skipped.
+
+use std::fmt;
+
+#[packet(
+    header_len = |data| usize::from(u16::from_le(read_offset!(data, 2, u16))),
+    // Version zero and no fields present.
+    init = |data| write_offset!(data, 0, [0, 0, 8, 0, 0, 0, 0, 0], [u8; 8])
+)]
+/// A Radiotap header. The payload is the captured 802.11 frame.
+pub struct Radiotap {
+    /// Returns the version of the header, always zero.
+    version: u8,
+    _pad: u8,
+    /// Returns the length of the whole Radiotap header, including the fields.
+    #[little_endian]
+    length: u16,
+    /// Returns the first present bitmap. Further bitmaps follow while bit 31 is set.
+    #[little_endian]
+    present: u32,
+}
+
+getters!(RadiotapPacket
+    /// Returns an iterator over the fields in the header.
+    pub fn fields(&self) -> Fields<'a> {
+        Fields::new(&self.0[..self.header_len()])
+    }
+
+    /// Returns the value of the first TSFT field, the time the frame was received, in
+    /// microseconds.
+    pub fn tsft(&self) -> Option<u64> {
+        self.fields().filter_map(|field| field.tsft()).next()
+    }
+
+    /// Returns the flags of the frame, from the first flags field.
+    pub fn flags(&self) -> Option<Flags> {
+        self.fields().filter_map(|field| field.flags()).next()
+    }
+
+    /// Returns the first TX/RX data rate, in units of 500 kbps.
+    pub fn rate(&self) -> Option<u8> {
+        self.fields().filter_map(|field| field.rate()).next()
+    }
+
+    /// Returns the first channel field.
+    pub fn channel(&self) -> Option<Channel> {
+        self.fields().filter_map(|field| field.channel()).next()
+    }
+
+    /// Returns the first antenna signal field, the combined signal power in dBm when the
+    /// header holds more than one.
+    pub fn antenna_signal(&self) -> Option<i8> {
+        self.fields().filter_map(|field| field.antenna_signal()).next()
+    }
+);
+
+
+/// Represents the type of a Radiotap field, its bit number in the present bitmap.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FieldType(pub u8);
+
+impl FieldType {
+    pub const TSFT: FieldType = FieldType(0);
+    pub const FLAGS: FieldType = FieldType(1);
+    pub const RATE: FieldType = FieldType(2);
+    pub const CHANNEL: FieldType = FieldType(3);
+    pub const FHSS: FieldType = FieldType(4);
+    pub const ANTENNA_SIGNAL: FieldType = FieldType(5);
+    pub const ANTENNA_NOISE: FieldType = FieldType(6);
+    pub const LOCK_QUALITY: FieldType = FieldType(7);
+    pub const TX_ATTENUATION: FieldType = FieldType(8);
+    pub const DB_TX_ATTENUATION: FieldType = FieldType(9);
+    pub const DBM_TX_POWER: FieldType = FieldType(10);
+    pub const ANTENNA: FieldType = FieldType(11);
+    pub const DB_ANTENNA_SIGNAL: FieldType = FieldType(12);
+    pub const DB_ANTENNA_NOISE: FieldType = FieldType(13);
+    pub const RX_FLAGS: FieldType = FieldType(14);
+    pub const TX_FLAGS: FieldType = FieldType(15);
+    pub const RTS_RETRIES: FieldType = FieldType(16);
+    pub const DATA_RETRIES: FieldType = FieldType(17);
+    pub const XCHANNEL: FieldType = FieldType(18);
+    pub const MCS: FieldType = FieldType(19);
+    pub const AMPDU_STATUS: FieldType = FieldType(20);
+    pub const VHT: FieldType = FieldType(21);
+    pub const TIMESTAMP: FieldType = FieldType(22);
+    pub const HE: FieldType = FieldType(23);
+    pub const HE_MU: FieldType = FieldType(24);
+    pub const HE_MU_OTHER_USER: FieldType = FieldType(25);
+    pub const ZERO_LENGTH_PSDU: FieldType = FieldType(26);
+    pub const L_SIG: FieldType = FieldType(27);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns the alignment and size of fields of this type, or `None` if unknown.
+    fn layout(&self) -> Option<(usize, usize)> {
+        Some(match self.0 {
+            0 => (8, 8),
+            1 | 2 | 5 | 6 | 10..=13 | 16 | 17 | 26 => (1, 1),
+            3 | 27 => (2, 4),
+            4 | 7..=9 | 14 | 15 => (2, 2),
+            18 | 20 => (4, 8),
+            19 => (1, 3),
+            21 | 23 | 24 => (2, 12),
+            22 => (8, 12),
+            25 => (2, 6),
+            _ => return None,
+        })
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags field.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "Sent/Received during CFP" position.
+        const CFP = 0x01;
+        /// A bitmask with a one in the "Short preamble" position.
+        const SHORT_PREAMBLE = 0x02;
+        /// A bitmask with a one in the "WEP" position, set for encrypted frames.
+        const WEP = 0x04;
+        /// A bitmask with a one in the "Fragmentation" position.
+        const FRAGMENTATION = 0x08;
+        /// A bitmask with a one in the "FCS at end" position, set when the frame includes
+        /// its four byte frame check sequence.
+        const FCS = 0x10;
+        /// A bitmask with a one in the "Data pad" position, set when the 802.11 header is
+        /// padded to a multiple of four bytes.
+        const DATA_PAD = 0x20;
+        /// A bitmask with a one in the "Bad FCS" position.
+        const BAD_FCS = 0x40;
+        /// A bitmask with a one in the "Short guard interval" position.
+        const SHORT_GI = 0x80;
+    }
+}
+
+bitflags! {
+    /// Bitmasks for the flags of the channel field.
+    pub struct ChannelFlags: u16 {
+        /// A bitmask with a one in the "Turbo" position.
+        const TURBO = 0x0010;
+        /// A bitmask with a one in the "CCK" position.
+        const CCK = 0x0020;
+        /// A bitmask with a one in the "OFDM" position.
+        const OFDM = 0x0040;
+        /// A bitmask with a one in the "2 GHz spectrum" position.
+        const SPECTRUM_2GHZ = 0x0080;
+        /// A bitmask with a one in the "5 GHz spectrum" position.
+        const SPECTRUM_5GHZ = 0x0100;
+        /// A bitmask with a one in the "Passive" position.
+        const PASSIVE = 0x0200;
+        /// A bitmask with a one in the "Dynamic CCK-OFDM" position.
+        const DYNAMIC = 0x0400;
+        /// A bitmask with a one in the "GFSK" position.
+        const GFSK = 0x0800;
+    }
+}
+
+/// The value of a channel field.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Channel {
+    /// The center frequency in MHz.
+    pub frequency: u16,
+    pub flags: ChannelFlags,
+}
+
+
+/// A field in a Radiotap header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Field<'a> {
+    pub field_type: FieldType,
+    /// The field value, exactly as long as the fields of this type.
+    pub data: &'a [u8],
+}
+
+impl<'a> Field<'a> {
+    /// Returns the value of a TSFT field, or `None` if this is another field.
+    pub fn tsft(&self) -> Option<u64> {
+        self.as_type(FieldType::TSFT).map(|data| u64::from_le(read_offset!(data, 0, u64)))
+    }
+
+    /// Returns the value of a flags field, or `None` if this is another field.
+    pub fn flags(&self) -> Option<Flags> {
+        self.as_type(FieldType::FLAGS).map(|data| Flags::from_bits_truncate(data[0]))
+    }
+
+    /// Returns the value of a rate field in units of 500 kbps, or `None` if this is another
+    /// field.
+    pub fn rate(&self) -> Option<u8> {
+        self.as_type(FieldType::RATE).map(|data| data[0])
+    }
+
+    /// Returns the value of a channel field, or `None` if this is another field.
+    pub fn channel(&self) -> Option<Channel> {
+        self.as_type(FieldType::CHANNEL).map(|data| Channel {
+            frequency: u16::from_le(read_offset!(data, 0, u16)),
+            flags: ChannelFlags::from_bits_truncate(u16::from_le(read_offset!(data, 2, u16))),
+        })
+    }
+
+    /// Returns the value of an antenna signal field in dBm, or `None` if this is another
+    /// field.
+    pub fn antenna_signal(&self) -> Option<i8> {
+        self.as_type(FieldType::ANTENNA_SIGNAL).map(|data| data[0] as i8)
+    }
+
+    /// Returns the value of an antenna noise field in dBm, or `None` if this is another field.
+    pub fn antenna_noise(&self) -> Option<i8> {
+        self.as_type(FieldType::ANTENNA_NOISE).map(|data| data[0] as i8)
+    }
+
+    /// Returns the index of the antenna of an antenna field, or `None` if this is another
+    /// field. Applies to the fields announced in the same namespace.
+    pub fn antenna(&self) -> Option<u8> {
+        self.as_type(FieldType::ANTENNA).map(|data| data[0])
+    }
+
+    fn as_type(&self, field_type: FieldType) -> Option<&'a [u8]> {
+        if self.field_type == field_type {
+            Some(self.data)
+        } else {
+            None
+        }
+    }
+}
+
+/// The bits of a present bitmap that announce fields.
+const FIELD_BITS: u32 = 0x1fff_ffff;
+/// The bit switching the following bitmap to the default Radiotap namespace.
+const RADIOTAP_NAMESPACE: u32 = 1 << 29;
+/// The bit switching the following bitmap to a vendor namespace.
+const VENDOR_NAMESPACE: u32 = 1 << 30;
+/// The bit telling that another bitmap follows.
+const EXT: u32 = 1 << 31;
+
+/// Iterator over the fields of a Radiotap header. Iteration stops at the first field of
+/// unknown type or that does not fit in the header.
+#[derive(Clone)]
+pub struct Fields<'a> {
+    /// The whole header, as alignment is relative to its start.
+    data: &'a [u8],
+    /// The present bitmaps after the current one.
+    bitmaps: &'a [u8],
+    /// The current present bitmap, with the bits of the fields already returned cleared.
+    present: u32,
+    /// Set when the current bitmap extends the previous one, announcing fields of types
+    /// above 31.
+    extended: bool,
+    /// Set when the current bitmap belongs to a vendor namespace.
+    vendor: bool,
+    offset: usize,
+}
+
+debug_items!(Fields<'a>);
+
+impl<'a> Fields<'a> {
+    /// Creates an iterator over the fields of the Radiotap header `data`. The slice must end
+    /// where the header does.
+    pub fn new(data: &'a [u8]) -> Fields<'a> {
+        let mut end = 4;
+        loop {
+            match data.get(end..end + 4) {
+                Some(bitmap) => {
+                    end += 4;
+                    if u32::from_le(read_offset!(bitmap, 0, u32)) & EXT == 0 {
+                        break;
+                    }
+                }
+                None => return Fields::empty(),
+            }
+        }
+        Fields {
+            data,
+            bitmaps: &data[8..end],
+            present: u32::from_le(read_offset!(data, 4, u32)),
+            extended: false,
+            vendor: false,
+            offset: end,
+        }
+    }
+
+    fn empty() -> Fields<'a> {
+        Fields {
+            data: &[],
+            bitmaps: &[],
+            present: 0,
+            extended: false,
+            vendor: false,
+            offset: 0,
+        }
+    }
+
+    /// Moves on to the next present bitmap. Returns false if there is none.
+    fn next_bitmap(&mut self) -> bool {
+        if self.bitmaps.len() < 4 {
+            return false;
+        }
+        let present = self.present;
+        self.present = u32::from_le(read_offset!(self.bitmaps, 0, u32));
+        self.bitmaps = &self.bitmaps[4..];
+        if present & VENDOR_NAMESPACE != 0 {
+            // The vendor namespace header tells how many bytes of fields to skip
+            self.offset = align(self.offset, 2);
+            let skip_len = match self.data.get(self.offset..self.offset + 6) {
+                Some(header) => usize::from(u16::from_le(read_offset!(header, 4, u16))),
+                None => return false,
+            };
+            self.offset += 6 + skip_len;
+            self.vendor = true;
+            self.extended = false;
+        } else if present & RADIOTAP_NAMESPACE != 0 {
+            self.vendor = false;
+            self.extended = false;
+        } else {
+            self.extended = true;
+        }
+        true
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Field<'a>;
+
+    fn next(&mut self) -> Option<Field<'a>> {
+        while self.vendor || self.present & FIELD_BITS == 0 {
+            if !self.next_bitmap() {
+                *self = Fields::empty();
+                return None;
+            }
+        }
+        let bit = self.present.trailing_zeros();
+        self.present &= !(1 << bit);
+        let field_type = FieldType(bit as u8);
+        let layout = if self.extended { None } else { field_type.layout() };
+        let (alignment, size) = match layout {
+            Some(layout) => layout,
+            None => {
+                *self = Fields::empty();
+                return None;
+            }
+        };
+        let offset = align(self.offset, alignment);
+        if offset + size > self.data.len() {
+            *self = Fields::empty();
+            return None;
+        }
+        self.offset = offset + size;
+        Some(Field {
+            field_type,
+            data: &self.data[offset..offset + size],
+        })
+    }
+}
+
+/// Rounds `offset` up to a multiple of `alignment`.
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+impl<'a> fmt::Display for RadiotapPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Radiotap len={} present={:#010x}",
+            self.length(),
+            self.present()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! radiotap_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutRadiotapPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    radiotap_setget_test!(version, set_version, 1, 0, [1]);
+    radiotap_setget_test!(length, set_length, 0xabcd, 2, [0xcd, 0xab]);
+    radiotap_setget_test!(present, set_present, 0x01020304, 4, [4, 3, 2, 1]);
+
+    #[test]
+    fn common_fields() {
+        let data = [
+            0, 0, 23, 0, 0x2f, 0, 0, 0,
+            1, 2, 3, 4, 5, 6, 7, 8,
+            0x10, 0x6c, 0x85, 0x09, 0xc0, 0x00, 0xd6,
+            0x80, 0x00,
+        ];
+        let testee = RadiotapPacket::new(&data).unwrap();
+        assert_eq!(23, testee.header_len());
+        assert_eq!(&[0x80, 0x00], testee.payload());
+        assert_eq!(Some(0x0807060504030201), testee.tsft());
+        assert_eq!(Some(Flags::FCS), testee.flags());
+        assert_eq!(Some(0x6c), testee.rate());
+        assert_eq!(
+            Some(Channel {
+                frequency: 2437,
+                flags: ChannelFlags::SPECTRUM_2GHZ | ChannelFlags::OFDM,
+            }),
+            testee.channel()
+        );
+        assert_eq!(Some(-42), testee.antenna_signal());
+        assert_eq!(5, testee.fields().count());
+    }
+
+    #[test]
+    fn alignment() {
+        // Flags, then a channel aligned to two bytes
+        let data = [0, 0, 14, 0, 0x0a, 0, 0, 0, 0x02, 0, 0x6c, 0x09, 0x40, 0];
+        let testee = RadiotapPacket::new(&data).unwrap();
+        assert_eq!(Some(Flags::SHORT_PREAMBLE), testee.flags());
+        assert_eq!(2412, testee.channel().unwrap().frequency);
+    }
+
+    #[test]
+    fn per_antenna_namespaces() {
+        let data = [
+            0, 0, 24, 0,
+            0x20, 0, 0, 0xa0,
+            0x20, 0x08, 0, 0xa0,
+            0x20, 0x08, 0, 0,
+            0xd0, 0xd4, 0, 0xd8, 1, 0, 0, 0,
+        ];
+        let testee = RadiotapPacket::new(&data).unwrap();
+        let fields = testee.fields().collect::<Vec<_>>();
+        assert_eq!(5, fields.len());
+        assert_eq!(Some(-48), testee.antenna_signal());
+        assert_eq!(Some(-44), fields[1].antenna_signal());
+        assert_eq!(Some(0), fields[2].antenna());
+        assert_eq!(Some(-40), fields[3].antenna_signal());
+        assert_eq!(Some(1), fields[4].antenna());
+    }
+
+    #[test]
+    fn vendor_namespace_skipped() {
+        let mut data = [
+            0, 0, 26, 0,
+            0x02, 0, 0, 0xc0,
+            0x01, 0, 0, 0xa0,
+            0x04, 0, 0, 0,
+            0x10, 0, 0x00, 0x11, 0x22, 1, 2, 0, 0xab, 0xcd, 0x6c,
+        ];
+        let testee = RadiotapPacket::new(&data[..26]).unwrap();
+        let fields = testee.fields().collect::<Vec<_>>();
+        assert_eq!(1, fields.len());
+        assert_eq!(Some(Flags::FCS), fields[0].flags());
+        data[2] = 27;
+        assert_eq!(Some(0x6c), RadiotapPacket::new(&data).unwrap().rate());
+    }
+
+    #[test]
+    fn unknown_or_truncated_fields() {
+        // Bit 28 is unknown, so its size and any fields after it are too
+        let data = [0, 0, 9, 0, 0x02, 0, 0, 0x10, 0x10];
+        let testee = RadiotapPacket::new(&data).unwrap();
+        assert_eq!(Some(Flags::FCS), testee.flags());
+        assert_eq!(1, testee.fields().count());
+        let data = [0, 0, 12, 0, 0x01, 0, 0, 0, 1, 2, 3, 4];
+        assert_eq!(None, RadiotapPacket::new(&data).unwrap().tsft());
+        // A missing bitmap
+        let data = [0, 0, 8, 0, 0x02, 0, 0, 0x80];
+        assert_eq!(0, RadiotapPacket::new(&data).unwrap().fields().count());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        RadiotapPacketBuf => |packet| packet.version() == 0 && packet.present() == 0
+    );
+}
@@ -0,0 +1,448 @@
+//! The Routing Information Protocol, versions 1 and 2, RFC 1058 and RFC 2453.
+//!
+//! A RIP message is a four byte header followed by up to 25 route entries of 20 bytes each.
+//! In version 2 the first entry may instead carry authentication, which is told apart from a
+//! route by its address family of `AddressFamily::AUTHENTICATION`. Keyed MD5 authentication,
+//! RFC 4822, also appends a trailer entry holding the digest.
+
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// The UDP port RIP routers send from and listen on.
+pub const UDP_PORT: u16 = 520;
+
+/// The multicast group RIP version 2 messages are sent to.
+pub const MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 9);
+
+/// The metric marking a destination as unreachable.
+pub const INFINITY: u32 = 16;
+
+packet!(
+    /// A RIP message. The payload is the list of entries, see `entries` and `authentication`.
+    RipPacket,
+    MutRipPacket,
+    RipPacketBuf,
+    4,
+    |_data| 4,
+    init |data| write_offset!(data, 0, [Command::RESPONSE.value(), 2, 0, 0], [u8; 4])
+);
+
+getters!(RipPacket
+    pub fn command(&self) -> Command {
+        Command(read_offset!(self.0, 0, u8))
+    }
+
+    pub fn version(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns an iterator over the route entries, skipping authentication entries.
+    pub fn entries(&self) -> RouteEntries<'a> {
+        RouteEntries { data: &self.0[4..] }
+    }
+
+    /// Returns the authentication entry, or `None` if the message is not authenticated. Only
+    /// the first entry of a version 2 message can be an authentication entry.
+    pub fn authentication(&self) -> Option<RouteEntryPacket<'a>> {
+        let entry = RouteEntryPacket::new(&self.0[4..])?;
+        if self.version() >= 2 && entry.is_authentication() {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if this is a request for the whole routing table, a request with a single
+    /// entry of address family zero and an infinite metric.
+    pub fn is_whole_table_request(&self) -> bool {
+        let mut entries = self.entries();
+        match (entries.next(), entries.next()) {
+            (Some(entry), None) => {
+                self.command() == Command::REQUEST
+                    && entry.address_family() == AddressFamily(0)
+                    && entry.metric() == INFINITY
+            }
+            _ => false,
+        }
+    }
+);
+
+setters!(MutRipPacket
+    pub fn set_command(&mut self, command: Command) {
+        write_offset!(self.0, 0, command.value(), u8);
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        write_offset!(self.0, 1, version, u8);
+    }
+);
+
+
+packet!(
+    /// A 20 byte entry in a RIP message, either a route or an authentication entry. The
+    /// authentication getters reuse the bytes of the route fields.
+    RouteEntryPacket,
+    MutRouteEntryPacket,
+    RouteEntryPacketBuf,
+    20,
+    |_data| 20,
+    init |data| write_offset!(data, 0, AddressFamily::INET.value(), u16, to_be)
+);
+
+getters!(RouteEntryPacket
+    pub fn address_family(&self) -> AddressFamily {
+        AddressFamily(read_offset!(self.0, 0, u16, from_be))
+    }
+
+    /// Returns the route tag, used to tell internal routes from external ones. Always zero in
+    /// version 1.
+    pub fn route_tag(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    pub fn ip_address(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 4, [u8; 4]))
+    }
+
+    /// Returns the subnet mask, zero in version 1 where it is implied by the address class.
+    pub fn subnet_mask(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 8, [u8; 4]))
+    }
+
+    /// Returns the next hop, or the unspecified address if it is the sender of the message.
+    pub fn next_hop(&self) -> Ipv4Addr {
+        Ipv4Addr::from(read_offset!(self.0, 12, [u8; 4]))
+    }
+
+    /// Returns the metric, from 1 to `INFINITY`.
+    pub fn metric(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns true if this is an authentication entry or a keyed MD5 trailer.
+    pub fn is_authentication(&self) -> bool {
+        self.address_family() == AddressFamily::AUTHENTICATION
+    }
+
+    /// Returns the authentication type of an authentication entry, in place of the route tag.
+    pub fn authentication_type(&self) -> AuthenticationType {
+        AuthenticationType(read_offset!(self.0, 2, u16, from_be))
+    }
+
+    /// Returns the 16 bytes following the authentication type, a zero padded password for
+    /// simple password authentication.
+    pub fn authentication_data(&self) -> [u8; 16] {
+        read_offset!(self.0, 4, [u8; 16])
+    }
+
+    /// Returns the offset of the MD5 trailer from the start of the message, with keyed MD5
+    /// authentication.
+    pub fn md5_packet_length(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    /// Returns the identifier of the key used, with keyed MD5 authentication.
+    pub fn md5_key_id(&self) -> u8 {
+        read_offset!(self.0, 6, u8)
+    }
+
+    /// Returns the length of the trailer including its four byte header, with keyed MD5
+    /// authentication.
+    pub fn md5_auth_data_length(&self) -> u8 {
+        read_offset!(self.0, 7, u8)
+    }
+
+    /// Returns the non-decreasing sequence number, with keyed MD5 authentication.
+    pub fn md5_sequence_number(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+);
+
+setters!(MutRouteEntryPacket
+    pub fn set_address_family(&mut self, address_family: AddressFamily) {
+        write_offset!(self.0, 0, address_family.value(), u16, to_be);
+    }
+
+    pub fn set_route_tag(&mut self, route_tag: u16) {
+        write_offset!(self.0, 2, route_tag, u16, to_be);
+    }
+
+    pub fn set_ip_address(&mut self, ip_address: Ipv4Addr) {
+        write_offset!(self.0, 4, ip_address.octets(), [u8; 4]);
+    }
+
+    pub fn set_subnet_mask(&mut self, subnet_mask: Ipv4Addr) {
+        write_offset!(self.0, 8, subnet_mask.octets(), [u8; 4]);
+    }
+
+    pub fn set_next_hop(&mut self, next_hop: Ipv4Addr) {
+        write_offset!(self.0, 12, next_hop.octets(), [u8; 4]);
+    }
+
+    pub fn set_metric(&mut self, metric: u32) {
+        write_offset!(self.0, 16, metric, u32, to_be);
+    }
+
+    pub fn set_authentication_type(&mut self, authentication_type: AuthenticationType) {
+        write_offset!(self.0, 2, authentication_type.value(), u16, to_be);
+    }
+
+    pub fn set_authentication_data(&mut self, authentication_data: [u8; 16]) {
+        write_offset!(self.0, 4, authentication_data, [u8; 16]);
+    }
+
+    pub fn set_md5_packet_length(&mut self, md5_packet_length: u16) {
+        write_offset!(self.0, 4, md5_packet_length, u16, to_be);
+    }
+
+    pub fn set_md5_key_id(&mut self, md5_key_id: u8) {
+        write_offset!(self.0, 6, md5_key_id, u8);
+    }
+
+    pub fn set_md5_auth_data_length(&mut self, md5_auth_data_length: u8) {
+        write_offset!(self.0, 7, md5_auth_data_length, u8);
+    }
+
+    pub fn set_md5_sequence_number(&mut self, md5_sequence_number: u32) {
+        write_offset!(self.0, 8, md5_sequence_number, u32, to_be);
+    }
+);
+
+
+/// Represents the command of a RIP message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Command(pub u8);
+
+impl Command {
+    pub const REQUEST: Command = Command(1);
+    pub const RESPONSE: Command = Command(2);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the address family of a RIP entry.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AddressFamily(pub u16);
+
+impl AddressFamily {
+    pub const INET: AddressFamily = AddressFamily(2);
+    pub const AUTHENTICATION: AddressFamily = AddressFamily(0xffff);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Represents the authentication type of a RIP authentication entry.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AuthenticationType(pub u16);
+
+impl AuthenticationType {
+    /// The type of the trailer entry holding the digest with keyed MD5 authentication.
+    pub const MD5_TRAILER: AuthenticationType = AuthenticationType(1);
+    pub const SIMPLE_PASSWORD: AuthenticationType = AuthenticationType(2);
+    pub const KEYED_MD5: AuthenticationType = AuthenticationType(3);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+
+/// Iterator over the route entries of a RIP message. Authentication entries are skipped, and
+/// iteration stops at the first entry that does not fit in the data.
+#[derive(Clone)]
+pub struct RouteEntries<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(RouteEntries<'a>);
+
+impl<'a> Iterator for RouteEntries<'a> {
+    type Item = RouteEntryPacket<'a>;
+
+    fn next(&mut self) -> Option<RouteEntryPacket<'a>> {
+        while self.data.len() >= 20 {
+            let (entry, rest) = self.data.split_at(20);
+            self.data = rest;
+            let entry = RouteEntryPacket::new(entry)?;
+            if !entry.is_authentication() {
+                return Some(entry);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> fmt::Display for RipPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RIPv{} command={} entries={}",
+            self.version(),
+            self.command().value(),
+            self.entries().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for RouteEntryPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RIP entry {}/{} next_hop={} metric={}",
+            self.ip_address(),
+            self.subnet_mask(),
+            self.next_hop(),
+            self.metric()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! rip_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutRipPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    rip_setget_test!(command, set_command, Command::RESPONSE, 0, [2]);
+    rip_setget_test!(version, set_version, 2, 1, [2]);
+
+    #[test]
+    fn whole_table_request() {
+        let mut data = [0; 24];
+        data[..2].copy_from_slice(&[1, 2]);
+        data[23] = 16;
+        assert!(RipPacket::new(&data).unwrap().is_whole_table_request());
+        data[1] = 1;
+        assert!(RipPacket::new(&data).unwrap().is_whole_table_request());
+        data[0] = Command::RESPONSE.value();
+        assert!(!RipPacket::new(&data).unwrap().is_whole_table_request());
+    }
+
+    #[test]
+    fn authenticated_response() {
+        let mut data = [0; 4 + 20 * 3 + 5];
+        data[..2].copy_from_slice(&[2, 2]);
+        {
+            let mut auth = MutRouteEntryPacket::new(&mut data[4..24]).unwrap();
+            auth.set_address_family(AddressFamily::AUTHENTICATION);
+            auth.set_authentication_type(AuthenticationType::SIMPLE_PASSWORD);
+            auth.set_authentication_data(*b"secret\0\0\0\0\0\0\0\0\0\0");
+        }
+        for (i, entry) in data[24..64].chunks_mut(20).enumerate() {
+            let mut entry = MutRouteEntryPacket::new(entry).unwrap();
+            entry.set_address_family(AddressFamily::INET);
+            entry.set_ip_address(Ipv4Addr::new(10, i as u8, 0, 0));
+            entry.set_subnet_mask(Ipv4Addr::new(255, 255, 0, 0));
+            entry.set_metric(i as u32 + 1);
+        }
+
+        let testee = RipPacket::new(&data).unwrap();
+        let auth = testee.authentication().unwrap();
+        assert_eq!(AuthenticationType::SIMPLE_PASSWORD, auth.authentication_type());
+        assert_eq!(b"secret", &auth.authentication_data()[..6]);
+        let entries = testee.entries().collect::<Vec<_>>();
+        assert_eq!(2, entries.len());
+        assert_eq!(Ipv4Addr::new(10, 1, 0, 0), entries[1].ip_address());
+        assert_eq!(2, entries[1].metric());
+
+        // Version 1 messages are never authenticated
+        data[1] = 1;
+        assert!(RipPacket::new(&data).unwrap().authentication().is_none());
+    }
+
+    #[test]
+    fn md5_trailer_skipped() {
+        let mut data = [0; 4 + 20 * 3];
+        data[..2].copy_from_slice(&[2, 2]);
+        data[4..8].copy_from_slice(&[0xff, 0xff, 0, 3]);
+        data[24..26].copy_from_slice(&[0, 2]);
+        data[44..48].copy_from_slice(&[0xff, 0xff, 0, 1]);
+        let testee = RipPacket::new(&data).unwrap();
+        let auth = testee.authentication().unwrap();
+        assert_eq!(AuthenticationType::KEYED_MD5, auth.authentication_type());
+        assert_eq!(1, testee.entries().count());
+    }
+
+    mod route_entry {
+        use super::super::*;
+
+        macro_rules! route_entry_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutRouteEntryPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        route_entry_setget_test!(
+            address_family,
+            set_address_family,
+            AddressFamily::INET,
+            0,
+            [0, 2]
+        );
+        route_entry_setget_test!(route_tag, set_route_tag, 0xabcd, 2, [0xab, 0xcd]);
+        route_entry_setget_test!(
+            ip_address,
+            set_ip_address,
+            Ipv4Addr::new(1, 2, 3, 4),
+            4,
+            [1, 2, 3, 4]
+        );
+        route_entry_setget_test!(
+            subnet_mask,
+            set_subnet_mask,
+            Ipv4Addr::new(1, 2, 3, 4),
+            8,
+            [1, 2, 3, 4]
+        );
+        route_entry_setget_test!(
+            next_hop,
+            set_next_hop,
+            Ipv4Addr::new(1, 2, 3, 4),
+            12,
+            [1, 2, 3, 4]
+        );
+        route_entry_setget_test!(metric, set_metric, 0xabcdef01, 16, [0xab, 0xcd, 0xef, 0x01]);
+        route_entry_setget_test!(
+            authentication_type,
+            set_authentication_type,
+            AuthenticationType::KEYED_MD5,
+            2,
+            [0, 3]
+        );
+        route_entry_setget_test!(
+            authentication_data,
+            set_authentication_data,
+            [0xab; 16],
+            4,
+            [0xab; 16]
+        );
+        route_entry_setget_test!(md5_packet_length, set_md5_packet_length, 0xabcd, 4, [0xab, 0xcd]);
+        route_entry_setget_test!(md5_key_id, set_md5_key_id, 0xab, 6, [0xab]);
+        route_entry_setget_test!(md5_auth_data_length, set_md5_auth_data_length, 20, 7, [20]);
+        route_entry_setget_test!(
+            md5_sequence_number,
+            set_md5_sequence_number,
+            0xabcdef01,
+            8,
+            [0xab, 0xcd, 0xef, 0x01]
+        );
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        RipPacketBuf => |packet| packet.command() == Command::RESPONSE && packet.version() == 2,
+        RouteEntryPacketBuf => |packet| !packet.is_authentication()
+    );
+}
@@ -0,0 +1,863 @@
+//! The RTP Control Protocol, RFC 3550. RTCP packets are sent as compound packets, several RTCP
+//! packets one after another in a single UDP datagram. `RtcpPackets` iterates over them.
+
+use ntp::Timestamp;
+use std::fmt;
+use types::*;
+
+macro_rules! rtcp_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn version(&self) -> u2 {
+                read_offset!(self.0, 0, u8) >> 6
+            }
+
+            /// Returns true if the packet ends with padding, only allowed for the last packet
+            /// of a compound packet.
+            pub fn has_padding(&self) -> bool {
+                read_offset!(self.0, 0, u8) & 0x20 != 0
+            }
+
+            /// Returns the count field, the number of report blocks in sender and receiver
+            /// reports, chunks in source descriptions and sources in goodbye packets.
+            pub fn count(&self) -> u5 {
+                read_offset!(self.0, 0, u8) & 0x1f
+            }
+
+            pub fn packet_type(&self) -> PacketType {
+                PacketType(read_offset!(self.0, 1, u8))
+            }
+
+            /// Returns the length field, the length of the packet in 32 bit words minus one.
+            /// See `packet_len`.
+            pub fn length(&self) -> u16 {
+                read_offset!(self.0, 2, u16, from_be)
+            }
+
+            /// Returns the length of the packet in bytes, as given by the length field.
+            pub fn packet_len(&self) -> usize {
+                (usize::from(self.length()) + 1) * 4
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_version(&mut self, version: u2) {
+                let new_byte = (version << 6) | (read_offset!(self.0, 0, u8) & 0x3f);
+                write_offset!(self.0, 0, new_byte, u8);
+            }
+
+            pub fn set_padding(&mut self, padding: bool) {
+                let new_byte = (read_offset!(self.0, 0, u8) & 0xdf) | ((padding as u8) << 5);
+                write_offset!(self.0, 0, new_byte, u8);
+            }
+
+            pub fn set_count(&mut self, count: u5) {
+                let new_byte = (read_offset!(self.0, 0, u8) & 0xe0) | (count & 0x1f);
+                write_offset!(self.0, 0, new_byte, u8);
+            }
+
+            pub fn set_packet_type(&mut self, packet_type: PacketType) {
+                write_offset!(self.0, 1, packet_type.value(), u8);
+            }
+
+            pub fn set_length(&mut self, length: u16) {
+                write_offset!(self.0, 2, length, u16, to_be);
+            }
+        );
+    }
+}
+
+packet!(
+    /// The header common to all RTCP packets. Use one of the conversion methods to get a view
+    /// of the packet type specific fields.
+    RtcpPacket,
+    MutRtcpPacket,
+    RtcpPacketBuf,
+    4,
+    |_data| 4,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, PacketType::BYE)
+);
+rtcp_common!(RtcpPacket, MutRtcpPacket);
+
+impl<'a> RtcpPacket<'a> {
+    /// Returns the packet as a sender report, or `None` if it is of another type or too short.
+    pub fn sender_report(&self) -> Option<SenderReportPacket<'a>> {
+        self.as_type(PacketType::SR).and_then(SenderReportPacket::new)
+    }
+
+    /// Returns the packet as a receiver report, or `None` if it is of another type or too
+    /// short.
+    pub fn receiver_report(&self) -> Option<ReceiverReportPacket<'a>> {
+        self.as_type(PacketType::RR).and_then(ReceiverReportPacket::new)
+    }
+
+    /// Returns the packet as a source description, or `None` if it is of another type.
+    pub fn source_description(&self) -> Option<SdesPacket<'a>> {
+        self.as_type(PacketType::SDES).and_then(SdesPacket::new)
+    }
+
+    /// Returns the packet as a goodbye packet, or `None` if it is of another type.
+    pub fn bye(&self) -> Option<ByePacket<'a>> {
+        self.as_type(PacketType::BYE).and_then(ByePacket::new)
+    }
+
+    fn as_type(&self, packet_type: PacketType) -> Option<&'a [u8]> {
+        if self.packet_type() == packet_type {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the part of `data` covered by the length field of the RTCP packet it holds, or all
+/// of it if the packet is truncated.
+fn packet_data(data: &[u8]) -> &[u8] {
+    &data[..data.len().min(packet_len(data))]
+}
+
+/// Returns the length of the RTCP packet in `data` given by its length field.
+fn packet_len(data: &[u8]) -> usize {
+    (usize::from(read_offset!(data, 2, u16, from_be)) + 1) * 4
+}
+
+/// Sets the length field of the RTCP packet in `data` for a packet of `len` bytes. Returns
+/// false if `len` is not a multiple of four bytes, as RTCP packets must be, or is too large.
+fn set_packet_len(data: &mut [u8], len: usize) -> bool {
+    len.is_multiple_of(4) && len / 4 - 1 <= usize::from(u16::MAX) && {
+        write_offset!(data, 2, (len / 4 - 1) as u16, u16, to_be);
+        true
+    }
+}
+
+/// Initializes `data` as an RTCP packet of version 2 and type `packet_type`, with a count of
+/// zero and a length covering as much of `data` as whole 32 bit words can.
+fn init_packet(data: &mut [u8], packet_type: PacketType) {
+    data[0] = 0x80;
+    data[1] = packet_type.value();
+    set_packet_len(data, data.len() / 4 * 4);
+}
+
+
+packet!(
+    /// A sender report. The payload holds the report blocks, see `report_blocks`, possibly
+    /// followed by profile specific extensions.
+    SenderReportPacket,
+    MutSenderReportPacket,
+    SenderReportPacketBuf,
+    28,
+    |_data| 28,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, PacketType::SR)
+);
+rtcp_common!(SenderReportPacket, MutSenderReportPacket);
+
+getters!(SenderReportPacket
+    /// Returns the synchronization source identifier of the sender.
+    pub fn ssrc(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the wallclock time the report was sent.
+    pub fn ntp_timestamp(&self) -> Timestamp {
+        Timestamp(read_offset!(self.0, 8, u64, from_be))
+    }
+
+    /// Returns the time the report was sent, in the same units as the RTP timestamps.
+    pub fn rtp_timestamp(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns the number of RTP packets sent by the sender.
+    pub fn packet_count(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    /// Returns the number of payload bytes sent by the sender.
+    pub fn octet_count(&self) -> u32 {
+        read_offset!(self.0, 24, u32, from_be)
+    }
+
+    /// Returns an iterator over the report blocks. The length field is honored, so no blocks
+    /// are returned if it is set too short.
+    pub fn report_blocks(&self) -> ReportBlocks<'a> {
+        ReportBlocks::new(packet_data(self.0).get(28..).unwrap_or(&[]), self.count())
+    }
+);
+
+setters!(MutSenderReportPacket
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        write_offset!(self.0, 4, ssrc, u32, to_be);
+    }
+
+    pub fn set_ntp_timestamp(&mut self, ntp_timestamp: Timestamp) {
+        write_offset!(self.0, 8, ntp_timestamp.value(), u64, to_be);
+    }
+
+    pub fn set_rtp_timestamp(&mut self, rtp_timestamp: u32) {
+        write_offset!(self.0, 16, rtp_timestamp, u32, to_be);
+    }
+
+    pub fn set_packet_count(&mut self, packet_count: u32) {
+        write_offset!(self.0, 20, packet_count, u32, to_be);
+    }
+
+    pub fn set_octet_count(&mut self, octet_count: u32) {
+        write_offset!(self.0, 24, octet_count, u32, to_be);
+    }
+);
+
+impl<'a> MutSenderReportPacket<'a> {
+    /// Returns the report block at `index` for modification, or `None` if it does not fit in
+    /// the backing slice. The count and length fields are not changed.
+    pub fn report_block(&mut self, index: usize) -> Option<MutReportBlockPacket<'_>> {
+        let offset = 28 + index * 24;
+        MutReportBlockPacket::new(self.0.get_mut(offset..offset + 24)?)
+    }
+}
+
+
+packet!(
+    /// A receiver report. The payload holds the report blocks, see `report_blocks`, possibly
+    /// followed by profile specific extensions.
+    ReceiverReportPacket,
+    MutReceiverReportPacket,
+    ReceiverReportPacketBuf,
+    8,
+    |_data| 8,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, PacketType::RR)
+);
+rtcp_common!(ReceiverReportPacket, MutReceiverReportPacket);
+
+getters!(ReceiverReportPacket
+    /// Returns the synchronization source identifier of the sender of the report.
+    pub fn ssrc(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns an iterator over the report blocks. The length field is honored, so no blocks
+    /// are returned if it is set too short.
+    pub fn report_blocks(&self) -> ReportBlocks<'a> {
+        ReportBlocks::new(packet_data(self.0).get(8..).unwrap_or(&[]), self.count())
+    }
+);
+
+setters!(MutReceiverReportPacket
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        write_offset!(self.0, 4, ssrc, u32, to_be);
+    }
+);
+
+impl<'a> MutReceiverReportPacket<'a> {
+    /// Returns the report block at `index` for modification, or `None` if it does not fit in
+    /// the backing slice. The count and length fields are not changed.
+    pub fn report_block(&mut self, index: usize) -> Option<MutReportBlockPacket<'_>> {
+        let offset = 8 + index * 24;
+        MutReportBlockPacket::new(self.0.get_mut(offset..offset + 24)?)
+    }
+}
+
+
+packet!(
+    /// A report block in a sender or receiver report, giving reception statistics for one
+    /// source.
+    ReportBlockPacket,
+    MutReportBlockPacket,
+    ReportBlockPacketBuf,
+    24
+);
+
+getters!(ReportBlockPacket
+    /// Returns the synchronization source identifier of the source this block is about.
+    pub fn ssrc(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    /// Returns the fraction of packets lost since the previous report, in units of 1/256.
+    pub fn fraction_lost(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    /// Returns the signed 24 bit number of packets lost since the start of reception. Negative
+    /// if duplicates have been received.
+    pub fn cumulative_lost(&self) -> i32 {
+        (read_offset!(self.0, 5, u24, from_be) << 8) as i32 >> 8
+    }
+
+    /// Returns the highest sequence number received, extended with the number of sequence
+    /// number cycles in the upper 16 bits.
+    pub fn highest_sequence_number(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns the interarrival jitter, in RTP timestamp units.
+    pub fn jitter(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns the middle 32 bits of the NTP timestamp of the last sender report received from
+    /// the source, or zero if none has been received.
+    pub fn last_sr(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns the delay since the last sender report was received, in units of 1/65536
+    /// seconds.
+    pub fn delay_since_last_sr(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+);
+
+setters!(MutReportBlockPacket
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        write_offset!(self.0, 0, ssrc, u32, to_be);
+    }
+
+    pub fn set_fraction_lost(&mut self, fraction_lost: u8) {
+        write_offset!(self.0, 4, fraction_lost, u8);
+    }
+
+    /// Sets the signed 24 bit number of packets lost. Values outside the 24 bit range are
+    /// truncated.
+    pub fn set_cumulative_lost(&mut self, cumulative_lost: i32) {
+        write_offset!(self.0, 5, cumulative_lost as u32, u24, to_be);
+    }
+
+    pub fn set_highest_sequence_number(&mut self, highest_sequence_number: u32) {
+        write_offset!(self.0, 8, highest_sequence_number, u32, to_be);
+    }
+
+    pub fn set_jitter(&mut self, jitter: u32) {
+        write_offset!(self.0, 12, jitter, u32, to_be);
+    }
+
+    pub fn set_last_sr(&mut self, last_sr: u32) {
+        write_offset!(self.0, 16, last_sr, u32, to_be);
+    }
+
+    pub fn set_delay_since_last_sr(&mut self, delay_since_last_sr: u32) {
+        write_offset!(self.0, 20, delay_since_last_sr, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A source description packet. The payload holds the chunks, see `chunks`.
+    SdesPacket,
+    MutSdesPacket,
+    SdesPacketBuf,
+    4,
+    |_data| 4,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, PacketType::SDES)
+);
+rtcp_common!(SdesPacket, MutSdesPacket);
+
+getters!(SdesPacket
+    /// Returns an iterator over the chunks, one per source.
+    pub fn chunks(&self) -> SdesChunks<'a> {
+        SdesChunks { data: &packet_data(self.0)[4..], remaining: self.count() }
+    }
+);
+
+
+packet!(
+    /// A goodbye packet, telling that one or more sources are no longer active.
+    ByePacket,
+    MutByePacket,
+    ByePacketBuf,
+    4,
+    |_data| 4,
+    len |data| packet_len(data),
+    set_len |data, len| set_packet_len(data, len),
+    init |data| init_packet(data, PacketType::BYE)
+);
+rtcp_common!(ByePacket, MutByePacket);
+
+getters!(ByePacket
+    /// Returns an iterator over the identifiers of the sources leaving. Iteration stops early
+    /// if the list does not fit in the packet.
+    pub fn ssrcs(&self) -> Ssrcs<'a> {
+        let data = &packet_data(self.0)[4..];
+        Ssrcs { data: &data[..data.len().min(usize::from(self.count()) * 4)] }
+    }
+
+    /// Returns the reason for leaving, or `None` if there is none or it does not fit in the
+    /// packet.
+    pub fn reason(&self) -> Option<&'a [u8]> {
+        let data = &packet_data(self.0)[4..];
+        let offset = usize::from(self.count()) * 4;
+        let len = usize::from(*data.get(offset)?);
+        data.get(offset + 1..offset + 1 + len)
+    }
+);
+
+
+/// The type of an RTCP packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PacketType(pub u8);
+
+impl PacketType {
+    /// Sender report.
+    pub const SR: PacketType = PacketType(200);
+    /// Receiver report.
+    pub const RR: PacketType = PacketType(201);
+    /// Source description.
+    pub const SDES: PacketType = PacketType(202);
+    /// Goodbye.
+    pub const BYE: PacketType = PacketType(203);
+    /// Application defined.
+    pub const APP: PacketType = PacketType(204);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The type of an item in a source description chunk.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SdesItemType(pub u8);
+
+impl SdesItemType {
+    /// Marks the end of the items in a chunk.
+    pub const END: SdesItemType = SdesItemType(0);
+    /// The canonical name, identifying a participant across all its sources.
+    pub const CNAME: SdesItemType = SdesItemType(1);
+    pub const NAME: SdesItemType = SdesItemType(2);
+    pub const EMAIL: SdesItemType = SdesItemType(3);
+    pub const PHONE: SdesItemType = SdesItemType(4);
+    pub const LOC: SdesItemType = SdesItemType(5);
+    pub const TOOL: SdesItemType = SdesItemType(6);
+    pub const NOTE: SdesItemType = SdesItemType(7);
+    pub const PRIV: SdesItemType = SdesItemType(8);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+/// Iterator over the packets of a compound RTCP packet. Each packet is cut to the length given
+/// by its length field. Iteration stops at the first packet that does not fit in the data.
+#[derive(Clone)]
+pub struct RtcpPackets<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(RtcpPackets<'a>);
+
+impl<'a> RtcpPackets<'a> {
+    /// Creates an iterator over the packets of the compound packet in `data`.
+    pub fn new(data: &'a [u8]) -> RtcpPackets<'a> {
+        RtcpPackets { data }
+    }
+}
+
+impl<'a> Iterator for RtcpPackets<'a> {
+    type Item = RtcpPacket<'a>;
+
+    fn next(&mut self) -> Option<RtcpPacket<'a>> {
+        let len = RtcpPacket::new(self.data)?.packet_len();
+        if len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let (packet, rest) = self.data.split_at(len);
+        self.data = rest;
+        RtcpPacket::new(packet)
+    }
+}
+
+/// Iterator over the report blocks of a sender or receiver report. Iteration stops after the
+/// number of blocks given in the report, or at the first block that does not fit in the data.
+#[derive(Clone)]
+pub struct ReportBlocks<'a> {
+    data: &'a [u8],
+    remaining: u8,
+}
+
+debug_items!(ReportBlocks<'a>);
+
+impl<'a> ReportBlocks<'a> {
+    fn new(data: &'a [u8], count: u8) -> ReportBlocks<'a> {
+        ReportBlocks { data, remaining: count }
+    }
+}
+
+impl<'a> Iterator for ReportBlocks<'a> {
+    type Item = ReportBlockPacket<'a>;
+
+    fn next(&mut self) -> Option<ReportBlockPacket<'a>> {
+        if self.remaining == 0 || self.data.len() < 24 {
+            return None;
+        }
+        self.remaining -= 1;
+        let (block, rest) = self.data.split_at(24);
+        self.data = rest;
+        ReportBlockPacket::new(block)
+    }
+}
+
+/// Iterator over the source identifiers in a goodbye packet.
+#[derive(Clone)]
+pub struct Ssrcs<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ssrcs<'a>);
+
+impl<'a> Iterator for Ssrcs<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (ssrc, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(read_offset!(ssrc, 0, u32, from_be))
+    }
+}
+
+/// A chunk in a source description, holding the items describing one source.
+#[derive(Debug, Clone)]
+pub struct SdesChunk<'a> {
+    pub ssrc: u32,
+    pub items: SdesItems<'a>,
+}
+
+/// Iterator over the chunks of a source description. Iteration stops after the number of
+/// chunks given in the packet, or at the first chunk that does not fit in the data.
+#[derive(Clone)]
+pub struct SdesChunks<'a> {
+    data: &'a [u8],
+    remaining: u8,
+}
+
+debug_items!(SdesChunks<'a>);
+
+impl<'a> Iterator for SdesChunks<'a> {
+    type Item = SdesChunk<'a>;
+
+    fn next(&mut self) -> Option<SdesChunk<'a>> {
+        if self.remaining == 0 || self.data.len() < 4 {
+            return None;
+        }
+        self.remaining -= 1;
+        // Items end with a zero type byte, followed by zero padding up to a 32 bit boundary
+        let mut end = 4;
+        loop {
+            match self.data.get(end) {
+                Some(0) => break,
+                Some(_) => match self.data.get(end + 1) {
+                    Some(&len) => end += 2 + usize::from(len),
+                    None => end = self.data.len(),
+                },
+                None => {
+                    self.data = &[];
+                    return None;
+                }
+            }
+        }
+        let (chunk, _) = self.data.split_at(end);
+        let padded_len = (end + 4) & !3;
+        self.data = &self.data[self.data.len().min(padded_len)..];
+        Some(SdesChunk {
+            ssrc: read_offset!(chunk, 0, u32, from_be),
+            items: SdesItems { data: &chunk[4..] },
+        })
+    }
+}
+
+/// Iterator over the items of a source description chunk, yielding the type and text of each
+/// item. Iteration stops at the first item that does not fit in the data.
+#[derive(Clone)]
+pub struct SdesItems<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(SdesItems<'a>);
+
+impl<'a> Iterator for SdesItems<'a> {
+    type Item = (SdesItemType, &'a [u8]);
+
+    fn next(&mut self) -> Option<(SdesItemType, &'a [u8])> {
+        let len = match self.data.get(1) {
+            Some(&len) if 2 + usize::from(len) <= self.data.len() => 2 + usize::from(len),
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (item, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some((SdesItemType(item[0]), &item[2..]))
+    }
+}
+
+impl<'a> fmt::Display for RtcpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RTCP type={} count={} len={}",
+            self.packet_type().value(),
+            self.count(),
+            self.length()
+        )
+    }
+}
+
+impl<'a> fmt::Display for SenderReportPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RTCP SR ssrc={:#010x} packets={} octets={}",
+            self.ssrc(),
+            self.packet_count(),
+            self.octet_count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for ReceiverReportPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RTCP RR ssrc={:#010x} reports={}",
+            self.ssrc(),
+            self.report_blocks().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for SdesPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RTCP SDES chunks={}", self.chunks().count())
+    }
+}
+
+impl<'a> fmt::Display for ByePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RTCP BYE ssrcs={}", self.ssrcs().count())
+    }
+}
+
+impl<'a> fmt::Display for ReportBlockPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RTCP report block ssrc={:#010x} lost={} jitter={}",
+            self.ssrc(),
+            self.cumulative_lost(),
+            self.jitter()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! rtcp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutRtcpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    rtcp_setget_test!(version, set_version, 2, 0, [0x80]);
+    rtcp_setget_test!(has_padding, set_padding, true, 0, [0x20]);
+    rtcp_setget_test!(count, set_count, 0x1f, 0, [0x1f]);
+    rtcp_setget_test!(packet_type, set_packet_type, PacketType::SDES, 1, [202]);
+    rtcp_setget_test!(length, set_length, 0xabcd, 2, [0xab, 0xcd]);
+
+    mod sender_report {
+        use super::super::*;
+
+        macro_rules! sr_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutSenderReportPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        sr_setget_test!(ssrc, set_ssrc, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 0x01]);
+        sr_setget_test!(
+            ntp_timestamp,
+            set_ntp_timestamp,
+            Timestamp(0x0102030405060708),
+            8,
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        sr_setget_test!(rtp_timestamp, set_rtp_timestamp, 0xabcdef01, 16, [0xab, 0xcd, 0xef, 1]);
+        sr_setget_test!(packet_count, set_packet_count, 0xabcdef01, 20, [0xab, 0xcd, 0xef, 1]);
+        sr_setget_test!(octet_count, set_octet_count, 0xabcdef01, 24, [0xab, 0xcd, 0xef, 1]);
+    }
+
+    mod report_block {
+        use super::super::*;
+
+        macro_rules! block_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutReportBlockPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        block_setget_test!(ssrc, set_ssrc, 0xabcdef01, 0, [0xab, 0xcd, 0xef, 0x01]);
+        block_setget_test!(fraction_lost, set_fraction_lost, 0xab, 4, [0xab]);
+        block_setget_test!(cumulative_lost, set_cumulative_lost, -2, 5, [0xff, 0xff, 0xfe]);
+        block_setget_test!(
+            highest_sequence_number,
+            set_highest_sequence_number,
+            0xabcdef01,
+            8,
+            [0xab, 0xcd, 0xef, 0x01]
+        );
+        block_setget_test!(jitter, set_jitter, 0xabcdef01, 12, [0xab, 0xcd, 0xef, 0x01]);
+        block_setget_test!(last_sr, set_last_sr, 0xabcdef01, 16, [0xab, 0xcd, 0xef, 0x01]);
+        block_setget_test!(
+            delay_since_last_sr,
+            set_delay_since_last_sr,
+            0xabcdef01,
+            20,
+            [0xab, 0xcd, 0xef, 0x01]
+        );
+    }
+
+    static COMPOUND: [u8; 32 + 16 + 12] = [
+        // Receiver report with one report block
+        0x81, 201, 0, 7, 0x11, 0x11, 0x11, 0x11,
+        0x22, 0x22, 0x22, 0x22, 0x10, 0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0x20,
+        0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0,
+        // Source description with a CNAME
+        0x81, 202, 0, 3, 0x11, 0x11, 0x11, 0x11, 1, 2, b'a', b'b', 0, 0, 0, 0,
+        // Goodbye without reason
+        0x82, 203, 0, 2, 0x11, 0x11, 0x11, 0x11, 0x22, 0x22, 0x22, 0x22,
+    ];
+
+    #[test]
+    fn compound_packet() {
+        let packets = RtcpPackets::new(&COMPOUND).collect::<Vec<_>>();
+        assert_eq!(3, packets.len());
+
+        let rr = packets[0].receiver_report().unwrap();
+        assert!(packets[0].sender_report().is_none());
+        assert_eq!(0x1111_1111, rr.ssrc());
+        let blocks = rr.report_blocks().collect::<Vec<_>>();
+        assert_eq!(1, blocks.len());
+        assert_eq!(0x2222_2222, blocks[0].ssrc());
+        assert_eq!(0x10, blocks[0].fraction_lost());
+        assert_eq!(5, blocks[0].cumulative_lost());
+        assert_eq!(0x0001_0020, blocks[0].highest_sequence_number());
+        assert_eq!(9, blocks[0].jitter());
+
+        let sdes = packets[1].source_description().unwrap();
+        let mut chunks = sdes.chunks();
+        let chunk = chunks.next().unwrap();
+        assert_eq!(0x1111_1111, chunk.ssrc);
+        assert_eq!(
+            vec![(SdesItemType::CNAME, &b"ab"[..])],
+            chunk.items.collect::<Vec<_>>()
+        );
+        assert!(chunks.next().is_none());
+
+        let bye = packets[2].bye().unwrap();
+        assert_eq!(vec![0x1111_1111, 0x2222_2222], bye.ssrcs().collect::<Vec<_>>());
+        assert_eq!(None, bye.reason());
+    }
+
+    #[test]
+    fn truncated_compound_packet() {
+        assert_eq!(1, RtcpPackets::new(&COMPOUND[..40]).count());
+        let rr = ReceiverReportPacket::new(&[0x81, 201, 0, 0, 0, 0, 0, 1, 0]).unwrap();
+        assert_eq!(0, rr.report_blocks().count());
+        let bye = [0x81, 203, 0, 3, 0, 0, 0, 1, 4, b'g', b'o', b'n', b'e', 0, 0, 0];
+        let bye = RtcpPacket::new(&bye).unwrap().bye().unwrap();
+        assert_eq!(Some(&b"gone"[..]), bye.reason());
+        let bye = ByePacket::new(&[0x81, 203, 0, 2, 0, 0, 0, 1, 4, b'g', b'o', b'n']).unwrap();
+        assert_eq!(None, bye.reason());
+    }
+
+    #[test]
+    fn sdes_chunks() {
+        let data = [
+            0x82, 202, 0, 6,
+            0, 0, 0, 1, 1, 1, b'a', 2, 1, b'b', 0, 0,
+            0, 0, 0, 2, 0, 0, 0, 0,
+            0, 0, 0, 3, 1, 9, b'a',
+        ];
+        let testee = SdesPacket::new(&data).unwrap();
+        let chunks = testee.chunks().collect::<Vec<_>>();
+        assert_eq!(2, chunks.len());
+        assert_eq!(2, chunks[0].items.clone().count());
+        assert_eq!(2, chunks[1].ssrc);
+        assert_eq!(0, chunks[1].items.clone().count());
+    }
+
+    #[test]
+    fn build_sender_report() {
+        let mut data = [0; 28 + 24];
+        {
+            let mut testee = MutSenderReportPacket::new(&mut data).unwrap();
+            testee.set_version(2);
+            testee.set_count(1);
+            testee.set_packet_type(PacketType::SR);
+            testee.set_length(12);
+            testee.set_ssrc(1);
+            let mut block = testee.report_block(0).unwrap();
+            block.set_ssrc(2);
+            block.set_cumulative_lost(-1);
+            assert!(testee.report_block(1).is_none());
+        }
+        let testee = RtcpPacket::new(&data).unwrap().sender_report().unwrap();
+        assert_eq!(52, testee.packet_len());
+        let blocks = testee.report_blocks().collect::<Vec<_>>();
+        assert_eq!(1, blocks.len());
+        assert_eq!(2, blocks[0].ssrc());
+        assert_eq!(-1, blocks[0].cumulative_lost());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 4 + 8];
+        let mut testee = MutRtcpPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[1, 2, 3, 4]));
+        assert_eq!(1, testee.as_immutable().length());
+        assert_eq!(&[1, 2, 3, 4], testee.as_immutable().payload());
+        // RTCP packets are a multiple of four bytes long
+        assert!(!testee.set_payload(&[1, 2]));
+        assert_eq!(1, testee.as_immutable().length());
+    }
+
+    #[cfg(feature = "arbitrary")]
+    macro_rules! is_well_formed {
+        ($packet:ident, $packet_type:expr) => {
+            $packet.version() == 2
+                && $packet.count() == 0
+                && $packet.packet_type() == $packet_type
+                && $packet.packet_len() == $packet.data().len()
+        };
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        RtcpPacketBuf => |packet| is_well_formed!(packet, PacketType::BYE),
+        SenderReportPacketBuf => |packet| {
+            let packets = RtcpPackets::new(packet.data()).collect::<Vec<_>>();
+            is_well_formed!(packet, PacketType::SR)
+                && packets.len() == 1
+                && packets[0].sender_report().is_some()
+        },
+        ReceiverReportPacketBuf => |packet| is_well_formed!(packet, PacketType::RR),
+        ReportBlockPacketBuf,
+        SdesPacketBuf => |packet| is_well_formed!(packet, PacketType::SDES),
+        ByePacketBuf => |packet| is_well_formed!(packet, PacketType::BYE),
+    );
+}
@@ -0,0 +1,326 @@
+//! The Real-time Transport Protocol, RFC 3550.
+
+use std::fmt;
+use types::*;
+
+packet!(
+    /// An RTP packet. The header covers the fixed part, the CSRC list and the header extension,
+    /// if any, so the payload is the media data, followed by padding if the padding bit is set.
+    RtpPacket,
+    MutRtpPacket,
+    RtpPacketBuf,
+    12,
+    |data| header_len(data),
+    // Version 2 without padding, extension or CSRCs.
+    init |data| data[0] = 0x80
+);
+
+getters!(RtpPacket
+    pub fn version(&self) -> u2 {
+        read_offset!(self.0, 0, u8) >> 6
+    }
+
+    /// Returns true if the payload ends with padding, see `padding_len`.
+    pub fn has_padding(&self) -> bool {
+        read_offset!(self.0, 0, u8) & 0x20 != 0
+    }
+
+    /// Returns true if the CSRC list is followed by a header extension.
+    pub fn has_extension(&self) -> bool {
+        read_offset!(self.0, 0, u8) & 0x10 != 0
+    }
+
+    /// Returns the number of CSRC identifiers following the fixed header.
+    pub fn csrc_count(&self) -> u4 {
+        read_offset!(self.0, 0, u8) & 0x0f
+    }
+
+    /// Returns the marker bit, whose meaning is defined by the profile. For video it usually
+    /// marks the last packet of a frame.
+    pub fn marker(&self) -> bool {
+        read_offset!(self.0, 1, u8) & 0x80 != 0
+    }
+
+    pub fn payload_type(&self) -> u7 {
+        read_offset!(self.0, 1, u8) & 0x7f
+    }
+
+    pub fn sequence_number(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the sampling instant of the first byte of the payload, in units of the clock
+    /// rate of the payload format.
+    pub fn timestamp(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the synchronization source identifier.
+    pub fn ssrc(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns an iterator over the contributing source identifiers. Iteration stops early if
+    /// the list does not fit in the data.
+    pub fn csrcs(&self) -> Csrcs<'a> {
+        let end = 12 + usize::from(self.csrc_count()) * 4;
+        Csrcs { data: &self.0[12..self.0.len().min(end)] }
+    }
+
+    /// Returns the header extension, or `None` if the extension bit is not set or the extension
+    /// does not fit in the data.
+    pub fn extension(&self) -> Option<HeaderExtension<'a>> {
+        if !self.has_extension() {
+            return None;
+        }
+        let offset = 12 + usize::from(self.csrc_count()) * 4;
+        let fields = self.0.get(offset..offset + 4)?;
+        let len = usize::from(read_offset!(fields, 2, u16, from_be)) * 4;
+        Some(HeaderExtension {
+            profile: read_offset!(fields, 0, u16, from_be),
+            data: self.0.get(offset + 4..offset + 4 + len)?,
+        })
+    }
+
+    /// Returns the number of padding bytes at the end of the payload, as given by its last
+    /// byte, or zero if the padding bit is not set. Never more than the payload length.
+    pub fn padding_len(&self) -> usize {
+        let payload = &self.0[self.header_len()..];
+        match payload.last() {
+            Some(&len) if self.has_padding() => payload.len().min(usize::from(len)),
+            _ => 0,
+        }
+    }
+
+    /// Returns the payload without any padding.
+    pub fn media_payload(&self) -> &'a [u8] {
+        let payload = &self.0[self.header_len()..];
+        &payload[..payload.len() - self.padding_len()]
+    }
+);
+
+setters!(MutRtpPacket
+    pub fn set_version(&mut self, version: u2) {
+        let new_byte = (version << 6) | (read_offset!(self.0, 0, u8) & 0x3f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_padding(&mut self, padding: bool) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xdf) | ((padding as u8) << 5);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_extension(&mut self, extension: bool) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xef) | ((extension as u8) << 4);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_csrc_count(&mut self, csrc_count: u4) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xf0) | (csrc_count & 0x0f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_marker(&mut self, marker: bool) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0x7f) | ((marker as u8) << 7);
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_payload_type(&mut self, payload_type: u7) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0x80) | (payload_type & 0x7f);
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u16) {
+        write_offset!(self.0, 2, sequence_number, u16, to_be);
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: u32) {
+        write_offset!(self.0, 4, timestamp, u32, to_be);
+    }
+
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        write_offset!(self.0, 8, ssrc, u32, to_be);
+    }
+);
+
+impl<'a> MutRtpPacket<'a> {
+    /// Sets the contributing source identifier at `index` in the CSRC list. Does not change the
+    /// CSRC count. Returns false if the identifier does not fit in the backing slice.
+    pub fn set_csrc(&mut self, index: usize, csrc: u32) -> bool {
+        match index.checked_mul(4).and_then(|offset| offset.checked_add(12)) {
+            Some(offset) if offset.checked_add(4).is_some_and(|end| end <= self.0.len()) => {
+                write_offset!(self.0, offset, csrc, u32, to_be);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the CSRC list to `csrcs`, updating the CSRC count. Leaves any header extension
+    /// where it is, so it is overwritten if the list grows. Returns false, leaving the packet
+    /// unchanged, if there are more than 15 identifiers or they do not fit in the backing slice.
+    pub fn set_csrcs(&mut self, csrcs: &[u32]) -> bool {
+        if csrcs.len() > 15 || 12 + csrcs.len() * 4 > self.0.len() {
+            return false;
+        }
+        for (index, &csrc) in csrcs.iter().enumerate() {
+            write_offset!(self.0, 12 + index * 4, csrc, u32, to_be);
+        }
+        self.set_csrc_count(csrcs.len() as u8);
+        true
+    }
+
+    /// Writes a header extension after the CSRC list and sets the extension bit. Returns false,
+    /// leaving the packet unchanged, if the length of `data` is not a multiple of four or too
+    /// long to encode, or the extension does not fit in the backing slice.
+    pub fn set_header_extension(&mut self, profile: u16, data: &[u8]) -> bool {
+        let offset = 12 + usize::from(self.as_immutable().csrc_count()) * 4;
+        if !data.len().is_multiple_of(4) ||
+            data.len() / 4 > usize::from(u16::MAX) ||
+            offset + 4 + data.len() > self.0.len()
+        {
+            return false;
+        }
+        let extension = &mut self.0[offset..offset + 4 + data.len()];
+        write_offset!(extension, 0, profile, u16, to_be);
+        write_offset!(extension, 2, (data.len() / 4) as u16, u16, to_be);
+        extension[4..].copy_from_slice(data);
+        self.set_extension(true);
+        true
+    }
+}
+
+fn header_len(data: &[u8]) -> usize {
+    let csrc_end = 12 + usize::from(data[0] & 0x0f) * 4;
+    if data[0] & 0x10 == 0 {
+        return csrc_end;
+    }
+    match data.get(csrc_end + 2..csrc_end + 4) {
+        Some(len) => csrc_end + 4 + usize::from(read_offset!(len, 0, u16, from_be)) * 4,
+        None => csrc_end,
+    }
+}
+
+
+/// A header extension in an RTP packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct HeaderExtension<'a> {
+    /// The profile defined value telling how to interpret the extension. For example 0xbede
+    /// for the one byte header extensions of RFC 8285.
+    pub profile: u16,
+    /// The extension data following the profile and length fields.
+    pub data: &'a [u8],
+}
+
+/// Iterator over the contributing source identifiers of an RTP packet.
+#[derive(Clone)]
+pub struct Csrcs<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Csrcs<'a>);
+
+impl<'a> Iterator for Csrcs<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (csrc, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(read_offset!(csrc, 0, u32, from_be))
+    }
+}
+
+impl<'a> fmt::Display for RtpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RTP pt={} seq={} ts={} ssrc={:#010x}",
+            self.payload_type(),
+            self.sequence_number(),
+            self.timestamp(),
+            self.ssrc()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! rtp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutRtpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    rtp_setget_test!(version, set_version, 2, 0, [0x80]);
+    rtp_setget_test!(has_padding, set_padding, true, 0, [0x20]);
+    rtp_setget_test!(has_extension, set_extension, true, 0, [0x10]);
+    rtp_setget_test!(csrc_count, set_csrc_count, 0x0f, 0, [0x0f]);
+    rtp_setget_test!(marker, set_marker, true, 1, [0x80]);
+    rtp_setget_test!(payload_type, set_payload_type, 0x7f, 1, [0x7f]);
+    rtp_setget_test!(sequence_number, set_sequence_number, 0xabcd, 2, [0xab, 0xcd]);
+    rtp_setget_test!(timestamp, set_timestamp, 0xabcdef01, 4, [0xab, 0xcd, 0xef, 0x01]);
+    rtp_setget_test!(ssrc, set_ssrc, 0xabcdef01, 8, [0xab, 0xcd, 0xef, 0x01]);
+
+    #[test]
+    fn csrcs_and_extension() {
+        let mut data = [0; 12 + 8 + 8 + 3 + 2];
+        {
+            let mut testee = MutRtpPacket::new(&mut data).unwrap();
+            testee.set_version(2);
+            assert!(testee.set_csrcs(&[0x1111_1111, 0x2222_2222]));
+            assert!(testee.set_header_extension(0xbede, &[0x10, 0xab, 0, 0]));
+            testee.set_padding(true);
+            testee.payload()[..3].copy_from_slice(b"abc");
+        }
+        data[32] = 2;
+        assert_eq!(0xb2, data[0]);
+        assert_eq!(&[0xbe, 0xde, 0, 1, 0x10, 0xab], &data[20..26]);
+
+        let testee = RtpPacket::new(&data).unwrap();
+        assert_eq!(28, testee.header_len());
+        assert_eq!(vec![0x1111_1111, 0x2222_2222], testee.csrcs().collect::<Vec<_>>());
+        let extension = testee.extension().unwrap();
+        assert_eq!(0xbede, extension.profile);
+        assert_eq!(&[0x10, 0xab, 0, 0], extension.data);
+        assert_eq!(2, testee.padding_len());
+        assert_eq!(b"abc", testee.media_payload());
+    }
+
+    #[test]
+    fn csrcs_and_extension_rejected() {
+        let mut data = [0; 12 + 8];
+        let mut testee = MutRtpPacket::new(&mut data).unwrap();
+        assert!(!testee.set_csrcs(&[0; 16]));
+        assert!(!testee.set_csrcs(&[0; 3]));
+        assert!(testee.set_csrcs(&[0; 1]));
+        assert!(!testee.set_csrc(2, 1));
+        assert!(!testee.set_csrc(usize::MAX / 4, 1));
+        assert!(!testee.set_csrc(usize::MAX, 1));
+        assert!(!testee.set_header_extension(0xbede, &[0; 3]));
+        assert!(!testee.set_header_extension(0xbede, &[0; 4]));
+        assert!(testee.set_header_extension(0xbede, &[]));
+        assert_eq!(1, testee.as_immutable().csrc_count());
+    }
+
+    #[test]
+    fn truncated() {
+        let data = [0x9f, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5];
+        let testee = RtpPacket::new(&data).unwrap();
+        assert_eq!(17, testee.header_len());
+        assert_eq!(vec![0x0102_0304], testee.csrcs().collect::<Vec<_>>());
+        assert!(testee.extension().is_none());
+        assert_eq!(0, testee.padding_len());
+        assert_eq!(0, testee.media_payload().len());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        RtpPacketBuf => |packet| packet.version() == 2 && packet.csrcs().next().is_none()
+    );
+}
@@ -0,0 +1,617 @@
+use std::cmp;
+use std::fmt;
+
+/// Implements the type and length fields common to all SCTP chunks for a pair of chunk types.
+macro_rules! sctp_chunk_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn chunk_type(&self) -> ChunkType {
+                ChunkType(read_offset!(self.0, 0, u8))
+            }
+
+            /// Returns the length of the chunk in bytes, including the chunk header but not any
+            /// trailing padding.
+            pub fn length(&self) -> u16 {
+                read_offset!(self.0, 2, u16, from_be)
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_chunk_type(&mut self, chunk_type: ChunkType) {
+                write_offset!(self.0, 0, chunk_type.value(), u8);
+            }
+
+            pub fn set_length(&mut self, length: u16) {
+                write_offset!(self.0, 2, length, u16, to_be);
+            }
+        );
+    }
+}
+
+/// Initializes the chunk header in `data` with a length covering all of `data`, and with
+/// `chunk_type` if given.
+fn init_chunk(data: &mut [u8], chunk_type: Option<ChunkType>) {
+    if let Some(chunk_type) = chunk_type {
+        write_offset!(data, 0, chunk_type.value(), u8);
+    }
+    write_offset!(data, 2, data.len() as u16, u16, to_be);
+}
+
+packet!(
+    /// Any SCTP chunk. Views for specific chunk types can be created from `data()`.
+    ChunkPacket,
+    MutChunkPacket,
+    ChunkPacketBuf,
+    4,
+    |_data| 4,
+    init |data| init_chunk(data, None)
+);
+sctp_chunk_common!(ChunkPacket, MutChunkPacket);
+
+getters!(ChunkPacket
+    pub fn flags(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+);
+
+setters!(MutChunkPacket
+    pub fn set_flags(&mut self, flags: u8) {
+        write_offset!(self.0, 1, flags, u8);
+    }
+);
+
+
+packet!(
+    /// A DATA chunk. The payload is the user data.
+    DataChunkPacket,
+    MutDataChunkPacket,
+    DataChunkPacketBuf,
+    16,
+    |_data| 16,
+    init |data| init_chunk(data, Some(ChunkType::DATA))
+);
+sctp_chunk_common!(DataChunkPacket, MutDataChunkPacket);
+
+getters!(DataChunkPacket
+    pub fn flags(&self) -> DataChunkFlags {
+        DataChunkFlags::from_bits_truncate(read_offset!(self.0, 1, u8))
+    }
+
+    /// Returns the Transmission Sequence Number.
+    pub fn tsn(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    pub fn stream_identifier(&self) -> u16 {
+        read_offset!(self.0, 8, u16, from_be)
+    }
+
+    pub fn stream_sequence_number(&self) -> u16 {
+        read_offset!(self.0, 10, u16, from_be)
+    }
+
+    pub fn payload_protocol_identifier(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+);
+
+setters!(MutDataChunkPacket
+    pub fn set_flags(&mut self, flags: DataChunkFlags) {
+        write_offset!(self.0, 1, flags.bits(), u8);
+    }
+
+    pub fn set_tsn(&mut self, tsn: u32) {
+        write_offset!(self.0, 4, tsn, u32, to_be);
+    }
+
+    pub fn set_stream_identifier(&mut self, stream_identifier: u16) {
+        write_offset!(self.0, 8, stream_identifier, u16, to_be);
+    }
+
+    pub fn set_stream_sequence_number(&mut self, stream_sequence_number: u16) {
+        write_offset!(self.0, 10, stream_sequence_number, u16, to_be);
+    }
+
+    pub fn set_payload_protocol_identifier(&mut self, payload_protocol_identifier: u32) {
+        write_offset!(self.0, 12, payload_protocol_identifier, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// An INIT or INIT ACK chunk. The two share the same fixed part and are told apart by
+    /// `chunk_type`. The payload holds the optional and variable length parameters.
+    InitChunkPacket,
+    MutInitChunkPacket,
+    InitChunkPacketBuf,
+    20,
+    |_data| 20,
+    init |data| init_chunk(data, Some(ChunkType::INIT))
+);
+sctp_chunk_common!(InitChunkPacket, MutInitChunkPacket);
+
+getters!(InitChunkPacket
+    pub fn initiate_tag(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the Advertised Receiver Window Credit.
+    pub fn a_rwnd(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    pub fn outbound_streams(&self) -> u16 {
+        read_offset!(self.0, 12, u16, from_be)
+    }
+
+    pub fn inbound_streams(&self) -> u16 {
+        read_offset!(self.0, 14, u16, from_be)
+    }
+
+    pub fn initial_tsn(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+);
+
+setters!(MutInitChunkPacket
+    pub fn set_initiate_tag(&mut self, initiate_tag: u32) {
+        write_offset!(self.0, 4, initiate_tag, u32, to_be);
+    }
+
+    pub fn set_a_rwnd(&mut self, a_rwnd: u32) {
+        write_offset!(self.0, 8, a_rwnd, u32, to_be);
+    }
+
+    pub fn set_outbound_streams(&mut self, outbound_streams: u16) {
+        write_offset!(self.0, 12, outbound_streams, u16, to_be);
+    }
+
+    pub fn set_inbound_streams(&mut self, inbound_streams: u16) {
+        write_offset!(self.0, 14, inbound_streams, u16, to_be);
+    }
+
+    pub fn set_initial_tsn(&mut self, initial_tsn: u32) {
+        write_offset!(self.0, 16, initial_tsn, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A SACK chunk. The header includes the gap ack blocks and duplicate TSNs.
+    SackChunkPacket,
+    MutSackChunkPacket,
+    SackChunkPacketBuf,
+    16,
+    |data| {
+        let blocks = usize::from(read_offset!(data, 12, u16, from_be));
+        let duplicates = usize::from(read_offset!(data, 14, u16, from_be));
+        16 + (blocks + duplicates) * 4
+    },
+    init |data| {
+        init_chunk(data, Some(ChunkType::SACK));
+        write_offset!(data, 12, [0; 4], [u8; 4]);
+    }
+);
+sctp_chunk_common!(SackChunkPacket, MutSackChunkPacket);
+
+getters!(SackChunkPacket
+    pub fn cumulative_tsn_ack(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the Advertised Receiver Window Credit.
+    pub fn a_rwnd(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    pub fn number_of_gap_ack_blocks(&self) -> u16 {
+        read_offset!(self.0, 12, u16, from_be)
+    }
+
+    pub fn number_of_duplicate_tsns(&self) -> u16 {
+        read_offset!(self.0, 14, u16, from_be)
+    }
+
+    /// Returns an iterator over the gap ack blocks, as start and end offsets relative to the
+    /// cumulative TSN ack. Only the blocks that fit in the backing slice are included.
+    pub fn gap_ack_blocks(&self) -> GapAckBlocks<'a> {
+        let end = 16 + usize::from(self.number_of_gap_ack_blocks()) * 4;
+        GapAckBlocks { data: &self.0[16..cmp::min(end, self.header_len())] }
+    }
+
+    /// Returns an iterator over the duplicate TSNs. Only the TSNs that fit in the backing slice
+    /// are included.
+    pub fn duplicate_tsns(&self) -> DuplicateTsns<'a> {
+        let start = 16 + usize::from(self.number_of_gap_ack_blocks()) * 4;
+        let start = cmp::min(start, self.header_len());
+        DuplicateTsns { data: &self.0[start..self.header_len()] }
+    }
+);
+
+setters!(MutSackChunkPacket
+    pub fn set_cumulative_tsn_ack(&mut self, cumulative_tsn_ack: u32) {
+        write_offset!(self.0, 4, cumulative_tsn_ack, u32, to_be);
+    }
+
+    pub fn set_a_rwnd(&mut self, a_rwnd: u32) {
+        write_offset!(self.0, 8, a_rwnd, u32, to_be);
+    }
+
+    pub fn set_number_of_gap_ack_blocks(&mut self, number_of_gap_ack_blocks: u16) {
+        write_offset!(self.0, 12, number_of_gap_ack_blocks, u16, to_be);
+    }
+
+    pub fn set_number_of_duplicate_tsns(&mut self, number_of_duplicate_tsns: u16) {
+        write_offset!(self.0, 14, number_of_duplicate_tsns, u16, to_be);
+    }
+);
+
+impl<'a> MutSackChunkPacket<'a> {
+    /// Writes the gap ack block at `index`, given as start and end offsets relative to the
+    /// cumulative TSN ack. Returns false if the backing slice is too short to hold the block.
+    pub fn set_gap_ack_block(&mut self, index: u16, (start, end): (u16, u16)) -> bool {
+        let offset = 16 + usize::from(index) * 4;
+        offset + 4 <= self.0.len() && {
+            write_offset!(self.0, offset, start, u16, to_be);
+            write_offset!(self.0, offset + 2, end, u16, to_be);
+            true
+        }
+    }
+
+    /// Writes the duplicate TSN at `index`. Duplicate TSNs are located after the number of gap
+    /// ack blocks given in the header, so that must be set first. Returns false if the backing
+    /// slice is too short to hold the TSN.
+    pub fn set_duplicate_tsn(&mut self, index: u16, tsn: u32) -> bool {
+        let blocks = usize::from(read_offset!(self.0, 12, u16, from_be));
+        let offset = 16 + (blocks + usize::from(index)) * 4;
+        offset + 4 <= self.0.len() && {
+            write_offset!(self.0, offset, tsn, u32, to_be);
+            true
+        }
+    }
+}
+
+
+packet!(
+    /// A HEARTBEAT or HEARTBEAT ACK chunk. The two have the same layout and are told apart by
+    /// `chunk_type`. The payload is the Heartbeat Info parameter.
+    HeartbeatChunkPacket,
+    MutHeartbeatChunkPacket,
+    HeartbeatChunkPacketBuf,
+    4,
+    |_data| 4,
+    init |data| init_chunk(data, Some(ChunkType::HEARTBEAT))
+);
+sctp_chunk_common!(HeartbeatChunkPacket, MutHeartbeatChunkPacket);
+
+getters!(HeartbeatChunkPacket
+    /// Returns the sender specific heartbeat information, meaning the value of the Heartbeat
+    /// Info parameter. Returns `None` if the parameter is missing or truncated.
+    pub fn heartbeat_info(&self) -> Option<&'a [u8]> {
+        let parameter = &self.0[4..];
+        if parameter.len() < 4 {
+            return None;
+        }
+        let len = usize::from(read_offset!(parameter, 2, u16, from_be));
+        if len < 4 || len > parameter.len() {
+            return None;
+        }
+        Some(&parameter[4..len])
+    }
+);
+
+
+bitflags! {
+    /// Bitmasks for the flags field of a DATA chunk.
+    pub struct DataChunkFlags: u8 {
+        /// The chunk belongs to a message that does not need to be delivered in order.
+        const UNORDERED = 0b0000_0100;
+        /// The chunk is the first fragment of a message.
+        const BEGINNING = 0b0000_0010;
+        /// The chunk is the last fragment of a message.
+        const ENDING = 0b0000_0001;
+    }
+}
+
+
+/// The 8 bit chunk type field of an SCTP chunk. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/sctp-parameters/sctp-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ChunkType(pub u8);
+
+impl ChunkType {
+    pub const DATA: ChunkType = ChunkType(0);
+    pub const INIT: ChunkType = ChunkType(1);
+    pub const INIT_ACK: ChunkType = ChunkType(2);
+    pub const SACK: ChunkType = ChunkType(3);
+    pub const HEARTBEAT: ChunkType = ChunkType(4);
+    pub const HEARTBEAT_ACK: ChunkType = ChunkType(5);
+    pub const ABORT: ChunkType = ChunkType(6);
+    pub const SHUTDOWN: ChunkType = ChunkType(7);
+    pub const SHUTDOWN_ACK: ChunkType = ChunkType(8);
+    pub const ERROR: ChunkType = ChunkType(9);
+    pub const COOKIE_ECHO: ChunkType = ChunkType(10);
+    pub const COOKIE_ACK: ChunkType = ChunkType(11);
+    pub const SHUTDOWN_COMPLETE: ChunkType = ChunkType(14);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+/// Iterator over the chunks in an SCTP packet. Each chunk is trimmed to its length field, so
+/// padding is not included. Iteration stops at the first chunk with an invalid length or that
+/// does not fit in the data.
+#[derive(Clone)]
+pub struct Chunks<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Chunks<'a>);
+
+impl<'a> Chunks<'a> {
+    /// Creates an iterator over the chunks encoded in `data`.
+    pub fn new(data: &'a [u8]) -> Chunks<'a> {
+        Chunks { data }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = ChunkPacket<'a>;
+
+    fn next(&mut self) -> Option<ChunkPacket<'a>> {
+        let len = match self.data.get(2..4) {
+            Some(len) => usize::from(read_offset!(len, 0, u16, from_be)),
+            None => return None,
+        };
+        if len < ChunkPacket::MIN_LEN || len > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let chunk = &self.data[..len];
+        let padded_len = cmp::min(len.div_ceil(4) * 4, self.data.len());
+        self.data = &self.data[padded_len..];
+        Some(ChunkPacket(chunk))
+    }
+}
+
+/// Iterator over the gap ack blocks of a SACK chunk.
+#[derive(Clone)]
+pub struct GapAckBlocks<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(GapAckBlocks<'a>);
+
+impl<'a> Iterator for GapAckBlocks<'a> {
+    type Item = (u16, u16);
+
+    fn next(&mut self) -> Option<(u16, u16)> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (block, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some((read_offset!(block, 0, u16, from_be), read_offset!(block, 2, u16, from_be)))
+    }
+}
+
+/// Iterator over the duplicate TSNs of a SACK chunk.
+#[derive(Clone)]
+pub struct DuplicateTsns<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(DuplicateTsns<'a>);
+
+impl<'a> Iterator for DuplicateTsns<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (tsn, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(read_offset!(tsn, 0, u32, from_be))
+    }
+}
+
+impl<'a> fmt::Display for ChunkPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SCTP chunk type={} len={}",
+            self.chunk_type().value(),
+            self.length()
+        )
+    }
+}
+
+impl<'a> fmt::Display for DataChunkPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SCTP DATA tsn={} stream={} ssn={} ppid={}",
+            self.tsn(),
+            self.stream_identifier(),
+            self.stream_sequence_number(),
+            self.payload_protocol_identifier()
+        )
+    }
+}
+
+impl<'a> fmt::Display for InitChunkPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SCTP INIT type={} tag={:#010x} tsn={} streams={}/{}",
+            self.chunk_type().value(),
+            self.initiate_tag(),
+            self.initial_tsn(),
+            self.outbound_streams(),
+            self.inbound_streams()
+        )
+    }
+}
+
+impl<'a> fmt::Display for SackChunkPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SCTP SACK cum_tsn={} a_rwnd={} gaps={} dups={}",
+            self.cumulative_tsn_ack(),
+            self.a_rwnd(),
+            self.number_of_gap_ack_blocks(),
+            self.number_of_duplicate_tsns()
+        )
+    }
+}
+
+impl<'a> fmt::Display for HeartbeatChunkPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SCTP heartbeat type={} len={}",
+            self.chunk_type().value(),
+            self.length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sctp::SctpPacket;
+
+    mod data {
+        use super::super::*;
+
+        macro_rules! data_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutDataChunkPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        data_setget_test!(chunk_type, set_chunk_type, ChunkType::DATA, 0, [0]);
+        data_setget_test!(flags, set_flags, DataChunkFlags::all(), 1, [0x07]);
+        data_setget_test!(length, set_length, 0xabcd, 2, [0xab, 0xcd]);
+        data_setget_test!(tsn, set_tsn, 0xdeadbeef, 4, [0xde, 0xad, 0xbe, 0xef]);
+        data_setget_test!(stream_identifier, set_stream_identifier, 0xabcd, 8, [0xab, 0xcd]);
+        data_setget_test!(
+            stream_sequence_number,
+            set_stream_sequence_number,
+            0xabcd,
+            10,
+            [0xab, 0xcd]
+        );
+        data_setget_test!(
+            payload_protocol_identifier,
+            set_payload_protocol_identifier,
+            0xdeadbeef,
+            12,
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+    }
+
+    mod init {
+        use super::super::*;
+
+        macro_rules! init_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutInitChunkPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        init_setget_test!(initiate_tag, set_initiate_tag, 0xdeadbeef, 4, [0xde, 0xad, 0xbe, 0xef]);
+        init_setget_test!(a_rwnd, set_a_rwnd, 0xdeadbeef, 8, [0xde, 0xad, 0xbe, 0xef]);
+        init_setget_test!(outbound_streams, set_outbound_streams, 0xabcd, 12, [0xab, 0xcd]);
+        init_setget_test!(inbound_streams, set_inbound_streams, 0xabcd, 14, [0xab, 0xcd]);
+        init_setget_test!(initial_tsn, set_initial_tsn, 0xdeadbeef, 16, [0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn iterate_chunks() {
+        let data = [
+            0x00, 0x00, 0x00, 0x00, // Common header
+            0x00, 0x00, 0x00, 0x00, //
+            0x00, 0x00, 0x00, 0x00, //
+            0x00, 0x03, 0x00, 0x11, // DATA, unordered is not set, 17 bytes
+            0x00, 0x00, 0x00, 0x01, //
+            0x00, 0x02, 0x00, 0x03, //
+            0x00, 0x00, 0x00, 0x00, //
+            0xab, 0x00, 0x00, 0x00, // One byte of user data and padding
+            0x0b, 0x00, 0x00, 0x04, // COOKIE ACK
+        ];
+        let packet = SctpPacket::new(&data).unwrap();
+        let chunks = packet.chunks().collect::<Vec<_>>();
+        assert_eq!(2, chunks.len());
+        assert_eq!(ChunkType::DATA, chunks[0].chunk_type());
+        assert_eq!(ChunkType::COOKIE_ACK, chunks[1].chunk_type());
+
+        let data_chunk = DataChunkPacket::new(chunks[0].data()).unwrap();
+        assert_eq!(
+            DataChunkFlags::BEGINNING | DataChunkFlags::ENDING,
+            data_chunk.flags()
+        );
+        assert_eq!(1, data_chunk.tsn());
+        assert_eq!(2, data_chunk.stream_identifier());
+        assert_eq!(3, data_chunk.stream_sequence_number());
+        assert_eq!(&[0xab], data_chunk.payload());
+    }
+
+    #[test]
+    fn invalid_chunk_length() {
+        assert_eq!(0, Chunks::new(&[0x0b, 0x00, 0x00, 0x03]).count());
+        assert_eq!(0, Chunks::new(&[0x0b, 0x00, 0x00, 0x08, 0x00]).count());
+        assert_eq!(0, Chunks::new(&[0x0b, 0x00]).count());
+    }
+
+    #[test]
+    fn sack_blocks_and_duplicates() {
+        let mut data = [0; 16 + 2 * 4 + 4];
+        {
+            let mut testee = MutSackChunkPacket::new(&mut data).unwrap();
+            testee.set_chunk_type(ChunkType::SACK);
+            testee.set_length(28);
+            testee.set_cumulative_tsn_ack(100);
+            testee.set_number_of_gap_ack_blocks(2);
+            testee.set_number_of_duplicate_tsns(1);
+            assert!(testee.set_gap_ack_block(0, (2, 3)));
+            assert!(testee.set_gap_ack_block(1, (5, 8)));
+            assert!(testee.set_duplicate_tsn(0, 99));
+            assert!(!testee.set_duplicate_tsn(1, 99));
+        }
+        let testee = SackChunkPacket::new(&data).unwrap();
+        assert_eq!(28, testee.header_len());
+        assert_eq!(100, testee.cumulative_tsn_ack());
+        assert_eq!(vec![(2, 3), (5, 8)], testee.gap_ack_blocks().collect::<Vec<_>>());
+        assert_eq!(vec![99], testee.duplicate_tsns().collect::<Vec<_>>());
+
+        let truncated = SackChunkPacket::new(&data[..22]).unwrap();
+        assert_eq!(1, truncated.gap_ack_blocks().count());
+        assert_eq!(0, truncated.duplicate_tsns().count());
+    }
+
+    #[test]
+    fn heartbeat_info() {
+        let data = [0x04, 0x00, 0x00, 0x0a, 0x00, 0x01, 0x00, 0x06, 0xab, 0xcd];
+        let testee = HeartbeatChunkPacket::new(&data).unwrap();
+        assert_eq!(Some(&[0xab, 0xcd][..]), testee.heartbeat_info());
+        assert_eq!(None, HeartbeatChunkPacket::new(&data[..6]).unwrap().heartbeat_info());
+        assert_eq!(None, HeartbeatChunkPacket::new(&data[..9]).unwrap().heartbeat_info());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        ChunkPacketBuf => |packet| usize::from(packet.length()) == packet.data().len(),
+        DataChunkPacketBuf => |packet| packet.chunk_type() == ChunkType::DATA,
+        InitChunkPacketBuf => |packet| packet.chunk_type() == ChunkType::INIT,
+        SackChunkPacketBuf => |packet| packet.gap_ack_blocks().next().is_none(),
+        HeartbeatChunkPacketBuf => |packet| packet.chunk_type() == ChunkType::HEARTBEAT
+    );
+}
@@ -0,0 +1,209 @@
+//! Stream Control Transmission Protocol, RFC 4960.
+
+use std::fmt;
+
+mod chunks;
+pub use self::chunks::*;
+
+packet!(
+    SctpPacket,
+    MutSctpPacket,
+    SctpPacketBuf,
+    12,
+    |_data| 12,
+    init |data| {
+        let checksum = SctpPacket(data).calculate_checksum();
+        MutSctpPacket(data).set_checksum(checksum);
+    }
+);
+
+getters!(SctpPacket
+    pub fn source_port(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    pub fn destination_port(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    pub fn verification_tag(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the checksum field, read as a big endian integer like every other field. Note
+    /// that the CRC32c is transmitted least significant byte first, see `calculate_checksum`.
+    pub fn checksum(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns an iterator over the chunks following the common header.
+    pub fn chunks(&self) -> Chunks<'a> {
+        Chunks::new(&self.0[Self::MIN_LEN..])
+    }
+);
+
+impl<'a> SctpPacket<'a> {
+    /// Computes the CRC32c checksum of the entire backing slice, treating the checksum field as
+    /// zero. The result is in the same representation as `checksum`, so it can be compared
+    /// against it or passed directly to `set_checksum`.
+    pub fn calculate_checksum(&self) -> u32 {
+        let mut crc = Crc32c::new();
+        crc.add_bytes(&self.0[..8]);
+        crc.add_bytes(&[0; 4]);
+        crc.add_bytes(&self.0[12..]);
+        crc.finish().swap_bytes()
+    }
+}
+
+setters!(MutSctpPacket
+    pub fn set_source_port(&mut self, source_port: u16) {
+        write_offset!(self.0, 0, source_port, u16, to_be);
+    }
+
+    pub fn set_destination_port(&mut self, destination_port: u16) {
+        write_offset!(self.0, 2, destination_port, u16, to_be);
+    }
+
+    pub fn set_verification_tag(&mut self, verification_tag: u32) {
+        write_offset!(self.0, 4, verification_tag, u32, to_be);
+    }
+
+    pub fn set_checksum(&mut self, checksum: u32) {
+        write_offset!(self.0, 8, checksum, u32, to_be);
+    }
+);
+
+
+/// Incremental CRC32c (Castagnoli) calculation, the checksum used by SCTP.
+#[derive(Debug, Copy, Clone)]
+pub struct Crc32c {
+    crc: u32,
+}
+
+impl Crc32c {
+    pub fn new() -> Crc32c {
+        Crc32c { crc: !0 }
+    }
+
+    pub fn add_bytes(&mut self, data: &[u8]) {
+        for &byte in data {
+            let index = usize::from((self.crc as u8) ^ byte);
+            self.crc = CRC32C_TABLE[index] ^ (self.crc >> 8);
+        }
+    }
+
+    /// Returns the final CRC of everything added so far.
+    pub fn finish(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl Default for Crc32c {
+    fn default() -> Crc32c {
+        Crc32c::new()
+    }
+}
+
+/// Returns the CRC32c of `data`.
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = Crc32c::new();
+    crc.add_bytes(data);
+    crc.finish()
+}
+
+/// The reversed Castagnoli polynomial.
+const CRC32C_POLYNOMIAL: u32 = 0x82f6_3b78;
+
+static CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ CRC32C_POLYNOMIAL } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+impl<'a> fmt::Display for SctpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SCTP {} -> {} tag={:#010x} chunks={}",
+            self.source_port(),
+            self.destination_port(),
+            self.verification_tag(),
+            self.chunks().count()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! sctp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutSctpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    sctp_setget_test!(source_port, set_source_port, 0xabcd, 0, [0xab, 0xcd]);
+    sctp_setget_test!(destination_port, set_destination_port, 0xabcd, 2, [0xab, 0xcd]);
+    sctp_setget_test!(
+        verification_tag,
+        set_verification_tag,
+        0xdeadbeef,
+        4,
+        [0xde, 0xad, 0xbe, 0xef]
+    );
+    sctp_setget_test!(checksum, set_checksum, 0xdeadbeef, 8, [0xde, 0xad, 0xbe, 0xef]);
+
+    #[test]
+    fn crc32c_check_value() {
+        assert_eq!(0xe306_9283, crc32c(b"123456789"));
+        assert_eq!(0, crc32c(&[]));
+    }
+
+    #[test]
+    fn crc32c_incremental() {
+        let mut crc = Crc32c::new();
+        crc.add_bytes(b"1234");
+        crc.add_bytes(b"56789");
+        assert_eq!(crc32c(b"123456789"), crc.finish());
+    }
+
+    #[test]
+    fn checksum_roundtrip() {
+        let mut data = [0; 12 + 4];
+        {
+            let mut testee = MutSctpPacket::new(&mut data).unwrap();
+            testee.set_source_port(5000);
+            testee.set_destination_port(5001);
+            testee.set_verification_tag(0x01020304);
+            testee.set_checksum(0xffff_ffff);
+            let checksum = testee.as_immutable().calculate_checksum();
+            testee.set_checksum(checksum);
+        }
+        // The CRC is stored least significant byte first
+        let mut zeroed = data;
+        zeroed[8..12].copy_from_slice(&[0; 4]);
+        assert_eq!(&crc32c(&zeroed).to_le_bytes(), &data[8..12]);
+
+        let testee = SctpPacket::new(&data).unwrap();
+        assert_eq!(testee.checksum(), testee.calculate_checksum());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        SctpPacketBuf => |packet| packet.calculate_checksum() == packet.checksum()
+    );
+}
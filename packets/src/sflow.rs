@@ -0,0 +1,1106 @@
+//! sFlow version 5 datagrams, as specified at sflow.org. An sFlow agent exports datagrams to a
+//! collector, each holding a number of samples. Flow samples describe sampled packets and
+//! counter samples hold interface statistics, both as a list of records in turn.
+//!
+//! `SflowPacket::samples` iterates over the samples of a datagram, and the conversion methods
+//! on `Record` give views of the known sample and record formats.
+
+use std::cmp;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use ethernet::EthernetPacket;
+use types::*;
+
+/// The UDP port collectors listen on for sFlow datagrams.
+pub const UDP_PORT: u16 = 6343;
+
+packet!(
+    /// An sFlow datagram. The header is 28 bytes when the agent address is IPv4 and 40 bytes
+    /// when it is IPv6. The payload holds the samples.
+    SflowPacket,
+    MutSflowPacket,
+    SflowPacketBuf,
+    28,
+    |data| 24 + agent_address_len(data),
+    init |data| {
+        write_offset!(data, 0, 5u32, u32, to_be);
+        write_offset!(data, 4, AddressType::IPV4.value(), u32, to_be);
+    }
+);
+
+getters!(SflowPacket
+    /// Returns the datagram version, 5 for the format implemented here.
+    pub fn version(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    pub fn agent_address_type(&self) -> AddressType {
+        AddressType(read_offset!(self.0, 4, u32, from_be))
+    }
+
+    /// Returns the address of the agent sending the datagram, or `None` if the address type
+    /// is unknown or the datagram is too short to hold the address.
+    pub fn agent_address(&self) -> Option<IpAddr> {
+        match self.agent_address_type() {
+            AddressType::IPV4 => {
+                Some(IpAddr::V4(Ipv4Addr::from(read_offset!(self.0, 8, [u8; 4]))))
+            }
+            AddressType::IPV6 if self.0.len() >= 40 => {
+                Some(IpAddr::V6(Ipv6Addr::from(read_offset!(self.0, 8, [u8; 16]))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the ID distinguishing several sub-agents on the same agent.
+    pub fn sub_agent_id(&self) -> u32 {
+        read_offset!(self.0, self.header_len() - 16, u32, from_be)
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, self.header_len() - 12, u32, from_be)
+    }
+
+    /// Returns the time since the agent booted, in milliseconds.
+    pub fn uptime(&self) -> u32 {
+        read_offset!(self.0, self.header_len() - 8, u32, from_be)
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        read_offset!(self.0, self.header_len() - 4, u32, from_be)
+    }
+
+    /// Returns an iterator over the samples in the datagram.
+    pub fn samples(&self) -> Records<'a> {
+        Records::new(&self.0[self.header_len()..], self.sample_count())
+    }
+);
+
+setters!(MutSflowPacket
+    pub fn set_version(&mut self, version: u32) {
+        write_offset!(self.0, 0, version, u32, to_be);
+    }
+
+    pub fn set_sub_agent_id(&mut self, sub_agent_id: u32) {
+        let offset = self.header_len() - 16;
+        write_offset!(self.0, offset, sub_agent_id, u32, to_be);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        let offset = self.header_len() - 12;
+        write_offset!(self.0, offset, sequence_number, u32, to_be);
+    }
+
+    pub fn set_uptime(&mut self, uptime: u32) {
+        let offset = self.header_len() - 8;
+        write_offset!(self.0, offset, uptime, u32, to_be);
+    }
+
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        let offset = self.header_len() - 4;
+        write_offset!(self.0, offset, sample_count, u32, to_be);
+    }
+);
+
+impl<'a> MutSflowPacket<'a> {
+    /// Sets the agent address and its type. The length of the header depends on the address
+    /// type, so set the address before the fields after it. Returns false, leaving the packet
+    /// unchanged, if `agent_address` is an IPv6 address and the packet is shorter than 40 bytes.
+    pub fn set_agent_address(&mut self, agent_address: IpAddr) -> bool {
+        match agent_address {
+            IpAddr::V4(address) => {
+                write_offset!(self.0, 4, AddressType::IPV4.value(), u32, to_be);
+                write_offset!(self.0, 8, address.octets(), [u8; 4]);
+            }
+            IpAddr::V6(address) => {
+                if self.0.len() < 40 {
+                    return false;
+                }
+                write_offset!(self.0, 4, AddressType::IPV6.value(), u32, to_be);
+                write_offset!(self.0, 8, address.octets(), [u8; 16]);
+            }
+        }
+        true
+    }
+}
+
+/// Returns the length of the agent address, 16 bytes for IPv6 and 4 bytes for anything else.
+fn agent_address_len(data: &[u8]) -> usize {
+    if AddressType(read_offset!(data, 4, u32, from_be)) == AddressType::IPV6 {
+        16
+    } else {
+        4
+    }
+}
+
+
+/// The type of an address in sFlow structures.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AddressType(pub u32);
+
+impl AddressType {
+    pub const UNKNOWN: AddressType = AddressType(0);
+    pub const IPV4: AddressType = AddressType(1);
+    pub const IPV6: AddressType = AddressType(2);
+
+    #[inline]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The format of a sample or record. The upper 20 bits are an enterprise number and the lower
+/// 12 bits a format number defined by that enterprise. The standard formats have enterprise
+/// zero. Sample and record formats are numbered separately, as are flow and counter records.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DataFormat(pub u32);
+
+impl DataFormat {
+    pub const FLOW_SAMPLE: DataFormat = DataFormat(1);
+    pub const COUNTER_SAMPLE: DataFormat = DataFormat(2);
+    pub const EXPANDED_FLOW_SAMPLE: DataFormat = DataFormat(3);
+    pub const EXPANDED_COUNTER_SAMPLE: DataFormat = DataFormat(4);
+
+    /// The raw packet header flow record.
+    pub const RAW_PACKET_HEADER: DataFormat = DataFormat(1);
+
+    /// The generic interface counter record.
+    pub const GENERIC_INTERFACE_COUNTERS: DataFormat = DataFormat(1);
+    /// The Ethernet interface counter record.
+    pub const ETHERNET_INTERFACE_COUNTERS: DataFormat = DataFormat(2);
+
+    /// Creates a format from an enterprise number and a format number. The upper 12 bits of
+    /// `enterprise` and the upper 4 bits of `format` are ignored.
+    pub fn new(enterprise: u32, format: u16) -> DataFormat {
+        DataFormat(((enterprise & 0x000f_ffff) << 12) | u32::from(format & 0x0fff))
+    }
+
+    #[inline]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+
+    pub fn enterprise(&self) -> u32 {
+        self.0 >> 12
+    }
+
+    pub fn format(&self) -> u16 {
+        (self.0 & 0x0fff) as u16
+    }
+}
+
+/// The protocol of the first layer of a sampled packet header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct HeaderProtocol(pub u32);
+
+impl HeaderProtocol {
+    pub const ETHERNET: HeaderProtocol = HeaderProtocol(1);
+    pub const PPP: HeaderProtocol = HeaderProtocol(7);
+    pub const IPV4: HeaderProtocol = HeaderProtocol(11);
+    pub const IPV6: HeaderProtocol = HeaderProtocol(12);
+    pub const MPLS: HeaderProtocol = HeaderProtocol(13);
+
+    #[inline]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+
+/// A sample in a datagram, or a record in a sample. Both are encoded as a format, a length
+/// and the data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Record<'a> {
+    pub format: DataFormat,
+    pub data: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    /// Returns the sample as a flow sample, or `None` if it is of another format or too short.
+    pub fn flow_sample(&self) -> Option<FlowSamplePacket<'a>> {
+        self.as_format(DataFormat::FLOW_SAMPLE).and_then(FlowSamplePacket::new)
+    }
+
+    /// Returns the sample as a counter sample, or `None` if it is of another format or too
+    /// short.
+    pub fn counter_sample(&self) -> Option<CounterSamplePacket<'a>> {
+        self.as_format(DataFormat::COUNTER_SAMPLE).and_then(CounterSamplePacket::new)
+    }
+
+    /// Returns the sample as an expanded flow sample, or `None` if it is of another format or
+    /// too short.
+    pub fn expanded_flow_sample(&self) -> Option<ExpandedFlowSamplePacket<'a>> {
+        self.as_format(DataFormat::EXPANDED_FLOW_SAMPLE).and_then(ExpandedFlowSamplePacket::new)
+    }
+
+    /// Returns the sample as an expanded counter sample, or `None` if it is of another format
+    /// or too short.
+    pub fn expanded_counter_sample(&self) -> Option<ExpandedCounterSamplePacket<'a>> {
+        self.as_format(DataFormat::EXPANDED_COUNTER_SAMPLE)
+            .and_then(ExpandedCounterSamplePacket::new)
+    }
+
+    /// Returns the flow record as a raw packet header, or `None` if it is of another format or
+    /// too short. Only meaningful for records of a flow sample.
+    pub fn raw_packet_header(&self) -> Option<RawPacketHeaderPacket<'a>> {
+        self.as_format(DataFormat::RAW_PACKET_HEADER).and_then(RawPacketHeaderPacket::new)
+    }
+
+    /// Returns the counter record as generic interface counters, or `None` if it is of another
+    /// format or too short. Only meaningful for records of a counter sample.
+    pub fn generic_interface_counters(&self) -> Option<GenericInterfaceCountersPacket<'a>> {
+        self.as_format(DataFormat::GENERIC_INTERFACE_COUNTERS)
+            .and_then(GenericInterfaceCountersPacket::new)
+    }
+
+    fn as_format(&self, format: DataFormat) -> Option<&'a [u8]> {
+        if self.format == format {
+            Some(self.data)
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the samples of a datagram or the records of a sample. Iteration stops after
+/// the number of entries given in the enclosing structure, or at the first entry that does not
+/// fit in the data.
+#[derive(Clone)]
+pub struct Records<'a> {
+    data: &'a [u8],
+    remaining: u32,
+}
+
+debug_items!(Records<'a>);
+
+impl<'a> Records<'a> {
+    fn new(data: &'a [u8], count: u32) -> Records<'a> {
+        Records { data, remaining: count }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Record<'a>> {
+        if self.remaining == 0 || self.data.len() < 8 {
+            return None;
+        }
+        self.remaining -= 1;
+        let format = DataFormat(read_offset!(self.data, 0, u32, from_be));
+        let len = read_offset!(self.data, 4, u32, from_be) as usize;
+        if len > self.data.len() - 8 {
+            self.data = &[];
+            return None;
+        }
+        let (data, rest) = self.data[8..].split_at(len);
+        self.data = rest;
+        Some(Record { format, data })
+    }
+}
+
+
+packet!(
+    /// A flow sample, describing a sampled packet with a list of flow records.
+    FlowSamplePacket,
+    MutFlowSamplePacket,
+    FlowSamplePacketBuf,
+    32
+);
+
+getters!(FlowSamplePacket
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    /// Returns the type of the data source, 0 for an interface index, 1 for a VLAN and 2 for a
+    /// physical entity.
+    pub fn source_id_type(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    /// Returns the 24 bit index of the data source.
+    pub fn source_id_index(&self) -> u24 {
+        read_offset!(self.0, 5, u24, from_be)
+    }
+
+    /// Returns the sampling rate, one packet sampled out of every `sampling_rate` packets.
+    pub fn sampling_rate(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns the total number of packets that could have been sampled.
+    pub fn sample_pool(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns the number of packets dropped due to a lack of resources.
+    pub fn drops(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns the input interface. The upper two bits give its format, where zero means the
+    /// lower 30 bits are an interface index.
+    pub fn input(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    /// Returns the output interface, in the same format as `input`.
+    pub fn output(&self) -> u32 {
+        read_offset!(self.0, 24, u32, from_be)
+    }
+
+    pub fn record_count(&self) -> u32 {
+        read_offset!(self.0, 28, u32, from_be)
+    }
+
+    /// Returns an iterator over the flow records.
+    pub fn records(&self) -> Records<'a> {
+        Records::new(&self.0[32..], self.record_count())
+    }
+);
+
+setters!(MutFlowSamplePacket
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 0, sequence_number, u32, to_be);
+    }
+
+    pub fn set_source_id_type(&mut self, source_id_type: u8) {
+        write_offset!(self.0, 4, source_id_type, u8);
+    }
+
+    /// Sets the 24 bit source index. The highest byte of `source_id_index` is ignored.
+    pub fn set_source_id_index(&mut self, source_id_index: u24) {
+        write_offset!(self.0, 5, source_id_index, u24, to_be);
+    }
+
+    pub fn set_sampling_rate(&mut self, sampling_rate: u32) {
+        write_offset!(self.0, 8, sampling_rate, u32, to_be);
+    }
+
+    pub fn set_sample_pool(&mut self, sample_pool: u32) {
+        write_offset!(self.0, 12, sample_pool, u32, to_be);
+    }
+
+    pub fn set_drops(&mut self, drops: u32) {
+        write_offset!(self.0, 16, drops, u32, to_be);
+    }
+
+    pub fn set_input(&mut self, input: u32) {
+        write_offset!(self.0, 20, input, u32, to_be);
+    }
+
+    pub fn set_output(&mut self, output: u32) {
+        write_offset!(self.0, 24, output, u32, to_be);
+    }
+
+    pub fn set_record_count(&mut self, record_count: u32) {
+        write_offset!(self.0, 28, record_count, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// An expanded flow sample. Like a flow sample, but with the source ID and interfaces in
+    /// separate 32 bit fields, for devices with interface indices above 2^24.
+    ExpandedFlowSamplePacket,
+    MutExpandedFlowSamplePacket,
+    ExpandedFlowSamplePacketBuf,
+    44
+);
+
+getters!(ExpandedFlowSamplePacket
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    pub fn source_id_type(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    pub fn source_id_index(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    pub fn sampling_rate(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    pub fn sample_pool(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    pub fn drops(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    pub fn input_format(&self) -> u32 {
+        read_offset!(self.0, 24, u32, from_be)
+    }
+
+    pub fn input_value(&self) -> u32 {
+        read_offset!(self.0, 28, u32, from_be)
+    }
+
+    pub fn output_format(&self) -> u32 {
+        read_offset!(self.0, 32, u32, from_be)
+    }
+
+    pub fn output_value(&self) -> u32 {
+        read_offset!(self.0, 36, u32, from_be)
+    }
+
+    pub fn record_count(&self) -> u32 {
+        read_offset!(self.0, 40, u32, from_be)
+    }
+
+    /// Returns an iterator over the flow records.
+    pub fn records(&self) -> Records<'a> {
+        Records::new(&self.0[44..], self.record_count())
+    }
+);
+
+setters!(MutExpandedFlowSamplePacket
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 0, sequence_number, u32, to_be);
+    }
+
+    pub fn set_source_id_type(&mut self, source_id_type: u32) {
+        write_offset!(self.0, 4, source_id_type, u32, to_be);
+    }
+
+    pub fn set_source_id_index(&mut self, source_id_index: u32) {
+        write_offset!(self.0, 8, source_id_index, u32, to_be);
+    }
+
+    pub fn set_sampling_rate(&mut self, sampling_rate: u32) {
+        write_offset!(self.0, 12, sampling_rate, u32, to_be);
+    }
+
+    pub fn set_sample_pool(&mut self, sample_pool: u32) {
+        write_offset!(self.0, 16, sample_pool, u32, to_be);
+    }
+
+    pub fn set_drops(&mut self, drops: u32) {
+        write_offset!(self.0, 20, drops, u32, to_be);
+    }
+
+    pub fn set_input_format(&mut self, input_format: u32) {
+        write_offset!(self.0, 24, input_format, u32, to_be);
+    }
+
+    pub fn set_input_value(&mut self, input_value: u32) {
+        write_offset!(self.0, 28, input_value, u32, to_be);
+    }
+
+    pub fn set_output_format(&mut self, output_format: u32) {
+        write_offset!(self.0, 32, output_format, u32, to_be);
+    }
+
+    pub fn set_output_value(&mut self, output_value: u32) {
+        write_offset!(self.0, 36, output_value, u32, to_be);
+    }
+
+    pub fn set_record_count(&mut self, record_count: u32) {
+        write_offset!(self.0, 40, record_count, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A counter sample, holding a list of counter records for a data source.
+    CounterSamplePacket,
+    MutCounterSamplePacket,
+    CounterSamplePacketBuf,
+    12
+);
+
+getters!(CounterSamplePacket
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    /// Returns the type of the data source, see `FlowSamplePacket::source_id_type`.
+    pub fn source_id_type(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    /// Returns the 24 bit index of the data source.
+    pub fn source_id_index(&self) -> u24 {
+        read_offset!(self.0, 5, u24, from_be)
+    }
+
+    pub fn record_count(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns an iterator over the counter records.
+    pub fn records(&self) -> Records<'a> {
+        Records::new(&self.0[12..], self.record_count())
+    }
+);
+
+setters!(MutCounterSamplePacket
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 0, sequence_number, u32, to_be);
+    }
+
+    pub fn set_source_id_type(&mut self, source_id_type: u8) {
+        write_offset!(self.0, 4, source_id_type, u8);
+    }
+
+    /// Sets the 24 bit source index. The highest byte of `source_id_index` is ignored.
+    pub fn set_source_id_index(&mut self, source_id_index: u24) {
+        write_offset!(self.0, 5, source_id_index, u24, to_be);
+    }
+
+    pub fn set_record_count(&mut self, record_count: u32) {
+        write_offset!(self.0, 8, record_count, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// An expanded counter sample. Like a counter sample, but with the source ID in two
+    /// separate 32 bit fields.
+    ExpandedCounterSamplePacket,
+    MutExpandedCounterSamplePacket,
+    ExpandedCounterSamplePacketBuf,
+    16
+);
+
+getters!(ExpandedCounterSamplePacket
+    pub fn sequence_number(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    pub fn source_id_type(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    pub fn source_id_index(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    pub fn record_count(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns an iterator over the counter records.
+    pub fn records(&self) -> Records<'a> {
+        Records::new(&self.0[16..], self.record_count())
+    }
+);
+
+setters!(MutExpandedCounterSamplePacket
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_offset!(self.0, 0, sequence_number, u32, to_be);
+    }
+
+    pub fn set_source_id_type(&mut self, source_id_type: u32) {
+        write_offset!(self.0, 4, source_id_type, u32, to_be);
+    }
+
+    pub fn set_source_id_index(&mut self, source_id_index: u32) {
+        write_offset!(self.0, 8, source_id_index, u32, to_be);
+    }
+
+    pub fn set_record_count(&mut self, record_count: u32) {
+        write_offset!(self.0, 12, record_count, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A raw packet header flow record, holding the first bytes of the sampled packet.
+    RawPacketHeaderPacket,
+    MutRawPacketHeaderPacket,
+    RawPacketHeaderPacketBuf,
+    16,
+    |_data| 16,
+    init |data| write_offset!(data, 12, (data.len() - 16) as u32, u32, to_be)
+);
+
+getters!(RawPacketHeaderPacket
+    pub fn header_protocol(&self) -> HeaderProtocol {
+        HeaderProtocol(read_offset!(self.0, 0, u32, from_be))
+    }
+
+    /// Returns the length of the original packet.
+    pub fn frame_length(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the number of bytes removed from the packet before sampling, such as trailing
+    /// checksums.
+    pub fn stripped(&self) -> u32 {
+        read_offset!(self.0, 8, u32, from_be)
+    }
+
+    /// Returns the number of bytes of the packet included in this record.
+    pub fn sampled_header_len(&self) -> u32 {
+        read_offset!(self.0, 12, u32, from_be)
+    }
+
+    /// Returns the sampled bytes of the packet, without the padding after them. Shorter than
+    /// given by the length field if the record is truncated.
+    pub fn sampled_header(&self) -> &'a [u8] {
+        let len = cmp::min(self.sampled_header_len() as usize, self.0.len() - 16);
+        &self.0[16..16 + len]
+    }
+
+    /// Returns the sampled packet as an Ethernet frame, or `None` if the header protocol is not
+    /// Ethernet or too few bytes were sampled.
+    pub fn ethernet(&self) -> Option<EthernetPacket<'a>> {
+        if self.header_protocol() == HeaderProtocol::ETHERNET {
+            EthernetPacket::new(self.sampled_header())
+        } else {
+            None
+        }
+    }
+);
+
+setters!(MutRawPacketHeaderPacket
+    pub fn set_header_protocol(&mut self, header_protocol: HeaderProtocol) {
+        write_offset!(self.0, 0, header_protocol.value(), u32, to_be);
+    }
+
+    pub fn set_frame_length(&mut self, frame_length: u32) {
+        write_offset!(self.0, 4, frame_length, u32, to_be);
+    }
+
+    pub fn set_stripped(&mut self, stripped: u32) {
+        write_offset!(self.0, 8, stripped, u32, to_be);
+    }
+
+    pub fn set_sampled_header_len(&mut self, sampled_header_len: u32) {
+        write_offset!(self.0, 12, sampled_header_len, u32, to_be);
+    }
+);
+
+
+packet!(
+    /// A generic interface counter record, with the interface statistics of RFC 2233.
+    GenericInterfaceCountersPacket,
+    MutGenericInterfaceCountersPacket,
+    GenericInterfaceCountersPacketBuf,
+    88
+);
+
+getters!(GenericInterfaceCountersPacket
+    pub fn if_index(&self) -> u32 {
+        read_offset!(self.0, 0, u32, from_be)
+    }
+
+    pub fn if_type(&self) -> u32 {
+        read_offset!(self.0, 4, u32, from_be)
+    }
+
+    /// Returns the interface speed in bits per second.
+    pub fn if_speed(&self) -> u64 {
+        read_offset!(self.0, 8, u64, from_be)
+    }
+
+    /// Returns the duplex mode, 0 for unknown, 1 for full duplex, 2 for half duplex, 3 for in
+    /// and 4 for out.
+    pub fn if_direction(&self) -> u32 {
+        read_offset!(self.0, 16, u32, from_be)
+    }
+
+    /// Returns the interface status. Bit 0 is the admin status and bit 1 the operational
+    /// status, where a one means up.
+    pub fn if_status(&self) -> u32 {
+        read_offset!(self.0, 20, u32, from_be)
+    }
+
+    pub fn in_octets(&self) -> u64 {
+        read_offset!(self.0, 24, u64, from_be)
+    }
+
+    pub fn in_unicast_packets(&self) -> u32 {
+        read_offset!(self.0, 32, u32, from_be)
+    }
+
+    pub fn in_multicast_packets(&self) -> u32 {
+        read_offset!(self.0, 36, u32, from_be)
+    }
+
+    pub fn in_broadcast_packets(&self) -> u32 {
+        read_offset!(self.0, 40, u32, from_be)
+    }
+
+    pub fn in_discards(&self) -> u32 {
+        read_offset!(self.0, 44, u32, from_be)
+    }
+
+    pub fn in_errors(&self) -> u32 {
+        read_offset!(self.0, 48, u32, from_be)
+    }
+
+    pub fn in_unknown_protocols(&self) -> u32 {
+        read_offset!(self.0, 52, u32, from_be)
+    }
+
+    pub fn out_octets(&self) -> u64 {
+        read_offset!(self.0, 56, u64, from_be)
+    }
+
+    pub fn out_unicast_packets(&self) -> u32 {
+        read_offset!(self.0, 64, u32, from_be)
+    }
+
+    pub fn out_multicast_packets(&self) -> u32 {
+        read_offset!(self.0, 68, u32, from_be)
+    }
+
+    pub fn out_broadcast_packets(&self) -> u32 {
+        read_offset!(self.0, 72, u32, from_be)
+    }
+
+    pub fn out_discards(&self) -> u32 {
+        read_offset!(self.0, 76, u32, from_be)
+    }
+
+    pub fn out_errors(&self) -> u32 {
+        read_offset!(self.0, 80, u32, from_be)
+    }
+
+    /// Returns 1 if the interface is in promiscuous mode, 2 if not.
+    pub fn if_promiscuous_mode(&self) -> u32 {
+        read_offset!(self.0, 84, u32, from_be)
+    }
+);
+
+setters!(MutGenericInterfaceCountersPacket
+    pub fn set_if_index(&mut self, if_index: u32) {
+        write_offset!(self.0, 0, if_index, u32, to_be);
+    }
+
+    pub fn set_if_type(&mut self, if_type: u32) {
+        write_offset!(self.0, 4, if_type, u32, to_be);
+    }
+
+    pub fn set_if_speed(&mut self, if_speed: u64) {
+        write_offset!(self.0, 8, if_speed, u64, to_be);
+    }
+
+    pub fn set_if_direction(&mut self, if_direction: u32) {
+        write_offset!(self.0, 16, if_direction, u32, to_be);
+    }
+
+    pub fn set_if_status(&mut self, if_status: u32) {
+        write_offset!(self.0, 20, if_status, u32, to_be);
+    }
+
+    pub fn set_in_octets(&mut self, in_octets: u64) {
+        write_offset!(self.0, 24, in_octets, u64, to_be);
+    }
+
+    pub fn set_in_unicast_packets(&mut self, in_unicast_packets: u32) {
+        write_offset!(self.0, 32, in_unicast_packets, u32, to_be);
+    }
+
+    pub fn set_in_multicast_packets(&mut self, in_multicast_packets: u32) {
+        write_offset!(self.0, 36, in_multicast_packets, u32, to_be);
+    }
+
+    pub fn set_in_broadcast_packets(&mut self, in_broadcast_packets: u32) {
+        write_offset!(self.0, 40, in_broadcast_packets, u32, to_be);
+    }
+
+    pub fn set_in_discards(&mut self, in_discards: u32) {
+        write_offset!(self.0, 44, in_discards, u32, to_be);
+    }
+
+    pub fn set_in_errors(&mut self, in_errors: u32) {
+        write_offset!(self.0, 48, in_errors, u32, to_be);
+    }
+
+    pub fn set_in_unknown_protocols(&mut self, in_unknown_protocols: u32) {
+        write_offset!(self.0, 52, in_unknown_protocols, u32, to_be);
+    }
+
+    pub fn set_out_octets(&mut self, out_octets: u64) {
+        write_offset!(self.0, 56, out_octets, u64, to_be);
+    }
+
+    pub fn set_out_unicast_packets(&mut self, out_unicast_packets: u32) {
+        write_offset!(self.0, 64, out_unicast_packets, u32, to_be);
+    }
+
+    pub fn set_out_multicast_packets(&mut self, out_multicast_packets: u32) {
+        write_offset!(self.0, 68, out_multicast_packets, u32, to_be);
+    }
+
+    pub fn set_out_broadcast_packets(&mut self, out_broadcast_packets: u32) {
+        write_offset!(self.0, 72, out_broadcast_packets, u32, to_be);
+    }
+
+    pub fn set_out_discards(&mut self, out_discards: u32) {
+        write_offset!(self.0, 76, out_discards, u32, to_be);
+    }
+
+    pub fn set_out_errors(&mut self, out_errors: u32) {
+        write_offset!(self.0, 80, out_errors, u32, to_be);
+    }
+
+    pub fn set_if_promiscuous_mode(&mut self, if_promiscuous_mode: u32) {
+        write_offset!(self.0, 84, if_promiscuous_mode, u32, to_be);
+    }
+);
+
+impl<'a> fmt::Display for SflowPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sFlow v{}", self.version())?;
+        if let Some(agent) = self.agent_address() {
+            write!(f, " agent={}", agent)?;
+        }
+        write!(
+            f,
+            " seq={} samples={}",
+            self.sequence_number(),
+            self.sample_count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for FlowSamplePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sFlow flow sample seq={} rate={} records={}",
+            self.sequence_number(),
+            self.sampling_rate(),
+            self.record_count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for CounterSamplePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sFlow counter sample seq={} source={}:{} records={}",
+            self.sequence_number(),
+            self.source_id_type(),
+            self.source_id_index(),
+            self.record_count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for ExpandedFlowSamplePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sFlow expanded flow sample seq={} rate={} records={}",
+            self.sequence_number(),
+            self.sampling_rate(),
+            self.record_count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for ExpandedCounterSamplePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sFlow expanded counter sample seq={} source={}:{} records={}",
+            self.sequence_number(),
+            self.source_id_type(),
+            self.source_id_index(),
+            self.record_count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for RawPacketHeaderPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sFlow raw header protocol={} frame_len={} sampled={}",
+            self.header_protocol().value(),
+            self.frame_length(),
+            self.sampled_header_len()
+        )
+    }
+}
+
+impl<'a> fmt::Display for GenericInterfaceCountersPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sFlow interface counters index={} speed={} in={} out={}",
+            self.if_index(),
+            self.if_speed(),
+            self.in_octets(),
+            self.out_octets()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! sflow_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutSflowPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    sflow_setget_test!(version, set_version, 5, 0, [0, 0, 0, 5]);
+    sflow_setget_test!(sub_agent_id, set_sub_agent_id, 0xabcdef01, 12, [0xab, 0xcd, 0xef, 1]);
+    sflow_setget_test!(sequence_number, set_sequence_number, 0xabcdef01, 16, [0xab, 0xcd, 0xef, 1]);
+    sflow_setget_test!(uptime, set_uptime, 0xabcdef01, 20, [0xab, 0xcd, 0xef, 1]);
+    sflow_setget_test!(sample_count, set_sample_count, 0xabcdef01, 24, [0xab, 0xcd, 0xef, 1]);
+
+    #[test]
+    fn agent_address() {
+        let mut data = [0; 40];
+        {
+            let mut testee = MutSflowPacket::new(&mut data).unwrap();
+            assert!(testee.set_agent_address(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+            assert_eq!(28, testee.header_len());
+            testee.set_sample_count(2);
+        }
+        assert_eq!(&[0, 0, 0, 1, 10, 0, 0, 1], &data[4..12]);
+        assert_eq!(&[0, 0, 0, 2], &data[24..28]);
+
+        let address = "2001:db8::1".parse().unwrap();
+        {
+            let mut testee = MutSflowPacket::new(&mut data[..39]).unwrap();
+            assert!(!testee.set_agent_address(IpAddr::V6(address)));
+            let agent_address = testee.as_immutable().agent_address();
+            assert_eq!(Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))), agent_address);
+        }
+        {
+            let mut testee = MutSflowPacket::new(&mut data).unwrap();
+            assert!(testee.set_agent_address(IpAddr::V6(address)));
+            assert_eq!(40, testee.header_len());
+            testee.set_uptime(0x1234);
+        }
+        let testee = SflowPacket::new(&data).unwrap();
+        assert_eq!(AddressType::IPV6, testee.agent_address_type());
+        assert_eq!(Some(IpAddr::V6(address)), testee.agent_address());
+        assert_eq!(0x1234, testee.uptime());
+        assert_eq!(None, SflowPacket::new(&data[..32]).unwrap().agent_address());
+
+        data[7] = 0;
+        assert_eq!(None, SflowPacket::new(&data).unwrap().agent_address());
+    }
+
+    #[test]
+    fn data_format() {
+        let format = DataFormat::new(4413, 5);
+        assert_eq!(0x0113_d005, format.value());
+        assert_eq!(4413, format.enterprise());
+        assert_eq!(5, format.format());
+    }
+
+    #[test]
+    fn samples() {
+        let mut data = vec![0; 28 + 8 + 12 + 8 + 88 + 8 + 32 + 8 + 16 + 16];
+        {
+            let mut testee = MutSflowPacket::new(&mut data).unwrap();
+            testee.set_version(5);
+            assert!(testee.set_agent_address(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+            testee.set_sample_count(3);
+        }
+        data[28..36].copy_from_slice(&[0, 0, 0, 2, 0, 0, 0, 108]);
+        MutCounterSamplePacket::new(&mut data[36..48]).unwrap().set_record_count(1);
+        data[48..56].copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 88]);
+        MutGenericInterfaceCountersPacket::new(&mut data[56..144]).unwrap().set_in_octets(1234);
+        data[144..152].copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 72]);
+        {
+            let mut sample = MutFlowSamplePacket::new(&mut data[152..184]).unwrap();
+            sample.set_sampling_rate(100);
+            sample.set_record_count(1);
+        }
+        data[184..192].copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 32]);
+        {
+            let mut record = MutRawPacketHeaderPacket::new(&mut data[192..]).unwrap();
+            record.set_header_protocol(HeaderProtocol::ETHERNET);
+            record.set_sampled_header_len(14);
+        }
+
+        let testee = SflowPacket::new(&data).unwrap();
+        let mut samples = testee.samples();
+
+        let counters = samples.next().unwrap().counter_sample().unwrap();
+        let mut records = counters.records();
+        let record = records.next().unwrap();
+        assert_eq!(DataFormat::GENERIC_INTERFACE_COUNTERS, record.format);
+        assert_eq!(1234, record.generic_interface_counters().unwrap().in_octets());
+        assert_eq!(None, records.next());
+
+        let sample = samples.next().unwrap();
+        assert_eq!(None, sample.counter_sample());
+        let flow = sample.flow_sample().unwrap();
+        assert_eq!(100, flow.sampling_rate());
+        let raw = flow.records().next().unwrap().raw_packet_header().unwrap();
+        assert_eq!(14, raw.sampled_header().len());
+        assert!(raw.ethernet().is_some());
+
+        assert_eq!(None, samples.next());
+    }
+
+    #[test]
+    fn truncated_samples() {
+        let data = [0, 0, 0, 5, 0, 0, 0, 1, 10, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                    0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 1, 2, 3, 4, 0, 0, 0, 3, 0, 0, 0, 8];
+        let mut samples = SflowPacket::new(&data).unwrap().samples();
+        let sample = samples.next().unwrap();
+        assert_eq!(DataFormat::EXPANDED_FLOW_SAMPLE, sample.format);
+        assert_eq!(&[1, 2, 3, 4], sample.data);
+        assert_eq!(None, sample.expanded_flow_sample());
+        assert_eq!(None, samples.next());
+    }
+
+    mod flow_sample {
+        use super::super::*;
+
+        macro_rules! flow_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutFlowSamplePacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        flow_setget_test!(source_id_type, set_source_id_type, 0xab, 4, [0xab]);
+        flow_setget_test!(source_id_index, set_source_id_index, 0xabcdef, 5, [0xab, 0xcd, 0xef]);
+        flow_setget_test!(sampling_rate, set_sampling_rate, 0xabcdef01, 8, [0xab, 0xcd, 0xef, 1]);
+        flow_setget_test!(drops, set_drops, 0xabcdef01, 16, [0xab, 0xcd, 0xef, 1]);
+        flow_setget_test!(output, set_output, 0xabcdef01, 24, [0xab, 0xcd, 0xef, 1]);
+    }
+
+    mod generic_interface_counters {
+        use super::super::*;
+
+        macro_rules! counters_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(
+                    MutGenericInterfaceCountersPacket,
+                    $name,
+                    $set_name,
+                    $value,
+                    $offset,
+                    $expected
+                );
+            }
+        }
+
+        counters_setget_test!(if_speed, set_if_speed, 0x1_0000_0002, 11, [1, 0, 0, 0, 2]);
+        counters_setget_test!(in_octets, set_in_octets, 0xab, 31, [0xab]);
+        counters_setget_test!(out_octets, set_out_octets, 0xab, 63, [0xab]);
+        counters_setget_test!(out_errors, set_out_errors, 0xab, 83, [0xab]);
+        counters_setget_test!(if_promiscuous_mode, set_if_promiscuous_mode, 2, 87, [2]);
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        SflowPacketBuf => |packet| packet.version() == 5 && packet.agent_address().is_some(),
+        RawPacketHeaderPacketBuf => |packet| packet.sampled_header() == packet.payload()
+    );
+}
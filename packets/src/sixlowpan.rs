@@ -0,0 +1,656 @@
+//! IPv6 header compression for 6LoWPAN networks, RFC 6282.
+//!
+//! An IPHC header replaces the IPv6 header of a packet sent over a low-power link such as
+//! IEEE 802.15.4. Fields are elided when they hold common values or can be derived from the
+//! link-layer addresses or from shared prefixes called contexts, and the rest are carried inline
+//! after the two byte base header. The UDP header can be compressed as well, with a next header
+//! compression (NHC) header following the IPHC header.
+//!
+//! `decompress` restores the full IPv6 and UDP headers, for use with `Ipv6Packet` and
+//! `UdpPacket`.
+
+use ip::Protocol;
+use ipv6::MutIpv6Packet;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ops::Range;
+use types::*;
+use udp::MutUdpPacket;
+
+/// The dispatch value in the top three bits of an IPHC header.
+pub const IPHC_DISPATCH: u3 = 0b011;
+
+/// The dispatch byte of an uncompressed IPv6 header.
+pub const IPV6_DISPATCH: u8 = 0x41;
+
+packet!(
+    /// An IPHC compressed IPv6 header. The header spans the base header and all inline fields,
+    /// and the payload starts with the next header, or with an NHC header if `nh` is set.
+    IphcPacket,
+    MutIphcPacket,
+    IphcPacketBuf,
+    2,
+    |data| layout(data).destination.end,
+    // Everything elided or compressed into the two IPHC bytes: traffic class and flow label
+    // elided, the next header compressed, hop limit 255 and both addresses derived from the
+    // link-layer addresses.
+    init |data| write_offset!(data, 0, [0x7f, 0x33], [u8; 2])
+);
+
+getters!(IphcPacket
+    pub fn dispatch(&self) -> u3 {
+        read_offset!(self.0, 0, u8) >> 5
+    }
+
+    /// Returns the traffic class and flow label compression mode.
+    pub fn tf(&self) -> u2 {
+        (read_offset!(self.0, 0, u8) >> 3) & 0x03
+    }
+
+    /// Returns true if the next header is compressed with NHC.
+    pub fn nh(&self) -> bool {
+        read_offset!(self.0, 0, u8) & 0x04 != 0
+    }
+
+    /// Returns the hop limit compression mode.
+    pub fn hlim(&self) -> u2 {
+        read_offset!(self.0, 0, u8) & 0x03
+    }
+
+    /// Returns true if a context identifier byte follows the base header.
+    pub fn cid(&self) -> bool {
+        read_offset!(self.0, 1, u8) & 0x80 != 0
+    }
+
+    /// Returns true if the source address is compressed based on a context.
+    pub fn sac(&self) -> bool {
+        read_offset!(self.0, 1, u8) & 0x40 != 0
+    }
+
+    /// Returns the source address mode.
+    pub fn sam(&self) -> u2 {
+        (read_offset!(self.0, 1, u8) >> 4) & 0x03
+    }
+
+    /// Returns true if the destination address is a multicast address.
+    pub fn m(&self) -> bool {
+        read_offset!(self.0, 1, u8) & 0x08 != 0
+    }
+
+    /// Returns true if the destination address is compressed based on a context.
+    pub fn dac(&self) -> bool {
+        read_offset!(self.0, 1, u8) & 0x04 != 0
+    }
+
+    /// Returns the destination address mode.
+    pub fn dam(&self) -> u2 {
+        read_offset!(self.0, 1, u8) & 0x03
+    }
+
+    /// Returns the source context identifier, zero unless given by the context identifier
+    /// byte.
+    pub fn source_context(&self) -> u4 {
+        match self.field(layout(self.0).cid) {
+            Some(&[cid]) => cid >> 4,
+            _ => 0,
+        }
+    }
+
+    /// Returns the destination context identifier, zero unless given by the context
+    /// identifier byte.
+    pub fn destination_context(&self) -> u4 {
+        match self.field(layout(self.0).cid) {
+            Some(&[cid]) => cid & 0x0f,
+            _ => 0,
+        }
+    }
+
+    /// Returns the traffic class, or `None` if the header is truncated.
+    pub fn traffic_class(&self) -> Option<u8> {
+        let field = self.field(layout(self.0).tf)?;
+        // The inline fields put the ECN bits before the DSCP
+        let (ecn, dscp) = match self.tf() {
+            0 | 2 => (field[0] >> 6, field[0] & 0x3f),
+            1 => (field[0] >> 6, 0),
+            _ => (0, 0),
+        };
+        Some((dscp << 2) | ecn)
+    }
+
+    /// Returns the flow label, or `None` if the header is truncated.
+    pub fn flow_label(&self) -> Option<u20> {
+        let field = self.field(layout(self.0).tf)?;
+        Some(match self.tf() {
+            0 => read_offset!(field, 0, u32, from_be) & 0x000f_ffff,
+            1 => {
+                (u32::from(field[0] & 0x0f) << 16) |
+                    u32::from(read_offset!(field, 1, u16, from_be))
+            }
+            _ => 0,
+        })
+    }
+
+    /// Returns the next header of the uncompressed packet, or `None` if the header is
+    /// truncated or the next header is compressed with an unsupported NHC header.
+    pub fn next_header(&self) -> Option<Protocol> {
+        if self.nh() {
+            self.udp().map(|_| Protocol::UDP)
+        } else {
+            self.field(layout(self.0).next_header).map(|field| Protocol(field[0]))
+        }
+    }
+
+    /// Returns the hop limit, or `None` if the header is truncated.
+    pub fn hop_limit(&self) -> Option<u8> {
+        match self.hlim() {
+            0 => self.field(layout(self.0).hop_limit).map(|field| field[0]),
+            1 => Some(1),
+            2 => Some(64),
+            _ => Some(255),
+        }
+    }
+
+    /// Returns the compressed UDP header at the start of the payload, or `None` if the next
+    /// header is not compressed as UDP.
+    pub fn udp(&self) -> Option<UdpNhcPacket<'a>> {
+        if !self.nh() || self.header_len() < layout(self.0).destination.end {
+            return None;
+        }
+        let payload = &self.0[self.header_len()..];
+        match payload.first() {
+            Some(&dispatch) if dispatch & 0xf8 == UdpNhcPacket::DISPATCH => {
+                UdpNhcPacket::new(payload)
+            }
+            _ => None,
+        }
+    }
+);
+
+impl<'a> IphcPacket<'a> {
+    /// Returns the source address. Elided interface identifiers are derived from `link`, the
+    /// link-layer source address, and context based addresses use the /64 prefix of the
+    /// address at the index of the context identifier in `contexts`. Returns `None` if the
+    /// header is truncated, the context is missing or the address mode is reserved.
+    pub fn source(&self, link: LinkAddress, contexts: &[Ipv6Addr]) -> Option<Ipv6Addr> {
+        let inline = self.field(layout(self.0).source)?;
+        if !self.sac() {
+            return Some(unicast_address(LINK_LOCAL_PREFIX, self.sam(), inline, link));
+        }
+        if self.sam() == 0 {
+            return Some(Ipv6Addr::UNSPECIFIED);
+        }
+        let context = contexts.get(usize::from(self.source_context()))?;
+        Some(unicast_address(context.octets(), self.sam(), inline, link))
+    }
+
+    /// Returns the destination address, derived like the source address from `link`, the
+    /// link-layer destination address, and `contexts`. Returns `None` if the header is
+    /// truncated, the context is missing or the address mode is reserved or unsupported.
+    pub fn destination(&self, link: LinkAddress, contexts: &[Ipv6Addr]) -> Option<Ipv6Addr> {
+        let inline = self.field(layout(self.0).destination)?;
+        match (self.m(), self.dac(), self.dam()) {
+            (false, false, dam) => Some(unicast_address(LINK_LOCAL_PREFIX, dam, inline, link)),
+            (false, true, 0) => None,
+            (false, true, dam) => {
+                let context = contexts.get(usize::from(self.destination_context()))?;
+                Some(unicast_address(context.octets(), dam, inline, link))
+            }
+            (true, false, dam) => {
+                let mut octets = [0; 16];
+                octets[0] = 0xff;
+                match dam {
+                    0 => octets.copy_from_slice(inline),
+                    1 | 2 => {
+                        octets[1] = inline[0];
+                        octets[17 - inline.len()..].copy_from_slice(&inline[1..]);
+                    }
+                    _ => {
+                        octets[1] = 0x02;
+                        octets[15] = inline[0];
+                    }
+                }
+                Some(Ipv6Addr::from(octets))
+            }
+            // Unicast prefix based multicast addresses are not supported
+            (true, true, _) => None,
+        }
+    }
+
+    fn field(&self, range: Range<usize>) -> Option<&'a [u8]> {
+        self.0.get(range)
+    }
+}
+
+setters!(MutIphcPacket
+    pub fn set_dispatch(&mut self, dispatch: u3) {
+        let new_byte = (dispatch << 5) | (read_offset!(self.0, 0, u8) & 0x1f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_tf(&mut self, tf: u2) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xe7) | ((tf & 0x03) << 3);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_nh(&mut self, nh: bool) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xfb) | if nh { 0x04 } else { 0 };
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_hlim(&mut self, hlim: u2) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xfc) | (hlim & 0x03);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_cid(&mut self, cid: bool) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0x7f) | if cid { 0x80 } else { 0 };
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_sac(&mut self, sac: bool) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xbf) | if sac { 0x40 } else { 0 };
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_sam(&mut self, sam: u2) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xcf) | ((sam & 0x03) << 4);
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_m(&mut self, m: bool) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xf7) | if m { 0x08 } else { 0 };
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_dac(&mut self, dac: bool) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xfb) | if dac { 0x04 } else { 0 };
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+
+    pub fn set_dam(&mut self, dam: u2) {
+        let new_byte = (read_offset!(self.0, 1, u8) & 0xfc) | (dam & 0x03);
+        write_offset!(self.0, 1, new_byte, u8);
+    }
+);
+
+/// The positions of the inline fields of an IPHC header, in the order they appear.
+struct Layout {
+    cid: Range<usize>,
+    tf: Range<usize>,
+    next_header: Range<usize>,
+    hop_limit: Range<usize>,
+    source: Range<usize>,
+    destination: Range<usize>,
+}
+
+fn layout(data: &[u8]) -> Layout {
+    let first = data[0];
+    let second = data[1];
+    let mut offset = 2;
+    let mut next = |len: usize| {
+        let range = offset..offset + len;
+        offset += len;
+        range
+    };
+    let cid = next(if second & 0x80 != 0 { 1 } else { 0 });
+    let tf = next([4, 3, 1, 0][usize::from((first >> 3) & 0x03)]);
+    let next_header = next(if first & 0x04 != 0 { 0 } else { 1 });
+    let hop_limit = next(if first & 0x03 == 0 { 1 } else { 0 });
+    let sam = usize::from((second >> 4) & 0x03);
+    let source = next(if second & 0x40 != 0 { [0, 8, 2, 0][sam] } else { [16, 8, 2, 0][sam] });
+    let dam = usize::from(second & 0x03);
+    let destination = next(match (second & 0x08 != 0, second & 0x04 != 0) {
+        (false, false) => [16, 8, 2, 0][dam],
+        (false, true) => [0, 8, 2, 0][dam],
+        (true, false) => [16, 6, 4, 1][dam],
+        (true, true) => [6, 0, 0, 0][dam],
+    });
+    Layout { cid, tf, next_header, hop_limit, source, destination }
+}
+
+/// The fe80::/64 prefix of link-local addresses.
+const LINK_LOCAL_PREFIX: [u8; 16] = [0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// Builds a unicast address from the /64 prefix in `prefix` and an interface identifier given
+/// by the address mode `mode`, either carried inline or derived from `link`.
+fn unicast_address(prefix: [u8; 16], mode: u2, inline: &[u8], link: LinkAddress) -> Ipv6Addr {
+    let mut octets = prefix;
+    match mode {
+        0 => octets.copy_from_slice(inline),
+        1 => octets[8..].copy_from_slice(inline),
+        2 => octets[8..].copy_from_slice(&short_interface_identifier(inline[0], inline[1])),
+        _ => octets[8..].copy_from_slice(&link.interface_identifier()),
+    }
+    Ipv6Addr::from(octets)
+}
+
+fn short_interface_identifier(high: u8, low: u8) -> [u8; 8] {
+    [0, 0, 0, 0xff, 0xfe, 0, high, low]
+}
+
+
+packet!(
+    /// A UDP header compressed with NHC. The header spans the NHC byte, the inline ports and
+    /// the checksum if not elided.
+    UdpNhcPacket,
+    MutUdpNhcPacket,
+    UdpNhcPacketBuf,
+    1,
+    |data| udp_nhc_len(data[0]),
+    // Both ports compressed to four bits and the checksum elided.
+    init |data| data[0] = UdpNhcPacket::DISPATCH | 0x07
+);
+
+getters!(UdpNhcPacket
+    /// Returns true if the checksum is elided and must be recomputed.
+    pub fn checksum_elided(&self) -> bool {
+        read_offset!(self.0, 0, u8) & 0x04 != 0
+    }
+
+    /// Returns the port compression mode.
+    pub fn ports(&self) -> u2 {
+        read_offset!(self.0, 0, u8) & 0x03
+    }
+
+    /// Returns the source port, or `None` if the header is truncated.
+    pub fn source_port(&self) -> Option<u16> {
+        let ports = self.0.get(1..udp_nhc_len(self.0[0]))?;
+        Some(match self.ports() {
+            0 | 1 => read_offset!(ports, 0, u16, from_be),
+            2 => 0xf000 | u16::from(ports[0]),
+            _ => 0xf0b0 | u16::from(ports[0] >> 4),
+        })
+    }
+
+    /// Returns the destination port, or `None` if the header is truncated.
+    pub fn destination_port(&self) -> Option<u16> {
+        let ports = self.0.get(1..udp_nhc_len(self.0[0]))?;
+        Some(match self.ports() {
+            0 => read_offset!(ports, 2, u16, from_be),
+            1 => 0xf000 | u16::from(ports[2]),
+            2 => read_offset!(ports, 1, u16, from_be),
+            _ => 0xf0b0 | u16::from(ports[0] & 0x0f),
+        })
+    }
+
+    /// Returns the checksum, or `None` if it is elided or the header is truncated.
+    pub fn checksum(&self) -> Option<u16> {
+        if self.checksum_elided() {
+            return None;
+        }
+        let len = udp_nhc_len(self.0[0]);
+        self.0.get(len - 2..len).map(|field| read_offset!(field, 0, u16, from_be))
+    }
+);
+
+impl<'a> UdpNhcPacket<'a> {
+    /// The value of the top five bits of the NHC byte of a compressed UDP header.
+    pub const DISPATCH: u8 = 0xf0;
+}
+
+setters!(MutUdpNhcPacket
+    pub fn set_checksum_elided(&mut self, checksum_elided: bool) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xfb) |
+            if checksum_elided { 0x04 } else { 0 };
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_ports(&mut self, ports: u2) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xfc) | (ports & 0x03);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+);
+
+fn udp_nhc_len(nhc: u8) -> usize {
+    let ports = [4, 3, 3, 1][usize::from(nhc & 0x03)];
+    let checksum = if nhc & 0x04 != 0 { 0 } else { 2 };
+    1 + ports + checksum
+}
+
+
+/// A link-layer address, from which elided interface identifiers are derived.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LinkAddress {
+    /// A 64 bit extended address.
+    Extended([u8; 8]),
+    /// A 16 bit short address.
+    Short(u16),
+}
+
+impl LinkAddress {
+    /// Returns the interface identifier derived from this address, RFC 4944 section 6.
+    pub fn interface_identifier(&self) -> [u8; 8] {
+        match *self {
+            LinkAddress::Extended(mut address) => {
+                address[0] ^= 0x02;
+                address
+            }
+            LinkAddress::Short(address) => {
+                let [high, low] = address.to_be_bytes();
+                short_interface_identifier(high, low)
+            }
+        }
+    }
+}
+
+/// Restores the full IPv6 header of `packet` into the beginning of `buffer`, followed by the
+/// full UDP header if it is compressed, and the rest of the payload. An elided UDP checksum is
+/// recomputed. See `IphcPacket::source` for how `source_link`, `destination_link` and
+/// `contexts` are used. Returns the number of bytes written, or `None` if the packet does not
+/// fit in `buffer`, the header is truncated or the packet uses an unsupported compression.
+pub fn decompress(
+    packet: IphcPacket,
+    source_link: LinkAddress,
+    destination_link: LinkAddress,
+    contexts: &[Ipv6Addr],
+    buffer: &mut [u8],
+) -> Option<usize> {
+    if packet.dispatch() != IPHC_DISPATCH {
+        return None;
+    }
+    let source = packet.source(source_link, contexts)?;
+    let destination = packet.destination(destination_link, contexts)?;
+    let next_header = packet.next_header()?;
+    let udp = packet.udp();
+    let (header_len, payload) = match udp {
+        Some(udp) => (40 + 8, &packet.payload()[udp.header_len()..]),
+        None => (40, packet.payload()),
+    };
+    let len = header_len + payload.len();
+    if len - 40 > usize::from(u16::MAX) {
+        return None;
+    }
+    let packet_data = buffer.get_mut(..len)?;
+    for byte in &mut packet_data[..header_len] {
+        *byte = 0;
+    }
+    packet_data[header_len..].copy_from_slice(payload);
+    {
+        let mut ipv6 = MutIpv6Packet::new(&mut packet_data[..])?;
+        ipv6.set_version(6);
+        ipv6.set_traffic_class(packet.traffic_class()?);
+        ipv6.set_flow_label(packet.flow_label()?);
+        ipv6.set_payload_length((len - 40) as u16);
+        ipv6.set_next_header(next_header);
+        ipv6.set_hop_limit(packet.hop_limit()?);
+        ipv6.set_source(source);
+        ipv6.set_destination(destination);
+    }
+    if let Some(udp) = udp {
+        let mut full = MutUdpPacket::new(&mut packet_data[40..])?;
+        full.set_source_port(udp.source_port()?);
+        full.set_destination_port(udp.destination_port()?);
+        full.set_length((len - 40) as u16);
+        match udp.checksum() {
+            Some(checksum) => full.set_checksum(checksum),
+            None => {
+                full.update_checksum(source.into(), destination.into());
+            }
+        }
+    }
+    Some(len)
+}
+
+impl<'a> fmt::Display for IphcPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "6LoWPAN IPHC tf={} nh={} hlim={} sam={} dam={}",
+            self.tf(),
+            self.nh(),
+            self.hlim(),
+            self.sam(),
+            self.dam()
+        )
+    }
+}
+
+impl<'a> fmt::Display for UdpNhcPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "6LoWPAN UDP")?;
+        if let (Some(source), Some(destination)) = (self.source_port(), self.destination_port()) {
+            write!(f, " {} -> {}", source, destination)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv6::Ipv6Packet;
+    use udp::UdpPacket;
+
+    macro_rules! iphc_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutIphcPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    iphc_setget_test!(dispatch, set_dispatch, 0b111, 0, [0xe0]);
+    iphc_setget_test!(tf, set_tf, 0b11, 0, [0x18]);
+    iphc_setget_test!(nh, set_nh, true, 0, [0x04]);
+    iphc_setget_test!(hlim, set_hlim, 0b11, 0, [0x03]);
+    iphc_setget_test!(cid, set_cid, true, 1, [0x80]);
+    iphc_setget_test!(sac, set_sac, true, 1, [0x40]);
+    iphc_setget_test!(sam, set_sam, 0b11, 1, [0x30]);
+    iphc_setget_test!(m, set_m, true, 1, [0x08]);
+    iphc_setget_test!(dac, set_dac, true, 1, [0x04]);
+    iphc_setget_test!(dam, set_dam, 0b11, 1, [0x03]);
+
+    const SOURCE_LINK: LinkAddress = LinkAddress::Extended([0x02, 0, 0, 0, 0, 0, 0, 0x01]);
+    const DESTINATION_LINK: LinkAddress = LinkAddress::Short(0x0002);
+
+    #[test]
+    fn fully_compressed_udp() {
+        // Link-local addresses and ports 0xf0b1 and 0xf0b2 with everything elided
+        let data = [0x7e, 0x33, 0xf7, 0x12, b'h', b'i'];
+        let testee = IphcPacket::new(&data).unwrap();
+        assert_eq!(IPHC_DISPATCH, testee.dispatch());
+        assert_eq!(2, testee.header_len());
+        assert_eq!(Some(0), testee.traffic_class());
+        assert_eq!(Some(64), testee.hop_limit());
+        assert_eq!(Some(Protocol::UDP), testee.next_header());
+        let source = "fe80::1".parse::<Ipv6Addr>().unwrap();
+        let destination = "fe80::ff:fe00:2".parse::<Ipv6Addr>().unwrap();
+        assert_eq!(Some(source), testee.source(SOURCE_LINK, &[]));
+        assert_eq!(Some(destination), testee.destination(DESTINATION_LINK, &[]));
+        let udp = testee.udp().unwrap();
+        assert!(udp.checksum_elided());
+        assert_eq!((Some(0xf0b1), Some(0xf0b2)), (udp.source_port(), udp.destination_port()));
+        assert_eq!(None, udp.checksum());
+
+        let mut buffer = [0; 64];
+        let len = decompress(testee, SOURCE_LINK, DESTINATION_LINK, &[], &mut buffer).unwrap();
+        assert_eq!(50, len);
+        let ipv6 = Ipv6Packet::new(&buffer[..len]).unwrap();
+        assert_eq!(6, ipv6.version());
+        assert_eq!(10, ipv6.payload_length());
+        assert_eq!(Protocol::UDP, ipv6.next_header());
+        assert_eq!(source, ipv6.source());
+        let udp = UdpPacket::new(ipv6.payload()).unwrap();
+        assert_eq!(0xf0b2, udp.destination_port());
+        assert_eq!(10, udp.length());
+        assert_eq!(b"hi", udp.payload());
+        assert!(udp.verify_checksum(source.into(), destination.into()));
+        assert_eq!(None, decompress(testee, SOURCE_LINK, DESTINATION_LINK, &[], &mut buffer[..49]));
+    }
+
+    #[test]
+    fn inline_fields() {
+        let mut data = vec![0x60, 0x00, 0x81, 0x0f, 0x12, 0x34, 58, 33];
+        data.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        data.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        data.extend_from_slice(&[128, 0]);
+        let testee = IphcPacket::new(&data).unwrap();
+        assert_eq!(40, testee.header_len());
+        assert_eq!(Some(6), testee.traffic_class());
+        assert_eq!(Some(0xf1234), testee.flow_label());
+        assert_eq!(Some(Protocol::ICMPV6), testee.next_header());
+        assert_eq!(Some(33), testee.hop_limit());
+        assert!(testee.udp().is_none());
+
+        let mut buffer = [0; 42];
+        let len = decompress(testee, SOURCE_LINK, DESTINATION_LINK, &[], &mut buffer).unwrap();
+        let ipv6 = Ipv6Packet::new(&buffer[..len]).unwrap();
+        assert_eq!(6, ipv6.traffic_class());
+        assert_eq!(0xf1234, ipv6.flow_label());
+        assert_eq!("2001:db8::2".parse::<Ipv6Addr>().unwrap(), ipv6.destination());
+        assert_eq!(&[128, 0], ipv6.payload());
+
+        // Truncated inline fields
+        let testee = IphcPacket::new(&data[..30]).unwrap();
+        assert_eq!(None, testee.destination(DESTINATION_LINK, &[]));
+        assert_eq!(None, decompress(testee, SOURCE_LINK, DESTINATION_LINK, &[], &mut buffer));
+    }
+
+    #[test]
+    fn context_and_multicast() {
+        let data = [0x7b, 0xdb, 0x10, 58, 0, 0, 0, 0, 0, 0, 0, 0x05, 0x1a];
+        let testee = IphcPacket::new(&data).unwrap();
+        assert_eq!(13, testee.header_len());
+        assert_eq!((1, 0), (testee.source_context(), testee.destination_context()));
+        let contexts = [Ipv6Addr::UNSPECIFIED, "2001:db8::".parse().unwrap()];
+        assert_eq!(
+            Some("2001:db8::5".parse().unwrap()),
+            testee.source(SOURCE_LINK, &contexts)
+        );
+        assert_eq!(None, testee.source(SOURCE_LINK, &contexts[..1]));
+        assert_eq!(
+            Some("ff02::1a".parse().unwrap()),
+            testee.destination(DESTINATION_LINK, &contexts)
+        );
+    }
+
+    #[test]
+    fn multicast_48_bits() {
+        let data = [0x7b, 0x39, 58, 0x05, 0x0a, 0x01, 0x02, 0x03, 0x04];
+        let testee = IphcPacket::new(&data).unwrap();
+        assert_eq!(
+            Some("ff05::a:102:304".parse().unwrap()),
+            testee.destination(DESTINATION_LINK, &[])
+        );
+    }
+
+    #[test]
+    fn udp_inline_ports() {
+        let data = [0xf0, 0x12, 0x34, 0x56, 0x78, 0xab, 0xcd, 1, 2];
+        let testee = UdpNhcPacket::new(&data).unwrap();
+        assert_eq!(7, testee.header_len());
+        assert_eq!((Some(0x1234), Some(0x5678)), (testee.source_port(), testee.destination_port()));
+        assert_eq!(Some(0xabcd), testee.checksum());
+        assert_eq!(&[1, 2], testee.payload());
+
+        let testee = UdpNhcPacket::new(&[0xf5, 0x12, 0x34, 0x56]).unwrap();
+        assert_eq!((Some(0x1234), Some(0xf056)), (testee.source_port(), testee.destination_port()));
+        assert_eq!(None, testee.checksum());
+        assert_eq!(None, UdpNhcPacket::new(&[0xf0, 0x12]).unwrap().source_port());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        IphcPacketBuf => |packet| packet.dispatch() == IPHC_DISPATCH
+            && packet.hop_limit() == Some(255)
+    );
+}
@@ -0,0 +1,322 @@
+//! Dissecting a whole frame in one call. `SlicedPacket::from_ethernet` walks the layers of an
+//! Ethernet frame: up to two VLAN tags, IPv4 or IPv6 with its extension headers and finally TCP,
+//! UDP, ICMPv4 or ICMPv6. Every layer it recognizes gets a typed view, and the walk stops at the
+//! first layer that is unknown, truncated or not the first fragment of a packet.
+//!
+//! ```rust
+//! extern crate rips_packets;
+//!
+//! use rips_packets::ethernet::EthernetBuilder;
+//! use rips_packets::ipv4::Ipv4Builder;
+//! use rips_packets::sliced::{SlicedPacket, TransportSlice};
+//! use rips_packets::udp::UdpBuilder;
+//!
+//! fn main() {
+//!     let udp = UdpBuilder::new().destination_port(53).payload(b"query");
+//!     let ipv4 = Ipv4Builder::new().payload(udp);
+//!     let frame = EthernetBuilder::new().payload(ipv4).build().unwrap();
+//!
+//!     let sliced = SlicedPacket::from_ethernet(&frame).unwrap();
+//!     match sliced.transport {
+//!         Some(TransportSlice::Udp(udp)) => assert_eq!(53, udp.destination_port()),
+//!         _ => panic!("Not UDP"),
+//!     }
+//!     assert_eq!(b"query", sliced.payload);
+//! }
+//! ```
+
+use ethernet::{EtherType, EthernetPacket};
+use icmpv4::Icmpv4Packet;
+use icmpv6::Icmpv6Packet;
+use ip::Protocol;
+use ipv4::Ipv4Packet;
+use ipv6::{
+    Ipv6DestinationOptionsPacket, Ipv6FragmentPacket, Ipv6HopByHopPacket, Ipv6Packet,
+    Ipv6RoutingPacket,
+};
+use std::net::IpAddr;
+use tcp::TcpPacket;
+use udp::UdpPacket;
+use vlan::VlanPacket;
+
+/// The network layer of a `SlicedPacket`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum IpSlice<'a> {
+    Ipv4(Ipv4Packet<'a>),
+    Ipv6(Ipv6Packet<'a>),
+}
+
+impl<'a> IpSlice<'a> {
+    pub fn source(&self) -> IpAddr {
+        match *self {
+            IpSlice::Ipv4(ref packet) => IpAddr::V4(packet.source()),
+            IpSlice::Ipv6(ref packet) => IpAddr::V6(packet.source()),
+        }
+    }
+
+    pub fn destination(&self) -> IpAddr {
+        match *self {
+            IpSlice::Ipv4(ref packet) => IpAddr::V4(packet.destination()),
+            IpSlice::Ipv6(ref packet) => IpAddr::V6(packet.destination()),
+        }
+    }
+}
+
+/// The transport layer of a `SlicedPacket`. ICMP is not strictly a transport protocol, but it
+/// is what follows the IP header in the same way.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TransportSlice<'a> {
+    Tcp(TcpPacket<'a>),
+    Udp(UdpPacket<'a>),
+    Icmpv4(Icmpv4Packet<'a>),
+    Icmpv6(Icmpv6Packet<'a>),
+}
+
+impl<'a> TransportSlice<'a> {
+    /// Returns the payload of the transport layer view.
+    pub fn payload(&self) -> &'a [u8] {
+        match *self {
+            TransportSlice::Tcp(ref packet) => packet.payload(),
+            TransportSlice::Udp(ref packet) => packet.payload(),
+            TransportSlice::Icmpv4(ref packet) => packet.payload(),
+            TransportSlice::Icmpv6(ref packet) => packet.payload(),
+        }
+    }
+}
+
+/// Typed views of the layers in an Ethernet frame. See the module documentation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SlicedPacket<'a> {
+    pub ethernet: EthernetPacket<'a>,
+    /// The outer VLAN tag, if the frame is tagged.
+    pub vlan: Option<VlanPacket<'a>>,
+    /// The inner VLAN tag of a frame with stacked (QinQ) tags.
+    pub inner_vlan: Option<VlanPacket<'a>>,
+    pub ip: Option<IpSlice<'a>>,
+    pub hop_by_hop: Option<Ipv6HopByHopPacket<'a>>,
+    pub routing: Option<Ipv6RoutingPacket<'a>>,
+    pub fragment: Option<Ipv6FragmentPacket<'a>>,
+    /// The last destination options header, the one in front of the upper layer.
+    pub destination_options: Option<Ipv6DestinationOptionsPacket<'a>>,
+    pub transport: Option<TransportSlice<'a>>,
+    /// Everything after the last layer that was recognized.
+    pub payload: &'a [u8],
+}
+
+impl<'a> SlicedPacket<'a> {
+    /// Dissects the Ethernet frame in `data`. Returns `None` only if `data` is too short to
+    /// hold an Ethernet header, any deeper layer that can't be read is left as `None`.
+    pub fn from_ethernet(data: &'a [u8]) -> Option<SlicedPacket<'a>> {
+        let ethernet = EthernetPacket::new(data)?;
+        let mut sliced = SlicedPacket {
+            ethernet,
+            vlan: None,
+            inner_vlan: None,
+            ip: None,
+            hop_by_hop: None,
+            routing: None,
+            fragment: None,
+            destination_options: None,
+            transport: None,
+            payload: ethernet.payload(),
+        };
+
+        // Offset of the EtherType field, or of the TPID of the next VLAN tag.
+        let mut offset = 12;
+        let mut ether_type = ethernet.ether_type();
+        while ether_type == EtherType::VLAN || ether_type == EtherType::QINQ {
+            let vlan = match VlanPacket::new(&data[offset..]) {
+                Some(vlan) if sliced.inner_vlan.is_none() => vlan,
+                _ => return Some(sliced),
+            };
+            if sliced.vlan.is_none() {
+                sliced.vlan = Some(vlan);
+            } else {
+                sliced.inner_vlan = Some(vlan);
+            }
+            sliced.payload = vlan.payload();
+            ether_type = vlan.ether_type();
+            offset += 4;
+        }
+
+        match ether_type {
+            EtherType::IPV4 => sliced.walk_ipv4(),
+            EtherType::IPV6 => sliced.walk_ipv6(),
+            _ => (),
+        }
+        Some(sliced)
+    }
+
+    fn walk_ipv4(&mut self) {
+        if let Some(ip) = Ipv4Packet::new(self.payload) {
+            self.ip = Some(IpSlice::Ipv4(ip));
+            self.payload = ip.payload();
+            if ip.fragment_offset() == 0 {
+                self.walk_transport(ip.protocol());
+            }
+        }
+    }
+
+    fn walk_ipv6(&mut self) {
+        let ip = match Ipv6Packet::new(self.payload) {
+            Some(ip) => ip,
+            None => return,
+        };
+        self.ip = Some(IpSlice::Ipv6(ip));
+        self.payload = ip.payload();
+        let mut next_header = ip.next_header();
+        // Every extension header is at least eight bytes, so this ends when the data runs out.
+        loop {
+            next_header = match next_header {
+                Protocol::HOPOPT => match Ipv6HopByHopPacket::new(self.payload) {
+                    Some(header) => {
+                        self.hop_by_hop = Some(header);
+                        self.payload = header.payload();
+                        header.next_header()
+                    }
+                    None => return,
+                },
+                Protocol::IPV6_ROUTE => match Ipv6RoutingPacket::new(self.payload) {
+                    Some(header) => {
+                        self.routing = Some(header);
+                        self.payload = header.payload();
+                        header.next_header()
+                    }
+                    None => return,
+                },
+                Protocol::IPV6_FRAG => match Ipv6FragmentPacket::new(self.payload) {
+                    Some(header) => {
+                        self.fragment = Some(header);
+                        self.payload = header.payload();
+                        if header.fragment_offset() != 0 {
+                            return;
+                        }
+                        header.next_header()
+                    }
+                    None => return,
+                },
+                Protocol::IPV6_OPTS => match Ipv6DestinationOptionsPacket::new(self.payload) {
+                    Some(header) => {
+                        self.destination_options = Some(header);
+                        self.payload = header.payload();
+                        header.next_header()
+                    }
+                    None => return,
+                },
+                protocol => return self.walk_transport(protocol),
+            };
+        }
+    }
+
+    fn walk_transport(&mut self, protocol: Protocol) {
+        let transport = match protocol {
+            Protocol::TCP => TcpPacket::new(self.payload).map(TransportSlice::Tcp),
+            Protocol::UDP => UdpPacket::new(self.payload).map(TransportSlice::Udp),
+            Protocol::ICMP => Icmpv4Packet::new(self.payload).map(TransportSlice::Icmpv4),
+            Protocol::ICMPV6 => Icmpv6Packet::new(self.payload).map(TransportSlice::Icmpv6),
+            _ => None,
+        };
+        if let Some(transport) = transport {
+            self.transport = Some(transport);
+            self.payload = transport.payload();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{EthernetBuilder, MacAddr};
+    use ipv4::Ipv4Builder;
+    use ipv6::Ipv6Builder;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use udp::UdpBuilder;
+
+    fn ipv4_udp_frame() -> Vec<u8> {
+        let udp = UdpBuilder::new().source_port(1234).destination_port(53).payload(b"hi");
+        let ipv4 = Ipv4Builder::new()
+            .source(Ipv4Addr::new(10, 0, 0, 1))
+            .destination(Ipv4Addr::new(10, 0, 0, 2))
+            .payload(udp);
+        EthernetBuilder::new().source(MacAddr([2, 0, 0, 0, 0, 1])).payload(ipv4).build().unwrap()
+    }
+
+    #[test]
+    fn ipv4_udp() {
+        let frame = ipv4_udp_frame();
+        let sliced = SlicedPacket::from_ethernet(&frame).unwrap();
+        assert_eq!(MacAddr([2, 0, 0, 0, 0, 1]), sliced.ethernet.source());
+        assert_eq!(None, sliced.vlan);
+        assert_eq!(IpAddr::from([10, 0, 0, 2]), sliced.ip.unwrap().destination());
+        match sliced.transport {
+            Some(TransportSlice::Udp(udp)) => assert_eq!(1234, udp.source_port()),
+            transport => panic!("Unexpected transport {:?}", transport),
+        }
+        assert_eq!(b"hi", sliced.payload);
+    }
+
+    #[test]
+    fn vlan_ipv6_extensions_tcp() {
+        let mut tcp = [0; 22];
+        tcp[0..2].copy_from_slice(&[0x1f, 0x90]);
+        tcp[12] = 0x50;
+        tcp[20..].copy_from_slice(b"ok");
+        let extensions = [
+            // Hop-by-hop options, next header routing, padded with PadN.
+            43, 0, 1, 4, 0, 0, 0, 0,
+            // Routing, next header fragment.
+            44, 0, 0, 0, 0, 0, 0, 0,
+            // Fragment, next header TCP, first fragment with more to follow.
+            6, 0, 0, 1, 0, 0, 0, 42,
+        ];
+        let ipv6 = Ipv6Builder::new()
+            .source(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1))
+            .next_header(Protocol::HOPOPT)
+            .payload([&extensions[..], &tcp[..]].concat())
+            .build()
+            .unwrap();
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x88, 0xa8, 0x00, 0x64, 0x81, 0x00, 0x00, 0x0a, 0x86, 0xdd]);
+        frame.extend_from_slice(&ipv6);
+
+        let sliced = SlicedPacket::from_ethernet(&frame).unwrap();
+        assert_eq!(100, sliced.vlan.unwrap().vid());
+        assert_eq!(10, sliced.inner_vlan.unwrap().vid());
+        assert_eq!(IpAddr::from([0xfe80, 0, 0, 0, 0, 0, 0, 1]), sliced.ip.unwrap().source());
+        assert_eq!(Protocol::IPV6_ROUTE, sliced.hop_by_hop.unwrap().next_header());
+        assert_eq!(Protocol::IPV6_FRAG, sliced.routing.unwrap().next_header());
+        assert_eq!(42, sliced.fragment.unwrap().identification());
+        assert_eq!(None, sliced.destination_options);
+        match sliced.transport {
+            Some(TransportSlice::Tcp(tcp)) => assert_eq!(8080, tcp.source_port()),
+            transport => panic!("Unexpected transport {:?}", transport),
+        }
+        assert_eq!(b"ok", sliced.payload);
+    }
+
+    #[test]
+    fn stops_at_non_first_fragment() {
+        let mut frame = ipv4_udp_frame();
+        frame[14 + 7] = 1;
+        let sliced = SlicedPacket::from_ethernet(&frame).unwrap();
+        assert!(sliced.ip.is_some());
+        assert_eq!(None, sliced.transport);
+        assert_eq!(&frame[34..], sliced.payload);
+    }
+
+    #[test]
+    fn truncated() {
+        let frame = ipv4_udp_frame();
+        let sliced = SlicedPacket::from_ethernet(&frame[..38]).unwrap();
+        assert!(sliced.ip.is_some());
+        assert_eq!(None, sliced.transport);
+        assert_eq!(&frame[34..38], sliced.payload);
+
+        let sliced = SlicedPacket::from_ethernet(&frame[..20]).unwrap();
+        assert_eq!(None, sliced.ip);
+        assert_eq!(&frame[14..20], sliced.payload);
+
+        assert_eq!(None, SlicedPacket::from_ethernet(&frame[..13]));
+    }
+}
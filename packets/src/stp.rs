@@ -0,0 +1,418 @@
+//! Bridge Protocol Data Units of the Spanning Tree Protocol and Rapid Spanning Tree Protocol,
+//! IEEE 802.1D. BPDUs are carried in 802.3 frames with an LLC header using the `LLC_SAP` service
+//! access point.
+
+use ethernet::MacAddr;
+use std::fmt;
+use types::*;
+
+/// The destination address of BPDUs.
+pub const BRIDGE_GROUP_ADDRESS: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x00]);
+
+/// The LLC service access point used for both the DSAP and SSAP of BPDUs.
+pub const LLC_SAP: u8 = 0x42;
+
+macro_rules! bpdu_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            /// Returns the protocol identifier, always zero for the Spanning Tree Protocol.
+            pub fn protocol_id(&self) -> u16 {
+                read_offset!(self.0, 0, u16, from_be)
+            }
+
+            /// Returns the protocol version, 0 for STP, 2 for RSTP and 3 for MSTP.
+            pub fn version(&self) -> u8 {
+                read_offset!(self.0, 2, u8)
+            }
+
+            pub fn bpdu_type(&self) -> BpduType {
+                BpduType(read_offset!(self.0, 3, u8))
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_protocol_id(&mut self, protocol_id: u16) {
+                write_offset!(self.0, 0, protocol_id, u16, to_be);
+            }
+
+            pub fn set_version(&mut self, version: u8) {
+                write_offset!(self.0, 2, version, u8);
+            }
+
+            pub fn set_bpdu_type(&mut self, bpdu_type: BpduType) {
+                write_offset!(self.0, 3, bpdu_type.value(), u8);
+            }
+        );
+    }
+}
+
+packet!(
+    /// The fields common to all BPDUs. A Topology Change Notification BPDU consists of nothing
+    /// else, while Configuration and RST BPDUs continue as a `ConfigBpduPacket`.
+    BpduPacket,
+    MutBpduPacket,
+    BpduPacketBuf,
+    4,
+    |_data| 4,
+    init |data| {
+        write_offset!(data, 0, [0; 3], [u8; 3]);
+        write_offset!(data, 3, BpduType::TOPOLOGY_CHANGE_NOTIFICATION.value(), u8);
+    }
+);
+bpdu_common!(BpduPacket, MutBpduPacket);
+
+
+packet!(
+    /// A Configuration BPDU or an RST BPDU. The latter has the one byte Version 1 Length field
+    /// at the end of the header.
+    ConfigBpduPacket,
+    MutConfigBpduPacket,
+    ConfigBpduPacketBuf,
+    35,
+    |data| if data[2] >= 2 { 36 } else { 35 },
+    // A configuration BPDU of STP, without the Version 1 Length field of later versions.
+    init |data| write_offset!(data, 0, [0; 4], [u8; 4])
+);
+bpdu_common!(ConfigBpduPacket, MutConfigBpduPacket);
+
+getters!(ConfigBpduPacket
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 4, u8))
+    }
+
+    /// Returns the role of the sending port. Only used by RST BPDUs.
+    pub fn port_role(&self) -> PortRole {
+        PortRole((read_offset!(self.0, 4, u8) >> 2) & 0x03)
+    }
+
+    pub fn root_id(&self) -> BridgeId {
+        BridgeId::from_slice(&self.0[5..13])
+    }
+
+    /// Returns the cost of the path from the sending bridge to the root bridge.
+    pub fn root_path_cost(&self) -> u32 {
+        read_offset!(self.0, 13, u32, from_be)
+    }
+
+    pub fn bridge_id(&self) -> BridgeId {
+        BridgeId::from_slice(&self.0[17..25])
+    }
+
+    /// Returns the port identifier, a 4 bit priority followed by a 12 bit port number.
+    pub fn port_id(&self) -> u16 {
+        read_offset!(self.0, 25, u16, from_be)
+    }
+
+    /// Returns the age of the information in units of 1/256 seconds.
+    pub fn message_age(&self) -> u16 {
+        read_offset!(self.0, 27, u16, from_be)
+    }
+
+    /// Returns the age at which the information is discarded, in units of 1/256 seconds.
+    pub fn max_age(&self) -> u16 {
+        read_offset!(self.0, 29, u16, from_be)
+    }
+
+    /// Returns the interval between Configuration BPDUs, in units of 1/256 seconds.
+    pub fn hello_time(&self) -> u16 {
+        read_offset!(self.0, 31, u16, from_be)
+    }
+
+    /// Returns the time spent in each of the listening and learning states, in units of 1/256
+    /// seconds.
+    pub fn forward_delay(&self) -> u16 {
+        read_offset!(self.0, 33, u16, from_be)
+    }
+
+    /// Returns the Version 1 Length field, always zero for RST BPDUs, or `None` if the version
+    /// is below 2 or the field does not fit in the backing slice.
+    pub fn version_1_length(&self) -> Option<u8> {
+        if self.version() >= 2 {
+            self.0.get(35).cloned()
+        } else {
+            None
+        }
+    }
+);
+
+setters!(MutConfigBpduPacket
+    /// Sets the flags, leaving the port role untouched.
+    pub fn set_flags(&mut self, flags: Flags) {
+        let new_byte = (read_offset!(self.0, 4, u8) & 0x0c) | flags.bits();
+        write_offset!(self.0, 4, new_byte, u8);
+    }
+
+    pub fn set_port_role(&mut self, port_role: PortRole) {
+        let new_byte = (read_offset!(self.0, 4, u8) & 0xf3) | ((port_role.value() & 0x03) << 2);
+        write_offset!(self.0, 4, new_byte, u8);
+    }
+
+    pub fn set_root_id(&mut self, root_id: BridgeId) {
+        root_id.write(&mut self.0[5..13]);
+    }
+
+    pub fn set_root_path_cost(&mut self, root_path_cost: u32) {
+        write_offset!(self.0, 13, root_path_cost, u32, to_be);
+    }
+
+    pub fn set_bridge_id(&mut self, bridge_id: BridgeId) {
+        bridge_id.write(&mut self.0[17..25]);
+    }
+
+    pub fn set_port_id(&mut self, port_id: u16) {
+        write_offset!(self.0, 25, port_id, u16, to_be);
+    }
+
+    pub fn set_message_age(&mut self, message_age: u16) {
+        write_offset!(self.0, 27, message_age, u16, to_be);
+    }
+
+    pub fn set_max_age(&mut self, max_age: u16) {
+        write_offset!(self.0, 29, max_age, u16, to_be);
+    }
+
+    pub fn set_hello_time(&mut self, hello_time: u16) {
+        write_offset!(self.0, 31, hello_time, u16, to_be);
+    }
+
+    pub fn set_forward_delay(&mut self, forward_delay: u16) {
+        write_offset!(self.0, 33, forward_delay, u16, to_be);
+    }
+);
+
+impl<'a> MutConfigBpduPacket<'a> {
+    /// Sets the Version 1 Length field. Returns false, leaving the packet unchanged, if the
+    /// version is below 2, or if the backing slice is too short. Set the version first.
+    pub fn set_version_1_length(&mut self, version_1_length: u8) -> bool {
+        if self.as_immutable().version() < 2 || self.0.len() < 36 {
+            return false;
+        }
+        self.0[35] = version_1_length;
+        true
+    }
+}
+
+
+/// A bridge identifier, made up of a priority and the MAC address of the bridge.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct BridgeId {
+    /// The 16 bit priority field. With 802.1t this is a 4 bit priority followed by a 12 bit
+    /// system ID extension, see `bridge_priority` and `system_id_extension`.
+    pub priority: u16,
+    pub address: MacAddr,
+}
+
+impl BridgeId {
+    pub fn new(priority: u16, address: MacAddr) -> BridgeId {
+        BridgeId { priority, address }
+    }
+
+    /// Returns the bridge priority, the upper 4 bits of the priority field, in its configured
+    /// form as a multiple of 4096.
+    pub fn bridge_priority(&self) -> u16 {
+        self.priority & 0xf000
+    }
+
+    /// Returns the system ID extension, usually the VLAN ID or MST instance.
+    pub fn system_id_extension(&self) -> u12 {
+        self.priority & 0x0fff
+    }
+
+    fn from_slice(data: &[u8]) -> BridgeId {
+        BridgeId::new(read_offset!(data, 0, u16, from_be), MacAddr::from_slice(&data[2..8]))
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        write_offset!(data, 0, self.priority, u16, to_be);
+        data[2..8].copy_from_slice(self.address.as_ref());
+    }
+}
+
+/// The type of a BPDU.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BpduType(pub u8);
+
+impl BpduType {
+    pub const CONFIGURATION: BpduType = BpduType(0x00);
+    /// Rapid Spanning Tree BPDU, also used by MSTP.
+    pub const RST: BpduType = BpduType(0x02);
+    pub const TOPOLOGY_CHANGE_NOTIFICATION: BpduType = BpduType(0x80);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// The two bit role of the port sending an RST BPDU.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PortRole(pub u2);
+
+impl PortRole {
+    pub const UNKNOWN: PortRole = PortRole(0);
+    pub const ALTERNATE_OR_BACKUP: PortRole = PortRole(1);
+    pub const ROOT: PortRole = PortRole(2);
+    pub const DESIGNATED: PortRole = PortRole(3);
+
+    #[inline]
+    pub fn value(&self) -> u2 {
+        self.0
+    }
+}
+
+
+bitflags! {
+    /// Bitmasks for the flags of a Configuration or RST BPDU. The two bits in between the
+    /// proposal and learning flags hold the port role, see `ConfigBpduPacket::port_role`.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "Topology Change" position.
+        const TOPOLOGY_CHANGE = 0b0000_0001;
+        /// A bitmask with a one in the "Proposal" position.
+        const PROPOSAL = 0b0000_0010;
+        /// A bitmask with a one in the "Learning" position.
+        const LEARNING = 0b0001_0000;
+        /// A bitmask with a one in the "Forwarding" position.
+        const FORWARDING = 0b0010_0000;
+        /// A bitmask with a one in the "Agreement" position.
+        const AGREEMENT = 0b0100_0000;
+        /// A bitmask with a one in the "Topology Change Acknowledgment" position.
+        const TOPOLOGY_CHANGE_ACK = 0b1000_0000;
+    }
+}
+
+impl<'a> fmt::Display for ConfigBpduPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (root, bridge) = (self.root_id(), self.bridge_id());
+        write!(
+            f,
+            "STP config root={}/{} cost={} bridge={}/{} port={:#06x}",
+            root.priority,
+            root.address,
+            self.root_path_cost(),
+            bridge.priority,
+            bridge.address,
+            self.port_id()
+        )
+    }
+}
+
+impl<'a> fmt::Display for BpduPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "STP BPDU version={} type={:#04x}",
+            self.version(),
+            self.bpdu_type().value()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static MAC: [u8; 6] = [0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e];
+
+    macro_rules! bpdu_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutConfigBpduPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    bpdu_setget_test!(protocol_id, set_protocol_id, 0xabcd, 0, [0xab, 0xcd]);
+    bpdu_setget_test!(version, set_version, 0xab, 2, [0xab]);
+    bpdu_setget_test!(bpdu_type, set_bpdu_type, BpduType::RST, 3, [0x02]);
+    bpdu_setget_test!(flags, set_flags, Flags::all(), 4, [0xf3]);
+    bpdu_setget_test!(port_role, set_port_role, PortRole::DESIGNATED, 4, [0x0c]);
+    bpdu_setget_test!(
+        root_id,
+        set_root_id,
+        BridgeId::new(0x8001, MacAddr(MAC)),
+        5,
+        [0x80, 0x01, 0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e]
+    );
+    bpdu_setget_test!(root_path_cost, set_root_path_cost, 0xabcdef01, 13, [0xab, 0xcd, 0xef, 0x01]);
+    bpdu_setget_test!(
+        bridge_id,
+        set_bridge_id,
+        BridgeId::new(0x8001, MacAddr(MAC)),
+        17,
+        [0x80, 0x01, 0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e]
+    );
+    bpdu_setget_test!(port_id, set_port_id, 0xabcd, 25, [0xab, 0xcd]);
+    bpdu_setget_test!(message_age, set_message_age, 0xabcd, 27, [0xab, 0xcd]);
+    bpdu_setget_test!(max_age, set_max_age, 0xabcd, 29, [0xab, 0xcd]);
+    bpdu_setget_test!(hello_time, set_hello_time, 0xabcd, 31, [0xab, 0xcd]);
+    bpdu_setget_test!(forward_delay, set_forward_delay, 0xabcd, 33, [0xab, 0xcd]);
+
+    #[test]
+    fn flags_and_port_role_are_independent() {
+        let mut data = [0; 36];
+        let mut testee = MutConfigBpduPacket::new(&mut data).unwrap();
+        testee.set_port_role(PortRole::ROOT);
+        testee.set_flags(Flags::LEARNING | Flags::FORWARDING);
+        assert_eq!(PortRole::ROOT, testee.as_immutable().port_role());
+        testee.set_port_role(PortRole::ALTERNATE_OR_BACKUP);
+        assert_eq!(Flags::LEARNING | Flags::FORWARDING, testee.as_immutable().flags());
+    }
+
+    #[test]
+    fn rst_bpdu() {
+        let data = [
+            0x00, 0x00, 0x02, 0x02, 0x3c, // RST BPDU, designated, learning and forwarding
+            0x80, 0x0a, 0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e, // Root, priority 32768 on VLAN 10
+            0x00, 0x00, 0x00, 0x04, // Root path cost
+            0x80, 0x0a, 0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5f, // Bridge
+            0x80, 0x01, 0x01, 0x00, 0x14, 0x00, 0x02, 0x00, 0x0f, 0x00, // Port and timers
+            0x00, // Version 1 Length
+        ];
+        let testee = ConfigBpduPacket::new(&data).unwrap();
+        assert_eq!(BpduType::RST, testee.bpdu_type());
+        assert_eq!(36, testee.header_len());
+        assert_eq!(Flags::LEARNING | Flags::FORWARDING, testee.flags());
+        assert_eq!(PortRole::DESIGNATED, testee.port_role());
+        assert_eq!(0x8000, testee.root_id().bridge_priority());
+        assert_eq!(10, testee.root_id().system_id_extension());
+        assert_eq!(MacAddr(MAC), testee.root_id().address);
+        assert_eq!(4, testee.root_path_cost());
+        assert_eq!(20 * 256, testee.max_age());
+        assert_eq!(2 * 256, testee.hello_time());
+        assert_eq!(15 * 256, testee.forward_delay());
+        assert_eq!(Some(0), testee.version_1_length());
+
+        let testee = ConfigBpduPacket::new(&data[..35]).unwrap();
+        assert_eq!(None, testee.version_1_length());
+    }
+
+    #[test]
+    fn stp_configuration_bpdu() {
+        let mut data = [0; 35];
+        let testee = ConfigBpduPacket::new(&data).unwrap();
+        assert_eq!(35, testee.header_len());
+        assert_eq!(None, testee.version_1_length());
+
+        data[3] = 0x80;
+        assert_eq!(
+            BpduType::TOPOLOGY_CHANGE_NOTIFICATION,
+            BpduPacket::new(&data[..4]).unwrap().bpdu_type()
+        );
+    }
+
+    #[test]
+    fn set_version_1_length_on_stp() {
+        let mut data = [0; 36];
+        assert!(!MutConfigBpduPacket::new(&mut data).unwrap().set_version_1_length(1));
+        assert_eq!(0, data[35]);
+        data[2] = 2;
+        assert!(!MutConfigBpduPacket::new(&mut data[..35]).unwrap().set_version_1_length(1));
+        assert!(MutConfigBpduPacket::new(&mut data).unwrap().set_version_1_length(1));
+        assert_eq!(1, data[35]);
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        BpduPacketBuf => |packet| packet.bpdu_type() == BpduType::TOPOLOGY_CHANGE_NOTIFICATION,
+        ConfigBpduPacketBuf => |packet| packet.bpdu_type() == BpduType::CONFIGURATION
+    );
+}
@@ -0,0 +1,490 @@
+use checksum;
+use error::{self, ParseError};
+use ip::Protocol;
+use std::cmp;
+use std::fmt;
+use std::net::IpAddr;
+use types::*;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+mod options;
+pub use self::options::*;
+
+packet!(
+    TcpPacket,
+    MutTcpPacket,
+    TcpPacketBuf,
+    20,
+    |data| usize::from(data[12] >> 4) * 4,
+    init |data| data[12] = 0x50 | (data[12] & 0x0f)
+);
+
+fields!(TcpPacket,
+    SOURCE_PORT: 0, 16;
+    DESTINATION_PORT: 16, 16;
+    SEQUENCE_NUMBER: 32, 32;
+    ACKNOWLEDGMENT_NUMBER: 64, 32;
+    DATA_OFFSET: 96, 4;
+    FLAGS: 103, 9;
+    WINDOW_SIZE: 112, 16;
+    CHECKSUM: 128, 16;
+    URGENT_POINTER: 144, 16;
+);
+
+getters!(TcpPacket
+    pub fn source_port(&self) -> u16 {
+        read_field!(self.0, fields::SOURCE_PORT, u16)
+    }
+
+    pub fn destination_port(&self) -> u16 {
+        read_field!(self.0, fields::DESTINATION_PORT, u16)
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        read_field!(self.0, fields::SEQUENCE_NUMBER, u32)
+    }
+
+    pub fn acknowledgment_number(&self) -> u32 {
+        read_field!(self.0, fields::ACKNOWLEDGMENT_NUMBER, u32)
+    }
+
+    /// Returns the size of the header in 32 bit words.
+    pub fn data_offset(&self) -> u4 {
+        read_field!(self.0, fields::DATA_OFFSET, u8)
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_field!(self.0, fields::FLAGS, u16))
+    }
+
+    pub fn window_size(&self) -> u16 {
+        read_field!(self.0, fields::WINDOW_SIZE, u16)
+    }
+
+    pub fn checksum(&self) -> u16 {
+        read_field!(self.0, fields::CHECKSUM, u16)
+    }
+
+    pub fn urgent_pointer(&self) -> u16 {
+        read_field!(self.0, fields::URGENT_POINTER, u16)
+    }
+
+    /// Returns an iterator over the options in the header.
+    pub fn options(&self) -> TcpOptions<'a> {
+        TcpOptions::new(&self.0[Self::MIN_LEN..self.header_len()])
+    }
+
+    /// Returns the raw options part of the header. Everything between the fixed header and the
+    /// offset given by `data_offset`.
+    pub fn options_raw(&self) -> &[u8] {
+        &self.0[Self::MIN_LEN..self.header_len()]
+    }
+);
+
+setters!(MutTcpPacket
+    pub fn set_source_port(&mut self, source_port: u16) {
+        write_field!(self.0, fields::SOURCE_PORT, source_port, u16);
+    }
+
+    pub fn set_destination_port(&mut self, destination_port: u16) {
+        write_field!(self.0, fields::DESTINATION_PORT, destination_port, u16);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        write_field!(self.0, fields::SEQUENCE_NUMBER, sequence_number, u32);
+    }
+
+    pub fn set_acknowledgment_number(&mut self, acknowledgment_number: u32) {
+        write_field!(self.0, fields::ACKNOWLEDGMENT_NUMBER, acknowledgment_number, u32);
+    }
+
+    pub fn set_data_offset(&mut self, data_offset: u4) {
+        write_field!(self.0, fields::DATA_OFFSET, data_offset, u8);
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_field!(self.0, fields::FLAGS, flags.bits(), u16);
+    }
+
+    pub fn set_window_size(&mut self, window_size: u16) {
+        write_field!(self.0, fields::WINDOW_SIZE, window_size, u16);
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        write_field!(self.0, fields::CHECKSUM, checksum, u16);
+    }
+
+    pub fn set_urgent_pointer(&mut self, urgent_pointer: u16) {
+        write_field!(self.0, fields::URGENT_POINTER, urgent_pointer, u16);
+    }
+);
+
+impl<'a> TcpPacket<'a> {
+    /// Creates a new immutable packet like `new`, but also validates it. The data offset must
+    /// cover the fixed header and fit in `data`.
+    pub fn parse(data: &'a [u8]) -> Result<TcpPacket<'a>, ParseError> {
+        error::check_len(data, Self::MIN_LEN)?;
+        let packet = TcpPacket(data);
+        let header_len = usize::from(packet.data_offset()) * 4;
+        if header_len < Self::MIN_LEN || header_len > data.len() {
+            return Err(ParseError::InvalidHeaderLength(header_len));
+        }
+        Ok(packet)
+    }
+
+    /// Computes the checksum of this segment when sent between the given addresses, ignoring
+    /// the current value of the checksum field. The backing slice is assumed to hold exactly
+    /// the TCP segment. Returns `None` if the addresses are not of the same IP version.
+    pub fn calculate_checksum(&self, source: IpAddr, destination: IpAddr) -> Option<u16> {
+        let segments = [&self.0[..16], &[0, 0], &self.0[18..]];
+        checksum::transport_checksum(source, destination, Protocol::TCP, &segments)
+    }
+
+    /// Returns true if the checksum field is valid for this segment sent between the given
+    /// addresses.
+    pub fn verify_checksum(&self, source: IpAddr, destination: IpAddr) -> bool {
+        checksum::transport_checksum(source, destination, Protocol::TCP, &[self.0]) == Some(0)
+    }
+}
+
+impl<'a> MutTcpPacket<'a> {
+    /// Computes the checksum of this segment and writes it to the checksum field. See
+    /// `TcpPacket::calculate_checksum` for details. Returns the written checksum.
+    pub fn update_checksum(&mut self, source: IpAddr, destination: IpAddr) -> Option<u16> {
+        let checksum = self.as_immutable().calculate_checksum(source, destination)?;
+        self.set_checksum(checksum);
+        Some(checksum)
+    }
+
+    /// Returns a mutable slice to the raw options part of the header. Everything between the
+    /// fixed header and the offset given by `data_offset`.
+    pub fn options_raw(&mut self) -> &mut [u8] {
+        let header_len = self.header_len();
+        &mut self.0[Self::MIN_LEN..header_len]
+    }
+
+    /// Encodes `options`, padded to a multiple of four bytes, after the fixed header and updates
+    /// the data offset to match. Returns the resulting header length, or `None` if the options
+    /// do not fit in the backing slice or in the 40 bytes available for options.
+    pub fn set_options(&mut self, options: &[TcpOption]) -> Option<usize> {
+        let end = cmp::min(self.0.len(), MAX_HEADER_LEN);
+        let header_len = Self::MIN_LEN + write_options(&mut self.0[Self::MIN_LEN..end], options)?;
+        self.set_data_offset((header_len / 4) as u4);
+        Some(header_len)
+    }
+}
+
+/// The largest header the data offset can describe.
+const MAX_HEADER_LEN: usize = 60;
+
+
+/// The fields of a TCP header as a plain value, one field per getter on `TcpPacket`. Options
+/// are not included. Implements `Serialize` and `Deserialize` with the `serde` feature enabled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TcpHeader {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub sequence_number: u32,
+    pub acknowledgment_number: u32,
+    pub data_offset: u4,
+    pub flags: Flags,
+    pub window_size: u16,
+    pub checksum: u16,
+    pub urgent_pointer: u16,
+}
+
+impl<'a> TcpPacket<'a> {
+    /// Reads all fields of the fixed header into a `TcpHeader`.
+    pub fn to_header(&self) -> TcpHeader {
+        TcpHeader {
+            source_port: self.source_port(),
+            destination_port: self.destination_port(),
+            sequence_number: self.sequence_number(),
+            acknowledgment_number: self.acknowledgment_number(),
+            data_offset: self.data_offset(),
+            flags: self.flags(),
+            window_size: self.window_size(),
+            checksum: self.checksum(),
+            urgent_pointer: self.urgent_pointer(),
+        }
+    }
+}
+
+impl<'a> MutTcpPacket<'a> {
+    /// Writes all fields of `header` to the fixed header, as they are. The checksum is not
+    /// recomputed and the options are left untouched.
+    pub fn set_header(&mut self, header: &TcpHeader) {
+        self.set_source_port(header.source_port);
+        self.set_destination_port(header.destination_port);
+        self.set_sequence_number(header.sequence_number);
+        self.set_acknowledgment_number(header.acknowledgment_number);
+        self.set_data_offset(header.data_offset);
+        self.set_flags(header.flags);
+        self.set_window_size(header.window_size);
+        self.set_checksum(header.checksum);
+        self.set_urgent_pointer(header.urgent_pointer);
+    }
+}
+
+
+bitflags! {
+    /// Bitmasks for the nine bit flags field in TCP
+    pub struct Flags: u9 {
+        /// A bitmask with a one in the "ECN-nonce concealment protection" position.
+        const NS = 0b1_0000_0000;
+        /// A bitmask with a one in the "Congestion Window Reduced" position.
+        const CWR = 0b0_1000_0000;
+        /// A bitmask with a one in the "ECN-Echo" position.
+        const ECE = 0b0_0100_0000;
+        /// A bitmask with a one in the "Urgent pointer field is significant" position.
+        const URG = 0b0_0010_0000;
+        /// A bitmask with a one in the "Acknowledgment field is significant" position.
+        const ACK = 0b0_0001_0000;
+        /// A bitmask with a one in the "Push function" position.
+        const PSH = 0b0_0000_1000;
+        /// A bitmask with a one in the "Reset the connection" position.
+        const RST = 0b0_0000_0100;
+        /// A bitmask with a one in the "Synchronize sequence numbers" position.
+        const SYN = 0b0_0000_0010;
+        /// A bitmask with a one in the "No more data from sender" position.
+        const FIN = 0b0_0000_0001;
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Flags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bits().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Flags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = u16::deserialize(deserializer)?;
+        Flags::from_bits(bits)
+            .ok_or_else(|| de::Error::custom(format!("invalid TCP flags {:#x}", bits)))
+    }
+}
+
+
+impl<'a> fmt::Display for TcpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TCP {} -> {} flags={:?} seq={} ack={} win={} len={}",
+            self.source_port(),
+            self.destination_port(),
+            self.flags(),
+            self.sequence_number(),
+            self.acknowledgment_number(),
+            self.window_size(),
+            self.payload().len()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let mut data = [0; 23];
+        data[..4].copy_from_slice(&[0x04, 0xd2, 0x00, 0x50]);
+        data[7] = 1;
+        data[12..16].copy_from_slice(&[0x50, 0x12, 0xff, 0xff]);
+        assert_eq!(
+            "TCP 1234 -> 80 flags=ACK | SYN seq=1 ack=0 win=65535 len=3",
+            TcpPacket::new(&data).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn min_length() {
+        assert_eq!(TcpPacket::MIN_LEN, 20);
+    }
+
+    #[test]
+    fn too_short_slice() {
+        assert!(TcpPacket::new(&[0; 19]).is_none());
+    }
+
+    macro_rules! tcp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutTcpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    tcp_setget_test!(source_port, set_source_port, 0xabcd, 0, [0xab, 0xcd]);
+    tcp_setget_test!(destination_port, set_destination_port, 0xabcd, 2, [0xab, 0xcd]);
+    tcp_setget_test!(
+        sequence_number,
+        set_sequence_number,
+        0xdeadbeef,
+        4,
+        [0xde, 0xad, 0xbe, 0xef]
+    );
+    tcp_setget_test!(
+        acknowledgment_number,
+        set_acknowledgment_number,
+        0xdeadbeef,
+        8,
+        [0xde, 0xad, 0xbe, 0xef]
+    );
+    tcp_setget_test!(data_offset, set_data_offset, 0xf, 12, [0xf0]);
+    tcp_setget_test!(flags, set_flags, Flags::all(), 12, [0x01, 0xff]);
+    tcp_setget_test!(window_size, set_window_size, 0xabcd, 14, [0xab, 0xcd]);
+    tcp_setget_test!(checksum, set_checksum, 0xabcd, 16, [0xab, 0xcd]);
+    tcp_setget_test!(urgent_pointer, set_urgent_pointer, 0xabcd, 18, [0xab, 0xcd]);
+
+    #[test]
+    fn calculate_and_verify_checksum() {
+        let source = IpAddr::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]);
+        let destination = IpAddr::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 2]);
+        let mut data = [0; 21];
+        data[12] = 0x50;
+        data[20] = 0x61;
+        let checksum = {
+            let mut testee = MutTcpPacket::new(&mut data).unwrap();
+            testee.update_checksum(source, destination).unwrap()
+        };
+        let testee = TcpPacket::new(&data).unwrap();
+        assert_eq!(checksum, testee.checksum());
+        assert_eq!(Some(checksum), testee.calculate_checksum(source, destination));
+        assert!(testee.verify_checksum(source, destination));
+        assert!(!testee.verify_checksum(IpAddr::from([10, 0, 0, 1]), destination));
+        data[20] = 0x62;
+        assert!(!TcpPacket::new(&data).unwrap().verify_checksum(source, destination));
+    }
+
+    #[test]
+    fn parse() {
+        let mut data = [0; 24];
+        data[12] = 0x60;
+        assert!(TcpPacket::parse(&data).is_ok());
+        assert_eq!(Err(ParseError::InvalidHeaderLength(24)), TcpPacket::parse(&data[..23]));
+        data[12] = 0x40;
+        assert_eq!(Err(ParseError::InvalidHeaderLength(16)), TcpPacket::parse(&data));
+        assert_eq!(
+            Err(ParseError::TooShort { min_len: 20, len: 19 }),
+            TcpPacket::parse(&data[..19])
+        );
+    }
+
+    #[test]
+    fn getters_alternating_bits() {
+        let backing_data = [0b1010_1010; 20];
+        let testee = TcpPacket::new(&backing_data).unwrap();
+        assert_eq!(0b1010, testee.data_offset());
+        assert_eq!(
+            Flags::CWR | Flags::URG | Flags::PSH | Flags::SYN,
+            testee.flags()
+        );
+    }
+
+    #[test]
+    fn flags_keep_data_offset() {
+        let mut backing_data = [0; 20];
+        let mut testee = MutTcpPacket::new(&mut backing_data).unwrap();
+        testee.set_data_offset(5);
+        testee.set_flags(Flags::SYN | Flags::ACK);
+        assert_eq!(5, testee.as_immutable().data_offset());
+        testee.set_flags(Flags::empty());
+        assert_eq!(5, testee.as_immutable().data_offset());
+    }
+
+    #[test]
+    fn options_and_payload() {
+        let mut data = [0; 27];
+        data[12] = 6 << 4;
+        data[20..24].copy_from_slice(&[1, 1, 1, 0]);
+        data[24..].copy_from_slice(&[7, 8, 9]);
+        let testee = TcpPacket::new(&data).unwrap();
+        assert_eq!(24, testee.header_len());
+        assert_eq!(&[1, 1, 1, 0], testee.options_raw());
+        assert_eq!(
+            vec![TcpOption::Nop, TcpOption::Nop, TcpOption::Nop, TcpOption::Eol],
+            testee.options().collect::<Vec<_>>()
+        );
+        assert_eq!(&[7, 8, 9], testee.payload());
+        assert!(format!("{:?}", testee).contains(", options: [Nop, Nop, Nop, Eol], "));
+    }
+
+    #[test]
+    fn invalid_data_offset() {
+        let mut data = [0; 24];
+        data[12] = 2 << 4;
+        assert_eq!(20, TcpPacket::new(&data).unwrap().header_len());
+        data[12] = 15 << 4;
+        let testee = TcpPacket::new(&data).unwrap();
+        assert_eq!(24, testee.header_len());
+        assert!(testee.payload().is_empty());
+    }
+
+    #[test]
+    fn set_options() {
+        let mut data = [0; 40];
+        let header_len = MutTcpPacket::new(&mut data)
+            .unwrap()
+            .set_options(&[TcpOption::Mss(1460), TcpOption::WindowScale(7)])
+            .unwrap();
+        assert_eq!(28, header_len);
+
+        let testee = TcpPacket::new(&data).unwrap();
+        assert_eq!(7, testee.data_offset());
+        assert_eq!(
+            vec![TcpOption::Mss(1460), TcpOption::WindowScale(7), TcpOption::Eol],
+            testee.options().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn set_options_too_long() {
+        let mut data = [0; 100];
+        let mut testee = MutTcpPacket::new(&mut data).unwrap();
+        assert_eq!(None, testee.set_options(&[TcpOption::Nop; 41]));
+        assert_eq!(Some(60), testee.set_options(&[TcpOption::Nop; 40]));
+    }
+
+
+    fn header() -> TcpHeader {
+        TcpHeader {
+            source_port: 1234,
+            destination_port: 80,
+            sequence_number: 0x0102_0304,
+            acknowledgment_number: 0x0506_0708,
+            data_offset: 5,
+            flags: Flags::SYN | Flags::ACK,
+            window_size: 65535,
+            checksum: 0xabcd,
+            urgent_pointer: 0,
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let mut buffer = [0; 20];
+        MutTcpPacket::new(&mut buffer).unwrap().set_header(&header());
+        assert_eq!(header(), TcpPacket::new(&buffer).unwrap().to_header());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn header_serde() {
+        extern crate serde_json;
+
+        let json = serde_json::to_string(&header()).unwrap();
+        assert!(json.contains("\"flags\":18,"));
+        assert_eq!(header(), serde_json::from_str(&json).unwrap());
+        assert!(serde_json::from_str::<Flags>("512").is_err());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        TcpPacketBuf => |packet| TcpPacket::parse(packet.data()).is_ok()
+    );
+}
@@ -0,0 +1,328 @@
+/// The 8 bit kind field of a TCP option. See [IANA's list] for the full definition.
+///
+/// [IANA's list]: https://www.iana.org/assignments/tcp-parameters/tcp-parameters.xhtml
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OptionKind(pub u8);
+
+impl OptionKind {
+    pub const EOL: OptionKind = OptionKind(0);
+    pub const NOP: OptionKind = OptionKind(1);
+    pub const MSS: OptionKind = OptionKind(2);
+    pub const WINDOW_SCALE: OptionKind = OptionKind(3);
+    pub const SACK_PERMITTED: OptionKind = OptionKind(4);
+    pub const SACK: OptionKind = OptionKind(5);
+    pub const TIMESTAMPS: OptionKind = OptionKind(8);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// A single option in a TCP header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TcpOption<'a> {
+    /// End of option list.
+    Eol,
+    /// No operation, used for padding between options.
+    Nop,
+    /// Maximum segment size.
+    Mss(u16),
+    /// Window scale shift count.
+    WindowScale(u8),
+    /// Selective acknowledgments are permitted.
+    SackPermitted,
+    /// Selective acknowledgment blocks.
+    Sack(SackBlocks),
+    /// Timestamp value and timestamp echo reply.
+    Timestamps(u32, u32),
+    /// Any other option, or one of the above with an invalid length, with its raw data.
+    Other(OptionKind, &'a [u8]),
+}
+
+impl<'a> TcpOption<'a> {
+    /// Returns the number of bytes this option occupies when encoded.
+    pub fn encoded_len(&self) -> usize {
+        match *self {
+            TcpOption::Eol | TcpOption::Nop => 1,
+            TcpOption::Mss(_) => 4,
+            TcpOption::WindowScale(_) => 3,
+            TcpOption::SackPermitted => 2,
+            TcpOption::Sack(ref blocks) => 2 + blocks.blocks().len() * 8,
+            TcpOption::Timestamps(..) => 10,
+            TcpOption::Other(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Encodes this option into the beginning of `buffer`. Returns the number of bytes written,
+    /// or `None` if the option does not fit in `buffer` or has more than 253 bytes of data.
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len();
+        if buffer.len() < len || len > 255 {
+            return None;
+        }
+        match *self {
+            TcpOption::Eol => buffer[0] = OptionKind::EOL.value(),
+            TcpOption::Nop => buffer[0] = OptionKind::NOP.value(),
+            TcpOption::Mss(mss) => {
+                buffer[0] = OptionKind::MSS.value();
+                write_offset!(buffer, 2, mss, u16, to_be);
+            }
+            TcpOption::WindowScale(shift) => {
+                buffer[0] = OptionKind::WINDOW_SCALE.value();
+                buffer[2] = shift;
+            }
+            TcpOption::SackPermitted => buffer[0] = OptionKind::SACK_PERMITTED.value(),
+            TcpOption::Sack(ref blocks) => {
+                buffer[0] = OptionKind::SACK.value();
+                for (i, &(left, right)) in blocks.blocks().iter().enumerate() {
+                    write_offset!(buffer, 2 + i * 8, left, u32, to_be);
+                    write_offset!(buffer, 6 + i * 8, right, u32, to_be);
+                }
+            }
+            TcpOption::Timestamps(value, echo_reply) => {
+                buffer[0] = OptionKind::TIMESTAMPS.value();
+                write_offset!(buffer, 2, value, u32, to_be);
+                write_offset!(buffer, 6, echo_reply, u32, to_be);
+            }
+            TcpOption::Other(kind, data) => {
+                buffer[0] = kind.value();
+                buffer[2..len].copy_from_slice(data);
+            }
+        }
+        if len > 1 {
+            buffer[1] = len as u8;
+        }
+        Some(len)
+    }
+}
+
+/// Up to four selective acknowledgment blocks, each the left and right edge of a block of
+/// received sequence numbers.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SackBlocks {
+    blocks: [(u32, u32); 4],
+    len: usize,
+}
+
+impl SackBlocks {
+    /// The maximum number of blocks that fit in a SACK option.
+    pub const MAX_BLOCKS: usize = 4;
+
+    /// Creates a set of SACK blocks. Returns `None` if more than `MAX_BLOCKS` blocks are given.
+    pub fn new(blocks: &[(u32, u32)]) -> Option<SackBlocks> {
+        if blocks.len() > Self::MAX_BLOCKS {
+            return None;
+        }
+        let mut sack_blocks = SackBlocks::default();
+        sack_blocks.blocks[..blocks.len()].copy_from_slice(blocks);
+        sack_blocks.len = blocks.len();
+        Some(sack_blocks)
+    }
+
+    /// Returns the blocks as a slice of left and right edges.
+    pub fn blocks(&self) -> &[(u32, u32)] {
+        &self.blocks[..self.len]
+    }
+
+    fn from_data(data: &[u8]) -> Option<SackBlocks> {
+        let len = data.len();
+        if len == 0 || !len.is_multiple_of(8) || len > Self::MAX_BLOCKS * 8 {
+            return None;
+        }
+        let mut sack_blocks = SackBlocks::default();
+        for block in data.chunks(8) {
+            sack_blocks.blocks[sack_blocks.len] = (
+                read_offset!(block, 0, u32, from_be),
+                read_offset!(block, 4, u32, from_be),
+            );
+            sack_blocks.len += 1;
+        }
+        Some(sack_blocks)
+    }
+}
+
+/// Encodes `options` into the beginning of `buffer`, followed by an end of option list and zero
+/// padding up to the next multiple of four bytes. Returns the number of bytes written, or `None`
+/// if the options and padding do not fit in `buffer`.
+pub fn write_options(buffer: &mut [u8], options: &[TcpOption]) -> Option<usize> {
+    let mut offset = 0;
+    for option in options {
+        offset += option.write(&mut buffer[offset..])?;
+    }
+    let len = offset.div_ceil(4) * 4;
+    if buffer.len() < len {
+        return None;
+    }
+    for byte in &mut buffer[offset..len] {
+        *byte = OptionKind::EOL.value();
+    }
+    Some(len)
+}
+
+/// Iterator over the options in a TCP header. Iteration stops after an end of option list, or
+/// at the first option that does not fit in the data.
+#[derive(Clone)]
+pub struct TcpOptions<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(TcpOptions<'a>);
+
+impl<'a> TcpOptions<'a> {
+    /// Creates an iterator over the options encoded in `data`.
+    pub fn new(data: &'a [u8]) -> TcpOptions<'a> {
+        TcpOptions { data }
+    }
+}
+
+impl<'a> Iterator for TcpOptions<'a> {
+    type Item = TcpOption<'a>;
+
+    fn next(&mut self) -> Option<TcpOption<'a>> {
+        let (&kind, rest) = self.data.split_first()?;
+        let kind = OptionKind(kind);
+        match kind {
+            OptionKind::EOL => {
+                self.data = &[];
+                return Some(TcpOption::Eol);
+            }
+            OptionKind::NOP => {
+                self.data = rest;
+                return Some(TcpOption::Nop);
+            }
+            _ => (),
+        }
+
+        let len = match rest.first() {
+            Some(&len) if len >= 2 && usize::from(len) <= self.data.len() => usize::from(len),
+            _ => {
+                self.data = &[];
+                return None;
+            }
+        };
+        let (option, rest) = self.data.split_at(len);
+        self.data = rest;
+        let value = &option[2..];
+
+        Some(match (kind, len) {
+            (OptionKind::MSS, 4) => TcpOption::Mss(read_offset!(value, 0, u16, from_be)),
+            (OptionKind::WINDOW_SCALE, 3) => TcpOption::WindowScale(value[0]),
+            (OptionKind::SACK_PERMITTED, 2) => TcpOption::SackPermitted,
+            (OptionKind::SACK, _) => match SackBlocks::from_data(value) {
+                Some(blocks) => TcpOption::Sack(blocks),
+                None => TcpOption::Other(kind, value),
+            },
+            (OptionKind::TIMESTAMPS, 10) => TcpOption::Timestamps(
+                read_offset!(value, 0, u32, from_be),
+                read_offset!(value, 4, u32, from_be),
+            ),
+            _ => TcpOption::Other(kind, value),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syn_options() {
+        // Options from a typical Linux SYN
+        let data = [
+            0x02, 0x04, 0x05, 0xb4, // MSS 1460
+            0x04, 0x02, // SACK permitted
+            0x08, 0x0a, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, // Timestamps
+            0x01, // NOP
+            0x03, 0x03, 0x07, // Window scale 7
+        ];
+        assert_eq!(
+            vec![
+                TcpOption::Mss(1460),
+                TcpOption::SackPermitted,
+                TcpOption::Timestamps(1, 0),
+                TcpOption::Nop,
+                TcpOption::WindowScale(7),
+            ],
+            TcpOptions::new(&data).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn sack() {
+        let data = [0x05, 0x12, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4];
+        let options = TcpOptions::new(&data).collect::<Vec<_>>();
+        let blocks = SackBlocks::new(&[(1, 2), (3, 4)]).unwrap();
+        assert_eq!(vec![TcpOption::Sack(blocks)], options);
+    }
+
+    #[test]
+    fn too_many_sack_blocks() {
+        assert!(SackBlocks::new(&[(0, 0); 5]).is_none());
+    }
+
+    #[test]
+    fn stop_at_eol() {
+        let data = [0x01, 0x00, 0x02, 0x04, 0x05, 0xb4];
+        assert_eq!(
+            vec![TcpOption::Nop, TcpOption::Eol],
+            TcpOptions::new(&data).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn invalid_length_is_other() {
+        let data = [0x02, 0x03, 0x05, 0x03, 0x02];
+        assert_eq!(
+            vec![
+                TcpOption::Other(OptionKind::MSS, &[0x05]),
+                TcpOption::Other(OptionKind::WINDOW_SCALE, &[]),
+            ],
+            TcpOptions::new(&data).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn truncated_option() {
+        let data = [0x01, 0x08, 0x0a, 0x00];
+        let mut options = TcpOptions::new(&data);
+        assert_eq!(Some(TcpOption::Nop), options.next());
+        assert_eq!(None, options.next());
+        assert_eq!(None, TcpOptions::new(&[0x02, 0x01, 0x00]).next());
+    }
+
+    #[test]
+    fn write_read_roundtrip() {
+        let options = [
+            TcpOption::Mss(1460),
+            TcpOption::SackPermitted,
+            TcpOption::Timestamps(0xdeadbeef, 0x01020304),
+            TcpOption::Nop,
+            TcpOption::WindowScale(7),
+            TcpOption::Sack(SackBlocks::new(&[(10, 20)]).unwrap()),
+            TcpOption::Other(OptionKind(30), &[1, 2]),
+        ];
+        let mut buffer = [0xff; 40];
+        let len = write_options(&mut buffer, &options).unwrap();
+        assert_eq!(36, len);
+        let mut expected = options.to_vec();
+        expected.push(TcpOption::Eol);
+        assert_eq!(expected, TcpOptions::new(&buffer[..len]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn write_padding() {
+        let mut buffer = [0xff; 8];
+        assert_eq!(Some(4), write_options(&mut buffer, &[TcpOption::WindowScale(2)]));
+        assert_eq!(&[0x03, 0x03, 0x02, 0x00], &buffer[..4]);
+        assert_eq!(Some(0), write_options(&mut buffer, &[]));
+    }
+
+    #[test]
+    fn write_too_short() {
+        let mut buffer = [0; 5];
+        assert_eq!(None, write_options(&mut buffer, &[TcpOption::Mss(1), TcpOption::Nop]));
+        assert_eq!(None, TcpOption::Timestamps(0, 0).write(&mut buffer));
+    }
+}
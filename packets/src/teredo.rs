@@ -0,0 +1,212 @@
+//! Teredo, tunneling IPv6 over UDP through NATs, RFC 4380.
+//!
+//! A Teredo UDP payload is an IPv6 packet, optionally preceded by an authentication indicator
+//! and an origin indicator, in that order, see `Indicators`. Teredo addresses embed the IPv4
+//! address of the Teredo server and the obfuscated public address and port of the client, see
+//! `TeredoAddress`.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+use ipv6::Ipv6Packet;
+
+/// The UDP port Teredo servers listen on.
+pub const UDP_PORT: u16 = 3544;
+
+/// The 2001::/32 prefix of all Teredo addresses.
+pub const PREFIX: Ipv6Addr = Ipv6Addr::new(0x2001, 0, 0, 0, 0, 0, 0, 0);
+
+/// The indicators at the start of a Teredo UDP payload, and the rest of the payload after them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Indicators<'a> {
+    pub authentication: Option<AuthenticationIndicator<'a>>,
+    pub origin: Option<OriginIndicator>,
+    /// The payload after the indicators, normally an IPv6 packet.
+    pub rest: &'a [u8],
+}
+
+impl<'a> Indicators<'a> {
+    /// Parses the indicators at the start of the Teredo UDP payload `data`. A truncated
+    /// indicator is left in `rest`.
+    pub fn parse(data: &'a [u8]) -> Indicators<'a> {
+        let (authentication, data) = match data.get(..4) {
+            Some(&[0, 1, id_len, au_len]) => {
+                let len = 4 + usize::from(id_len) + usize::from(au_len) + 9;
+                if len <= data.len() {
+                    let (indicator, rest) = data.split_at(len);
+                    let (client_identifier, fields) = indicator[4..].split_at(usize::from(id_len));
+                    let (authentication_value, fields) = fields.split_at(usize::from(au_len));
+                    let authentication = AuthenticationIndicator {
+                        client_identifier,
+                        authentication_value,
+                        nonce: read_offset!(fields, 0, [u8; 8]),
+                        confirmation: fields[8],
+                    };
+                    (Some(authentication), rest)
+                } else {
+                    (None, data)
+                }
+            }
+            _ => (None, data),
+        };
+        let (origin, rest) = match data.get(..8) {
+            Some(indicator) if indicator[..2] == [0, 0] => {
+                let origin = OriginIndicator {
+                    port: !read_offset!(indicator, 2, u16, from_be),
+                    address: Ipv4Addr::from(!read_offset!(indicator, 4, u32, from_be)),
+                };
+                (Some(origin), &data[8..])
+            }
+            _ => (None, data),
+        };
+        Indicators { authentication, origin, rest }
+    }
+
+    /// Returns the tunneled IPv6 packet, or `None` if it is too short or of another IP
+    /// version.
+    pub fn ipv6(&self) -> Option<Ipv6Packet<'a>> {
+        Ipv6Packet::new(self.rest).filter(|packet| packet.version() == 6)
+    }
+}
+
+/// Encodes an origin indicator into the beginning of `buffer`, obfuscating the port and
+/// address. Returns the number of bytes written, or `None` if the indicator does not fit in
+/// `buffer`.
+pub fn write_origin_indicator(buffer: &mut [u8], origin: OriginIndicator) -> Option<usize> {
+    let indicator = buffer.get_mut(..8)?;
+    write_offset!(indicator, 0, 0, u16, to_be);
+    write_offset!(indicator, 2, !origin.port, u16, to_be);
+    write_offset!(indicator, 4, !u32::from(origin.address), u32, to_be);
+    Some(8)
+}
+
+
+/// An authentication indicator, exchanged with the Teredo server during qualification.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AuthenticationIndicator<'a> {
+    pub client_identifier: &'a [u8],
+    pub authentication_value: &'a [u8],
+    pub nonce: [u8; 8],
+    pub confirmation: u8,
+}
+
+/// An origin indicator, telling a client its public address and port as seen by the server.
+/// The fields hold the values after undoing the obfuscation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OriginIndicator {
+    pub port: u16,
+    pub address: Ipv4Addr,
+}
+
+/// The parts of a Teredo address. The client port and address are kept without obfuscation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TeredoAddress {
+    /// The IPv4 address of the Teredo server of the client.
+    pub server: Ipv4Addr,
+    pub flags: u16,
+    /// The public UDP port of the client.
+    pub port: u16,
+    /// The public IPv4 address of the client.
+    pub client: Ipv4Addr,
+}
+
+impl TeredoAddress {
+    /// The flag set for clients behind a cone NAT.
+    pub const CONE: u16 = 0x8000;
+
+    /// Splits up `address`, or returns `None` if it is not within the Teredo prefix.
+    pub fn from_ipv6(address: Ipv6Addr) -> Option<TeredoAddress> {
+        let segments = address.segments();
+        if segments[..2] != PREFIX.segments()[..2] {
+            return None;
+        }
+        let octets = address.octets();
+        Some(TeredoAddress {
+            server: Ipv4Addr::new(octets[4], octets[5], octets[6], octets[7]),
+            flags: segments[4],
+            port: !segments[5],
+            client: Ipv4Addr::new(!octets[12], !octets[13], !octets[14], !octets[15]),
+        })
+    }
+
+    /// Returns the Teredo address with these parts.
+    pub fn to_ipv6(&self) -> Ipv6Addr {
+        let server = self.server.octets();
+        let client = self.client.octets();
+        let mut octets = PREFIX.octets();
+        octets[4..8].copy_from_slice(&server);
+        octets[8..10].copy_from_slice(&self.flags.to_be_bytes());
+        octets[10..12].copy_from_slice(&(!self.port).to_be_bytes());
+        for (octet, &client) in octets[12..].iter_mut().zip(&client) {
+            *octet = !client;
+        }
+        Ipv6Addr::from(octets)
+    }
+
+    /// Returns true if the client is behind a cone NAT.
+    pub fn is_cone(&self) -> bool {
+        self.flags & TeredoAddress::CONE != 0
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address() {
+        // The example from RFC 4380 section 4
+        let address = "2001:0:4136:e378:8000:63bf:3fff:fdd2".parse::<Ipv6Addr>().unwrap();
+        let testee = TeredoAddress::from_ipv6(address).unwrap();
+        assert_eq!(Ipv4Addr::new(65, 54, 227, 120), testee.server);
+        assert!(testee.is_cone());
+        assert_eq!(40000, testee.port);
+        assert_eq!(Ipv4Addr::new(192, 0, 2, 45), testee.client);
+        assert_eq!(address, testee.to_ipv6());
+        assert_eq!(None, TeredoAddress::from_ipv6("2002::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn plain_ipv6() {
+        let mut data = [0; 40];
+        data[0] = 0x60;
+        let testee = Indicators::parse(&data);
+        assert_eq!(None, testee.authentication);
+        assert_eq!(None, testee.origin);
+        assert_eq!(40, testee.rest.len());
+        assert!(testee.ipv6().is_some());
+    }
+
+    #[test]
+    fn indicators() {
+        let mut data = [0; 4 + 2 + 1 + 9 + 8 + 40];
+        data[..7].copy_from_slice(&[0, 1, 2, 1, b'i', b'd', 0xab]);
+        data[7..15].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let origin = OriginIndicator {
+            port: 40000,
+            address: Ipv4Addr::new(192, 0, 2, 45),
+        };
+        assert_eq!(Some(8), write_origin_indicator(&mut data[16..], origin));
+        assert_eq!(&[0, 0, 0x63, 0xbf, 0x3f, 0xff, 0xfd, 0xd2], &data[16..24]);
+        data[24] = 0x60;
+
+        let testee = Indicators::parse(&data);
+        let authentication = testee.authentication.unwrap();
+        assert_eq!(b"id", authentication.client_identifier);
+        assert_eq!(&[0xab], authentication.authentication_value);
+        assert_eq!([1, 2, 3, 4, 5, 6, 7, 8], authentication.nonce);
+        assert_eq!(Some(origin), testee.origin);
+        assert_eq!(40, testee.rest.len());
+        assert!(testee.ipv6().is_some());
+
+        // Without the authentication indicator
+        let testee = Indicators::parse(&data[16..]);
+        assert_eq!(None, testee.authentication);
+        assert_eq!(Some(origin), testee.origin);
+
+        // A truncated authentication indicator
+        let testee = Indicators::parse(&data[..15]);
+        assert_eq!(None, testee.authentication);
+        assert_eq!(15, testee.rest.len());
+        assert_eq!(None, testee.ipv6());
+    }
+}
@@ -0,0 +1,490 @@
+//! Trivial File Transfer Protocol, RFC 1350, with the option extension of RFC 2347.
+//!
+//! Every TFTP packet starts with a two byte opcode. `TftpPacket` gives access to it, and its
+//! conversion methods to views of the request, data, acknowledgment and error packets. Strings
+//! in requests and errors are zero terminated.
+
+use std::fmt;
+
+/// The UDP port TFTP servers listen on for requests.
+pub const UDP_PORT: u16 = 69;
+
+/// The number of data bytes in each data packet but the last, unless another block size is
+/// negotiated.
+pub const DEFAULT_BLOCK_SIZE: usize = 512;
+
+macro_rules! tftp_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn opcode(&self) -> Opcode {
+                Opcode(read_offset!(self.0, 0, u16, from_be))
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_opcode(&mut self, opcode: Opcode) {
+                write_offset!(self.0, 0, opcode.value(), u16, to_be);
+            }
+        );
+    }
+}
+
+packet!(
+    /// The opcode common to all TFTP packets. Use one of the conversion methods to get a view
+    /// of the opcode specific fields.
+    TftpPacket,
+    MutTftpPacket,
+    TftpPacketBuf,
+    2
+);
+tftp_common!(TftpPacket, MutTftpPacket);
+
+impl<'a> TftpPacket<'a> {
+    /// Returns the packet as a read or write request, or `None` if it is of another type.
+    pub fn request(&self) -> Option<RequestPacket<'a>> {
+        match self.opcode() {
+            Opcode::RRQ | Opcode::WRQ => RequestPacket::new(self.0),
+            _ => None,
+        }
+    }
+
+    /// Returns the packet as a data packet, or `None` if it is of another type or too short.
+    pub fn data_packet(&self) -> Option<DataPacket<'a>> {
+        self.as_type(Opcode::DATA).and_then(DataPacket::new)
+    }
+
+    /// Returns the packet as an acknowledgment, or `None` if it is of another type or too short.
+    pub fn ack(&self) -> Option<AckPacket<'a>> {
+        self.as_type(Opcode::ACK).and_then(AckPacket::new)
+    }
+
+    /// Returns the packet as an error packet, or `None` if it is of another type or too short.
+    pub fn error(&self) -> Option<ErrorPacket<'a>> {
+        self.as_type(Opcode::ERROR).and_then(ErrorPacket::new)
+    }
+
+    /// Returns the packet as an option acknowledgment, or `None` if it is of another type.
+    pub fn option_ack(&self) -> Option<OptionAckPacket<'a>> {
+        self.as_type(Opcode::OACK).and_then(OptionAckPacket::new)
+    }
+
+    fn as_type(&self, opcode: Opcode) -> Option<&'a [u8]> {
+        if self.opcode() == opcode {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+
+packet!(
+    /// A read or write request. The payload holds the zero terminated file name and transfer
+    /// mode, optionally followed by option names and values.
+    RequestPacket,
+    MutRequestPacket,
+    RequestPacketBuf,
+    2,
+    |_data| 2,
+    init |data| write_offset!(data, 0, Opcode::RRQ.value(), u16, to_be)
+);
+tftp_common!(RequestPacket, MutRequestPacket);
+
+getters!(RequestPacket
+    /// Returns the name of the file to transfer, or `None` if it is missing or not valid UTF-8.
+    pub fn filename(&self) -> Option<&'a str> {
+        let mut data = &self.0[2..];
+        next_string(&mut data)
+    }
+
+    /// Returns the transfer mode, "netascii" or "octet" in any case, or `None` if it is missing
+    /// or not valid UTF-8.
+    pub fn mode(&self) -> Option<&'a str> {
+        let mut data = &self.0[2..];
+        next_string(&mut data)?;
+        next_string(&mut data)
+    }
+
+    /// Returns an iterator over the names and values of the options following the mode.
+    pub fn options(&self) -> TftpOptions<'a> {
+        let mut data = &self.0[2..];
+        if next_string(&mut data).and_then(|_| next_string(&mut data)).is_none() {
+            data = &[];
+        }
+        TftpOptions { data }
+    }
+);
+
+/// Encodes a request with the opcode `opcode`, `Opcode::RRQ` or `Opcode::WRQ`, into the
+/// beginning of `buffer`. Returns the number of bytes written, or `None` if the request does not
+/// fit in `buffer` or one of the strings contains a zero byte.
+pub fn write_request(
+    buffer: &mut [u8],
+    opcode: Opcode,
+    filename: &str,
+    mode: &str,
+    options: &[(&str, &str)],
+) -> Option<usize> {
+    if buffer.len() < 2 {
+        return None;
+    }
+    write_offset!(buffer, 0, opcode.value(), u16, to_be);
+    let mut offset = 2;
+    offset += write_string(&mut buffer[offset..], filename)?;
+    offset += write_string(&mut buffer[offset..], mode)?;
+    for &(name, value) in options {
+        offset += write_string(&mut buffer[offset..], name)?;
+        offset += write_string(&mut buffer[offset..], value)?;
+    }
+    Some(offset)
+}
+
+
+packet!(
+    /// A data packet, carrying one block of the file as the payload. A payload shorter than the
+    /// block size ends the transfer.
+    DataPacket,
+    MutDataPacket,
+    DataPacketBuf,
+    4,
+    |_data| 4,
+    init |data| write_offset!(data, 0, Opcode::DATA.value(), u16, to_be)
+);
+tftp_common!(DataPacket, MutDataPacket);
+
+getters!(DataPacket
+    /// Returns the number of the block, starting at one.
+    pub fn block(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+);
+
+setters!(MutDataPacket
+    pub fn set_block(&mut self, block: u16) {
+        write_offset!(self.0, 2, block, u16, to_be);
+    }
+);
+
+
+packet!(
+    /// An acknowledgment of a data packet, or of a write request with block number zero.
+    AckPacket,
+    MutAckPacket,
+    AckPacketBuf,
+    4,
+    |_data| 4,
+    init |data| write_offset!(data, 0, Opcode::ACK.value(), u16, to_be)
+);
+tftp_common!(AckPacket, MutAckPacket);
+
+getters!(AckPacket
+    /// Returns the number of the acknowledged block.
+    pub fn block(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+);
+
+setters!(MutAckPacket
+    pub fn set_block(&mut self, block: u16) {
+        write_offset!(self.0, 2, block, u16, to_be);
+    }
+);
+
+
+packet!(
+    /// An error packet, terminating the transfer. The payload holds the zero terminated error
+    /// message.
+    ErrorPacket,
+    MutErrorPacket,
+    ErrorPacketBuf,
+    4,
+    |_data| 4,
+    init |data| write_offset!(data, 0, Opcode::ERROR.value(), u16, to_be)
+);
+tftp_common!(ErrorPacket, MutErrorPacket);
+
+getters!(ErrorPacket
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode(read_offset!(self.0, 2, u16, from_be))
+    }
+
+    /// Returns the error message, or `None` if it is missing or not valid UTF-8.
+    pub fn message(&self) -> Option<&'a str> {
+        let mut data = &self.0[4..];
+        next_string(&mut data)
+    }
+);
+
+setters!(MutErrorPacket
+    pub fn set_error_code(&mut self, error_code: ErrorCode) {
+        write_offset!(self.0, 2, error_code.value(), u16, to_be);
+    }
+);
+
+/// Encodes an error packet into the beginning of `buffer`. Returns the number of bytes written,
+/// or `None` if the packet does not fit in `buffer` or `message` contains a zero byte.
+pub fn write_error(buffer: &mut [u8], error_code: ErrorCode, message: &str) -> Option<usize> {
+    if buffer.len() < 4 {
+        return None;
+    }
+    write_offset!(buffer, 0, Opcode::ERROR.value(), u16, to_be);
+    write_offset!(buffer, 2, error_code.value(), u16, to_be);
+    Some(4 + write_string(&mut buffer[4..], message)?)
+}
+
+
+packet!(
+    /// An option acknowledgment, answering a request with the options the server accepted,
+    /// RFC 2347. The payload holds the option names and values.
+    OptionAckPacket,
+    MutOptionAckPacket,
+    OptionAckPacketBuf,
+    2,
+    |_data| 2,
+    init |data| write_offset!(data, 0, Opcode::OACK.value(), u16, to_be)
+);
+tftp_common!(OptionAckPacket, MutOptionAckPacket);
+
+getters!(OptionAckPacket
+    /// Returns an iterator over the names and values of the accepted options.
+    pub fn options(&self) -> TftpOptions<'a> {
+        TftpOptions { data: &self.0[2..] }
+    }
+);
+
+
+/// The opcode of a TFTP packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Opcode(pub u16);
+
+impl Opcode {
+    /// Read request.
+    pub const RRQ: Opcode = Opcode(1);
+    /// Write request.
+    pub const WRQ: Opcode = Opcode(2);
+    pub const DATA: Opcode = Opcode(3);
+    pub const ACK: Opcode = Opcode(4);
+    pub const ERROR: Opcode = Opcode(5);
+    /// Option acknowledgment.
+    pub const OACK: Opcode = Opcode(6);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// The error code of a TFTP error packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ErrorCode(pub u16);
+
+impl ErrorCode {
+    /// Not defined, see the error message.
+    pub const NOT_DEFINED: ErrorCode = ErrorCode(0);
+    pub const FILE_NOT_FOUND: ErrorCode = ErrorCode(1);
+    pub const ACCESS_VIOLATION: ErrorCode = ErrorCode(2);
+    pub const DISK_FULL: ErrorCode = ErrorCode(3);
+    pub const ILLEGAL_OPERATION: ErrorCode = ErrorCode(4);
+    pub const UNKNOWN_TRANSFER_ID: ErrorCode = ErrorCode(5);
+    pub const FILE_ALREADY_EXISTS: ErrorCode = ErrorCode(6);
+    pub const NO_SUCH_USER: ErrorCode = ErrorCode(7);
+    /// The options of the request were refused, RFC 2347.
+    pub const OPTION_NEGOTIATION_FAILED: ErrorCode = ErrorCode(8);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Iterator over the names and values of the options in a request or option acknowledgment.
+/// Iteration stops at the first option that is not zero terminated or not valid UTF-8.
+#[derive(Clone)]
+pub struct TftpOptions<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(TftpOptions<'a>);
+
+impl<'a> Iterator for TftpOptions<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<(&'a str, &'a str)> {
+        let option = next_string(&mut self.data).and_then(|name| {
+            next_string(&mut self.data).map(|value| (name, value))
+        });
+        if option.is_none() {
+            self.data = &[];
+        }
+        option
+    }
+}
+
+/// Splits the zero terminated string at the start of `data` off, or returns `None` if there is
+/// no terminator or the string is not valid UTF-8.
+fn next_string<'a>(data: &mut &'a [u8]) -> Option<&'a str> {
+    let end = data.iter().position(|&byte| byte == 0)?;
+    let string = ::std::str::from_utf8(&data[..end]).ok()?;
+    *data = &data[end + 1..];
+    Some(string)
+}
+
+fn write_string(buffer: &mut [u8], string: &str) -> Option<usize> {
+    let len = string.len() + 1;
+    if buffer.len() < len || string.bytes().any(|byte| byte == 0) {
+        return None;
+    }
+    buffer[..string.len()].copy_from_slice(string.as_bytes());
+    buffer[string.len()] = 0;
+    Some(len)
+}
+
+impl<'a> fmt::Display for TftpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TFTP opcode={}", self.opcode().value())
+    }
+}
+
+impl<'a> fmt::Display for RequestPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TFTP {} {:?} mode={:?}",
+            if self.opcode() == Opcode::WRQ {
+                "WRQ"
+            } else {
+                "RRQ"
+            },
+            self.filename().unwrap_or(""),
+            self.mode().unwrap_or("")
+        )
+    }
+}
+
+impl<'a> fmt::Display for DataPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TFTP DATA block={} len={}",
+            self.block(),
+            self.payload().len()
+        )
+    }
+}
+
+impl<'a> fmt::Display for AckPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TFTP ACK block={}", self.block())
+    }
+}
+
+impl<'a> fmt::Display for ErrorPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TFTP ERROR code={} message={:?}",
+            self.error_code().value(),
+            self.message().unwrap_or("")
+        )
+    }
+}
+
+impl<'a> fmt::Display for OptionAckPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TFTP OACK options={}", self.options().count())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! data_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutDataPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    data_setget_test!(opcode, set_opcode, Opcode(0xabcd), 0, [0xab, 0xcd]);
+    data_setget_test!(block, set_block, 0xabcd, 2, [0xab, 0xcd]);
+
+    #[test]
+    fn read_request() {
+        let mut buffer = [0; 64];
+        let options = [("blksize", "1428"), ("tsize", "0")];
+        let len = write_request(&mut buffer, Opcode::RRQ, "pxelinux.0", "octet", &options)
+            .unwrap();
+        let expected = b"\x00\x01pxelinux.0\x00octet\x00blksize\x001428\x00tsize\x000\x00";
+        assert_eq!(&expected[..], &buffer[..len]);
+
+        let packet = TftpPacket::new(&buffer[..len]).unwrap();
+        assert!(packet.data_packet().is_none());
+        let testee = packet.request().unwrap();
+        assert_eq!(Opcode::RRQ, testee.opcode());
+        assert_eq!(Some("pxelinux.0"), testee.filename());
+        assert_eq!(Some("octet"), testee.mode());
+        assert_eq!(options.to_vec(), testee.options().collect::<Vec<_>>());
+        assert_eq!("TFTP RRQ \"pxelinux.0\" mode=\"octet\"", testee.to_string());
+
+        assert_eq!(None, write_request(&mut buffer[..20], Opcode::RRQ, "a", "octet", &options));
+        assert_eq!(None, write_request(&mut buffer, Opcode::WRQ, "a\0b", "octet", &[]));
+    }
+
+    #[test]
+    fn truncated_request() {
+        let testee = RequestPacket::new(b"\x00\x02file\x00oct").unwrap();
+        assert_eq!(Some("file"), testee.filename());
+        assert_eq!(None, testee.mode());
+        assert_eq!(0, testee.options().count());
+        // An option without a value ends the iteration
+        let testee = RequestPacket::new(b"\x00\x01f\x00octet\x00blksize\x00").unwrap();
+        assert_eq!(0, testee.options().count());
+    }
+
+    #[test]
+    fn data_and_ack() {
+        let mut data = [0; 4 + 3];
+        {
+            let mut testee = MutDataPacket::new(&mut data).unwrap();
+            testee.set_opcode(Opcode::DATA);
+            testee.set_block(1);
+            testee.payload().copy_from_slice(b"abc");
+        }
+        let testee = TftpPacket::new(&data).unwrap().data_packet().unwrap();
+        assert_eq!(1, testee.block());
+        assert!(testee.payload().len() < DEFAULT_BLOCK_SIZE);
+
+        let testee = TftpPacket::new(&[0, 4, 0, 1]).unwrap().ack().unwrap();
+        assert_eq!(1, testee.block());
+        assert!(TftpPacket::new(&[0, 4, 0]).unwrap().ack().is_none());
+    }
+
+    #[test]
+    fn error() {
+        let mut buffer = [0; 32];
+        let len = write_error(&mut buffer, ErrorCode::FILE_NOT_FOUND, "No such file").unwrap();
+        assert_eq!(17, len);
+        let testee = TftpPacket::new(&buffer[..len]).unwrap().error().unwrap();
+        assert_eq!(ErrorCode::FILE_NOT_FOUND, testee.error_code());
+        assert_eq!(Some("No such file"), testee.message());
+        assert_eq!("TFTP ERROR code=1 message=\"No such file\"", testee.to_string());
+        assert_eq!(None, ErrorPacket::new(&buffer[..10]).unwrap().message());
+    }
+
+    #[test]
+    fn option_ack() {
+        let testee = TftpPacket::new(b"\x00\x06blksize\x001428\x00").unwrap();
+        let options = testee.option_ack().unwrap().options().collect::<Vec<_>>();
+        assert_eq!(vec![("blksize", "1428")], options);
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        RequestPacketBuf => |packet| packet.opcode() == Opcode::RRQ,
+        DataPacketBuf => |packet| packet.opcode() == Opcode::DATA,
+        AckPacketBuf => |packet| packet.opcode() == Opcode::ACK,
+        ErrorPacketBuf => |packet| packet.opcode() == Opcode::ERROR,
+        OptionAckPacketBuf => |packet| packet.opcode() == Opcode::OACK
+    );
+}
@@ -0,0 +1,609 @@
+//! The TLS record layer and enough of the handshake to read a ClientHello, RFC 8446 and
+//! RFC 5246.
+//!
+//! All data on a TLS connection is sent in records with a five byte header. Handshake records
+//! carry handshake messages, the first of which is the ClientHello. Its extensions are sent in
+//! the clear and name the server and application protocols asked for, see
+//! `ClientHelloPacket::server_name` and `ClientHelloPacket::alpn_protocols`. A ClientHello
+//! fragmented over several records is not supported, its extensions are cut off at the end of
+//! the first record.
+
+use std::cmp;
+use std::fmt;
+use std::str;
+
+/// The TCP port used by HTTPS.
+pub const HTTPS_PORT: u16 = 443;
+
+packet!(
+    /// A TLS record. The payload is the record fragment, see `fragment`.
+    TlsRecordPacket,
+    MutTlsRecordPacket,
+    TlsRecordPacketBuf,
+    5,
+    |_data| 5,
+    len |data| 5 + usize::from(read_offset!(data, 3, u16, from_be)),
+    set_len |data, len| {
+        len - 5 <= usize::from(u16::MAX) && {
+            write_offset!(data, 3, (len - 5) as u16, u16, to_be);
+            true
+        }
+    },
+    init |data| {
+        write_offset!(data, 1, ProtocolVersion::TLS_1_2.value(), u16, to_be);
+        MutTlsRecordPacket::set_len(data, data.len());
+    }
+);
+
+getters!(TlsRecordPacket
+    pub fn content_type(&self) -> ContentType {
+        ContentType(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the record layer version. Fixed to `ProtocolVersion::TLS_1_2`, or `TLS_1_0`
+    /// in a first ClientHello, since TLS 1.3.
+    pub fn version(&self) -> ProtocolVersion {
+        ProtocolVersion(read_offset!(self.0, 1, u16, from_be))
+    }
+
+    /// Returns the length of the fragment in bytes.
+    pub fn length(&self) -> u16 {
+        read_offset!(self.0, 3, u16, from_be)
+    }
+
+    /// Returns the fragment, limited to the length given by the header.
+    pub fn fragment(&self) -> &'a [u8] {
+        let end = cmp::min(5 + usize::from(self.length()), self.0.len());
+        &self.0[5..end]
+    }
+
+    /// Returns the handshake message at the start of the fragment, or `None` if this is not a
+    /// handshake record or the fragment is too short.
+    pub fn handshake(&self) -> Option<HandshakePacket<'a>> {
+        if self.content_type() == ContentType::HANDSHAKE {
+            HandshakePacket::new(self.fragment())
+        } else {
+            None
+        }
+    }
+);
+
+setters!(MutTlsRecordPacket
+    pub fn set_content_type(&mut self, content_type: ContentType) {
+        write_offset!(self.0, 0, content_type.value(), u8);
+    }
+
+    pub fn set_version(&mut self, version: ProtocolVersion) {
+        write_offset!(self.0, 1, version.value(), u16, to_be);
+    }
+
+    pub fn set_length(&mut self, length: u16) {
+        write_offset!(self.0, 3, length, u16, to_be);
+    }
+);
+
+
+macro_rules! handshake_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn handshake_type(&self) -> HandshakeType {
+                HandshakeType(read_offset!(self.0, 0, u8))
+            }
+
+            /// Returns the length of the message in bytes, excluding this header.
+            pub fn length(&self) -> u32 {
+                read_offset!(self.0, 1, u24, from_be)
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_handshake_type(&mut self, handshake_type: HandshakeType) {
+                write_offset!(self.0, 0, handshake_type.value(), u8);
+            }
+
+            /// Sets the length of the message. Only the lower 24 bits are used.
+            pub fn set_length(&mut self, length: u32) {
+                write_offset!(self.0, 1, length, u24, to_be);
+            }
+        );
+    }
+}
+
+packet!(
+    /// A handshake message header. The payload is the message body.
+    HandshakePacket,
+    MutHandshakePacket,
+    HandshakePacketBuf,
+    4,
+    |_data| 4,
+    init |data| write_offset!(data, 1, (data.len() - 4) as u32, u24, to_be)
+);
+handshake_common!(HandshakePacket, MutHandshakePacket);
+
+impl<'a> HandshakePacket<'a> {
+    /// Returns the message as a ClientHello, or `None` if it is of another type or too short.
+    pub fn client_hello(&self) -> Option<ClientHelloPacket<'a>> {
+        if self.handshake_type() == HandshakeType::CLIENT_HELLO {
+            ClientHelloPacket::new(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+
+packet!(
+    /// A ClientHello message, including the handshake header. The variable length fields are
+    /// available through their getters, and limited to the message length.
+    ClientHelloPacket,
+    MutClientHelloPacket,
+    ClientHelloPacketBuf,
+    39,
+    |_data| 39,
+    // An empty session ID, leaving the cipher suites and the rest to the payload.
+    init |data| {
+        write_offset!(data, 0, HandshakeType::CLIENT_HELLO.value(), u8);
+        write_offset!(data, 1, (data.len() - 4) as u32, u24, to_be);
+        write_offset!(data, 4, ProtocolVersion::TLS_1_2.value(), u16, to_be);
+        write_offset!(data, 38, 0u8, u8);
+    }
+);
+handshake_common!(ClientHelloPacket, MutClientHelloPacket);
+
+getters!(ClientHelloPacket
+    /// Returns the legacy version, fixed to `ProtocolVersion::TLS_1_2` since TLS 1.3, which
+    /// is negotiated with the supported versions extension instead.
+    pub fn legacy_version(&self) -> ProtocolVersion {
+        ProtocolVersion(read_offset!(self.0, 4, u16, from_be))
+    }
+
+    pub fn random(&self) -> [u8; 32] {
+        read_offset!(self.0, 6, [u8; 32])
+    }
+
+    pub fn session_id(&self) -> &'a [u8] {
+        self.sections().map(|sections| sections.session_id).unwrap_or(&[])
+    }
+
+    /// Returns an iterator over the offered cipher suites, in order of preference.
+    pub fn cipher_suites(&self) -> CipherSuites<'a> {
+        CipherSuites { data: self.sections().map(|sections| sections.cipher_suites).unwrap_or(&[]) }
+    }
+
+    pub fn compression_methods(&self) -> &'a [u8] {
+        self.sections().map(|sections| sections.compression_methods).unwrap_or(&[])
+    }
+
+    /// Returns an iterator over the extensions.
+    pub fn extensions(&self) -> Extensions<'a> {
+        Extensions { data: self.sections().map(|sections| sections.extensions).unwrap_or(&[]) }
+    }
+
+    /// Returns the host name from the server name indication extension, or `None` if there
+    /// is none or it is not valid UTF-8.
+    pub fn server_name(&self) -> Option<&'a str> {
+        self.extensions().filter_map(|extension| extension.server_name()).next()
+    }
+
+    /// Returns an iterator over the protocols offered by the application layer protocol
+    /// negotiation extension, or `None` if there is none.
+    pub fn alpn_protocols(&self) -> Option<AlpnProtocols<'a>> {
+        self.extensions().filter_map(|extension| extension.alpn_protocols()).next()
+    }
+);
+
+impl<'a> ClientHelloPacket<'a> {
+    /// Splits the message after the random into the session ID, cipher suites, compression
+    /// methods and extensions. Returns `None` if any but the extensions is truncated, while
+    /// truncated extensions are cut off at the end of the data.
+    fn sections(&self) -> Option<Sections<'a>> {
+        let end = cmp::min(4 + self.length() as usize, self.0.len());
+        let mut data = self.0.get(38..end)?;
+        let session_id = split_vector(&mut data, 1)?;
+        let cipher_suites = split_vector(&mut data, 2)?;
+        let compression_methods = split_vector(&mut data, 1)?;
+        let extensions = match data.get(..2) {
+            Some(len) => {
+                let len = usize::from(read_offset!(len, 0, u16, from_be));
+                &data[2..cmp::min(2 + len, data.len())]
+            }
+            None => &[],
+        };
+        Some(Sections {
+            session_id,
+            cipher_suites,
+            compression_methods,
+            extensions,
+        })
+    }
+}
+
+/// The variable length fields of a ClientHello.
+struct Sections<'a> {
+    session_id: &'a [u8],
+    cipher_suites: &'a [u8],
+    compression_methods: &'a [u8],
+    extensions: &'a [u8],
+}
+
+setters!(MutClientHelloPacket
+    pub fn set_legacy_version(&mut self, legacy_version: ProtocolVersion) {
+        write_offset!(self.0, 4, legacy_version.value(), u16, to_be);
+    }
+
+    pub fn set_random(&mut self, random: [u8; 32]) {
+        write_offset!(self.0, 6, random, [u8; 32]);
+    }
+);
+
+/// Splits a vector with a big endian length of `length_size` bytes off the beginning of
+/// `data`, returning its content. Returns `None` if the vector does not fit.
+fn split_vector<'a>(data: &mut &'a [u8], length_size: usize) -> Option<&'a [u8]> {
+    let len = match length_size {
+        1 => usize::from(*data.first()?),
+        _ => usize::from(read_offset!(data.get(..2)?, 0, u16, from_be)),
+    };
+    let vector = data.get(length_size..length_size + len)?;
+    *data = &data[length_size + len..];
+    Some(vector)
+}
+
+
+/// Represents the content type of a TLS record.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ContentType(pub u8);
+
+impl ContentType {
+    pub const CHANGE_CIPHER_SPEC: ContentType = ContentType(20);
+    pub const ALERT: ContentType = ContentType(21);
+    pub const HANDSHAKE: ContentType = ContentType(22);
+    pub const APPLICATION_DATA: ContentType = ContentType(23);
+    pub const HEARTBEAT: ContentType = ContentType(24);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents a version of SSL or TLS.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ProtocolVersion(pub u16);
+
+impl ProtocolVersion {
+    pub const SSL_3_0: ProtocolVersion = ProtocolVersion(0x0300);
+    pub const TLS_1_0: ProtocolVersion = ProtocolVersion(0x0301);
+    pub const TLS_1_1: ProtocolVersion = ProtocolVersion(0x0302);
+    pub const TLS_1_2: ProtocolVersion = ProtocolVersion(0x0303);
+    pub const TLS_1_3: ProtocolVersion = ProtocolVersion(0x0304);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Represents the type of a handshake message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct HandshakeType(pub u8);
+
+impl HandshakeType {
+    pub const CLIENT_HELLO: HandshakeType = HandshakeType(1);
+    pub const SERVER_HELLO: HandshakeType = HandshakeType(2);
+    pub const NEW_SESSION_TICKET: HandshakeType = HandshakeType(4);
+    pub const ENCRYPTED_EXTENSIONS: HandshakeType = HandshakeType(8);
+    pub const CERTIFICATE: HandshakeType = HandshakeType(11);
+    pub const SERVER_KEY_EXCHANGE: HandshakeType = HandshakeType(12);
+    pub const CERTIFICATE_REQUEST: HandshakeType = HandshakeType(13);
+    pub const SERVER_HELLO_DONE: HandshakeType = HandshakeType(14);
+    pub const CERTIFICATE_VERIFY: HandshakeType = HandshakeType(15);
+    pub const CLIENT_KEY_EXCHANGE: HandshakeType = HandshakeType(16);
+    pub const FINISHED: HandshakeType = HandshakeType(20);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents the type of a handshake extension.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExtensionType(pub u16);
+
+impl ExtensionType {
+    pub const SERVER_NAME: ExtensionType = ExtensionType(0);
+    pub const SUPPORTED_GROUPS: ExtensionType = ExtensionType(10);
+    pub const SIGNATURE_ALGORITHMS: ExtensionType = ExtensionType(13);
+    pub const ALPN: ExtensionType = ExtensionType(16);
+    pub const SUPPORTED_VERSIONS: ExtensionType = ExtensionType(43);
+    pub const KEY_SHARE: ExtensionType = ExtensionType(51);
+
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+
+/// Iterator over the cipher suites offered in a ClientHello.
+#[derive(Clone)]
+pub struct CipherSuites<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(CipherSuites<'a>);
+
+impl<'a> Iterator for CipherSuites<'a> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        if self.data.len() < 2 {
+            return None;
+        }
+        let (cipher_suite, rest) = self.data.split_at(2);
+        self.data = rest;
+        Some(read_offset!(cipher_suite, 0, u16, from_be))
+    }
+}
+
+/// An extension in a handshake message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Extension<'a> {
+    pub extension_type: ExtensionType,
+    pub data: &'a [u8],
+}
+
+impl<'a> Extension<'a> {
+    /// Returns the host name of a server name extension, or `None` if this is another
+    /// extension, it holds no host name, or the name is not valid UTF-8.
+    pub fn server_name(&self) -> Option<&'a str> {
+        if self.extension_type != ExtensionType::SERVER_NAME {
+            return None;
+        }
+        let mut names = split_vector(&mut &self.data[..], 2)?;
+        while !names.is_empty() {
+            let name_type = names[0];
+            names = &names[1..];
+            let name = split_vector(&mut names, 2)?;
+            if name_type == 0 {
+                return str::from_utf8(name).ok();
+            }
+        }
+        None
+    }
+
+    /// Returns an iterator over the protocols of an ALPN extension, or `None` if this is
+    /// another extension or it is malformed.
+    pub fn alpn_protocols(&self) -> Option<AlpnProtocols<'a>> {
+        if self.extension_type == ExtensionType::ALPN {
+            split_vector(&mut &self.data[..], 2).map(|data| AlpnProtocols { data })
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the extensions of a handshake message. Iteration stops at the first
+/// extension that does not fit in the data.
+#[derive(Clone)]
+pub struct Extensions<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Extensions<'a>);
+
+impl<'a> Iterator for Extensions<'a> {
+    type Item = Extension<'a>;
+
+    fn next(&mut self) -> Option<Extension<'a>> {
+        if self.data.len() < 4 {
+            self.data = &[];
+            return None;
+        }
+        let extension_type = ExtensionType(read_offset!(self.data, 0, u16, from_be));
+        let mut rest = &self.data[2..];
+        match split_vector(&mut rest, 2) {
+            Some(data) => {
+                self.data = rest;
+                Some(Extension { extension_type, data })
+            }
+            None => {
+                self.data = &[];
+                None
+            }
+        }
+    }
+}
+
+/// Iterator over the protocol names in an ALPN extension, such as `b"h2"` or `b"http/1.1"`.
+/// Iteration stops at the first name that does not fit in the data.
+#[derive(Clone)]
+pub struct AlpnProtocols<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(AlpnProtocols<'a>);
+
+impl<'a> Iterator for AlpnProtocols<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let protocol = split_vector(&mut self.data, 1);
+        if protocol.is_none() {
+            self.data = &[];
+        }
+        protocol
+    }
+}
+
+impl<'a> fmt::Display for ClientHelloPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TLS ClientHello version={:#06x} sni={:?} ciphers={}",
+            self.legacy_version().value(),
+            self.server_name().unwrap_or(""),
+            self.cipher_suites().count()
+        )
+    }
+}
+
+impl<'a> fmt::Display for TlsRecordPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TLS record type={} version={:#06x} len={}",
+            self.content_type().value(),
+            self.version().value(),
+            self.length()
+        )
+    }
+}
+
+impl<'a> fmt::Display for HandshakePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TLS handshake type={} len={}",
+            self.handshake_type().value(),
+            self.length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! tls_record_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutTlsRecordPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    tls_record_setget_test!(content_type, set_content_type, ContentType::ALERT, 0, [21]);
+    tls_record_setget_test!(version, set_version, ProtocolVersion::TLS_1_2, 1, [3, 3]);
+    tls_record_setget_test!(length, set_length, 0xabcd, 3, [0xab, 0xcd]);
+
+    /// Returns a ClientHello record for example.com offering h2 and http/1.1.
+    fn client_hello() -> Vec<u8> {
+        let mut hello = vec![3, 3];
+        hello.extend_from_slice(&[7; 32]);
+        hello.extend_from_slice(&[2, 0xaa, 0xbb]);
+        hello.extend_from_slice(&[0, 4, 0x13, 0x01, 0xc0, 0x2f]);
+        hello.extend_from_slice(&[1, 0]);
+        let mut extensions = vec![0, 43, 0, 3, 2, 3, 4];
+        extensions.extend_from_slice(&[0, 0, 0, 16, 0, 14, 0, 0, 11]);
+        extensions.extend_from_slice(b"example.com");
+        extensions.extend_from_slice(&[0, 16, 0, 14, 0, 12, 2, b'h', b'2', 8]);
+        extensions.extend_from_slice(b"http/1.1");
+        hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        hello.extend_from_slice(&extensions);
+
+        let mut record = vec![22, 3, 1];
+        record.extend_from_slice(&(hello.len() as u16 + 4).to_be_bytes());
+        record.extend_from_slice(&[1, 0]);
+        record.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+        record.extend_from_slice(&hello);
+        record
+    }
+
+    #[test]
+    fn sni_and_alpn() {
+        let data = client_hello();
+        let record = TlsRecordPacket::new(&data).unwrap();
+        assert_eq!(ProtocolVersion::TLS_1_0, record.version());
+        let testee = record.handshake().unwrap().client_hello().unwrap();
+        assert_eq!(&[0xaa, 0xbb], testee.session_id());
+        assert_eq!(vec![0x1301, 0xc02f], testee.cipher_suites().collect::<Vec<_>>());
+        assert_eq!(&[0], testee.compression_methods());
+        assert_eq!(3, testee.extensions().count());
+        assert_eq!(Some("example.com"), testee.server_name());
+        let protocols = testee.alpn_protocols().unwrap().collect::<Vec<_>>();
+        assert_eq!(vec![&b"h2"[..], &b"http/1.1"[..]], protocols);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = client_hello();
+        // A record cut off in the ALPN extension
+        let testee = TlsRecordPacket::new(&data[..data.len() - 4]).unwrap();
+        let testee = testee.handshake().unwrap().client_hello().unwrap();
+        assert_eq!(2, testee.extensions().count());
+        assert_eq!(Some("example.com"), testee.server_name());
+        assert!(testee.alpn_protocols().is_none());
+
+        // A record cut off in the cipher suites
+        let testee = TlsRecordPacket::new(&data[..49]).unwrap();
+        let testee = testee.handshake().unwrap().client_hello().unwrap();
+        assert!(testee.session_id().is_empty());
+        assert_eq!(0, testee.cipher_suites().count());
+        assert_eq!(0, testee.extensions().count());
+
+        // The record length limits the fragment
+        let mut data = data;
+        data[4] -= 30;
+        let testee = TlsRecordPacket::new(&data).unwrap();
+        assert_eq!(data.len() - 5 - 30, testee.fragment().len());
+
+        data[0] = ContentType::APPLICATION_DATA.value();
+        assert!(TlsRecordPacket::new(&data).unwrap().handshake().is_none());
+    }
+
+    #[test]
+    fn without_extensions() {
+        let mut data = [0; 45];
+        data[..4].copy_from_slice(&[1, 0, 0, 41]);
+        data[38..].copy_from_slice(&[0, 0, 2, 0, 0x2f, 1, 0]);
+        let testee = HandshakePacket::new(&data).unwrap().client_hello().unwrap();
+        assert_eq!(vec![0x2f], testee.cipher_suites().collect::<Vec<_>>());
+        assert_eq!(0, testee.extensions().count());
+        assert!(testee.alpn_protocols().is_none());
+    }
+
+    mod client_hello {
+        use super::super::*;
+
+        macro_rules! client_hello_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutClientHelloPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        client_hello_setget_test!(
+            handshake_type,
+            set_handshake_type,
+            HandshakeType::CLIENT_HELLO,
+            0,
+            [1]
+        );
+        client_hello_setget_test!(length, set_length, 0x020304, 1, [2, 3, 4]);
+        client_hello_setget_test!(
+            legacy_version,
+            set_legacy_version,
+            ProtocolVersion::TLS_1_2,
+            4,
+            [3, 3]
+        );
+        client_hello_setget_test!(random, set_random, [0xab; 32], 6, [0xab; 32]);
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 5 + 4];
+        let mut testee = MutTlsRecordPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(&[1, 2]));
+        assert_eq!(2, testee.as_immutable().length());
+        assert_eq!(&[1, 2], testee.as_immutable().payload());
+        assert_eq!(testee.as_immutable().fragment(), testee.as_immutable().payload());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        TlsRecordPacketBuf => |packet| packet.fragment() == packet.payload(),
+        HandshakePacketBuf => |packet| packet.length() as usize == packet.payload().len(),
+        ClientHelloPacketBuf => |packet| packet.session_id().is_empty()
+            && packet.length() as usize + 4 == packet.data().len()
+    );
+}
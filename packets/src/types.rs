@@ -4,5 +4,13 @@
 pub type u2 = u8;
 pub type u3 = u8;
 pub type u4 = u8;
+pub type u5 = u8;
 pub type u6 = u8;
+pub type u7 = u8;
+pub type u9 = u16;
+pub type u10 = u16;
+pub type u12 = u16;
 pub type u13 = u16;
+pub type u20 = u32;
+pub type u24 = u32;
+pub type u48 = u64;
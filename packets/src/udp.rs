@@ -0,0 +1,353 @@
+use alloc::vec::Vec;
+use builder::{self, Payload};
+use checksum;
+use error::{self, ParseError};
+use ip::Protocol;
+use std::fmt;
+use std::net::IpAddr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// A length of zero is used by jumbograms, RFC 2675, whose length is only given by IPv6.
+#[packet(
+    len = |data| match read_offset!(data, 4, u16, from_be) {
+        0 => data.len(),
+        length => usize::from(length),
+    },
+    set_len = |data, len| len <= usize::from(u16::MAX) && {
+        write_offset!(data, 4, len as u16, u16, to_be);
+        true
+    },
+    init = |data| write_offset!(data, 4, data.len() as u16, u16, to_be)
+)]
+pub struct Udp {
+    source_port: u16,
+    destination_port: u16,
+    /// Returns the length of the header and payload in bytes.
+    length: u16,
+    checksum: u16,
+}
+
+impl<'a> UdpPacket<'a> {
+    /// Creates a new immutable packet like `new`, but also validates it. The length field must
+    /// cover the header and fit in `data`.
+    pub fn parse(data: &'a [u8]) -> Result<UdpPacket<'a>, ParseError> {
+        error::check_len(data, Self::MIN_LEN)?;
+        let packet = UdpPacket(data);
+        let len = usize::from(packet.length());
+        if len < Self::MIN_LEN || len > data.len() {
+            return Err(ParseError::InvalidLength(len));
+        }
+        Ok(packet)
+    }
+
+    /// Computes the checksum of this packet when sent between the given addresses, ignoring
+    /// the current value of the checksum field. The backing slice is assumed to hold exactly
+    /// the UDP packet. Returns `None` if the addresses are not of the same IP version.
+    pub fn calculate_checksum(&self, source: IpAddr, destination: IpAddr) -> Option<u16> {
+        let segments = [&self.0[..6], &[0, 0], &self.0[8..]];
+        let checksum = checksum::transport_checksum(source, destination, Protocol::UDP, &segments)?;
+        // A checksum of zero means no checksum, it is transmitted as all ones instead.
+        Some(match checksum {
+            0 => 0xffff,
+            checksum => checksum,
+        })
+    }
+
+    /// Returns true if the checksum field is valid for this packet sent between the given
+    /// addresses. Over IPv4 a checksum of zero means the sender did not compute one, and is
+    /// always accepted.
+    pub fn verify_checksum(&self, source: IpAddr, destination: IpAddr) -> bool {
+        if source.is_ipv4() && destination.is_ipv4() && self.checksum() == 0 {
+            return true;
+        }
+        checksum::transport_checksum(source, destination, Protocol::UDP, &[self.0]) == Some(0)
+    }
+}
+
+impl<'a> MutUdpPacket<'a> {
+    /// Computes the checksum of this packet and writes it to the checksum field. See
+    /// `UdpPacket::calculate_checksum` for details. Returns the written checksum.
+    pub fn update_checksum(&mut self, source: IpAddr, destination: IpAddr) -> Option<u16> {
+        let checksum = self.as_immutable().calculate_checksum(source, destination)?;
+        self.set_checksum(checksum);
+        Some(checksum)
+    }
+}
+
+
+/// Builds a UDP packet. The length is filled in when building, and so is the checksum when the
+/// packet is the payload of an IP builder. Otherwise the checksum is left as zero. See the
+/// `builder` module for an example.
+#[derive(Debug, Copy, Clone)]
+pub struct UdpBuilder<P = &'static [u8]> {
+    source_port: u16,
+    destination_port: u16,
+    payload: P,
+}
+
+impl UdpBuilder {
+    /// Creates a builder for a packet with zero ports and an empty payload.
+    pub fn new() -> UdpBuilder {
+        UdpBuilder::default()
+    }
+}
+
+impl Default for UdpBuilder {
+    fn default() -> UdpBuilder {
+        UdpBuilder {
+            source_port: 0,
+            destination_port: 0,
+            payload: &[],
+        }
+    }
+}
+
+impl<P: Payload> UdpBuilder<P> {
+    pub fn source_port(mut self, source_port: u16) -> Self {
+        self.source_port = source_port;
+        self
+    }
+
+    pub fn destination_port(mut self, destination_port: u16) -> Self {
+        self.destination_port = destination_port;
+        self
+    }
+
+    pub fn payload<Q: Payload>(self, payload: Q) -> UdpBuilder<Q> {
+        UdpBuilder {
+            source_port: self.source_port,
+            destination_port: self.destination_port,
+            payload,
+        }
+    }
+
+    /// Writes the packet, without a checksum, to the start of `buffer`. Returns the length of
+    /// the packet, or `None` if it does not fit in `buffer` or in the length field, or the
+    /// payload can't be encoded.
+    pub fn build_into(&self, buffer: &mut [u8]) -> Option<usize> {
+        self.write(buffer, None)
+    }
+
+    /// Writes the packet to a newly allocated buffer. See `build_into`.
+    pub fn build(&self) -> Option<Vec<u8>> {
+        builder::build(self)
+    }
+}
+
+impl<P: Payload> Payload for UdpBuilder<P> {
+    fn encoded_len(&self) -> usize {
+        UdpPacket::MIN_LEN + self.payload.encoded_len()
+    }
+
+    fn write(&self, buffer: &mut [u8], addresses: Option<(IpAddr, IpAddr)>) -> Option<usize> {
+        let len = self.encoded_len();
+        if len > usize::from(u16::MAX) {
+            return None;
+        }
+        let buffer = buffer.get_mut(..len)?;
+        self.payload.write(&mut buffer[UdpPacket::MIN_LEN..], addresses)?;
+        let mut packet = MutUdpPacket::new(buffer)?;
+        packet.set_source_port(self.source_port);
+        packet.set_destination_port(self.destination_port);
+        packet.set_length(len as u16);
+        packet.set_checksum(0);
+        if let Some((source, destination)) = addresses {
+            packet.update_checksum(source, destination)?;
+        }
+        Some(len)
+    }
+
+    fn protocol(&self) -> Option<Protocol> {
+        Some(Protocol::UDP)
+    }
+}
+
+
+/// The fields of a UDP header as a plain value, one field per getter on `UdpPacket`. Implements
+/// `Serialize` and `Deserialize` with the `serde` feature enabled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UdpHeader {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub length: u16,
+    pub checksum: u16,
+}
+
+impl<'a> UdpPacket<'a> {
+    /// Reads all fields of the header into a `UdpHeader`.
+    pub fn to_header(&self) -> UdpHeader {
+        UdpHeader {
+            source_port: self.source_port(),
+            destination_port: self.destination_port(),
+            length: self.length(),
+            checksum: self.checksum(),
+        }
+    }
+}
+
+impl<'a> MutUdpPacket<'a> {
+    /// Writes all fields of `header` to the header, as they are. The length and checksum are not
+    /// recomputed.
+    pub fn set_header(&mut self, header: &UdpHeader) {
+        self.set_source_port(header.source_port);
+        self.set_destination_port(header.destination_port);
+        self.set_length(header.length);
+        self.set_checksum(header.checksum);
+    }
+}
+
+
+impl<'a> fmt::Display for UdpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UDP {} -> {} len={}",
+            self.source_port(),
+            self.destination_port(),
+            self.length()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let data = [0x30, 0x39, 0x00, 0x35, 0x00, 0x0b, 0x00, 0x00, 0x61, 0x62, 0x63];
+        assert_eq!("UDP 12345 -> 53 len=11", UdpPacket::new(&data).unwrap().to_string());
+    }
+
+    #[test]
+    fn min_length() {
+        assert_eq!(UdpPacket::MIN_LEN, 8);
+    }
+
+    #[test]
+    fn too_short_slice() {
+        assert!(UdpPacket::new(&[0; 7]).is_none());
+        assert!(MutUdpPacket::new(&mut [0; 7]).is_none());
+    }
+
+    #[test]
+    fn build() {
+        let builder = UdpBuilder::new().source_port(12345).destination_port(53).payload(b"abc");
+        assert_eq!(
+            vec![0x30, 0x39, 0x00, 0x35, 0x00, 0x0b, 0x00, 0x00, 0x61, 0x62, 0x63],
+            builder.build().unwrap()
+        );
+
+        let source = IpAddr::from([192, 168, 0, 1]);
+        let destination = IpAddr::from([192, 168, 0, 2]);
+        let mut buffer = [0xff; 11];
+        assert_eq!(Some(11), builder.write(&mut buffer, Some((source, destination))));
+        let testee = UdpPacket::new(&buffer).unwrap();
+        assert_eq!(0x89b3, testee.checksum());
+        assert_eq!(None, builder.build_into(&mut buffer[..10]));
+    }
+
+    #[test]
+    fn parse() {
+        let data = [0x30, 0x39, 0x00, 0x35, 0x00, 0x09, 0x00, 0x00, 0x61];
+        assert!(UdpPacket::parse(&data).is_ok());
+        assert_eq!(Err(ParseError::InvalidLength(9)), UdpPacket::parse(&data[..8]));
+        assert_eq!(
+            Err(ParseError::TooShort { min_len: 8, len: 7 }),
+            UdpPacket::parse(&data[..7])
+        );
+        let data = [0x30, 0x39, 0x00, 0x35, 0x00, 0x07, 0x00, 0x00];
+        assert_eq!(Err(ParseError::InvalidLength(7)), UdpPacket::parse(&data));
+    }
+
+    #[test]
+    fn user_provided_buffer() {
+        let mut testee = UdpPacketBuf::new([0; 10]).unwrap();
+        testee.packet_mut().set_destination_port(53);
+        assert_eq!(53, testee.packet().destination_port());
+        assert_eq!([0, 0, 0, 53, 0, 0, 0, 0, 0, 0], testee.into_inner());
+        assert!(UdpPacketBuf::new([0; 7]).is_none());
+
+        let mut buffer = [0; 8];
+        UdpPacketBuf::new(&mut buffer[..]).unwrap().packet_mut().set_length(8);
+        assert_eq!(8, buffer[5]);
+    }
+
+    #[test]
+    fn correct_payload() {
+        let data = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let packet = UdpPacket::new(&data).unwrap();
+        assert_eq!(&data[..8], packet.header());
+        assert_eq!(&[8, 9], packet.payload());
+    }
+
+    #[test]
+    fn set_payload() {
+        let mut data = [0; 12];
+        let mut testee = MutUdpPacket::new(&mut data).unwrap();
+        assert!(testee.set_payload(b"hi"));
+        assert_eq!(10, testee.as_immutable().length());
+        assert_eq!(b"hi", testee.as_immutable().payload());
+        assert!(!testee.set_payload(b"hello"));
+        assert_eq!(10, testee.as_immutable().length());
+    }
+
+    macro_rules! udp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutUdpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    #[test]
+    fn calculate_and_verify_checksum() {
+        let source = IpAddr::from([192, 168, 0, 1]);
+        let destination = IpAddr::from([192, 168, 0, 2]);
+        let mut data = [0x30, 0x39, 0x00, 0x35, 0x00, 0x0b, 0x00, 0x00, 0x61, 0x62, 0x63];
+        {
+            let mut testee = MutUdpPacket::new(&mut data).unwrap();
+            assert_eq!(Some(0x89b3), testee.update_checksum(source, destination));
+        }
+        let testee = UdpPacket::new(&data).unwrap();
+        assert_eq!(0x89b3, testee.checksum());
+        assert!(testee.verify_checksum(source, destination));
+        assert!(!testee.verify_checksum(destination, destination));
+        assert!(!testee.verify_checksum(source, IpAddr::from([0u16; 8])));
+    }
+
+    #[test]
+    fn no_checksum() {
+        let data = [0x30, 0x39, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00];
+        let testee = UdpPacket::new(&data).unwrap();
+        let address = IpAddr::from([192, 168, 0, 1]);
+        assert!(testee.verify_checksum(address, address));
+        let address = IpAddr::from([0xfe80, 0, 0, 0, 0, 0, 0, 1]);
+        assert!(!testee.verify_checksum(address, address));
+    }
+
+    udp_setget_test!(source_port, set_source_port, 0xabcd, 0, [0xab, 0xcd]);
+    udp_setget_test!(destination_port, set_destination_port, 0xabcd, 2, [0xab, 0xcd]);
+    udp_setget_test!(length, set_length, 0xabcd, 4, [0xab, 0xcd]);
+    udp_setget_test!(checksum, set_checksum, 0xabcd, 6, [0xab, 0xcd]);
+
+
+    #[test]
+    fn header_round_trip() {
+        let header = UdpHeader {
+            source_port: 1234,
+            destination_port: 53,
+            length: 8,
+            checksum: 0xabcd,
+        };
+        let mut buffer = [0; 8];
+        MutUdpPacket::new(&mut buffer).unwrap().set_header(&header);
+        assert_eq!(header, UdpPacket::new(&buffer).unwrap().to_header());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        UdpPacketBuf => |packet| UdpPacket::parse(packet.data()).is_ok()
+    );
+}
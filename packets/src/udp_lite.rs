@@ -0,0 +1,235 @@
+//! UDP-Lite, RFC 3828. A variant of UDP where the checksum only has to cover the first part of
+//! the packet, so payloads that tolerate bit errors can still be delivered.
+
+use checksum::{self, Checksum};
+use ip::Protocol;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+packet!(
+    UdpLitePacket,
+    MutUdpLitePacket,
+    UdpLitePacketBuf,
+    8,
+    |_data| 8,
+    // A checksum coverage of zero, covering the entire packet.
+    init |data| write_offset!(data, 4, 0u16, u16, to_be)
+);
+
+getters!(UdpLitePacket
+    pub fn source_port(&self) -> u16 {
+        read_offset!(self.0, 0, u16, from_be)
+    }
+
+    pub fn destination_port(&self) -> u16 {
+        read_offset!(self.0, 2, u16, from_be)
+    }
+
+    /// Returns the number of bytes, counting from the start of the header, covered by the
+    /// checksum. Zero means the entire packet is covered.
+    pub fn checksum_coverage(&self) -> u16 {
+        read_offset!(self.0, 4, u16, from_be)
+    }
+
+    pub fn checksum(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+);
+
+impl<'a> UdpLitePacket<'a> {
+    /// Returns the number of bytes covered by the checksum, resolving a coverage of zero to
+    /// the length of the backing slice. Returns `None` if the coverage is invalid, meaning it is
+    /// shorter than the header or longer than the backing slice.
+    pub fn coverage_len(&self) -> Option<usize> {
+        match usize::from(self.checksum_coverage()) {
+            0 => Some(self.0.len()),
+            len if len >= Self::MIN_LEN && len <= self.0.len() => Some(len),
+            _ => None,
+        }
+    }
+
+    /// Computes the checksum of this packet when sent over IPv4 between the given addresses.
+    /// Only the bytes given by `coverage_len` are included, and the current value of the
+    /// checksum field is ignored. The backing slice is assumed to hold exactly the UDP-Lite
+    /// packet. Returns `None` if the checksum coverage is invalid.
+    pub fn ipv4_checksum(&self, source: Ipv4Addr, destination: Ipv4Addr) -> Option<u16> {
+        let mut checksum = Checksum::new();
+        checksum::add_ipv4_pseudo_header(
+            &mut checksum,
+            source,
+            destination,
+            Protocol::UDPLITE,
+            self.0.len(),
+        );
+        self.covered_checksum(checksum)
+    }
+
+    /// Computes the checksum of this packet when sent over IPv6 between the given addresses.
+    /// See `ipv4_checksum` for details.
+    pub fn ipv6_checksum(&self, source: Ipv6Addr, destination: Ipv6Addr) -> Option<u16> {
+        let mut checksum = Checksum::new();
+        checksum::add_ipv6_pseudo_header(
+            &mut checksum,
+            source,
+            destination,
+            Protocol::UDPLITE,
+            self.0.len(),
+        );
+        self.covered_checksum(checksum)
+    }
+
+    fn covered_checksum(&self, mut checksum: Checksum) -> Option<u16> {
+        let coverage_len = self.coverage_len()?;
+        checksum.add_bytes(&self.0[..6]);
+        checksum.add_bytes(&self.0[8..coverage_len]);
+        // A checksum of zero is not allowed, it is transmitted as all ones instead.
+        Some(match checksum.finish() {
+            0 => 0xffff,
+            sum => sum,
+        })
+    }
+}
+
+setters!(MutUdpLitePacket
+    pub fn set_source_port(&mut self, source_port: u16) {
+        write_offset!(self.0, 0, source_port, u16, to_be);
+    }
+
+    pub fn set_destination_port(&mut self, destination_port: u16) {
+        write_offset!(self.0, 2, destination_port, u16, to_be);
+    }
+
+    pub fn set_checksum_coverage(&mut self, checksum_coverage: u16) {
+        write_offset!(self.0, 4, checksum_coverage, u16, to_be);
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        write_offset!(self.0, 6, checksum, u16, to_be);
+    }
+);
+
+impl<'a> fmt::Display for UdpLitePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UDP-Lite {} -> {} coverage={}",
+            self.source_port(),
+            self.destination_port(),
+            self.checksum_coverage()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! udp_lite_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutUdpLitePacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    udp_lite_setget_test!(source_port, set_source_port, 0xabcd, 0, [0xab, 0xcd]);
+    udp_lite_setget_test!(destination_port, set_destination_port, 0xabcd, 2, [0xab, 0xcd]);
+    udp_lite_setget_test!(
+        checksum_coverage,
+        set_checksum_coverage,
+        0xabcd,
+        4,
+        [0xab, 0xcd]
+    );
+    udp_lite_setget_test!(checksum, set_checksum, 0xabcd, 6, [0xab, 0xcd]);
+
+    #[test]
+    fn coverage_len() {
+        let mut data = [0; 20];
+        assert_eq!(Some(20), UdpLitePacket::new(&data).unwrap().coverage_len());
+        data[5] = 8;
+        assert_eq!(Some(8), UdpLitePacket::new(&data).unwrap().coverage_len());
+        data[5] = 7;
+        assert_eq!(None, UdpLitePacket::new(&data).unwrap().coverage_len());
+        data[5] = 21;
+        assert_eq!(None, UdpLitePacket::new(&data).unwrap().coverage_len());
+    }
+
+    /// Sums the pseudo header and covered bytes, including the checksum field. A correct
+    /// checksum makes this come out as zero.
+    fn verify_ipv4(data: &[u8], source: Ipv4Addr, destination: Ipv4Addr, covered: usize) -> u16 {
+        let mut checksum = Checksum::new();
+        checksum::add_ipv4_pseudo_header(
+            &mut checksum,
+            source,
+            destination,
+            Protocol::UDPLITE,
+            data.len(),
+        );
+        checksum.add_bytes(&data[..covered]);
+        checksum.finish()
+    }
+
+    #[test]
+    fn partial_coverage_checksum() {
+        let source = Ipv4Addr::new(10, 0, 0, 1);
+        let destination = Ipv4Addr::new(10, 0, 0, 2);
+        let mut data = [0; 14];
+        {
+            let mut testee = MutUdpLitePacket::new(&mut data).unwrap();
+            testee.set_source_port(1234);
+            testee.set_destination_port(5678);
+            testee.set_checksum_coverage(10);
+            testee.payload().copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+            let checksum = testee.as_immutable().ipv4_checksum(source, destination).unwrap();
+            testee.set_checksum(checksum);
+        }
+        assert_eq!(0, verify_ipv4(&data, source, destination, 10));
+
+        // Bytes outside the coverage do not affect the checksum
+        let before = UdpLitePacket::new(&data).unwrap().ipv4_checksum(source, destination);
+        data[13] = 0xff;
+        let after = UdpLitePacket::new(&data).unwrap().ipv4_checksum(source, destination);
+        assert_eq!(before, after);
+        data[9] = 0xff;
+        let after = UdpLitePacket::new(&data).unwrap().ipv4_checksum(source, destination);
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    fn full_coverage_ipv6_checksum() {
+        let source = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let destination = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 2);
+        let mut data = [0, 1, 0, 2, 0, 0, 0, 0, 0xab];
+        let checksum = UdpLitePacket::new(&data)
+            .unwrap()
+            .ipv6_checksum(source, destination)
+            .unwrap();
+        data[6..8].copy_from_slice(&checksum.to_be_bytes());
+
+        let mut sum = Checksum::new();
+        checksum::add_ipv6_pseudo_header(
+            &mut sum,
+            source,
+            destination,
+            Protocol::UDPLITE,
+            data.len(),
+        );
+        sum.add_bytes(&data);
+        assert_eq!(0, sum.finish());
+    }
+
+    #[test]
+    fn invalid_coverage_checksum() {
+        let data = [0, 0, 0, 0, 0, 4, 0, 0];
+        let testee = UdpLitePacket::new(&data).unwrap();
+        assert_eq!(
+            None,
+            testee.ipv4_checksum(Ipv4Addr::new(1, 1, 1, 1), Ipv4Addr::new(2, 2, 2, 2))
+        );
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        UdpLitePacketBuf => |packet| packet.coverage_len() == Some(packet.data().len())
+    );
+}
@@ -0,0 +1,223 @@
+//! IEEE 802.1Q VLAN tags.
+//!
+//! A VLAN tag is inserted between the source MAC address and the EtherType of an Ethernet frame.
+//! `VlanPacket` is therefore a view that starts at the Tag Protocol Identifier (TPID), which sits
+//! where the EtherType of an untagged frame would be, followed by the Tag Control Information
+//! (TCI) and the EtherType of the encapsulated payload. In other words, a tagged frame `frame`
+//! can be read with `VlanPacket::new(&frame[12..])`.
+//!
+//! Provider bridges (802.1ad, "QinQ") stack several tags after each other. `VlanTags` and
+//! `inner_ether_type` walk such stacks.
+
+use ethernet::EtherType;
+use std::fmt;
+use types::*;
+
+packet!(VlanPacket, MutVlanPacket, VlanPacketBuf, 6);
+
+getters!(VlanPacket
+    /// Returns the Tag Protocol Identifier. `EtherType::VLAN` for a regular 802.1Q tag.
+    pub fn tpid(&self) -> EtherType {
+        EtherType(read_offset!(self.0, 0, u16, from_be))
+    }
+
+    /// Returns the Priority Code Point, the 802.1p class of service of the frame.
+    pub fn pcp(&self) -> u3 {
+        read_field!(self.0, bits!(2, 0, 3), u8)
+    }
+
+    /// Returns the Drop Eligible Indicator.
+    pub fn dei(&self) -> bool {
+        read_field!(self.0, bits!(2, 3, 1), u8) != 0
+    }
+
+    /// Returns the VLAN Identifier.
+    pub fn vid(&self) -> u12 {
+        read_field!(self.0, bits!(2, 4, 12), u16)
+    }
+
+    /// Returns the EtherType of the payload following this tag.
+    pub fn ether_type(&self) -> EtherType {
+        EtherType(read_offset!(self.0, 4, u16, from_be))
+    }
+);
+
+setters!(MutVlanPacket
+    pub fn set_tpid(&mut self, tpid: EtherType) {
+        write_offset!(self.0, 0, tpid.value(), u16, to_be);
+    }
+
+    pub fn set_pcp(&mut self, pcp: u3) {
+        write_field!(self.0, bits!(2, 0, 3), pcp, u8);
+    }
+
+    pub fn set_dei(&mut self, dei: bool) {
+        write_field!(self.0, bits!(2, 3, 1), u8::from(dei), u8);
+    }
+
+    pub fn set_vid(&mut self, vid: u12) {
+        write_field!(self.0, bits!(2, 4, 12), vid, u16);
+    }
+
+    pub fn set_ether_type(&mut self, ether_type: EtherType) {
+        write_offset!(self.0, 4, ether_type.value(), u16, to_be);
+    }
+);
+
+
+/// Iterator over stacked 802.1ad and 802.1Q tags. Yields one `VlanPacket` per tag, outermost
+/// first, as long as the TPID is `EtherType::QINQ` or `EtherType::VLAN`.
+#[derive(Clone)]
+pub struct VlanTags<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+debug_items!(VlanTags<'a>);
+
+impl<'a> VlanTags<'a> {
+    /// Creates an iterator over the tags in `data`. Just as for `VlanPacket`, `data` should start
+    /// at the TPID of the outermost tag.
+    pub fn new(data: &'a [u8]) -> VlanTags<'a> {
+        VlanTags { data, offset: 0 }
+    }
+
+    /// Returns the offset, relative to the slice given to `new`, of the EtherType following the
+    /// tags iterated over so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for VlanTags<'a> {
+    type Item = VlanPacket<'a>;
+
+    fn next(&mut self) -> Option<VlanPacket<'a>> {
+        let tag = VlanPacket::new(&self.data[self.offset..])?;
+        if is_tag_protocol(tag.tpid()) {
+            self.offset += 4;
+            Some(tag)
+        } else {
+            None
+        }
+    }
+}
+
+/// Skips all stacked VLAN tags in `data` and returns the innermost EtherType together with the
+/// offset of the payload it describes. `data` should start where the EtherType of an untagged
+/// frame would be. Returns `None` if `data` is too short to hold the tags and the final EtherType.
+pub fn inner_ether_type(data: &[u8]) -> Option<(EtherType, usize)> {
+    let mut tags = VlanTags::new(data);
+    while tags.next().is_some() {}
+    let offset = tags.offset();
+    if data.len() < offset + 2 {
+        return None;
+    }
+    let ether_type = EtherType(read_offset!(data, offset, u16, from_be));
+    if is_tag_protocol(ether_type) {
+        None
+    } else {
+        Some((ether_type, offset + 2))
+    }
+}
+
+fn is_tag_protocol(tpid: EtherType) -> bool {
+    tpid == EtherType::VLAN || tpid == EtherType::QINQ
+}
+
+
+impl<'a> fmt::Display for VlanPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VLAN vid={} pcp={} type={}", self.vid(), self.pcp(), self.ether_type())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let data = [0x81, 0x00, 0xa0, 0x64, 0x86, 0xdd];
+        assert_eq!("VLAN vid=100 pcp=5 type=IPv6", VlanPacket::new(&data).unwrap().to_string());
+    }
+
+    macro_rules! vlan_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutVlanPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    vlan_setget_test!(tpid, set_tpid, EtherType(0xffff), 0, [0xff, 0xff]);
+    vlan_setget_test!(pcp, set_pcp, 0x7, 2, [0xe0]);
+    vlan_setget_test!(dei, set_dei, true, 2, [0x10]);
+    vlan_setget_test!(vid, set_vid, 0xfff, 2, [0x0f, 0xff]);
+    vlan_setget_test!(ether_type, set_ether_type, EtherType(0xffff), 4, [0xff, 0xff]);
+
+    #[test]
+    fn getters_alternating_bits() {
+        let backing_data = [0b1010_1010; 6];
+        let testee = VlanPacket::new(&backing_data).unwrap();
+        assert_eq!(0b101, testee.pcp());
+        assert!(!testee.dei());
+        assert_eq!(0b1010_1010_1010, testee.vid());
+    }
+
+    #[test]
+    fn tagged_frame() {
+        let frame = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // Destination
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, // Source
+            0x81, 0x00, 0xa0, 0x64, // 802.1Q tag, PCP 5, VID 100
+            0x08, 0x00, // IPv4
+            0x45,
+        ];
+        let testee = VlanPacket::new(&frame[12..]).unwrap();
+        assert_eq!(EtherType::VLAN, testee.tpid());
+        assert_eq!(5, testee.pcp());
+        assert!(!testee.dei());
+        assert_eq!(100, testee.vid());
+        assert_eq!(EtherType::IPV4, testee.ether_type());
+        assert_eq!(&[0x45], testee.payload());
+    }
+
+    static DOUBLE_TAGGED: [u8; 11] = [
+        0x88, 0xa8, 0x00, 0x0a, // 802.1ad tag, VID 10
+        0x81, 0x00, 0x00, 0x14, // 802.1Q tag, VID 20
+        0x86, 0xdd, // IPv6
+        0x60,
+    ];
+
+    #[test]
+    fn stacked_tags() {
+        let vids = VlanTags::new(&DOUBLE_TAGGED)
+            .map(|tag| tag.vid())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![10, 20], vids);
+    }
+
+    #[test]
+    fn inner_ether_type_double_tagged() {
+        let (ether_type, offset) = inner_ether_type(&DOUBLE_TAGGED).unwrap();
+        assert_eq!(EtherType::IPV6, ether_type);
+        assert_eq!(10, offset);
+        assert_eq!(&[0x60], &DOUBLE_TAGGED[offset..]);
+    }
+
+    #[test]
+    fn inner_ether_type_untagged() {
+        assert_eq!(
+            Some((EtherType::IPV4, 2)),
+            inner_ether_type(&[0x08, 0x00, 0x45])
+        );
+    }
+
+    #[test]
+    fn inner_ether_type_truncated() {
+        assert_eq!(None, inner_ether_type(&DOUBLE_TAGGED[..9]));
+        assert_eq!(None, inner_ether_type(&[0x81]));
+    }
+
+    arbitrary_test!(arbitrary_well_formed, VlanPacketBuf);
+}
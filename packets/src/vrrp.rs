@@ -0,0 +1,414 @@
+//! The Virtual Router Redundancy Protocol, version 2 (RFC 3768) and version 3 (RFC 5798).
+//!
+//! The master of a virtual router multicasts advertisements listing the addresses of the
+//! virtual router. The two versions share the layout of the first four bytes. Version 2 has an
+//! authentication type and an interval in seconds where version 3 has an interval in
+//! centiseconds, and version 3 also supports IPv6 and covers a pseudo header in its checksum.
+
+use checksum::{self, Checksum};
+use ip::Protocol;
+use std::cmp;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use types::*;
+
+/// The IPv4 multicast group advertisements are sent to.
+pub const IPV4_MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 18);
+
+/// The IPv6 multicast group advertisements are sent to.
+pub const IPV6_MULTICAST_ADDRESS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x12);
+
+/// The priority of the router owning the virtual router addresses.
+pub const PRIORITY_OWNER: u8 = 255;
+
+/// The default priority of backup routers.
+pub const PRIORITY_DEFAULT: u8 = 100;
+
+/// The priority sent by a master that stops participating, to trigger a quick failover.
+pub const PRIORITY_STOP: u8 = 0;
+
+packet!(
+    /// A VRRP advertisement. The header is followed by `address_count` IPv4 or IPv6
+    /// addresses, and in version 2 by eight bytes of authentication data.
+    VrrpPacket,
+    MutVrrpPacket,
+    VrrpPacketBuf,
+    8,
+    |_data| 8,
+    // A version 3 advertisement with as many IPv4 addresses as fit in the payload.
+    init |data| {
+        data[0] = 0x30 | VrrpType::ADVERTISEMENT.value();
+        data[3] = cmp::min((data.len() - 8) / 4, 255) as u8;
+    }
+);
+
+getters!(VrrpPacket
+    pub fn version(&self) -> u4 {
+        read_offset!(self.0, 0, u8) >> 4
+    }
+
+    pub fn vrrp_type(&self) -> VrrpType {
+        VrrpType(read_offset!(self.0, 0, u8) & 0x0f)
+    }
+
+    /// Returns the virtual router ID.
+    pub fn vrid(&self) -> u8 {
+        read_offset!(self.0, 1, u8)
+    }
+
+    /// Returns the priority of the sending router, higher values winning the election.
+    pub fn priority(&self) -> u8 {
+        read_offset!(self.0, 2, u8)
+    }
+
+    /// Returns the number of addresses in the advertisement.
+    pub fn address_count(&self) -> u8 {
+        read_offset!(self.0, 3, u8)
+    }
+
+    /// Returns the authentication type. Only present in version 2, where RFC 3768 requires it
+    /// to be zero.
+    pub fn auth_type(&self) -> u8 {
+        read_offset!(self.0, 4, u8)
+    }
+
+    /// Returns the advertisement interval in seconds. Only present in version 2.
+    pub fn advertisement_interval(&self) -> u8 {
+        read_offset!(self.0, 5, u8)
+    }
+
+    /// Returns the maximum advertisement interval in centiseconds. Only present in version 3.
+    pub fn max_advertisement_interval(&self) -> u12 {
+        read_offset!(self.0, 4, u16, from_be) & 0x0fff
+    }
+
+    pub fn checksum(&self) -> u16 {
+        read_offset!(self.0, 6, u16, from_be)
+    }
+
+    /// Returns an iterator over the addresses as IPv4 addresses. Only the addresses that fit in
+    /// the backing slice are included.
+    pub fn ipv4_addresses(&self) -> Ipv4Addresses<'a> {
+        Ipv4Addresses { data: self.addresses(4) }
+    }
+
+    /// Returns an iterator over the addresses as IPv6 addresses, only valid in version 3. Only
+    /// the addresses that fit in the backing slice are included.
+    pub fn ipv6_addresses(&self) -> Ipv6Addresses<'a> {
+        Ipv6Addresses { data: self.addresses(16) }
+    }
+);
+
+impl<'a> VrrpPacket<'a> {
+    /// Returns the advertisement interval, read from the field of the packet's version.
+    pub fn interval(&self) -> Duration {
+        if self.version() == 2 {
+            Duration::from_secs(u64::from(self.advertisement_interval()))
+        } else {
+            Duration::from_millis(u64::from(self.max_advertisement_interval()) * 10)
+        }
+    }
+
+    /// Computes the version 2 checksum, covering the entire backing slice and ignoring the
+    /// current value of the checksum field.
+    pub fn calculate_checksum(&self) -> u16 {
+        checksum_with(Checksum::new(), self.0)
+    }
+
+    /// Computes the version 3 checksum when sent over IPv4 between the given addresses. The
+    /// checksum covers an IPv4 pseudo header and the entire backing slice, so the slice must
+    /// hold exactly the VRRP packet. The current value of the checksum field is ignored.
+    pub fn ipv4_checksum(&self, source: Ipv4Addr, destination: Ipv4Addr) -> u16 {
+        let mut checksum = Checksum::new();
+        checksum::add_ipv4_pseudo_header(
+            &mut checksum,
+            source,
+            destination,
+            Protocol::VRRP,
+            self.0.len(),
+        );
+        checksum_with(checksum, self.0)
+    }
+
+    /// Computes the version 3 checksum when sent over IPv6 between the given addresses. See
+    /// `ipv4_checksum` for details.
+    pub fn ipv6_checksum(&self, source: Ipv6Addr, destination: Ipv6Addr) -> u16 {
+        let mut checksum = Checksum::new();
+        checksum::add_ipv6_pseudo_header(
+            &mut checksum,
+            source,
+            destination,
+            Protocol::VRRP,
+            self.0.len(),
+        );
+        checksum_with(checksum, self.0)
+    }
+
+    fn addresses(&self, address_len: usize) -> &'a [u8] {
+        let end = 8 + usize::from(self.address_count()) * address_len;
+        &self.0[8..cmp::min(end, self.0.len())]
+    }
+}
+
+fn checksum_with(mut checksum: Checksum, data: &[u8]) -> u16 {
+    checksum.add_bytes(&data[..6]);
+    checksum.add_bytes(&data[8..]);
+    checksum.finish()
+}
+
+setters!(MutVrrpPacket
+    pub fn set_version(&mut self, version: u4) {
+        let new_byte = (version << 4) | (read_offset!(self.0, 0, u8) & 0x0f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_vrrp_type(&mut self, vrrp_type: VrrpType) {
+        let new_byte = (read_offset!(self.0, 0, u8) & 0xf0) | (vrrp_type.value() & 0x0f);
+        write_offset!(self.0, 0, new_byte, u8);
+    }
+
+    pub fn set_vrid(&mut self, vrid: u8) {
+        write_offset!(self.0, 1, vrid, u8);
+    }
+
+    pub fn set_priority(&mut self, priority: u8) {
+        write_offset!(self.0, 2, priority, u8);
+    }
+
+    pub fn set_address_count(&mut self, address_count: u8) {
+        write_offset!(self.0, 3, address_count, u8);
+    }
+
+    pub fn set_auth_type(&mut self, auth_type: u8) {
+        write_offset!(self.0, 4, auth_type, u8);
+    }
+
+    pub fn set_advertisement_interval(&mut self, advertisement_interval: u8) {
+        write_offset!(self.0, 5, advertisement_interval, u8);
+    }
+
+    /// Sets the 12 bit maximum advertisement interval. The reserved bits before it are
+    /// left unchanged.
+    pub fn set_max_advertisement_interval(&mut self, max_advertisement_interval: u12) {
+        let new_value = (read_offset!(self.0, 4, u16, from_be) & 0xf000) |
+            (max_advertisement_interval & 0x0fff);
+        write_offset!(self.0, 4, new_value, u16, to_be);
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        write_offset!(self.0, 6, checksum, u16, to_be);
+    }
+);
+
+impl<'a> MutVrrpPacket<'a> {
+    /// Writes `addresses` after the header and sets the address count. Returns false, leaving
+    /// the packet unchanged, if there are more than 255 addresses or the backing slice is too
+    /// short to hold them.
+    pub fn set_ipv4_addresses(&mut self, addresses: &[Ipv4Addr]) -> bool {
+        if addresses.len() > 255 || 8 + addresses.len() * 4 > self.0.len() {
+            return false;
+        }
+        let data = &mut self.0[8..8 + addresses.len() * 4];
+        for (address, chunk) in addresses.iter().zip(data.chunks_mut(4)) {
+            chunk.copy_from_slice(&address.octets());
+        }
+        self.set_address_count(addresses.len() as u8);
+        true
+    }
+
+    /// Writes `addresses` after the header and sets the address count. See
+    /// `set_ipv4_addresses` for details.
+    pub fn set_ipv6_addresses(&mut self, addresses: &[Ipv6Addr]) -> bool {
+        if addresses.len() > 255 || 8 + addresses.len() * 16 > self.0.len() {
+            return false;
+        }
+        let data = &mut self.0[8..8 + addresses.len() * 16];
+        for (address, chunk) in addresses.iter().zip(data.chunks_mut(16)) {
+            chunk.copy_from_slice(&address.octets());
+        }
+        self.set_address_count(addresses.len() as u8);
+        true
+    }
+}
+
+
+/// Represents the type of a VRRP packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct VrrpType(pub u8);
+
+impl VrrpType {
+    pub const ADVERTISEMENT: VrrpType = VrrpType(1);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+
+/// Iterator over the IPv4 addresses of a VRRP advertisement.
+#[derive(Clone)]
+pub struct Ipv4Addresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ipv4Addresses<'a>);
+
+impl<'a> Iterator for Ipv4Addresses<'a> {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Ipv4Addr> {
+        if self.data.len() < 4 {
+            return None;
+        }
+        let (address, rest) = self.data.split_at(4);
+        self.data = rest;
+        Some(Ipv4Addr::from(read_offset!(address, 0, [u8; 4])))
+    }
+}
+
+/// Iterator over the IPv6 addresses of a VRRP advertisement.
+#[derive(Clone)]
+pub struct Ipv6Addresses<'a> {
+    data: &'a [u8],
+}
+
+debug_items!(Ipv6Addresses<'a>);
+
+impl<'a> Iterator for Ipv6Addresses<'a> {
+    type Item = Ipv6Addr;
+
+    fn next(&mut self) -> Option<Ipv6Addr> {
+        if self.data.len() < 16 {
+            return None;
+        }
+        let (address, rest) = self.data.split_at(16);
+        self.data = rest;
+        Some(Ipv6Addr::from(read_offset!(address, 0, [u8; 16])))
+    }
+}
+
+impl<'a> fmt::Display for VrrpPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "VRRPv{} vrid={} priority={} addresses={}",
+            self.version(),
+            self.vrid(),
+            self.priority(),
+            self.address_count()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! vrrp_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutVrrpPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    vrrp_setget_test!(version, set_version, 0x0f, 0, [0xf0]);
+    vrrp_setget_test!(vrrp_type, set_vrrp_type, VrrpType(0x0f), 0, [0x0f]);
+    vrrp_setget_test!(vrid, set_vrid, 0xab, 1, [0xab]);
+    vrrp_setget_test!(priority, set_priority, 0xab, 2, [0xab]);
+    vrrp_setget_test!(address_count, set_address_count, 0xab, 3, [0xab]);
+    vrrp_setget_test!(auth_type, set_auth_type, 0xab, 4, [0xab]);
+    vrrp_setget_test!(advertisement_interval, set_advertisement_interval, 0xab, 5, [0xab]);
+    vrrp_setget_test!(
+        max_advertisement_interval,
+        set_max_advertisement_interval,
+        0x0abc,
+        4,
+        [0x0a, 0xbc]
+    );
+    vrrp_setget_test!(checksum, set_checksum, 0xabcd, 6, [0xab, 0xcd]);
+
+    #[test]
+    fn v2_advertisement() {
+        let mut data = [0; 8 + 8 + 8];
+        let addresses = [Ipv4Addr::new(192, 168, 0, 1), Ipv4Addr::new(192, 168, 0, 2)];
+        {
+            let mut testee = MutVrrpPacket::new(&mut data).unwrap();
+            testee.set_version(2);
+            testee.set_vrrp_type(VrrpType::ADVERTISEMENT);
+            testee.set_vrid(1);
+            testee.set_priority(PRIORITY_DEFAULT);
+            testee.set_advertisement_interval(1);
+            assert!(testee.set_ipv4_addresses(&addresses));
+            let checksum = testee.as_immutable().calculate_checksum();
+            testee.set_checksum(checksum);
+        }
+        assert_eq!(&[0x21, 1, 100, 2, 0, 1], &data[..6]);
+        let testee = VrrpPacket::new(&data).unwrap();
+        assert_eq!(addresses.to_vec(), testee.ipv4_addresses().collect::<Vec<_>>());
+        assert_eq!(Duration::from_secs(1), testee.interval());
+
+        let mut sum = Checksum::new();
+        sum.add_bytes(&data);
+        assert_eq!(0, sum.finish());
+    }
+
+    #[test]
+    fn v3_advertisement() {
+        let source = "fe80::1".parse().unwrap();
+        let address = "2001:db8::1".parse().unwrap();
+        let mut data = [0; 8 + 16];
+        {
+            let mut testee = MutVrrpPacket::new(&mut data).unwrap();
+            testee.set_version(3);
+            testee.set_max_advertisement_interval(100);
+            assert!(testee.set_ipv6_addresses(&[address]));
+            let checksum = testee.as_immutable().ipv6_checksum(source, IPV6_MULTICAST_ADDRESS);
+            testee.set_checksum(checksum);
+        }
+        let testee = VrrpPacket::new(&data).unwrap();
+        assert_eq!(vec![address], testee.ipv6_addresses().collect::<Vec<_>>());
+        assert_eq!(Duration::from_secs(1), testee.interval());
+
+        let mut sum = Checksum::new();
+        checksum::add_ipv6_pseudo_header(
+            &mut sum,
+            source,
+            IPV6_MULTICAST_ADDRESS,
+            Protocol::VRRP,
+            data.len(),
+        );
+        sum.add_bytes(&data);
+        assert_eq!(0, sum.finish());
+        let ipv4_source = Ipv4Addr::new(10, 0, 0, 1);
+        assert_ne!(testee.checksum(), testee.ipv4_checksum(ipv4_source, IPV4_MULTICAST_ADDRESS));
+    }
+
+    #[test]
+    fn truncated_addresses() {
+        let data = [0x31, 1, 100, 3, 0, 100, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2, 10, 0];
+        let testee = VrrpPacket::new(&data).unwrap();
+        assert_eq!(2, testee.ipv4_addresses().count());
+        assert_eq!(0, testee.ipv6_addresses().count());
+    }
+
+    #[test]
+    fn too_many_addresses() {
+        let mut data = [0; 8 + 16];
+        let mut testee = MutVrrpPacket::new(&mut data).unwrap();
+        assert!(!testee.set_ipv4_addresses(&[Ipv4Addr::UNSPECIFIED; 256]));
+        assert!(!testee.set_ipv4_addresses(&[Ipv4Addr::UNSPECIFIED; 5]));
+        assert!(!testee.set_ipv6_addresses(&[Ipv6Addr::UNSPECIFIED; 2]));
+        assert_eq!(0, testee.as_immutable().address_count());
+        assert!(testee.set_ipv4_addresses(&[Ipv4Addr::UNSPECIFIED; 4]));
+        assert_eq!(4, testee.as_immutable().address_count());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        VrrpPacketBuf => |packet| packet.version() == 3
+            && packet.vrrp_type() == VrrpType::ADVERTISEMENT
+            && packet.ipv4_addresses().count() == usize::from(packet.address_count())
+    );
+}
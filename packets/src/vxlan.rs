@@ -0,0 +1,122 @@
+//! Virtual eXtensible Local Area Network, RFC 7348.
+
+use ethernet::{EthernetPacket, MutEthernetPacket};
+use std::fmt;
+use types::*;
+
+/// The UDP destination port assigned to VXLAN by IANA.
+pub const UDP_PORT: u16 = 4789;
+
+packet!(
+    VxlanPacket,
+    MutVxlanPacket,
+    VxlanPacketBuf,
+    8,
+    |_data| 8,
+    init |data| {
+        write_offset!(data, 0, [Flags::VNI.bits(), 0, 0, 0], [u8; 4]);
+        write_offset!(data, 7, 0u8, u8);
+    }
+);
+
+getters!(VxlanPacket
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits_truncate(read_offset!(self.0, 0, u8))
+    }
+
+    /// Returns the 24 bit VXLAN Network Identifier.
+    pub fn vni(&self) -> u24 {
+        read_offset!(self.0, 4, u24, from_be)
+    }
+
+    /// Returns the encapsulated Ethernet frame, or `None` if the payload is too short to hold
+    /// an Ethernet header.
+    pub fn inner_ethernet(&self) -> Option<EthernetPacket<'a>> {
+        EthernetPacket::new(&self.0[8..])
+    }
+);
+
+setters!(MutVxlanPacket
+    pub fn set_flags(&mut self, flags: Flags) {
+        write_offset!(self.0, 0, flags.bits(), u8);
+    }
+
+    /// Sets the 24 bit VXLAN Network Identifier. The highest byte of `vni` is ignored, and the
+    /// reserved byte following the field is left untouched.
+    pub fn set_vni(&mut self, vni: u24) {
+        write_offset!(self.0, 4, vni, u24, to_be);
+    }
+);
+
+impl<'a> MutVxlanPacket<'a> {
+    /// Returns the encapsulated Ethernet frame for modification, or `None` if the payload is too
+    /// short to hold an Ethernet header.
+    pub fn inner_ethernet(&mut self) -> Option<MutEthernetPacket<'_>> {
+        MutEthernetPacket::new(&mut self.0[8..])
+    }
+}
+
+
+bitflags! {
+    /// Bitmasks for the flags in the first byte of a VXLAN header.
+    pub struct Flags: u8 {
+        /// A bitmask with a one in the "I" position, meaning the VNI is valid.
+        const VNI = 0b0000_1000;
+    }
+}
+
+impl<'a> fmt::Display for VxlanPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VXLAN vni={}", self.vni())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{EtherType, MacAddr};
+
+    macro_rules! vxlan_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutVxlanPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    vxlan_setget_test!(flags, set_flags, Flags::VNI, 0, [0x08]);
+    vxlan_setget_test!(vni, set_vni, 0xabcdef, 4, [0xab, 0xcd, 0xef]);
+
+    #[test]
+    fn vni_keeps_reserved_byte() {
+        let mut data = [0; 8];
+        data[7] = 0x55;
+        MutVxlanPacket::new(&mut data).unwrap().set_vni(0xff_123456);
+        assert_eq!(&[0x12, 0x34, 0x56, 0x55], &data[4..]);
+    }
+
+    #[test]
+    fn inner_ethernet() {
+        let mut data = [0; 8 + 14];
+        {
+            let mut testee = MutVxlanPacket::new(&mut data).unwrap();
+            testee.set_flags(Flags::VNI);
+            testee.set_vni(100);
+            let mut inner = testee.inner_ethernet().unwrap();
+            inner.set_destination(MacAddr::BROADCAST);
+            inner.set_ether_type(EtherType::ARP);
+        }
+        let testee = VxlanPacket::new(&data).unwrap();
+        assert_eq!(100, testee.vni());
+        let inner = testee.inner_ethernet().unwrap();
+        assert_eq!(MacAddr::BROADCAST, inner.destination());
+        assert_eq!(EtherType::ARP, inner.ether_type());
+
+        assert!(VxlanPacket::new(&data[..21]).unwrap().inner_ethernet().is_none());
+        assert_eq!("VXLAN vni=100", testee.to_string());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        VxlanPacketBuf => |packet| packet.flags() == Flags::VNI
+    );
+}
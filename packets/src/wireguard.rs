@@ -0,0 +1,538 @@
+//! WireGuard messages, as described in the WireGuard whitepaper. All messages start with a one
+//! byte message type followed by three reserved zero bytes. Unlike most protocols WireGuard
+//! encodes its integers in little endian byte order.
+
+use std::fmt;
+
+/// The UDP port WireGuard listens on by default. There is no assigned port, this is just what
+/// the reference implementation uses.
+pub const DEFAULT_UDP_PORT: u16 = 51820;
+
+macro_rules! wireguard_common {
+    ($name:ident, $mut_name:ident) => {
+        getters!(common $name
+            pub fn message_type(&self) -> MessageType {
+                MessageType(read_offset!(self.0, 0, u8))
+            }
+
+            /// Returns the three reserved bytes following the message type, which must be zero.
+            pub fn reserved(&self) -> [u8; 3] {
+                read_offset!(self.0, 1, [u8; 3])
+            }
+        );
+
+        setters!($mut_name
+            pub fn set_message_type(&mut self, message_type: MessageType) {
+                write_offset!(self.0, 0, message_type.value(), u8);
+            }
+
+            pub fn set_reserved(&mut self, reserved: [u8; 3]) {
+                write_offset!(self.0, 1, reserved, [u8; 3]);
+            }
+        );
+    }
+}
+
+packet!(
+    /// The fields common to all WireGuard messages. Use one of the conversion methods to get a
+    /// view of the message type specific fields.
+    WireguardPacket,
+    MutWireguardPacket,
+    WireguardPacketBuf,
+    4,
+    |_data| 4,
+    init |data| write_offset!(data, 1, [0; 3], [u8; 3])
+);
+wireguard_common!(WireguardPacket, MutWireguardPacket);
+
+impl<'a> WireguardPacket<'a> {
+    /// Returns the message as a handshake initiation, or `None` if it is of another type or too
+    /// short.
+    pub fn handshake_initiation(&self) -> Option<HandshakeInitiationPacket<'a>> {
+        self.as_type(MessageType::HANDSHAKE_INITIATION).and_then(HandshakeInitiationPacket::new)
+    }
+
+    /// Returns the message as a handshake response, or `None` if it is of another type or too
+    /// short.
+    pub fn handshake_response(&self) -> Option<HandshakeResponsePacket<'a>> {
+        self.as_type(MessageType::HANDSHAKE_RESPONSE).and_then(HandshakeResponsePacket::new)
+    }
+
+    /// Returns the message as a cookie reply, or `None` if it is of another type or too short.
+    pub fn cookie_reply(&self) -> Option<CookieReplyPacket<'a>> {
+        self.as_type(MessageType::COOKIE_REPLY).and_then(CookieReplyPacket::new)
+    }
+
+    /// Returns the message as transport data, or `None` if it is of another type or too short.
+    pub fn transport_data(&self) -> Option<TransportDataPacket<'a>> {
+        self.as_type(MessageType::TRANSPORT_DATA).and_then(TransportDataPacket::new)
+    }
+
+    fn as_type(&self, message_type: MessageType) -> Option<&'a [u8]> {
+        if self.message_type() == message_type {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+
+packet!(
+    /// The first message of the handshake, sent by the initiator.
+    HandshakeInitiationPacket,
+    MutHandshakeInitiationPacket,
+    HandshakeInitiationPacketBuf,
+    148,
+    |_data| 148,
+    init |data| {
+        write_offset!(data, 0, MessageType::HANDSHAKE_INITIATION.value(), u8);
+        write_offset!(data, 1, [0; 3], [u8; 3]);
+    }
+);
+wireguard_common!(HandshakeInitiationPacket, MutHandshakeInitiationPacket);
+
+getters!(HandshakeInitiationPacket
+    /// Returns the index the initiator chose to identify this session.
+    pub fn sender_index(&self) -> u32 {
+        u32::from_le(read_offset!(self.0, 4, u32))
+    }
+
+    /// Returns the ephemeral public key of the initiator.
+    pub fn unencrypted_ephemeral(&self) -> [u8; 32] {
+        read_offset!(self.0, 8, [u8; 32])
+    }
+
+    /// Returns the encrypted static public key of the initiator, including the AEAD tag.
+    pub fn encrypted_static(&self) -> [u8; 48] {
+        read_offset!(self.0, 40, [u8; 48])
+    }
+
+    /// Returns the encrypted TAI64N timestamp, including the AEAD tag.
+    pub fn encrypted_timestamp(&self) -> [u8; 28] {
+        read_offset!(self.0, 88, [u8; 28])
+    }
+
+    pub fn mac1(&self) -> [u8; 16] {
+        read_offset!(self.0, 116, [u8; 16])
+    }
+
+    /// Returns the second MAC, which is all zeroes unless the responder sent a cookie.
+    pub fn mac2(&self) -> [u8; 16] {
+        read_offset!(self.0, 132, [u8; 16])
+    }
+
+    /// Returns the part of the message the first MAC is computed over.
+    pub fn mac1_input(&self) -> &'a [u8] {
+        &self.0[..116]
+    }
+
+    /// Returns the part of the message the second MAC is computed over.
+    pub fn mac2_input(&self) -> &'a [u8] {
+        &self.0[..132]
+    }
+);
+
+setters!(MutHandshakeInitiationPacket
+    pub fn set_sender_index(&mut self, sender_index: u32) {
+        write_offset!(self.0, 4, sender_index.to_le(), u32);
+    }
+
+    pub fn set_unencrypted_ephemeral(&mut self, unencrypted_ephemeral: [u8; 32]) {
+        write_offset!(self.0, 8, unencrypted_ephemeral, [u8; 32]);
+    }
+
+    pub fn set_encrypted_static(&mut self, encrypted_static: [u8; 48]) {
+        write_offset!(self.0, 40, encrypted_static, [u8; 48]);
+    }
+
+    pub fn set_encrypted_timestamp(&mut self, encrypted_timestamp: [u8; 28]) {
+        write_offset!(self.0, 88, encrypted_timestamp, [u8; 28]);
+    }
+
+    pub fn set_mac1(&mut self, mac1: [u8; 16]) {
+        write_offset!(self.0, 116, mac1, [u8; 16]);
+    }
+
+    pub fn set_mac2(&mut self, mac2: [u8; 16]) {
+        write_offset!(self.0, 132, mac2, [u8; 16]);
+    }
+);
+
+
+packet!(
+    /// The second message of the handshake, sent by the responder.
+    HandshakeResponsePacket,
+    MutHandshakeResponsePacket,
+    HandshakeResponsePacketBuf,
+    92,
+    |_data| 92,
+    init |data| {
+        write_offset!(data, 0, MessageType::HANDSHAKE_RESPONSE.value(), u8);
+        write_offset!(data, 1, [0; 3], [u8; 3]);
+    }
+);
+wireguard_common!(HandshakeResponsePacket, MutHandshakeResponsePacket);
+
+getters!(HandshakeResponsePacket
+    /// Returns the index the responder chose to identify this session.
+    pub fn sender_index(&self) -> u32 {
+        u32::from_le(read_offset!(self.0, 4, u32))
+    }
+
+    /// Returns the sender index of the initiation this message responds to.
+    pub fn receiver_index(&self) -> u32 {
+        u32::from_le(read_offset!(self.0, 8, u32))
+    }
+
+    /// Returns the ephemeral public key of the responder.
+    pub fn unencrypted_ephemeral(&self) -> [u8; 32] {
+        read_offset!(self.0, 12, [u8; 32])
+    }
+
+    /// Returns the AEAD tag of the encrypted empty payload.
+    pub fn encrypted_nothing(&self) -> [u8; 16] {
+        read_offset!(self.0, 44, [u8; 16])
+    }
+
+    pub fn mac1(&self) -> [u8; 16] {
+        read_offset!(self.0, 60, [u8; 16])
+    }
+
+    /// Returns the second MAC, which is all zeroes unless the initiator sent a cookie.
+    pub fn mac2(&self) -> [u8; 16] {
+        read_offset!(self.0, 76, [u8; 16])
+    }
+
+    /// Returns the part of the message the first MAC is computed over.
+    pub fn mac1_input(&self) -> &'a [u8] {
+        &self.0[..60]
+    }
+
+    /// Returns the part of the message the second MAC is computed over.
+    pub fn mac2_input(&self) -> &'a [u8] {
+        &self.0[..76]
+    }
+);
+
+setters!(MutHandshakeResponsePacket
+    pub fn set_sender_index(&mut self, sender_index: u32) {
+        write_offset!(self.0, 4, sender_index.to_le(), u32);
+    }
+
+    pub fn set_receiver_index(&mut self, receiver_index: u32) {
+        write_offset!(self.0, 8, receiver_index.to_le(), u32);
+    }
+
+    pub fn set_unencrypted_ephemeral(&mut self, unencrypted_ephemeral: [u8; 32]) {
+        write_offset!(self.0, 12, unencrypted_ephemeral, [u8; 32]);
+    }
+
+    pub fn set_encrypted_nothing(&mut self, encrypted_nothing: [u8; 16]) {
+        write_offset!(self.0, 44, encrypted_nothing, [u8; 16]);
+    }
+
+    pub fn set_mac1(&mut self, mac1: [u8; 16]) {
+        write_offset!(self.0, 60, mac1, [u8; 16]);
+    }
+
+    pub fn set_mac2(&mut self, mac2: [u8; 16]) {
+        write_offset!(self.0, 76, mac2, [u8; 16]);
+    }
+);
+
+
+packet!(
+    /// A cookie reply, sent instead of a handshake response when the responder is under load.
+    CookieReplyPacket,
+    MutCookieReplyPacket,
+    CookieReplyPacketBuf,
+    64,
+    |_data| 64,
+    init |data| {
+        write_offset!(data, 0, MessageType::COOKIE_REPLY.value(), u8);
+        write_offset!(data, 1, [0; 3], [u8; 3]);
+    }
+);
+wireguard_common!(CookieReplyPacket, MutCookieReplyPacket);
+
+getters!(CookieReplyPacket
+    /// Returns the sender index of the handshake message this cookie is for.
+    pub fn receiver_index(&self) -> u32 {
+        u32::from_le(read_offset!(self.0, 4, u32))
+    }
+
+    pub fn nonce(&self) -> [u8; 24] {
+        read_offset!(self.0, 8, [u8; 24])
+    }
+
+    /// Returns the encrypted cookie, including the AEAD tag.
+    pub fn encrypted_cookie(&self) -> [u8; 32] {
+        read_offset!(self.0, 32, [u8; 32])
+    }
+);
+
+setters!(MutCookieReplyPacket
+    pub fn set_receiver_index(&mut self, receiver_index: u32) {
+        write_offset!(self.0, 4, receiver_index.to_le(), u32);
+    }
+
+    pub fn set_nonce(&mut self, nonce: [u8; 24]) {
+        write_offset!(self.0, 8, nonce, [u8; 24]);
+    }
+
+    pub fn set_encrypted_cookie(&mut self, encrypted_cookie: [u8; 32]) {
+        write_offset!(self.0, 32, encrypted_cookie, [u8; 32]);
+    }
+);
+
+
+packet!(
+    /// A transport data message, carrying an encrypted IP packet. The payload is the encrypted
+    /// packet including the AEAD tag. An empty encrypted packet is a keepalive.
+    TransportDataPacket,
+    MutTransportDataPacket,
+    TransportDataPacketBuf,
+    16,
+    |_data| 16,
+    init |data| {
+        write_offset!(data, 0, MessageType::TRANSPORT_DATA.value(), u8);
+        write_offset!(data, 1, [0; 3], [u8; 3]);
+    }
+);
+wireguard_common!(TransportDataPacket, MutTransportDataPacket);
+
+getters!(TransportDataPacket
+    /// Returns the index the receiving peer chose to identify the session.
+    pub fn receiver_index(&self) -> u32 {
+        u32::from_le(read_offset!(self.0, 4, u32))
+    }
+
+    /// Returns the counter, used as the nonce for decryption and to detect replays.
+    pub fn counter(&self) -> u64 {
+        u64::from_le(read_offset!(self.0, 8, u64))
+    }
+);
+
+setters!(MutTransportDataPacket
+    pub fn set_receiver_index(&mut self, receiver_index: u32) {
+        write_offset!(self.0, 4, receiver_index.to_le(), u32);
+    }
+
+    pub fn set_counter(&mut self, counter: u64) {
+        write_offset!(self.0, 8, counter.to_le(), u64);
+    }
+);
+
+
+/// The type of a WireGuard message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    pub const HANDSHAKE_INITIATION: MessageType = MessageType(1);
+    pub const HANDSHAKE_RESPONSE: MessageType = MessageType(2);
+    pub const COOKIE_REPLY: MessageType = MessageType(3);
+    pub const TRANSPORT_DATA: MessageType = MessageType(4);
+
+    #[inline]
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl<'a> fmt::Display for WireguardPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WireGuard type={}", self.message_type().value())
+    }
+}
+
+impl<'a> fmt::Display for HandshakeInitiationPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WireGuard handshake initiation sender={:#010x}",
+            self.sender_index()
+        )
+    }
+}
+
+impl<'a> fmt::Display for HandshakeResponsePacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WireGuard handshake response sender={:#010x} receiver={:#010x}",
+            self.sender_index(),
+            self.receiver_index()
+        )
+    }
+}
+
+impl<'a> fmt::Display for CookieReplyPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WireGuard cookie reply receiver={:#010x}",
+            self.receiver_index()
+        )
+    }
+}
+
+impl<'a> fmt::Display for TransportDataPacket<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WireGuard transport data receiver={:#010x} counter={}",
+            self.receiver_index(),
+            self.counter()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! wireguard_setget_test {
+        ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+            setget_test!(MutWireguardPacket, $name, $set_name, $value, $offset, $expected);
+        }
+    }
+
+    wireguard_setget_test!(message_type, set_message_type, MessageType::COOKIE_REPLY, 0, [3]);
+    wireguard_setget_test!(reserved, set_reserved, [1, 2, 3], 1, [1, 2, 3]);
+
+    mod handshake_initiation {
+        use super::super::*;
+
+        macro_rules! initiation_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(
+                    MutHandshakeInitiationPacket,
+                    $name,
+                    $set_name,
+                    $value,
+                    $offset,
+                    $expected
+                );
+            }
+        }
+
+        initiation_setget_test!(sender_index, set_sender_index, 0x01020304, 4, [4, 3, 2, 1]);
+        initiation_setget_test!(
+            unencrypted_ephemeral,
+            set_unencrypted_ephemeral,
+            [0xab; 32],
+            8,
+            [0xab; 32]
+        );
+        initiation_setget_test!(encrypted_static, set_encrypted_static, [0xab; 48], 40, [0xab; 48]);
+        initiation_setget_test!(
+            encrypted_timestamp,
+            set_encrypted_timestamp,
+            [0xab; 28],
+            88,
+            [0xab; 28]
+        );
+        initiation_setget_test!(mac1, set_mac1, [0xab; 16], 116, [0xab; 16]);
+        initiation_setget_test!(mac2, set_mac2, [0xab; 16], 132, [0xab; 16]);
+    }
+
+    mod handshake_response {
+        use super::super::*;
+
+        macro_rules! response_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(
+                    MutHandshakeResponsePacket,
+                    $name,
+                    $set_name,
+                    $value,
+                    $offset,
+                    $expected
+                );
+            }
+        }
+
+        response_setget_test!(sender_index, set_sender_index, 0x01020304, 4, [4, 3, 2, 1]);
+        response_setget_test!(receiver_index, set_receiver_index, 0x01020304, 8, [4, 3, 2, 1]);
+        response_setget_test!(
+            unencrypted_ephemeral,
+            set_unencrypted_ephemeral,
+            [0xab; 32],
+            12,
+            [0xab; 32]
+        );
+        response_setget_test!(
+            encrypted_nothing,
+            set_encrypted_nothing,
+            [0xab; 16],
+            44,
+            [0xab; 16]
+        );
+        response_setget_test!(mac1, set_mac1, [0xab; 16], 60, [0xab; 16]);
+        response_setget_test!(mac2, set_mac2, [0xab; 16], 76, [0xab; 16]);
+    }
+
+    mod cookie_reply {
+        use super::super::*;
+
+        macro_rules! cookie_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutCookieReplyPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        cookie_setget_test!(receiver_index, set_receiver_index, 0x01020304, 4, [4, 3, 2, 1]);
+        cookie_setget_test!(nonce, set_nonce, [0xab; 24], 8, [0xab; 24]);
+        cookie_setget_test!(encrypted_cookie, set_encrypted_cookie, [0xab; 32], 32, [0xab; 32]);
+    }
+
+    mod transport_data {
+        use super::super::*;
+
+        macro_rules! data_setget_test {
+            ($name:ident, $set_name:ident, $value:expr, $offset:expr, $expected:expr) => {
+                setget_test!(MutTransportDataPacket, $name, $set_name, $value, $offset, $expected);
+            }
+        }
+
+        data_setget_test!(receiver_index, set_receiver_index, 0x01020304, 4, [4, 3, 2, 1]);
+        data_setget_test!(counter, set_counter, 0x0102030405060708, 8, [8, 7, 6, 5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn message_types() {
+        let mut data = [0; 148];
+        data[0] = 1;
+        let testee = WireguardPacket::new(&data).unwrap();
+        let initiation = testee.handshake_initiation().unwrap();
+        assert_eq!(116, initiation.mac1_input().len());
+        assert_eq!(132, initiation.mac2_input().len());
+        assert!(testee.handshake_response().is_none());
+        assert!(testee.transport_data().is_none());
+
+        data[0] = 4;
+        let testee = WireguardPacket::new(&data[..32]).unwrap();
+        let transport_data = testee.transport_data().unwrap();
+        assert_eq!(16, transport_data.payload().len());
+        assert!(WireguardPacket::new(&data[..8]).unwrap().transport_data().is_none());
+        data[0] = 3;
+        assert!(WireguardPacket::new(&data[..63]).unwrap().cookie_reply().is_none());
+        assert!(WireguardPacket::new(&data[..64]).unwrap().cookie_reply().is_some());
+    }
+
+    arbitrary_test!(
+        arbitrary_well_formed,
+        WireguardPacketBuf => |packet| packet.reserved() == [0; 3],
+        HandshakeInitiationPacketBuf => |packet| {
+            packet.message_type() == MessageType::HANDSHAKE_INITIATION
+        },
+        HandshakeResponsePacketBuf => |packet| {
+            packet.message_type() == MessageType::HANDSHAKE_RESPONSE
+        },
+        CookieReplyPacketBuf => |packet| packet.message_type() == MessageType::COOKIE_REPLY,
+        TransportDataPacketBuf => |packet| packet.message_type() == MessageType::TRANSPORT_DATA
+    );
+}